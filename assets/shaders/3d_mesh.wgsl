@@ -0,0 +1,50 @@
+// Import the standard mesh uniforms and set their bind groups
+#import bevy_pbr::mesh_types
+#import bevy_pbr::mesh_view_bindings
+
+@group(1) @binding(0)
+var<uniform> mesh: Mesh;
+
+// NOTE: Bindings must come before functions that use them!
+#import bevy_pbr::mesh_functions
+
+// The structure of the vertex buffer is as specified in `specialize()`
+struct Vertex {
+    @location(0) position: vec3<f32>,
+    @location(1) normal: vec3<f32>,
+    @location(2) color: u32,
+};
+
+struct VertexOutput {
+    // The vertex shader must set the on-screen position of the vertex
+    @builtin(position) clip_position: vec4<f32>,
+    @location(0) color: vec4<f32>,
+    @location(1) world_normal: vec3<f32>,
+};
+
+/// Entry point for the vertex shader
+@vertex
+fn vertex(vertex: Vertex) -> VertexOutput {
+    var out: VertexOutput;
+    // Project the world position of the mesh into screen position
+    out.clip_position = mesh_position_local_to_clip(mesh.model, vec4<f32>(vertex.position, 1.0));
+    out.world_normal = mesh_normal_local_to_world(vertex.normal);
+    // Unpack the `u32` from the vertex buffer into the `vec4<f32>` used by the fragment shader
+    out.color = vec4<f32>((vec4<u32>(vertex.color) >> vec4<u32>(0u, 8u, 16u, 24u)) & vec4<u32>(255u)) / 255.0;
+    return out;
+}
+
+// The input of the fragment shader must correspond to the output of the vertex shader for all `location`s
+struct FragmentInput {
+    @location(0) color: vec4<f32>,
+    @location(1) world_normal: vec3<f32>,
+};
+
+/// Entry point for the fragment shader
+@fragment
+fn fragment(in: FragmentInput) -> @location(0) vec4<f32> {
+    // A fixed light from above, both sides of the surface are lit
+    let light = normalize(vec3<f32>(0.6, 0.7, 0.4));
+    let diffuse = abs(dot(normalize(in.world_normal), light));
+    return vec4<f32>(in.color.rgb * (0.3 + 0.7 * diffuse), in.color.a);
+}
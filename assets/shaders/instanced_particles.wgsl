@@ -0,0 +1,53 @@
+// Import the standard mesh uniforms and set their bind groups
+#import bevy_pbr::mesh_types
+#import bevy_pbr::mesh_view_bindings
+
+@group(1) @binding(0)
+var<uniform> mesh: Mesh;
+
+// NOTE: Bindings must come before functions that use them!
+#import bevy_pbr::mesh_functions
+
+// The mesh attributes come first, the per instance attributes are as
+// specified in `specialize()`
+struct Vertex {
+    @location(0) position: vec3<f32>,
+    @location(1) normal: vec3<f32>,
+    @location(2) uv: vec2<f32>,
+
+    @location(3) i_position_scale: vec4<f32>,
+    @location(4) i_color: vec4<f32>,
+};
+
+struct VertexOutput {
+    @builtin(position) clip_position: vec4<f32>,
+    @location(0) color: vec4<f32>,
+    @location(1) normal: vec3<f32>,
+};
+
+/// Entry point for the vertex shader
+@vertex
+fn vertex(vertex: Vertex) -> VertexOutput {
+    // Move and scale the mesh to the instance, the particles are not rotated
+    let position = vertex.position * vertex.i_position_scale.w + vertex.i_position_scale.xyz;
+
+    var out: VertexOutput;
+    out.clip_position = mesh_position_local_to_clip(mesh.model, vec4<f32>(position, 1.0));
+    out.color = vertex.i_color;
+    out.normal = vertex.normal;
+    return out;
+}
+
+struct FragmentInput {
+    @location(0) color: vec4<f32>,
+    @location(1) normal: vec3<f32>,
+};
+
+/// Entry point for the fragment shader
+@fragment
+fn fragment(in: FragmentInput) -> @location(0) vec4<f32> {
+    // A fixed light from above, roughly where the scenes place theirs
+    let light = normalize(vec3<f32>(0.6, 0.7, 0.4));
+    let diffuse = max(dot(normalize(in.normal), light), 0.0);
+    return vec4<f32>(in.color.rgb * (0.3 + 0.7 * diffuse), in.color.a);
+}
@@ -0,0 +1,60 @@
+// Import the standard mesh uniforms and set their bind groups
+#import bevy_pbr::mesh_types
+#import bevy_pbr::mesh_view_bindings
+
+@group(1) @binding(0)
+var<uniform> mesh: Mesh;
+
+// The vertices of the quad span -1 to 1, the per instance attributes are
+// as specified in `specialize()`
+struct Vertex {
+    @location(0) position: vec3<f32>,
+    @location(1) normal: vec3<f32>,
+    @location(2) uv: vec2<f32>,
+
+    @location(3) i_position_scale: vec4<f32>,
+    @location(4) i_color: vec4<f32>,
+};
+
+struct VertexOutput {
+    @builtin(position) clip_position: vec4<f32>,
+    @location(0) color: vec4<f32>,
+    // position on the disc, the rim is at a length of 1
+    @location(1) offset: vec2<f32>,
+};
+
+/// Entry point for the vertex shader
+@vertex
+fn vertex(vertex: Vertex) -> VertexOutput {
+    // Span the quad along the right and up axes of the camera
+    let right = view.view[0].xyz;
+    let up = view.view[1].xyz;
+    let offset = vertex.position.xy * vertex.i_position_scale.w;
+    let position = vertex.i_position_scale.xyz + right * offset.x + up * offset.y;
+
+    var out: VertexOutput;
+    out.clip_position = view.view_proj * vec4<f32>(position, 1.0);
+    out.color = vertex.i_color;
+    out.offset = vertex.position.xy;
+    return out;
+}
+
+struct FragmentInput {
+    @location(0) color: vec4<f32>,
+    @location(1) offset: vec2<f32>,
+};
+
+/// Entry point for the fragment shader
+@fragment
+fn fragment(in: FragmentInput) -> @location(0) vec4<f32> {
+    let distance_squared = dot(in.offset, in.offset);
+    if (distance_squared > 1.0) {
+        discard;
+    }
+
+    // Shade the disc like a sphere lit from the upper left of the view
+    let normal = vec3<f32>(in.offset, sqrt(1.0 - distance_squared));
+    let light = normalize(vec3<f32>(-0.4, 0.6, 0.7));
+    let diffuse = max(dot(normal, light), 0.0);
+    return vec4<f32>(in.color.rgb * (0.3 + 0.7 * diffuse), in.color.a);
+}
@@ -0,0 +1,182 @@
+//! Benchmarks of the code which runs for every cell or particle each frame
+//!
+//! The modules are included by path, as the crate has no library target.
+
+#![allow(dead_code)]
+
+use bevy::prelude::*;
+use criterion::{
+    black_box, criterion_group, criterion_main, BenchmarkId, Criterion,
+};
+
+#[path = "../src/colormap.rs"]
+mod colormap;
+#[path = "../src/wave_2d_simulation/finite_difference.rs"]
+mod finite_difference;
+#[path = "../src/longitudinal_wave_3d_simulation/lattice.rs"]
+mod lattice;
+
+use colormap::{log_compress, Colormap, LogCompressionTable};
+use finite_difference::update_with_laplace_operator;
+use lattice::{compression, Lattice};
+
+const GRID_SIZES: [(usize, usize); 3] = [(320, 180), (640, 360), (1280, 720)];
+
+const LATTICE_SIZES: [i32; 2] = [10, 20];
+
+/// A smooth field with values in `-1.0..=1.0`
+fn field(dimx: usize, dimy: usize, phase: f32) -> Vec<f32> {
+    (0..dimx * dimy)
+        .map(|i| {
+            ((i / dimy) as f32 * 0.05 + (i % dimy) as f32 * 0.03 + phase).sin()
+        })
+        .collect()
+}
+
+/// The solver on a single thread and on all threads, the difference shows
+/// how well the rows are spread over the cores
+fn laplace_operator(c: &mut Criterion) {
+    let mut group = c.benchmark_group("update_with_laplace_operator");
+
+    let pools = [
+        ("1 thread", 1),
+        (
+            "all threads",
+            std::thread::available_parallelism().map_or(1, usize::from),
+        ),
+    ];
+
+    for (dimx, dimy) in GRID_SIZES {
+        let current = field(dimx, dimy, 0.0);
+        let previous = field(dimx, dimy, 0.1);
+        let mut next = vec![0.0; dimx * dimy];
+
+        for (name, threads) in pools {
+            let pool = rayon::ThreadPoolBuilder::new()
+                .num_threads(threads)
+                .build()
+                .expect("failed to build the thread pool");
+
+            group.bench_function(
+                BenchmarkId::new(name, format!("{}x{}", dimx, dimy)),
+                |b| {
+                    b.iter(|| {
+                        pool.install(|| {
+                            update_with_laplace_operator(
+                                dimx,
+                                dimy,
+                                black_box(0.1),
+                                &mut next,
+                                &current,
+                                &previous,
+                            )
+                        })
+                    })
+                },
+            );
+        }
+    }
+
+    group.finish();
+}
+
+/// The logarithm against its lookup table over a whole grid
+fn log_compression(c: &mut Criterion) {
+    let mut group = c.benchmark_group("log_compression");
+
+    let (dimx, dimy) = GRID_SIZES[0];
+    let amplitudes = field(dimx, dimy, 0.0);
+    let log_table = LogCompressionTable::default();
+
+    group.bench_function("logarithm", |b| {
+        b.iter(|| {
+            amplitudes
+                .iter()
+                .map(|amplitude| log_compress(*amplitude))
+                .sum::<f32>()
+        })
+    });
+    group.bench_function("lookup table", |b| {
+        b.iter(|| {
+            amplitudes
+                .iter()
+                .map(|amplitude| log_table.get(*amplitude))
+                .sum::<f32>()
+        })
+    });
+
+    group.finish();
+}
+
+/// Recoloring every vertex of a plot, the worst case of an update
+fn color_vector(c: &mut Criterion) {
+    let mut group = c.benchmark_group("color_vector");
+
+    let (dimx, dimy) = GRID_SIZES[0];
+    let amplitudes = field(dimx, dimy, 0.0);
+    let log_table = LogCompressionTable::default();
+    let mut colors = vec![0; dimx * dimy];
+
+    for colormap in Colormap::ALL {
+        group.bench_function(String::from(colormap), |b| {
+            b.iter(|| {
+                for (color, amplitude) in colors.iter_mut().zip(&amplitudes) {
+                    *color = colormap
+                        .color(log_table.get(*amplitude))
+                        .as_linear_rgba_u32();
+                }
+                black_box(&colors);
+            })
+        });
+    }
+
+    group.finish();
+}
+
+/// Building the lattice lookup and the compression of every particle, as
+/// done each frame by the compression coloring of the 3d simulation
+fn neighbor_search(c: &mut Criterion) {
+    let mut group = c.benchmark_group("neighbor_search");
+
+    for size in LATTICE_SIZES {
+        let particles: Vec<(IVec3, Vec3, Vec3)> = (0..size.pow(3))
+            .map(|i| {
+                let cell =
+                    IVec3::new(i % size, i / size % size, i / size / size);
+                let initial = cell.as_vec3() * 2.0;
+                let displaced =
+                    initial + Vec3::X * (i as f32 * 0.1).sin() * 0.2;
+                (cell, initial, displaced)
+            })
+            .collect();
+
+        group.bench_function(BenchmarkId::from_parameter(size.pow(3)), |b| {
+            b.iter(|| {
+                let lattice: Lattice = particles
+                    .iter()
+                    .map(|(cell, initial, displaced)| {
+                        (*cell, (*initial, *displaced))
+                    })
+                    .collect();
+
+                particles
+                    .iter()
+                    .map(|(cell, initial, displaced)| {
+                        compression(&lattice, *cell, *initial, *displaced)
+                    })
+                    .sum::<f32>()
+            })
+        });
+    }
+
+    group.finish();
+}
+
+criterion_group!(
+    benches,
+    laplace_operator,
+    log_compression,
+    color_vector,
+    neighbor_search
+);
+criterion_main!(benches);
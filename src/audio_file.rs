@@ -0,0 +1,197 @@
+use std::path::Path;
+
+use bevy::prelude::*;
+use bevy_egui::egui;
+
+use crate::file_dialog::pick_file;
+
+const AUDIO_DIRECTORY: &str = "audio";
+
+/// Asks for a WAV file to play as drive signal
+pub struct LoadAudioFile;
+
+/// Samples of a WAV file mixed down to a single channel, played back at the
+/// rate the simulations consume them with
+#[derive(Resource)]
+pub struct AudioFile {
+    name: Option<String>,
+    samples: Vec<f32>,
+    sample_rate: u32,
+    /// playback position in seconds of the file
+    position_secs: f64,
+    pub playing: bool,
+    pub looping: bool,
+    pub gain: f32,
+}
+
+impl Default for AudioFile {
+    fn default() -> Self {
+        Self {
+            name: None,
+            samples: Vec::new(),
+            sample_rate: 44_100,
+            position_secs: 0.0,
+            playing: false,
+            looping: true,
+            gain: 1.0,
+        }
+    }
+}
+
+impl AudioFile {
+    pub fn duration_secs(&self) -> f64 {
+        self.samples.len() as f64 / self.sample_rate as f64
+    }
+
+    pub fn rewind(&mut self) {
+        self.position_secs = 0.0;
+    }
+
+    /// Drive value of the next `secs` of the file, the mean of the samples
+    /// they cover, zero while stopped
+    pub fn next_sample(&mut self, secs: f32) -> f32 {
+        if !self.playing || self.samples.is_empty() {
+            return 0.0;
+        }
+
+        let rate = self.sample_rate as f64;
+        let start = (self.position_secs * rate) as usize;
+        // a step shorter than a sample still plays the sample it lands on
+        let end = (((self.position_secs + secs as f64) * rate) as usize)
+            .max(start + 1)
+            .min(self.samples.len());
+        let covered = &self.samples[start.min(end)..end];
+        let value = if covered.is_empty() {
+            0.0
+        } else {
+            covered.iter().sum::<f32>() / covered.len() as f32
+        };
+
+        self.position_secs += secs as f64;
+        if self.position_secs >= self.duration_secs() {
+            if self.looping {
+                self.position_secs %= self.duration_secs();
+            } else {
+                self.playing = false;
+                self.rewind();
+            }
+        }
+
+        value * self.gain
+    }
+}
+
+pub struct AudioFilePlugin;
+
+impl Plugin for AudioFilePlugin {
+    fn build(&self, app: &mut App) {
+        app.add_event::<LoadAudioFile>()
+            .insert_resource(AudioFile::default())
+            .add_system(on_load_audio_file);
+    }
+}
+
+fn on_load_audio_file(
+    mut events: EventReader<LoadAudioFile>,
+    mut audio_file: ResMut<AudioFile>,
+) {
+    for _ in events.iter() {
+        let path = if let Some(path) =
+            pick_file(AUDIO_DIRECTORY, "wav", "drive", false)
+        {
+            path
+        } else {
+            continue;
+        };
+
+        match read_wav(&path) {
+            Ok((samples, sample_rate)) => {
+                *audio_file = AudioFile {
+                    name: path
+                        .file_name()
+                        .map(|name| name.to_string_lossy().into_owned()),
+                    samples,
+                    sample_rate,
+                    playing: true,
+                    looping: audio_file.looping,
+                    gain: audio_file.gain,
+                    ..default()
+                };
+            }
+            Err(error) => {
+                error!("failed to load {}: {}", path.display(), error);
+            }
+        }
+    }
+}
+
+/// Samples in `-1.0..=1.0` averaged over the channels, and the sample rate
+fn read_wav(path: &Path) -> Result<(Vec<f32>, u32), hound::Error> {
+    let mut reader = hound::WavReader::open(path)?;
+    let spec = reader.spec();
+
+    let interleaved = match spec.sample_format {
+        hound::SampleFormat::Float => {
+            reader.samples::<f32>().collect::<Result<Vec<_>, _>>()?
+        }
+        hound::SampleFormat::Int => {
+            let full_scale = (1i64 << (spec.bits_per_sample - 1)) as f32;
+            reader
+                .samples::<i32>()
+                .map(|sample| sample.map(|sample| sample as f32 / full_scale))
+                .collect::<Result<Vec<_>, _>>()?
+        }
+    };
+
+    let channels = usize::from(spec.channels).max(1);
+    let samples = interleaved
+        .chunks(channels)
+        .map(|frame| frame.iter().sum::<f32>() / frame.len() as f32)
+        .collect();
+
+    Ok((samples, spec.sample_rate))
+}
+
+pub fn show_audio_file(
+    ui: &mut egui::Ui,
+    audio_file: &mut AudioFile,
+    load_events: &mut EventWriter<LoadAudioFile>,
+) {
+    ui.horizontal(|ui| {
+        if ui.button("Load WAV").clicked() {
+            load_events.send(LoadAudioFile);
+        }
+        if let Some(name) = &audio_file.name {
+            ui.label(name);
+        }
+    });
+
+    if audio_file.samples.is_empty() {
+        return;
+    }
+
+    ui.horizontal(|ui| {
+        let label = if audio_file.playing { "Pause" } else { "Play" };
+        if ui.button(label).clicked() {
+            audio_file.playing = !audio_file.playing;
+        }
+        if ui.button("Rewind").clicked() {
+            audio_file.rewind();
+        }
+        ui.checkbox(&mut audio_file.looping, "loop");
+    });
+
+    let duration = audio_file.duration_secs();
+    ui.add(
+        egui::ProgressBar::new((audio_file.position_secs / duration) as f32)
+            .text(format!(
+                "{:.1} / {:.1} s",
+                audio_file.position_secs, duration
+            )),
+    );
+    ui.add(
+        egui::Slider::new(&mut audio_file.gain, 0.01..=10.0)
+            .logarithmic(true)
+            .text("file gain"),
+    );
+}
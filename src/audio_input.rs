@@ -0,0 +1,247 @@
+use std::collections::VecDeque;
+use std::sync::{Arc, Mutex};
+
+use bevy::prelude::*;
+use bevy_egui::egui;
+use serde::{Deserialize, Serialize};
+
+#[cfg(not(target_arch = "wasm32"))]
+use cpal::traits::{DeviceTrait, HostTrait, StreamTrait};
+
+/// Captured seconds kept while the simulation doesn't consume them, older
+/// samples are dropped so the drive doesn't lag behind the voice
+const MAX_BUFFERED_SECS: f32 = 0.25;
+
+/// How the captured samples of a solver step become a single drive value
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub enum AudioInputSignal {
+    /// the mean of the samples, which keeps what the solver can resolve
+    Waveform,
+    /// the rms of the samples, the loudness follows the voice even when its
+    /// pitch is far above the frequencies of the grid
+    Loudness,
+}
+
+impl AudioInputSignal {
+    pub const ALL: [AudioInputSignal; 2] =
+        [AudioInputSignal::Waveform, AudioInputSignal::Loudness];
+}
+
+impl From<AudioInputSignal> for String {
+    fn from(value: AudioInputSignal) -> Self {
+        match value {
+            AudioInputSignal::Waveform => "waveform".to_string(),
+            AudioInputSignal::Loudness => "loudness".to_string(),
+        }
+    }
+}
+
+/// Samples captured from the default microphone, downsampled to the rate
+/// the simulations consume them with.
+///
+/// The microphone is opened while a simulation drives with it and closed
+/// once nothing consumed a sample for a frame.
+#[derive(Resource)]
+pub struct AudioInput {
+    pub signal: AudioInputSignal,
+    pub gain: f32,
+    buffer: Arc<Mutex<VecDeque<f32>>>,
+    sample_rate: u32,
+    /// a sample was taken since the last frame
+    used: bool,
+    /// the last drive value, shown as level
+    pub level: f32,
+    pub error: Option<String>,
+}
+
+impl Default for AudioInput {
+    fn default() -> Self {
+        Self {
+            signal: AudioInputSignal::Waveform,
+            gain: 4.0,
+            buffer: Arc::default(),
+            sample_rate: 44_100,
+            used: false,
+            level: 0.0,
+            error: None,
+        }
+    }
+}
+
+impl AudioInput {
+    /// Drive value of the next `secs` of captured audio, zero until the
+    /// microphone delivers samples
+    pub fn next_sample(&mut self, secs: f32) -> f32 {
+        self.used = true;
+
+        let mut buffer = if let Ok(buffer) = self.buffer.lock() {
+            buffer
+        } else {
+            return 0.0;
+        };
+
+        let max_len = (self.sample_rate as f32 * MAX_BUFFERED_SECS) as usize;
+        let excess = buffer.len().saturating_sub(max_len);
+        buffer.drain(..excess);
+
+        // the capture and the solver steps don't tick in lockstep, a short
+        // buffer gives what it has
+        let count = ((self.sample_rate as f32 * secs).round() as usize)
+            .max(1)
+            .min(buffer.len());
+        let samples = buffer.drain(..count);
+        let (sum, sum_of_squares, len) =
+            samples.fold((0.0, 0.0, 0), |(sum, squares, len), sample| {
+                (sum + sample, squares + sample * sample, len + 1)
+            });
+        if len == 0 {
+            return 0.0;
+        }
+
+        let value = match self.signal {
+            AudioInputSignal::Waveform => sum / len as f32,
+            AudioInputSignal::Loudness => (sum_of_squares / len as f32).sqrt(),
+        };
+        self.level = value * self.gain;
+
+        self.level
+    }
+}
+
+/// Open microphone stream, the stream can't leave the main thread on every
+/// platform
+#[derive(Default)]
+struct AudioInputStream(
+    #[cfg(not(target_arch = "wasm32"))] Option<cpal::Stream>,
+);
+
+pub struct AudioInputPlugin;
+
+impl Plugin for AudioInputPlugin {
+    fn build(&self, app: &mut App) {
+        app.insert_resource(AudioInput::default())
+            .insert_non_send_resource(AudioInputStream::default())
+            .add_system_to_stage(CoreStage::Last, update_stream);
+    }
+}
+
+/// Opens the microphone when a sample was taken and closes it when none
+/// was, runs last so the consumers of the frame have been seen
+#[cfg(not(target_arch = "wasm32"))]
+fn update_stream(
+    mut input: ResMut<AudioInput>,
+    mut stream: NonSendMut<AudioInputStream>,
+) {
+    let used = std::mem::take(&mut input.used);
+
+    if !used {
+        if stream.0.take().is_some() {
+            input.level = 0.0;
+            if let Ok(mut buffer) = input.buffer.lock() {
+                buffer.clear();
+            }
+        }
+        return;
+    }
+
+    // a failed device is not retried every frame
+    if stream.0.is_some() || input.error.is_some() {
+        return;
+    }
+
+    match open_stream(&input.buffer) {
+        Ok((opened, sample_rate)) => {
+            input.sample_rate = sample_rate;
+            stream.0 = Some(opened);
+        }
+        Err(error) => {
+            error!("failed to open the microphone: {}", error);
+            input.error = Some(error);
+        }
+    }
+}
+
+#[cfg(target_arch = "wasm32")]
+fn update_stream(mut input: ResMut<AudioInput>) {
+    if std::mem::take(&mut input.used) && input.error.is_none() {
+        input.error = Some("not available in the browser".to_string());
+    }
+}
+
+/// Starts capturing the default input device into `buffer`, mixed down to
+/// a single channel, returns the stream and its sample rate
+#[cfg(not(target_arch = "wasm32"))]
+fn open_stream(
+    buffer: &Arc<Mutex<VecDeque<f32>>>,
+) -> Result<(cpal::Stream, u32), String> {
+    let device = cpal::default_host()
+        .default_input_device()
+        .ok_or_else(|| "no input device".to_string())?;
+    let config = device
+        .default_input_config()
+        .map_err(|error| error.to_string())?;
+    let sample_rate = config.sample_rate().0;
+
+    let stream = match config.sample_format() {
+        cpal::SampleFormat::F32 => {
+            build_stream::<f32>(&device, &config.into(), buffer.clone())
+        }
+        cpal::SampleFormat::I16 => {
+            build_stream::<i16>(&device, &config.into(), buffer.clone())
+        }
+        cpal::SampleFormat::U16 => {
+            build_stream::<u16>(&device, &config.into(), buffer.clone())
+        }
+    }
+    .map_err(|error| error.to_string())?;
+    stream.play().map_err(|error| error.to_string())?;
+
+    Ok((stream, sample_rate))
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+fn build_stream<T: cpal::Sample>(
+    device: &cpal::Device,
+    config: &cpal::StreamConfig,
+    buffer: Arc<Mutex<VecDeque<f32>>>,
+) -> Result<cpal::Stream, cpal::BuildStreamError> {
+    let channels = usize::from(config.channels).max(1);
+
+    device.build_input_stream(
+        config,
+        move |data: &[T], _: &cpal::InputCallbackInfo| {
+            if let Ok(mut buffer) = buffer.lock() {
+                buffer.extend(data.chunks(channels).map(|frame| {
+                    frame.iter().map(|sample| sample.to_f32()).sum::<f32>()
+                        / frame.len() as f32
+                }));
+            }
+        },
+        |error| error!("microphone stream failed: {}", error),
+    )
+}
+
+pub fn show_audio_input(ui: &mut egui::Ui, input: &mut AudioInput) {
+    ui.horizontal(|ui| {
+        ui.label("microphone signal:");
+        for option in AudioInputSignal::ALL {
+            ui.radio_value(&mut input.signal, option, String::from(option));
+        }
+    });
+    ui.add(
+        egui::Slider::new(&mut input.gain, 0.1..=100.0)
+            .logarithmic(true)
+            .text("microphone gain"),
+    );
+    ui.add(
+        egui::ProgressBar::new(input.level.abs().min(1.0))
+            .text(format!("level {:.2}", input.level)),
+    );
+
+    if let Some(error) = &input.error {
+        ui.label(format!("microphone unavailable: {}", error));
+        if ui.button("Retry").clicked() {
+            input.error = None;
+        }
+    }
+}
@@ -0,0 +1,577 @@
+use std::num::NonZeroU32;
+use std::path::PathBuf;
+use std::sync::{Arc, Mutex};
+#[cfg(not(target_arch = "wasm32"))]
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use bevy::core_pipeline::core_2d::Camera2d;
+use bevy::prelude::*;
+use bevy::render::camera::RenderTarget;
+use bevy::render::extract_resource::{ExtractResource, ExtractResourcePlugin};
+use bevy::render::render_asset::RenderAssets;
+use bevy::render::render_resource::{
+    BufferDescriptor, BufferUsages, CommandEncoderDescriptor, Extent3d,
+    ImageCopyBuffer, ImageDataLayout, Maintain, MapMode, TextureFormat,
+};
+#[cfg(not(target_arch = "wasm32"))]
+use bevy::render::render_resource::{
+    TextureDescriptor, TextureDimension, TextureUsages,
+};
+use bevy::render::renderer::{RenderDevice, RenderQueue};
+#[cfg(not(target_arch = "wasm32"))]
+use bevy::render::texture::BevyDefault;
+use bevy::render::{RenderApp, RenderStage};
+use bevy::tasks::IoTaskPool;
+use bevy_egui::{egui, EguiContext};
+
+use crate::AppCamera;
+#[cfg(not(target_arch = "wasm32"))]
+use crate::AppState;
+
+#[cfg(not(target_arch = "wasm32"))]
+const CAPTURE_DIRECTORY: &str = "captures";
+#[cfg(not(target_arch = "wasm32"))]
+const SCREENSHOT_DIRECTORY: &str = "screenshots";
+
+/// wgpu requires the rows of a texture copy to be aligned to 256 bytes
+const COPY_BYTES_PER_ROW_ALIGNMENT: u32 = 256;
+
+/// How long the confirmation of a saved screenshot is shown
+const TOAST_SECS: f64 = 3.0;
+
+pub enum CaptureEvents {
+    Start,
+    Stop,
+    /// saves the next rendered frame as a single image
+    Screenshot,
+}
+
+#[derive(Resource)]
+pub struct Capture {
+    pub every_n_frames: u32,
+    directory: Option<PathBuf>,
+    /// file the next frame is saved to
+    screenshot: Option<PathBuf>,
+    target: Option<Handle<Image>>,
+    /// the app camera the capture camera is mirroring
+    source: Option<Entity>,
+    frame: u64,
+    saved_frames: u64,
+}
+
+impl Default for Capture {
+    fn default() -> Self {
+        Self {
+            every_n_frames: 1,
+            directory: None,
+            screenshot: None,
+            target: None,
+            source: None,
+            frame: 0,
+            saved_frames: 0,
+        }
+    }
+}
+
+/// Renders the view of the app camera into the capture target
+#[derive(Component)]
+struct CaptureCamera;
+
+/// Texture to read back in the render world, set on the frames which are
+/// saved
+#[derive(Clone, Default, Resource, ExtractResource)]
+struct CaptureRequest {
+    target: Option<(Handle<Image>, PathBuf)>,
+    /// single images are confirmed in the ui, captured frames are not
+    screenshot: bool,
+}
+
+/// Results of writing screenshots in the background, shared by the main and
+/// the render world
+#[derive(Clone, Default, Resource)]
+struct SavedScreenshots(Arc<Mutex<Vec<Result<PathBuf, String>>>>);
+
+pub struct CapturePlugin;
+
+impl Plugin for CapturePlugin {
+    fn build(&self, app: &mut App) {
+        let saved_screenshots = SavedScreenshots::default();
+
+        app.add_event::<CaptureEvents>()
+            .insert_resource(Capture::default())
+            .insert_resource(CaptureRequest::default())
+            .insert_resource(saved_screenshots.clone())
+            .add_plugin(ExtractResourcePlugin::<CaptureRequest>::default())
+            .add_system(on_capture_events)
+            .add_system(show_screenshot_toast)
+            .add_system(update_capture_camera.after(on_capture_events))
+            .add_system_to_stage(
+                CoreStage::PostUpdate,
+                request_capture.before(TransformSystem::TransformPropagate),
+            );
+
+        if let Ok(render_app) = app.get_sub_app_mut(RenderApp) {
+            render_app
+                .insert_resource(saved_screenshots)
+                .add_system_to_stage(
+                    RenderStage::Cleanup,
+                    read_back_capture_target,
+                );
+        }
+    }
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+fn on_capture_events(
+    mut commands: Commands,
+    mut capture_events: EventReader<CaptureEvents>,
+    mut capture: ResMut<Capture>,
+    mut images: ResMut<Assets<Image>>,
+    windows: Res<Windows>,
+    app_state: Res<State<AppState>>,
+    capture_cameras: Query<Entity, With<CaptureCamera>>,
+) {
+    // a screenshot only needs the capture target for a single frame
+    if capture.directory.is_none()
+        && capture.screenshot.is_none()
+        && capture.target.is_some()
+    {
+        capture.target = None;
+        capture.source = None;
+
+        for entity in capture_cameras.iter() {
+            commands.entity(entity).despawn();
+        }
+    }
+
+    for event in capture_events.iter() {
+        let window = if let Some(window) = windows.get_primary() {
+            window
+        } else {
+            continue;
+        };
+
+        let timestamp = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .unwrap_or_default()
+            .as_millis();
+
+        match event {
+            CaptureEvents::Start => {
+                let directory = PathBuf::from(CAPTURE_DIRECTORY)
+                    .join(format!("capture_{}", timestamp));
+
+                if let Err(error) = std::fs::create_dir_all(&directory) {
+                    error!(
+                        "failed to create {}: {}",
+                        directory.display(),
+                        error
+                    );
+                    continue;
+                }
+
+                let target = images.add(capture_target(
+                    window.physical_width(),
+                    window.physical_height(),
+                ));
+
+                *capture = Capture {
+                    every_n_frames: capture.every_n_frames,
+                    directory: Some(directory),
+                    screenshot: capture.screenshot.take(),
+                    target: Some(target),
+                    ..default()
+                };
+            }
+            CaptureEvents::Screenshot => {
+                let directory = PathBuf::from(SCREENSHOT_DIRECTORY);
+                if let Err(error) = std::fs::create_dir_all(&directory) {
+                    error!(
+                        "failed to create {}: {}",
+                        directory.display(),
+                        error
+                    );
+                    continue;
+                }
+
+                if capture.target.is_none() {
+                    capture.target = Some(images.add(capture_target(
+                        window.physical_width(),
+                        window.physical_height(),
+                    )));
+                }
+                capture.screenshot = Some(directory.join(format!(
+                    "{}_{}.png",
+                    app_state.current().name(),
+                    timestamp
+                )));
+            }
+            CaptureEvents::Stop => {
+                if let Some(directory) = capture.directory.take() {
+                    info!(
+                        "captured {} frames to {}",
+                        capture.saved_frames,
+                        directory.display()
+                    );
+                }
+                // a pending screenshot still needs the target
+                if capture.screenshot.is_none() {
+                    capture.target = None;
+                    capture.source = None;
+
+                    for entity in capture_cameras.iter() {
+                        commands.entity(entity).despawn();
+                    }
+                }
+            }
+        }
+    }
+}
+
+/// Files can't be written in the browser, so nothing is captured
+#[cfg(target_arch = "wasm32")]
+fn on_capture_events(mut capture_events: EventReader<CaptureEvents>) {
+    if capture_events.iter().count() > 0 {
+        warn!("captures are not supported in the web build");
+    }
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+fn capture_target(width: u32, height: u32) -> Image {
+    let size = Extent3d {
+        width,
+        height,
+        depth_or_array_layers: 1,
+    };
+
+    // the pipelines of the simulations are specialized for this format
+    let mut image = Image {
+        texture_descriptor: TextureDescriptor {
+            label: Some("capture_target"),
+            size,
+            dimension: TextureDimension::D2,
+            format: TextureFormat::bevy_default(),
+            mip_level_count: 1,
+            sample_count: 1,
+            usage: TextureUsages::TEXTURE_BINDING
+                | TextureUsages::COPY_SRC
+                | TextureUsages::RENDER_ATTACHMENT,
+        },
+        ..default()
+    };
+    image.resize(size);
+
+    image
+}
+
+/// Keeps a camera rendering into the capture target which follows the app
+/// camera, the app camera is replaced whenever the simulation or its view
+/// changes
+#[allow(clippy::type_complexity)]
+fn update_capture_camera(
+    mut commands: Commands,
+    mut capture: ResMut<Capture>,
+    app_cameras: Query<
+        (Entity, &Transform, Option<&Projection>, Option<&Camera2d>),
+        With<AppCamera>,
+    >,
+    mut capture_cameras: Query<
+        (Entity, &mut Transform),
+        (With<CaptureCamera>, Without<AppCamera>),
+    >,
+) {
+    let target = if let Some(target) = capture.target.clone() {
+        target
+    } else {
+        return;
+    };
+
+    let (source, source_transform, projection, camera_2d) =
+        if let Ok(app_camera) = app_cameras.get_single() {
+            app_camera
+        } else {
+            return;
+        };
+
+    if capture.source == Some(source) {
+        if let Ok((_, mut transform)) = capture_cameras.get_single_mut() {
+            *transform = *source_transform;
+            return;
+        }
+    }
+
+    for (entity, _) in capture_cameras.iter() {
+        commands.entity(entity).despawn();
+    }
+
+    let camera = Camera {
+        target: RenderTarget::Image(target),
+        ..default()
+    };
+
+    if camera_2d.is_some() {
+        commands.spawn((
+            CaptureCamera,
+            Camera2dBundle {
+                camera,
+                transform: *source_transform,
+                ..default()
+            },
+        ));
+    } else {
+        commands.spawn((
+            CaptureCamera,
+            Camera3dBundle {
+                camera,
+                projection: projection.cloned().unwrap_or_default(),
+                transform: *source_transform,
+                ..default()
+            },
+        ));
+    }
+
+    capture.source = Some(source);
+}
+
+fn request_capture(
+    mut capture: ResMut<Capture>,
+    mut request: ResMut<CaptureRequest>,
+) {
+    request.target = None;
+    request.screenshot = false;
+
+    let target = if let Some(target) = capture.target.clone() {
+        target
+    } else {
+        return;
+    };
+
+    // the capture camera renders from the frame after it was spawned on
+    if capture.source.is_none() {
+        return;
+    }
+
+    if let Some(path) = capture.screenshot.take() {
+        request.target = Some((target, path));
+        request.screenshot = true;
+        return;
+    }
+
+    let directory = if let Some(directory) = capture.directory.clone() {
+        directory
+    } else {
+        return;
+    };
+
+    if capture.frame % capture.every_n_frames.max(1) as u64 == 0 {
+        let path = directory.join(format!("{:06}.png", capture.saved_frames));
+        request.target = Some((target, path));
+        capture.saved_frames += 1;
+    }
+
+    capture.frame += 1;
+}
+
+/// Copies the capture target into a buffer once the frame is rendered and
+/// writes it as PNG in the background
+fn read_back_capture_target(
+    request: Res<CaptureRequest>,
+    saved_screenshots: Res<SavedScreenshots>,
+    images: Res<RenderAssets<Image>>,
+    render_device: Res<RenderDevice>,
+    render_queue: Res<RenderQueue>,
+) {
+    let (target, path) = if let Some(target) = &request.target {
+        target
+    } else {
+        return;
+    };
+
+    let gpu_image = if let Some(gpu_image) = images.get(target) {
+        gpu_image
+    } else {
+        return;
+    };
+
+    let width = gpu_image.size.x as u32;
+    let height = gpu_image.size.y as u32;
+    let bytes_per_row = width * 4;
+    let padded_bytes_per_row = padded_bytes_per_row(bytes_per_row);
+
+    let buffer = render_device.create_buffer(&BufferDescriptor {
+        label: Some("capture_buffer"),
+        size: (padded_bytes_per_row * height) as u64,
+        usage: BufferUsages::MAP_READ | BufferUsages::COPY_DST,
+        mapped_at_creation: false,
+    });
+
+    let mut encoder =
+        render_device.create_command_encoder(&CommandEncoderDescriptor {
+            label: Some("capture_encoder"),
+        });
+
+    encoder.copy_texture_to_buffer(
+        gpu_image.texture.as_image_copy(),
+        ImageCopyBuffer {
+            buffer: &buffer,
+            layout: ImageDataLayout {
+                offset: 0,
+                bytes_per_row: NonZeroU32::new(padded_bytes_per_row),
+                rows_per_image: None,
+            },
+        },
+        Extent3d {
+            width,
+            height,
+            depth_or_array_layers: 1,
+        },
+    );
+
+    render_queue.submit([encoder.finish()]);
+
+    let slice = buffer.slice(..);
+    slice.map_async(MapMode::Read, |result| {
+        if let Err(error) = result {
+            error!("failed to map the capture buffer: {}", error);
+        }
+    });
+    render_device.wgpu_device().poll(Maintain::Wait);
+
+    let bgra = gpu_image.texture_format == TextureFormat::Bgra8UnormSrgb;
+    let mut pixels = Vec::with_capacity((bytes_per_row * height) as usize);
+    for row in slice
+        .get_mapped_range()
+        .chunks(padded_bytes_per_row as usize)
+    {
+        for pixel in row[..bytes_per_row as usize].chunks(4) {
+            if bgra {
+                pixels.extend_from_slice(&[pixel[2], pixel[1], pixel[0], 255]);
+            } else {
+                pixels.extend_from_slice(&[pixel[0], pixel[1], pixel[2], 255]);
+            }
+        }
+    }
+    buffer.unmap();
+
+    let path = path.clone();
+    let saved_screenshots =
+        request.screenshot.then(|| saved_screenshots.0.clone());
+    IoTaskPool::get()
+        .spawn(async move {
+            let result = image::save_buffer(
+                &path,
+                &pixels,
+                width,
+                height,
+                image::ColorType::Rgba8,
+            )
+            .map(|_| path.clone())
+            .map_err(|error| {
+                format!("failed to save {}: {}", path.display(), error)
+            });
+            if let Err(error) = &result {
+                error!("{}", error);
+            }
+
+            if let Some(saved_screenshots) = saved_screenshots {
+                if let Ok(mut saved_screenshots) = saved_screenshots.lock() {
+                    saved_screenshots.push(result);
+                }
+            }
+        })
+        .detach();
+}
+
+fn padded_bytes_per_row(bytes_per_row: u32) -> u32 {
+    let remainder = bytes_per_row % COPY_BYTES_PER_ROW_ALIGNMENT;
+    if remainder == 0 {
+        bytes_per_row
+    } else {
+        bytes_per_row + COPY_BYTES_PER_ROW_ALIGNMENT - remainder
+    }
+}
+
+/// Confirms the last saved screenshot, or why it could not be saved, in the
+/// bottom right corner for a few seconds
+fn show_screenshot_toast(
+    mut egui_ctx: ResMut<EguiContext>,
+    time: Res<Time>,
+    saved_screenshots: Res<SavedScreenshots>,
+    mut toast: Local<Option<(Result<PathBuf, String>, f64)>>,
+) {
+    let now = time.elapsed_seconds_f64();
+
+    if let Ok(mut saved_screenshots) = saved_screenshots.0.lock() {
+        if let Some(result) = saved_screenshots.drain(..).last() {
+            *toast = Some((result, now));
+        }
+    }
+
+    let (result, shown_at) = if let Some(toast) = &*toast {
+        toast
+    } else {
+        return;
+    };
+    if now - shown_at > TOAST_SECS {
+        *toast = None;
+        return;
+    }
+
+    egui::Area::new("screenshot_toast")
+        .anchor(egui::Align2::RIGHT_BOTTOM, egui::vec2(-10.0, -10.0))
+        .interactable(false)
+        .show(egui_ctx.ctx_mut(), |ui| {
+            egui::Frame::popup(ui.style()).show(ui, |ui| match result {
+                Ok(path) => {
+                    ui.label(format!("saved {}", path.display()));
+                }
+                Err(error) => {
+                    ui.colored_label(egui::Color32::RED, error.as_str());
+                }
+            });
+        });
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+pub fn show_capture(
+    ui: &mut egui::Ui,
+    capture: &mut Capture,
+    capture_events: &mut EventWriter<CaptureEvents>,
+) {
+    if ui
+        .button("Screenshot")
+        .on_hover_text(format!(
+            "saves the next frame to {}",
+            SCREENSHOT_DIRECTORY
+        ))
+        .clicked()
+    {
+        capture_events.send(CaptureEvents::Screenshot);
+    }
+
+    if capture.directory.is_some() {
+        ui.label(format!("captured {} frames", capture.saved_frames));
+        if ui.button("Stop capture").clicked() {
+            capture_events.send(CaptureEvents::Stop);
+        }
+    } else {
+        ui.add(
+            egui::DragValue::new(&mut capture.every_n_frames)
+                .clamp_range(1..=600)
+                .prefix("every ")
+                .suffix(" frames"),
+        );
+        if ui.button("Start capture").clicked() {
+            capture_events.send(CaptureEvents::Start);
+        }
+    }
+}
+
+#[cfg(target_arch = "wasm32")]
+pub fn show_capture(
+    ui: &mut egui::Ui,
+    _capture: &mut Capture,
+    _capture_events: &mut EventWriter<CaptureEvents>,
+) {
+    ui.label("captures are not available in the browser");
+}
@@ -0,0 +1,274 @@
+use std::path::{Path, PathBuf};
+
+use clap::{Parser, ValueEnum};
+use serde::Deserialize;
+
+use crate::wave_2d_simulation::{SweepMetric, SweptParameter};
+use crate::RESOLUTION;
+
+/// Read from the working directory if no other file is given
+const DEFAULT_CONFIG_FILE: &str = "wave_sim.toml";
+
+const DEFAULT_WINDOW_HEIGHT: f32 = 900.0;
+
+const DEFAULT_STEPS: usize = 600;
+
+const DEFAULT_OUTPUT: &str = "exports";
+
+const DEFAULT_SWEEP_VALUES: usize = 20;
+
+#[derive(Debug, Parser)]
+#[command(name = "wave_sim", about = "wave simulations with bevy")]
+pub struct Cli {
+    /// startup options in toml, the flags take precedence over the file
+    #[arg(long)]
+    pub config: Option<PathBuf>,
+
+    /// name of the simulation to start with, or to run in headless mode
+    #[arg(long)]
+    pub simulation: Option<String>,
+
+    /// window width in logical pixels, 16:9 to the height if omitted
+    #[arg(long)]
+    pub width: Option<f32>,
+
+    /// window height in logical pixels
+    #[arg(long)]
+    pub height: Option<f32>,
+
+    /// wait for the vertical blank of the display, on if omitted
+    #[arg(long)]
+    pub vsync: Option<bool>,
+
+    /// samples per pixel of the multisample anti-aliasing, 1 or 4
+    #[arg(long)]
+    pub msaa: Option<u32>,
+
+    /// run the simulation without a window and write the results to files
+    #[arg(long)]
+    pub headless: bool,
+
+    /// compare the 2d finite difference solver with analytical solutions,
+    /// print the errors and convergence orders and exit
+    #[arg(long)]
+    pub validate: bool,
+
+    /// number of solver steps in headless mode [default: 600]
+    #[arg(long)]
+    pub steps: Option<usize>,
+
+    /// directory the headless results are written to [default: exports]
+    #[arg(long)]
+    pub output: Option<PathBuf>,
+
+    /// grid cell to record in headless mode, given as `x,y`, can be repeated
+    #[arg(long = "probe", value_parser = parse_cell)]
+    pub probes: Vec<(usize, usize)>,
+
+    /// grid width in cells
+    #[arg(long)]
+    pub dimx: Option<usize>,
+
+    /// grid height in cells
+    #[arg(long)]
+    pub dimy: Option<usize>,
+
+    /// frequency in Hz of the applied force, no force is applied if omitted
+    #[arg(long)]
+    pub frequency: Option<f32>,
+
+    /// wave velocity in world units per second
+    #[arg(long)]
+    pub wave_velocity: Option<f32>,
+
+    /// fraction of the amplitude kept after every step
+    #[arg(long)]
+    pub energy_loss_fraction: Option<f32>,
+
+    /// listen for json commands on this udp port right away
+    #[arg(long)]
+    pub remote_port: Option<u16>,
+
+    /// accept the json commands from other machines, only this one can
+    /// send them if omitted
+    #[arg(long)]
+    pub remote_all_interfaces: bool,
+
+    /// in headless mode, run `--steps` steps at every value of this
+    /// parameter and write the metric of every run instead of the grid
+    #[arg(long, value_enum)]
+    pub sweep: Option<SweptParameter>,
+
+    /// first value of the swept parameter
+    #[arg(long)]
+    pub sweep_from: Option<f32>,
+
+    /// last value of the swept parameter
+    #[arg(long)]
+    pub sweep_to: Option<f32>,
+
+    /// number of values of the sweep [default: 20]
+    #[arg(long)]
+    pub sweep_values: Option<usize>,
+
+    /// measured at the first `--probe`, or over the whole grid without one
+    /// [default: max-amplitude]
+    #[arg(long, value_enum)]
+    pub sweep_metric: Option<SweepMetric>,
+}
+
+/// Startup options of `wave_sim.toml`, every option is optional and named
+/// like its flag
+#[derive(Debug, Default, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+struct Config {
+    /// named like the value of `--simulation`
+    simulation: Option<String>,
+    width: Option<f32>,
+    height: Option<f32>,
+    vsync: Option<bool>,
+    msaa: Option<u32>,
+    headless: Option<bool>,
+    steps: Option<usize>,
+    output: Option<PathBuf>,
+    /// cells as `[x, y]`
+    probes: Option<Vec<(usize, usize)>>,
+    dimx: Option<usize>,
+    dimy: Option<usize>,
+    frequency: Option<f32>,
+    wave_velocity: Option<f32>,
+    energy_loss_fraction: Option<f32>,
+    remote_port: Option<u16>,
+    remote_all_interfaces: Option<bool>,
+    /// named like the values of `--sweep` and `--sweep-metric`
+    sweep: Option<String>,
+    sweep_from: Option<f32>,
+    sweep_to: Option<f32>,
+    sweep_values: Option<usize>,
+    sweep_metric: Option<String>,
+}
+
+impl Cli {
+    /// Parses the flags and fills in the options they leave out from the
+    /// config file
+    pub fn load() -> Result<Self, String> {
+        let mut cli = Cli::parse();
+
+        // only a config file which was asked for has to exist
+        let config = match &cli.config {
+            Some(path) => Some(read_config(path)?),
+            None if Path::new(DEFAULT_CONFIG_FILE).exists() => {
+                Some(read_config(Path::new(DEFAULT_CONFIG_FILE))?)
+            }
+            None => None,
+        };
+        if let Some(config) = config {
+            cli.merge(config)?;
+        }
+
+        if let Some(samples) = cli.msaa {
+            if samples != 1 && samples != 4 {
+                return Err(format!(
+                    "msaa supports 1 or 4 samples, got {}",
+                    samples
+                ));
+            }
+        }
+
+        Ok(cli)
+    }
+
+    fn merge(&mut self, config: Config) -> Result<(), String> {
+        self.simulation = self.simulation.take().or(config.simulation);
+        self.width = self.width.or(config.width);
+        self.height = self.height.or(config.height);
+        self.vsync = self.vsync.or(config.vsync);
+        self.msaa = self.msaa.or(config.msaa);
+        self.headless |= config.headless.unwrap_or(false);
+        self.steps = self.steps.or(config.steps);
+        self.output = self.output.take().or(config.output);
+        if self.probes.is_empty() {
+            self.probes = config.probes.unwrap_or_default();
+        }
+        self.dimx = self.dimx.or(config.dimx);
+        self.dimy = self.dimy.or(config.dimy);
+        self.frequency = self.frequency.or(config.frequency);
+        self.wave_velocity = self.wave_velocity.or(config.wave_velocity);
+        self.energy_loss_fraction =
+            self.energy_loss_fraction.or(config.energy_loss_fraction);
+        self.remote_port = self.remote_port.or(config.remote_port);
+        self.remote_all_interfaces |=
+            config.remote_all_interfaces.unwrap_or(false);
+        if self.sweep.is_none() {
+            if let Some(sweep) = config.sweep {
+                self.sweep = Some(
+                    SweptParameter::from_str(&sweep, false)
+                        .map_err(|error| format!("invalid sweep: {}", error))?,
+                );
+            }
+        }
+        self.sweep_from = self.sweep_from.or(config.sweep_from);
+        self.sweep_to = self.sweep_to.or(config.sweep_to);
+        self.sweep_values = self.sweep_values.or(config.sweep_values);
+        if self.sweep_metric.is_none() {
+            if let Some(metric) = config.sweep_metric {
+                self.sweep_metric =
+                    Some(SweepMetric::from_str(&metric, false).map_err(
+                        |error| format!("invalid sweep metric: {}", error),
+                    )?);
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Logical size of the window, a missing side follows the other one at
+    /// the default resolution
+    pub fn window_size(&self) -> (f32, f32) {
+        match (self.width, self.height) {
+            (Some(width), Some(height)) => (width, height),
+            (Some(width), None) => (width, width / RESOLUTION),
+            (None, height) => {
+                let height = height.unwrap_or(DEFAULT_WINDOW_HEIGHT);
+                (height * RESOLUTION, height)
+            }
+        }
+    }
+
+    pub fn steps(&self) -> usize {
+        self.steps.unwrap_or(DEFAULT_STEPS)
+    }
+
+    pub fn sweep_values(&self) -> usize {
+        self.sweep_values.unwrap_or(DEFAULT_SWEEP_VALUES)
+    }
+
+    pub fn output(&self) -> PathBuf {
+        self.output
+            .clone()
+            .unwrap_or_else(|| PathBuf::from(DEFAULT_OUTPUT))
+    }
+}
+
+fn read_config(path: &Path) -> Result<Config, String> {
+    let contents = std::fs::read_to_string(path).map_err(|error| {
+        format!("failed to read {}: {}", path.display(), error)
+    })?;
+
+    toml::from_str(&contents)
+        .map_err(|error| format!("invalid {}: {}", path.display(), error))
+}
+
+fn parse_cell(value: &str) -> Result<(usize, usize), String> {
+    let (x, y) = value
+        .split_once(',')
+        .ok_or_else(|| format!("expected `x,y`, got `{}`", value))?;
+
+    let parse = |coordinate: &str| {
+        coordinate.trim().parse::<usize>().map_err(|error| {
+            format!("invalid coordinate `{}`: {}", coordinate, error)
+        })
+    };
+
+    Ok((parse(x)?, parse(y)?))
+}
@@ -1,16 +1,29 @@
+use bevy::pbr::{DrawMesh, SetMeshBindGroup, SetMeshViewBindGroup};
 use bevy::prelude::*;
+use bevy::render::mesh::{Indices, MeshVertexAttribute};
 use bevy::render::render_phase::SetItemPipeline;
+use bevy::render::render_resource::{PrimitiveTopology, VertexFormat};
 use bevy::sprite::{DrawMesh2d, SetMesh2dBindGroup, SetMesh2dViewBindGroup};
 
 mod pipeline;
 mod plugin;
 
-pub use plugin::ColoredMesh2dPlugin;
+pub use plugin::{ColoredMesh2dPlugin, ColoredMesh3dPlugin};
+
+/// Linear rgba color of every vertex, as returned by
+/// `Color::as_linear_rgba_u32`
+pub const VERTEX_ATTRIBUTE_COLOR_ID: MeshVertexAttribute =
+    MeshVertexAttribute::new("Vertex_Color", 1, VertexFormat::Uint32);
 
 /// A marker component for colored 2d meshes
 #[derive(Component, Default)]
 pub struct ColoredMesh2d;
 
+/// A marker component for colored 3d meshes, they need normals besides the
+/// positions and colors and are lit by a fixed light
+#[derive(Component, Default)]
+pub struct ColoredMesh3d;
+
 // This specifies how to render a colored 2d mesh
 pub type DrawColoredMesh2d = (
     // Set the pipeline
@@ -22,3 +35,64 @@ pub type DrawColoredMesh2d = (
     // Draw the mesh
     DrawMesh2d,
 );
+
+// This specifies how to render a colored 3d mesh
+pub type DrawColoredMesh3d = (
+    // Set the pipeline
+    SetItemPipeline,
+    // Set the view uniform as bind group 0
+    SetMeshViewBindGroup<0>,
+    // Set the mesh uniform as bind group 1
+    SetMeshBindGroup<1>,
+    // Draw the mesh
+    DrawMesh,
+);
+
+/// Flat mesh with a white vertex per cell of a `dimx * dimy` grid, the first
+/// cell at the origin
+pub fn grid_mesh(dimx: usize, dimy: usize, cellsize: f32) -> Mesh {
+    let cells = dimx * dimy;
+    let dimx: u32 = (dimx - 1).try_into().unwrap();
+    let dimy: u32 = (dimy - 1).try_into().unwrap();
+
+    let mut mesh = Mesh::new(PrimitiveTopology::TriangleList);
+
+    let mut v_pos: Vec<[f32; 3]> = Vec::with_capacity(cells);
+    let mut v_color: Vec<u32> = Vec::with_capacity(cells);
+
+    let white = Color::WHITE.as_linear_rgba_u32();
+
+    for x in 0..=dimx {
+        for y in 0..=dimy {
+            // positions of vertices
+            let scaled_x = x as f32 * cellsize;
+            let scaled_y = y as f32 * cellsize;
+            v_pos.push([scaled_x, scaled_y, 0.0]);
+
+            // color of vertices
+            v_color.push(white);
+        }
+    }
+
+    mesh.insert_attribute(Mesh::ATTRIBUTE_POSITION, v_pos);
+    mesh.insert_attribute(VERTEX_ATTRIBUTE_COLOR_ID, v_color);
+
+    // indices of vertices
+    let mut indices: Vec<u32> = Vec::with_capacity(cells);
+
+    for c in 0..dimx {
+        for r in 0..dimy {
+            let i = c * (dimy + 1) + r;
+
+            let r_ru_triangle = [i, i + dimy + 1, i + dimy + 2]; // right and right up triangle
+            let ru_u_triangle = [i, i + dimy + 2, i + 1]; // right up and up triagle
+
+            indices.extend_from_slice(&r_ru_triangle);
+            indices.extend_from_slice(&ru_u_triangle);
+        }
+    }
+
+    mesh.set_indices(Some(Indices::U32(indices)));
+
+    mesh
+}
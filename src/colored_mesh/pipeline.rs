@@ -1,9 +1,13 @@
+use bevy::pbr::{MeshPipeline, MeshPipelineKey};
 use bevy::prelude::*;
+use bevy::render::mesh::MeshVertexBufferLayout;
 use bevy::render::render_resource::*;
 use bevy::render::texture::BevyDefault;
 use bevy::render::view::ViewTarget;
 use bevy::sprite::{Mesh2dPipeline, Mesh2dPipelineKey};
 
+use super::VERTEX_ATTRIBUTE_COLOR_ID;
+
 /// Custom pipeline for 2d meshes with vertex colors
 #[derive(Resource)]
 pub struct ColoredMesh2dPipeline {
@@ -95,3 +99,64 @@ impl SpecializedRenderPipeline for ColoredMesh2dPipeline {
         }
     }
 }
+
+/// Custom pipeline for 3d meshes with vertex colors
+#[derive(Resource)]
+pub struct ColoredMesh3dPipeline {
+    /// this pipeline wraps the standard [`MeshPipeline`]
+    mesh_pipeline: MeshPipeline,
+    shader_handle: Handle<Shader>,
+}
+
+impl FromWorld for ColoredMesh3dPipeline {
+    fn from_world(world: &mut World) -> Self {
+        let shader_handle = world
+            .resource::<AssetServer>()
+            .load::<Shader, &str>("shaders/3d_mesh.wgsl");
+
+        Self {
+            mesh_pipeline: world.resource::<MeshPipeline>().clone(),
+            shader_handle,
+        }
+    }
+}
+
+// We implement `SpecializedMeshPipeline` to customize the default rendering
+// from `MeshPipeline`
+impl SpecializedMeshPipeline for ColoredMesh3dPipeline {
+    type Key = MeshPipelineKey;
+
+    fn specialize(
+        &self,
+        key: Self::Key,
+        layout: &MeshVertexBufferLayout,
+    ) -> Result<RenderPipelineDescriptor, SpecializedMeshPipelineError> {
+        let mut descriptor = self.mesh_pipeline.specialize(key, layout)?;
+
+        // Our meshes only have positions, normals and colors
+        let vertex_layout = layout.get_layout(&[
+            Mesh::ATTRIBUTE_POSITION.at_shader_location(0),
+            Mesh::ATTRIBUTE_NORMAL.at_shader_location(1),
+            VERTEX_ATTRIBUTE_COLOR_ID.at_shader_location(2),
+        ])?;
+        descriptor.vertex.buffers = vec![vertex_layout];
+
+        // Use our custom shader
+        descriptor.vertex.shader = self.shader_handle.clone();
+        descriptor.fragment.as_mut().unwrap().shader =
+            self.shader_handle.clone();
+
+        // Surfaces are seen from both sides
+        descriptor.primitive.cull_mode = None;
+
+        // Use the two standard uniforms for meshes
+        descriptor.layout = Some(vec![
+            // Bind group 0 is the view uniform
+            self.mesh_pipeline.view_layout.clone(),
+            // Bind group 1 is the mesh uniform
+            self.mesh_pipeline.mesh_layout.clone(),
+        ]);
+
+        Ok(descriptor)
+    }
+}
@@ -1,19 +1,23 @@
 use bevy::core_pipeline::core_2d::Transparent2d;
+use bevy::core_pipeline::core_3d::Opaque3d;
+use bevy::pbr::{MeshPipelineKey, MeshUniform};
 use bevy::prelude::*;
 use bevy::render::render_asset::RenderAssets;
 use bevy::render::render_phase::{
     AddRenderCommand, DrawFunctions, RenderPhase,
 };
 use bevy::render::render_resource::{
-    PipelineCache, SpecializedRenderPipelines,
+    PipelineCache, SpecializedMeshPipelines, SpecializedRenderPipelines,
 };
 use bevy::render::view::{ExtractedView, VisibleEntities};
 use bevy::render::{Extract, RenderApp, RenderStage};
 use bevy::sprite::{Mesh2dHandle, Mesh2dPipelineKey, Mesh2dUniform};
 use bevy::utils::FloatOrd;
 
-use super::pipeline::ColoredMesh2dPipeline;
-use super::{ColoredMesh2d, DrawColoredMesh2d};
+use super::pipeline::{ColoredMesh2dPipeline, ColoredMesh3dPipeline};
+use super::{
+    ColoredMesh2d, ColoredMesh3d, DrawColoredMesh2d, DrawColoredMesh3d,
+};
 
 /// Plugin that renders [`ColoredMesh2d`]s
 pub struct ColoredMesh2dPlugin;
@@ -114,3 +118,108 @@ pub fn queue_colored_mesh2d(
         }
     }
 }
+
+/// Plugin that renders [`ColoredMesh3d`]s
+pub struct ColoredMesh3dPlugin;
+
+impl Plugin for ColoredMesh3dPlugin {
+    fn build(&self, app: &mut App) {
+        let sub_app = app.get_sub_app_mut(RenderApp).unwrap();
+
+        sub_app
+            .add_render_command::<Opaque3d, DrawColoredMesh3d>()
+            .init_resource::<ColoredMesh3dPipeline>()
+            .init_resource::<SpecializedMeshPipelines<ColoredMesh3dPipeline>>()
+            .add_system_to_stage(RenderStage::Extract, extract_colored_mesh3d)
+            .add_system_to_stage(RenderStage::Queue, queue_colored_mesh3d);
+    }
+}
+
+/// Extract the [`ColoredMesh3d`] marker component into the render app
+pub fn extract_colored_mesh3d(
+    mut commands: Commands,
+    mut previous_len: Local<usize>,
+    query: Extract<Query<(Entity, &ComputedVisibility), With<ColoredMesh3d>>>,
+) {
+    let mut values = Vec::with_capacity(*previous_len);
+    for (entity, computed_visibility) in &query {
+        if !computed_visibility.is_visible() {
+            continue;
+        }
+        values.push((entity, ColoredMesh3d));
+    }
+    *previous_len = values.len();
+    commands.insert_or_spawn_batch(values);
+}
+
+/// Queue the 3d meshes marked with [`ColoredMesh3d`] using our custom
+/// pipeline and draw function
+#[allow(clippy::too_many_arguments)]
+pub fn queue_colored_mesh3d(
+    opaque_draw_functions: Res<DrawFunctions<Opaque3d>>,
+    colored_mesh3d_pipeline: Res<ColoredMesh3dPipeline>,
+    mut pipelines: ResMut<SpecializedMeshPipelines<ColoredMesh3dPipeline>>,
+    mut pipeline_cache: ResMut<PipelineCache>,
+    msaa: Res<Msaa>,
+    render_meshes: Res<RenderAssets<Mesh>>,
+    colored_mesh3d: Query<(&Handle<Mesh>, &MeshUniform), With<ColoredMesh3d>>,
+    mut views: Query<(
+        &VisibleEntities,
+        &mut RenderPhase<Opaque3d>,
+        &ExtractedView,
+    )>,
+) {
+    if colored_mesh3d.is_empty() {
+        return;
+    }
+    // Iterate each view (a camera is a view)
+    for (visible_entities, mut opaque_phase, view) in &mut views {
+        let draw_colored_mesh3d = opaque_draw_functions
+            .read()
+            .get_id::<DrawColoredMesh3d>()
+            .unwrap();
+
+        let view_key = MeshPipelineKey::from_msaa_samples(msaa.samples)
+            | MeshPipelineKey::from_hdr(view.hdr);
+        let rangefinder = view.rangefinder3d();
+
+        // Queue all entities visible to that view
+        for visible_entity in &visible_entities.entities {
+            if let Ok((mesh_handle, mesh_uniform)) =
+                colored_mesh3d.get(*visible_entity)
+            {
+                let mesh = if let Some(mesh) = render_meshes.get(mesh_handle) {
+                    mesh
+                } else {
+                    continue;
+                };
+
+                // Get our specialized pipeline
+                let key = view_key
+                    | MeshPipelineKey::from_primitive_topology(
+                        mesh.primitive_topology,
+                    );
+                let pipeline_id = match pipelines.specialize(
+                    &mut pipeline_cache,
+                    &colored_mesh3d_pipeline,
+                    key,
+                    &mesh.layout,
+                ) {
+                    Ok(pipeline_id) => pipeline_id,
+                    Err(error) => {
+                        error!("{}", error);
+                        continue;
+                    }
+                };
+
+                opaque_phase.add(Opaque3d {
+                    entity: *visible_entity,
+                    draw_function: draw_colored_mesh3d,
+                    pipeline: pipeline_id,
+                    // Opaque meshes are sorted front to back
+                    distance: rangefinder.distance(&mesh_uniform.transform),
+                });
+            }
+        }
+    }
+}
@@ -0,0 +1,217 @@
+use bevy::prelude::*;
+use serde::{Deserialize, Serialize};
+
+/// Gain of the normalized amplitude inside the logarithm of
+/// [`log_compress`], larger values lift small amplitudes further
+const LOG_GAIN: f32 = 48.0;
+
+/// Value of the logarithm which maps to the full color range
+const LOG_RANGE: f32 = 4.0;
+
+/// Samples of the lookup table of [`log_compress`]
+const LOG_TABLE_SIZE: usize = 1024;
+
+/// Colormaps used to render signed amplitudes
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub enum Colormap {
+    Grayscale,
+    Viridis,
+    Inferno,
+    Seismic,
+    /// cyclic map whose ends meet, used for phases and not offered for
+    /// amplitudes
+    Twilight,
+}
+
+impl Colormap {
+    pub const ALL: [Colormap; 4] = [
+        Colormap::Grayscale,
+        Colormap::Viridis,
+        Colormap::Inferno,
+        Colormap::Seismic,
+    ];
+
+    /// Maps a normalized, signed amplitude in `-1.0..=1.0` to a color.
+    ///
+    /// Sequential maps spread the whole range over their gradient, so zero
+    /// lands in the middle. The diverging map keeps zero white and shows the
+    /// sign as blue (negative) or red (positive).
+    pub fn color(&self, amplitude: f32) -> Color {
+        let amplitude = if amplitude.is_nan() {
+            0.0
+        } else {
+            amplitude.clamp(-1.0, 1.0)
+        };
+        let t = (amplitude + 1.0) / 2.0;
+
+        match self {
+            Colormap::Grayscale => Color::rgb(t, t, t),
+            Colormap::Viridis => polynomial(t, &VIRIDIS),
+            Colormap::Inferno => polynomial(t, &INFERNO),
+            Colormap::Seismic => piecewise_linear(t, &SEISMIC),
+            Colormap::Twilight => piecewise_linear(t, &TWILIGHT),
+        }
+    }
+}
+
+impl From<Colormap> for String {
+    fn from(value: Colormap) -> Self {
+        match value {
+            Colormap::Grayscale => "grayscale".to_string(),
+            Colormap::Viridis => "viridis".to_string(),
+            Colormap::Inferno => "inferno".to_string(),
+            Colormap::Seismic => "seismic".to_string(),
+            Colormap::Twilight => "twilight".to_string(),
+        }
+    }
+}
+
+/// Compresses a normalized amplitude logarithmically while keeping its sign,
+/// so small amplitudes stay visible and the result lies in `-1.0..=1.0`
+pub fn log_compress(amplitude: f32) -> f32 {
+    let scaled = (amplitude.abs() * LOG_GAIN + 1.0).ln() / LOG_RANGE;
+    (scaled * amplitude.signum()).clamp(-1.0, 1.0)
+}
+
+/// Inverse of [`log_compress`], the normalized amplitude which is shown with
+/// the color of `value`
+pub fn log_expand(value: f32) -> f32 {
+    let value = value.clamp(-1.0, 1.0);
+    ((value.abs() * LOG_RANGE).exp() - 1.0) / LOG_GAIN * value.signum()
+}
+
+/// [`log_compress`] sampled up to the amplitude where it saturates and
+/// interpolated linearly, which is cheaper than a logarithm for every vertex
+/// and stays within 1e-4 of it
+pub struct LogCompressionTable {
+    samples: Vec<f32>,
+    /// samples per unit of amplitude
+    resolution: f32,
+}
+
+impl Default for LogCompressionTable {
+    fn default() -> Self {
+        let saturation = (LOG_RANGE.exp() - 1.0) / LOG_GAIN;
+        let resolution = (LOG_TABLE_SIZE - 1) as f32 / saturation;
+        let samples = (0..LOG_TABLE_SIZE)
+            .map(|i| log_compress(i as f32 / resolution))
+            .collect();
+
+        Self {
+            samples,
+            resolution,
+        }
+    }
+}
+
+impl LogCompressionTable {
+    pub fn get(&self, amplitude: f32) -> f32 {
+        let position = amplitude.abs() * self.resolution;
+        let last = self.samples.len() - 1;
+
+        // saturated, NaN stays NaN like in `log_compress`
+        if position >= last as f32 || position.is_nan() {
+            return amplitude.signum() * self.samples[last];
+        }
+
+        let index = position as usize;
+        let fraction = position - index as f32;
+        let value = self.samples[index]
+            + (self.samples[index + 1] - self.samples[index]) * fraction;
+        value.copysign(amplitude)
+    }
+}
+
+/// Builds `len` materials sampling `colormap` evenly from -1.0 to 1.0
+pub fn build_palette(
+    colormap: Colormap,
+    len: usize,
+    materials: &mut Assets<StandardMaterial>,
+) -> Vec<Handle<StandardMaterial>> {
+    (0..len)
+        .map(|i| {
+            let amplitude = i as f32 / (len - 1) as f32 * 2.0 - 1.0;
+            materials.add(StandardMaterial::from(colormap.color(amplitude)))
+        })
+        .collect()
+}
+
+/// Index into a palette created by [`build_palette`] for a signed amplitude
+pub fn palette_index(amplitude: f32, len: usize) -> usize {
+    let t = (amplitude.clamp(-1.0, 1.0) + 1.0) / 2.0;
+    ((t * (len - 1) as f32).round() as usize).min(len - 1)
+}
+
+// polynomial fits of the matplotlib colormaps, courtesy of:
+// https://www.shadertoy.com/view/WlfXRN
+
+const VIRIDIS: [[f32; 3]; 7] = [
+    [0.2777273, 0.005407345, 0.3340998],
+    [0.105093, 1.404613, 1.38459],
+    [-0.3308618, 0.2148476, 0.09509516],
+    [-4.634231, -5.799101, -19.33244],
+    [6.22827, 14.17993, 56.69055],
+    [4.776385, -13.74515, -65.35304],
+    [-5.435456, 4.645853, 26.31244],
+];
+
+const INFERNO: [[f32; 3]; 7] = [
+    [0.0002189404, 0.001651005, -0.0194809],
+    [0.1065134, 0.5639564, 3.932712],
+    [11.60249, -3.972854, -15.94239],
+    [-41.70399, 17.4364, 44.35415],
+    [77.16294, -33.40236, -81.80731],
+    [-71.31943, 32.62606, 73.20952],
+    [25.13113, -12.24267, -23.07033],
+];
+
+const SEISMIC: [(f32, [f32; 3]); 5] = [
+    (0.0, [0.0, 0.0, 0.3]),
+    (0.25, [0.0, 0.0, 1.0]),
+    (0.5, [1.0, 1.0, 1.0]),
+    (0.75, [1.0, 0.0, 0.0]),
+    (1.0, [0.5, 0.0, 0.0]),
+];
+
+// the first and the last stop are equal, so the map wraps around
+const TWILIGHT: [(f32, [f32; 3]); 5] = [
+    (0.0, [0.89, 0.85, 0.89]),
+    (0.25, [0.37, 0.42, 0.73]),
+    (0.5, [0.19, 0.07, 0.24]),
+    (0.75, [0.7, 0.3, 0.23]),
+    (1.0, [0.89, 0.85, 0.89]),
+];
+
+fn polynomial(t: f32, coefficients: &[[f32; 3]; 7]) -> Color {
+    let mut rgb = [0.0; 3];
+
+    for (channel, value) in rgb.iter_mut().enumerate() {
+        // horner scheme
+        *value = coefficients
+            .iter()
+            .rev()
+            .fold(0.0, |acc, c| acc * t + c[channel])
+            .clamp(0.0, 1.0);
+    }
+
+    Color::rgb(rgb[0], rgb[1], rgb[2])
+}
+
+fn piecewise_linear(t: f32, stops: &[(f32, [f32; 3])]) -> Color {
+    for window in stops.windows(2) {
+        let (t0, c0) = window[0];
+        let (t1, c1) = window[1];
+
+        if t <= t1 {
+            let f = (t - t0) / (t1 - t0);
+            return Color::rgb(
+                c0[0] + (c1[0] - c0[0]) * f,
+                c0[1] + (c1[1] - c0[1]) * f,
+                c0[2] + (c1[2] - c0[2]) * f,
+            );
+        }
+    }
+
+    let [r, g, b] = stops[stops.len() - 1].1;
+    Color::rgb(r, g, b)
+}
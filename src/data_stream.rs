@@ -0,0 +1,207 @@
+#[cfg(not(target_arch = "wasm32"))]
+use std::net::{Ipv4Addr, TcpListener, TcpStream};
+#[cfg(not(target_arch = "wasm32"))]
+use std::sync::mpsc::{sync_channel, Receiver};
+use std::sync::mpsc::{SyncSender, TrySendError};
+use std::sync::{Arc, Mutex};
+#[cfg(not(target_arch = "wasm32"))]
+use std::thread;
+
+use bevy::prelude::*;
+use bevy_egui::egui;
+#[cfg(not(target_arch = "wasm32"))]
+use tungstenite::Message;
+
+pub const DEFAULT_STREAM_PORT: u16 = 9001;
+
+/// Messages queued per client, a client which falls further behind misses
+/// messages instead of slowing down the simulation
+const CLIENT_QUEUE_SIZE: usize = 16;
+
+type Clients = Arc<Mutex<Vec<SyncSender<Arc<str>>>>>;
+
+/// WebSocket server which streams JSON messages of the running simulation
+/// to every connected client, e.g. a dashboard or a notebook
+#[derive(Resource)]
+pub struct DataStream {
+    pub enabled: bool,
+    pub port: u16,
+    /// accept clients from other machines, only the local one otherwise
+    pub all_interfaces: bool,
+    /// frames between two grid messages
+    pub frame_interval: u32,
+    /// cells averaged along each side into a single value of a grid message
+    pub downsample: usize,
+    clients: Clients,
+    /// port the accepting thread listens on
+    listening: Option<u16>,
+    last_error: Option<String>,
+}
+
+impl Default for DataStream {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            port: DEFAULT_STREAM_PORT,
+            all_interfaces: false,
+            frame_interval: 6,
+            downsample: 4,
+            clients: Clients::default(),
+            listening: None,
+            last_error: None,
+        }
+    }
+}
+
+impl DataStream {
+    /// Whether anybody listens, so messages are only built when needed
+    pub fn has_clients(&self) -> bool {
+        self.listening.is_some()
+            && self
+                .clients
+                .lock()
+                .map_or(false, |clients| !clients.is_empty())
+    }
+
+    /// Sends a message to all clients, clients which disconnected are
+    /// dropped
+    pub fn publish(&self, message: &str) {
+        let message: Arc<str> = Arc::from(message);
+
+        if let Ok(mut clients) = self.clients.lock() {
+            clients.retain(|client| match client.try_send(message.clone()) {
+                Ok(()) | Err(TrySendError::Full(_)) => true,
+                Err(TrySendError::Disconnected(_)) => false,
+            });
+        }
+    }
+}
+
+pub struct DataStreamPlugin;
+
+impl Plugin for DataStreamPlugin {
+    fn build(&self, app: &mut App) {
+        app.insert_resource(DataStream::default())
+            .add_system(update_listener);
+    }
+}
+
+/// Starts accepting clients once the stream is enabled, the listener can't
+/// be stopped from another thread, so a disabled stream only drops its
+/// clients and keeps the port
+#[cfg(not(target_arch = "wasm32"))]
+fn update_listener(mut stream: ResMut<DataStream>) {
+    if !stream.enabled {
+        if let Ok(mut clients) = stream.clients.lock() {
+            clients.clear();
+        }
+        return;
+    }
+
+    if stream.listening.is_some() || stream.last_error.is_some() {
+        return;
+    }
+
+    let ip = if stream.all_interfaces {
+        Ipv4Addr::UNSPECIFIED
+    } else {
+        Ipv4Addr::LOCALHOST
+    };
+    match TcpListener::bind((ip, stream.port)) {
+        Ok(listener) => {
+            info!("streaming data on ws://{}:{}", ip, stream.port);
+            let clients = stream.clients.clone();
+            thread::spawn(move || accept_clients(listener, clients));
+            stream.listening = Some(stream.port);
+        }
+        Err(error) => {
+            error!("failed to listen on tcp port {}: {}", stream.port, error);
+            stream.last_error = Some(error.to_string());
+        }
+    }
+}
+
+#[cfg(target_arch = "wasm32")]
+fn update_listener(mut stream: ResMut<DataStream>) {
+    if stream.enabled && stream.last_error.is_none() {
+        stream.last_error = Some("not available in the browser".to_string());
+    }
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+fn accept_clients(listener: TcpListener, clients: Clients) {
+    for connection in listener.incoming().flatten() {
+        let (sender, receiver) = sync_channel(CLIENT_QUEUE_SIZE);
+        if let Ok(mut clients) = clients.lock() {
+            clients.push(sender);
+        }
+        thread::spawn(move || serve_client(connection, receiver));
+    }
+}
+
+/// Forwards the queued messages until the client disconnects or the stream
+/// drops the client
+#[cfg(not(target_arch = "wasm32"))]
+fn serve_client(connection: TcpStream, messages: Receiver<Arc<str>>) {
+    let address = connection.peer_addr().ok();
+    let mut websocket = match tungstenite::accept(connection) {
+        Ok(websocket) => websocket,
+        Err(error) => {
+            warn!("websocket handshake failed: {}", error);
+            return;
+        }
+    };
+    info!("stream client connected: {:?}", address);
+
+    for message in messages {
+        if websocket
+            .write_message(Message::Text(message.to_string()))
+            .is_err()
+        {
+            break;
+        }
+    }
+
+    let _ = websocket.close(None);
+    info!("stream client disconnected: {:?}", address);
+}
+
+pub fn show_data_stream(ui: &mut egui::Ui, stream: &mut DataStream) {
+    egui::CollapsingHeader::new("Data stream").show(ui, |ui| {
+        ui.checkbox(&mut stream.enabled, "stream over websocket");
+
+        // the port and the interfaces are fixed once the listener runs
+        ui.add_enabled_ui(stream.listening.is_none(), |ui| {
+            let (port, all_interfaces) = (stream.port, stream.all_interfaces);
+            ui.add(egui::DragValue::new(&mut stream.port).prefix("port: "));
+            ui.checkbox(&mut stream.all_interfaces, "listen on all interfaces")
+                .on_hover_text("without, only this machine can connect");
+            if stream.port != port || stream.all_interfaces != all_interfaces {
+                stream.last_error = None;
+            }
+        });
+        if stream.all_interfaces {
+            ui.label(
+                "anyone who reaches this machine over the network can \
+                 receive the data",
+            );
+        }
+
+        ui.add(
+            egui::Slider::new(&mut stream.frame_interval, 1..=60)
+                .text("frames between grids"),
+        );
+        ui.add(
+            egui::Slider::new(&mut stream.downsample, 1..=16)
+                .text("cells per grid value"),
+        );
+
+        if let Some(error) = &stream.last_error {
+            ui.label(format!("failed to listen: {}", error));
+        } else if let Some(port) = stream.listening {
+            let clients =
+                stream.clients.lock().map_or(0, |clients| clients.len());
+            ui.label(format!("ws://localhost:{}, {} clients", port, clients));
+        }
+    });
+}
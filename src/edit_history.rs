@@ -0,0 +1,157 @@
+use bevy::prelude::*;
+use bevy_egui::egui;
+
+/// Most states kept to undo, older ones are dropped
+const MAX_UNDO_STATES: usize = 32;
+
+/// Undoes or redoes the last edit of the running simulation, sent by the
+/// keyboard shortcuts and the buttons of the side panel
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum EditHistoryEvent {
+    Undo,
+    Redo,
+}
+
+/// States before and after the edits of a simulation, the simulation
+/// commits its state whenever an edit is finished
+pub struct EditHistory<T> {
+    undo: Vec<T>,
+    redo: Vec<T>,
+    /// state after the last committed edit
+    current: Option<T>,
+}
+
+impl<T> Default for EditHistory<T> {
+    fn default() -> Self {
+        Self {
+            undo: Vec::new(),
+            redo: Vec::new(),
+            current: None,
+        }
+    }
+}
+
+impl<T> EditHistory<T> {
+    pub fn current(&self) -> Option<&T> {
+        self.current.as_ref()
+    }
+
+    pub fn can_undo(&self) -> bool {
+        !self.undo.is_empty()
+    }
+
+    pub fn can_redo(&self) -> bool {
+        !self.redo.is_empty()
+    }
+
+    /// Commits the state after an edit, which can't be redone anymore
+    pub fn commit(&mut self, state: T) {
+        if let Some(previous) = self.current.replace(state) {
+            if self.undo.len() == MAX_UNDO_STATES {
+                self.undo.remove(0);
+            }
+            self.undo.push(previous);
+        }
+        self.redo.clear();
+    }
+
+    /// Steps back to the state before the last edit and returns it
+    pub fn undo(&mut self) -> Option<&T> {
+        let state = self.undo.pop()?;
+        if let Some(current) = self.current.replace(state) {
+            self.redo.push(current);
+        }
+        self.current.as_ref()
+    }
+
+    /// Steps forward to the state of the last undone edit and returns it
+    pub fn redo(&mut self) -> Option<&T> {
+        let state = self.redo.pop()?;
+        if let Some(current) = self.current.replace(state) {
+            self.undo.push(current);
+        }
+        self.current.as_ref()
+    }
+
+    pub fn clear(&mut self) {
+        *self = Self::default();
+    }
+}
+
+pub struct EditHistoryPlugin;
+
+impl Plugin for EditHistoryPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_event::<EditHistoryEvent>();
+    }
+}
+
+pub fn show_edit_history<T>(
+    ui: &mut egui::Ui,
+    history: &EditHistory<T>,
+    history_events: &mut EventWriter<EditHistoryEvent>,
+) {
+    ui.horizontal(|ui| {
+        if ui
+            .add_enabled(history.can_undo(), egui::Button::new("Undo"))
+            .clicked()
+        {
+            history_events.send(EditHistoryEvent::Undo);
+        }
+        if ui
+            .add_enabled(history.can_redo(), egui::Button::new("Redo"))
+            .clicked()
+        {
+            history_events.send(EditHistoryEvent::Redo);
+        }
+    });
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn undo_and_redo_round_trip() {
+        let mut history = EditHistory::default();
+        history.commit(1);
+        history.commit(2);
+
+        assert_eq!(history.undo(), Some(&1));
+        assert!(!history.can_undo());
+        assert_eq!(history.redo(), Some(&2));
+        assert!(!history.can_redo());
+        assert_eq!(history.current(), Some(&2));
+    }
+
+    #[test]
+    fn commit_after_undo_clears_redo() {
+        let mut history = EditHistory::default();
+        history.commit(1);
+        history.commit(2);
+        history.undo();
+
+        history.commit(3);
+
+        assert!(!history.can_redo());
+        assert_eq!(history.redo(), None);
+        assert_eq!(history.undo(), Some(&1));
+    }
+
+    #[test]
+    fn oldest_state_is_dropped() {
+        let mut history = EditHistory::default();
+        for state in 0..=MAX_UNDO_STATES + 1 {
+            history.commit(state);
+        }
+
+        let mut undone = 0;
+        while history.undo().is_some() {
+            undone += 1;
+        }
+
+        assert_eq!(undone, MAX_UNDO_STATES);
+        // the first state was dropped to make room for the later ones
+        assert_eq!(history.current(), Some(&1));
+    }
+}
@@ -0,0 +1,47 @@
+use std::path::PathBuf;
+
+/// Opens a native dialog to choose a file with the given extension inside
+/// `directory`, which is created if it doesn't exist yet
+pub fn pick_file(
+    directory: &str,
+    extension: &str,
+    file_name: &str,
+    save: bool,
+) -> Option<PathBuf> {
+    pick_file_of_types(directory, &[extension], file_name, save)
+}
+
+/// Like [`pick_file`] for files with any of the `extensions`, the first one
+/// is proposed for a new file
+#[cfg(not(target_arch = "wasm32"))]
+pub fn pick_file_of_types(
+    directory: &str,
+    extensions: &[&str],
+    file_name: &str,
+    save: bool,
+) -> Option<PathBuf> {
+    // the dialog refuses to open in a missing directory
+    let _ = std::fs::create_dir_all(directory);
+
+    let dialog = rfd::FileDialog::new()
+        .add_filter(&extensions.join(", "), extensions)
+        .set_directory(directory)
+        .set_file_name(&format!("{}.{}", file_name, extensions[0]));
+
+    if save {
+        dialog.save_file()
+    } else {
+        dialog.pick_file()
+    }
+}
+
+#[cfg(target_arch = "wasm32")]
+pub fn pick_file_of_types(
+    _directory: &str,
+    _extensions: &[&str],
+    _file_name: &str,
+    _save: bool,
+) -> Option<PathBuf> {
+    bevy::log::warn!("files are not supported in the web build");
+    None
+}
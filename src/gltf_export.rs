@@ -0,0 +1,241 @@
+use std::fs::File;
+use std::io::{self, BufWriter, Write};
+
+use bevy::prelude::*;
+use bevy::render::mesh::{Indices, VertexAttributeValues};
+use serde_json::json;
+
+use crate::colored_mesh::VERTEX_ATTRIBUTE_COLOR_ID;
+use crate::file_dialog::pick_file;
+
+const GLTF_DIRECTORY: &str = "exports";
+
+const GLB_MAGIC: &[u8; 4] = b"glTF";
+const GLB_VERSION: u32 = 2;
+const CHUNK_JSON: &[u8; 4] = b"JSON";
+const CHUNK_BIN: &[u8; 4] = b"BIN\0";
+
+const COMPONENT_FLOAT: u32 = 5126;
+const COMPONENT_UNSIGNED_INT: u32 = 5125;
+const TARGET_ARRAY_BUFFER: u32 = 34962;
+const TARGET_ELEMENT_ARRAY_BUFFER: u32 = 34963;
+const MODE_TRIANGLES: u32 = 4;
+
+/// Asks for a file name and writes the mesh as binary glTF, with the
+/// transform of its entity applied to the vertices
+pub fn save_gltf(name: &str, mesh: &Mesh, transform: &GlobalTransform) {
+    let path = if let Some(path) = pick_file(GLTF_DIRECTORY, "glb", name, true)
+    {
+        path
+    } else {
+        return;
+    };
+
+    let result = File::create(&path).and_then(|file| {
+        let mut writer = BufWriter::new(file);
+        write_glb(&mut writer, mesh, transform)?;
+        writer.flush()
+    });
+
+    match result {
+        Ok(()) => info!("exported {}", path.display()),
+        Err(error) => {
+            error!("failed to export {}: {}", path.display(), error)
+        }
+    }
+}
+
+/// Writes the positions, normals, vertex colors and triangles of a mesh as
+/// a single `.glb` file, e.g. for a renderer or a slicer
+pub fn write_glb(
+    writer: &mut impl Write,
+    mesh: &Mesh,
+    transform: &GlobalTransform,
+) -> io::Result<()> {
+    let positions: Vec<Vec3> = match mesh.attribute(Mesh::ATTRIBUTE_POSITION) {
+        Some(VertexAttributeValues::Float32x3(positions)) => positions
+            .iter()
+            .map(|&position| transform.transform_point(position.into()))
+            .collect(),
+        _ => {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidData,
+                "the mesh has no positions",
+            ))
+        }
+    };
+
+    let matrix = transform.compute_matrix();
+    let normal_matrix = Mat3::from_mat4(matrix).inverse().transpose();
+    let normals: Option<Vec<Vec3>> =
+        match mesh.attribute(Mesh::ATTRIBUTE_NORMAL) {
+            Some(VertexAttributeValues::Float32x3(normals)) => Some(
+                normals
+                    .iter()
+                    .map(|&normal| {
+                        (normal_matrix * Vec3::from(normal)).normalize_or_zero()
+                    })
+                    .collect(),
+            ),
+            _ => None,
+        };
+
+    let colors: Option<Vec<[f32; 4]>> = match (
+        mesh.attribute(Mesh::ATTRIBUTE_COLOR),
+        mesh.attribute(VERTEX_ATTRIBUTE_COLOR_ID),
+    ) {
+        (Some(VertexAttributeValues::Float32x4(colors)), _) => {
+            Some(colors.clone())
+        }
+        // colored meshes pack linear rgba into a byte per channel
+        (_, Some(VertexAttributeValues::Uint32(colors))) => Some(
+            colors
+                .iter()
+                .map(|color| color.to_le_bytes().map(|c| c as f32 / 255.0))
+                .collect(),
+        ),
+        _ => None,
+    };
+
+    let indices: Option<Vec<u32>> =
+        mesh.indices().map(|indices| match indices {
+            Indices::U16(indices) => {
+                indices.iter().map(|&i| i as u32).collect()
+            }
+            Indices::U32(indices) => indices.clone(),
+        });
+
+    let mut buffer = Vec::new();
+    let mut buffer_views = Vec::new();
+    let mut accessors = Vec::new();
+    let mut attributes = serde_json::Map::new();
+
+    let mut add_view = |bytes: &[u8], target: u32| {
+        let view = json!({
+            "buffer": 0,
+            "byteOffset": buffer.len(),
+            "byteLength": bytes.len(),
+            "target": target,
+        });
+        buffer.extend_from_slice(bytes);
+        buffer_views.push(view);
+        buffer_views.len() - 1
+    };
+
+    let (min, max) = positions.iter().fold(
+        (Vec3::splat(f32::MAX), Vec3::splat(f32::MIN)),
+        |(min, max), &position| (min.min(position), max.max(position)),
+    );
+    let view = add_view(&float_bytes(&positions), TARGET_ARRAY_BUFFER);
+    attributes.insert("POSITION".to_string(), json!(accessors.len()));
+    accessors.push(json!({
+        "bufferView": view,
+        "componentType": COMPONENT_FLOAT,
+        "count": positions.len(),
+        "type": "VEC3",
+        "min": min.to_array(),
+        "max": max.to_array(),
+    }));
+
+    if let Some(normals) = &normals {
+        let view = add_view(&float_bytes(normals), TARGET_ARRAY_BUFFER);
+        attributes.insert("NORMAL".to_string(), json!(accessors.len()));
+        accessors.push(json!({
+            "bufferView": view,
+            "componentType": COMPONENT_FLOAT,
+            "count": normals.len(),
+            "type": "VEC3",
+        }));
+    }
+
+    if let Some(colors) = &colors {
+        let bytes: Vec<u8> = colors
+            .iter()
+            .flatten()
+            .flat_map(|value| value.to_le_bytes())
+            .collect();
+        let view = add_view(&bytes, TARGET_ARRAY_BUFFER);
+        attributes.insert("COLOR_0".to_string(), json!(accessors.len()));
+        accessors.push(json!({
+            "bufferView": view,
+            "componentType": COMPONENT_FLOAT,
+            "count": colors.len(),
+            "type": "VEC4",
+        }));
+    }
+
+    let mut primitive = json!({
+        "attributes": attributes,
+        "material": 0,
+        "mode": MODE_TRIANGLES,
+    });
+    if let Some(indices) = &indices {
+        let bytes: Vec<u8> = indices
+            .iter()
+            .flat_map(|index| index.to_le_bytes())
+            .collect();
+        let view = add_view(&bytes, TARGET_ELEMENT_ARRAY_BUFFER);
+        primitive["indices"] = json!(accessors.len());
+        accessors.push(json!({
+            "bufferView": view,
+            "componentType": COMPONENT_UNSIGNED_INT,
+            "count": indices.len(),
+            "type": "SCALAR",
+        }));
+    }
+
+    let document = json!({
+        "asset": { "version": "2.0", "generator": "wave_sim" },
+        "scene": 0,
+        "scenes": [{ "nodes": [0] }],
+        "nodes": [{ "mesh": 0 }],
+        "meshes": [{ "primitives": [primitive] }],
+        // both sides of the surfaces are seen in the app
+        "materials": [{
+            "doubleSided": true,
+            "pbrMetallicRoughness": {
+                "baseColorFactor": [1.0, 1.0, 1.0, 1.0],
+                "metallicFactor": 0.0,
+                "roughnessFactor": 0.8,
+            },
+        }],
+        "buffers": [{ "byteLength": buffer.len() }],
+        "bufferViews": buffer_views,
+        "accessors": accessors,
+    });
+
+    write_chunks(writer, document.to_string().into_bytes(), buffer)
+}
+
+fn float_bytes(vectors: &[Vec3]) -> Vec<u8> {
+    vectors
+        .iter()
+        .flat_map(|vector| vector.to_array())
+        .flat_map(|value| value.to_le_bytes())
+        .collect()
+}
+
+/// Writes the header and the two chunks of a `.glb`, chunks are padded to
+/// four bytes, the JSON with spaces
+fn write_chunks(
+    writer: &mut impl Write,
+    mut json: Vec<u8>,
+    mut binary: Vec<u8>,
+) -> io::Result<()> {
+    json.resize((json.len() + 3) / 4 * 4, b' ');
+    binary.resize((binary.len() + 3) / 4 * 4, 0);
+
+    let length = 12 + 8 + json.len() + 8 + binary.len();
+
+    writer.write_all(GLB_MAGIC)?;
+    writer.write_all(&GLB_VERSION.to_le_bytes())?;
+    writer.write_all(&(length as u32).to_le_bytes())?;
+
+    for (kind, data) in [(CHUNK_JSON, &json), (CHUNK_BIN, &binary)] {
+        writer.write_all(&(data.len() as u32).to_le_bytes())?;
+        writer.write_all(kind)?;
+        writer.write_all(data)?;
+    }
+
+    Ok(())
+}
@@ -0,0 +1,172 @@
+use bevy::prelude::*;
+use bevy_egui::{egui, EguiContext};
+use bevy_rapier3d::prelude::*;
+
+use crate::ui::PointerOverUi;
+use crate::viewports::{cursor_in_viewport, world_to_egui};
+use crate::AppCamera;
+
+const HOVER_COLOR: egui::Color32 = egui::Color32::YELLOW;
+
+/// Particle of a 3d simulation which is highlighted and described under the
+/// cursor, it needs a collider to be hit by the ray
+#[derive(Component)]
+pub struct Hoverable {
+    /// position the displacement is measured against, free particles have
+    /// none
+    pub rest_position: Option<Vec3>,
+    /// of the highlighting ring
+    pub radius: f32,
+}
+
+/// Particle under the cursor in this frame
+#[derive(Default, Resource)]
+pub struct Hovered(pub Option<Entity>);
+
+pub struct HoverPlugin;
+
+impl Plugin for HoverPlugin {
+    fn build(&self, app: &mut App) {
+        app.insert_resource(Hovered::default())
+            .add_system(hover_particles)
+            .add_system(show_hovered.after(hover_particles));
+    }
+}
+
+/// Casts a ray from the cursor into the scene, nothing is hovered while the
+/// cursor is over the ui or a mouse button is held
+pub fn hover_particles(
+    windows: Res<Windows>,
+    buttons: Res<Input<MouseButton>>,
+    pointer_over_ui: Res<PointerOverUi>,
+    rapier_context: Res<RapierContext>,
+    cameras: Query<(&Camera, &GlobalTransform), With<AppCamera>>,
+    hoverables: Query<(), With<Hoverable>>,
+    mut hovered: ResMut<Hovered>,
+) {
+    hovered.0 = None;
+
+    if hoverables.is_empty()
+        || pointer_over_ui.0
+        || buttons.get_pressed().next().is_some()
+    {
+        return;
+    }
+
+    let (camera, camera_transform) = if let Ok(camera) = cameras.get_single() {
+        camera
+    } else {
+        return;
+    };
+
+    let ray = if let Some(ray) = windows
+        .get_primary()
+        .and_then(|window| cursor_in_viewport(camera, window))
+        .and_then(|cursor| camera.viewport_to_world(camera_transform, cursor))
+    {
+        ray
+    } else {
+        return;
+    };
+
+    let is_hoverable = |entity| hoverables.contains(entity);
+    hovered.0 = rapier_context
+        .cast_ray(
+            ray.origin,
+            ray.direction,
+            Real::MAX,
+            true,
+            QueryFilter::default().predicate(&is_hoverable),
+        )
+        .map(|(entity, _)| entity);
+}
+
+/// Rings the hovered particle and shows its position, velocity and
+/// displacement next to the cursor
+fn show_hovered(
+    mut egui_ctx: ResMut<EguiContext>,
+    windows: Res<Windows>,
+    hovered: Res<Hovered>,
+    cameras: Query<(&Camera, &GlobalTransform), With<AppCamera>>,
+    particles: Query<(&GlobalTransform, &Hoverable, Option<&Velocity>)>,
+) {
+    let entity = if let Some(entity) = hovered.0 {
+        entity
+    } else {
+        return;
+    };
+
+    let ((transform, hoverable, velocity), (camera, camera_transform), window) =
+        match (
+            particles.get(entity),
+            cameras.get_single(),
+            windows.get_primary(),
+        ) {
+            (Ok(particle), Ok(camera), Some(window)) => {
+                (particle, camera, window)
+            }
+            _ => return,
+        };
+
+    let position = transform.translation();
+    let ctx = egui_ctx.ctx_mut();
+
+    highlight(
+        ctx,
+        window,
+        (camera, camera_transform),
+        position,
+        hoverable.radius,
+        HOVER_COLOR,
+    );
+
+    egui::show_tooltip_at_pointer(ctx, egui::Id::new("hover_tooltip"), |ui| {
+        ui.label(format!("entity {:?}", entity));
+        ui.label(format!("position: {}", format_vec3(position)));
+        if let Some(velocity) = velocity {
+            ui.label(format!(
+                "velocity: {}, |v| = {:.3}",
+                format_vec3(velocity.linvel),
+                velocity.linvel.length()
+            ));
+        }
+        if let Some(rest_position) = hoverable.rest_position {
+            let displacement = position - rest_position;
+            ui.label(format!(
+                "displacement: {}, |d| = {:.3}",
+                format_vec3(displacement),
+                displacement.length()
+            ));
+        }
+    });
+}
+
+/// Draws a ring of the given color around a particle at `position`
+pub fn highlight(
+    ctx: &egui::Context,
+    window: &Window,
+    (camera, camera_transform): (&Camera, &GlobalTransform),
+    position: Vec3,
+    radius: f32,
+    color: egui::Color32,
+) {
+    let to_egui =
+        |world: Vec3| world_to_egui(camera, camera_transform, window, world);
+
+    let edge = position + camera_transform.right() * radius;
+    if let (Some(center), Some(edge)) = (to_egui(position), to_egui(edge)) {
+        ctx.layer_painter(egui::LayerId::new(
+            egui::Order::Foreground,
+            egui::Id::new("particle_highlight"),
+        ))
+        .circle_stroke(
+            center,
+            center.distance(edge) + 2.0,
+            egui::Stroke::new(2.0, color),
+        );
+    }
+}
+
+pub fn format_vec3(vector: Vec3) -> String {
+    format!("({:.3}, {:.3}, {:.3})", vector.x, vector.y, vector.z)
+}
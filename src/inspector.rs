@@ -0,0 +1,132 @@
+use bevy::prelude::*;
+use bevy_egui::{egui, EguiContext};
+use bevy_rapier3d::prelude::*;
+
+use crate::hover::{
+    format_vec3, highlight, hover_particles, Hoverable, Hovered,
+};
+use crate::AppCamera;
+
+const SELECTION_COLOR: egui::Color32 = egui::Color32::LIGHT_BLUE;
+
+/// Particle shown in the inspector, selected with a control click
+#[derive(Default, Resource)]
+pub struct Selection(pub Option<Entity>);
+
+pub struct InspectorPlugin;
+
+impl Plugin for InspectorPlugin {
+    fn build(&self, app: &mut App) {
+        app.insert_resource(Selection::default())
+            .add_system(select_hovered.before(hover_particles))
+            .add_system(show_inspector);
+    }
+}
+
+/// Whether a click selects a particle instead of acting on the simulation
+pub fn selects(keys: &Input<KeyCode>) -> bool {
+    keys.any_pressed([KeyCode::LControl, KeyCode::RControl])
+}
+
+/// Selects the particle hovered in the last frame, nothing is hovered while
+/// the button is held so this runs before hovering
+fn select_hovered(
+    buttons: Res<Input<MouseButton>>,
+    keys: Res<Input<KeyCode>>,
+    hovered: Res<Hovered>,
+    mut selection: ResMut<Selection>,
+) {
+    if buttons.just_pressed(MouseButton::Left) && selects(&keys) {
+        selection.0 = hovered.0;
+    }
+}
+
+/// Window to view and edit the rigid body and velocity of the selected
+/// particle, edits are not recorded
+fn show_inspector(
+    mut egui_ctx: ResMut<EguiContext>,
+    windows: Res<Windows>,
+    mut selection: ResMut<Selection>,
+    cameras: Query<(&Camera, &GlobalTransform), With<AppCamera>>,
+    mut particles: Query<(
+        &GlobalTransform,
+        &Hoverable,
+        Option<&mut RigidBody>,
+        Option<&mut Velocity>,
+    )>,
+) {
+    let entity = if let Some(entity) = selection.0 {
+        entity
+    } else {
+        return;
+    };
+
+    // the particle is gone after a reset or a mode switch
+    let (transform, hoverable, rigid_body, velocity) =
+        if let Ok(particle) = particles.get_mut(entity) {
+            particle
+        } else {
+            selection.0 = None;
+            return;
+        };
+
+    let position = transform.translation();
+    let ctx = egui_ctx.ctx_mut();
+
+    if let (Ok(camera), Some(window)) =
+        (cameras.get_single(), windows.get_primary())
+    {
+        highlight(
+            ctx,
+            window,
+            camera,
+            position,
+            hoverable.radius,
+            SELECTION_COLOR,
+        );
+    }
+
+    let mut open = true;
+    egui::Window::new("inspector")
+        .open(&mut open)
+        .resizable(false)
+        .show(ctx, |ui| {
+            ui.label(format!("entity {:?}", entity));
+            ui.label(format!("position: {}", format_vec3(position)));
+
+            if let Some(mut rigid_body) = rigid_body {
+                ui.horizontal(|ui| {
+                    ui.label("rigid body:");
+                    for (option, name) in [
+                        (RigidBody::Dynamic, "dynamic"),
+                        (RigidBody::Fixed, "fixed"),
+                    ] {
+                        if ui.radio(*rigid_body == option, name).clicked() {
+                            *rigid_body = option;
+                        }
+                    }
+                });
+            }
+
+            if let Some(mut velocity) = velocity {
+                ui.horizontal(|ui| {
+                    ui.label("velocity:");
+                    let linvel = &mut velocity.linvel;
+                    for component in
+                        [&mut linvel.x, &mut linvel.y, &mut linvel.z]
+                    {
+                        ui.add(egui::DragValue::new(component).speed(0.01));
+                    }
+                });
+                if ui.button("Stop").clicked() {
+                    *velocity = Velocity::zero();
+                }
+            }
+
+            ui.label("control click another particle to select it");
+        });
+
+    if !open {
+        selection.0 = None;
+    }
+}
@@ -0,0 +1,97 @@
+use bevy::pbr::{SetMeshBindGroup, SetMeshViewBindGroup};
+use bevy::prelude::*;
+use bevy::render::extract_component::ExtractComponent;
+use bevy::render::render_phase::SetItemPipeline;
+use bevy_egui::egui;
+use bytemuck::{Pod, Zeroable};
+
+mod pipeline;
+mod plugin;
+
+pub use plugin::InstancedParticlesPlugin;
+
+/// Whether particles marked with [`InstancedParticle`] are drawn in one
+/// instanced draw call per mesh instead of one draw call each
+#[derive(Resource)]
+pub struct InstancedRendering(pub bool);
+
+impl Default for InstancedRendering {
+    fn default() -> Self {
+        Self(true)
+    }
+}
+
+/// A marker component for particles of a lattice, their mesh, global
+/// transform and the base color of their material are drawn as instances
+#[derive(Component, Default)]
+pub struct InstancedParticle;
+
+/// Particles with this component are drawn as camera facing discs instead of
+/// their mesh, which is hidden while the component is present
+#[derive(Component)]
+pub struct PointSprite {
+    pub radius: f32,
+}
+
+/// Quad the point sprites are drawn on, spanning -1 to 1
+#[derive(Resource)]
+pub struct SpriteQuad(Handle<Mesh>);
+
+impl FromWorld for SpriteQuad {
+    fn from_world(world: &mut World) -> Self {
+        let quad = Mesh::from(shape::Quad::new(Vec2::splat(2.0)));
+        Self(world.resource_mut::<Assets<Mesh>>().add(quad))
+    }
+}
+
+/// Position, uniform scale and linear rgba color of one particle, as read by
+/// the vertex shader
+#[derive(Clone, Copy, Pod, Zeroable)]
+#[repr(C)]
+pub struct ParticleInstance {
+    position: [f32; 3],
+    scale: f32,
+    color: [f32; 4],
+}
+
+/// Instances of all particles sharing the mesh of this entity
+#[derive(Clone, Component, Default)]
+pub struct ParticleInstances {
+    instances: Vec<ParticleInstance>,
+    /// the mesh is the [`SpriteQuad`], facing the camera
+    sprites: bool,
+}
+
+impl ExtractComponent for ParticleInstances {
+    type Query = &'static ParticleInstances;
+    type Filter = ();
+
+    fn extract_component(item: &ParticleInstances) -> Self {
+        item.clone()
+    }
+}
+
+// This specifies how to render the instanced particles
+pub type DrawParticleInstances = (
+    // Set the pipeline
+    SetItemPipeline,
+    // Set the view uniform as bind group 0
+    SetMeshViewBindGroup<0>,
+    // Set the mesh uniform as bind group 1
+    SetMeshBindGroup<1>,
+    // Draw the mesh once per instance
+    plugin::DrawMeshInstanced,
+);
+
+pub fn show_instanced_rendering(
+    ui: &mut egui::Ui,
+    instanced_rendering: &mut InstancedRendering,
+) {
+    ui.add(egui::Checkbox::new(
+        &mut instanced_rendering.0,
+        "instanced particles",
+    ))
+    .on_hover_text(
+        "draws the particles of a lattice in a single call, without shadows",
+    );
+}
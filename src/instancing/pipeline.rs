@@ -0,0 +1,95 @@
+use bevy::pbr::{MeshPipeline, MeshPipelineKey};
+use bevy::prelude::*;
+use bevy::render::mesh::MeshVertexBufferLayout;
+use bevy::render::render_resource::*;
+
+use super::ParticleInstance;
+
+/// Custom pipeline for meshes drawn once per [`ParticleInstance`]
+#[derive(Resource)]
+pub struct InstancedParticlesPipeline {
+    /// this pipeline wraps the standard [`MeshPipeline`]
+    mesh_pipeline: MeshPipeline,
+    shader_handle: Handle<Shader>,
+    sprite_shader_handle: Handle<Shader>,
+}
+
+#[derive(Clone, Copy, Hash, PartialEq, Eq)]
+pub struct InstancedParticlesKey {
+    pub mesh_key: MeshPipelineKey,
+    /// draws the instances as point sprites
+    pub sprites: bool,
+}
+
+impl FromWorld for InstancedParticlesPipeline {
+    fn from_world(world: &mut World) -> Self {
+        let asset_server = world.resource::<AssetServer>();
+        let shader_handle = asset_server
+            .load::<Shader, &str>("shaders/instanced_particles.wgsl");
+        let sprite_shader_handle =
+            asset_server.load::<Shader, &str>("shaders/particle_sprites.wgsl");
+
+        Self {
+            mesh_pipeline: world.resource::<MeshPipeline>().clone(),
+            shader_handle,
+            sprite_shader_handle,
+        }
+    }
+}
+
+// We implement `SpecializedMeshPipeline` to add the instance buffer to the
+// default rendering from `MeshPipeline`
+impl SpecializedMeshPipeline for InstancedParticlesPipeline {
+    type Key = InstancedParticlesKey;
+
+    fn specialize(
+        &self,
+        key: Self::Key,
+        layout: &MeshVertexBufferLayout,
+    ) -> Result<RenderPipelineDescriptor, SpecializedMeshPipelineError> {
+        let mut descriptor =
+            self.mesh_pipeline.specialize(key.mesh_key, layout)?;
+
+        // Use our custom shader
+        let shader_handle = if key.sprites {
+            // the quads are turned towards the camera in the shader
+            descriptor.primitive.cull_mode = None;
+            self.sprite_shader_handle.clone()
+        } else {
+            self.shader_handle.clone()
+        };
+        descriptor.vertex.shader = shader_handle.clone();
+        descriptor.fragment.as_mut().unwrap().shader = shader_handle;
+
+        // The second vertex buffer advances once per instance, locations
+        // 0 to 2 are taken by the position, normal and uv of the mesh
+        descriptor.vertex.buffers.push(VertexBufferLayout {
+            array_stride: std::mem::size_of::<ParticleInstance>() as u64,
+            step_mode: VertexStepMode::Instance,
+            attributes: vec![
+                // Position and scale
+                VertexAttribute {
+                    format: VertexFormat::Float32x4,
+                    offset: 0,
+                    shader_location: 3,
+                },
+                // Color
+                VertexAttribute {
+                    format: VertexFormat::Float32x4,
+                    offset: VertexFormat::Float32x4.size(),
+                    shader_location: 4,
+                },
+            ],
+        });
+
+        // Use the two standard uniforms for meshes
+        descriptor.layout = Some(vec![
+            // Bind group 0 is the view uniform
+            self.mesh_pipeline.view_layout.clone(),
+            // Bind group 1 is the mesh uniform
+            self.mesh_pipeline.mesh_layout.clone(),
+        ]);
+
+        Ok(descriptor)
+    }
+}
@@ -0,0 +1,331 @@
+use bevy::core_pipeline::core_3d::Transparent3d;
+use bevy::ecs::system::lifetimeless::{Read, SQuery, SRes};
+use bevy::ecs::system::SystemParamItem;
+use bevy::pbr::{MeshPipelineKey, MeshUniform, NotShadowCaster};
+use bevy::prelude::*;
+use bevy::render::extract_component::ExtractComponentPlugin;
+use bevy::render::mesh::GpuBufferInfo;
+use bevy::render::render_asset::RenderAssets;
+use bevy::render::render_phase::{
+    AddRenderCommand, DrawFunctions, EntityRenderCommand, RenderCommandResult,
+    RenderPhase, TrackedRenderPass,
+};
+use bevy::render::render_resource::{
+    Buffer, BufferInitDescriptor, BufferUsages, PipelineCache,
+    SpecializedMeshPipelines,
+};
+use bevy::render::renderer::RenderDevice;
+use bevy::render::view::{ExtractedView, NoFrustumCulling, VisibilitySystems};
+use bevy::render::{RenderApp, RenderStage};
+use bevy::transform::TransformSystem;
+use bevy::utils::HashMap;
+
+use super::pipeline::{InstancedParticlesKey, InstancedParticlesPipeline};
+use super::{
+    DrawParticleInstances, InstancedParticle, InstancedRendering,
+    ParticleInstance, ParticleInstances, PointSprite, SpriteQuad,
+};
+
+/// Plugin that renders [`InstancedParticle`]s as instances
+pub struct InstancedParticlesPlugin;
+
+impl Plugin for InstancedParticlesPlugin {
+    fn build(&self, app: &mut App) {
+        app.insert_resource(InstancedRendering::default())
+            .init_resource::<SpriteQuad>()
+            .add_plugin(ExtractComponentPlugin::<ParticleInstances>::default())
+            .add_system_to_stage(
+                CoreStage::PostUpdate,
+                hide_instanced_particles
+                    .before(VisibilitySystems::CheckVisibility),
+            )
+            .add_system_to_stage(
+                CoreStage::PostUpdate,
+                gather_particle_instances
+                    .after(TransformSystem::TransformPropagate),
+            );
+
+        let sub_app = app.get_sub_app_mut(RenderApp).unwrap();
+
+        sub_app
+            .add_render_command::<Transparent3d, DrawParticleInstances>()
+            .init_resource::<InstancedParticlesPipeline>()
+            .init_resource::<SpecializedMeshPipelines<InstancedParticlesPipeline>>()
+            .add_system_to_stage(RenderStage::Prepare, prepare_instance_buffers)
+            .add_system_to_stage(RenderStage::Queue, queue_particle_instances);
+    }
+}
+
+/// Hides the meshes of new particles, or of all particles when instancing is
+/// toggled, the instances are drawn in their place, point sprites hide the
+/// mesh as long as they are present
+#[allow(clippy::type_complexity)]
+fn hide_instanced_particles(
+    instanced_rendering: Res<InstancedRendering>,
+    removed_sprites: RemovedComponents<PointSprite>,
+    mut particles: Query<
+        (&mut Visibility, ChangeTrackers<InstancedParticle>),
+        With<InstancedParticle>,
+    >,
+    mut sprites: Query<
+        (&mut Visibility, ChangeTrackers<PointSprite>),
+        Without<InstancedParticle>,
+    >,
+) {
+    let toggled = instanced_rendering.is_changed();
+    for (mut visibility, tracker) in particles.iter_mut() {
+        if toggled || tracker.is_added() {
+            visibility.is_visible = !instanced_rendering.0;
+        }
+    }
+
+    for (mut visibility, tracker) in sprites.iter_mut() {
+        if tracker.is_added() {
+            visibility.is_visible = false;
+        }
+    }
+    for entity in removed_sprites.iter() {
+        if let Ok((mut visibility, _)) = sprites.get_mut(entity) {
+            visibility.is_visible = true;
+        }
+    }
+}
+
+/// Collects the instances of all particles into one entity per mesh, and
+/// the point sprites into one entity with the [`SpriteQuad`], entities of
+/// meshes without particles are despawned
+#[allow(clippy::too_many_arguments, clippy::type_complexity)]
+fn gather_particle_instances(
+    mut commands: Commands,
+    instanced_rendering: Res<InstancedRendering>,
+    sprite_quad: Res<SpriteQuad>,
+    materials: Res<Assets<StandardMaterial>>,
+    particles: Query<
+        (&GlobalTransform, &Handle<Mesh>, &Handle<StandardMaterial>),
+        (With<InstancedParticle>, Without<PointSprite>),
+    >,
+    sprites: Query<(
+        &GlobalTransform,
+        &PointSprite,
+        Option<&Handle<StandardMaterial>>,
+    )>,
+    mut batches: Query<(Entity, &Handle<Mesh>, &mut ParticleInstances)>,
+) {
+    let mut instances_by_mesh = HashMap::<Handle<Mesh>, Vec<_>>::default();
+
+    let instance = |transform: &GlobalTransform,
+                    radius: f32,
+                    material: &Handle<StandardMaterial>| {
+        let color = materials
+            .get(material)
+            .map_or(Color::WHITE, |material| material.base_color);
+
+        // the particles are spheres, their rotation does not show
+        ParticleInstance {
+            position: transform.translation().to_array(),
+            scale: radius * transform.affine().matrix3.x_axis.length(),
+            color: color.as_linear_rgba_f32(),
+        }
+    };
+
+    if instanced_rendering.0 {
+        for (transform, mesh, material) in particles.iter() {
+            let instance = instance(transform, 1.0, material);
+
+            if let Some(instances) = instances_by_mesh.get_mut(mesh) {
+                instances.push(instance);
+            } else {
+                instances_by_mesh.insert(mesh.clone(), vec![instance]);
+            }
+        }
+    }
+
+    let default_material = Handle::<StandardMaterial>::default();
+    let sprite_instances: Vec<_> = sprites
+        .iter()
+        .map(|(transform, sprite, material)| {
+            instance(
+                transform,
+                sprite.radius,
+                material.unwrap_or(&default_material),
+            )
+        })
+        .collect();
+    if !sprite_instances.is_empty() {
+        instances_by_mesh.insert(sprite_quad.0.clone(), sprite_instances);
+    }
+
+    for (entity, mesh, mut instances) in batches.iter_mut() {
+        if let Some(mesh_instances) = instances_by_mesh.remove(mesh) {
+            instances.instances = mesh_instances;
+        } else {
+            commands.entity(entity).despawn();
+        }
+    }
+
+    for (mesh, instances) in instances_by_mesh {
+        let sprites = mesh == sprite_quad.0;
+        commands.spawn((
+            mesh,
+            ParticleInstances { instances, sprites },
+            SpatialBundle::VISIBLE_IDENTITY,
+            // the mesh at the origin is not where the instances are
+            NoFrustumCulling,
+            // the shadow pass would draw the mesh only once
+            NotShadowCaster,
+        ));
+    }
+}
+
+/// Vertex buffer with the instances of one mesh
+#[derive(Component)]
+pub struct InstanceBuffer {
+    buffer: Buffer,
+    length: usize,
+}
+
+/// Uploads the extracted instances, the buffers are recreated every frame
+fn prepare_instance_buffers(
+    mut commands: Commands,
+    batches: Query<(Entity, &ParticleInstances)>,
+    render_device: Res<RenderDevice>,
+) {
+    for (entity, instances) in &batches {
+        let buffer =
+            render_device.create_buffer_with_data(&BufferInitDescriptor {
+                label: Some("particle instance buffer"),
+                contents: bytemuck::cast_slice(instances.instances.as_slice()),
+                usage: BufferUsages::VERTEX | BufferUsages::COPY_DST,
+            });
+        commands.entity(entity).insert(InstanceBuffer {
+            buffer,
+            length: instances.instances.len(),
+        });
+    }
+}
+
+/// Queue the meshes with [`ParticleInstances`] using our custom pipeline and
+/// draw function
+#[allow(clippy::too_many_arguments)]
+fn queue_particle_instances(
+    transparent_draw_functions: Res<DrawFunctions<Transparent3d>>,
+    instanced_particles_pipeline: Res<InstancedParticlesPipeline>,
+    mut pipelines: ResMut<SpecializedMeshPipelines<InstancedParticlesPipeline>>,
+    mut pipeline_cache: ResMut<PipelineCache>,
+    msaa: Res<Msaa>,
+    render_meshes: Res<RenderAssets<Mesh>>,
+    batches: Query<(Entity, &MeshUniform, &Handle<Mesh>, &ParticleInstances)>,
+    mut views: Query<(&ExtractedView, &mut RenderPhase<Transparent3d>)>,
+) {
+    if batches.is_empty() {
+        return;
+    }
+
+    let draw_particle_instances = transparent_draw_functions
+        .read()
+        .get_id::<DrawParticleInstances>()
+        .unwrap();
+
+    let msaa_key = MeshPipelineKey::from_msaa_samples(msaa.samples);
+
+    // Iterate each view (a camera is a view)
+    for (view, mut transparent_phase) in &mut views {
+        let view_key = msaa_key | MeshPipelineKey::from_hdr(view.hdr);
+        let rangefinder = view.rangefinder3d();
+
+        for (entity, mesh_uniform, mesh_handle, instances) in &batches {
+            let mesh = if let Some(mesh) = render_meshes.get(mesh_handle) {
+                mesh
+            } else {
+                continue;
+            };
+
+            // Get our specialized pipeline
+            let key = InstancedParticlesKey {
+                mesh_key: view_key
+                    | MeshPipelineKey::from_primitive_topology(
+                        mesh.primitive_topology,
+                    ),
+                sprites: instances.sprites,
+            };
+            let pipeline_id = match pipelines.specialize(
+                &mut pipeline_cache,
+                &instanced_particles_pipeline,
+                key,
+                &mesh.layout,
+            ) {
+                Ok(pipeline_id) => pipeline_id,
+                Err(error) => {
+                    error!("{}", error);
+                    continue;
+                }
+            };
+
+            transparent_phase.add(Transparent3d {
+                entity,
+                pipeline: pipeline_id,
+                draw_function: draw_particle_instances,
+                distance: rangefinder.distance(&mesh_uniform.transform),
+            });
+        }
+    }
+}
+
+/// Draws the mesh of the item once per instance in its [`InstanceBuffer`]
+pub struct DrawMeshInstanced;
+
+impl EntityRenderCommand for DrawMeshInstanced {
+    type Param = (
+        SRes<RenderAssets<Mesh>>,
+        SQuery<Read<Handle<Mesh>>>,
+        SQuery<Read<InstanceBuffer>>,
+    );
+
+    #[inline]
+    fn render<'w>(
+        _view: Entity,
+        item: Entity,
+        (render_meshes, meshes, instance_buffers): SystemParamItem<
+            'w,
+            '_,
+            Self::Param,
+        >,
+        pass: &mut TrackedRenderPass<'w>,
+    ) -> RenderCommandResult {
+        let (mesh_handle, instance_buffer) =
+            match (meshes.get(item), instance_buffers.get_inner(item)) {
+                (Ok(mesh_handle), Ok(instance_buffer)) => {
+                    (mesh_handle, instance_buffer)
+                }
+                _ => return RenderCommandResult::Failure,
+            };
+
+        let gpu_mesh = if let Some(gpu_mesh) =
+            render_meshes.into_inner().get(mesh_handle)
+        {
+            gpu_mesh
+        } else {
+            return RenderCommandResult::Failure;
+        };
+
+        pass.set_vertex_buffer(0, gpu_mesh.vertex_buffer.slice(..));
+        pass.set_vertex_buffer(1, instance_buffer.buffer.slice(..));
+
+        let instances = 0..instance_buffer.length as u32;
+        match &gpu_mesh.buffer_info {
+            GpuBufferInfo::Indexed {
+                buffer,
+                index_format,
+                count,
+            } => {
+                pass.set_index_buffer(buffer.slice(..), 0, *index_format);
+                pass.draw_indexed(0..*count, 0, instances);
+            }
+            GpuBufferInfo::NonIndexed { vertex_count } => {
+                pass.draw(0..*vertex_count, instances);
+            }
+        }
+
+        RenderCommandResult::Success
+    }
+}
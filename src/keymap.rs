@@ -0,0 +1,229 @@
+use bevy::prelude::*;
+use bevy::utils::HashMap;
+use bevy::window::WindowMode;
+use bevy_egui::{egui, EguiContext};
+
+use crate::capture::CaptureEvents;
+use crate::edit_history::EditHistoryEvent;
+use crate::longitudinal_wave_3d_simulation::LongitudinalWave3dSimulationPlugin;
+use crate::particle_mess::ParticleMessPlugin;
+use crate::simulation::Simulation;
+use crate::simulation_control::SimulationControlEvent;
+use crate::wave_2d_simulation::Wave2dSimulationPlugin;
+use crate::wave_in_panel::WaveInPanelPlugin;
+use crate::AppState;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum KeyAction {
+    TogglePause,
+    Step,
+    Reset,
+    SwitchToWave2d,
+    SwitchToLongitudinalWave3d,
+    SwitchToParticleMess,
+    SwitchToWaveInPanel,
+    ToggleFullscreen,
+    Screenshot,
+    Undo,
+    Redo,
+}
+
+impl KeyAction {
+    pub const ALL: [KeyAction; 11] = [
+        KeyAction::TogglePause,
+        KeyAction::Step,
+        KeyAction::Reset,
+        KeyAction::SwitchToWave2d,
+        KeyAction::SwitchToLongitudinalWave3d,
+        KeyAction::SwitchToParticleMess,
+        KeyAction::SwitchToWaveInPanel,
+        KeyAction::ToggleFullscreen,
+        KeyAction::Screenshot,
+        KeyAction::Undo,
+        KeyAction::Redo,
+    ];
+
+    /// Whether the key of the action is pressed together with control
+    fn with_control(self) -> bool {
+        matches!(self, KeyAction::Undo | KeyAction::Redo)
+    }
+
+    /// The simulation the action switches to
+    fn simulation(self) -> Option<AppState> {
+        match self {
+            KeyAction::SwitchToWave2d => Some(Wave2dSimulationPlugin::STATE),
+            KeyAction::SwitchToLongitudinalWave3d => {
+                Some(LongitudinalWave3dSimulationPlugin::STATE)
+            }
+            KeyAction::SwitchToParticleMess => Some(ParticleMessPlugin::STATE),
+            KeyAction::SwitchToWaveInPanel => Some(WaveInPanelPlugin::STATE),
+            _ => None,
+        }
+    }
+}
+
+impl From<KeyAction> for String {
+    fn from(value: KeyAction) -> Self {
+        match value {
+            KeyAction::TogglePause => "pause / resume".to_string(),
+            KeyAction::Step => "single step".to_string(),
+            KeyAction::Reset => "reset".to_string(),
+            KeyAction::SwitchToWave2d => "wave_2d".to_string(),
+            KeyAction::SwitchToLongitudinalWave3d => {
+                "longitudinal_wave_3d".to_string()
+            }
+            KeyAction::SwitchToParticleMess => "particle_mess".to_string(),
+            KeyAction::SwitchToWaveInPanel => "wave_in_panel".to_string(),
+            KeyAction::ToggleFullscreen => "fullscreen".to_string(),
+            KeyAction::Screenshot => "screenshot".to_string(),
+            KeyAction::Undo => "undo".to_string(),
+            KeyAction::Redo => "redo".to_string(),
+        }
+    }
+}
+
+/// Key bound to every action, editable in the side panel
+#[derive(Resource)]
+pub struct Keymap {
+    bindings: HashMap<KeyAction, KeyCode>,
+    /// the action which is bound to the next pressed key
+    rebinding: Option<KeyAction>,
+}
+
+impl Default for Keymap {
+    fn default() -> Self {
+        Self {
+            bindings: HashMap::from_iter([
+                (KeyAction::TogglePause, KeyCode::Space),
+                (KeyAction::Step, KeyCode::Period),
+                (KeyAction::Reset, KeyCode::R),
+                (KeyAction::SwitchToWave2d, KeyCode::Key1),
+                (KeyAction::SwitchToLongitudinalWave3d, KeyCode::Key2),
+                (KeyAction::SwitchToParticleMess, KeyCode::Key3),
+                (KeyAction::SwitchToWaveInPanel, KeyCode::Key4),
+                (KeyAction::ToggleFullscreen, KeyCode::F),
+                (KeyAction::Screenshot, KeyCode::S),
+                (KeyAction::Undo, KeyCode::Z),
+                (KeyAction::Redo, KeyCode::Y),
+            ]),
+            rebinding: None,
+        }
+    }
+}
+
+impl Keymap {
+    pub fn key(&self, action: KeyAction) -> Option<KeyCode> {
+        self.bindings.get(&action).copied()
+    }
+}
+
+pub struct KeymapPlugin;
+
+impl Plugin for KeymapPlugin {
+    fn build(&self, app: &mut App) {
+        app.insert_resource(Keymap::default())
+            .add_system(on_keyboard_events);
+    }
+}
+
+/// Sends the same events as the buttons of the bound actions
+fn on_keyboard_events(
+    keys: Res<Input<KeyCode>>,
+    mut keymap: ResMut<Keymap>,
+    mut egui_ctx: ResMut<EguiContext>,
+    mut app_state: ResMut<State<AppState>>,
+    mut windows: ResMut<Windows>,
+    mut control_events: EventWriter<SimulationControlEvent>,
+    mut capture_events: EventWriter<CaptureEvents>,
+    mut history_events: EventWriter<EditHistoryEvent>,
+) {
+    if let Some(action) = keymap.rebinding {
+        if let Some(key) = keys.get_just_pressed().next() {
+            if *key != KeyCode::Escape {
+                keymap.bindings.insert(action, *key);
+            }
+            keymap.rebinding = None;
+        }
+        return;
+    }
+
+    // typing into a text field or drag value
+    if egui_ctx.ctx_mut().wants_keyboard_input() {
+        return;
+    }
+
+    let control = keys.any_pressed([KeyCode::LControl, KeyCode::RControl]);
+
+    for action in KeyAction::ALL {
+        match keymap.key(action) {
+            Some(key)
+                if keys.just_pressed(key)
+                    && control == action.with_control() => {}
+            _ => continue,
+        }
+
+        match action {
+            KeyAction::TogglePause => {
+                control_events.send(SimulationControlEvent::TogglePause);
+            }
+            KeyAction::Step => {
+                control_events.send(SimulationControlEvent::Step);
+            }
+            KeyAction::Reset => {
+                control_events.send(SimulationControlEvent::Reset);
+            }
+            KeyAction::ToggleFullscreen => {
+                if let Some(window) = windows.get_primary_mut() {
+                    let mode = if window.mode() == WindowMode::Windowed {
+                        WindowMode::BorderlessFullscreen
+                    } else {
+                        WindowMode::Windowed
+                    };
+                    window.set_mode(mode);
+                }
+            }
+            KeyAction::Screenshot => {
+                capture_events.send(CaptureEvents::Screenshot);
+            }
+            KeyAction::Undo => history_events.send(EditHistoryEvent::Undo),
+            KeyAction::Redo => history_events.send(EditHistoryEvent::Redo),
+            _ => {
+                if let Some(simulation) = action.simulation() {
+                    if simulation != *app_state.current() {
+                        if let Err(error) = app_state.set(simulation) {
+                            warn!("failed to switch simulation: {:?}", error);
+                        }
+                    }
+                }
+            }
+        }
+    }
+}
+
+pub fn show_keymap(ui: &mut egui::Ui, keymap: &mut Keymap) {
+    egui::CollapsingHeader::new("Keyboard shortcuts").show(ui, |ui| {
+        egui::Grid::new("keymap").num_columns(2).show(ui, |ui| {
+            for action in KeyAction::ALL {
+                ui.label(String::from(action));
+
+                let text = if keymap.rebinding == Some(action) {
+                    "press a key".to_string()
+                } else {
+                    let control =
+                        if action.with_control() { "Ctrl + " } else { "" };
+                    keymap.key(action).map_or("-".to_string(), |key| {
+                        format!("{}{:?}", control, key)
+                    })
+                };
+                if ui
+                    .button(text)
+                    .on_hover_text("click to rebind, escape to cancel")
+                    .clicked()
+                {
+                    keymap.rebinding = Some(action);
+                }
+                ui.end_row();
+            }
+        });
+    });
+}
@@ -0,0 +1,138 @@
+use bevy::prelude::*;
+use bevy::utils::HashMap;
+use bevy_egui::egui;
+
+use crate::pan_orbit_camera::PanOrbitCamera;
+
+/// Subdivisions of the icospheres from close to far
+const SUBDIVISIONS: [usize; 3] = [4, 2, 0];
+
+/// Distances from the camera, in particle radii, beyond which the particles
+/// are drawn with fewer subdivisions
+#[derive(Resource)]
+pub struct LodSettings {
+    pub enabled: bool,
+    pub near: f32,
+    pub far: f32,
+}
+
+impl Default for LodSettings {
+    fn default() -> Self {
+        Self {
+            enabled: true,
+            near: 40.0,
+            far: 200.0,
+        }
+    }
+}
+
+/// Spherical particle whose mesh is swapped for a coarser icosphere of the
+/// same radius when it is far from the camera
+#[derive(Component)]
+pub struct LevelOfDetail {
+    pub radius: f32,
+}
+
+/// Mesh the particle was spawned with, restored when the level of detail is
+/// disabled
+#[derive(Component)]
+struct FullDetail(Handle<Mesh>);
+
+pub struct LevelOfDetailPlugin;
+
+impl Plugin for LevelOfDetailPlugin {
+    fn build(&self, app: &mut App) {
+        app.insert_resource(LodSettings::default())
+            .add_system(keep_full_detail)
+            .add_system(update_level_of_detail.after(keep_full_detail));
+    }
+}
+
+fn keep_full_detail(
+    mut commands: Commands,
+    particles: Query<(Entity, &Handle<Mesh>), Added<LevelOfDetail>>,
+) {
+    for (entity, mesh) in particles.iter() {
+        commands.entity(entity).insert(FullDetail(mesh.clone()));
+    }
+}
+
+/// Picks the mesh of every particle by its distance to the closest orbit
+/// camera, the meshes are shared by all particles of a radius
+fn update_level_of_detail(
+    settings: Res<LodSettings>,
+    mut meshes: ResMut<Assets<Mesh>>,
+    mut lod_meshes: Local<HashMap<u32, [Handle<Mesh>; 3]>>,
+    cameras: Query<&GlobalTransform, With<PanOrbitCamera>>,
+    mut particles: Query<(
+        &GlobalTransform,
+        &LevelOfDetail,
+        &FullDetail,
+        &mut Handle<Mesh>,
+    )>,
+) {
+    if !settings.enabled {
+        if settings.is_changed() {
+            for (_, _, full_detail, mut mesh) in particles.iter_mut() {
+                *mesh = full_detail.0.clone();
+            }
+        }
+        return;
+    }
+
+    if cameras.is_empty() {
+        return;
+    }
+
+    for (transform, lod, _, mut mesh) in particles.iter_mut() {
+        let position = transform.translation();
+        let distance = cameras
+            .iter()
+            .map(|camera| camera.translation().distance(position))
+            .fold(f32::INFINITY, f32::min)
+            / lod.radius.max(f32::EPSILON);
+
+        let level = if distance < settings.near {
+            0
+        } else if distance < settings.far {
+            1
+        } else {
+            2
+        };
+
+        // radii of the same species are bit identical
+        let levels =
+            lod_meshes.entry(lod.radius.to_bits()).or_insert_with(|| {
+                SUBDIVISIONS.map(|subdivisions| {
+                    meshes.add(Mesh::from(shape::Icosphere {
+                        radius: lod.radius,
+                        subdivisions,
+                    }))
+                })
+            });
+
+        // avoids flagging every mesh as changed each frame
+        if *mesh != levels[level] {
+            *mesh = levels[level].clone();
+        }
+    }
+}
+
+pub fn show_level_of_detail(ui: &mut egui::Ui, settings: &mut LodSettings) {
+    ui.add(egui::Checkbox::new(
+        &mut settings.enabled,
+        "coarser particles far away",
+    ));
+    if settings.enabled {
+        ui.add(
+            egui::Slider::new(&mut settings.near, 5.0..=500.0)
+                .logarithmic(true)
+                .text("full detail within radii"),
+        );
+        ui.add(
+            egui::Slider::new(&mut settings.far, settings.near..=2000.0)
+                .logarithmic(true)
+                .text("lowest detail beyond radii"),
+        );
+    }
+}
@@ -1,13 +1,29 @@
+use std::cmp::Ordering;
 use std::f32::consts::{PI, TAU};
+use std::time::Duration;
 
 use bevy::prelude::*;
 use bevy::time::Stopwatch;
 use bevy_rapier3d::prelude::*;
+use serde::{Deserialize, Serialize};
 
+use crate::colormap::{build_palette, palette_index, Colormap};
+use crate::hover::Hoverable;
+use crate::instancing::InstancedParticle;
+use crate::level_of_detail::LevelOfDetail;
 use crate::pan_orbit_camera::{update_pan_orbit_camera, PanOrbitCamera};
-use crate::{AppCamera, AppState};
-
-use super::{LongitudinalWave3dSimulationParameters, UiEvents};
+use crate::persistence::RestoreParameters;
+use crate::simulation::Simulation;
+use crate::simulation_control::{SimulationClock, SimulationControl};
+use crate::snapshot::{load_snapshot, save_snapshot, BodyState};
+use crate::vtk::{save_vtk, write_points};
+use crate::AppCamera;
+
+use super::lattice::{compression, Lattice};
+use super::LongitudinalWave3dSimulationPlugin;
+use super::{
+    LongitudinalWave3dSimulationParameters, ParticleColoring, TubeEnd, UiEvents,
+};
 
 #[derive(Default, Resource)]
 struct Entities(Vec<Entity>);
@@ -15,11 +31,26 @@ struct Entities(Vec<Entity>);
 #[derive(Resource)]
 struct AnimationTimer(Stopwatch);
 
+const PALETTE_SIZE: usize = 32;
+
+/// Materials sampled from the current colormap, used to color the particles
+/// by their displacement
+#[derive(Default, Resource)]
+struct Palette {
+    colormap: Option<Colormap>,
+    materials: Vec<Handle<StandardMaterial>>,
+}
+
 #[derive(Component)]
-struct Particle {
-    initial_translation: Vec3,
+pub(super) struct Particle {
+    pub(super) initial_translation: Vec3,
+    /// integer coordinates in the lattice
+    lattice_position: IVec3,
 }
 
+#[derive(Component)]
+struct Floor;
+
 #[derive(Component)]
 struct ApplyingForce;
 
@@ -29,19 +60,23 @@ impl Plugin for AnimationPlugin {
     fn build(&self, app: &mut App) {
         app.insert_resource(Entities::default())
             .insert_resource(AnimationTimer(Stopwatch::new()))
+            .insert_resource(Palette::default())
             .add_system_set(
-                SystemSet::on_enter(AppState::LongitudinalWaveSimulation3d)
-                    .with_system(setup),
+                SystemSet::on_enter(LongitudinalWave3dSimulationPlugin::STATE)
+                    .with_system(setup.after(RestoreParameters)),
             )
             .add_system_set(
-                SystemSet::on_update(AppState::LongitudinalWaveSimulation3d)
+                SystemSet::on_update(LongitudinalWave3dSimulationPlugin::STATE)
                     .with_system(update_pan_orbit_camera)
                     .with_system(apply_impulse)
-                    .with_system(apply_equilibrium_force)
-                    .with_system(on_ui_events),
+                    .with_system(apply_equilibrium_force.after(apply_impulse))
+                    .with_system(update_particle_colors)
+                    .with_system(on_ui_events)
+                    .with_system(on_snapshot_events)
+                    .with_system(on_export_events),
             )
             .add_system_set(
-                SystemSet::on_exit(AppState::LongitudinalWaveSimulation3d)
+                SystemSet::on_exit(LongitudinalWave3dSimulationPlugin::STATE)
                     .with_system(cleanup),
             );
     }
@@ -49,7 +84,7 @@ impl Plugin for AnimationPlugin {
 
 #[allow(clippy::too_many_arguments)]
 fn setup(
-    mut time: ResMut<Time>,
+    mut control: ResMut<SimulationControl>,
     mut commands: Commands,
     cameras: Query<Entity, With<AppCamera>>,
     mut mouse_button: ResMut<Input<MouseButton>>,
@@ -65,31 +100,19 @@ fn setup(
 
     mouse_button.reset_all();
 
-    time.pause();
+    control.pause();
 
     if let Ok(camera_entity) = cameras.get_single() {
         commands.entity(camera_entity).despawn();
     }
 
-    let max_x_z = parameters.dimx.max(parameters.dimz) as f32 * 2.0;
-
-    let plane = commands.spawn((
-        PbrBundle {
-            mesh: meshes.add(Mesh::from(shape::Plane {
-                size: max_x_z * 2.0,
-            })),
-            material: materials.add(Color::rgb(0.3, 0.5, 0.3).into()),
-            transform: Transform::from_xyz(
-                parameters.dimx as f32 / 2.0,
-                -2.0,
-                parameters.dimz as f32 / 2.0,
-            ),
-            ..default()
-        },
-        Collider::cuboid(max_x_z, 0.1, max_x_z),
-    ));
-
-    entities.0.push(plane.id());
+    spawn_floor(
+        &mut commands,
+        &mut meshes,
+        &mut materials,
+        &parameters,
+        &mut entities,
+    );
 
     // spheres
     initialize_spheres(
@@ -136,6 +159,61 @@ fn setup(
         });
 }
 
+fn spawn_floor(
+    commands: &mut Commands,
+    meshes: &mut Assets<Mesh>,
+    materials: &mut Assets<StandardMaterial>,
+    parameters: &LongitudinalWave3dSimulationParameters,
+    entities: &mut Entities,
+) {
+    let max_x_z =
+        parameters.dimx.max(parameters.dimz) as f32 * parameters.spacing * 2.0;
+
+    let plane = commands.spawn((
+        Floor,
+        PbrBundle {
+            mesh: meshes.add(Mesh::from(shape::Plane {
+                size: max_x_z * 2.0,
+            })),
+            material: materials.add(Color::rgb(0.3, 0.5, 0.3).into()),
+            transform: Transform::from_xyz(
+                parameters.dimx as f32 * parameters.spacing / 2.0,
+                -2.0,
+                parameters.dimz as f32 * parameters.spacing / 2.0,
+            ),
+            ..default()
+        },
+        Collider::cuboid(max_x_z, 0.1, max_x_z),
+    ));
+
+    entities.0.push(plane.id());
+}
+
+/// Replaces the floor and the particles with ones built from the current
+/// dimensions, radius and spacing, returns the index of the first particle
+/// in `entities`
+#[allow(clippy::type_complexity)]
+fn respawn_lattice(
+    commands: &mut Commands,
+    meshes: &mut Assets<Mesh>,
+    materials: &mut Assets<StandardMaterial>,
+    parameters: &LongitudinalWave3dSimulationParameters,
+    entities: &mut Entities,
+    lattice: &Query<Entity, Or<(With<Particle>, With<Floor>)>>,
+) -> usize {
+    for entity in lattice.iter() {
+        commands.entity(entity).despawn();
+    }
+    entities.0.retain(|entity| !lattice.contains(*entity));
+
+    spawn_floor(commands, meshes, materials, parameters, entities);
+
+    let first_particle = entities.0.len();
+    initialize_spheres(commands, meshes, materials, parameters, entities);
+
+    first_particle
+}
+
 fn initialize_spheres(
     commands: &mut Commands,
     meshes: &mut Assets<Mesh>,
@@ -151,6 +229,8 @@ fn initialize_spheres(
     let material1_handle = materials.add(Color::rgb(0.6, 0.6, 0.6).into());
     let material2_handle = materials.add(Color::rgb(0.7, 0.5, 0.5).into());
 
+    let last_z = parameters.dimz.saturating_sub(1);
+
     for x in 0..parameters.dimx {
         for y in 0..parameters.dimy {
             for z in 0..parameters.dimz {
@@ -160,11 +240,15 @@ fn initialize_spheres(
                     material1_handle.clone()
                 };
 
-                let translation = Vec3::new(x as f32, y as f32, z as f32);
+                let lattice_position =
+                    UVec3::new(x as u32, y as u32, z as u32).as_ivec3();
+                let translation =
+                    lattice_position.as_vec3() * parameters.spacing;
 
                 let mut particle = commands.spawn((
                     Particle {
                         initial_translation: translation,
+                        lattice_position,
                     },
                     PbrBundle {
                         mesh: mesh.clone(),
@@ -176,10 +260,25 @@ fn initialize_spheres(
                     Restitution::coefficient(0.7),
                     ExternalImpulse::default(),
                     ExternalForce::default(),
+                    Velocity::default(),
+                    Hoverable {
+                        rest_position: Some(translation),
+                        radius: parameters.radius,
+                    },
+                    InstancedParticle,
+                    LevelOfDetail {
+                        radius: parameters.radius,
+                    },
                 ));
 
-                if z == 0 {
+                let fixed = if z == 0 {
                     particle.insert(ApplyingForce);
+                    parameters.near_end == TubeEnd::Closed
+                } else {
+                    z == last_z && parameters.far_end == TubeEnd::Closed
+                };
+
+                if fixed {
                     particle.insert(RigidBody::Fixed);
                 } else {
                     particle.insert(RigidBody::Dynamic);
@@ -193,6 +292,8 @@ fn initialize_spheres(
 
 fn apply_impulse(
     time: Res<Time>,
+    control: Res<SimulationControl>,
+    mut clock: ResMut<SimulationClock>,
     mut animation_timer: ResMut<AnimationTimer>,
     mut force_sources: Query<
         (&Particle, &mut ExternalImpulse, &mut Transform),
@@ -200,66 +301,253 @@ fn apply_impulse(
     >,
     parameters: Res<LongitudinalWave3dSimulationParameters>,
 ) {
-    animation_timer.0.tick(time.delta());
+    animation_timer.0.tick(control.delta(&time));
+    // rapier takes a single step per frame
+    if control.is_running() {
+        clock.advance(1, control.delta_seconds(&time));
+    }
 
-    let elapsed = animation_timer.0.elapsed();
-    let z =
-        (elapsed.as_secs_f32() * parameters.applying_force_freq * TAU).sin();
+    // an open end is pulled along by its equilibrium force instead
+    if parameters.near_end == TubeEnd::Open {
+        return;
+    }
+
+    let displacement = driver_displacement(&animation_timer, &parameters);
 
     for (particle, _, mut transform) in force_sources.iter_mut() {
-        transform.translation.z = particle.initial_translation.z
-            + (z * parameters.applying_force_factor);
+        transform.translation = particle.initial_translation + displacement;
     }
 }
 
+fn driver_displacement(
+    animation_timer: &AnimationTimer,
+    parameters: &LongitudinalWave3dSimulationParameters,
+) -> Vec3 {
+    let elapsed = animation_timer.0.elapsed();
+
+    parameters.drive_direction.axis()
+        * (elapsed.as_secs_f32() * parameters.applying_force_freq * TAU).sin()
+        * parameters.applying_force_factor
+}
+
 fn apply_equilibrium_force(
-    mut force_sources: Query<(&Particle, &Transform, &mut ExternalForce)>,
+    animation_timer: Res<AnimationTimer>,
+    mut force_sources: Query<(
+        &Particle,
+        &Transform,
+        &mut ExternalForce,
+        Option<&ApplyingForce>,
+    )>,
     parameters: Res<LongitudinalWave3dSimulationParameters>,
 ) {
-    for (particle, transform, mut external_force) in force_sources.iter_mut() {
-        let equilizing_force_direction =
-            particle.initial_translation - transform.translation;
+    let offset = driver_displacement(&animation_timer, &parameters);
+
+    for (particle, transform, mut external_force, applying_force) in
+        force_sources.iter_mut()
+    {
+        let equilibrium = if applying_force.is_some() {
+            particle.initial_translation + offset
+        } else {
+            particle.initial_translation
+        };
+        let equilizing_force_direction = equilibrium - transform.translation;
 
         external_force.force =
             equilizing_force_direction * parameters.equilibrium_force_factor;
     }
 }
 
-#[allow(clippy::too_many_arguments)]
+fn update_particle_colors(
+    mut materials: ResMut<Assets<StandardMaterial>>,
+    mut palette: ResMut<Palette>,
+    parameters: Res<LongitudinalWave3dSimulationParameters>,
+    positions: Query<(&Particle, &Transform)>,
+    mut particles: Query<
+        (&Particle, &Transform, &mut Handle<StandardMaterial>),
+        Without<ApplyingForce>,
+    >,
+) {
+    if palette.colormap != Some(parameters.colormap) {
+        palette.materials =
+            build_palette(parameters.colormap, PALETTE_SIZE, &mut materials);
+        palette.colormap = Some(parameters.colormap);
+    }
+
+    let lattice: Lattice =
+        if parameters.coloring == ParticleColoring::Compression {
+            positions
+                .iter()
+                .map(|(particle, transform)| {
+                    (
+                        particle.lattice_position,
+                        (particle.initial_translation, transform.translation),
+                    )
+                })
+                .collect()
+        } else {
+            Lattice::default()
+        };
+
+    // displacements along the driven axis are shown relative to the
+    // amplitude of the driving plane
+    let axis = parameters.drive_direction.axis();
+    let amplitude = parameters.applying_force_factor.max(f32::EPSILON);
+    let compression_scale =
+        parameters.compression_color_scale.max(f32::EPSILON);
+
+    for (particle, transform, mut material) in particles.iter_mut() {
+        let value = match parameters.coloring {
+            ParticleColoring::Displacement => {
+                (transform.translation - particle.initial_translation).dot(axis)
+                    / amplitude
+            }
+            ParticleColoring::Compression => {
+                compression(
+                    &lattice,
+                    particle.lattice_position,
+                    particle.initial_translation,
+                    transform.translation,
+                ) / compression_scale
+            }
+        };
+
+        let index = palette_index(value, palette.materials.len());
+        if *material != palette.materials[index] {
+            *material = palette.materials[index].clone();
+        }
+    }
+}
+
 fn on_ui_events(
-    mut time: ResMut<Time>,
     mut ui_events: EventReader<UiEvents>,
     mut commands: Commands,
     mut meshes: ResMut<Assets<Mesh>>,
     mut materials: ResMut<Assets<StandardMaterial>>,
     parameters: Res<LongitudinalWave3dSimulationParameters>,
     mut entities: ResMut<Entities>,
-    particles: Query<Entity, With<Particle>>,
+    lattice: Query<Entity, Or<(With<Particle>, With<Floor>)>>,
 ) {
+    for event in ui_events.iter() {
+        if let UiEvents::Reset = event {
+            respawn_lattice(
+                &mut commands,
+                &mut meshes,
+                &mut materials,
+                &parameters,
+                &mut entities,
+                &lattice,
+            );
+        }
+    }
+}
+
+/// State of the particles written to and read from snapshot files
+#[derive(Serialize, Deserialize)]
+struct LongitudinalWave3dSnapshot {
+    parameters: LongitudinalWave3dSimulationParameters,
+    /// particles in spawn order
+    particles: Vec<BodyState>,
+    animation_elapsed_secs: f32,
+}
+
+#[allow(clippy::too_many_arguments)]
+fn on_snapshot_events(
+    mut ui_events: EventReader<UiEvents>,
+    mut commands: Commands,
+    mut meshes: ResMut<Assets<Mesh>>,
+    mut materials: ResMut<Assets<StandardMaterial>>,
+    mut animation_timer: ResMut<AnimationTimer>,
+    mut parameters: ResMut<LongitudinalWave3dSimulationParameters>,
+    mut entities: ResMut<Entities>,
+    particles: Query<(Entity, &Particle, &Transform, &Velocity)>,
+    lattice: Query<Entity, Or<(With<Particle>, With<Floor>)>>,
+) {
+    let name = LongitudinalWave3dSimulationPlugin::NAME.to_string();
+
     for event in ui_events.iter() {
         match event {
-            UiEvents::StartStop => {
-                if time.is_paused() {
-                    time.unpause();
-                } else {
-                    time.pause();
-                }
+            UiEvents::SaveSnapshot => {
+                // particles are spawned ordered by their initial position
+                let mut states: Vec<_> = particles.iter().collect();
+                states.sort_by(|(_, a, ..), (_, b, ..)| {
+                    a.initial_translation
+                        .to_array()
+                        .partial_cmp(&b.initial_translation.to_array())
+                        .unwrap_or(Ordering::Equal)
+                });
+
+                let snapshot = LongitudinalWave3dSnapshot {
+                    parameters: parameters.clone(),
+                    particles: states
+                        .into_iter()
+                        .map(|(_, _, transform, velocity)| {
+                            BodyState::new(transform, velocity)
+                        })
+                        .collect(),
+                    animation_elapsed_secs: animation_timer.0.elapsed_secs(),
+                };
+
+                save_snapshot(&name, &snapshot);
             }
-            UiEvents::Reset => {
-                for entity in particles.iter() {
-                    if let Some(mut entity) = commands.get_entity(entity) {
-                        entity.despawn();
-                    }
-                }
+            UiEvents::LoadSnapshot => {
+                let snapshot: LongitudinalWave3dSnapshot =
+                    if let Some(snapshot) = load_snapshot(&name) {
+                        snapshot
+                    } else {
+                        continue;
+                    };
+
+                *parameters = snapshot.parameters;
+                animation_timer.0.set_elapsed(Duration::from_secs_f32(
+                    snapshot.animation_elapsed_secs,
+                ));
 
-                initialize_spheres(
+                let first_particle = respawn_lattice(
                     &mut commands,
                     &mut meshes,
                     &mut materials,
                     &parameters,
                     &mut entities,
+                    &lattice,
                 );
+
+                let spawned = &entities.0[first_particle..];
+                if spawned.len() != snapshot.particles.len() {
+                    error!("snapshot particles don't match its parameters");
+                }
+
+                for (entity, state) in
+                    spawned.iter().zip(snapshot.particles.iter())
+                {
+                    commands
+                        .entity(*entity)
+                        .insert((state.transform(), state.velocity()));
+                }
             }
+            _ => {}
+        }
+    }
+}
+
+fn on_export_events(
+    mut ui_events: EventReader<UiEvents>,
+    particles: Query<(&Particle, &Transform)>,
+) {
+    for event in ui_events.iter() {
+        if let UiEvents::ExportVtk = event {
+            let (positions, displacements): (Vec<_>, Vec<_>) = particles
+                .iter()
+                .map(|(particle, transform)| {
+                    (
+                        transform.translation,
+                        transform.translation - particle.initial_translation,
+                    )
+                })
+                .unzip();
+
+            save_vtk("lattice", "vtu", |writer| {
+                write_points(writer, &positions, &displacements)
+            });
         }
     }
 }
@@ -0,0 +1,229 @@
+use std::sync::{Arc, Mutex};
+
+use bevy::audio::{AddAudioSource, AudioSink, Decodable, Source};
+use bevy::prelude::*;
+use bevy::reflect::TypeUuid;
+use bevy::utils::Duration;
+
+use super::microphone::{update_microphone, Microphone};
+use super::LongitudinalWave3dSimulationParameters;
+use super::LongitudinalWave3dSimulationPlugin;
+use crate::persistence::RestoreParameters;
+use crate::simulation::Simulation;
+use crate::simulation_control::SimulationControl;
+
+const SAMPLE_RATE: u32 = 44_100;
+
+/// Simulated time of the microphone signal looped by the audio stream
+const WINDOW_SECS: f64 = 2.0;
+
+/// The frame rate samples the simulated wave far below the audible range, so
+/// the most recent window of the microphone signal is played back sped up by
+/// the pitch factor and looped until the next window is ready
+#[derive(TypeUuid)]
+#[uuid = "42a28779-db13-438c-a0da-8b381e8c2746"]
+pub struct MicrophoneStream {
+    window: Arc<Mutex<Vec<f32>>>,
+}
+
+pub struct MicrophoneStreamDecoder {
+    window: Arc<Mutex<Vec<f32>>>,
+    playing: Vec<f32>,
+    index: usize,
+}
+
+impl Iterator for MicrophoneStreamDecoder {
+    type Item = f32;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        // the newest window is picked up whenever the current one ends
+        if self.index >= self.playing.len() {
+            self.index = 0;
+            if let Ok(window) = self.window.lock() {
+                self.playing.clone_from(&window);
+            }
+        }
+
+        let sample = self.playing.get(self.index).copied().unwrap_or(0.0);
+        self.index += 1;
+
+        Some(sample)
+    }
+}
+
+impl Source for MicrophoneStreamDecoder {
+    fn current_frame_len(&self) -> Option<usize> {
+        None
+    }
+
+    fn channels(&self) -> u16 {
+        1
+    }
+
+    fn sample_rate(&self) -> u32 {
+        SAMPLE_RATE
+    }
+
+    fn total_duration(&self) -> Option<Duration> {
+        None
+    }
+}
+
+impl Decodable for MicrophoneStream {
+    type DecoderItem = f32;
+    type Decoder = MicrophoneStreamDecoder;
+
+    fn decoder(&self) -> Self::Decoder {
+        MicrophoneStreamDecoder {
+            window: self.window.clone(),
+            playing: Vec::new(),
+            index: 0,
+        }
+    }
+}
+
+/// Window shared with the audio thread and the sink of the playing stream
+#[derive(Default, Resource)]
+struct MicrophoneAudio {
+    window: Arc<Mutex<Vec<f32>>>,
+    sink: Option<Handle<AudioSink>>,
+}
+
+pub struct MicrophoneAudioPlugin;
+
+impl Plugin for MicrophoneAudioPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_audio_source::<MicrophoneStream>()
+            .insert_resource(MicrophoneAudio::default())
+            .add_system_set(
+                SystemSet::on_enter(LongitudinalWave3dSimulationPlugin::STATE)
+                    .with_system(setup.after(RestoreParameters)),
+            )
+            .add_system_set(
+                SystemSet::on_update(LongitudinalWave3dSimulationPlugin::STATE)
+                    .with_system(update_window.after(update_microphone))
+                    .with_system(update_sink),
+            )
+            .add_system_set(
+                SystemSet::on_exit(LongitudinalWave3dSimulationPlugin::STATE)
+                    .with_system(cleanup),
+            );
+    }
+}
+
+fn setup(
+    mut microphone_audio: ResMut<MicrophoneAudio>,
+    mut streams: ResMut<Assets<MicrophoneStream>>,
+    audio: Res<Audio<MicrophoneStream>>,
+    audio_sinks: Res<Assets<AudioSink>>,
+) {
+    let stream = streams.add(MicrophoneStream {
+        window: microphone_audio.window.clone(),
+    });
+
+    // the returned handle is weak, the sink only exists once playing started
+    let sink = audio.play(stream);
+    microphone_audio.sink = Some(audio_sinks.get_handle(sink));
+}
+
+/// Resamples the last window of the microphone signal to the audio rate,
+/// compressed in time by the pitch factor
+fn update_window(
+    control: Res<SimulationControl>,
+    parameters: Res<LongitudinalWave3dSimulationParameters>,
+    microphone: Res<Microphone>,
+    microphone_audio: Res<MicrophoneAudio>,
+) {
+    if control.is_paused() || !parameters.microphone_audio {
+        return;
+    }
+
+    let samples = &microphone.samples;
+    let (first, last) = match (samples.front(), samples.back()) {
+        (Some(first), Some(last)) => (first[0], last[0]),
+        _ => return,
+    };
+    let start = first.max(last - WINDOW_SECS);
+    let duration = last - start;
+    if duration <= 0.0 {
+        return;
+    }
+
+    let len = (duration / parameters.microphone_audio_pitch as f64
+        * SAMPLE_RATE as f64) as usize;
+    let mean = samples
+        .iter()
+        .filter(|[t, _]| *t >= start)
+        .map(|[_, pressure]| *pressure)
+        .sum::<f64>()
+        / samples.iter().filter(|[t, _]| *t >= start).count() as f64;
+
+    let mut window = Vec::with_capacity(len);
+    let mut i = 0;
+    for n in 0..len {
+        let t = start + duration * n as f64 / len as f64;
+        while i + 2 < samples.len() && samples[i + 1][0] < t {
+            i += 1;
+        }
+
+        // linear interpolation between the neighboring frames
+        let [t0, p0] = samples[i];
+        let [t1, p1] = samples[(i + 1).min(samples.len() - 1)];
+        let f = if t1 > t0 {
+            ((t - t0) / (t1 - t0)).clamp(0.0, 1.0)
+        } else {
+            0.0
+        };
+        let pressure = p0 + (p1 - p0) * f - mean;
+
+        window.push(
+            (pressure as f32 * parameters.microphone_audio_gain)
+                .clamp(-1.0, 1.0),
+        );
+    }
+
+    if let Ok(mut shared) = microphone_audio.window.lock() {
+        *shared = window;
+    }
+}
+
+fn update_sink(
+    control: Res<SimulationControl>,
+    parameters: Res<LongitudinalWave3dSimulationParameters>,
+    microphone_audio: Res<MicrophoneAudio>,
+    audio_sinks: Res<Assets<AudioSink>>,
+) {
+    let sink = if let Some(sink) = microphone_audio
+        .sink
+        .as_ref()
+        .and_then(|sink| audio_sinks.get(sink))
+    {
+        sink
+    } else {
+        return;
+    };
+
+    if parameters.microphone_audio && !control.is_paused() {
+        sink.play();
+    } else {
+        sink.pause();
+    }
+}
+
+fn cleanup(
+    mut microphone_audio: ResMut<MicrophoneAudio>,
+    audio_sinks: Res<Assets<AudioSink>>,
+) {
+    if let Some(sink) = microphone_audio
+        .sink
+        .take()
+        .and_then(|sink| audio_sinks.get(&sink))
+    {
+        // a dropped sink would keep playing detached
+        sink.pause();
+    }
+
+    if let Ok(mut window) = microphone_audio.window.lock() {
+        window.clear();
+    }
+}
@@ -0,0 +1,42 @@
+use bevy::prelude::*;
+use bevy::utils::HashMap;
+
+/// Offsets of the direct lattice neighbors
+const NEIGHBOR_OFFSETS: [IVec3; 6] = [
+    IVec3::X,
+    IVec3::NEG_X,
+    IVec3::Y,
+    IVec3::NEG_Y,
+    IVec3::Z,
+    IVec3::NEG_Z,
+];
+
+/// Initial and current translation of the particles by lattice position
+pub type Lattice = HashMap<IVec3, (Vec3, Vec3)>;
+
+/// Relative shortening of the distances from the particle at `cell` to its
+/// direct lattice neighbors, positive when compressed and negative when
+/// stretched
+pub fn compression(
+    lattice: &Lattice,
+    cell: IVec3,
+    initial_translation: Vec3,
+    translation: Vec3,
+) -> f32 {
+    let (sum, count) = NEIGHBOR_OFFSETS
+        .iter()
+        .filter_map(|offset| lattice.get(&(cell + *offset)))
+        .fold((0.0, 0), |(sum, count), (neighbor_initial, neighbor)| {
+            let rest_distance = initial_translation.distance(*neighbor_initial);
+            (
+                sum + translation.distance(*neighbor) / rest_distance,
+                count + 1,
+            )
+        });
+
+    if count > 0 {
+        1.0 - sum / count as f32
+    } else {
+        0.0
+    }
+}
@@ -0,0 +1,213 @@
+use std::collections::VecDeque;
+
+use bevy::prelude::*;
+use bevy_egui::egui;
+use bevy_egui::egui::plot::{Line, Plot, PlotPoints};
+
+use super::animation_plugin::Particle;
+use super::LongitudinalWave3dSimulationPlugin;
+use super::{LongitudinalWave3dSimulationParameters, UiEvents};
+use crate::persistence::RestoreParameters;
+use crate::simulation::Simulation;
+use crate::simulation_control::SimulationControl;
+
+/// Number of frames kept in the pressure plot
+const MICROPHONE_BUFFER_SIZE: usize = 600;
+
+/// Pressure signal of the microphone, the relative deviation of the
+/// particle density around it from the density of the lattice at rest
+#[derive(Default, Resource)]
+pub struct Microphone {
+    elapsed_secs: f32,
+    /// `[simulated time in s, pressure]`, oldest sample first
+    pub samples: VecDeque<[f64; 2]>,
+}
+
+/// Sphere showing where the microphone listens
+#[derive(Component)]
+struct MicrophoneMarker;
+
+pub struct MicrophonePlugin;
+
+impl Plugin for MicrophonePlugin {
+    fn build(&self, app: &mut App) {
+        app.insert_resource(Microphone::default())
+            .add_system_set(
+                SystemSet::on_enter(LongitudinalWave3dSimulationPlugin::STATE)
+                    .with_system(setup.after(RestoreParameters)),
+            )
+            .add_system_set(
+                SystemSet::on_update(LongitudinalWave3dSimulationPlugin::STATE)
+                    .with_system(update_microphone)
+                    .with_system(update_marker)
+                    .with_system(on_ui_events),
+            )
+            .add_system_set(
+                SystemSet::on_exit(LongitudinalWave3dSimulationPlugin::STATE)
+                    .with_system(cleanup),
+            );
+    }
+}
+
+fn setup(
+    mut commands: Commands,
+    mut meshes: ResMut<Assets<Mesh>>,
+    mut materials: ResMut<Assets<StandardMaterial>>,
+    mut microphone: ResMut<Microphone>,
+) {
+    *microphone = Microphone::default();
+
+    commands.spawn((
+        MicrophoneMarker,
+        PbrBundle {
+            mesh: meshes.add(Mesh::from(shape::UVSphere {
+                radius: 1.0,
+                ..default()
+            })),
+            material: materials.add(StandardMaterial {
+                base_color: Color::rgba(1.0, 0.9, 0.2, 0.25),
+                alpha_mode: AlphaMode::Blend,
+                unlit: true,
+                ..default()
+            }),
+            ..default()
+        },
+    ));
+}
+
+/// Smoothly decreasing weight of a particle at `distance` from the
+/// microphone, so particles crossing the radius don't make the signal jump
+fn kernel(distance: f32, radius: f32) -> f32 {
+    (1.0 - distance / radius).max(0.0).powi(2)
+}
+
+pub(super) fn update_microphone(
+    time: Res<Time>,
+    control: Res<SimulationControl>,
+    parameters: Res<LongitudinalWave3dSimulationParameters>,
+    mut microphone: ResMut<Microphone>,
+    particles: Query<(&Particle, &Transform)>,
+) {
+    if !control.is_running() {
+        return;
+    }
+
+    let position = parameters.microphone_position;
+    let radius = parameters.microphone_radius.max(f32::EPSILON);
+
+    let (density, rest_density) = particles.iter().fold(
+        (0.0, 0.0),
+        |(density, rest_density), (particle, transform)| {
+            (
+                density
+                    + kernel(transform.translation.distance(position), radius),
+                rest_density
+                    + kernel(
+                        particle.initial_translation.distance(position),
+                        radius,
+                    ),
+            )
+        },
+    );
+
+    let pressure = if rest_density > 0.0 {
+        density / rest_density - 1.0
+    } else {
+        0.0
+    };
+
+    microphone.elapsed_secs += control.delta_seconds(&time);
+    let sample = [microphone.elapsed_secs as f64, pressure as f64];
+
+    if microphone.samples.len() == MICROPHONE_BUFFER_SIZE {
+        microphone.samples.pop_front();
+    }
+    microphone.samples.push_back(sample);
+}
+
+fn update_marker(
+    parameters: Res<LongitudinalWave3dSimulationParameters>,
+    mut markers: Query<&mut Transform, With<MicrophoneMarker>>,
+) {
+    for mut transform in markers.iter_mut() {
+        transform.translation = parameters.microphone_position;
+        transform.scale = Vec3::splat(parameters.microphone_radius);
+    }
+}
+
+fn on_ui_events(
+    mut ui_events: EventReader<UiEvents>,
+    mut microphone: ResMut<Microphone>,
+) {
+    for event in ui_events.iter() {
+        if let UiEvents::Reset | UiEvents::LoadSnapshot = event {
+            microphone.samples.clear();
+        }
+    }
+}
+
+fn cleanup(
+    mut commands: Commands,
+    markers: Query<Entity, With<MicrophoneMarker>>,
+) {
+    for marker in markers.iter() {
+        commands.entity(marker).despawn();
+    }
+}
+
+pub fn show_microphone(
+    ui: &mut egui::Ui,
+    parameters: &mut LongitudinalWave3dSimulationParameters,
+    microphone: &Microphone,
+) {
+    ui.label("microphone position");
+    let (dimx, dimy, dimz) = (
+        parameters.dimx as f32 * parameters.spacing,
+        parameters.dimy as f32 * parameters.spacing,
+        parameters.dimz as f32 * parameters.spacing,
+    );
+    let position = &mut parameters.microphone_position;
+    ui.add(egui::Slider::new(&mut position.x, 0.0..=dimx).text("x"));
+    ui.add(egui::Slider::new(&mut position.y, 0.0..=dimy).text("y"));
+    ui.add(egui::Slider::new(&mut position.z, 0.0..=dimz).text("z"));
+    ui.add(
+        egui::Slider::new(&mut parameters.microphone_radius, 0.5..=5.0)
+            .text("radius"),
+    );
+
+    ui.label("pressure (relative density)");
+    Plot::new("microphone_plot")
+        .height(160.0)
+        .allow_drag(false)
+        .allow_zoom(false)
+        .include_y(-0.1)
+        .include_y(0.1)
+        .show(ui, |plot_ui| {
+            let points: PlotPoints =
+                microphone.samples.iter().copied().collect();
+            plot_ui.line(Line::new(points).name("pressure"));
+        });
+
+    ui.add(egui::Checkbox::new(
+        &mut parameters.microphone_audio,
+        "play the pressure signal",
+    ));
+    if parameters.microphone_audio {
+        ui.add(
+            egui::Slider::new(
+                &mut parameters.microphone_audio_pitch,
+                10.0..=400.0,
+            )
+            .logarithmic(true)
+            .text("pitch factor"),
+        )
+        .on_hover_text("the signal is played back this much faster");
+        ui.add(
+            egui::Slider::new(
+                &mut parameters.microphone_audio_gain,
+                0.0..=50.0,
+            )
+            .text("gain"),
+        );
+    }
+}
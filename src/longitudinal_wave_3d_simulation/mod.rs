@@ -1,24 +1,133 @@
 use bevy::prelude::*;
+use serde::{Deserialize, Serialize};
+
+use crate::colormap::Colormap;
+use crate::persistence::PersistenceAppExt;
+use crate::recording::{RecordableParameters, RecordingAppExt};
+use crate::simulation::Simulation;
+use crate::simulation_control::forward_reset;
+use crate::AppState;
 
 mod animation_plugin;
+mod audio;
+mod lattice;
+mod microphone;
 mod simulation_plugin;
 mod ui;
 
 pub use animation_plugin::AnimationPlugin;
+use audio::MicrophoneAudioPlugin;
+use microphone::MicrophonePlugin;
 pub use simulation_plugin::SimulationPlugin;
-pub use ui::{show_ui, UiEvents};
+pub use ui::UiEvents;
+
+/// Quantity the particles are colored by
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub enum ParticleColoring {
+    /// displacement along z relative to the driving amplitude
+    Displacement,
+    /// shortening of the distances to the lattice neighbors relative to the
+    /// rest distances, positive where the medium is compressed
+    Compression,
+}
+
+impl ParticleColoring {
+    pub const ALL: [ParticleColoring; 2] = [
+        ParticleColoring::Displacement,
+        ParticleColoring::Compression,
+    ];
+}
+
+impl From<ParticleColoring> for String {
+    fn from(value: ParticleColoring) -> Self {
+        match value {
+            ParticleColoring::Displacement => "displacement".to_string(),
+            ParticleColoring::Compression => "compression".to_string(),
+        }
+    }
+}
+
+/// Boundary of the lattice at one end along z
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub enum TubeEnd {
+    /// the end plane is held in place, or driven as a rigid piston
+    Closed,
+    /// the end plane moves freely, a driven open end is pulled along by the
+    /// driver instead of being moved rigidly
+    Open,
+}
+
+impl TubeEnd {
+    pub const ALL: [TubeEnd; 2] = [TubeEnd::Closed, TubeEnd::Open];
+}
+
+impl From<TubeEnd> for String {
+    fn from(value: TubeEnd) -> Self {
+        match value {
+            TubeEnd::Closed => "closed".to_string(),
+            TubeEnd::Open => "open".to_string(),
+        }
+    }
+}
+
+/// Axis the driving plane at z = 0 oscillates along
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub enum DriveDirection {
+    /// along z, the direction the wave travels in
+    Longitudinal,
+    /// along x, perpendicular to the direction the wave travels in
+    Transverse,
+}
+
+impl DriveDirection {
+    pub const ALL: [DriveDirection; 2] =
+        [DriveDirection::Longitudinal, DriveDirection::Transverse];
+
+    pub fn axis(&self) -> Vec3 {
+        match self {
+            DriveDirection::Longitudinal => Vec3::Z,
+            DriveDirection::Transverse => Vec3::X,
+        }
+    }
+}
 
-#[derive(Resource)]
+impl From<DriveDirection> for String {
+    fn from(value: DriveDirection) -> Self {
+        match value {
+            DriveDirection::Longitudinal => "longitudinal".to_string(),
+            DriveDirection::Transverse => "transverse".to_string(),
+        }
+    }
+}
+
+#[derive(Clone, Resource, Serialize, Deserialize)]
+#[serde(default)]
 pub struct LongitudinalWave3dSimulationParameters {
     // set on initialization
     pub dimx: usize,
     pub dimy: usize,
     pub dimz: usize,
     pub radius: f32,
+    /// rest distance between neighboring particles
+    pub spacing: f32,
+    /// driven end at z = 0
+    pub near_end: TubeEnd,
+    pub far_end: TubeEnd,
     // set on update
     pub applying_force_freq: f32,
     pub applying_force_factor: f32,
+    pub drive_direction: DriveDirection,
     pub equilibrium_force_factor: f32,
+    pub colormap: Colormap,
+    pub coloring: ParticleColoring,
+    /// relative compression shown with the most saturated color
+    pub compression_color_scale: f32,
+    pub microphone_position: Vec3,
+    pub microphone_radius: f32,
+    pub microphone_audio: bool,
+    /// speedup of the played back pressure signal
+    pub microphone_audio_pitch: f32,
+    pub microphone_audio_gain: f32,
 }
 
 impl Default for LongitudinalWave3dSimulationParameters {
@@ -28,20 +137,53 @@ impl Default for LongitudinalWave3dSimulationParameters {
             dimy: 4,
             dimz: 10,
             radius: 0.4,
+            spacing: 1.0,
+            near_end: TubeEnd::Closed,
+            far_end: TubeEnd::Open,
             applying_force_freq: 3.7,
             applying_force_factor: 0.6,
+            drive_direction: DriveDirection::Longitudinal,
             equilibrium_force_factor: 6.0,
+            colormap: Colormap::Seismic,
+            coloring: ParticleColoring::Displacement,
+            compression_color_scale: 0.2,
+            microphone_position: Vec3::new(5.0, 2.0, 5.0),
+            microphone_radius: 1.5,
+            microphone_audio: false,
+            microphone_audio_pitch: 100.0,
+            microphone_audio_gain: 10.0,
         }
     }
 }
 
+impl RecordableParameters for LongitudinalWave3dSimulationParameters {
+    const KIND: &'static str = "longitudinal_wave_3d_parameters";
+    const SIMULATION: AppState = LongitudinalWave3dSimulationPlugin::STATE;
+    const SET_ON_INITIALIZATION: &'static [&'static str] = &[
+        "dimx", "dimy", "dimz", "radius", "spacing", "near_end", "far_end",
+    ];
+
+    fn restore(&mut self, recorded: Self) {
+        *self = recorded;
+    }
+}
+
 pub struct LongitudinalWave3dSimulationPlugin;
 
 impl Plugin for LongitudinalWave3dSimulationPlugin {
     fn build(&self, app: &mut App) {
         app.add_event::<UiEvents>()
+            .add_recordable_event::<UiEvents>()
+            .add_recordable_parameters::<LongitudinalWave3dSimulationParameters>()
+            .add_persistent_parameters::<LongitudinalWave3dSimulationParameters>()
             .add_plugin(SimulationPlugin)
             .add_plugin(AnimationPlugin)
-            .insert_resource(LongitudinalWave3dSimulationParameters::default());
+            .add_plugin(MicrophonePlugin)
+            .add_plugin(MicrophoneAudioPlugin)
+            .insert_resource(LongitudinalWave3dSimulationParameters::default())
+            .add_system_set(
+                SystemSet::on_update(LongitudinalWave3dSimulationPlugin::STATE)
+                    .with_system(forward_reset::<UiEvents>),
+            );
     }
 }
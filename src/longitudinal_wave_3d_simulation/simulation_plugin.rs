@@ -1,14 +1,16 @@
 use bevy::prelude::*;
 
-use crate::AppState;
+use super::LongitudinalWave3dSimulationPlugin;
+use crate::persistence::RestoreParameters;
+use crate::simulation::Simulation;
 
 pub struct SimulationPlugin;
 
 impl Plugin for SimulationPlugin {
     fn build(&self, app: &mut App) {
         app.add_system_set(
-            SystemSet::on_enter(AppState::LongitudinalWaveSimulation3d)
-                .with_system(setup),
+            SystemSet::on_enter(LongitudinalWave3dSimulationPlugin::STATE)
+                .with_system(setup.after(RestoreParameters)),
         );
     }
 }
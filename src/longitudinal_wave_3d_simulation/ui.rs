@@ -1,58 +1,254 @@
+use bevy::ecs::system::SystemState;
 use bevy::prelude::*;
 use bevy_egui::egui;
 use bevy_rapier3d::render::DebugRenderContext;
+use serde::{Deserialize, Serialize};
 
+use crate::recording::RecordableEvent;
+use crate::simulation::Simulation;
+use crate::simulation_control::{ResetEvent, SimulationControlEvent};
+use crate::ui::{
+    select_colormap, show_colormap_legend, show_tunables, Tunable,
+    TunableParameter,
+};
 use crate::AppState;
 
-use super::LongitudinalWave3dSimulationParameters;
+use super::microphone::{show_microphone, Microphone};
+use super::{
+    DriveDirection, LongitudinalWave3dSimulationParameters,
+    LongitudinalWave3dSimulationPlugin, ParticleColoring, TubeEnd,
+};
 
+#[derive(Serialize, Deserialize)]
 pub enum UiEvents {
-    StartStop,
     Reset,
+    SaveSnapshot,
+    LoadSnapshot,
+    ExportVtk,
 }
 
-pub fn show_ui(
+impl RecordableEvent for UiEvents {
+    const KIND: &'static str = "longitudinal_wave_3d_ui";
+
+    fn is_recordable(&self) -> bool {
+        !matches!(
+            self,
+            UiEvents::SaveSnapshot
+                | UiEvents::LoadSnapshot
+                | UiEvents::ExportVtk
+        )
+    }
+}
+
+impl ResetEvent for UiEvents {
+    fn reset() -> Self {
+        UiEvents::Reset
+    }
+}
+
+impl Simulation for LongitudinalWave3dSimulationPlugin {
+    const NAME: &'static str = "longitudinal_wave_3d";
+    type Parameters = LongitudinalWave3dSimulationParameters;
+
+    #[allow(clippy::type_complexity)]
+    fn show_ui(ui: &mut egui::Ui, world: &mut World) {
+        let mut state: SystemState<(
+            ResMut<State<AppState>>,
+            ResMut<LongitudinalWave3dSimulationParameters>,
+            EventWriter<UiEvents>,
+            EventWriter<SimulationControlEvent>,
+            ResMut<DebugRenderContext>,
+            Res<Microphone>,
+        )> = SystemState::new(world);
+        let (
+            mut app_state,
+            mut parameters,
+            ui_events,
+            mut control_events,
+            mut rapier_debug_config,
+            microphone,
+        ) = state.get_mut(world);
+
+        show_ui(
+            ui,
+            &mut app_state,
+            &mut parameters,
+            ui_events,
+            &mut control_events,
+            &mut rapier_debug_config,
+            &microphone,
+        );
+    }
+}
+
+impl Tunable for LongitudinalWave3dSimulationParameters {
+    fn tunables() -> Vec<TunableParameter<Self>> {
+        vec![
+            TunableParameter::<Self>::new(
+                "applying force frequency in Hz",
+                0.0..=10.0,
+                |p| p.applying_force_freq as f64,
+                |p, v| p.applying_force_freq = v as f32,
+            ),
+            TunableParameter::<Self>::new(
+                "applying force factor",
+                0.0..=2.0,
+                |p| p.applying_force_factor as f64,
+                |p, v| p.applying_force_factor = v as f32,
+            )
+            .step_by(0.01),
+            TunableParameter::<Self>::new(
+                "equilibrium force factor",
+                0.0..=1000.0,
+                |p| p.equilibrium_force_factor as f64,
+                |p, v| p.equilibrium_force_factor = v as f32,
+            ),
+        ]
+    }
+}
+
+fn show_ui(
     ui: &mut egui::Ui,
     _app_state: &mut State<AppState>,
     parameters: &mut LongitudinalWave3dSimulationParameters,
     mut ui_events: EventWriter<UiEvents>,
+    control_events: &mut EventWriter<SimulationControlEvent>,
     rapier_debug_config: &mut DebugRenderContext,
+    microphone: &Microphone,
 ) {
     ui.allocate_space(egui::Vec2::new(1.0, 10.0));
 
+    show_tunables(ui, parameters);
+
+    ui.horizontal(|ui| {
+        ui.label("drive:");
+        for option in DriveDirection::ALL {
+            ui.radio_value(
+                &mut parameters.drive_direction,
+                option,
+                String::from(option),
+            );
+        }
+    });
+
+    ui.label("lattice, applied on reset:");
+    ui.add(egui::Slider::new(&mut parameters.dimx, 1..=30).text("particles x"));
+    ui.add(egui::Slider::new(&mut parameters.dimy, 1..=10).text("particles y"));
+    ui.add(egui::Slider::new(&mut parameters.dimz, 2..=60).text("particles z"));
     ui.add(
-        egui::Slider::new(&mut parameters.applying_force_freq, 0.0..=10.0)
-            .text("applying force frequency in Hz"),
+        egui::Slider::new(&mut parameters.radius, 0.1..=1.0)
+            .step_by(0.05)
+            .text("particle radius"),
     );
-
     ui.add(
-        egui::Slider::new(&mut parameters.applying_force_factor, 0.0..=2.0)
-            .step_by(0.01)
-            .text("applying force factor"),
+        egui::Slider::new(&mut parameters.spacing, 0.5..=3.0)
+            .step_by(0.05)
+            .text("rest spacing"),
     );
 
-    ui.add(
-        egui::Slider::new(
-            &mut parameters.equilibrium_force_factor,
-            0.0..=1000.0,
+    ui.label("tube ends, applied on reset:");
+    for (label, end) in [
+        ("driven end", &mut parameters.near_end),
+        ("far end", &mut parameters.far_end),
+    ] {
+        ui.horizontal(|ui| {
+            ui.label(label);
+            for option in TubeEnd::ALL {
+                ui.radio_value(end, option, String::from(option));
+            }
+        });
+    }
+    if ui
+        .button("Tube")
+        .on_hover_text(
+            "a long, thin lattice: with equal ends all harmonics resonate, \
+            with one open and one closed end only the odd ones",
         )
-        .text("equilibrium force factor"),
-    );
+        .clicked()
+    {
+        parameters.dimx = 2;
+        parameters.dimy = 2;
+        parameters.dimz = 40;
+        parameters.microphone_position =
+            Vec3::new(0.5, 0.5, 20.0) * parameters.spacing;
+        // forwarded as the reset of the lattice, also restarts the clock
+        control_events.send(SimulationControlEvent::Reset);
+    }
 
-    ui.separator();
+    select_colormap(ui, &mut parameters.colormap);
 
     ui.horizontal(|ui| {
-        if ui.button("Start/Stop").clicked() {
-            ui_events.send(UiEvents::StartStop);
+        ui.label("color by:");
+        for option in ParticleColoring::ALL {
+            ui.radio_value(
+                &mut parameters.coloring,
+                option,
+                String::from(option),
+            );
         }
-        if ui.button("Reset values").clicked() {
-            *parameters = LongitudinalWave3dSimulationParameters::default();
+    });
+
+    match parameters.coloring {
+        ParticleColoring::Displacement => {
+            show_colormap_legend(
+                ui,
+                parameters.colormap,
+                &format!("{:.2}", -parameters.applying_force_factor),
+                &format!("{:.2}", parameters.applying_force_factor),
+            );
         }
-        if ui.button("Reset particles").clicked() {
-            ui_events.send(UiEvents::Reset);
+        ParticleColoring::Compression => {
+            ui.add(
+                egui::Slider::new(
+                    &mut parameters.compression_color_scale,
+                    0.01..=1.0,
+                )
+                .logarithmic(true)
+                .text("compression scale"),
+            );
+            show_colormap_legend(
+                ui,
+                parameters.colormap,
+                &format!(
+                    "rarefaction {:.0} %",
+                    -parameters.compression_color_scale * 100.0
+                ),
+                &format!(
+                    "{:.0} % compression",
+                    parameters.compression_color_scale * 100.0
+                ),
+            );
+        }
+    }
+
+    ui.separator();
+
+    show_microphone(ui, parameters, microphone);
+
+    ui.separator();
+
+    if ui.button("Reset values").clicked() {
+        *parameters = LongitudinalWave3dSimulationParameters::default();
+    }
+
+    ui.horizontal(|ui| {
+        ui.label("snapshot:");
+        if ui.button("Save").clicked() {
+            ui_events.send(UiEvents::SaveSnapshot);
+        }
+        if ui.button("Load").clicked() {
+            ui_events.send(UiEvents::LoadSnapshot);
         }
     });
 
+    if ui
+        .button("Export VTK")
+        .on_hover_text("particles with their displacement, for ParaView")
+        .clicked()
+    {
+        ui_events.send(UiEvents::ExportVtk);
+    }
+
     ui.separator();
 
     ui.add(egui::Checkbox::new(
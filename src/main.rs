@@ -1,49 +1,92 @@
+use std::borrow::Cow;
+
 use bevy::diagnostic::FrameTimeDiagnosticsPlugin;
 use bevy::prelude::*;
 use bevy::window::PresentMode;
 use bevy_rapier3d::prelude::*;
+use serde::{Deserialize, Serialize};
 
+mod audio_file;
+mod audio_input;
+mod capture;
+mod cli;
 mod colored_mesh;
+mod colormap;
+mod data_stream;
+mod edit_history;
+mod file_dialog;
+mod gltf_export;
+mod hover;
+mod inspector;
+mod instancing;
+mod keymap;
+mod level_of_detail;
 mod longitudinal_wave_3d_simulation;
 mod objects_3d;
 mod pan_orbit_camera;
 mod particle_mess;
+mod persistence;
+mod presets;
+mod recording;
+mod remote_control;
+mod ripple_tank;
+mod schroedinger_2d_simulation;
+mod scripting;
+mod simulation;
+mod simulation_control;
+mod snapshot;
+mod spectrum;
 mod ui;
+mod velocity_arrows;
+mod viewports;
+mod vtk;
+mod wave_1d_simulation;
 mod wave_2d_simulation;
 mod wave_in_panel;
 
+use audio_file::AudioFilePlugin;
+use audio_input::AudioInputPlugin;
+use capture::CapturePlugin;
+use cli::Cli;
+use colored_mesh::ColoredMesh3dPlugin;
+use data_stream::DataStreamPlugin;
+use edit_history::EditHistoryPlugin;
+use hover::HoverPlugin;
+use inspector::InspectorPlugin;
+use instancing::InstancedParticlesPlugin;
+use keymap::KeymapPlugin;
+use level_of_detail::LevelOfDetailPlugin;
 use longitudinal_wave_3d_simulation::LongitudinalWave3dSimulationPlugin;
+use pan_orbit_camera::PanOrbitCameraPlugin;
 use particle_mess::ParticleMessPlugin;
+use recording::RecordingPlugin;
+use remote_control::RemoteControlPlugin;
+use ripple_tank::RippleTankPlugin;
+use schroedinger_2d_simulation::Schroedinger2dSimulationPlugin;
+use scripting::ScriptingPlugin;
+use simulation::{Simulation, SimulationAppExt, Simulations};
+use simulation_control::SimulationControlPlugin;
 use ui::UiPlugin;
-use wave_2d_simulation::Wave2dSimulationPlugin;
+use velocity_arrows::VelocityArrowsPlugin;
+use viewports::ViewportPlugin;
+use wave_1d_simulation::Wave1dSimulationPlugin;
+use wave_2d_simulation::{Wave2dComparisonPlugin, Wave2dSimulationPlugin};
 use wave_in_panel::WaveInPanelPlugin;
 
 pub const RESOLUTION: f32 = 16.0 / 9.0;
 
-#[derive(Debug, Clone, PartialEq, Eq, Hash)]
-pub enum AppState {
-    Wave2dSimulation,
-    LongitudinalWaveSimulation3d,
-    ParticleMess,
-    WaveInPanel,
-}
+/// The running simulation, identified by the name of its `Simulation`
+#[derive(Debug, Clone, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(transparent)]
+pub struct AppState(Cow<'static, str>);
 
 impl AppState {
-    fn start() -> Self {
-        Self::WaveInPanel
+    pub const fn named(name: &'static str) -> Self {
+        Self(Cow::Borrowed(name))
     }
-}
 
-impl From<AppState> for String {
-    fn from(value: AppState) -> Self {
-        match value {
-            AppState::Wave2dSimulation => "wave_2d".to_string(),
-            AppState::LongitudinalWaveSimulation3d => {
-                "longitudinal_wave_3d".to_string()
-            }
-            AppState::ParticleMess => "particle_mess".to_string(),
-            AppState::WaveInPanel => "wave_in_panel".to_string(),
-        }
+    pub fn name(&self) -> &str {
+        &self.0
     }
 }
 
@@ -51,24 +94,61 @@ impl From<AppState> for String {
 pub struct AppCamera;
 
 fn main() {
-    let height = 900.0;
+    let cli = match Cli::load() {
+        Ok(cli) => cli,
+        Err(error) => {
+            eprintln!("error: {}", error);
+            std::process::exit(2);
+        }
+    };
+
+    if cli.validate {
+        let passed = wave_2d_simulation::run_validation();
+        std::process::exit(if passed { 0 } else { 1 });
+    }
+
+    if cli.headless {
+        let result = match cli.simulation.as_deref() {
+            Some(simulation) if simulation == Wave2dSimulationPlugin::NAME => {
+                wave_2d_simulation::run_headless(&cli)
+            }
+            Some(simulation) => {
+                Err(format!("{} can not run headless", simulation))
+            }
+            None => Err("--headless requires --simulation".to_string()),
+        };
+
+        if let Err(error) = result {
+            eprintln!("error: {}", error);
+            std::process::exit(1);
+        }
+        return;
+    }
 
-    App::new()
+    let (width, height) = cli.window_size();
+    let present_mode = if cli.vsync.unwrap_or(true) {
+        PresentMode::AutoVsync
+    } else {
+        PresentMode::AutoNoVsync
+    };
+
+    let mut app = App::new();
+    app
         // core systems
         .add_plugins(DefaultPlugins.set(WindowPlugin {
             window: WindowDescriptor {
                 height,
-                width: height * RESOLUTION,
+                width,
                 title: "wave_sim".to_string(),
-                present_mode: PresentMode::AutoVsync,
+                present_mode,
                 resizable: true,
                 ..default()
             },
             ..default()
         }))
-        .insert_resource(Msaa { samples: 1 })
-        // app
-        .add_state(AppState::start())
+        .insert_resource(Msaa {
+            samples: cli.msaa.unwrap_or(1),
+        })
         // physics
         .insert_resource(RapierConfiguration::default())
         .add_plugin(RapierPhysicsPlugin::<()>::default())
@@ -77,10 +157,55 @@ fn main() {
         .add_plugin(RapierDebugRenderPlugin::default())
         // ui configuration
         .add_plugin(UiPlugin)
+        .add_plugin(SimulationControlPlugin)
+        .add_plugin(KeymapPlugin)
+        .add_plugin(EditHistoryPlugin)
+        .add_plugin(PanOrbitCameraPlugin)
+        .add_plugin(HoverPlugin)
+        .add_plugin(InspectorPlugin)
+        .add_plugin(ViewportPlugin)
+        .add_plugin(ColoredMesh3dPlugin)
+        .add_plugin(InstancedParticlesPlugin)
+        .add_plugin(LevelOfDetailPlugin)
+        .add_plugin(VelocityArrowsPlugin)
+        .add_plugin(AudioInputPlugin)
+        .add_plugin(AudioFilePlugin)
         // simulation systems
-        .add_plugin(Wave2dSimulationPlugin)
-        .add_plugin(LongitudinalWave3dSimulationPlugin)
-        .add_plugin(ParticleMessPlugin)
-        .add_plugin(WaveInPanelPlugin)
-        .run();
+        .add_simulation(Wave2dSimulationPlugin)
+        .add_simulation(LongitudinalWave3dSimulationPlugin)
+        .add_simulation(ParticleMessPlugin)
+        .add_simulation(WaveInPanelPlugin)
+        .add_simulation(Wave2dComparisonPlugin)
+        .add_simulation(Wave1dSimulationPlugin)
+        .add_simulation(Schroedinger2dSimulationPlugin)
+        .add_simulation(RippleTankPlugin)
+        .insert_resource(wave_2d_simulation::parameters_from_cli(&cli))
+        .add_plugin(RecordingPlugin)
+        .add_plugin(CapturePlugin)
+        .add_plugin(RemoteControlPlugin {
+            port: cli.remote_port,
+            all_interfaces: cli.remote_all_interfaces,
+        })
+        .add_plugin(DataStreamPlugin)
+        .add_plugin(ScriptingPlugin);
+
+    // the simulations are looked up by name once all of them are added
+    let start = match &cli.simulation {
+        Some(name) => {
+            let simulations = app.world.resource::<Simulations>();
+            simulations.state(name).unwrap_or_else(|| {
+                let names: Vec<&str> =
+                    simulations.states().map(AppState::name).collect();
+                eprintln!(
+                    "error: unknown simulation {}, expected one of {}",
+                    name,
+                    names.join(", ")
+                );
+                std::process::exit(2);
+            })
+        }
+        None => WaveInPanelPlugin::STATE,
+    };
+
+    app.add_state(start).run();
 }
@@ -39,7 +39,6 @@ pub struct ContainerBundle {
     pub external_impulse: ExternalImpulse,
 }
 
-#[allow(dead_code)]
 impl ContainerBundle {
     pub fn new_from_xyz(
         x: f32,
@@ -1,5 +1,68 @@
+use std::f32::consts::{FRAC_PI_2, FRAC_PI_4};
+
 use bevy::input::mouse::{MouseMotion, MouseWheel};
 use bevy::prelude::*;
+use bevy::render::camera::ScalingMode;
+use bevy_egui::egui;
+
+use crate::ui::PointerOverUi;
+use crate::viewports::cursor_in_viewport;
+
+/// Seconds the camera takes to turn to a preset or to move to a new focus
+const ANIMATION_SECS: f32 = 0.5;
+
+/// Longest time in seconds between the clicks of a double click
+const DOUBLE_CLICK_SECS: f64 = 0.3;
+
+/// Distance in pixels from the cursor within which a double click hits an
+/// entity
+const PICK_DISTANCE: f32 = 12.0;
+
+/// Angular velocity in radians per second below which a coasting camera
+/// stops
+const MIN_COASTING_VELOCITY: f32 = 0.01;
+
+const MOUSE_BUTTONS: [MouseButton; 3] =
+    [MouseButton::Left, MouseButton::Middle, MouseButton::Right];
+
+/// Bindings and feel of the pan orbit cameras, shared by all 3d simulations
+/// so it is kept when their cameras are spawned again
+#[derive(Clone, Copy, Debug, PartialEq, Resource)]
+pub struct PanOrbitSettings {
+    pub orbit_button: MouseButton,
+    pub pan_button: MouseButton,
+    /// fraction of the radius zoomed per notch of the wheel
+    pub zoom_speed: f32,
+    /// the camera keeps turning after the orbit button is released
+    pub inertia: bool,
+    /// rate per second at which a coasting camera slows down
+    pub damping: f32,
+    pub min_radius: f32,
+    pub max_radius: f32,
+}
+
+impl Default for PanOrbitSettings {
+    fn default() -> Self {
+        Self {
+            orbit_button: MouseButton::Middle,
+            pan_button: MouseButton::Left,
+            zoom_speed: 0.05,
+            inertia: false,
+            damping: 4.0,
+            // dont allow zoom to reach zero or you get stuck
+            min_radius: 0.05,
+            max_radius: 1000.0,
+        }
+    }
+}
+
+pub struct PanOrbitCameraPlugin;
+
+impl Plugin for PanOrbitCameraPlugin {
+    fn build(&self, app: &mut App) {
+        app.insert_resource(PanOrbitSettings::default());
+    }
+}
 
 #[derive(Component)]
 pub struct PanOrbitCamera {
@@ -7,6 +70,14 @@ pub struct PanOrbitCamera {
     pub focus: Vec3,
     pub radius: f32,
     pub upside_down: bool,
+    /// keep the focus on a double clicked entity while it moves
+    pub follow_focused: bool,
+    /// entity whose position is the focus
+    following: Option<Entity>,
+    animation: Option<CameraAnimation>,
+    /// yaw and pitch in radians per second the camera keeps turning with
+    /// after orbiting, if the settings have inertia
+    orbit_velocity: Vec2,
 }
 
 impl Default for PanOrbitCamera {
@@ -15,24 +86,138 @@ impl Default for PanOrbitCamera {
             focus: Vec3::ZERO,
             radius: 5.0,
             upside_down: false,
+            follow_focused: false,
+            following: None,
+            animation: None,
+            orbit_velocity: Vec2::ZERO,
+        }
+    }
+}
+
+impl PanOrbitCamera {
+    /// Turns the camera smoothly around the focus until it has the given
+    /// rotation
+    pub fn animate_to(&mut self, rotation: Quat) {
+        self.orbit_velocity = Vec2::ZERO;
+        self.animation = Some(CameraAnimation {
+            from: None,
+            rotation: Some(rotation),
+            focus: self.focus,
+            radius: self.radius,
+            elapsed_secs: 0.0,
+        });
+    }
+
+    /// Moves the focus smoothly to the given point and zooms to the given
+    /// distance, keeping the direction of the camera
+    pub fn focus_on(&mut self, focus: Vec3, radius: f32) {
+        self.orbit_velocity = Vec2::ZERO;
+        self.animation = Some(CameraAnimation {
+            from: None,
+            rotation: None,
+            focus,
+            radius,
+            elapsed_secs: 0.0,
+        });
+    }
+
+    /// Keeps the focus on the position of the entity, until the camera is
+    /// panned or the entity is despawned
+    pub fn follow(&mut self, entity: Option<Entity>) {
+        self.following = entity;
+    }
+
+    pub fn following(&self) -> Option<Entity> {
+        self.following
+    }
+}
+
+struct CameraAnimation {
+    /// rotation, focus and radius in the first animated frame
+    from: Option<(Quat, Vec3, f32)>,
+    /// the rotation is kept if none is given
+    rotation: Option<Quat>,
+    focus: Vec3,
+    radius: f32,
+    elapsed_secs: f32,
+}
+
+/// Entity which is focused by double clicking it
+#[derive(Component)]
+pub struct Focusable {
+    /// distance of the camera to the focused entity
+    pub radius: f32,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum CameraPreset {
+    Front,
+    Top,
+    Side,
+    Isometric,
+}
+
+impl CameraPreset {
+    pub const ALL: [CameraPreset; 4] = [
+        CameraPreset::Front,
+        CameraPreset::Top,
+        CameraPreset::Side,
+        CameraPreset::Isometric,
+    ];
+
+    /// Rotation of a camera looking at the focus from the preset direction
+    pub fn rotation(self) -> Quat {
+        match self {
+            CameraPreset::Front => Quat::IDENTITY,
+            CameraPreset::Top => Quat::from_rotation_x(-FRAC_PI_2),
+            CameraPreset::Side => Quat::from_rotation_y(FRAC_PI_2),
+            // looks along the space diagonal
+            CameraPreset::Isometric => {
+                Quat::from_rotation_y(FRAC_PI_4)
+                    * Quat::from_rotation_x(-(0.5_f32.sqrt()).atan())
+            }
+        }
+    }
+}
+
+impl From<CameraPreset> for String {
+    fn from(value: CameraPreset) -> Self {
+        match value {
+            CameraPreset::Front => "front".to_string(),
+            CameraPreset::Top => "top".to_string(),
+            CameraPreset::Side => "side".to_string(),
+            CameraPreset::Isometric => "isometric".to_string(),
         }
     }
 }
 
 /// courtesy of: https://bevy-cheatbook.github.io/cookbook/pan-orbit-camera.html
+#[allow(clippy::too_many_arguments)]
 pub fn update_pan_orbit_camera(
     windows: Res<Windows>,
     mut ev_motion: EventReader<MouseMotion>,
     mut ev_scroll: EventReader<MouseWheel>,
     input_mouse: Res<Input<MouseButton>>,
-    mut query: Query<(&mut PanOrbitCamera, &mut Transform, &Projection)>,
+    touches: Res<Touches>,
+    pointer_over_ui: Res<PointerOverUi>,
+    settings: Res<PanOrbitSettings>,
+    time: Res<Time>,
+    mut query: Query<(&mut PanOrbitCamera, &mut Transform, &mut Projection)>,
+    targets: Query<&GlobalTransform>,
 ) {
-    let orbit_button = MouseButton::Middle;
-    let pan_button = MouseButton::Left;
+    for (mut pan_orbit, mut transform, mut projection) in query.iter_mut() {
+        follow_entity(&mut pan_orbit, &mut transform, &targets);
+        animate(&mut pan_orbit, &mut transform, time.delta_seconds());
+        follow_radius(&pan_orbit, &mut projection);
+    }
+
+    let orbit_button = settings.orbit_button;
+    let pan_button = settings.pan_button;
 
     let mut pan = Vec2::ZERO;
     let mut rotation_move = Vec2::ZERO;
     let mut scroll: f32 = 0.0;
+    let mut pinch: f32 = 1.0;
     let mut orbit_button_changed = false;
 
     for ev in ev_motion.iter() {
@@ -57,13 +242,32 @@ pub fn update_pan_orbit_camera(
         orbit_button_changed = true;
     }
 
-    // TODO: remove hardcoded filter for ui
-    for window in windows.iter() {
-        if let Some(position) = window.cursor_position() {
-            if position.x < 350.0 {
-                return;
+    // the buttons and the wheel are already consumed over the ui, touches
+    // are not
+    let fingers: Vec<&Touch> = if pointer_over_ui.0 {
+        Vec::new()
+    } else {
+        touches.iter().collect()
+    };
+
+    // one finger orbits, two fingers pan and pinch to zoom
+    match fingers[..] {
+        [finger] => {
+            rotation_move += finger.delta();
+            orbit_button_changed |= touches.iter_just_pressed().count() > 0;
+        }
+        [first, second] => {
+            pan += (first.delta() + second.delta()) / 2.0;
+
+            let distance = first.position().distance(second.position());
+            let previous_distance = first
+                .previous_position()
+                .distance(second.previous_position());
+            if distance > 0.0 && previous_distance > 0.0 {
+                pinch = previous_distance / distance;
             }
         }
+        _ => {}
     }
 
     for (mut pan_orbit, mut transform, projection) in query.iter_mut() {
@@ -74,6 +278,7 @@ pub fn update_pan_orbit_camera(
             pan_orbit.upside_down = up.y <= 0.0;
         }
 
+        let delta_seconds = time.delta_seconds();
         let mut any = false;
         if rotation_move.length_squared() > 0.0 {
             any = true;
@@ -88,15 +293,17 @@ pub fn update_pan_orbit_camera(
                 }
             };
             let delta_y = rotation_move.y / window.y * std::f32::consts::PI;
-            let yaw = Quat::from_rotation_y(-delta_x);
-            let pitch = Quat::from_rotation_x(-delta_y);
-            transform.rotation = yaw * transform.rotation; // rotate around global y axis
-            transform.rotation *= pitch; // rotate around local x axis
+            orbit(&mut transform, delta_x, delta_y);
+
+            if delta_seconds > 0.0 {
+                pan_orbit.orbit_velocity =
+                    Vec2::new(delta_x, delta_y) / delta_seconds;
+            }
         } else if pan.length_squared() > 0.0 {
             any = true;
             // make panning distance independent of resolution and FOV,
             let window = get_primary_window_size(&windows);
-            if let Projection::Perspective(projection) = projection {
+            if let Projection::Perspective(projection) = &*projection {
                 pan *= Vec2::new(
                     projection.fov * projection.aspect_ratio,
                     projection.fov,
@@ -108,14 +315,35 @@ pub fn update_pan_orbit_camera(
             // make panning proportional to distance away from focus point
             let translation = (right + up) * pan_orbit.radius;
             pan_orbit.focus += translation;
-        } else if scroll.abs() > 0.0 {
+            pan_orbit.following = None;
+        } else if scroll.abs() > 0.0 || pinch != 1.0 {
             any = true;
-            pan_orbit.radius -= scroll * pan_orbit.radius * 0.05;
-            // dont allow zoom to reach zero or you get stuck
-            pan_orbit.radius = pan_orbit.radius.clamp(0.05, 1000.0);
+            pan_orbit.radius -= scroll * pan_orbit.radius * settings.zoom_speed;
+            pan_orbit.radius *= pinch;
+            pan_orbit.radius = pan_orbit
+                .radius
+                .clamp(settings.min_radius, settings.max_radius);
+        }
+
+        // a released camera coasts on, holding the orbit button or a finger
+        // still stops it
+        let holding = input_mouse.pressed(orbit_button) || !fingers.is_empty();
+        if !settings.inertia || (holding && rotation_move == Vec2::ZERO) {
+            pan_orbit.orbit_velocity = Vec2::ZERO;
+        } else if !holding
+            && pan_orbit.orbit_velocity.length() > MIN_COASTING_VELOCITY
+        {
+            any = true;
+            let delta = pan_orbit.orbit_velocity * delta_seconds;
+            orbit(&mut transform, delta.x, delta.y);
+            pan_orbit.orbit_velocity *=
+                (-settings.damping * delta_seconds).exp();
         }
 
         if any {
+            // the user takes over from a running animation
+            pan_orbit.animation = None;
+
             // emulating parent/child to make the yaw/y-axis rotation behave like a turntable
             // parent = x and y rotation
             // child = z-offset
@@ -130,6 +358,282 @@ pub fn update_pan_orbit_camera(
     }
 }
 
+/// Turns the camera by `delta_x` around the global y axis, like a
+/// turntable, and by `delta_y` around its local x axis
+fn orbit(transform: &mut Transform, delta_x: f32, delta_y: f32) {
+    let yaw = Quat::from_rotation_y(-delta_x);
+    let pitch = Quat::from_rotation_x(-delta_y);
+    transform.rotation = yaw * transform.rotation;
+    transform.rotation *= pitch;
+}
+
+/// Moves the focus, or the target of a running animation, along with the
+/// followed entity
+fn follow_entity(
+    pan_orbit: &mut PanOrbitCamera,
+    transform: &mut Transform,
+    targets: &Query<&GlobalTransform>,
+) {
+    let entity = if let Some(entity) = pan_orbit.following {
+        entity
+    } else {
+        return;
+    };
+    let position = if let Ok(target) = targets.get(entity) {
+        target.translation()
+    } else {
+        pan_orbit.following = None;
+        return;
+    };
+
+    if let Some(animation) = &mut pan_orbit.animation {
+        animation.focus = position;
+    } else {
+        pan_orbit.focus = position;
+        transform.translation = pan_orbit.focus
+            + transform.rotation * Vec3::new(0.0, 0.0, pan_orbit.radius);
+    }
+}
+
+/// Advances a running animation of the camera towards its target rotation,
+/// focus and radius
+fn animate(
+    pan_orbit: &mut PanOrbitCamera,
+    transform: &mut Transform,
+    delta_seconds: f32,
+) {
+    let (focus, radius) = (pan_orbit.focus, pan_orbit.radius);
+    let animation = if let Some(animation) = &mut pan_orbit.animation {
+        animation
+    } else {
+        return;
+    };
+
+    let (from_rotation, from_focus, from_radius) = *animation
+        .from
+        .get_or_insert((transform.rotation, focus, radius));
+    animation.elapsed_secs += delta_seconds;
+
+    let t = (animation.elapsed_secs / ANIMATION_SECS).min(1.0);
+    // eases in and out
+    let smooth_t = t * t * (3.0 - 2.0 * t);
+    if let Some(rotation) = animation.rotation {
+        transform.rotation = from_rotation.slerp(rotation, smooth_t);
+    }
+    let focus = from_focus.lerp(animation.focus, smooth_t);
+    let radius = from_radius + (animation.radius - from_radius) * smooth_t;
+
+    if t >= 1.0 {
+        pan_orbit.animation = None;
+        pan_orbit.upside_down = (transform.rotation * Vec3::Y).y <= 0.0;
+    }
+
+    pan_orbit.focus = focus;
+    pan_orbit.radius = radius;
+    transform.translation =
+        focus + transform.rotation * Vec3::new(0.0, 0.0, radius);
+}
+
+/// Focuses the camera on the double clicked [`Focusable`] entity closest to
+/// the cursor, and follows it if the camera follows focused entities
+pub fn focus_on_double_click(
+    windows: Res<Windows>,
+    input_mouse: Res<Input<MouseButton>>,
+    time: Res<Time>,
+    mut last_click: Local<Option<f64>>,
+    mut cameras: Query<(&mut PanOrbitCamera, &Camera, &GlobalTransform)>,
+    focusables: Query<(Entity, &GlobalTransform, &Focusable)>,
+) {
+    if !input_mouse.just_pressed(MouseButton::Left) {
+        return;
+    }
+
+    let now = time.elapsed_seconds_f64();
+    let is_double_click = last_click
+        .replace(now)
+        .map_or(false, |last| now - last <= DOUBLE_CLICK_SECS);
+    if !is_double_click {
+        return;
+    }
+    *last_click = None;
+
+    let window = if let Some(window) = windows.get_primary() {
+        window
+    } else {
+        return;
+    };
+
+    for (mut pan_orbit, camera, camera_transform) in cameras.iter_mut() {
+        let cursor = if let Some(cursor) = cursor_in_viewport(camera, window) {
+            cursor
+        } else {
+            continue;
+        };
+        let hit = focusables
+            .iter()
+            .filter_map(|(entity, transform, focusable)| {
+                let position = camera.world_to_viewport(
+                    camera_transform,
+                    transform.translation(),
+                )?;
+                let distance = position.distance(cursor);
+                (distance <= PICK_DISTANCE).then_some((
+                    entity,
+                    transform.translation(),
+                    focusable,
+                    distance,
+                ))
+            })
+            .min_by(|a, b| a.3.total_cmp(&b.3));
+
+        if let Some((entity, position, focusable, _)) = hit {
+            pan_orbit.focus_on(position, focusable.radius);
+            if pan_orbit.follow_focused {
+                pan_orbit.follow(Some(entity));
+            }
+        }
+    }
+}
+
+/// An orthographic projection has no depth to zoom by moving the camera, it
+/// shows as much as a perspective projection shows at the focus instead
+fn follow_radius(pan_orbit: &PanOrbitCamera, projection: &mut Projection) {
+    if let Projection::Orthographic(orthographic) = projection {
+        let scale = pan_orbit.radius
+            * (PerspectiveProjection::default().fov / 2.0).tan();
+        if orthographic.scale != scale {
+            orthographic.scale = scale;
+        }
+    }
+}
+
+/// Presets, projection, bindings and feel of the camera of a 3d simulation
+pub fn show_camera_controls(
+    ui: &mut egui::Ui,
+    pan_orbit: &mut PanOrbitCamera,
+    projection: &mut Projection,
+    settings: &mut PanOrbitSettings,
+) {
+    egui::CollapsingHeader::new("Camera").show(ui, |ui| {
+        ui.horizontal(|ui| {
+            for preset in CameraPreset::ALL {
+                if ui.button(String::from(preset)).clicked() {
+                    pan_orbit.animate_to(preset.rotation());
+                }
+            }
+        });
+
+        ui.horizontal(|ui| {
+            ui.add(egui::Checkbox::new(
+                &mut pan_orbit.follow_focused,
+                "follow double clicked",
+            ));
+            if ui
+                .add_enabled(
+                    pan_orbit.following.is_some(),
+                    egui::Button::new("Stop following"),
+                )
+                .clicked()
+            {
+                pan_orbit.follow(None);
+            }
+        });
+
+        let mut orthographic =
+            matches!(projection, Projection::Orthographic(_));
+        if ui
+            .add(egui::Checkbox::new(&mut orthographic, "orthographic"))
+            .on_hover_text(
+                "keeps the size of the displacements independent of the depth",
+            )
+            .changed()
+        {
+            *projection = if orthographic {
+                Projection::Orthographic(OrthographicProjection {
+                    scaling_mode: ScalingMode::FixedVertical(2.0),
+                    ..default()
+                })
+            } else {
+                Projection::Perspective(PerspectiveProjection::default())
+            };
+        }
+
+        ui.separator();
+
+        show_camera_settings(ui, settings);
+    });
+}
+
+fn show_camera_settings(ui: &mut egui::Ui, settings: &mut PanOrbitSettings) {
+    select_mouse_button(ui, "orbit with", &mut settings.orbit_button);
+    select_mouse_button(ui, "pan with", &mut settings.pan_button);
+    if settings.orbit_button == settings.pan_button {
+        ui.colored_label(
+            egui::Color32::YELLOW,
+            "the same button orbits, panning is not possible",
+        );
+    }
+
+    ui.add(
+        egui::Slider::new(&mut settings.zoom_speed, 0.01..=0.3)
+            .logarithmic(true)
+            .text("zoom per wheel notch"),
+    );
+
+    ui.horizontal(|ui| {
+        ui.label("distance:");
+        let max_radius = settings.max_radius;
+        ui.add(
+            egui::DragValue::new(&mut settings.min_radius)
+                .clamp_range(0.01..=max_radius)
+                .speed(0.01)
+                .prefix("min "),
+        );
+        let min_radius = settings.min_radius;
+        ui.add(
+            egui::DragValue::new(&mut settings.max_radius)
+                .clamp_range(min_radius..=10000.0)
+                .prefix("max "),
+        );
+    });
+
+    ui.add(egui::Checkbox::new(&mut settings.inertia, "inertia"))
+        .on_hover_text("the camera keeps turning after orbiting");
+    if settings.inertia {
+        ui.add(
+            egui::Slider::new(&mut settings.damping, 0.5..=20.0)
+                .logarithmic(true)
+                .text("damping per second"),
+        );
+    }
+
+    if ui.button("Reset camera settings").clicked() {
+        *settings = PanOrbitSettings::default();
+    }
+}
+
+fn select_mouse_button(
+    ui: &mut egui::Ui,
+    label: &str,
+    button: &mut MouseButton,
+) {
+    ui.horizontal(|ui| {
+        ui.label(label);
+        for option in MOUSE_BUTTONS {
+            ui.radio_value(button, option, mouse_button_name(option));
+        }
+    });
+}
+
+fn mouse_button_name(button: MouseButton) -> &'static str {
+    match button {
+        MouseButton::Left => "left",
+        MouseButton::Middle => "middle",
+        MouseButton::Right => "right",
+        MouseButton::Other(_) => "other",
+    }
+}
+
 fn get_primary_window_size(windows: &Res<Windows>) -> Vec2 {
     let window = windows.get_primary().unwrap();
     Vec2::new(window.width(), window.height())
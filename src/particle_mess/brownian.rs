@@ -0,0 +1,272 @@
+use bevy::prelude::*;
+use bevy::render::render_resource::PrimitiveTopology;
+use bevy_egui::egui;
+use bevy_egui::egui::plot::{Legend, Line, Plot, PlotPoints};
+use bevy_rapier3d::prelude::*;
+
+use super::species::ball_mass;
+use super::{
+    Entities, ParticleMessParameters, UiEvents, FOCUS_DISTANCE_FACTOR,
+};
+use crate::objects_3d::BallBundle;
+use crate::pan_orbit_camera::{Focusable, PanOrbitCamera};
+use crate::simulation_control::SimulationControl;
+use crate::AppCamera;
+
+/// Radius of the pollen relative to the particle radius
+const POLLEN_RADIUS_FACTOR: f32 = 8.0;
+
+/// Time between two recorded pollen positions
+const TRACK_PERIOD_SECS: f32 = 0.05;
+
+const MAX_TRAJECTORY_POINTS: usize = 1000;
+
+/// The mean squared displacement is recomputed after this many samples
+const MSD_UPDATE_SAMPLES: usize = 20;
+
+/// Large particle which is only pushed around by the small ones
+#[derive(Component)]
+pub struct Pollen;
+
+#[derive(Component)]
+struct Trajectory;
+
+/// Position history of the pollen and its mean squared displacement
+#[derive(Default, Resource)]
+pub struct BrownianTracker {
+    elapsed_secs: f32,
+    positions: Vec<Vec3>,
+    /// `[lag in s, mean squared displacement]`
+    msd: Vec<[f64; 2]>,
+    /// slope of the mean squared displacement divided by 6
+    diffusion_coefficient: Option<f64>,
+    trajectory_mesh: Handle<Mesh>,
+}
+
+#[allow(clippy::too_many_arguments, clippy::type_complexity)]
+pub fn on_pollen_events(
+    mut commands: Commands,
+    mut ui_events: EventReader<UiEvents>,
+    mut tracker: ResMut<BrownianTracker>,
+    mut meshes: ResMut<Assets<Mesh>>,
+    mut materials: ResMut<Assets<StandardMaterial>>,
+    mut entities: ResMut<Entities>,
+    parameters: Res<ParticleMessParameters>,
+    pollen: Query<Entity, Or<(With<Pollen>, With<Trajectory>)>>,
+) {
+    for event in ui_events.iter() {
+        if !matches!(event, UiEvents::AddPollen | UiEvents::Reset) {
+            continue;
+        }
+
+        for entity in pollen.iter() {
+            commands.entity(entity).despawn();
+        }
+
+        if let UiEvents::Reset = event {
+            *tracker = BrownianTracker::default();
+            continue;
+        }
+
+        let radius = parameters.particle_radius * POLLEN_RADIUS_FACTOR;
+        let center =
+            Vec3::new(parameters.dimx, parameters.dimy, parameters.dimz);
+
+        let mut ball = BallBundle::new_from_xyz(
+            center.x * parameters.piston_position,
+            center.y,
+            center.z,
+            radius,
+        );
+        ball.pbr.mesh = meshes.add(Mesh::from(shape::Icosphere {
+            radius,
+            subdivisions: 6,
+        }));
+        ball.pbr.material = materials.add(Color::rgb(0.9, 0.8, 0.2).into());
+        entities.0.push(
+            commands
+                .spawn((
+                    Pollen,
+                    Focusable {
+                        radius: radius * FOCUS_DISTANCE_FACTOR,
+                    },
+                    ball,
+                    ColliderMassProperties::Mass(ball_mass(radius)),
+                ))
+                .id(),
+        );
+
+        *tracker = BrownianTracker {
+            trajectory_mesh: meshes.add(trajectory_mesh(&[])),
+            ..default()
+        };
+        entities.0.push(
+            commands
+                .spawn((
+                    Trajectory,
+                    PbrBundle {
+                        mesh: tracker.trajectory_mesh.clone(),
+                        material: materials.add(StandardMaterial {
+                            base_color: Color::rgb(0.9, 0.8, 0.2),
+                            unlit: true,
+                            ..default()
+                        }),
+                        ..default()
+                    },
+                ))
+                .id(),
+        );
+    }
+}
+
+pub fn follow_pollen(
+    mut ui_events: EventReader<UiEvents>,
+    parameters: Res<ParticleMessParameters>,
+    pollen: Query<(Entity, &Transform), With<Pollen>>,
+    mut cameras: Query<&mut PanOrbitCamera, With<AppCamera>>,
+) {
+    for event in ui_events.iter() {
+        if !matches!(event, UiEvents::FollowPollen) {
+            continue;
+        }
+
+        if let (Ok((entity, transform)), Ok(mut camera)) =
+            (pollen.get_single(), cameras.get_single_mut())
+        {
+            let radius = parameters.particle_radius * POLLEN_RADIUS_FACTOR;
+            camera.focus_on(
+                transform.translation,
+                radius * FOCUS_DISTANCE_FACTOR,
+            );
+            camera.follow(Some(entity));
+        }
+    }
+}
+
+fn trajectory_mesh(positions: &[Vec3]) -> Mesh {
+    let mut mesh = Mesh::new(PrimitiveTopology::LineStrip);
+
+    let vertices: Vec<[f32; 3]> = positions
+        .iter()
+        .map(|position| position.to_array())
+        .collect();
+    let len = vertices.len();
+
+    mesh.insert_attribute(Mesh::ATTRIBUTE_POSITION, vertices);
+    mesh.insert_attribute(Mesh::ATTRIBUTE_NORMAL, vec![[0.0, 1.0, 0.0]; len]);
+    mesh.insert_attribute(Mesh::ATTRIBUTE_UV_0, vec![[0.0, 0.0]; len]);
+
+    mesh
+}
+
+pub fn track_pollen(
+    time: Res<Time>,
+    control: Res<SimulationControl>,
+    mut tracker: ResMut<BrownianTracker>,
+    mut meshes: ResMut<Assets<Mesh>>,
+    pollen: Query<&Transform, With<Pollen>>,
+) {
+    let position = if let Ok(transform) = pollen.get_single() {
+        transform.translation
+    } else {
+        return;
+    };
+
+    tracker.elapsed_secs += control.delta_seconds(&time);
+    if tracker.elapsed_secs < TRACK_PERIOD_SECS {
+        return;
+    }
+    tracker.elapsed_secs = 0.0;
+
+    if tracker.positions.len() == MAX_TRAJECTORY_POINTS {
+        tracker.positions.remove(0);
+    }
+    tracker.positions.push(position);
+
+    if let Some(mesh) = meshes.get_mut(&tracker.trajectory_mesh) {
+        *mesh = trajectory_mesh(&tracker.positions);
+    }
+
+    if tracker.positions.len() % MSD_UPDATE_SAMPLES == 0 {
+        tracker.msd = mean_squared_displacement(&tracker.positions);
+        tracker.diffusion_coefficient = diffusion_coefficient(&tracker.msd);
+    }
+}
+
+/// Mean squared displacement for lags up to a quarter of the trajectory,
+/// longer lags are averaged over too few intervals
+fn mean_squared_displacement(positions: &[Vec3]) -> Vec<[f64; 2]> {
+    (1..positions.len() / 4)
+        .map(|lag| {
+            let sum: f64 = positions
+                .iter()
+                .zip(positions[lag..].iter())
+                .map(|(a, b)| a.distance_squared(*b) as f64)
+                .sum();
+            let count = (positions.len() - lag) as f64;
+
+            [lag as f64 * TRACK_PERIOD_SECS as f64, sum / count]
+        })
+        .collect()
+}
+
+/// Least squares slope through the origin, in three dimensions the mean
+/// squared displacement grows as 6 D t
+fn diffusion_coefficient(msd: &[[f64; 2]]) -> Option<f64> {
+    let tt: f64 = msd.iter().map(|[t, _]| t * t).sum();
+    let tm: f64 = msd.iter().map(|[t, m]| t * m).sum();
+
+    (tt > 0.0).then(|| tm / tt / 6.0)
+}
+
+pub fn show_brownian_tracker(
+    ui: &mut egui::Ui,
+    tracker: &BrownianTracker,
+    ui_events: &mut EventWriter<UiEvents>,
+) {
+    ui.horizontal(|ui| {
+        ui.label("brownian motion:");
+        if ui
+            .button("Add pollen")
+            .on_hover_text("a large particle pushed around by the small ones")
+            .clicked()
+        {
+            ui_events.send(UiEvents::AddPollen);
+        }
+        if ui
+            .add_enabled(
+                !tracker.positions.is_empty(),
+                egui::Button::new("Follow"),
+            )
+            .on_hover_text("keeps the camera on the pollen, panning stops")
+            .clicked()
+        {
+            ui_events.send(UiEvents::FollowPollen);
+        }
+    });
+
+    if tracker.positions.is_empty() {
+        return;
+    }
+
+    if let Some(diffusion_coefficient) = tracker.diffusion_coefficient {
+        ui.label(format!(
+            "diffusion coefficient: {:.3e}",
+            diffusion_coefficient
+        ));
+    }
+
+    Plot::new("mean_squared_displacement")
+        .height(140.0)
+        .allow_drag(false)
+        .allow_zoom(false)
+        .include_x(0.0)
+        .include_y(0.0)
+        .legend(Legend::default())
+        .show(ui, |plot_ui| {
+            plot_ui.line(
+                Line::new(PlotPoints::from(tracker.msd.clone()))
+                    .name("mean squared displacement"),
+            );
+        });
+}
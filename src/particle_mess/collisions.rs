@@ -0,0 +1,131 @@
+use std::f64::consts::PI;
+
+use bevy::prelude::*;
+use bevy_egui::egui;
+use bevy_rapier3d::prelude::*;
+
+use super::species::SpeciesIndex;
+use super::{Particle, ParticleMessParameters};
+use crate::simulation_control::SimulationControl;
+
+/// Time over which the collisions are counted
+const COUNTING_PERIOD_SECS: f32 = 1.0;
+
+/// Collision rates and the mean free path, measured over the last second
+#[derive(Default, Resource)]
+pub struct CollisionStatistics {
+    elapsed_secs: f32,
+    particle_collisions: usize,
+    wall_collisions: usize,
+
+    /// particle-particle collisions per second
+    particle_rate: f64,
+    /// particle-wall collisions per second
+    wall_rate: f64,
+    /// time a particle travels between two collisions with other particles
+    mean_free_time: Option<f64>,
+    mean_free_path: Option<f64>,
+    /// mean free path of an ideal gas of hard spheres with the same density
+    expected_mean_free_path: Option<f64>,
+}
+
+pub fn count_collisions(
+    time: Res<Time>,
+    control: Res<SimulationControl>,
+    parameters: Res<ParticleMessParameters>,
+    mut statistics: ResMut<CollisionStatistics>,
+    mut collision_events: EventReader<CollisionEvent>,
+    particles: Query<(&Velocity, &SpeciesIndex), With<Particle>>,
+) {
+    for event in collision_events.iter() {
+        let (a, b) = if let CollisionEvent::Started(a, b, _) = event {
+            (*a, *b)
+        } else {
+            continue;
+        };
+
+        // the container, the piston and the pollen count as walls
+        match (particles.contains(a), particles.contains(b)) {
+            (true, true) => statistics.particle_collisions += 1,
+            (true, false) | (false, true) => statistics.wall_collisions += 1,
+            (false, false) => {}
+        }
+    }
+
+    statistics.elapsed_secs += control.delta_seconds(&time);
+    if statistics.elapsed_secs < COUNTING_PERIOD_SECS {
+        return;
+    }
+
+    let elapsed_secs = statistics.elapsed_secs as f64;
+    statistics.particle_rate =
+        statistics.particle_collisions as f64 / elapsed_secs;
+    statistics.wall_rate = statistics.wall_collisions as f64 / elapsed_secs;
+    statistics.elapsed_secs = 0.0;
+    statistics.particle_collisions = 0;
+    statistics.wall_collisions = 0;
+
+    if particles.is_empty() {
+        *statistics = CollisionStatistics::default();
+        return;
+    }
+    let n = particles.iter().len() as f64;
+
+    let mean_speed = particles
+        .iter()
+        .map(|(velocity, _)| velocity.linvel.length() as f64)
+        .sum::<f64>()
+        / n;
+
+    // every collision ends the free flight of two particles
+    statistics.mean_free_time = (statistics.particle_rate > 0.0)
+        .then(|| n / (2.0 * statistics.particle_rate));
+    statistics.mean_free_path = statistics
+        .mean_free_time
+        .map(|mean_free_time| mean_speed * mean_free_time);
+
+    // λ = 1 / (√2 n π d²) with the mean diameter of the particles
+    let mean_diameter = particles
+        .iter()
+        .map(|(_, species_index)| {
+            let radius = parameters
+                .species
+                .get(species_index.0)
+                .map_or(parameters.particle_radius, |species| species.radius);
+            2.0 * radius as f64
+        })
+        .sum::<f64>()
+        / n;
+    let volume = 8.0
+        * (parameters.dimx * parameters.piston_position) as f64
+        * parameters.dimy as f64
+        * parameters.dimz as f64;
+    let density = n / volume;
+    statistics.expected_mean_free_path = (density > 0.0).then(|| {
+        1.0 / (2.0_f64.sqrt() * density * PI * mean_diameter * mean_diameter)
+    });
+}
+
+pub fn show_collision_statistics(
+    ui: &mut egui::Ui,
+    statistics: &CollisionStatistics,
+) {
+    let format_option = |value: Option<f64>| {
+        value.map_or(String::from("-"), |value| format!("{:.4}", value))
+    };
+
+    ui.label(format!(
+        "collisions per second, particle: {:.0}, wall: {:.0}",
+        statistics.particle_rate, statistics.wall_rate
+    ));
+    ui.label(format!(
+        "mean free time: {}",
+        format_option(statistics.mean_free_time)
+    ));
+    ui.label(format!(
+        "mean free path: {}, ideal gas: {}",
+        format_option(statistics.mean_free_path),
+        format_option(statistics.expected_mean_free_path)
+    ))
+    .on_hover_text("the ideal gas value assumes hard spheres of the mean size");
+}
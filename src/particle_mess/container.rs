@@ -0,0 +1,84 @@
+use bevy::prelude::*;
+use bevy_rapier3d::prelude::*;
+
+use super::{Entities, ParticleMessParameters};
+use crate::objects_3d::ContainerBundle;
+
+/// Thickness of the piston relative to the container width
+const PISTON_THICKNESS: f32 = 0.02;
+
+/// Wall moving along x inside the container, the particles are kept between
+/// it and the near wall
+#[derive(Component)]
+pub struct Piston;
+
+/// Spawns the container the particles are spawned in, its inside spans from
+/// the origin to twice the dimensions
+pub fn spawn_container(
+    commands: &mut Commands,
+    meshes: &mut Assets<Mesh>,
+    materials: &mut Assets<StandardMaterial>,
+    parameters: &ParticleMessParameters,
+    entities: &mut Entities,
+) {
+    let mut container = ContainerBundle::new_from_xyz(
+        parameters.dimx,
+        parameters.dimy,
+        parameters.dimz,
+        meshes,
+    );
+    container.pbr.material = materials.add(StandardMaterial {
+        base_color: Color::rgba(0.6, 0.6, 0.8, 0.1),
+        alpha_mode: AlphaMode::Blend,
+        cull_mode: None,
+        ..default()
+    });
+    entities.0.push(commands.spawn(container).id());
+
+    let thickness = parameters.dimx * 2.0 * PISTON_THICKNESS;
+    let piston = commands.spawn((
+        Piston,
+        PbrBundle {
+            mesh: meshes.add(Mesh::from(shape::Box::new(
+                thickness,
+                parameters.dimy * 2.0,
+                parameters.dimz * 2.0,
+            ))),
+            material: materials.add(StandardMaterial {
+                base_color: Color::rgba(0.8, 0.8, 0.8, 0.6),
+                alpha_mode: AlphaMode::Blend,
+                ..default()
+            }),
+            transform: piston_transform(parameters),
+            ..default()
+        },
+        RigidBody::KinematicPositionBased,
+        Collider::cuboid(thickness / 2.0, parameters.dimy, parameters.dimz),
+    ));
+    entities.0.push(piston.id());
+}
+
+/// The face of the piston is at the piston position, a fraction of the
+/// container width
+fn piston_transform(parameters: &ParticleMessParameters) -> Transform {
+    let width = parameters.dimx * 2.0;
+    let thickness = width * PISTON_THICKNESS;
+
+    Transform::from_xyz(
+        width * parameters.piston_position + thickness / 2.0,
+        parameters.dimy,
+        parameters.dimz,
+    )
+}
+
+pub fn update_piston(
+    parameters: Res<ParticleMessParameters>,
+    mut pistons: Query<&mut Transform, With<Piston>>,
+) {
+    for mut transform in pistons.iter_mut() {
+        let target = piston_transform(&parameters);
+        if *transform != target {
+            *transform = target;
+        }
+    }
+}
@@ -0,0 +1,135 @@
+use bevy::prelude::*;
+use bevy_egui::egui;
+use bevy_egui::egui::plot::{Bar, BarChart, Legend, Line, Plot, PlotPoints};
+use bevy_rapier3d::prelude::*;
+
+use super::{GravityMode, Particle, ParticleMessParameters};
+use crate::simulation_control::SimulationControl;
+
+const PROFILE_BINS: usize = 20;
+
+/// Time between two profiles
+const PROFILE_PERIOD_SECS: f32 = 1.0;
+
+/// Distribution of the particle heights in the container under uniform
+/// gravity, rebuilt every second
+#[derive(Default, Resource)]
+pub struct DensityProfile {
+    elapsed_secs: f32,
+    /// `[bin center height, probability density]`
+    bins: Vec<[f64; 2]>,
+    bin_height: f64,
+    /// `[height, probability density]` of the barometric formula
+    curve: Vec<[f64; 2]>,
+    /// height over which the density drops by a factor of e
+    scale_height: Option<f64>,
+}
+
+pub fn update_density_profile(
+    time: Res<Time>,
+    control: Res<SimulationControl>,
+    parameters: Res<ParticleMessParameters>,
+    mut profile: ResMut<DensityProfile>,
+    particles: Query<(&Transform, &Velocity), With<Particle>>,
+) {
+    profile.elapsed_secs += control.delta_seconds(&time);
+    if profile.elapsed_secs < PROFILE_PERIOD_SECS {
+        return;
+    }
+    profile.elapsed_secs = 0.0;
+
+    let height = parameters.dimy as f64 * 2.0;
+    if parameters.gravity_mode != GravityMode::Uniform
+        || particles.is_empty()
+        || height <= 0.0
+    {
+        *profile = DensityProfile::default();
+        return;
+    }
+
+    let bin_height = height / PROFILE_BINS as f64;
+    let mut counts = vec![0usize; PROFILE_BINS];
+    for (transform, _) in particles.iter() {
+        let y = (transform.translation.y as f64).clamp(0.0, height);
+        let bin = ((y / bin_height) as usize).min(PROFILE_BINS - 1);
+        counts[bin] += 1;
+    }
+
+    let n = particles.iter().len() as f64;
+    profile.bin_height = bin_height;
+    profile.bins = counts
+        .iter()
+        .enumerate()
+        .map(|(i, count)| {
+            [
+                (i as f64 + 0.5) * bin_height,
+                *count as f64 / (n * bin_height),
+            ]
+        })
+        .collect();
+
+    // k T / m follows from the mean kinetic energy, m <v²> / 2 = 3 k T / 2,
+    // and the density falls off as exp(-m g y / k T)
+    let mean_squared_speed = particles
+        .iter()
+        .map(|(_, velocity)| velocity.linvel.length_squared() as f64)
+        .sum::<f64>()
+        / n;
+    let g = parameters.gravity_acceleration as f64;
+    profile.scale_height = (g > 0.0).then(|| mean_squared_speed / (3.0 * g));
+    profile.curve = profile.scale_height.map_or(Vec::new(), |scale_height| {
+        barometric_formula(scale_height, height)
+    });
+}
+
+/// Height distribution of an ideal gas with the given scale height in a
+/// container of the given height
+fn barometric_formula(scale_height: f64, height: f64) -> Vec<[f64; 2]> {
+    if scale_height <= 0.0 {
+        return Vec::new();
+    }
+
+    let normalization =
+        1.0 / (scale_height * (1.0 - (-height / scale_height).exp()));
+
+    (0..=100)
+        .map(|i| {
+            let y = height * i as f64 / 100.0;
+            [y, normalization * (-y / scale_height).exp()]
+        })
+        .collect()
+}
+
+pub fn show_density_profile(ui: &mut egui::Ui, profile: &DensityProfile) {
+    ui.label(format!(
+        "density over height, scale height: {}",
+        profile
+            .scale_height
+            .map_or(String::from("-"), |h| format!("{:.4}", h))
+    ));
+
+    Plot::new("density_profile")
+        .height(160.0)
+        .allow_drag(false)
+        .allow_zoom(false)
+        .include_y(0.0)
+        .legend(Legend::default())
+        .show(ui, |plot_ui| {
+            plot_ui.bar_chart(
+                BarChart::new(
+                    profile
+                        .bins
+                        .iter()
+                        .map(|[y, density]| {
+                            Bar::new(*y, *density).width(profile.bin_height)
+                        })
+                        .collect(),
+                )
+                .name("measured"),
+            );
+            plot_ui.line(
+                Line::new(PlotPoints::from(profile.curve.clone()))
+                    .name("barometric formula"),
+            );
+        });
+}
@@ -0,0 +1,128 @@
+use std::f64::consts::PI;
+
+use bevy::prelude::*;
+use bevy_egui::egui;
+use bevy_egui::egui::plot::{Bar, BarChart, Legend, Line, Plot, PlotPoints};
+use bevy_rapier3d::prelude::*;
+
+use super::Particle;
+use crate::simulation_control::SimulationControl;
+
+const HISTOGRAM_BINS: usize = 30;
+
+/// Time between two histograms
+const HISTOGRAM_PERIOD_SECS: f32 = 1.0;
+
+/// Distribution of the particle speeds, rebuilt every second
+#[derive(Default, Resource)]
+pub struct SpeedHistogram {
+    elapsed_secs: f32,
+    /// `[bin center, probability density]`
+    bins: Vec<[f64; 2]>,
+    bin_width: f64,
+    /// `[speed, probability density]` of the maxwell boltzmann distribution
+    curve: Vec<[f64; 2]>,
+    mean_speed: f64,
+}
+
+pub fn update_speed_histogram(
+    time: Res<Time>,
+    control: Res<SimulationControl>,
+    mut histogram: ResMut<SpeedHistogram>,
+    particles: Query<&Velocity, With<Particle>>,
+) {
+    histogram.elapsed_secs += control.delta_seconds(&time);
+    if histogram.elapsed_secs < HISTOGRAM_PERIOD_SECS {
+        return;
+    }
+    histogram.elapsed_secs = 0.0;
+
+    let speeds: Vec<f64> = particles
+        .iter()
+        .map(|velocity| velocity.linvel.length() as f64)
+        .collect();
+
+    let max_speed = speeds.iter().copied().fold(0.0, f64::max);
+    if speeds.is_empty() || max_speed <= 0.0 {
+        *histogram = SpeedHistogram::default();
+        return;
+    }
+
+    let bin_width = max_speed / HISTOGRAM_BINS as f64;
+    let mut counts = vec![0usize; HISTOGRAM_BINS];
+    for speed in speeds.iter() {
+        let bin = ((speed / bin_width) as usize).min(HISTOGRAM_BINS - 1);
+        counts[bin] += 1;
+    }
+
+    let n = speeds.len() as f64;
+    histogram.bin_width = bin_width;
+    histogram.bins = counts
+        .iter()
+        .enumerate()
+        .map(|(i, count)| {
+            [
+                (i as f64 + 0.5) * bin_width,
+                *count as f64 / (n * bin_width),
+            ]
+        })
+        .collect();
+    histogram.mean_speed = speeds.iter().sum::<f64>() / n;
+
+    // the temperature follows from the mean kinetic energy,
+    // m <v²> / 2 = 3 k T / 2
+    let mean_squared_speed = speeds.iter().map(|v| v * v).sum::<f64>() / n;
+    histogram.curve = maxwell_boltzmann(mean_squared_speed, max_speed);
+}
+
+/// Speed distribution of an ideal gas with the given mean squared speed,
+/// sampled from zero to `max_speed`
+fn maxwell_boltzmann(mean_squared_speed: f64, max_speed: f64) -> Vec<[f64; 2]> {
+    // a² = k T / m
+    let a2 = mean_squared_speed / 3.0;
+    if a2 <= 0.0 {
+        return Vec::new();
+    }
+
+    let normalization = (2.0 / PI).sqrt() / a2.powf(1.5);
+
+    (0..=100)
+        .map(|i| {
+            let v = max_speed * i as f64 / 100.0;
+            [v, normalization * v * v * (-v * v / (2.0 * a2)).exp()]
+        })
+        .collect()
+}
+
+pub fn show_speed_histogram(ui: &mut egui::Ui, histogram: &SpeedHistogram) {
+    ui.label(format!(
+        "speed distribution, mean speed: {:.4}",
+        histogram.mean_speed
+    ));
+
+    Plot::new("speed_histogram")
+        .height(160.0)
+        .allow_drag(false)
+        .allow_zoom(false)
+        .include_y(0.0)
+        .legend(Legend::default())
+        .show(ui, |plot_ui| {
+            plot_ui.bar_chart(
+                BarChart::new(
+                    histogram
+                        .bins
+                        .iter()
+                        .map(|[speed, density]| {
+                            Bar::new(*speed, *density)
+                                .width(histogram.bin_width)
+                        })
+                        .collect(),
+                )
+                .name("measured"),
+            );
+            plot_ui.line(
+                Line::new(PlotPoints::from(histogram.curve.clone()))
+                    .name("maxwell boltzmann"),
+            );
+        });
+}
@@ -0,0 +1,101 @@
+use bevy::prelude::*;
+use bevy::utils::HashMap;
+use bevy_rapier3d::prelude::*;
+
+use super::species::SpeciesIndex;
+use super::{Particle, ParticleMessParameters};
+
+/// The potential is evaluated no closer than this fraction of sigma, so
+/// overlapping particles aren't shot apart
+const MIN_DISTANCE_FACTOR: f32 = 0.8;
+
+/// Adds a pairwise lennard jones force, repulsive at contact and attractive
+/// further out, so cold particles condense into clusters.
+///
+/// Sigma is chosen so the potential has its minimum where two particles
+/// touch, pairs further apart than the cutoff times sigma don't interact.
+pub fn apply_lennard_jones(
+    parameters: Res<ParticleMessParameters>,
+    mut particles: Query<
+        (&Transform, &SpeciesIndex, &mut ExternalForce),
+        With<Particle>,
+    >,
+) {
+    if !parameters.lennard_jones {
+        return;
+    }
+
+    let bodies: Vec<(Vec3, f32)> = particles
+        .iter()
+        .map(|(transform, species_index, _)| {
+            let radius = parameters
+                .species
+                .get(species_index.0)
+                .map_or(parameters.particle_radius, |species| species.radius);
+            (transform.translation, radius)
+        })
+        .collect();
+
+    let max_radius = bodies.iter().map(|(_, r)| *r).fold(0.0, f32::max);
+    let sigma_factor = 2.0_f32.powf(-1.0 / 6.0);
+    let cell_size = 2.0 * max_radius * sigma_factor * parameters.lj_cutoff;
+    if cell_size <= 0.0 {
+        return;
+    }
+
+    // uniform grid as large as the largest cutoff, so only particles in the
+    // same or an adjacent cell can interact
+    let cell_of = |position: Vec3| (position / cell_size).floor().as_ivec3();
+    let mut cells: HashMap<IVec3, Vec<usize>> = HashMap::default();
+    for (i, (position, _)) in bodies.iter().enumerate() {
+        cells.entry(cell_of(*position)).or_default().push(i);
+    }
+
+    let mut forces = vec![Vec3::ZERO; bodies.len()];
+    for (i, (position, radius)) in bodies.iter().enumerate() {
+        let cell = cell_of(*position);
+
+        for offset in (-1..=1).flat_map(|x| {
+            (-1..=1)
+                .flat_map(move |y| (-1..=1).map(move |z| IVec3::new(x, y, z)))
+        }) {
+            let candidates =
+                if let Some(candidates) = cells.get(&(cell + offset)) {
+                    candidates
+                } else {
+                    continue;
+                };
+
+            for &j in candidates {
+                // every pair once
+                if j <= i {
+                    continue;
+                }
+
+                let (other_position, other_radius) = bodies[j];
+                let sigma = (radius + other_radius) * sigma_factor;
+                let delta = other_position - *position;
+                let distance = delta.length();
+                if distance <= 0.0 || distance > sigma * parameters.lj_cutoff {
+                    continue;
+                }
+
+                let r = distance.max(sigma * MIN_DISTANCE_FACTOR);
+                let s6 = (sigma / r).powi(6);
+                // positive pushes the pair apart
+                let magnitude =
+                    24.0 * parameters.lj_epsilon / r * (2.0 * s6 * s6 - s6);
+                let force = delta / distance * magnitude;
+
+                forces[i] -= force;
+                forces[j] += force;
+            }
+        }
+    }
+
+    for ((.., mut external_force), force) in
+        particles.iter_mut().zip(forces.into_iter())
+    {
+        external_force.force += force;
+    }
+}
@@ -0,0 +1,951 @@
+use std::f32::consts::PI;
+
+use bevy::ecs::system::SystemState;
+use bevy::prelude::*;
+use bevy::time::Stopwatch;
+use bevy_egui::egui;
+use bevy_rapier3d::prelude::*;
+use rand::rngs::StdRng;
+use rand::Rng;
+use serde::{Deserialize, Serialize};
+
+use crate::hover::Hoverable;
+use crate::instancing::PointSprite;
+use crate::level_of_detail::LevelOfDetail;
+use crate::objects_3d::BallBundle;
+use crate::pan_orbit_camera::{
+    focus_on_double_click, update_pan_orbit_camera, Focusable, PanOrbitCamera,
+};
+use crate::persistence::{PersistenceAppExt, RestoreParameters};
+use crate::recording::{
+    RecordableEvent, RecordableParameters, RecordingAppExt, SimulationRng,
+};
+use crate::simulation::Simulation;
+use crate::simulation_control::{
+    forward_reset, ResetEvent, SimulationClock, SimulationControl,
+};
+use crate::snapshot::{load_snapshot, save_snapshot, BodyState};
+use crate::ui::{show_tunables, Tunable, TunableParameter};
+use crate::{AppCamera, AppState};
+
+mod brownian;
+mod collisions;
+mod container;
+mod density_profile;
+mod histogram;
+mod interactions;
+mod species;
+
+use brownian::{
+    follow_pollen, on_pollen_events, show_brownian_tracker, track_pollen,
+    BrownianTracker,
+};
+use collisions::{
+    count_collisions, show_collision_statistics, CollisionStatistics,
+};
+use container::{spawn_container, update_piston};
+use density_profile::{
+    show_density_profile, update_density_profile, DensityProfile,
+};
+use histogram::{show_speed_histogram, update_speed_histogram, SpeedHistogram};
+use interactions::apply_lennard_jones;
+use species::{
+    build_species_assets, pick_species, show_species, update_species_assets,
+    Species, SpeciesIndex,
+};
+
+/// Distance of the camera to a double clicked particle relative to its
+/// radius
+const FOCUS_DISTANCE_FACTOR: f32 = 30.0;
+
+#[derive(Default, Resource)]
+struct Entities(Vec<Entity>);
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub enum GravityMode {
+    /// constant force toward the origin marker
+    Central,
+    /// downward acceleration, heavier particles settle lower
+    Uniform,
+    None,
+}
+
+impl GravityMode {
+    pub const ALL: [GravityMode; 3] = [
+        GravityMode::Central,
+        GravityMode::Uniform,
+        GravityMode::None,
+    ];
+}
+
+impl From<GravityMode> for String {
+    fn from(value: GravityMode) -> Self {
+        match value {
+            GravityMode::Central => "central".to_string(),
+            GravityMode::Uniform => "uniform".to_string(),
+            GravityMode::None => "none".to_string(),
+        }
+    }
+}
+
+#[derive(Clone, Resource, Serialize, Deserialize)]
+#[serde(default)]
+pub struct ParticleMessParameters {
+    dimx: f32,
+    dimy: f32,
+    dimz: f32,
+    #[serde(skip)]
+    origin: Vec3,
+    particle_radius: f32,
+    restitution_coefficient: f32,
+    number_of_particles: usize,
+    species: Vec<Species>,
+
+    #[serde(skip)]
+    particle_mesh: Handle<Mesh>,
+    #[serde(skip)]
+    default_particle_material: Handle<StandardMaterial>,
+    #[serde(skip)]
+    marked_particle_material: Handle<StandardMaterial>,
+
+    spawn_particles: bool,
+    spawn_frequency_hz: f32,
+    spawn_particles_num: usize,
+    max_entities: usize,
+    gravity_mode: GravityMode,
+    gravitation_on_particle: f32,
+    /// acceleration of the uniform gravity
+    gravity_acceleration: f32,
+    heat: f32,
+    energy_conservation_factor: f32,
+    /// position of the piston as fraction of the container width
+    piston_position: f32,
+    lennard_jones: bool,
+    /// depth of the potential well
+    lj_epsilon: f32,
+    /// interaction range as multiple of sigma
+    lj_cutoff: f32,
+    /// draws the particles as camera facing discs instead of spheres
+    point_sprites: bool,
+}
+
+impl Default for ParticleMessParameters {
+    fn default() -> Self {
+        let origin = Vec3::new(1.0, 0.2, 1.0);
+        Self {
+            dimx: origin.x,
+            dimy: origin.y,
+            dimz: origin.z,
+            origin,
+            particle_radius: 0.01,
+            number_of_particles: 0,
+            species: vec![Species::default()],
+
+            particle_mesh: Handle::<Mesh>::default(),
+            default_particle_material: Handle::<StandardMaterial>::default(),
+            marked_particle_material: Handle::<StandardMaterial>::default(),
+
+            max_entities: 1000,
+            spawn_particles: false,
+            spawn_frequency_hz: 20.0,
+            spawn_particles_num: 1,
+            restitution_coefficient: 0.5,
+            gravity_mode: GravityMode::Central,
+            gravitation_on_particle: 0.0,
+            gravity_acceleration: 0.5,
+            heat: 0.0,
+            energy_conservation_factor: 1.0,
+            piston_position: 1.0,
+            lennard_jones: false,
+            lj_epsilon: 1e-8,
+            lj_cutoff: 2.5,
+            point_sprites: false,
+        }
+    }
+}
+
+impl RecordableParameters for ParticleMessParameters {
+    const KIND: &'static str = "particle_mess_parameters";
+    const SIMULATION: AppState = ParticleMessPlugin::STATE;
+
+    fn restore(&mut self, recorded: Self) {
+        // the handles belong to this session
+        *self = Self {
+            particle_mesh: self.particle_mesh.clone(),
+            default_particle_material: self.default_particle_material.clone(),
+            marked_particle_material: self.marked_particle_material.clone(),
+            number_of_particles: self.number_of_particles,
+            ..recorded
+        };
+    }
+}
+
+#[derive(Default, Resource)]
+struct ParticleMessStopwatch(Stopwatch);
+
+#[derive(Default, Component)]
+struct Particle;
+
+pub struct ParticleMessPlugin;
+
+impl Plugin for ParticleMessPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_event::<UiEvents>()
+            .add_recordable_event::<UiEvents>()
+            .add_recordable_parameters::<ParticleMessParameters>()
+            .add_persistent_parameters::<ParticleMessParameters>()
+            .insert_resource(Entities::default())
+            .insert_resource(ParticleMessParameters::default())
+            .insert_resource(ParticleMessStopwatch::default())
+            .insert_resource(SpeedHistogram::default())
+            .insert_resource(BrownianTracker::default())
+            .insert_resource(CollisionStatistics::default())
+            .insert_resource(DensityProfile::default())
+            .add_system_set(
+                SystemSet::on_enter(ParticleMessPlugin::STATE)
+                    .with_system(setup.after(RestoreParameters)),
+            )
+            .add_system_set(
+                SystemSet::on_update(ParticleMessPlugin::STATE)
+                    .with_system(update_pan_orbit_camera)
+                    .with_system(focus_on_double_click)
+                    .with_system(follow_pollen)
+                    .with_system(update_species_assets)
+                    .with_system(update.after(update_species_assets))
+                    .with_system(update_global_parameters)
+                    .with_system(update_point_sprites)
+                    .with_system(apply_gravity)
+                    .with_system(apply_lennard_jones.after(apply_gravity))
+                    .with_system(apply_heat)
+                    .with_system(update_speed_histogram)
+                    .with_system(count_collisions)
+                    .with_system(update_density_profile)
+                    .with_system(update_piston)
+                    .with_system(on_pollen_events)
+                    .with_system(track_pollen)
+                    .with_system(on_ui_events)
+                    .with_system(on_snapshot_events)
+                    .with_system(forward_reset::<UiEvents>),
+            )
+            .add_system_set(
+                SystemSet::on_exit(ParticleMessPlugin::STATE)
+                    .with_system(cleanup),
+            );
+    }
+}
+
+#[allow(clippy::too_many_arguments)]
+fn setup(
+    mut commands: Commands,
+    cameras: Query<Entity, With<AppCamera>>,
+    mut meshes: ResMut<Assets<Mesh>>,
+    mut materials: ResMut<Assets<StandardMaterial>>,
+    mut parameters: ResMut<ParticleMessParameters>,
+    mut entities: ResMut<Entities>,
+    mut rapier_debug_config: ResMut<DebugRenderContext>,
+    mut rapier_config: ResMut<RapierConfiguration>,
+) {
+    rapier_debug_config.enabled = false;
+    rapier_config.gravity = Vec3::ZERO;
+    rapier_config.scaled_shape_subdivision = 1;
+
+    if let Ok(camera_entity) = cameras.get_single() {
+        commands.entity(camera_entity).despawn();
+    }
+
+    // mesh
+    parameters.particle_mesh = meshes.add(Mesh::from(shape::Icosphere {
+        radius: parameters.particle_radius,
+        subdivisions: 6,
+    }));
+
+    // materials
+    parameters.default_particle_material =
+        materials.add(Color::rgb(0.3, 0.1, 0.1).into());
+
+    parameters.marked_particle_material =
+        materials.add(Color::rgb(1.0, 0.0, 0.0).into());
+
+    // plane
+    entities.0.push(
+        commands
+            .spawn(PbrBundle {
+                mesh: meshes.add(Mesh::from(shape::Plane { size: 1000.0 })),
+                material: materials.add(Color::rgba(0.3, 0.3, 0.6, 0.8).into()),
+                transform: Transform::from_translation(Vec3::new(
+                    0.0, -4.0, 0.0,
+                )),
+                ..default()
+            })
+            .id(),
+    );
+
+    // container
+    spawn_container(
+        &mut commands,
+        &mut meshes,
+        &mut materials,
+        &parameters,
+        &mut entities,
+    );
+
+    // origin
+    entities.0.push(
+        commands
+            .spawn(PbrBundle {
+                mesh: parameters.particle_mesh.clone(),
+                material: parameters.marked_particle_material.clone(),
+                transform: Transform::from_translation(parameters.origin),
+                ..default()
+            })
+            .id(),
+    );
+
+    // directional 'sun' light
+    let sunlight = commands.spawn(DirectionalLightBundle {
+        directional_light: DirectionalLight {
+            shadows_enabled: true,
+            illuminance: 10000.0,
+            ..default()
+        },
+        transform: Transform {
+            translation: Vec3::new(0.0, 2.0, 0.0),
+            rotation: Quat::from_rotation_x(-PI / 4.0)
+                .mul_quat(Quat::from_rotation_y(PI / 4.0)),
+            ..default()
+        },
+        ..default()
+    });
+    entities.0.push(sunlight.id());
+
+    // camera
+    let translation =
+        Vec3::new(parameters.dimx, parameters.dimy * 0.1, parameters.dimz);
+    let radius = translation.length();
+
+    commands
+        .spawn((
+            AppCamera,
+            Camera3dBundle {
+                transform: Transform::from_translation(translation)
+                    .looking_at(Vec3::new(0.0, 0.0, 0.0), Vec3::Y),
+                ..default()
+            },
+        ))
+        .insert(PanOrbitCamera {
+            radius,
+            ..Default::default()
+        });
+}
+
+#[allow(clippy::too_many_arguments)]
+fn update(
+    time: Res<Time>,
+    control: Res<SimulationControl>,
+    mut clock: ResMut<SimulationClock>,
+    mut stopwatch: ResMut<ParticleMessStopwatch>,
+    mut commands: Commands,
+    parameters: Res<ParticleMessParameters>,
+    particles: Query<&SpeciesIndex, With<Particle>>,
+    mut entities: ResMut<Entities>,
+    mut rng: ResMut<SimulationRng>,
+) {
+    stopwatch.0.tick(control.delta(&time));
+    // rapier takes a single step per frame
+    if control.is_running() {
+        clock.advance(1, control.delta_seconds(&time));
+    }
+
+    let period = 1000.0 / parameters.spawn_frequency_hz;
+
+    if stopwatch.0.elapsed().as_millis() >= period as u128
+        && particles.iter().len() < parameters.max_entities
+        && parameters.spawn_particles
+    {
+        stopwatch.0.reset();
+
+        let mut counts = vec![0; parameters.species.len()];
+        for species_index in particles.iter() {
+            if let Some(count) = counts.get_mut(species_index.0) {
+                *count += 1;
+            }
+        }
+
+        for _ in 0..parameters
+            .spawn_particles_num
+            .min(parameters.max_entities - parameters.number_of_particles)
+        {
+            let i = if let Some(i) =
+                pick_species(&parameters.species, &counts, &mut rng.0)
+            {
+                i
+            } else {
+                break;
+            };
+            counts[i] += 1;
+
+            let particle = commands.spawn((
+                Particle,
+                SpeciesIndex(i),
+                Focusable {
+                    radius: parameters.species[i].radius
+                        * FOCUS_DISTANCE_FACTOR,
+                },
+                Hoverable {
+                    rest_position: None,
+                    radius: parameters.species[i].radius,
+                },
+                randomly_placed_particle(
+                    &parameters,
+                    &parameters.species[i],
+                    &mut rng.0,
+                ),
+            ));
+            entities.0.push(particle.id());
+        }
+    }
+}
+
+fn apply_gravity(
+    control: Res<SimulationControl>,
+    parameters: Res<ParticleMessParameters>,
+    mut rapier_config: ResMut<RapierConfiguration>,
+    mut particles: Query<
+        (&mut ExternalForce, &mut Velocity, &Transform),
+        With<Particle>,
+    >,
+) {
+    // uniform gravity is left to rapier, so it scales with the mass
+    let gravity = match parameters.gravity_mode {
+        GravityMode::Uniform => Vec3::NEG_Y * parameters.gravity_acceleration,
+        GravityMode::Central | GravityMode::None => Vec3::ZERO,
+    };
+    if rapier_config.gravity != gravity {
+        rapier_config.gravity = gravity;
+    }
+
+    for (mut particle, mut velocity, transform) in particles.iter_mut() {
+        if control.is_running() {
+            velocity.linvel *= parameters.energy_conservation_factor;
+        }
+
+        particle.force = match parameters.gravity_mode {
+            GravityMode::Central => {
+                (parameters.origin - transform.translation).normalize()
+                    * parameters.gravitation_on_particle
+            }
+            GravityMode::Uniform | GravityMode::None => Vec3::ZERO,
+        };
+    }
+}
+
+fn apply_heat(
+    control: Res<SimulationControl>,
+    parameters: Res<ParticleMessParameters>,
+    mut particles: Query<&mut ExternalImpulse, With<Particle>>,
+    mut rng: ResMut<SimulationRng>,
+) {
+    let dimx = parameters.particle_radius * (parameters.heat / 1000.0);
+    let dimy = parameters.particle_radius * (parameters.heat / 1000.0);
+    let dimz = parameters.particle_radius * (parameters.heat / 1000.0);
+
+    for mut particle in particles.iter_mut() {
+        // impulses change the velocities even while rapier is paused
+        if parameters.heat > 0.0 && control.is_running() {
+            let x: f32 = rng.0.gen_range(-dimx..dimx);
+            let y: f32 = rng.0.gen_range(-dimy..dimy);
+            let z: f32 = rng.0.gen_range(-dimz..dimz);
+
+            particle.impulse = Vec3::new(x, y, z);
+        } else {
+            particle.impulse = Vec3::splat(0.0);
+        }
+    }
+}
+
+fn update_global_parameters(
+    mut parameters: ResMut<ParticleMessParameters>,
+    particles: Query<&Particle>,
+) {
+    parameters.number_of_particles = particles.iter().len();
+}
+
+/// Swaps the meshes of the particles for point sprites and back, sprites
+/// keep the radius of the species the particle was spawned with
+fn update_point_sprites(
+    mut commands: Commands,
+    parameters: Res<ParticleMessParameters>,
+    particles: Query<
+        (Entity, &Hoverable, Option<&PointSprite>),
+        With<Particle>,
+    >,
+) {
+    for (entity, hoverable, sprite) in particles.iter() {
+        match (parameters.point_sprites, sprite) {
+            (true, None) => {
+                commands.entity(entity).insert(PointSprite {
+                    radius: hoverable.radius,
+                });
+            }
+            (false, Some(_)) => {
+                commands.entity(entity).remove::<PointSprite>();
+            }
+            _ => {}
+        }
+    }
+}
+
+fn cleanup(
+    mut commands: Commands,
+    mut entities: ResMut<Entities>,
+    mut rapier_debug_config: ResMut<DebugRenderContext>,
+    mut rapier_config: ResMut<RapierConfiguration>,
+) {
+    for entity in entities.0.drain(..) {
+        if let Some(mut entity) = commands.get_entity(entity) {
+            entity.despawn();
+        }
+    }
+
+    *rapier_debug_config = DebugRenderContext::default();
+    *rapier_config = RapierConfiguration::default();
+}
+
+fn randomly_placed_particle(
+    parameters: &ParticleMessParameters,
+    species: &Species,
+    rng: &mut StdRng,
+) -> (
+    BallBundle,
+    ColliderMassProperties,
+    ActiveEvents,
+    LevelOfDetail,
+) {
+    // in front of the piston
+    let x: f32 = rng
+        .gen_range(0.001..parameters.dimx * 1.99 * parameters.piston_position);
+    let y: f32 = rng.gen_range(0.001..parameters.dimy * 1.99);
+    let z: f32 = rng.gen_range(0.001..parameters.dimz * 1.99);
+
+    species_particle(parameters, species, Transform::from_xyz(x, y, z))
+}
+
+fn species_particle(
+    parameters: &ParticleMessParameters,
+    species: &Species,
+    transform: Transform,
+) -> (
+    BallBundle,
+    ColliderMassProperties,
+    ActiveEvents,
+    LevelOfDetail,
+) {
+    let mut particle = BallBundle::new_from_xyz(0.0, 0.0, 0.0, species.radius);
+
+    particle.restitution =
+        Restitution::coefficient(parameters.restitution_coefficient);
+
+    particle.pbr.transform = transform;
+    particle.pbr.mesh = species.mesh.clone();
+    particle.pbr.material = species.material.clone();
+
+    (
+        particle,
+        ColliderMassProperties::Mass(species.mass),
+        // counted by the collision statistics
+        ActiveEvents::COLLISION_EVENTS,
+        LevelOfDetail {
+            radius: species.radius,
+        },
+    )
+}
+
+fn on_ui_events(
+    mut commands: Commands,
+    mut ui_events: EventReader<UiEvents>,
+    mut parameters: ResMut<ParticleMessParameters>,
+    mut stopwatch: ResMut<ParticleMessStopwatch>,
+    particles: Query<Entity, With<Particle>>,
+) {
+    for event in ui_events.iter() {
+        match event {
+            UiEvents::StartStop => {
+                parameters.spawn_particles = !parameters.spawn_particles;
+                stopwatch.0.reset();
+            }
+            UiEvents::Reset | UiEvents::ClearParticles => {
+                for entity in particles.iter() {
+                    commands.entity(entity).despawn();
+                }
+                stopwatch.0.reset();
+            }
+            _ => {}
+        }
+    }
+}
+
+/// State of the particles written to and read from snapshot files
+#[derive(Serialize, Deserialize)]
+struct ParticleMessSnapshot {
+    parameters: ParticleMessParameters,
+    particles: Vec<BodyState>,
+    /// species index of every particle
+    #[serde(default)]
+    species: Vec<usize>,
+}
+
+fn on_snapshot_events(
+    mut commands: Commands,
+    mut meshes: ResMut<Assets<Mesh>>,
+    mut materials: ResMut<Assets<StandardMaterial>>,
+    mut ui_events: EventReader<UiEvents>,
+    mut parameters: ResMut<ParticleMessParameters>,
+    mut entities: ResMut<Entities>,
+    particles: Query<
+        (Entity, &Transform, &Velocity, &SpeciesIndex),
+        With<Particle>,
+    >,
+) {
+    let name = ParticleMessPlugin::NAME.to_string();
+
+    for event in ui_events.iter() {
+        match event {
+            UiEvents::SaveSnapshot => {
+                let snapshot = ParticleMessSnapshot {
+                    parameters: parameters.clone(),
+                    particles: particles
+                        .iter()
+                        .map(|(_, transform, velocity, _)| {
+                            BodyState::new(transform, velocity)
+                        })
+                        .collect(),
+                    species: particles
+                        .iter()
+                        .map(|(.., species_index)| species_index.0)
+                        .collect(),
+                };
+
+                save_snapshot(&name, &snapshot);
+            }
+            UiEvents::LoadSnapshot => {
+                let snapshot: ParticleMessSnapshot =
+                    if let Some(snapshot) = load_snapshot(&name) {
+                        snapshot
+                    } else {
+                        continue;
+                    };
+
+                parameters.restore(snapshot.parameters);
+                if parameters.species.is_empty() {
+                    parameters.species.push(Species::default());
+                }
+                build_species_assets(
+                    &mut parameters.species,
+                    &mut meshes,
+                    &mut materials,
+                );
+
+                for (entity, ..) in particles.iter() {
+                    commands.entity(entity).despawn();
+                }
+
+                for (i, state) in snapshot.particles.iter().enumerate() {
+                    // snapshots without species only know the first one
+                    let species_index = snapshot
+                        .species
+                        .get(i)
+                        .copied()
+                        .unwrap_or(0)
+                        .min(parameters.species.len() - 1);
+
+                    let (mut particle, mass, events, lod) = species_particle(
+                        &parameters,
+                        &parameters.species[species_index],
+                        state.transform(),
+                    );
+                    particle.velocity = state.velocity();
+
+                    entities.0.push(
+                        commands
+                            .spawn((
+                                Particle,
+                                SpeciesIndex(species_index),
+                                Focusable {
+                                    radius: parameters.species[species_index]
+                                        .radius
+                                        * FOCUS_DISTANCE_FACTOR,
+                                },
+                                Hoverable {
+                                    rest_position: None,
+                                    radius: parameters.species[species_index]
+                                        .radius,
+                                },
+                                particle,
+                                mass,
+                                events,
+                                lod,
+                            ))
+                            .id(),
+                    );
+                }
+            }
+            _ => {}
+        }
+    }
+}
+
+#[derive(Serialize, Deserialize)]
+pub enum UiEvents {
+    Reset,
+    /// starts or stops spawning new particles
+    StartStop,
+    /// removes the particles but keeps the pollen and its trajectory
+    ClearParticles,
+    SaveSnapshot,
+    LoadSnapshot,
+    /// replaces the pollen particle and starts a new trajectory
+    AddPollen,
+    /// focuses the camera on the pollen and follows it
+    FollowPollen,
+}
+
+impl RecordableEvent for UiEvents {
+    const KIND: &'static str = "particle_mess_ui";
+
+    fn is_recordable(&self) -> bool {
+        !matches!(
+            self,
+            UiEvents::SaveSnapshot
+                | UiEvents::LoadSnapshot
+                | UiEvents::FollowPollen
+        )
+    }
+}
+
+impl ResetEvent for UiEvents {
+    fn reset() -> Self {
+        UiEvents::Reset
+    }
+}
+
+// ui
+
+impl Simulation for ParticleMessPlugin {
+    const NAME: &'static str = "particle_mess";
+    type Parameters = ParticleMessParameters;
+
+    #[allow(clippy::type_complexity)]
+    fn show_ui(ui: &mut egui::Ui, world: &mut World) {
+        let mut state: SystemState<(
+            ResMut<DebugRenderContext>,
+            EventWriter<UiEvents>,
+            ResMut<ParticleMessParameters>,
+            Res<SpeedHistogram>,
+            Res<BrownianTracker>,
+            Res<CollisionStatistics>,
+            Res<DensityProfile>,
+        )> = SystemState::new(world);
+        let (
+            mut rapier_debug_config,
+            ui_events,
+            mut parameters,
+            speed_histogram,
+            brownian_tracker,
+            collision_statistics,
+            density_profile,
+        ) = state.get_mut(world);
+
+        show_ui(
+            ui,
+            &mut rapier_debug_config,
+            ui_events,
+            &mut parameters,
+            &speed_histogram,
+            &brownian_tracker,
+            &collision_statistics,
+            &density_profile,
+        );
+    }
+}
+
+impl Tunable for ParticleMessParameters {
+    fn tunables() -> Vec<TunableParameter<Self>> {
+        vec![
+            TunableParameter::<Self>::new(
+                "max particles",
+                0.0..=10000.0,
+                |p| p.max_entities as f64,
+                |p, v| p.max_entities = v as usize,
+            )
+            .step_by(500.0)
+            .integer(),
+            TunableParameter::<Self>::new(
+                "spawn this many particles at once",
+                1.0..=500.0,
+                |p| p.spawn_particles_num as f64,
+                |p, v| p.spawn_particles_num = v as usize,
+            )
+            .integer(),
+            TunableParameter::<Self>::new(
+                "spawn frequency",
+                0.0..=100.0,
+                |p| p.spawn_frequency_hz as f64,
+                |p, v| p.spawn_frequency_hz = v as f32,
+            )
+            .step_by(1.0),
+            TunableParameter::<Self>::new(
+                "restitution coefficient",
+                0.0..=1.0,
+                |p| p.restitution_coefficient as f64,
+                |p, v| p.restitution_coefficient = v as f32,
+            )
+            .step_by(0.1),
+            TunableParameter::<Self>::new(
+                "piston position",
+                0.1..=1.0,
+                |p| p.piston_position as f64,
+                |p, v| p.piston_position = v as f32,
+            )
+            .step_by(0.01)
+            .on_hover_text(
+                "fraction of the container width left to the particles",
+            ),
+            TunableParameter::<Self>::new(
+                "heat",
+                0.0..=0.2,
+                |p| p.heat as f64,
+                |p, v| p.heat = v as f32,
+            )
+            .step_by(0.001),
+            TunableParameter::<Self>::new(
+                "synthetic velocity loss factor",
+                0.95..=1.0,
+                |p| p.energy_conservation_factor as f64,
+                |p, v| p.energy_conservation_factor = v as f32,
+            )
+            .step_by(0.0001),
+        ]
+    }
+}
+
+fn show_ui(
+    ui: &mut egui::Ui,
+    rapier_debug_config: &mut DebugRenderContext,
+    mut ui_events: EventWriter<UiEvents>,
+    parameters: &mut ParticleMessParameters,
+    speed_histogram: &SpeedHistogram,
+    brownian_tracker: &BrownianTracker,
+    collision_statistics: &CollisionStatistics,
+    density_profile: &DensityProfile,
+) {
+    ui.allocate_space(egui::vec2(1.0, 10.0));
+
+    show_tunables(ui, parameters);
+
+    ui.horizontal(|ui| {
+        let label = if parameters.spawn_particles {
+            "Stop spawning"
+        } else {
+            "Start spawning"
+        };
+        if ui.button(label).clicked() {
+            ui_events.send(UiEvents::StartStop);
+        }
+        if ui.button("Clear particles").clicked() {
+            ui_events.send(UiEvents::ClearParticles);
+        }
+    });
+
+    show_species(ui, &mut parameters.species);
+
+    ui.horizontal(|ui| {
+        ui.label("gravity:");
+        for option in GravityMode::ALL {
+            ui.radio_value(
+                &mut parameters.gravity_mode,
+                option,
+                String::from(option),
+            );
+        }
+    });
+
+    match parameters.gravity_mode {
+        GravityMode::Central => {
+            ui.add(
+                egui::Slider::new(
+                    &mut parameters.gravitation_on_particle,
+                    0.0..=0.0001,
+                )
+                .step_by(0.00001)
+                .text("factor of gravitation on the particle"),
+            );
+        }
+        GravityMode::Uniform => {
+            ui.add(
+                egui::Slider::new(
+                    &mut parameters.gravity_acceleration,
+                    0.0..=10.0,
+                )
+                .logarithmic(true)
+                .text("gravitational acceleration"),
+            );
+            show_density_profile(ui, density_profile);
+        }
+        GravityMode::None => {}
+    }
+
+    ui.add(egui::Checkbox::new(
+        &mut parameters.lennard_jones,
+        "lennard jones attraction",
+    ));
+    if parameters.lennard_jones {
+        ui.add(
+            egui::Slider::new(&mut parameters.lj_epsilon, 1e-10..=1e-6)
+                .logarithmic(true)
+                .text("well depth"),
+        );
+        ui.add(
+            egui::Slider::new(&mut parameters.lj_cutoff, 1.2..=5.0)
+                .step_by(0.1)
+                .text("cutoff in sigma"),
+        );
+    }
+
+    ui.horizontal(|ui| {
+        ui.label("snapshot:");
+        if ui.button("Save").clicked() {
+            ui_events.send(UiEvents::SaveSnapshot);
+        }
+        if ui.button("Load").clicked() {
+            ui_events.send(UiEvents::LoadSnapshot);
+        }
+    });
+
+    ui.separator();
+
+    ui.label(format!(
+        "number of particles: {}",
+        parameters.number_of_particles
+    ));
+
+    show_speed_histogram(ui, speed_histogram);
+
+    show_collision_statistics(ui, collision_statistics);
+
+    ui.separator();
+
+    show_brownian_tracker(ui, brownian_tracker, &mut ui_events);
+
+    ui.separator();
+
+    ui.add(egui::Checkbox::new(
+        &mut parameters.point_sprites,
+        "draw particles as point sprites",
+    ))
+    .on_hover_text("cheaper to draw for many particles");
+
+    ui.add(egui::Checkbox::new(
+        &mut rapier_debug_config.enabled,
+        "rapier debug",
+    ));
+}
@@ -0,0 +1,159 @@
+use std::f32::consts::PI;
+
+use bevy::prelude::*;
+use bevy_egui::egui;
+use rand::rngs::StdRng;
+use rand::Rng;
+use serde::{Deserialize, Serialize};
+
+use super::ParticleMessParameters;
+
+/// A kind of particle, spawned until `count` of them exist
+#[derive(Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Species {
+    pub radius: f32,
+    pub mass: f32,
+    pub color: [f32; 3],
+    pub count: usize,
+
+    #[serde(skip)]
+    pub mesh: Handle<Mesh>,
+    #[serde(skip)]
+    pub material: Handle<StandardMaterial>,
+    /// radius and color the mesh and material were built for
+    #[serde(skip)]
+    built: Option<(f32, [f32; 3])>,
+}
+
+impl Default for Species {
+    fn default() -> Self {
+        let radius = 0.01;
+
+        Self {
+            radius,
+            mass: ball_mass(radius),
+            color: [0.3, 0.1, 0.1],
+            count: 1000,
+            mesh: Handle::<Mesh>::default(),
+            material: Handle::<StandardMaterial>::default(),
+            built: None,
+        }
+    }
+}
+
+/// Mass of a ball with unit density, which rapier assigns by default
+pub fn ball_mass(radius: f32) -> f32 {
+    4.0 / 3.0 * PI * radius.powi(3)
+}
+
+/// Index of the species of a particle in the species list
+#[derive(Component)]
+pub struct SpeciesIndex(pub usize);
+
+/// Picks a species weighted by how many of its particles are still missing
+pub fn pick_species(
+    species: &[Species],
+    counts: &[usize],
+    rng: &mut StdRng,
+) -> Option<usize> {
+    let missing: Vec<usize> = species
+        .iter()
+        .enumerate()
+        .map(|(i, species)| {
+            species
+                .count
+                .saturating_sub(counts.get(i).copied().unwrap_or(0))
+        })
+        .collect();
+
+    let total: usize = missing.iter().sum();
+    if total == 0 {
+        return None;
+    }
+
+    let mut pick = rng.gen_range(0..total);
+    for (i, missing) in missing.iter().enumerate() {
+        if pick < *missing {
+            return Some(i);
+        }
+        pick -= missing;
+    }
+
+    None
+}
+
+/// Rebuilds the mesh and the material of edited species, particles already
+/// spawned keep theirs
+pub fn update_species_assets(
+    mut parameters: ResMut<ParticleMessParameters>,
+    mut meshes: ResMut<Assets<Mesh>>,
+    mut materials: ResMut<Assets<StandardMaterial>>,
+) {
+    // avoids marking the parameters as changed every frame
+    if parameters
+        .species
+        .iter()
+        .all(|species| species.built == Some((species.radius, species.color)))
+    {
+        return;
+    }
+
+    build_species_assets(&mut parameters.species, &mut meshes, &mut materials);
+}
+
+pub fn build_species_assets(
+    species: &mut [Species],
+    meshes: &mut Assets<Mesh>,
+    materials: &mut Assets<StandardMaterial>,
+) {
+    for species in species.iter_mut() {
+        let built = (species.radius, species.color);
+        if species.built == Some(built) {
+            continue;
+        }
+
+        species.mesh = meshes.add(Mesh::from(shape::Icosphere {
+            radius: species.radius,
+            subdivisions: 6,
+        }));
+        let [r, g, b] = species.color;
+        species.material = materials.add(Color::rgb(r, g, b).into());
+        species.built = Some(built);
+    }
+}
+
+pub fn show_species(ui: &mut egui::Ui, species: &mut Vec<Species>) {
+    ui.label("species (radius, mass, color, count):");
+
+    let mut removed = None;
+    for (i, species) in species.iter_mut().enumerate() {
+        ui.horizontal(|ui| {
+            ui.add(
+                egui::DragValue::new(&mut species.radius)
+                    .speed(0.001)
+                    .clamp_range(0.002..=0.1),
+            );
+            ui.add(
+                egui::DragValue::new(&mut species.mass)
+                    .speed(species.mass * 0.01)
+                    .clamp_range(1e-9..=1.0),
+            );
+            ui.color_edit_button_rgb(&mut species.color);
+            ui.add(
+                egui::DragValue::new(&mut species.count).clamp_range(0..=10000),
+            );
+            if ui.button("x").on_hover_text("remove").clicked() {
+                removed = Some(i);
+            }
+        });
+    }
+
+    if let Some(i) = removed {
+        species.remove(i);
+    }
+
+    if ui.button("Add species").clicked() {
+        species.push(Species::default());
+    }
+}
@@ -0,0 +1,66 @@
+use bevy::prelude::*;
+use bevy::utils::HashMap;
+
+use crate::recording::RecordableParameters;
+
+/// Setup systems which read the parameters run after they are restored
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, SystemLabel)]
+pub struct RestoreParameters;
+
+/// Serialized parameters of every simulation as they were when it was left,
+/// keyed by the kind of the parameters
+#[derive(Default, Resource)]
+struct PersistedParameters(HashMap<&'static str, String>);
+
+pub trait PersistenceAppExt {
+    /// Keeps the parameters of a simulation across switching to another
+    /// simulation and back
+    fn add_persistent_parameters<P: RecordableParameters>(
+        &mut self,
+    ) -> &mut Self;
+}
+
+impl PersistenceAppExt for App {
+    fn add_persistent_parameters<P: RecordableParameters>(
+        &mut self,
+    ) -> &mut Self {
+        self.init_resource::<PersistedParameters>()
+            .add_system_set(
+                SystemSet::on_exit(P::SIMULATION)
+                    .with_system(persist_parameters::<P>),
+            )
+            .add_system_set(
+                SystemSet::on_enter(P::SIMULATION).with_system(
+                    restore_parameters::<P>.label(RestoreParameters),
+                ),
+            )
+    }
+}
+
+fn persist_parameters<P: RecordableParameters>(
+    parameters: Res<P>,
+    mut persisted: ResMut<PersistedParameters>,
+) {
+    match ron::to_string(&*parameters) {
+        Ok(serialized) => {
+            persisted.0.insert(P::KIND, serialized);
+        }
+        Err(error) => error!("failed to persist {}: {}", P::KIND, error),
+    }
+}
+
+fn restore_parameters<P: RecordableParameters>(
+    mut parameters: ResMut<P>,
+    persisted: Res<PersistedParameters>,
+) {
+    let serialized = if let Some(serialized) = persisted.0.get(P::KIND) {
+        serialized
+    } else {
+        return;
+    };
+
+    match ron::from_str(serialized) {
+        Ok(restored) => parameters.restore(restored),
+        Err(error) => error!("failed to restore {}: {}", P::KIND, error),
+    }
+}
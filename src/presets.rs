@@ -0,0 +1,95 @@
+use std::fs;
+use std::path::Path;
+
+use bevy::prelude::*;
+use bevy_egui::egui;
+use serde::de::DeserializeOwned;
+use serde::Serialize;
+
+use crate::file_dialog::pick_file;
+use crate::AppState;
+
+const PRESET_DIRECTORY: &str = "presets";
+
+/// Buttons to store the parameters of the current simulation in a RON file
+/// and to restore them, or to go back to the defaults.
+///
+/// The simulation is restarted after loading a preset, so parameters which
+/// are only read on initialization take effect too.
+pub fn show_presets<T: Serialize + DeserializeOwned + Default>(
+    ui: &mut egui::Ui,
+    parameters: &mut T,
+    app_state: &mut State<AppState>,
+) {
+    let name = app_state.current().name().to_string();
+
+    ui.horizontal(|ui| {
+        ui.label("preset:");
+
+        if ui.button("Save").clicked() {
+            if let Some(path) = pick_file(PRESET_DIRECTORY, "ron", &name, true)
+            {
+                match save_preset(&path, parameters) {
+                    Ok(()) => info!("saved preset to {}", path.display()),
+                    Err(error) => error!(
+                        "failed to save preset {}: {}",
+                        path.display(),
+                        error
+                    ),
+                }
+            }
+        }
+
+        if ui.button("Load").clicked() {
+            if let Some(path) = pick_file(PRESET_DIRECTORY, "ron", &name, false)
+            {
+                match load_preset(&path) {
+                    Ok(loaded) => {
+                        *parameters = loaded;
+                        if let Err(error) = app_state.restart() {
+                            warn!("failed to restart {}: {:?}", name, error);
+                        }
+                    }
+                    Err(error) => error!(
+                        "failed to load preset {}: {}",
+                        path.display(),
+                        error
+                    ),
+                }
+            }
+        }
+
+        if ui
+            .button("Restore defaults")
+            .on_hover_text("the parameters are kept when switching simulations")
+            .clicked()
+        {
+            *parameters = T::default();
+            if let Err(error) = app_state.restart() {
+                warn!("failed to restart {}: {:?}", name, error);
+            }
+        }
+    });
+}
+
+pub fn save_preset<T: Serialize>(
+    path: &Path,
+    parameters: &T,
+) -> Result<(), String> {
+    let serialized =
+        ron::ser::to_string_pretty(parameters, ron::ser::PrettyConfig::new())
+            .map_err(|error| error.to_string())?;
+
+    if let Some(directory) = path.parent() {
+        fs::create_dir_all(directory).map_err(|error| error.to_string())?;
+    }
+
+    fs::write(path, serialized).map_err(|error| error.to_string())
+}
+
+pub fn load_preset<T: DeserializeOwned>(path: &Path) -> Result<T, String> {
+    let serialized =
+        fs::read_to_string(path).map_err(|error| error.to_string())?;
+
+    ron::from_str(&serialized).map_err(|error| error.to_string())
+}
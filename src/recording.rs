@@ -0,0 +1,465 @@
+use std::fs;
+use std::time::Duration;
+
+use bevy::prelude::*;
+use bevy::time::TimeSystem;
+use bevy::utils::Instant;
+use bevy_egui::egui;
+use rand::rngs::StdRng;
+use rand::SeedableRng;
+use serde::de::DeserializeOwned;
+use serde::{Deserialize, Serialize};
+
+use crate::file_dialog::pick_file;
+use crate::AppState;
+
+const RECORDING_DIRECTORY: &str = "recordings";
+
+/// Time every frame advances by while recording or replaying, independent of
+/// the actual frame rate
+const FRAME_DURATION: Duration = Duration::from_nanos(1_000_000_000 / 60);
+
+/// Random number generator of the simulations, seeded when a recording or
+/// replay starts so both draw the same numbers
+#[derive(Resource)]
+pub struct SimulationRng(pub StdRng);
+
+impl Default for SimulationRng {
+    fn default() -> Self {
+        Self(StdRng::from_entropy())
+    }
+}
+
+/// An event which is written to recordings and sent again on replay
+pub trait RecordableEvent:
+    Serialize + DeserializeOwned + Send + Sync + 'static
+{
+    /// identifies the event type in a recording
+    const KIND: &'static str;
+
+    /// events which open dialogs or touch files are not replayed
+    fn is_recordable(&self) -> bool {
+        true
+    }
+}
+
+/// Parameters which are written to recordings whenever they change
+pub trait RecordableParameters:
+    Resource + Serialize + DeserializeOwned
+{
+    const KIND: &'static str;
+    const SIMULATION: AppState;
+    /// Fields which are only read when the simulation starts, they can't be
+    /// set while it runs
+    const SET_ON_INITIALIZATION: &'static [&'static str] = &[];
+
+    /// Takes over the recorded values but keeps the state which belongs to
+    /// the running session, e.g. asset handles
+    fn restore(&mut self, recorded: Self);
+}
+
+pub trait RecordingAppExt {
+    fn add_recordable_event<E: RecordableEvent>(&mut self) -> &mut Self;
+    fn add_recordable_parameters<P: RecordableParameters>(
+        &mut self,
+    ) -> &mut Self;
+}
+
+impl RecordingAppExt for App {
+    fn add_recordable_event<E: RecordableEvent>(&mut self) -> &mut Self {
+        self.add_system_to_stage(CoreStage::PreUpdate, replay_events::<E>)
+            .add_system_to_stage(CoreStage::PostUpdate, record_events::<E>)
+    }
+
+    fn add_recordable_parameters<P: RecordableParameters>(
+        &mut self,
+    ) -> &mut Self {
+        self.add_system_to_stage(CoreStage::PreUpdate, replay_parameters::<P>)
+            .add_system_to_stage(CoreStage::PostUpdate, record_parameters::<P>)
+    }
+}
+
+pub enum RecordingEvents {
+    Record,
+    Stop,
+    Replay,
+    Save,
+    Load,
+}
+
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+enum Phase {
+    #[default]
+    Idle,
+    StartingRecording,
+    Recording,
+    StartingReplay,
+    Replaying,
+}
+
+#[derive(Default, Serialize, Deserialize)]
+struct Recording {
+    simulation: Option<AppState>,
+    seed: u64,
+    /// ordered by frame
+    inputs: Vec<RecordedInput>,
+}
+
+#[derive(Serialize, Deserialize)]
+struct RecordedInput {
+    frame: u64,
+    kind: String,
+    payload: String,
+}
+
+impl Recording {
+    fn inputs_at(
+        &self,
+        frame: u64,
+        kind: &'static str,
+    ) -> impl Iterator<Item = &RecordedInput> {
+        let start = self.inputs.partition_point(|input| input.frame < frame);
+
+        self.inputs[start..]
+            .iter()
+            .take_while(move |input| input.frame == frame)
+            .filter(move |input| input.kind == kind)
+    }
+
+    fn push(&mut self, frame: u64, kind: &str, payload: &impl Serialize) {
+        match ron::to_string(payload) {
+            Ok(payload) => self.inputs.push(RecordedInput {
+                frame,
+                kind: kind.to_string(),
+                payload,
+            }),
+            Err(error) => error!("failed to record {}: {}", kind, error),
+        }
+    }
+}
+
+#[derive(Default, Resource)]
+pub struct Recorder {
+    phase: Phase,
+    recording: Recording,
+    /// frames since the recording or replay started
+    frame: u64,
+    /// deterministic replacement of the global time while active
+    clock: Option<Time>,
+    last_parameters: Option<String>,
+}
+
+impl Recorder {
+    fn is_recording(&self, simulation: &AppState) -> bool {
+        self.phase == Phase::Recording
+            && self.recording.simulation.as_ref() == Some(simulation)
+    }
+
+    fn is_replaying(&self, simulation: &AppState) -> bool {
+        self.phase == Phase::Replaying
+            && self.recording.simulation.as_ref() == Some(simulation)
+    }
+
+    /// Whether a replay is starting or running, the live input is ignored
+    /// meanwhile so only the recorded input reaches the simulation
+    pub fn is_replaying_any(&self) -> bool {
+        matches!(self.phase, Phase::StartingReplay | Phase::Replaying)
+    }
+}
+
+pub struct RecordingPlugin;
+
+impl Plugin for RecordingPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_event::<RecordingEvents>()
+            .insert_resource(Recorder::default())
+            .insert_resource(SimulationRng::default())
+            .add_system_to_stage(
+                CoreStage::First,
+                drive_recording.after(TimeSystem),
+            )
+            .add_system(on_recording_events);
+    }
+}
+
+fn on_recording_events(
+    mut recording_events: EventReader<RecordingEvents>,
+    mut recorder: ResMut<Recorder>,
+    app_state: Res<State<AppState>>,
+) {
+    for event in recording_events.iter() {
+        match event {
+            RecordingEvents::Record => {
+                recorder.recording = Recording {
+                    simulation: Some(app_state.current().clone()),
+                    seed: rand::random(),
+                    inputs: Vec::new(),
+                };
+                recorder.phase = Phase::StartingRecording;
+            }
+            RecordingEvents::Stop => {
+                recorder.phase = Phase::Idle;
+                recorder.clock = None;
+            }
+            RecordingEvents::Replay => {
+                if recorder.recording.simulation.is_some() {
+                    recorder.phase = Phase::StartingReplay;
+                }
+            }
+            RecordingEvents::Save => {
+                let name = app_state.current().name().to_string();
+                if let Some(path) =
+                    pick_file(RECORDING_DIRECTORY, "ron", &name, true)
+                {
+                    let result = ron::to_string(&recorder.recording)
+                        .map_err(|error| error.to_string())
+                        .and_then(|serialized| {
+                            fs::write(&path, serialized)
+                                .map_err(|error| error.to_string())
+                        });
+
+                    match result {
+                        Ok(()) => {
+                            info!("saved recording to {}", path.display())
+                        }
+                        Err(error) => error!(
+                            "failed to save recording {}: {}",
+                            path.display(),
+                            error
+                        ),
+                    }
+                }
+            }
+            RecordingEvents::Load => {
+                let name = app_state.current().name().to_string();
+                if let Some(path) =
+                    pick_file(RECORDING_DIRECTORY, "ron", &name, false)
+                {
+                    let result = fs::read_to_string(&path)
+                        .map_err(|error| error.to_string())
+                        .and_then(|serialized| {
+                            ron::from_str(&serialized)
+                                .map_err(|error| error.to_string())
+                        });
+
+                    match result {
+                        Ok(recording) => {
+                            recorder.recording = recording;
+                            recorder.phase = Phase::Idle;
+                            recorder.clock = None;
+                        }
+                        Err(error) => error!(
+                            "failed to load recording {}: {}",
+                            path.display(),
+                            error
+                        ),
+                    }
+                }
+            }
+        }
+    }
+}
+
+/// Starts recordings and replays from a freshly restarted simulation and
+/// replaces the global time with a clock which advances by a fixed duration
+/// every frame
+fn drive_recording(
+    mut time: ResMut<Time>,
+    mut recorder: ResMut<Recorder>,
+    mut rng: ResMut<SimulationRng>,
+    mut app_state: ResMut<State<AppState>>,
+) {
+    let simulation =
+        if let Some(simulation) = recorder.recording.simulation.clone() {
+            simulation
+        } else {
+            return;
+        };
+
+    match recorder.phase {
+        Phase::Idle => return,
+        Phase::StartingRecording | Phase::StartingReplay => {
+            rng.0 = StdRng::seed_from_u64(recorder.recording.seed);
+            recorder.clock = Some(time.clone());
+            recorder.frame = 0;
+            recorder.last_parameters = None;
+
+            let result = if *app_state.current() == simulation {
+                app_state.restart()
+            } else {
+                app_state.set(simulation)
+            };
+            if let Err(error) = result {
+                warn!("failed to restart the simulation: {:?}", error);
+            }
+
+            recorder.phase = if recorder.phase == Phase::StartingRecording {
+                Phase::Recording
+            } else {
+                Phase::Replaying
+            };
+        }
+        Phase::Recording | Phase::Replaying => {
+            let last_input_frame = recorder
+                .recording
+                .inputs
+                .last()
+                .map_or(0, |input| input.frame);
+
+            let finished = recorder.phase == Phase::Replaying
+                && recorder.frame >= last_input_frame;
+
+            // switching the simulation ends the recording
+            if finished || *app_state.current() != simulation {
+                recorder.phase = Phase::Idle;
+                recorder.clock = None;
+                return;
+            }
+
+            recorder.frame += 1;
+        }
+    }
+
+    if let Some(clock) = recorder.clock.as_mut() {
+        // pausing happens on the global time
+        if time.is_paused() && !clock.is_paused() {
+            clock.pause();
+        } else if !time.is_paused() && clock.is_paused() {
+            clock.unpause();
+        }
+
+        let instant =
+            clock.last_update().unwrap_or_else(Instant::now) + FRAME_DURATION;
+        clock.update_with_instant(instant);
+
+        *time = clock.clone();
+    }
+}
+
+fn record_events<E: RecordableEvent>(
+    mut events: EventReader<E>,
+    mut recorder: ResMut<Recorder>,
+    app_state: Res<State<AppState>>,
+) {
+    let recording = recorder.is_recording(app_state.current());
+    let frame = recorder.frame;
+
+    for event in events.iter() {
+        if recording && event.is_recordable() {
+            recorder.recording.push(frame, E::KIND, event);
+        }
+    }
+}
+
+fn replay_events<E: RecordableEvent>(
+    mut events: EventWriter<E>,
+    recorder: Res<Recorder>,
+    app_state: Res<State<AppState>>,
+) {
+    if !recorder.is_replaying(app_state.current()) {
+        return;
+    }
+
+    for input in recorder.recording.inputs_at(recorder.frame, E::KIND) {
+        match ron::from_str(&input.payload) {
+            Ok(event) => events.send(event),
+            Err(error) => error!("failed to replay {}: {}", E::KIND, error),
+        }
+    }
+}
+
+fn record_parameters<P: RecordableParameters>(
+    parameters: Res<P>,
+    mut recorder: ResMut<Recorder>,
+) {
+    if !recorder.is_recording(&P::SIMULATION) {
+        return;
+    }
+
+    let serialized = match ron::to_string(&*parameters) {
+        Ok(serialized) => serialized,
+        Err(error) => {
+            error!("failed to record {}: {}", P::KIND, error);
+            return;
+        }
+    };
+
+    if recorder.last_parameters.as_ref() != Some(&serialized) {
+        let frame = recorder.frame;
+        recorder.recording.inputs.push(RecordedInput {
+            frame,
+            kind: P::KIND.to_string(),
+            payload: serialized.clone(),
+        });
+        recorder.last_parameters = Some(serialized);
+    }
+}
+
+fn replay_parameters<P: RecordableParameters>(
+    mut parameters: ResMut<P>,
+    recorder: Res<Recorder>,
+) {
+    if !recorder.is_replaying(&P::SIMULATION) {
+        return;
+    }
+
+    for input in recorder.recording.inputs_at(recorder.frame, P::KIND) {
+        match ron::from_str(&input.payload) {
+            Ok(recorded) => parameters.restore(recorded),
+            Err(error) => error!("failed to replay {}: {}", P::KIND, error),
+        }
+    }
+}
+
+pub fn show_recording(
+    ui: &mut egui::Ui,
+    recorder: &Recorder,
+    recording_events: &mut EventWriter<RecordingEvents>,
+) {
+    ui.horizontal(|ui| {
+        ui.label("recording:");
+
+        match recorder.phase {
+            Phase::Idle => {
+                if ui.button("Record").clicked() {
+                    recording_events.send(RecordingEvents::Record);
+                }
+                if ui.button("Replay").clicked() {
+                    recording_events.send(RecordingEvents::Replay);
+                }
+                if ui.button("Save").clicked() {
+                    recording_events.send(RecordingEvents::Save);
+                }
+                if ui.button("Load").clicked() {
+                    recording_events.send(RecordingEvents::Load);
+                }
+            }
+            _ => {
+                if ui.button("Stop").clicked() {
+                    recording_events.send(RecordingEvents::Stop);
+                }
+            }
+        }
+    });
+
+    match recorder.phase {
+        Phase::Recording => ui.label(format!(
+            "recording frame {}, {} inputs",
+            recorder.frame,
+            recorder.recording.inputs.len()
+        )),
+        Phase::Replaying => ui.label(format!(
+            "replaying frame {} of {}",
+            recorder.frame,
+            recorder
+                .recording
+                .inputs
+                .last()
+                .map_or(0, |input| input.frame)
+        )),
+        _ => ui.label(format!(
+            "{} recorded inputs",
+            recorder.recording.inputs.len()
+        )),
+    };
+}
@@ -0,0 +1,286 @@
+use std::net::{Ipv4Addr, SocketAddr, UdpSocket};
+
+use bevy::prelude::*;
+use bevy_egui::egui;
+use serde::Deserialize;
+use serde_json::json;
+
+use crate::simulation::{set_current_parameter, Simulation, Simulations};
+use crate::simulation_control::SimulationControlEvent;
+use crate::wave_2d_simulation::Wave2dSimulationPlugin;
+use crate::AppState;
+
+pub const DEFAULT_REMOTE_PORT: u16 = 9000;
+
+/// Largest datagram which is read, longer commands are cut off
+const MAX_DATAGRAM_SIZE: usize = 4096;
+
+/// A command sent as a JSON object in a single datagram, e.g.
+/// `{"command": "set", "name": "time_scale", "value": 2.0}`
+#[derive(Debug, Deserialize)]
+#[serde(tag = "command", rename_all = "snake_case")]
+pub enum RemoteCommand {
+    /// sets a field of the parameters of the running simulation, named like
+    /// in the presets
+    Set {
+        name: String,
+        value: serde_json::Value,
+    },
+    /// excites the 2d grid at a position given as fractions of its size
+    Pulse {
+        x: f32,
+        y: f32,
+        #[serde(default = "default_pulse_strength")]
+        strength: f32,
+    },
+    /// switches to a simulation, named like the value of `--simulation`
+    Switch {
+        simulation: String,
+    },
+    Play,
+    Pause,
+    Step,
+    Reset,
+}
+
+fn default_pulse_strength() -> f32 {
+    1.0
+}
+
+/// A pulse requested by a remote command or a script, position and
+/// strength as in [`RemoteCommand::Pulse`]
+pub struct RemotePulse {
+    pub x: f32,
+    pub y: f32,
+    pub strength: f32,
+}
+
+/// Listens for [`RemoteCommand`]s on a UDP port, every command is answered
+/// with `{"ok": true}` or `{"error": "..."}`
+#[derive(Resource)]
+pub struct RemoteControl {
+    pub enabled: bool,
+    pub port: u16,
+    /// accept commands from other machines, only the local one otherwise
+    pub all_interfaces: bool,
+    socket: Option<UdpSocket>,
+    received: u64,
+    last_error: Option<String>,
+}
+
+impl Default for RemoteControl {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            port: DEFAULT_REMOTE_PORT,
+            all_interfaces: false,
+            socket: None,
+            received: 0,
+            last_error: None,
+        }
+    }
+}
+
+impl RemoteControl {
+    fn address(&self) -> SocketAddr {
+        let ip = if self.all_interfaces {
+            Ipv4Addr::UNSPECIFIED
+        } else {
+            Ipv4Addr::LOCALHOST
+        };
+        SocketAddr::from((ip, self.port))
+    }
+}
+
+pub struct RemoteControlPlugin {
+    /// starts listening right away on this port
+    pub port: Option<u16>,
+    /// listens on all interfaces instead of the loopback one
+    pub all_interfaces: bool,
+}
+
+impl Plugin for RemoteControlPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_event::<RemotePulse>()
+            .insert_resource(RemoteControl {
+                enabled: self.port.is_some(),
+                port: self.port.unwrap_or(DEFAULT_REMOTE_PORT),
+                all_interfaces: self.all_interfaces,
+                ..default()
+            })
+            .add_system(update_socket)
+            .add_system(receive_remote_commands.after(update_socket));
+    }
+}
+
+/// Binds the socket when the remote control is enabled or its address
+/// changed, and closes it when disabled
+fn update_socket(mut remote: ResMut<RemoteControl>) {
+    let bound_address = remote
+        .socket
+        .as_ref()
+        .and_then(|socket| socket.local_addr().ok());
+
+    if !remote.enabled {
+        remote.socket = None;
+        remote.last_error = None;
+        return;
+    }
+
+    // an address which failed is only tried again once it was edited
+    let address = remote.address();
+    if bound_address == Some(address) || remote.last_error.is_some() {
+        return;
+    }
+
+    match bind(address) {
+        Ok(socket) => {
+            info!("remote control listening on udp {}", address);
+            remote.socket = Some(socket);
+        }
+        Err(error) => {
+            error!("failed to listen on udp {}: {}", address, error);
+            remote.socket = None;
+            remote.last_error = Some(error.to_string());
+        }
+    }
+}
+
+fn bind(address: SocketAddr) -> std::io::Result<UdpSocket> {
+    let socket = UdpSocket::bind(address)?;
+    socket.set_nonblocking(true)?;
+    Ok(socket)
+}
+
+/// Exclusive, a command may set the parameters of any simulation
+fn receive_remote_commands(world: &mut World) {
+    let datagrams = {
+        let remote = world.resource::<RemoteControl>();
+        let socket = if let Some(socket) = &remote.socket {
+            socket
+        } else {
+            return;
+        };
+
+        let mut datagrams = Vec::new();
+        let mut buffer = [0; MAX_DATAGRAM_SIZE];
+        while let Ok((len, sender)) = socket.recv_from(&mut buffer) {
+            datagrams.push((buffer[..len].to_vec(), sender));
+        }
+        datagrams
+    };
+
+    for (datagram, sender) in datagrams {
+        let result = serde_json::from_slice::<RemoteCommand>(&datagram)
+            .map_err(|error| format!("invalid command: {}", error))
+            .and_then(|command| execute(world, command));
+
+        let reply = match &result {
+            Ok(()) => json!({ "ok": true }),
+            Err(error) => json!({ "error": error }),
+        };
+
+        let mut remote = world.resource_mut::<RemoteControl>();
+        remote.received += 1;
+        if let Err(error) = result {
+            warn!("remote command from {} failed: {}", sender, error);
+        }
+        reply_to(&remote, sender, &reply);
+    }
+}
+
+fn reply_to(
+    remote: &RemoteControl,
+    sender: SocketAddr,
+    reply: &serde_json::Value,
+) {
+    if let Some(socket) = &remote.socket {
+        // the sender may not listen for replies
+        let _ = socket.send_to(reply.to_string().as_bytes(), sender);
+    }
+}
+
+pub fn execute(
+    world: &mut World,
+    command: RemoteCommand,
+) -> Result<(), String> {
+    let control_event = match command {
+        RemoteCommand::Set { name, value } => {
+            return set_current_parameter(world, &name, value);
+        }
+        RemoteCommand::Pulse { x, y, strength } => {
+            if *world.resource::<State<AppState>>().current()
+                != Wave2dSimulationPlugin::STATE
+            {
+                return Err("pulses need the wave_2d simulation".to_string());
+            }
+            world
+                .resource_mut::<Events<RemotePulse>>()
+                .send(RemotePulse { x, y, strength });
+            return Ok(());
+        }
+        RemoteCommand::Switch { simulation } => {
+            let simulation = world
+                .resource::<Simulations>()
+                .state(&simulation)
+                .ok_or_else(|| format!("unknown simulation {}", simulation))?;
+            let mut app_state = world.resource_mut::<State<AppState>>();
+            if *app_state.current() != simulation {
+                app_state.set(simulation).map_err(|error| {
+                    format!("failed to switch simulation: {:?}", error)
+                })?;
+            }
+            return Ok(());
+        }
+        RemoteCommand::Play => SimulationControlEvent::Play,
+        RemoteCommand::Pause => SimulationControlEvent::Pause,
+        RemoteCommand::Step => SimulationControlEvent::Step,
+        RemoteCommand::Reset => SimulationControlEvent::Reset,
+    };
+
+    world
+        .resource_mut::<Events<SimulationControlEvent>>()
+        .send(control_event);
+    Ok(())
+}
+
+pub fn show_remote_control(ui: &mut egui::Ui, remote: &mut RemoteControl) {
+    egui::CollapsingHeader::new("Remote control").show(ui, |ui| {
+        ui.checkbox(&mut remote.enabled, "listen for udp commands");
+
+        let port = remote.port;
+        ui.add(egui::DragValue::new(&mut remote.port).prefix("port: "));
+        if remote.port != port {
+            remote.last_error = None;
+        }
+
+        if ui
+            .checkbox(&mut remote.all_interfaces, "listen on all interfaces")
+            .on_hover_text("without, only this machine can send commands")
+            .changed()
+        {
+            remote.last_error = None;
+        }
+        if remote.all_interfaces {
+            ui.label(
+                "anyone who reaches this machine over the network can \
+                 control the simulation",
+            );
+        }
+
+        if let Some(error) = &remote.last_error {
+            ui.label(format!("failed to listen: {}", error));
+        } else if remote.socket.is_some() {
+            ui.label(format!("{} commands received", remote.received));
+        }
+
+        ui.label(
+            "send json like {\"command\": \"set\", \"name\": \"time_scale\", \
+             \"value\": 2.0}",
+        )
+        .on_hover_text(
+            "commands: set, pulse (x and y from 0 to 1), switch, play, \
+             pause, step, reset",
+        );
+    });
+}
@@ -0,0 +1,583 @@
+use bevy::ecs::system::SystemState;
+use bevy::prelude::*;
+use bevy::render::mesh::VertexAttributeValues;
+use bevy::sprite::Mesh2dHandle;
+use bevy_egui::egui;
+use ndarray::Array2;
+use serde::{Deserialize, Serialize};
+
+use crate::colored_mesh::{
+    grid_mesh, ColoredMesh2d, VERTEX_ATTRIBUTE_COLOR_ID,
+};
+use crate::colormap::Colormap;
+use crate::persistence::{PersistenceAppExt, RestoreParameters};
+use crate::recording::{
+    RecordableEvent, RecordableParameters, RecordingAppExt,
+};
+use crate::simulation::Simulation;
+use crate::simulation_control::{
+    forward_reset, ResetEvent, SimulationClock, SimulationControl,
+};
+use crate::ui::{select_colormap, show_tunables, Tunable, TunableParameter};
+use crate::{AppCamera, AppState};
+
+mod spectral;
+
+use spectral::SpectralSurface;
+
+/// Cells of the tank along both axes, a power of two keeps the transforms
+/// fast
+const CELLS: usize = 128;
+
+/// Width of a cell in world units
+const DRAWN_CELLSIZE: f32 = 3.0;
+
+/// World units between the two tanks when they are compared
+const GAP: f32 = 20.0;
+
+/// The side panel covers the left part of the window, the camera looks a bit
+/// to the left so the tank is centered in the rest
+const CAMERA_OFFSET: f32 = -175.0;
+
+/// Simulated seconds of a single step while paused
+const PAUSED_STEP_SECS: f32 = 0.01;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub enum Dispersion {
+    /// `omega^2 = g k + sigma / rho k^3`, long waves are faster than short
+    /// ones, except for ripples dominated by surface tension
+    DeepWater,
+    /// every wavelength travels with the phase velocity deep water waves of
+    /// the packet wavelength have
+    NonDispersive,
+}
+
+impl Dispersion {
+    pub const ALL: [Dispersion; 2] =
+        [Dispersion::DeepWater, Dispersion::NonDispersive];
+
+    fn index(self) -> usize {
+        match self {
+            Dispersion::DeepWater => 0,
+            Dispersion::NonDispersive => 1,
+        }
+    }
+}
+
+impl From<Dispersion> for String {
+    fn from(value: Dispersion) -> Self {
+        match value {
+            Dispersion::DeepWater => "deep water".to_string(),
+            Dispersion::NonDispersive => "non-dispersive".to_string(),
+        }
+    }
+}
+
+#[derive(Clone, Resource, Serialize, Deserialize)]
+#[serde(default)]
+pub struct RippleTankParameters {
+    /// width of a cell in m
+    pub cellsize: f32,
+    /// in m/s^2
+    pub gravity: f32,
+    /// surface tension divided by the density of the water in m^3/s^2, zero
+    /// leaves pure gravity waves
+    pub surface_tension: f32,
+    /// simulated seconds per second
+    pub time_scale: f32,
+    /// carrier wavelength of launched wave packets in m
+    pub packet_wavelength: f32,
+    /// width of the envelope of launched wave packets in m
+    pub packet_width: f32,
+    /// elevation relative to a droplet which is drawn with the ends of the
+    /// colormap
+    pub color_range: f32,
+    /// shows the non-dispersive solver next to the dispersive one
+    pub compare: bool,
+    pub colormap: Colormap,
+}
+
+impl Default for RippleTankParameters {
+    fn default() -> Self {
+        Self {
+            cellsize: 0.005,
+            gravity: 9.81,
+            surface_tension: 7.3e-5,
+            time_scale: 0.25,
+            packet_wavelength: 0.04,
+            packet_width: 0.06,
+            color_range: 0.3,
+            compare: true,
+            colormap: Colormap::Seismic,
+        }
+    }
+}
+
+impl RippleTankParameters {
+    /// Angular frequency in rad/s of a wave with `wavenumber` rad/m
+    fn angular_frequency(
+        &self,
+        dispersion: Dispersion,
+        wavenumber: f32,
+    ) -> f32 {
+        match dispersion {
+            Dispersion::DeepWater => (self.gravity * wavenumber
+                + self.surface_tension * wavenumber.powi(3))
+            .sqrt(),
+            Dispersion::NonDispersive => {
+                self.phase_velocity(Dispersion::DeepWater) * wavenumber
+            }
+        }
+    }
+
+    fn packet_wavenumber(&self) -> f32 {
+        std::f32::consts::TAU / self.packet_wavelength
+    }
+
+    /// Velocity of the crests of the packet carrier in m/s
+    fn phase_velocity(&self, dispersion: Dispersion) -> f32 {
+        let wavenumber = self.packet_wavenumber();
+        self.angular_frequency(dispersion, wavenumber) / wavenumber
+    }
+
+    /// Velocity of the envelope of a packet in m/s, `d omega / d k` at the
+    /// packet wavenumber
+    fn group_velocity(&self, dispersion: Dispersion) -> f32 {
+        let wavenumber = self.packet_wavenumber();
+        let delta = 1e-3 * wavenumber;
+
+        (self.angular_frequency(dispersion, wavenumber + delta)
+            - self.angular_frequency(dispersion, wavenumber - delta))
+            / (2.0 * delta)
+    }
+}
+
+impl RecordableParameters for RippleTankParameters {
+    const KIND: &'static str = "ripple_tank_parameters";
+    const SIMULATION: AppState = RippleTankPlugin::STATE;
+
+    fn restore(&mut self, recorded: Self) {
+        *self = recorded;
+    }
+}
+
+impl Tunable for RippleTankParameters {
+    fn tunables() -> Vec<TunableParameter<Self>> {
+        vec![
+            TunableParameter::<Self>::new(
+                "cell size in m",
+                0.001..=0.1,
+                |p| p.cellsize as f64,
+                |p, v| p.cellsize = v as f32,
+            )
+            .logarithmic(),
+            TunableParameter::<Self>::new(
+                "gravity in m/s²",
+                0.0..=30.0,
+                |p| p.gravity as f64,
+                |p, v| p.gravity = v as f32,
+            )
+            .step_by(0.01),
+            TunableParameter::<Self>::new(
+                "surface tension / density in m³/s²",
+                0.0..=3e-4,
+                |p| p.surface_tension as f64,
+                |p, v| p.surface_tension = v as f32,
+            )
+            .on_hover_text("7.3e-5 for water, which makes ripples faster"),
+            TunableParameter::<Self>::new(
+                "simulated time per second",
+                0.01..=4.0,
+                |p| p.time_scale as f64,
+                |p, v| p.time_scale = v as f32,
+            )
+            .logarithmic(),
+            TunableParameter::<Self>::new(
+                "packet wavelength in m",
+                0.005..=0.3,
+                |p| p.packet_wavelength as f64,
+                |p, v| p.packet_wavelength = v as f32,
+            )
+            .logarithmic(),
+            TunableParameter::<Self>::new(
+                "packet width in m",
+                0.005..=0.3,
+                |p| p.packet_width as f64,
+                |p, v| p.packet_width = v as f32,
+            )
+            .logarithmic(),
+            TunableParameter::<Self>::new(
+                "color range",
+                0.01..=1.0,
+                |p| p.color_range as f64,
+                |p, v| p.color_range = v as f32,
+            )
+            .logarithmic(),
+        ]
+    }
+}
+
+#[derive(Clone, Copy, Debug, Serialize, Deserialize)]
+pub enum RippleTankUiEvents {
+    Reset,
+    /// drops a droplet at a position in cells
+    Drop(f32, f32),
+    /// launches a wave packet from the left edge to the right
+    LaunchPacket,
+}
+
+impl RecordableEvent for RippleTankUiEvents {
+    const KIND: &'static str = "ripple_tank_ui";
+}
+
+impl ResetEvent for RippleTankUiEvents {
+    fn reset() -> Self {
+        RippleTankUiEvents::Reset
+    }
+}
+
+/// The same surface evolved with both dispersion relations, indexed by
+/// [`Dispersion::index`]
+#[derive(Resource)]
+struct Tanks([SpectralSurface; 2]);
+
+#[derive(Component)]
+struct TankPlot(Dispersion);
+
+pub struct RippleTankPlugin;
+
+impl Plugin for RippleTankPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_event::<RippleTankUiEvents>()
+            .add_recordable_event::<RippleTankUiEvents>()
+            .add_recordable_parameters::<RippleTankParameters>()
+            .add_persistent_parameters::<RippleTankParameters>()
+            .insert_resource(RippleTankParameters::default())
+            .add_system_set(
+                SystemSet::on_enter(RippleTankPlugin::STATE)
+                    .with_system(setup.after(RestoreParameters)),
+            )
+            .add_system_set(
+                SystemSet::on_update(RippleTankPlugin::STATE)
+                    .with_system(forward_reset::<RippleTankUiEvents>)
+                    .with_system(on_mouse_events)
+                    .with_system(on_ui_events)
+                    .with_system(update_tanks.after(on_ui_events))
+                    .with_system(update_plots.after(update_tanks)),
+            )
+            .add_system_set(
+                SystemSet::on_exit(RippleTankPlugin::STATE)
+                    .with_system(cleanup),
+            );
+    }
+}
+
+fn new_tanks(parameters: &RippleTankParameters) -> Tanks {
+    Tanks(Dispersion::ALL.map(|dispersion| {
+        let mut surface = SpectralSurface::new(CELLS, CELLS);
+        surface.set_dispersion(parameters.cellsize, |wavenumber| {
+            parameters.angular_frequency(dispersion, wavenumber)
+        });
+        surface
+    }))
+}
+
+fn setup(
+    mut commands: Commands,
+    mut meshes: ResMut<Assets<Mesh>>,
+    parameters: Res<RippleTankParameters>,
+    cameras: Query<Entity, With<AppCamera>>,
+) {
+    if let Ok(camera_entity) = cameras.get_single() {
+        commands.entity(camera_entity).despawn();
+    }
+
+    commands.insert_resource(new_tanks(&parameters));
+
+    commands.spawn((
+        AppCamera,
+        Camera2dBundle {
+            transform: Transform::from_xyz(CAMERA_OFFSET, 0.0, 999.9),
+            ..default()
+        },
+    ));
+
+    for dispersion in Dispersion::ALL {
+        commands.spawn((
+            TankPlot(dispersion),
+            ColoredMesh2d::default(),
+            Mesh2dHandle(meshes.add(grid_mesh(CELLS, CELLS, DRAWN_CELLSIZE))),
+            SpatialBundle::default(),
+        ));
+    }
+}
+
+/// Position of the lower left corner of a tank, the tanks are centered
+/// side by side when compared
+fn plot_origin(dispersion: Dispersion, compare: bool) -> Vec2 {
+    let size = (CELLS - 1) as f32 * DRAWN_CELLSIZE;
+
+    let x = if compare {
+        match dispersion {
+            Dispersion::DeepWater => -size - GAP / 2.0,
+            Dispersion::NonDispersive => GAP / 2.0,
+        }
+    } else {
+        -size / 2.0
+    };
+
+    Vec2::new(x, -size / 2.0)
+}
+
+/// A click into a tank drops a droplet at the same cell of both tanks
+fn on_mouse_events(
+    windows: Res<Windows>,
+    buttons: Res<Input<MouseButton>>,
+    parameters: Res<RippleTankParameters>,
+    cameras: Query<(&Camera, &GlobalTransform), With<AppCamera>>,
+    mut ui_events: EventWriter<RippleTankUiEvents>,
+) {
+    if !buttons.just_pressed(MouseButton::Left) {
+        return;
+    }
+
+    let (camera, camera_transform) = if let Ok(camera) = cameras.get_single() {
+        camera
+    } else {
+        return;
+    };
+
+    let world_position = if let Some(ray) = windows
+        .get_primary()
+        .and_then(|window| window.cursor_position())
+        .and_then(|position| {
+            camera.viewport_to_world(camera_transform, position)
+        }) {
+        ray.origin.truncate()
+    } else {
+        return;
+    };
+
+    for dispersion in Dispersion::ALL {
+        if dispersion == Dispersion::NonDispersive && !parameters.compare {
+            continue;
+        }
+
+        let cell = (world_position
+            - plot_origin(dispersion, parameters.compare))
+            / DRAWN_CELLSIZE;
+        if cell.cmpge(Vec2::ZERO).all()
+            && cell.cmplt(Vec2::splat(CELLS as f32)).all()
+        {
+            ui_events.send(RippleTankUiEvents::Drop(cell.x, cell.y));
+            return;
+        }
+    }
+}
+
+fn on_ui_events(
+    mut commands: Commands,
+    mut ui_events: EventReader<RippleTankUiEvents>,
+    parameters: Res<RippleTankParameters>,
+    tanks: Option<ResMut<Tanks>>,
+) {
+    let mut tanks = if let Some(tanks) = tanks {
+        tanks
+    } else {
+        return;
+    };
+
+    if parameters.is_changed() {
+        for dispersion in Dispersion::ALL {
+            let surface = &mut tanks.0[dispersion.index()];
+            surface.set_dispersion(parameters.cellsize, |wavenumber| {
+                parameters.angular_frequency(dispersion, wavenumber)
+            });
+        }
+    }
+
+    for event in ui_events.iter() {
+        match *event {
+            RippleTankUiEvents::Reset => {
+                commands.insert_resource(new_tanks(&parameters));
+            }
+            RippleTankUiEvents::Drop(x, y) => {
+                // a few cells wide, so it is resolved by the grid
+                let elevation = gaussian(x, y, 2.0, |_, _| 1.0);
+                for surface in tanks.0.iter_mut() {
+                    surface.add_at_rest(&elevation);
+                }
+            }
+            RippleTankUiEvents::LaunchPacket => {
+                let center = CELLS as f32 / 2.0;
+                let width = parameters.packet_width / parameters.cellsize;
+                let wavenumber =
+                    parameters.packet_wavenumber() * parameters.cellsize;
+
+                let elevation =
+                    gaussian(CELLS as f32 / 5.0, center, width, |dx, _| {
+                        (wavenumber * dx).cos()
+                    });
+                for surface in tanks.0.iter_mut() {
+                    surface.add_moving(&elevation, [1.0, 0.0]);
+                }
+            }
+        }
+    }
+}
+
+/// Gaussian of `width` cells around `(x, y)`, multiplied with a carrier of
+/// the distance in cells
+fn gaussian(
+    x: f32,
+    y: f32,
+    width: f32,
+    carrier: impl Fn(f32, f32) -> f32,
+) -> Array2<f32> {
+    let width = width.max(0.5);
+
+    Array2::from_shape_fn((CELLS, CELLS), |(cell_x, cell_y)| {
+        let (dx, dy) = (cell_x as f32 - x, cell_y as f32 - y);
+        (-(dx * dx + dy * dy) / (2.0 * width * width)).exp() * carrier(dx, dy)
+    })
+}
+
+fn update_tanks(
+    time: Res<Time>,
+    control: Res<SimulationControl>,
+    mut clock: ResMut<SimulationClock>,
+    parameters: Res<RippleTankParameters>,
+    tanks: Option<ResMut<Tanks>>,
+) {
+    let mut tanks = if let Some(tanks) = tanks {
+        tanks
+    } else {
+        return;
+    };
+
+    // the surface is evolved exactly, so a whole frame is a single step
+    let dt = if control.is_paused() {
+        if control.is_stepping() {
+            PAUSED_STEP_SECS
+        } else {
+            return;
+        }
+    } else {
+        control.delta_seconds(&time) * parameters.time_scale
+    };
+    clock.advance(1, dt);
+
+    for surface in tanks.0.iter_mut() {
+        surface.step(dt);
+    }
+}
+
+fn update_plots(
+    parameters: Res<RippleTankParameters>,
+    tanks: Option<Res<Tanks>>,
+    mut meshes: ResMut<Assets<Mesh>>,
+    mut plots: Query<(
+        &TankPlot,
+        &Mesh2dHandle,
+        &mut Transform,
+        &mut Visibility,
+    )>,
+) {
+    let tanks = if let Some(tanks) = tanks {
+        tanks
+    } else {
+        return;
+    };
+
+    for (plot, mesh_handle, mut transform, mut visibility) in plots.iter_mut() {
+        let dispersion = plot.0;
+        visibility.is_visible =
+            dispersion == Dispersion::DeepWater || parameters.compare;
+        if !visibility.is_visible {
+            continue;
+        }
+        transform.translation =
+            plot_origin(dispersion, parameters.compare).extend(0.0);
+
+        let elevation = tanks.0[dispersion.index()].elevation();
+
+        let colors = meshes
+            .get_mut(&mesh_handle.0)
+            .and_then(|mesh| mesh.attribute_mut(VERTEX_ATTRIBUTE_COLOR_ID));
+        if let Some(VertexAttributeValues::Uint32(colors)) = colors {
+            for (color, elevation) in colors.iter_mut().zip(elevation.iter()) {
+                *color = parameters
+                    .colormap
+                    .color(elevation / parameters.color_range)
+                    .as_linear_rgba_u32();
+            }
+        }
+    }
+}
+
+fn cleanup(mut commands: Commands, plots: Query<Entity, With<TankPlot>>) {
+    for entity in plots.iter() {
+        commands.entity(entity).despawn();
+    }
+    commands.remove_resource::<Tanks>();
+}
+
+impl Simulation for RippleTankPlugin {
+    const NAME: &'static str = "ripple_tank";
+    type Parameters = RippleTankParameters;
+
+    fn show_ui(ui: &mut egui::Ui, world: &mut World) {
+        let mut state: SystemState<(
+            ResMut<RippleTankParameters>,
+            EventWriter<RippleTankUiEvents>,
+        )> = SystemState::new(world);
+        let (mut parameters, mut ui_events) = state.get_mut(world);
+
+        show_ui(ui, &mut parameters, &mut ui_events);
+    }
+}
+
+fn show_ui(
+    ui: &mut egui::Ui,
+    parameters: &mut RippleTankParameters,
+    ui_events: &mut EventWriter<RippleTankUiEvents>,
+) {
+    ui.allocate_space(egui::Vec2::new(1.0, 10.0));
+
+    show_tunables(ui, parameters);
+
+    ui.add(egui::Checkbox::new(
+        &mut parameters.compare,
+        "compare with the non-dispersive solver",
+    ))
+    .on_hover_text("right side, both start from the same excitation");
+
+    select_colormap(ui, &mut parameters.colormap);
+
+    ui.separator();
+
+    if ui.button("Launch wave packet").clicked() {
+        ui_events.send(RippleTankUiEvents::LaunchPacket);
+    }
+
+    for dispersion in Dispersion::ALL {
+        ui.label(format!(
+            "{}: phase velocity {:.3} m/s, group velocity {:.3} m/s",
+            String::from(dispersion),
+            parameters.phase_velocity(dispersion),
+            parameters.group_velocity(dispersion),
+        ));
+    }
+
+    let resolution = parameters.packet_wavelength / parameters.cellsize;
+    if resolution < 4.0 {
+        ui.colored_label(
+            egui::Color32::YELLOW,
+            format!("the packet wavelength only spans {:.1} cells", resolution),
+        );
+    }
+
+    ui.separator();
+
+    ui.label("click into a tank to drop a droplet, the edges are periodic");
+}
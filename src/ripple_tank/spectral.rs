@@ -0,0 +1,178 @@
+use std::f32::consts::TAU;
+use std::sync::Arc;
+
+use ndarray::{Array2, Axis, Zip};
+use rustfft::num_complex::Complex;
+use rustfft::{Fft, FftPlanner};
+
+/// Linear water surface on a periodic grid, evolved exactly in Fourier space.
+///
+/// Every plane wave of the surface oscillates with the angular frequency the
+/// dispersion relation assigns to its wavenumber, so any dispersion relation
+/// can be used and the result does not depend on the time step.
+pub struct SpectralSurface {
+    /// Fourier transform of the elevation
+    elevation: Array2<Complex<f32>>,
+    /// Fourier transform of the vertical velocity of the surface
+    velocity: Array2<Complex<f32>>,
+    /// wavenumber of every mode in rad per cell
+    wavenumbers: Array2<f32>,
+    /// angular frequency of every mode in rad per s
+    angular_frequencies: Array2<f32>,
+    transforms: Transforms,
+}
+
+struct Transforms {
+    forward_x: Arc<dyn Fft<f32>>,
+    forward_y: Arc<dyn Fft<f32>>,
+    inverse_x: Arc<dyn Fft<f32>>,
+    inverse_y: Arc<dyn Fft<f32>>,
+}
+
+impl SpectralSurface {
+    /// A flat surface of `dimx` by `dimy` cells, which does not move until a
+    /// dispersion relation is set
+    pub fn new(dimx: usize, dimy: usize) -> Self {
+        let mut planner = FftPlanner::new();
+        let transforms = Transforms {
+            forward_x: planner.plan_fft_forward(dimx),
+            forward_y: planner.plan_fft_forward(dimy),
+            inverse_x: planner.plan_fft_inverse(dimx),
+            inverse_y: planner.plan_fft_inverse(dimy),
+        };
+
+        let wavenumber = |index: usize, len: usize| {
+            let index = if index < len / 2 {
+                index as f32
+            } else {
+                index as f32 - len as f32
+            };
+            TAU * index / len as f32
+        };
+        let wavenumbers = Array2::from_shape_fn((dimx, dimy), |(x, y)| {
+            wavenumber(x, dimx).hypot(wavenumber(y, dimy))
+        });
+
+        Self {
+            elevation: Array2::zeros((dimx, dimy)),
+            velocity: Array2::zeros((dimx, dimy)),
+            angular_frequencies: Array2::zeros((dimx, dimy)),
+            wavenumbers,
+            transforms,
+        }
+    }
+
+    /// Assigns every mode the angular frequency of its wavenumber in rad per
+    /// m, has to be called whenever the dispersion relation or the width of
+    /// the cells in m changed
+    pub fn set_dispersion(
+        &mut self,
+        cellsize: f32,
+        angular_frequency: impl Fn(f32) -> f32,
+    ) {
+        Zip::from(&mut self.angular_frequencies)
+            .and(&self.wavenumbers)
+            .for_each(|omega, &k| *omega = angular_frequency(k / cellsize));
+    }
+
+    /// Advances every mode by `dt` seconds
+    pub fn step(&mut self, dt: f32) {
+        Zip::from(&mut self.elevation)
+            .and(&mut self.velocity)
+            .and(&self.angular_frequencies)
+            .for_each(|elevation, velocity, &omega| {
+                if omega <= 0.0 {
+                    // the mean level does not oscillate
+                    *elevation += *velocity * dt;
+                    return;
+                }
+
+                let (sin, cos) = (omega * dt).sin_cos();
+                let next_elevation = *elevation * cos + *velocity * sin / omega;
+                *velocity = *velocity * cos - *elevation * omega * sin;
+                *elevation = next_elevation;
+            });
+    }
+
+    /// Adds an elevation which starts at rest, it splits into waves running
+    /// in all directions
+    pub fn add_at_rest(&mut self, elevation: &Array2<f32>) {
+        let transformed = self.transform(elevation);
+        self.elevation += &transformed;
+    }
+
+    /// Adds an elevation whose waves all run in `direction`, which is given
+    /// in cells
+    pub fn add_moving(&mut self, elevation: &Array2<f32>, direction: [f32; 2]) {
+        let transformed = self.transform(elevation);
+        let (dimx, dimy) = transformed.dim();
+
+        for ((x, y), value) in transformed.indexed_iter() {
+            // only the sign of the wavenumber along the direction matters
+            let kx = if x < dimx / 2 {
+                x as f32
+            } else {
+                x as f32 - dimx as f32
+            };
+            let ky = if y < dimy / 2 {
+                y as f32
+            } else {
+                y as f32 - dimy as f32
+            };
+            let along = (kx * direction[0] + ky * direction[1]).signum();
+            let omega = self.angular_frequencies[(x, y)];
+
+            // a mode `exp(i (k x - omega t))` has the time derivative
+            // `-i omega` times itself
+            self.elevation[(x, y)] += value;
+            self.velocity[(x, y)] += value * Complex::new(0.0, -omega * along);
+        }
+    }
+
+    /// Elevation of every cell
+    pub fn elevation(&self) -> Array2<f32> {
+        let mut buffer = self.elevation.clone();
+        fft_2d(
+            &mut buffer,
+            &*self.transforms.inverse_x,
+            &*self.transforms.inverse_y,
+        );
+
+        let normalization = 1.0 / buffer.len() as f32;
+        buffer.mapv(|value| value.re * normalization)
+    }
+
+    fn transform(&self, elevation: &Array2<f32>) -> Array2<Complex<f32>> {
+        let mut buffer = elevation.mapv(|value| Complex::new(value, 0.0));
+        fft_2d(
+            &mut buffer,
+            &*self.transforms.forward_x,
+            &*self.transforms.forward_y,
+        );
+        buffer
+    }
+}
+
+/// Transforms along y, where the rows are contiguous, and then along x
+fn fft_2d(
+    buffer: &mut Array2<Complex<f32>>,
+    along_x: &dyn Fft<f32>,
+    along_y: &dyn Fft<f32>,
+) {
+    for mut row in buffer.lanes_mut(Axis(1)) {
+        if let Some(row) = row.as_slice_mut() {
+            along_y.process(row);
+        }
+    }
+
+    let mut column = vec![Complex::default(); buffer.dim().0];
+    for mut lane in buffer.lanes_mut(Axis(0)) {
+        for (value, lane_value) in column.iter_mut().zip(lane.iter()) {
+            *value = *lane_value;
+        }
+        along_x.process(&mut column);
+        for (lane_value, value) in lane.iter_mut().zip(&column) {
+            *lane_value = *value;
+        }
+    }
+}
@@ -0,0 +1,576 @@
+use bevy::ecs::system::SystemState;
+use bevy::prelude::*;
+use bevy::render::mesh::VertexAttributeValues;
+use bevy::sprite::Mesh2dHandle;
+use bevy_egui::egui;
+use ndarray::{Array2, Array3};
+use serde::{Deserialize, Serialize};
+
+use crate::colored_mesh::{
+    grid_mesh, ColoredMesh2d, VERTEX_ATTRIBUTE_COLOR_ID,
+};
+use crate::colormap::Colormap;
+use crate::persistence::{PersistenceAppExt, RestoreParameters};
+use crate::recording::{
+    RecordableEvent, RecordableParameters, RecordingAppExt,
+};
+use crate::simulation::Simulation;
+use crate::simulation_control::{
+    forward_reset, ResetEvent, SimulationClock, SimulationControl,
+};
+use crate::ui::{select_colormap, show_tunables, Tunable, TunableParameter};
+use crate::{AppCamera, AppState};
+
+mod solver;
+
+use solver::{
+    add_wave_packet, normalize, probability_density, step_schroedinger,
+    MAX_STABLE_TIME_STEP,
+};
+
+const DIMX: usize = 256;
+const DIMY: usize = 144;
+const CELLSIZE: f32 = 3.4;
+
+/// Cells with the highest potential are tinted with this color
+const POTENTIAL_COLOR: Color = Color::rgb(0.55, 0.35, 0.1);
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub enum PotentialPreset {
+    /// a thin wall the packet partly tunnels through
+    Barrier,
+    DoubleSlit,
+    /// parabolic well around the center
+    HarmonicWell,
+}
+
+impl PotentialPreset {
+    pub const ALL: [PotentialPreset; 3] = [
+        PotentialPreset::Barrier,
+        PotentialPreset::DoubleSlit,
+        PotentialPreset::HarmonicWell,
+    ];
+
+    fn potential(self, height: f32) -> Array2<f32> {
+        let center_x = DIMX as f32 / 2.0;
+        let center_y = DIMY as f32 / 2.0;
+
+        Array2::from_shape_fn((DIMX, DIMY), |(x, y)| {
+            let (dx, dy) = (x as f32 - center_x, y as f32 - center_y);
+            let in_wall = dx.abs() < 2.0;
+
+            match self {
+                PotentialPreset::Barrier => {
+                    if in_wall {
+                        height
+                    } else {
+                        0.0
+                    }
+                }
+                PotentialPreset::DoubleSlit => {
+                    let in_slit = (dy.abs() - 10.0).abs() < 3.0;
+                    if in_wall && !in_slit {
+                        height
+                    } else {
+                        0.0
+                    }
+                }
+                PotentialPreset::HarmonicWell => {
+                    let radius = center_y;
+                    height * (dx * dx + dy * dy) / (radius * radius)
+                }
+            }
+        })
+    }
+}
+
+impl From<PotentialPreset> for String {
+    fn from(value: PotentialPreset) -> Self {
+        match value {
+            PotentialPreset::Barrier => "barrier".to_string(),
+            PotentialPreset::DoubleSlit => "double slit".to_string(),
+            PotentialPreset::HarmonicWell => "harmonic well".to_string(),
+        }
+    }
+}
+
+#[derive(Clone, Resource, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Schroedinger2dParameters {
+    /// in units of `hbar / (m * cell^2)`
+    pub dt: f32,
+    pub steps_per_frame: usize,
+    /// simulated time per second
+    pub time_scale: f32,
+    /// width of new wave packets in cells
+    pub packet_width: f32,
+    /// momentum of new wave packets in radians per cell
+    pub packet_momentum: f32,
+    /// direction new wave packets move in, in degrees from the x axis
+    pub packet_direction: f32,
+    /// the left mouse button paints the potential instead of adding packets
+    pub painting_potential: bool,
+    pub potential_height: f32,
+    /// in cells
+    pub brush_radius: f32,
+    pub colormap: Colormap,
+}
+
+impl Default for Schroedinger2dParameters {
+    fn default() -> Self {
+        Self {
+            dt: 0.2,
+            steps_per_frame: 50,
+            time_scale: 100.0,
+            packet_width: 6.0,
+            packet_momentum: 1.0,
+            packet_direction: 0.0,
+            painting_potential: false,
+            potential_height: 1.0,
+            brush_radius: 4.0,
+            colormap: Colormap::Inferno,
+        }
+    }
+}
+
+impl Schroedinger2dParameters {
+    /// Largest time step which is stable with the highest potential of the
+    /// grid
+    fn max_stable_dt(&self, max_potential: f32) -> f32 {
+        2.0 / (2.0 / MAX_STABLE_TIME_STEP + max_potential.max(0.0))
+    }
+}
+
+impl RecordableParameters for Schroedinger2dParameters {
+    const KIND: &'static str = "schroedinger_2d_parameters";
+    const SIMULATION: AppState = Schroedinger2dSimulationPlugin::STATE;
+
+    fn restore(&mut self, recorded: Self) {
+        *self = recorded;
+    }
+}
+
+impl Tunable for Schroedinger2dParameters {
+    fn tunables() -> Vec<TunableParameter<Self>> {
+        vec![
+            TunableParameter::<Self>::new(
+                "time step",
+                0.01..=1.0,
+                |p| p.dt as f64,
+                |p, v| p.dt = v as f32,
+            )
+            .logarithmic(),
+            TunableParameter::<Self>::new(
+                "steps per frame",
+                1.0..=100.0,
+                |p| p.steps_per_frame as f64,
+                |p, v| p.steps_per_frame = v as usize,
+            )
+            .integer(),
+            TunableParameter::<Self>::new(
+                "simulated time per second",
+                1.0..=1000.0,
+                |p| p.time_scale as f64,
+                |p, v| p.time_scale = v as f32,
+            )
+            .logarithmic(),
+            TunableParameter::<Self>::new(
+                "packet width in cells",
+                2.0..=30.0,
+                |p| p.packet_width as f64,
+                |p, v| p.packet_width = v as f32,
+            )
+            .step_by(0.5),
+            TunableParameter::<Self>::new(
+                "packet momentum in rad per cell",
+                0.0..=2.5,
+                |p| p.packet_momentum as f64,
+                |p, v| p.packet_momentum = v as f32,
+            )
+            .step_by(0.01)
+            .on_hover_text("above pi / 2 the grid can not resolve the phase"),
+            TunableParameter::<Self>::new(
+                "packet direction in °",
+                -180.0..=180.0,
+                |p| p.packet_direction as f64,
+                |p, v| p.packet_direction = v as f32,
+            )
+            .step_by(1.0),
+        ]
+    }
+}
+
+#[derive(Clone, Copy, Debug, Serialize, Deserialize)]
+pub enum Schroedinger2dUiEvents {
+    Reset,
+    /// adds a wave packet at a position in cells
+    AddPacket(f32, f32),
+    /// paints the potential at a position in cells
+    PaintPotential(f32, f32),
+    ClearPotential,
+    LoadPotential(PotentialPreset),
+}
+
+impl RecordableEvent for Schroedinger2dUiEvents {
+    const KIND: &'static str = "schroedinger_2d_ui";
+}
+
+impl ResetEvent for Schroedinger2dUiEvents {
+    fn reset() -> Self {
+        Schroedinger2dUiEvents::Reset
+    }
+}
+
+/// Wave function, with the real and imaginary part as the two channels of
+/// the first axis, and the potential of every cell
+#[derive(Resource)]
+struct QuantumField {
+    psi: Array3<f32>,
+    potential: Array2<f32>,
+    /// simulated time not yet stepped
+    accumulator: f32,
+}
+
+impl Default for QuantumField {
+    fn default() -> Self {
+        Self {
+            psi: Array3::zeros((2, DIMX, DIMY)),
+            potential: Array2::zeros((DIMX, DIMY)),
+            accumulator: 0.0,
+        }
+    }
+}
+
+impl QuantumField {
+    fn max_potential(&self) -> f32 {
+        self.potential.iter().copied().fold(0.0, f32::max)
+    }
+}
+
+#[derive(Component)]
+struct DensityPlot;
+
+pub struct Schroedinger2dSimulationPlugin;
+
+impl Plugin for Schroedinger2dSimulationPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_event::<Schroedinger2dUiEvents>()
+            .add_recordable_event::<Schroedinger2dUiEvents>()
+            .add_recordable_parameters::<Schroedinger2dParameters>()
+            .add_persistent_parameters::<Schroedinger2dParameters>()
+            .insert_resource(Schroedinger2dParameters::default())
+            .insert_resource(QuantumField::default())
+            .add_system_set(
+                SystemSet::on_enter(Schroedinger2dSimulationPlugin::STATE)
+                    .with_system(setup.after(RestoreParameters)),
+            )
+            .add_system_set(
+                SystemSet::on_update(Schroedinger2dSimulationPlugin::STATE)
+                    .with_system(forward_reset::<Schroedinger2dUiEvents>)
+                    .with_system(on_mouse_events)
+                    .with_system(on_ui_events)
+                    .with_system(update_field.after(on_ui_events))
+                    .with_system(update_mesh.after(update_field)),
+            )
+            .add_system_set(
+                SystemSet::on_exit(Schroedinger2dSimulationPlugin::STATE)
+                    .with_system(cleanup),
+            );
+    }
+}
+
+fn setup(
+    mut commands: Commands,
+    mut meshes: ResMut<Assets<Mesh>>,
+    mut field: ResMut<QuantumField>,
+    parameters: Res<Schroedinger2dParameters>,
+    cameras: Query<Entity, With<AppCamera>>,
+) {
+    if let Ok(camera_entity) = cameras.get_single() {
+        commands.entity(camera_entity).despawn();
+    }
+
+    *field = QuantumField::default();
+    add_packet(
+        &mut field,
+        &parameters,
+        DIMX as f32 / 4.0,
+        DIMY as f32 / 2.0,
+    );
+
+    commands.spawn((AppCamera, Camera2dBundle::default()));
+
+    // shifted like the plot of the 2d waves, so it is not hidden by the
+    // side panel
+    commands.spawn((
+        DensityPlot,
+        ColoredMesh2d::default(),
+        Mesh2dHandle(meshes.add(grid_mesh(DIMX, DIMY, CELLSIZE))),
+        SpatialBundle::from_transform(Transform::from_xyz(
+            -((DIMX - 1) as f32) * CELLSIZE / 4.0,
+            -((DIMY - 1) as f32) * CELLSIZE / 2.0,
+            0.0,
+        )),
+    ));
+}
+
+fn add_packet(
+    field: &mut QuantumField,
+    parameters: &Schroedinger2dParameters,
+    x: f32,
+    y: f32,
+) {
+    let direction = parameters.packet_direction.to_radians();
+    let momentum = [
+        parameters.packet_momentum * direction.cos(),
+        parameters.packet_momentum * direction.sin(),
+    ];
+
+    add_wave_packet(&mut field.psi, x, y, parameters.packet_width, momentum);
+    normalize(&mut field.psi);
+}
+
+/// A click adds a wave packet, while painting the left mouse button paints
+/// the potential
+fn on_mouse_events(
+    windows: Res<Windows>,
+    buttons: Res<Input<MouseButton>>,
+    parameters: Res<Schroedinger2dParameters>,
+    cameras: Query<(&Camera, &GlobalTransform), With<AppCamera>>,
+    plots: Query<&Transform, With<DensityPlot>>,
+    mut ui_events: EventWriter<Schroedinger2dUiEvents>,
+) {
+    let active = if parameters.painting_potential {
+        buttons.pressed(MouseButton::Left)
+    } else {
+        buttons.just_pressed(MouseButton::Left)
+    };
+    if !active {
+        return;
+    }
+
+    let (camera, camera_transform) = if let Ok(camera) = cameras.get_single() {
+        camera
+    } else {
+        return;
+    };
+    let plot_transform = if let Ok(transform) = plots.get_single() {
+        transform
+    } else {
+        return;
+    };
+
+    if let Some(ray) = windows
+        .get_primary()
+        .and_then(|window| window.cursor_position())
+        .and_then(|position| {
+            camera.viewport_to_world(camera_transform, position)
+        })
+    {
+        let cell = (ray.origin.truncate()
+            - plot_transform.translation.truncate())
+            / CELLSIZE;
+
+        ui_events.send(if parameters.painting_potential {
+            Schroedinger2dUiEvents::PaintPotential(cell.x, cell.y)
+        } else {
+            Schroedinger2dUiEvents::AddPacket(cell.x, cell.y)
+        });
+    }
+}
+
+fn on_ui_events(
+    mut ui_events: EventReader<Schroedinger2dUiEvents>,
+    parameters: Res<Schroedinger2dParameters>,
+    mut field: ResMut<QuantumField>,
+) {
+    for event in ui_events.iter() {
+        match *event {
+            Schroedinger2dUiEvents::Reset => {
+                field.psi.fill(0.0);
+            }
+            Schroedinger2dUiEvents::AddPacket(x, y) => {
+                if (0.0..DIMX as f32).contains(&x)
+                    && (0.0..DIMY as f32).contains(&y)
+                {
+                    add_packet(&mut field, &parameters, x, y);
+                }
+            }
+            Schroedinger2dUiEvents::PaintPotential(x, y) => {
+                paint_potential(&mut field.potential, &parameters, x, y);
+            }
+            Schroedinger2dUiEvents::ClearPotential => {
+                field.potential.fill(0.0);
+            }
+            Schroedinger2dUiEvents::LoadPotential(preset) => {
+                field.potential = preset.potential(parameters.potential_height);
+            }
+        }
+    }
+}
+
+fn paint_potential(
+    potential: &mut Array2<f32>,
+    parameters: &Schroedinger2dParameters,
+    x: f32,
+    y: f32,
+) {
+    let radius = parameters.brush_radius.max(0.5);
+
+    for ((cell_x, cell_y), value) in potential.indexed_iter_mut() {
+        let distance = Vec2::new(cell_x as f32 - x, cell_y as f32 - y);
+        if distance.length() <= radius {
+            *value = parameters.potential_height;
+        }
+    }
+}
+
+fn update_field(
+    time: Res<Time>,
+    control: Res<SimulationControl>,
+    mut clock: ResMut<SimulationClock>,
+    parameters: Res<Schroedinger2dParameters>,
+    mut field: ResMut<QuantumField>,
+) {
+    let dt = parameters
+        .dt
+        .min(parameters.max_stable_dt(field.max_potential()));
+
+    let field = &mut *field;
+    let steps = if control.is_paused() {
+        usize::from(control.is_stepping())
+    } else {
+        field.accumulator +=
+            control.delta_seconds(&time) * parameters.time_scale;
+
+        let steps = ((field.accumulator / dt).floor() as usize)
+            .min(parameters.steps_per_frame);
+        field.accumulator = (field.accumulator - steps as f32 * dt).min(dt);
+
+        steps
+    };
+    clock.advance(steps, dt);
+
+    for _ in 0..steps {
+        step_schroedinger(&mut field.psi, &field.potential, dt);
+    }
+}
+
+/// Colors the probability density, normalized to its maximum, and tints the
+/// cells with a potential
+fn update_mesh(
+    field: Res<QuantumField>,
+    parameters: Res<Schroedinger2dParameters>,
+    mut meshes: ResMut<Assets<Mesh>>,
+    plots: Query<&Mesh2dHandle, With<DensityPlot>>,
+) {
+    let density = probability_density(&field.psi);
+    let max_density = density.iter().copied().fold(f32::EPSILON, f32::max);
+    let max_potential = field.max_potential().max(f32::EPSILON);
+
+    for mesh_handle in plots.iter() {
+        let colors = meshes
+            .get_mut(&mesh_handle.0)
+            .and_then(|mesh| mesh.attribute_mut(VERTEX_ATTRIBUTE_COLOR_ID));
+        let colors = if let Some(VertexAttributeValues::Uint32(colors)) = colors
+        {
+            colors
+        } else {
+            continue;
+        };
+
+        for ((color, &density), &potential) in colors
+            .iter_mut()
+            .zip(density.iter())
+            .zip(field.potential.iter())
+        {
+            let color_of_density =
+                parameters.colormap.color(2.0 * density / max_density - 1.0);
+            let tint = 0.7 * (potential / max_potential).clamp(0.0, 1.0);
+
+            *color = (color_of_density * (1.0 - tint) + POTENTIAL_COLOR * tint)
+                .as_linear_rgba_u32();
+        }
+    }
+}
+
+fn cleanup(mut commands: Commands, plots: Query<Entity, With<DensityPlot>>) {
+    for entity in plots.iter() {
+        commands.entity(entity).despawn();
+    }
+}
+
+impl Simulation for Schroedinger2dSimulationPlugin {
+    const NAME: &'static str = "schroedinger_2d";
+    type Parameters = Schroedinger2dParameters;
+
+    fn show_ui(ui: &mut egui::Ui, world: &mut World) {
+        let mut state: SystemState<(
+            ResMut<Schroedinger2dParameters>,
+            Res<QuantumField>,
+            EventWriter<Schroedinger2dUiEvents>,
+        )> = SystemState::new(world);
+        let (mut parameters, field, mut ui_events) = state.get_mut(world);
+
+        show_ui(ui, &mut parameters, &field, &mut ui_events);
+    }
+}
+
+fn show_ui(
+    ui: &mut egui::Ui,
+    parameters: &mut Schroedinger2dParameters,
+    field: &QuantumField,
+    ui_events: &mut EventWriter<Schroedinger2dUiEvents>,
+) {
+    ui.allocate_space(egui::Vec2::new(1.0, 10.0));
+
+    show_tunables(ui, parameters);
+
+    let max_stable_dt = parameters.max_stable_dt(field.max_potential());
+    if parameters.dt <= max_stable_dt {
+        ui.label(format!("stable up to a time step of {:.3}", max_stable_dt));
+    } else {
+        ui.colored_label(
+            egui::Color32::YELLOW,
+            format!("time step clamped to {:.3}", max_stable_dt),
+        );
+    }
+
+    let total_probability = probability_density(&field.psi).sum();
+    ui.label(format!("total probability: {:.4}", total_probability));
+
+    select_colormap(ui, &mut parameters.colormap);
+
+    ui.separator();
+
+    ui.add(egui::Checkbox::new(
+        &mut parameters.painting_potential,
+        "paint potential",
+    ));
+    ui.add(
+        egui::Slider::new(&mut parameters.potential_height, 0.0..=4.0)
+            .step_by(0.01)
+            .text("potential height (0 erases)"),
+    );
+    if parameters.painting_potential {
+        ui.add(
+            egui::Slider::new(&mut parameters.brush_radius, 1.0..=20.0)
+                .text("brush radius in cells"),
+        );
+    }
+
+    ui.horizontal(|ui| {
+        for preset in PotentialPreset::ALL {
+            if ui.button(String::from(preset)).clicked() {
+                ui_events.send(Schroedinger2dUiEvents::LoadPotential(preset));
+            }
+        }
+    });
+    if ui.button("Clear potential").clicked() {
+        ui_events.send(Schroedinger2dUiEvents::ClearPotential);
+    }
+
+    ui.separator();
+
+    ui.label("click to add a wave packet, the color shows |psi|^2");
+}
@@ -0,0 +1,100 @@
+use ndarray::{s, Array2, Array3, ArrayView2, Axis, Zip};
+
+/// Real and imaginary channel of the wave function
+pub const REAL: usize = 0;
+pub const IMAGINARY: usize = 1;
+
+/// Largest time step for which the staggered scheme stays stable without a
+/// potential, the scheme is stable as long as `dt * E_max <= 2` and the
+/// kinetic energy of the checkerboard mode is 4
+pub const MAX_STABLE_TIME_STEP: f32 = 0.5;
+
+/// Advances the wave function by one step of the staggered leapfrog scheme
+/// by Visscher, with `hbar = m = 1` and lengths in cells.
+///
+/// The real part lives at whole and the imaginary part at half steps, which
+/// conserves the probability as long as the step is stable. The cells on the
+/// edge stay zero, so the grid is an infinitely deep well.
+pub fn step_schroedinger(
+    psi: &mut Array3<f32>,
+    potential: &Array2<f32>,
+    dt: f32,
+) {
+    let (mut real, mut imaginary) =
+        psi.multi_slice_mut((s![REAL, .., ..], s![IMAGINARY, .., ..]));
+
+    let hamiltonian_real = hamiltonian(imaginary.view(), potential);
+    Zip::from(&mut real)
+        .and(&hamiltonian_real)
+        .for_each(|real, h| *real += dt * h);
+
+    let hamiltonian_imaginary = hamiltonian(real.view(), potential);
+    Zip::from(&mut imaginary)
+        .and(&hamiltonian_imaginary)
+        .for_each(|imaginary, h| *imaginary -= dt * h);
+}
+
+/// `-1/2 * laplace(f) + V * f` in the inner cells, zero on the edge
+fn hamiltonian(f: ArrayView2<f32>, potential: &Array2<f32>) -> Array2<f32> {
+    let (dimx, dimy) = f.dim();
+    let mut result = Array2::zeros((dimx, dimy));
+
+    for x in 1..dimx.saturating_sub(1) {
+        for y in 1..dimy.saturating_sub(1) {
+            let laplace_operator =
+                f[(x - 1, y)] + f[(x + 1, y)] + f[(x, y - 1)] + f[(x, y + 1)]
+                    - 4.0 * f[(x, y)];
+
+            result[(x, y)] =
+                -0.5 * laplace_operator + potential[(x, y)] * f[(x, y)];
+        }
+    }
+
+    result
+}
+
+/// Probability of finding the particle in every cell
+pub fn probability_density(psi: &Array3<f32>) -> Array2<f32> {
+    let real = psi.index_axis(Axis(0), REAL);
+    let imaginary = psi.index_axis(Axis(0), IMAGINARY);
+
+    Zip::from(&real)
+        .and(&imaginary)
+        .map_collect(|re, im| re * re + im * im)
+}
+
+/// Adds a gaussian wave packet centered at `(x, y)` in cells, with a width
+/// of `sigma` cells, moving with `momentum` radians per cell in x and y
+pub fn add_wave_packet(
+    psi: &mut Array3<f32>,
+    x: f32,
+    y: f32,
+    sigma: f32,
+    momentum: [f32; 2],
+) {
+    let (_, dimx, dimy) = psi.dim();
+    let sigma = sigma.max(0.5);
+
+    for cell_x in 1..dimx.saturating_sub(1) {
+        for cell_y in 1..dimy.saturating_sub(1) {
+            let dx = cell_x as f32 - x;
+            let dy = cell_y as f32 - y;
+            let envelope = (-(dx * dx + dy * dy) / (4.0 * sigma * sigma)).exp();
+            if envelope < 1e-6 {
+                continue;
+            }
+
+            let phase = momentum[0] * dx + momentum[1] * dy;
+            psi[(REAL, cell_x, cell_y)] += envelope * phase.cos();
+            psi[(IMAGINARY, cell_x, cell_y)] += envelope * phase.sin();
+        }
+    }
+}
+
+/// Scales the wave function to a total probability of one
+pub fn normalize(psi: &mut Array3<f32>) {
+    let norm = probability_density(psi).sum();
+    if norm > 0.0 {
+        psi.mapv_inplace(|value| value / norm.sqrt());
+    }
+}
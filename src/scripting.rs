@@ -0,0 +1,496 @@
+use std::collections::VecDeque;
+use std::sync::{Arc, Mutex};
+
+use bevy::prelude::*;
+use bevy_egui::{egui, EguiContext};
+use rhai::{Dynamic, Engine, Scope, AST, FLOAT};
+
+use crate::file_dialog::pick_file;
+use crate::remote_control::{execute, RemoteCommand};
+use crate::simulation::{current_parameters, Simulation};
+use crate::simulation_control::{SimulationClock, SimulationControl};
+use crate::wave_2d_simulation::Wave2dSimulationPlugin;
+use crate::AppState;
+
+const SCRIPT_DIRECTORY: &str = "scripts";
+
+/// Lines of printed output kept for the editor
+const LOG_SIZE: usize = 100;
+
+/// Operations a single call may take, so an endless loop stops with an
+/// error instead of freezing the app
+const MAX_OPERATIONS: u64 = 1_000_000;
+
+/// Called every frame, `state` is a map the script keeps its values in
+const STEP_CALL: &str = "state.on_step(t)";
+
+const EXAMPLE_SCRIPT: &str = r#"// runs once when started
+state.next_pulse = 0.0;
+
+// runs every frame in which the simulation advances, `t` is the simulated
+// time in seconds and `this` the `state` map
+fn on_step(t) {
+    // sweeps the frequency of the applied force
+    set("applied_force_frequency_hz", 0.5 + t / 10.0);
+
+    if t >= this.next_pulse {
+        pulse(0.25, 0.5, 2.0);
+        this.next_pulse = t + 1.0;
+    }
+}
+"#;
+
+/// Opens or saves the source of the editor
+pub enum ScriptFileEvent {
+    Open,
+    Save,
+}
+
+/// Marks a rectangle of the 2d grid as obstacle or clears it, the corners
+/// are given as fractions of the grid size
+pub struct ScriptObstacle {
+    pub x0: f32,
+    pub y0: f32,
+    pub x1: f32,
+    pub y1: f32,
+    pub solid: bool,
+}
+
+/// Side effects of a script call, applied to the world once the call
+/// returned
+enum ScriptCommand {
+    Remote(RemoteCommand),
+    Obstacle(ScriptObstacle),
+}
+
+/// A Rhai script which drives the running simulation.
+///
+/// The top level statements run once when the script is started, then its
+/// `on_step(t)` function is called every frame in which the simulation
+/// advances, with the `state` map of the top level statements as `this`,
+/// script functions can't see variables otherwise.
+///
+/// Scripts change the simulation through `set(name, value)` with the
+/// parameter names of the presets, `get(name)`, `pulse(x, y)`,
+/// `obstacle(x0, y0, x1, y1)`, `clear_obstacle(x0, y0, x1, y1)`, `pause()`
+/// and `reset()`, positions are fractions of the grid size.
+#[derive(Resource)]
+pub struct Scripting {
+    pub source: String,
+    pub show_editor: bool,
+    engine: Engine,
+    running: bool,
+    /// functions of the running script and the call of `on_step`, if the
+    /// script defines it
+    step: Option<AST>,
+    scope: Scope<'static>,
+    /// set by the editor, the script is started with the next frame
+    start_requested: bool,
+    commands: Arc<Mutex<Vec<ScriptCommand>>>,
+    /// parameters at the start of the call, read by `get`
+    parameters: Arc<Mutex<serde_json::Value>>,
+    log: Arc<Mutex<VecDeque<String>>>,
+    error: Option<String>,
+}
+
+impl Default for Scripting {
+    fn default() -> Self {
+        let commands = Arc::<Mutex<Vec<ScriptCommand>>>::default();
+        let parameters = Arc::<Mutex<serde_json::Value>>::default();
+        let log = Arc::<Mutex<VecDeque<String>>>::default();
+
+        Self {
+            source: EXAMPLE_SCRIPT.to_string(),
+            show_editor: false,
+            engine: build_engine(&commands, &parameters, &log),
+            running: false,
+            step: None,
+            scope: Scope::new(),
+            start_requested: false,
+            commands,
+            parameters,
+            log,
+            error: None,
+        }
+    }
+}
+
+impl Scripting {
+    pub fn is_running(&self) -> bool {
+        self.running
+    }
+
+    /// Compiles the source and runs its top level statements
+    fn start(&mut self, parameters: serde_json::Value) {
+        self.stop();
+        self.error = None;
+        self.set_parameters(parameters);
+
+        let ast = match self.engine.compile(&self.source) {
+            Ok(ast) => ast,
+            Err(error) => {
+                self.error = Some(error.to_string());
+                return;
+            }
+        };
+        let step_call = self
+            .engine
+            .compile(STEP_CALL)
+            .expect("the call of on_step compiles");
+
+        self.scope.push("state", rhai::Map::new());
+        self.scope.push("t", 0.0 as FLOAT);
+        if let Err(error) =
+            self.engine.run_ast_with_scope(&mut self.scope, &ast)
+        {
+            self.error = Some(error.to_string());
+            self.stop();
+            return;
+        }
+
+        let has_on_step = ast.iter_functions().any(|function| {
+            function.name == "on_step" && function.params.len() == 1
+        });
+        self.step =
+            has_on_step.then(|| ast.clone_functions_only().merge(&step_call));
+        self.running = true;
+    }
+
+    fn stop(&mut self) {
+        self.running = false;
+        self.step = None;
+        self.scope.clear();
+    }
+
+    fn on_step(&mut self, time: f64, parameters: serde_json::Value) {
+        let step = if let Some(step) = &self.step {
+            step
+        } else {
+            return;
+        };
+        self.set_parameters(parameters);
+
+        self.scope.set_value("t", time as FLOAT);
+        let result = self
+            .engine
+            .eval_ast_with_scope::<Dynamic>(&mut self.scope, step);
+        if let Err(error) = result {
+            self.error = Some(error.to_string());
+            self.stop();
+        }
+    }
+
+    fn set_parameters(&self, parameters: serde_json::Value) {
+        if let Ok(mut current) = self.parameters.lock() {
+            *current = parameters;
+        }
+    }
+
+    fn take_commands(&self) -> Vec<ScriptCommand> {
+        self.commands
+            .lock()
+            .map(|mut commands| std::mem::take(&mut *commands))
+            .unwrap_or_default()
+    }
+}
+
+/// The engine with the functions scripts call, which only queue commands
+/// because the world isn't available during a call
+fn build_engine(
+    commands: &Arc<Mutex<Vec<ScriptCommand>>>,
+    parameters: &Arc<Mutex<serde_json::Value>>,
+    log: &Arc<Mutex<VecDeque<String>>>,
+) -> Engine {
+    let mut engine = Engine::new();
+    engine.set_max_operations(MAX_OPERATIONS);
+
+    let queue = |commands: &Arc<Mutex<Vec<ScriptCommand>>>| {
+        let commands = commands.clone();
+        move |command: ScriptCommand| {
+            if let Ok(mut commands) = commands.lock() {
+                commands.push(command);
+            }
+        }
+    };
+
+    let push = queue(commands);
+    engine.register_fn("set", move |name: &str, value: Dynamic| {
+        // values which don't fit the parameter fail when applied
+        let value = rhai::serde::from_dynamic(&value)
+            .unwrap_or(serde_json::Value::Null);
+        push(ScriptCommand::Remote(RemoteCommand::Set {
+            name: name.to_string(),
+            value,
+        }));
+    });
+
+    let current = parameters.clone();
+    engine.register_fn("get", move |name: &str| -> Dynamic {
+        current
+            .lock()
+            .ok()
+            .and_then(|parameters| {
+                parameters
+                    .get(name)
+                    .and_then(|value| rhai::serde::to_dynamic(value).ok())
+            })
+            .unwrap_or(Dynamic::UNIT)
+    });
+
+    let push = queue(commands);
+    engine.register_fn("pulse", move |x: FLOAT, y: FLOAT| {
+        push(ScriptCommand::Remote(RemoteCommand::Pulse {
+            x: x as f32,
+            y: y as f32,
+            strength: 1.0,
+        }));
+    });
+    let push = queue(commands);
+    engine.register_fn("pulse", move |x: FLOAT, y: FLOAT, strength: FLOAT| {
+        push(ScriptCommand::Remote(RemoteCommand::Pulse {
+            x: x as f32,
+            y: y as f32,
+            strength: strength as f32,
+        }));
+    });
+
+    for (name, solid) in [("obstacle", true), ("clear_obstacle", false)] {
+        let push = queue(commands);
+        engine.register_fn(
+            name,
+            move |x0: FLOAT, y0: FLOAT, x1: FLOAT, y1: FLOAT| {
+                push(ScriptCommand::Obstacle(ScriptObstacle {
+                    x0: x0 as f32,
+                    y0: y0 as f32,
+                    x1: x1 as f32,
+                    y1: y1 as f32,
+                    solid,
+                }));
+            },
+        );
+    }
+
+    let push = queue(commands);
+    engine.register_fn("pause", move || {
+        push(ScriptCommand::Remote(RemoteCommand::Pause));
+    });
+    let push = queue(commands);
+    engine.register_fn("reset", move || {
+        push(ScriptCommand::Remote(RemoteCommand::Reset));
+    });
+
+    let output = log.clone();
+    engine.on_print(move |text| {
+        if let Ok(mut log) = output.lock() {
+            if log.len() >= LOG_SIZE {
+                log.pop_front();
+            }
+            log.push_back(text.to_string());
+        }
+    });
+
+    engine
+}
+
+pub struct ScriptingPlugin;
+
+impl Plugin for ScriptingPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_event::<ScriptFileEvent>()
+            .add_event::<ScriptObstacle>()
+            .insert_resource(Scripting::default())
+            .add_system(run_script)
+            .add_system(on_script_file_events)
+            .add_system(show_editor)
+            .add_system(stop_on_simulation_change);
+    }
+}
+
+/// Exclusive, the commands of a script may set the parameters of any
+/// simulation
+fn run_script(world: &mut World) {
+    let starting =
+        std::mem::take(&mut world.resource_mut::<Scripting>().start_requested);
+    let stepping = world.resource::<Scripting>().is_running()
+        && world.resource::<SimulationControl>().is_running();
+    if !starting && !stepping {
+        return;
+    }
+
+    let time = world.resource::<SimulationClock>().elapsed_secs();
+    let parameters =
+        current_parameters(world).unwrap_or(serde_json::Value::Null);
+
+    let commands = {
+        let mut scripting = world.resource_mut::<Scripting>();
+        if starting {
+            scripting.start(parameters);
+        } else {
+            scripting.on_step(time, parameters);
+        }
+        scripting.take_commands()
+    };
+    apply_commands(world, commands);
+}
+
+fn apply_commands(world: &mut World, commands: Vec<ScriptCommand>) {
+    for command in commands {
+        let result = match command {
+            ScriptCommand::Remote(command) => execute(world, command),
+            ScriptCommand::Obstacle(obstacle) => {
+                if *world.resource::<State<AppState>>().current()
+                    == Wave2dSimulationPlugin::STATE
+                {
+                    world
+                        .resource_mut::<Events<ScriptObstacle>>()
+                        .send(obstacle);
+                    Ok(())
+                } else {
+                    Err("obstacles need the wave_2d simulation".to_string())
+                }
+            }
+        };
+
+        // a failing command stops the script like a failing call
+        if let Err(error) = result {
+            let mut scripting = world.resource_mut::<Scripting>();
+            scripting.error = Some(error);
+            scripting.stop();
+            return;
+        }
+    }
+}
+
+/// Scripts are written for a simulation, another one stops them
+fn stop_on_simulation_change(
+    app_state: Res<State<AppState>>,
+    mut simulation: Local<Option<AppState>>,
+    mut scripting: ResMut<Scripting>,
+) {
+    let current = app_state.current();
+    // the state is changed every frame, so the simulation is compared
+    if simulation
+        .as_ref()
+        .map_or(false, |previous| previous != current)
+    {
+        scripting.stop();
+    }
+    *simulation = Some(current.clone());
+}
+
+fn on_script_file_events(
+    mut events: EventReader<ScriptFileEvent>,
+    mut scripting: ResMut<Scripting>,
+) {
+    for event in events.iter() {
+        let save = matches!(event, ScriptFileEvent::Save);
+        let path = if let Some(path) =
+            pick_file(SCRIPT_DIRECTORY, "rhai", "script", save)
+        {
+            path
+        } else {
+            continue;
+        };
+
+        let result = if save {
+            std::fs::write(&path, &scripting.source)
+        } else {
+            std::fs::read_to_string(&path).map(|source| {
+                scripting.stop();
+                scripting.source = source;
+            })
+        };
+        if let Err(error) = result {
+            error!("failed to access {}: {}", path.display(), error);
+        }
+    }
+}
+
+fn show_editor(
+    mut egui_context: ResMut<EguiContext>,
+    mut scripting: ResMut<Scripting>,
+    mut file_events: EventWriter<ScriptFileEvent>,
+) {
+    if !scripting.show_editor {
+        return;
+    }
+
+    let mut open = true;
+    egui::Window::new("script")
+        .open(&mut open)
+        .default_width(480.0)
+        .show(egui_context.ctx_mut(), |ui| {
+            ui.horizontal(|ui| {
+                if scripting.is_running() {
+                    if ui.button("Stop").clicked() {
+                        scripting.stop();
+                    }
+                } else if ui.button("Run").clicked() {
+                    scripting.start_requested = true;
+                }
+                if ui.button("Open").clicked() {
+                    file_events.send(ScriptFileEvent::Open);
+                }
+                if ui.button("Save").clicked() {
+                    file_events.send(ScriptFileEvent::Save);
+                }
+            });
+
+            egui::ScrollArea::vertical()
+                .id_source("script_source")
+                .max_height(320.0)
+                .show(ui, |ui| {
+                    ui.add_enabled(
+                        !scripting.is_running(),
+                        egui::TextEdit::multiline(&mut scripting.source)
+                            .code_editor()
+                            .desired_rows(16)
+                            .desired_width(f32::INFINITY),
+                    );
+                });
+
+            if let Some(error) = &scripting.error {
+                ui.colored_label(egui::Color32::LIGHT_RED, error);
+            }
+
+            ui.separator();
+            ui.horizontal(|ui| {
+                ui.label("output:");
+                if ui.button("Clear").clicked() {
+                    if let Ok(mut log) = scripting.log.lock() {
+                        log.clear();
+                    }
+                }
+            });
+            egui::ScrollArea::vertical()
+                .id_source("script_output")
+                .max_height(120.0)
+                .stick_to_bottom(true)
+                .show(ui, |ui| {
+                    if let Ok(log) = scripting.log.lock() {
+                        for line in log.iter() {
+                            ui.monospace(line);
+                        }
+                    }
+                });
+        });
+
+    scripting.show_editor = open;
+}
+
+pub fn show_scripting(ui: &mut egui::Ui, scripting: &mut Scripting) {
+    egui::CollapsingHeader::new("Scripting").show(ui, |ui| {
+        ui.checkbox(&mut scripting.show_editor, "show script editor");
+
+        if scripting.is_running() {
+            ui.horizontal(|ui| {
+                ui.label("script running");
+                if ui.button("Stop").clicked() {
+                    scripting.stop();
+                }
+            });
+        } else if scripting.error.is_some() {
+            ui.label("script failed, see the editor");
+        }
+    });
+}
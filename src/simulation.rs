@@ -0,0 +1,184 @@
+use bevy::prelude::*;
+use bevy_egui::egui;
+
+use crate::presets::show_presets;
+use crate::recording::RecordableParameters;
+use crate::AppState;
+
+/// A simulation which can be selected in the side panel.
+///
+/// The state it runs in is its `STATE`, which is also the `SIMULATION` of its
+/// parameters.
+pub trait Simulation: Plugin {
+    /// unique name of the simulation, used for `--simulation`, in file names
+    /// and in the simulation selection
+    const NAME: &'static str;
+
+    const STATE: AppState = AppState::named(Self::NAME);
+
+    /// parameters which are persisted, recorded and saved as presets
+    type Parameters: RecordableParameters + Default;
+
+    /// Side panel content of the simulation, below the shared controls
+    fn show_ui(ui: &mut egui::Ui, world: &mut World);
+
+    /// Shown next to the frame rate at the bottom of the side panel
+    fn debug_info(_world: &World) -> Option<String> {
+        None
+    }
+}
+
+struct RegisteredSimulation {
+    state: AppState,
+    show_presets: fn(&mut egui::Ui, &mut World),
+    show_ui: fn(&mut egui::Ui, &mut World),
+    debug_info: fn(&World) -> Option<String>,
+    set_parameter:
+        fn(&mut World, &str, serde_json::Value) -> Result<(), String>,
+    parameters: fn(&World) -> Result<serde_json::Value, String>,
+}
+
+/// All simulations in the order they were added, the simulation selection
+/// and the side panel are built from it
+#[derive(Default, Resource)]
+pub struct Simulations(Vec<RegisteredSimulation>);
+
+impl Simulations {
+    pub fn states(&self) -> impl Iterator<Item = &AppState> {
+        self.0.iter().map(|simulation| &simulation.state)
+    }
+
+    /// The state of the simulation with this name
+    pub fn state(&self, name: &str) -> Option<AppState> {
+        self.states().find(|state| state.name() == name).cloned()
+    }
+
+    fn get(&self, state: &AppState) -> Option<&RegisteredSimulation> {
+        self.0.iter().find(|simulation| simulation.state == *state)
+    }
+}
+
+pub trait SimulationAppExt {
+    /// Adds the plugin of the simulation and makes it selectable
+    fn add_simulation<S: Simulation>(&mut self, simulation: S) -> &mut Self;
+}
+
+impl SimulationAppExt for App {
+    fn add_simulation<S: Simulation>(&mut self, simulation: S) -> &mut Self {
+        self.init_resource::<Simulations>();
+
+        let mut simulations = self.world.resource_mut::<Simulations>();
+        assert!(
+            simulations.state(S::NAME).is_none(),
+            "simulation {} is added twice",
+            S::NAME
+        );
+        simulations.0.push(RegisteredSimulation {
+            state: S::STATE,
+            show_presets: show_simulation_presets::<S::Parameters>,
+            show_ui: S::show_ui,
+            debug_info: S::debug_info,
+            set_parameter: set_simulation_parameter::<S::Parameters>,
+            parameters: simulation_parameters::<S::Parameters>,
+        });
+
+        self.add_plugin(simulation)
+    }
+}
+
+fn show_simulation_presets<P: RecordableParameters + Default>(
+    ui: &mut egui::Ui,
+    world: &mut World,
+) {
+    world.resource_scope(|world, mut parameters: Mut<P>| {
+        let mut app_state = world.resource_mut::<State<AppState>>();
+        show_presets(ui, &mut *parameters, &mut app_state);
+    });
+}
+
+/// Replaces a single field of the serialized parameters, so every field
+/// which is saved in presets can be set by its name, except the ones only
+/// read on initialization
+fn set_simulation_parameter<P: RecordableParameters>(
+    world: &mut World,
+    name: &str,
+    value: serde_json::Value,
+) -> Result<(), String> {
+    if P::SET_ON_INITIALIZATION.contains(&name) {
+        return Err(format!(
+            "{} is only read on initialization, load a preset to change it",
+            name
+        ));
+    }
+
+    let mut parameters = world.resource_mut::<P>();
+
+    let mut fields = serde_json::to_value(&*parameters)
+        .map_err(|error| error.to_string())?;
+    let field = fields
+        .get_mut(name)
+        .ok_or_else(|| format!("unknown parameter {}", name))?;
+    *field = value;
+
+    let updated = serde_json::from_value(fields)
+        .map_err(|error| format!("invalid value of {}: {}", name, error))?;
+    parameters.restore(updated);
+
+    Ok(())
+}
+
+/// The parameters serialized like in the presets, a map of the fields
+fn simulation_parameters<P: RecordableParameters>(
+    world: &World,
+) -> Result<serde_json::Value, String> {
+    serde_json::to_value(world.resource::<P>())
+        .map_err(|error| error.to_string())
+}
+
+impl RegisteredSimulation {
+    fn current(world: &World) -> Option<&RegisteredSimulation> {
+        let current = world.resource::<State<AppState>>().current();
+        world.resource::<Simulations>().get(current)
+    }
+}
+
+pub fn show_current_presets(ui: &mut egui::Ui, world: &mut World) {
+    if let Some(show_presets) = RegisteredSimulation::current(world)
+        .map(|simulation| simulation.show_presets)
+    {
+        show_presets(ui, world);
+    }
+}
+
+pub fn show_current_ui(ui: &mut egui::Ui, world: &mut World) {
+    if let Some(show_ui) = RegisteredSimulation::current(world)
+        .map(|simulation| simulation.show_ui)
+    {
+        show_ui(ui, world);
+    }
+}
+
+pub fn set_current_parameter(
+    world: &mut World,
+    name: &str,
+    value: serde_json::Value,
+) -> Result<(), String> {
+    let set_parameter = RegisteredSimulation::current(world)
+        .map(|simulation| simulation.set_parameter)
+        .ok_or_else(|| "no simulation is running".to_string())?;
+
+    set_parameter(world, name, value)
+}
+
+pub fn current_parameters(world: &World) -> Result<serde_json::Value, String> {
+    let parameters = RegisteredSimulation::current(world)
+        .map(|simulation| simulation.parameters)
+        .ok_or_else(|| "no simulation is running".to_string())?;
+
+    parameters(world)
+}
+
+pub fn current_debug_info(world: &World) -> Option<String> {
+    RegisteredSimulation::current(world)
+        .and_then(|simulation| (simulation.debug_info)(world))
+}
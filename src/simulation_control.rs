@@ -0,0 +1,247 @@
+use std::time::Duration;
+
+use bevy::prelude::*;
+use bevy_egui::egui;
+use bevy_rapier3d::prelude::*;
+use serde::{Deserialize, Serialize};
+
+use crate::recording::{RecordableEvent, RecordingAppExt};
+use crate::AppState;
+
+/// Transport controls shared by all simulations, sent by the top panel
+#[derive(Clone, Copy, Debug, Serialize, Deserialize)]
+pub enum SimulationControlEvent {
+    Play,
+    Pause,
+    TogglePause,
+    /// advances a paused simulation by a single step
+    Step,
+    /// forwarded to the reset event of the running simulation
+    Reset,
+    SetSpeed(f32),
+}
+
+impl RecordableEvent for SimulationControlEvent {
+    const KIND: &'static str = "simulation_control";
+
+    fn is_recordable(&self) -> bool {
+        // the forwarded reset event of the simulation is recorded instead
+        !matches!(self, SimulationControlEvent::Reset)
+    }
+}
+
+/// Whether and how fast the simulations advance.
+///
+/// Pausing this instead of `Time` keeps the camera, the ui and the recording
+/// clock running while the simulation stands still.
+#[derive(Resource)]
+pub struct SimulationControl {
+    paused: bool,
+    /// set for the single frame in which a paused simulation steps
+    stepping: bool,
+    speed: f32,
+}
+
+impl Default for SimulationControl {
+    fn default() -> Self {
+        Self {
+            paused: false,
+            stepping: false,
+            speed: 1.0,
+        }
+    }
+}
+
+impl SimulationControl {
+    pub fn is_paused(&self) -> bool {
+        self.paused
+    }
+
+    /// A paused simulation is stepping during one frame after a step was
+    /// requested
+    pub fn is_stepping(&self) -> bool {
+        self.paused && self.stepping
+    }
+
+    /// Whether the simulation advances in this frame
+    pub fn is_running(&self) -> bool {
+        !self.paused || self.stepping
+    }
+
+    pub fn pause(&mut self) {
+        self.paused = true;
+    }
+
+    /// Simulated time passing in this frame, zero while paused
+    pub fn delta(&self, time: &Time) -> Duration {
+        if self.is_running() {
+            time.delta().mul_f32(self.speed)
+        } else {
+            Duration::ZERO
+        }
+    }
+
+    pub fn delta_seconds(&self, time: &Time) -> f32 {
+        self.delta(time).as_secs_f32()
+    }
+}
+
+/// Simulated time and steps of the running simulation, as opposed to the
+/// wall time in `Time`.
+///
+/// The simulations advance it by every solver step, it restarts with the
+/// transport bar reset and whenever another simulation is selected.
+#[derive(Default, Resource)]
+pub struct SimulationClock {
+    elapsed_secs: f64,
+    steps: u64,
+    /// simulation the clock is counting for
+    simulation: Option<AppState>,
+}
+
+impl SimulationClock {
+    /// Counts `steps` steps of `dt` simulated seconds each
+    pub fn advance(&mut self, steps: usize, dt: f32) {
+        self.elapsed_secs += steps as f64 * dt as f64;
+        self.steps += steps as u64;
+    }
+
+    pub fn elapsed_secs(&self) -> f64 {
+        self.elapsed_secs
+    }
+
+    pub fn steps(&self) -> u64 {
+        self.steps
+    }
+
+    fn reset(&mut self) {
+        self.elapsed_secs = 0.0;
+        self.steps = 0;
+    }
+}
+
+/// Event of a simulation which resets it
+pub trait ResetEvent: Send + Sync + 'static {
+    fn reset() -> Self;
+}
+
+/// Sends the reset event of the running simulation when the transport bar
+/// resets
+pub fn forward_reset<E: ResetEvent>(
+    mut control_events: EventReader<SimulationControlEvent>,
+    mut events: EventWriter<E>,
+) {
+    for event in control_events.iter() {
+        if let SimulationControlEvent::Reset = event {
+            events.send(E::reset());
+        }
+    }
+}
+
+pub struct SimulationControlPlugin;
+
+impl Plugin for SimulationControlPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_event::<SimulationControlEvent>()
+            .add_recordable_event::<SimulationControlEvent>()
+            .insert_resource(SimulationControl::default())
+            .insert_resource(SimulationClock::default())
+            // before the replayed and the ui events are sent, so both take
+            // effect in the next frame
+            .add_system_to_stage(CoreStage::First, on_simulation_control_events)
+            .add_system(update_physics);
+    }
+}
+
+fn on_simulation_control_events(
+    mut control: ResMut<SimulationControl>,
+    mut clock: ResMut<SimulationClock>,
+    mut control_events: EventReader<SimulationControlEvent>,
+    app_state: Res<State<AppState>>,
+) {
+    control.stepping = false;
+
+    if clock.simulation.as_ref() != Some(app_state.current()) {
+        clock.reset();
+        clock.simulation = Some(app_state.current().clone());
+    }
+
+    for event in control_events.iter() {
+        match event {
+            SimulationControlEvent::Play => control.paused = false,
+            SimulationControlEvent::Pause => control.paused = true,
+            SimulationControlEvent::TogglePause => {
+                control.paused = !control.paused;
+            }
+            SimulationControlEvent::Step => {
+                if control.paused {
+                    control.stepping = true;
+                }
+            }
+            SimulationControlEvent::SetSpeed(speed) => {
+                control.speed = speed.max(0.0);
+            }
+            SimulationControlEvent::Reset => clock.reset(),
+        }
+    }
+}
+
+/// Rapier advances by one frame while stepping, scaled by the speed
+fn update_physics(
+    control: Res<SimulationControl>,
+    mut rapier_config: ResMut<RapierConfiguration>,
+) {
+    rapier_config.physics_pipeline_active = control.is_running();
+
+    if let TimestepMode::Variable { time_scale, .. } =
+        &mut rapier_config.timestep_mode
+    {
+        *time_scale = control.speed;
+    }
+}
+
+/// Play / pause, step, reset, speed and simulated time of the running
+/// simulation
+pub fn show_transport(
+    ui: &mut egui::Ui,
+    control: &SimulationControl,
+    clock: &SimulationClock,
+    control_events: &mut EventWriter<SimulationControlEvent>,
+) {
+    if ui.button("Reset").clicked() {
+        control_events.send(SimulationControlEvent::Reset);
+    }
+
+    if ui
+        .add_enabled(control.is_paused(), egui::Button::new("Step"))
+        .clicked()
+    {
+        control_events.send(SimulationControlEvent::Step);
+    }
+
+    let label = if control.is_paused() { "Play" } else { "Pause" };
+    if ui.button(label).clicked() {
+        control_events.send(SimulationControlEvent::TogglePause);
+    }
+
+    let mut speed = control.speed;
+    if ui
+        .add(
+            egui::Slider::new(&mut speed, 0.1..=4.0)
+                .logarithmic(true)
+                .text("speed"),
+        )
+        .changed()
+    {
+        control_events.send(SimulationControlEvent::SetSpeed(speed));
+    }
+
+    ui.separator();
+
+    ui.label(format!(
+        "t = {:.3} s, {} steps",
+        clock.elapsed_secs(),
+        clock.steps()
+    ))
+    .on_hover_text("simulated time since the last reset");
+}
@@ -0,0 +1,103 @@
+use std::fs::{self, File};
+use std::io::{BufReader, BufWriter};
+use std::path::Path;
+
+use bevy::prelude::*;
+use bevy_rapier3d::prelude::Velocity;
+use serde::de::DeserializeOwned;
+use serde::{Deserialize, Serialize};
+
+use crate::file_dialog::pick_file;
+
+const SNAPSHOT_DIRECTORY: &str = "snapshots";
+const SNAPSHOT_EXTENSION: &str = "snapshot";
+
+/// Position and velocity of a rigid body
+#[derive(Clone, Copy, Debug, Serialize, Deserialize)]
+pub struct BodyState {
+    pub translation: [f32; 3],
+    pub rotation: [f32; 4],
+    pub linvel: [f32; 3],
+    pub angvel: [f32; 3],
+}
+
+impl BodyState {
+    pub fn new(transform: &Transform, velocity: &Velocity) -> Self {
+        Self {
+            translation: transform.translation.to_array(),
+            rotation: transform.rotation.to_array(),
+            linvel: velocity.linvel.to_array(),
+            angvel: velocity.angvel.to_array(),
+        }
+    }
+
+    pub fn transform(&self) -> Transform {
+        Transform {
+            translation: Vec3::from_array(self.translation),
+            rotation: Quat::from_array(self.rotation),
+            ..default()
+        }
+    }
+
+    pub fn velocity(&self) -> Velocity {
+        Velocity {
+            linvel: Vec3::from_array(self.linvel),
+            angvel: Vec3::from_array(self.angvel),
+        }
+    }
+}
+
+/// Asks for a file name and writes the snapshot to it
+pub fn save_snapshot<T: Serialize>(name: &str, snapshot: &T) {
+    let path = if let Some(path) =
+        pick_file(SNAPSHOT_DIRECTORY, SNAPSHOT_EXTENSION, name, true)
+    {
+        path
+    } else {
+        return;
+    };
+
+    match write_snapshot(&path, snapshot) {
+        Ok(()) => info!("saved snapshot to {}", path.display()),
+        Err(error) => {
+            error!("failed to save snapshot {}: {}", path.display(), error)
+        }
+    }
+}
+
+/// Asks for a snapshot file and reads it
+pub fn load_snapshot<T: DeserializeOwned>(name: &str) -> Option<T> {
+    let path = pick_file(SNAPSHOT_DIRECTORY, SNAPSHOT_EXTENSION, name, false)?;
+
+    match read_snapshot(&path) {
+        Ok(snapshot) => {
+            info!("loaded snapshot from {}", path.display());
+            Some(snapshot)
+        }
+        Err(error) => {
+            error!("failed to load snapshot {}: {}", path.display(), error);
+            None
+        }
+    }
+}
+
+pub fn write_snapshot<T: Serialize>(
+    path: &Path,
+    snapshot: &T,
+) -> Result<(), String> {
+    if let Some(directory) = path.parent() {
+        fs::create_dir_all(directory).map_err(|error| error.to_string())?;
+    }
+
+    let writer =
+        BufWriter::new(File::create(path).map_err(|error| error.to_string())?);
+
+    bincode::serialize_into(writer, snapshot).map_err(|error| error.to_string())
+}
+
+pub fn read_snapshot<T: DeserializeOwned>(path: &Path) -> Result<T, String> {
+    let reader =
+        BufReader::new(File::open(path).map_err(|error| error.to_string())?);
+
+    bincode::deserialize_from(reader).map_err(|error| error.to_string())
+}
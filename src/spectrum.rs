@@ -0,0 +1,75 @@
+use std::f32::consts::TAU;
+use std::sync::Arc;
+
+use bevy::prelude::*;
+use bevy::utils::HashMap;
+use rustfft::num_complex::Complex;
+use rustfft::{Fft, FftPlanner};
+
+/// Forward transforms of the spectra by signal length, a probe keeps its
+/// length so its transform is planned once
+#[derive(Default, Resource)]
+pub struct SpectrumTransforms(HashMap<usize, Arc<dyn Fft<f32>>>);
+
+impl SpectrumTransforms {
+    fn forward(&mut self, len: usize) -> Arc<dyn Fft<f32>> {
+        self.0
+            .entry(len)
+            .or_insert_with(|| FftPlanner::new().plan_fft_forward(len))
+            .clone()
+    }
+}
+
+/// Single sided amplitude spectrum of a real signal.
+///
+/// The signal is Hann windowed before the transform. Returns pairs of
+/// `[frequency, amplitude]`, where the frequency is given in cycles per
+/// `sample_interval`.
+pub fn amplitude_spectrum(
+    transforms: &mut SpectrumTransforms,
+    samples: &[f32],
+    sample_interval: f32,
+) -> Vec<[f64; 2]> {
+    let len = samples.len();
+    if len < 2 {
+        return Vec::new();
+    }
+
+    let mean = samples.iter().sum::<f32>() / len as f32;
+
+    let mut buffer: Vec<Complex<f32>> = samples
+        .iter()
+        .enumerate()
+        .map(|(i, sample)| {
+            let window = 0.5 - 0.5 * (TAU * i as f32 / (len - 1) as f32).cos();
+            Complex::new((sample - mean) * window, 0.0)
+        })
+        .collect();
+
+    transforms.forward(len).process(&mut buffer);
+
+    // the hann window halves the coherent gain
+    let normalization = 4.0 / len as f32;
+    let frequency_resolution = 1.0 / (len as f32 * sample_interval);
+
+    buffer
+        .iter()
+        .take(len / 2 + 1)
+        .enumerate()
+        .map(|(i, value)| {
+            [
+                (i as f32 * frequency_resolution) as f64,
+                (value.norm() * normalization) as f64,
+            ]
+        })
+        .collect()
+}
+
+/// Frequency of the highest peak of a spectrum, ignoring the DC bin
+pub fn peak_frequency(spectrum: &[[f64; 2]]) -> Option<f64> {
+    spectrum
+        .iter()
+        .skip(1)
+        .max_by(|a, b| a[1].total_cmp(&b[1]))
+        .map(|peak| peak[0])
+}
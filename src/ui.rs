@@ -1,18 +1,34 @@
 use std::collections::VecDeque;
+use std::ops::RangeInclusive;
 
 use bevy::diagnostic::{Diagnostics, FrameTimeDiagnosticsPlugin};
+use bevy::ecs::system::SystemState;
+use bevy::input::mouse::MouseWheel;
+use bevy::input::InputSystem;
 use bevy::prelude::*;
-use bevy_egui::{egui, EguiContext, EguiPlugin};
-use bevy_rapier3d::render::DebugRenderContext;
-
-use crate::longitudinal_wave_3d_simulation::LongitudinalWave3dSimulationParameters;
-use crate::particle_mess::ParticleMessParameters;
-use crate::wave_2d_simulation::Wave2dSimulationParameters;
-use crate::wave_in_panel::WaveInPanelParameters;
-use crate::{
-    longitudinal_wave_3d_simulation, particle_mess, wave_2d_simulation,
-    wave_in_panel, AppState,
+use bevy_egui::{egui, EguiContext, EguiPlugin, EguiSystem};
+
+use crate::capture::{show_capture, Capture, CaptureEvents};
+use crate::colormap::Colormap;
+use crate::data_stream::{show_data_stream, DataStream};
+use crate::instancing::{show_instanced_rendering, InstancedRendering};
+use crate::keymap::{show_keymap, Keymap};
+use crate::level_of_detail::{show_level_of_detail, LodSettings};
+use crate::pan_orbit_camera::{
+    show_camera_controls, PanOrbitCamera, PanOrbitSettings,
+};
+use crate::recording::{show_recording, Recorder, RecordingEvents};
+use crate::remote_control::{show_remote_control, RemoteControl};
+use crate::scripting::{show_scripting, Scripting};
+use crate::simulation::{
+    current_debug_info, show_current_presets, show_current_ui, Simulations,
+};
+use crate::simulation_control::{
+    show_transport, SimulationClock, SimulationControl, SimulationControlEvent,
 };
+use crate::velocity_arrows::{show_velocity_arrows, VelocityArrows};
+use crate::viewports::{show_viewports, Viewports};
+use crate::{AppCamera, AppState};
 
 pub struct UiPlugin;
 
@@ -20,15 +36,71 @@ impl Plugin for UiPlugin {
     fn build(&self, app: &mut App) {
         app.add_plugin(EguiPlugin)
             .insert_resource(UiState::default())
+            .insert_resource(PointerOverUi::default())
+            .init_resource::<Simulations>()
             .add_startup_system(configure_ui)
+            // the layout of the last frame is still known before egui
+            // begins the next one
+            .add_system_to_stage(
+                CoreStage::PreUpdate,
+                route_pointer
+                    .after(InputSystem)
+                    .before(EguiSystem::BeginFrame),
+            )
             .add_system(show_ui);
     }
 }
 
+/// Whether the pointer is over a panel or window of the ui, or egui is
+/// dragging something, in this frame, always set while a recording is
+/// replayed
+#[derive(Default, Resource)]
+pub struct PointerOverUi(pub bool);
+
+/// Keeps clicks, drags and scrolling which start over the ui from reaching
+/// the simulations, touches have to be checked against `PointerOverUi`.
+///
+/// Buttons held by a drag which started in the scene stay pressed over the
+/// ui, so the drag still sees its release.
+///
+/// During a replay no live mouse, touch or key input reaches the
+/// simulations.
+fn route_pointer(
+    mut egui_ctx: ResMut<EguiContext>,
+    mut pointer_over_ui: ResMut<PointerOverUi>,
+    mut mouse_buttons: ResMut<Input<MouseButton>>,
+    mut keys: ResMut<Input<KeyCode>>,
+    mut mouse_wheel_events: ResMut<Events<MouseWheel>>,
+    recorder: Res<Recorder>,
+) {
+    if recorder.is_replaying_any() {
+        pointer_over_ui.0 = true;
+        mouse_buttons.reset_all();
+        keys.reset_all();
+        mouse_wheel_events.clear();
+        return;
+    }
+
+    let ctx = egui_ctx.ctx_mut();
+    pointer_over_ui.0 = ctx.is_pointer_over_area() || ctx.is_using_pointer();
+
+    if pointer_over_ui.0 {
+        let pressed_over_ui: Vec<MouseButton> =
+            mouse_buttons.get_just_pressed().copied().collect();
+        for button in pressed_over_ui {
+            mouse_buttons.reset(button);
+        }
+        mouse_wheel_events.clear();
+    }
+}
+
 #[derive(Resource)]
 pub struct UiState {
     fps_avg: VecDeque<f64>,
     pub panel_x: f32,
+    /// part of the window which is not covered by the panels, in logical
+    /// pixels, as of the last frame
+    free_area: egui::Rect,
 }
 
 impl Default for UiState {
@@ -36,10 +108,34 @@ impl Default for UiState {
         Self {
             fps_avg: VecDeque::from(vec![0.0; 27]),
             panel_x: 350.0,
+            free_area: egui::Rect::NOTHING,
         }
     }
 }
 
+impl UiState {
+    /// Physical position and size of the part of the window which is not
+    /// covered by the panels, for camera viewports
+    pub fn free_area(&self, window: &Window) -> (UVec2, UVec2) {
+        let window_size =
+            UVec2::new(window.physical_width(), window.physical_height());
+        if !self.free_area.is_positive() {
+            return (UVec2::ZERO, window_size);
+        }
+
+        let scale_factor = window.scale_factor() as f32;
+        let to_physical = |pos: egui::Pos2| {
+            (Vec2::new(pos.x, pos.y) * scale_factor)
+                .as_uvec2()
+                .min(window_size)
+        };
+        let min = to_physical(self.free_area.min);
+        let max = to_physical(self.free_area.max).max(min + UVec2::ONE);
+
+        (min, max - min)
+    }
+}
+
 fn configure_ui(mut egui_ctx: ResMut<EguiContext>) {
     egui_ctx.ctx_mut().set_visuals(egui::Visuals {
         window_rounding: 0.0.into(),
@@ -47,34 +143,48 @@ fn configure_ui(mut egui_ctx: ResMut<EguiContext>) {
     });
 }
 
-#[allow(clippy::too_many_arguments)]
-fn show_ui(
-    mut egui_ctx: ResMut<EguiContext>,
-    mut ui_state: ResMut<UiState>,
-    mut app_state: ResMut<State<AppState>>,
-    diagnostics: Res<Diagnostics>,
-    mut rapier_debug_config: ResMut<DebugRenderContext>,
-    mut wave_2d_parameters: ResMut<Wave2dSimulationParameters>,
-    wave_2d_events: EventWriter<wave_2d_simulation::UiEvents>,
-    mut longitudinal_wave_3d_parameters: ResMut<
-        LongitudinalWave3dSimulationParameters,
-    >,
-    longitudinal_wave_3d_events: EventWriter<
-        longitudinal_wave_3d_simulation::UiEvents,
-    >,
-    mut particle_mess_parameters: ResMut<ParticleMessParameters>,
-    particle_mess_events: EventWriter<particle_mess::UiEvents>,
-    mut wave_in_panel_parameters: ResMut<WaveInPanelParameters>,
-    wave_in_panel_events: EventWriter<wave_in_panel::UiEvents>,
-) {
+/// Exclusive, so the running simulation can fetch what its panel needs
+/// from the world
+#[allow(clippy::type_complexity)]
+fn show_ui(world: &mut World) {
+    let ctx = world.resource_mut::<EguiContext>().ctx_mut().clone();
+    // only the recording controls stay usable while a replay runs
+    let replaying = world.resource::<Recorder>().is_replaying_any();
+
     egui::TopBottomPanel::top("top_panel")
         .resizable(false)
-        .show(egui_ctx.ctx_mut(), |ui| {
+        .show(&ctx, |ui| {
+            let mut state: SystemState<(
+                ResMut<Capture>,
+                EventWriter<CaptureEvents>,
+                Res<SimulationControl>,
+                Res<SimulationClock>,
+                EventWriter<SimulationControlEvent>,
+            )> = SystemState::new(world);
+            let (
+                mut capture,
+                mut capture_events,
+                simulation_control,
+                simulation_clock,
+                mut simulation_control_events,
+            ) = state.get_mut(world);
+
             ui.with_layout(
                 egui::Layout::right_to_left(egui::Align::Center),
                 |ui| {
                     ui.heading("wave_sim");
                     ui.allocate_space(egui::Vec2::new(0.0, 27.0));
+                    ui.separator();
+                    show_capture(ui, &mut capture, &mut capture_events);
+                    ui.separator();
+                    ui.add_enabled_ui(!replaying, |ui| {
+                        show_transport(
+                            ui,
+                            &simulation_control,
+                            &simulation_clock,
+                            &mut simulation_control_events,
+                        );
+                    });
                 },
             );
         });
@@ -82,95 +192,286 @@ fn show_ui(
     egui::SidePanel::left("side_panel")
         .default_width(200.0)
         .resizable(true)
-        .show(egui_ctx.ctx_mut(), |ui| {
+        .show(&ctx, |ui| {
             ui.allocate_space(egui::Vec2::new(1.0, 20.0));
 
-            // simulation selection
-            select_simulation(ui, &mut app_state);
+            ui.add_enabled_ui(!replaying, |ui| {
+                // simulation selection
+                world.resource_scope(
+                    |world, mut app_state: Mut<State<AppState>>| {
+                        select_simulation(
+                            ui,
+                            &mut app_state,
+                            world.resource::<Simulations>(),
+                        );
+                    },
+                );
+
+                // presets of the current simulation
+                show_current_presets(ui, world);
+            });
+
+            {
+                let mut state: SystemState<(
+                    Res<Recorder>,
+                    EventWriter<RecordingEvents>,
+                    ResMut<Keymap>,
+                    ResMut<RemoteControl>,
+                    ResMut<DataStream>,
+                    ResMut<Scripting>,
+                    ResMut<Viewports>,
+                    ResMut<PanOrbitSettings>,
+                    ResMut<InstancedRendering>,
+                    ResMut<LodSettings>,
+                    ResMut<VelocityArrows>,
+                    Query<
+                        (&mut PanOrbitCamera, &mut Projection),
+                        With<AppCamera>,
+                    >,
+                )> = SystemState::new(world);
+                let (
+                    recorder,
+                    mut recording_events,
+                    mut keymap,
+                    mut remote_control,
+                    mut data_stream,
+                    mut scripting,
+                    mut viewports,
+                    mut pan_orbit_settings,
+                    mut instanced_rendering,
+                    mut lod_settings,
+                    mut velocity_arrows,
+                    mut orbit_cameras,
+                ) = state.get_mut(world);
+
+                show_recording(ui, &recorder, &mut recording_events);
+
+                ui.add_enabled_ui(!replaying, |ui| {
+                    show_keymap(ui, &mut keymap);
+
+                    show_remote_control(ui, &mut remote_control);
+
+                    show_data_stream(ui, &mut data_stream);
+
+                    show_scripting(ui, &mut scripting);
+
+                    // only the 3d views can be split
+                    if let Ok((mut pan_orbit, mut projection)) =
+                        orbit_cameras.get_single_mut()
+                    {
+                        show_viewports(ui, &mut viewports);
+                        show_camera_controls(
+                            ui,
+                            &mut pan_orbit,
+                            &mut projection,
+                            &mut pan_orbit_settings,
+                        );
+                        show_instanced_rendering(ui, &mut instanced_rendering);
+                        show_level_of_detail(ui, &mut lod_settings);
+                        show_velocity_arrows(ui, &mut velocity_arrows);
+                    }
+                });
+            }
 
             ui.separator();
 
             // simulation parameter
-            match app_state.current() {
-                AppState::Wave2dSimulation => {
-                    wave_2d_simulation::show_ui(
-                        ui,
-                        &mut ui_state,
-                        &mut app_state,
-                        &mut wave_2d_parameters,
-                        wave_2d_events,
-                    );
-                }
-                AppState::LongitudinalWaveSimulation3d => {
-                    longitudinal_wave_3d_simulation::show_ui(
-                        ui,
-                        &mut app_state,
-                        &mut longitudinal_wave_3d_parameters,
-                        longitudinal_wave_3d_events,
-                        &mut rapier_debug_config,
-                    );
-                }
-                AppState::ParticleMess => {
-                    particle_mess::show_ui(
-                        ui,
-                        &mut rapier_debug_config,
-                        particle_mess_events,
-                        &mut particle_mess_parameters,
-                    );
-                }
-                AppState::WaveInPanel => {
-                    wave_in_panel::show_ui(
-                        ui,
-                        &mut rapier_debug_config,
-                        wave_in_panel_events,
-                        &mut wave_in_panel_parameters,
-                    );
-                }
-            }
+            ui.add_enabled_ui(!replaying, |ui| show_current_ui(ui, world));
 
             // debug info
-            show_debug(ui, &diagnostics, &mut ui_state);
+            let debug_info = current_debug_info(world);
+            world.resource_scope(|world, mut ui_state: Mut<UiState>| {
+                show_debug(
+                    ui,
+                    world.resource::<Diagnostics>(),
+                    &mut ui_state,
+                    debug_info,
+                );
+            });
         });
+
+    world.resource_mut::<UiState>().free_area = ctx.available_rect();
 }
 
-fn select_simulation(ui: &mut egui::Ui, app_state: &mut State<AppState>) {
+fn select_simulation(
+    ui: &mut egui::Ui,
+    app_state: &mut State<AppState>,
+    simulations: &Simulations,
+) {
     ui.heading("Simulations: ");
     let mut current_state = app_state.current().clone();
     egui::ComboBox::from_id_source("simulation_selection")
-        .selected_text(format!("{:?}", current_state))
+        .selected_text(current_state.name())
         .show_ui(ui, |ui| {
-            ui.selectable_value(
-                &mut current_state,
-                AppState::Wave2dSimulation,
-                String::from(AppState::Wave2dSimulation),
-            );
-            ui.selectable_value(
-                &mut current_state,
-                AppState::LongitudinalWaveSimulation3d,
-                String::from(AppState::LongitudinalWaveSimulation3d),
-            );
-            ui.selectable_value(
-                &mut current_state,
-                AppState::ParticleMess,
-                String::from(AppState::ParticleMess),
-            );
-            ui.selectable_value(
-                &mut current_state,
-                AppState::WaveInPanel,
-                String::from(AppState::WaveInPanel),
-            );
+            for state in simulations.states() {
+                ui.selectable_value(
+                    &mut current_state,
+                    state.clone(),
+                    state.name(),
+                );
+            }
         });
     if current_state != *app_state.current() {
         app_state.set(current_state).unwrap();
     }
 }
 
+pub fn to_color32(color: Color) -> egui::Color32 {
+    let [r, g, b, a] = color.as_rgba_f32();
+    egui::Color32::from_rgba_unmultiplied(
+        (r * 255.0) as u8,
+        (g * 255.0) as u8,
+        (b * 255.0) as u8,
+        (a * 255.0) as u8,
+    )
+}
+
+pub fn select_colormap(ui: &mut egui::Ui, colormap: &mut Colormap) {
+    ui.horizontal(|ui| {
+        ui.label("colormap:");
+        egui::ComboBox::from_id_source("colormap_selection")
+            .selected_text(String::from(*colormap))
+            .show_ui(ui, |ui| {
+                for option in Colormap::ALL {
+                    ui.selectable_value(colormap, option, String::from(option));
+                }
+            });
+    });
+}
+
+/// Horizontal bar sampling the colormap from -1.0 to 1.0, labelled at both
+/// ends
+pub fn show_colormap_legend(
+    ui: &mut egui::Ui,
+    colormap: Colormap,
+    low_label: &str,
+    high_label: &str,
+) {
+    const SEGMENTS: usize = 64;
+
+    ui.horizontal(|ui| {
+        ui.label(low_label);
+
+        let (rect, _) = ui.allocate_exact_size(
+            egui::vec2(120.0, ui.spacing().interact_size.y),
+            egui::Sense::hover(),
+        );
+        let width = rect.width() / SEGMENTS as f32;
+        for i in 0..SEGMENTS {
+            let amplitude = (i as f32 + 0.5) / SEGMENTS as f32 * 2.0 - 1.0;
+            let left = rect.left() + i as f32 * width;
+
+            ui.painter().rect_filled(
+                egui::Rect::from_min_max(
+                    egui::pos2(left, rect.top()),
+                    egui::pos2(left + width, rect.bottom()),
+                ),
+                0.0,
+                to_color32(colormap.color(amplitude)),
+            );
+        }
+
+        ui.label(high_label);
+    });
+}
+
+/// Slider of a numeric parameter, described once and rendered by
+/// `show_tunables`
+pub struct TunableParameter<T> {
+    pub name: &'static str,
+    pub range: RangeInclusive<f64>,
+    /// zero lets the slider move freely
+    pub step: f64,
+    pub logarithmic: bool,
+    pub integer: bool,
+    pub hover_text: Option<&'static str>,
+    pub get: fn(&T) -> f64,
+    pub set: fn(&mut T, f64),
+}
+
+impl<T> TunableParameter<T> {
+    pub fn new(
+        name: &'static str,
+        range: RangeInclusive<f64>,
+        get: fn(&T) -> f64,
+        set: fn(&mut T, f64),
+    ) -> Self {
+        Self {
+            name,
+            range,
+            step: 0.0,
+            logarithmic: false,
+            integer: false,
+            hover_text: None,
+            get,
+            set,
+        }
+    }
+
+    pub fn step_by(mut self, step: f64) -> Self {
+        self.step = step;
+        self
+    }
+
+    pub fn logarithmic(mut self) -> Self {
+        self.logarithmic = true;
+        self
+    }
+
+    pub fn integer(mut self) -> Self {
+        self.integer = true;
+        self
+    }
+
+    pub fn on_hover_text(mut self, hover_text: &'static str) -> Self {
+        self.hover_text = Some(hover_text);
+        self
+    }
+}
+
+/// Parameters whose plain sliders are built from a list of descriptors
+/// instead of by hand
+pub trait Tunable: Sized {
+    fn tunables() -> Vec<TunableParameter<Self>>;
+}
+
+/// Renders a slider for every tunable parameter, in the order of the
+/// descriptors
+pub fn show_tunables<T: Tunable>(ui: &mut egui::Ui, parameters: &mut T) {
+    for tunable in T::tunables() {
+        let mut value = (tunable.get)(parameters);
+
+        let mut slider = egui::Slider::new(&mut value, tunable.range.clone())
+            .logarithmic(tunable.logarithmic)
+            .text(tunable.name);
+        if tunable.integer {
+            slider = slider.integer();
+        }
+        if tunable.step > 0.0 {
+            slider = slider.step_by(tunable.step);
+        }
+
+        let mut response = ui.add(slider);
+        if let Some(hover_text) = tunable.hover_text {
+            response = response.on_hover_text(hover_text);
+        }
+        if response.changed() {
+            (tunable.set)(parameters, value);
+        }
+    }
+}
+
 fn show_debug(
     ui: &mut egui::Ui,
     diagnostics: &Diagnostics,
     ui_state: &mut UiState,
+    debug_info: Option<String>,
 ) {
     ui.with_layout(egui::Layout::bottom_up(egui::Align::Center), |ui| {
+        if let Some(debug_info) = debug_info {
+            ui.label(debug_info);
+        }
+
         if let Some(fps) =
             diagnostics.get_measurement(FrameTimeDiagnosticsPlugin::FPS)
         {
@@ -0,0 +1,104 @@
+use bevy::prelude::*;
+use bevy_egui::{egui, EguiContext};
+use bevy_rapier3d::prelude::*;
+
+use crate::hover::Hoverable;
+use crate::viewports::world_to_egui;
+use crate::AppCamera;
+
+const ARROW_COLOR: egui::Color32 = egui::Color32::from_rgb(120, 220, 120);
+
+/// Arrows of the velocities of the particles in the 3d simulations
+#[derive(Resource)]
+pub struct VelocityArrows {
+    pub show: bool,
+    /// draws an arrow for every n-th particle
+    pub every: usize,
+    /// arrow length in world units per velocity
+    pub scale: f32,
+}
+
+impl Default for VelocityArrows {
+    fn default() -> Self {
+        Self {
+            show: false,
+            every: 1,
+            scale: 0.5,
+        }
+    }
+}
+
+pub struct VelocityArrowsPlugin;
+
+impl Plugin for VelocityArrowsPlugin {
+    fn build(&self, app: &mut App) {
+        app.insert_resource(VelocityArrows::default())
+            .add_system(draw_velocity_arrows);
+    }
+}
+
+fn draw_velocity_arrows(
+    mut egui_ctx: ResMut<EguiContext>,
+    windows: Res<Windows>,
+    arrows: Res<VelocityArrows>,
+    cameras: Query<(&Camera, &GlobalTransform), With<AppCamera>>,
+    particles: Query<(&GlobalTransform, &Velocity), With<Hoverable>>,
+) {
+    if !arrows.show {
+        return;
+    }
+
+    let ((camera, camera_transform), window) =
+        match (cameras.get_single(), windows.get_primary()) {
+            (Ok(camera), Some(window)) => (camera, window),
+            _ => return,
+        };
+
+    let to_egui =
+        |world: Vec3| world_to_egui(camera, camera_transform, window, world);
+
+    let painter = egui_ctx.ctx_mut().layer_painter(egui::LayerId::new(
+        egui::Order::Foreground,
+        egui::Id::new("velocity_arrows"),
+    ));
+    let stroke = egui::Stroke::new(1.0, ARROW_COLOR);
+
+    for (transform, velocity) in particles.iter().step_by(arrows.every.max(1)) {
+        let start = transform.translation();
+        let end = start + velocity.linvel * arrows.scale;
+
+        if let (Some(start), Some(end)) = (to_egui(start), to_egui(end)) {
+            paint_arrow(&painter, start, end, stroke);
+        }
+    }
+}
+
+/// Arrow from `start` to `end`, skipped if it's shorter than a pixel as it
+/// would only draw its head
+pub fn paint_arrow(
+    painter: &egui::Painter,
+    start: egui::Pos2,
+    end: egui::Pos2,
+    stroke: egui::Stroke,
+) {
+    if start.distance(end) >= 1.0 {
+        painter.arrow(start, end - start, stroke);
+    }
+}
+
+pub fn show_velocity_arrows(ui: &mut egui::Ui, arrows: &mut VelocityArrows) {
+    ui.add(egui::Checkbox::new(&mut arrows.show, "velocity arrows"));
+
+    if arrows.show {
+        ui.add(
+            egui::Slider::new(&mut arrows.every, 1..=100)
+                .logarithmic(true)
+                .text("arrow for every n-th particle"),
+        );
+        ui.add(
+            egui::Slider::new(&mut arrows.scale, 0.01..=10.0)
+                .logarithmic(true)
+                .text("arrow length per velocity"),
+        );
+    }
+}
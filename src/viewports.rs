@@ -0,0 +1,287 @@
+use bevy::core_pipeline::clear_color::ClearColorConfig;
+use bevy::math::Rect;
+use bevy::prelude::*;
+use bevy::render::camera::Viewport;
+use bevy_egui::egui;
+
+use crate::pan_orbit_camera::PanOrbitCamera;
+use crate::ui::UiState;
+use crate::AppCamera;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ViewportLayout {
+    Single,
+    /// free orbit next to the top view
+    SideBySide,
+    /// free orbit, top, front and side view in a 2x2 grid
+    Quad,
+}
+
+impl ViewportLayout {
+    pub const ALL: [ViewportLayout; 3] = [
+        ViewportLayout::Single,
+        ViewportLayout::SideBySide,
+        ViewportLayout::Quad,
+    ];
+
+    /// Views of the layout with their region as fractions of the free part
+    /// of the window, `None` is the free orbit camera of the simulation
+    fn regions(self) -> Vec<(Option<ViewDirection>, Rect)> {
+        let region = |min_x, min_y, max_x, max_y| Rect {
+            min: Vec2::new(min_x, min_y),
+            max: Vec2::new(max_x, max_y),
+        };
+
+        match self {
+            ViewportLayout::Single => vec![(None, region(0.0, 0.0, 1.0, 1.0))],
+            ViewportLayout::SideBySide => vec![
+                (None, region(0.0, 0.0, 0.5, 1.0)),
+                (Some(ViewDirection::Top), region(0.5, 0.0, 1.0, 1.0)),
+            ],
+            ViewportLayout::Quad => vec![
+                (None, region(0.0, 0.0, 0.5, 0.5)),
+                (Some(ViewDirection::Top), region(0.5, 0.0, 1.0, 0.5)),
+                (Some(ViewDirection::Front), region(0.0, 0.5, 0.5, 1.0)),
+                (Some(ViewDirection::Side), region(0.5, 0.5, 1.0, 1.0)),
+            ],
+        }
+    }
+}
+
+impl From<ViewportLayout> for String {
+    fn from(value: ViewportLayout) -> Self {
+        match value {
+            ViewportLayout::Single => "single".to_string(),
+            ViewportLayout::SideBySide => "side by side".to_string(),
+            ViewportLayout::Quad => "quad".to_string(),
+        }
+    }
+}
+
+/// Fixed direction of an additional view onto the focus of the free orbit
+/// camera
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+enum ViewDirection {
+    Top,
+    Front,
+    Side,
+}
+
+impl ViewDirection {
+    /// Direction from the focus to the camera
+    fn offset(self) -> Vec3 {
+        match self {
+            ViewDirection::Top => Vec3::Y,
+            ViewDirection::Front => Vec3::Z,
+            ViewDirection::Side => Vec3::X,
+        }
+    }
+
+    fn up(self) -> Vec3 {
+        match self {
+            ViewDirection::Top => Vec3::NEG_Z,
+            ViewDirection::Front | ViewDirection::Side => Vec3::Y,
+        }
+    }
+}
+
+/// Layout of the cameras of the 3d simulations
+#[derive(Resource)]
+pub struct Viewports {
+    pub layout: ViewportLayout,
+}
+
+impl Default for Viewports {
+    fn default() -> Self {
+        Self {
+            layout: ViewportLayout::Single,
+        }
+    }
+}
+
+/// Camera which follows the focus of the free orbit camera from a fixed
+/// direction
+#[derive(Component)]
+struct AuxiliaryView(ViewDirection);
+
+pub struct ViewportPlugin;
+
+impl Plugin for ViewportPlugin {
+    fn build(&self, app: &mut App) {
+        app.insert_resource(Viewports::default())
+            .add_system(update_auxiliary_views)
+            .add_system(update_viewports.after(update_auxiliary_views));
+    }
+}
+
+/// Spawns the views of the layout while a simulation shows a 3d camera and
+/// removes them otherwise
+fn update_auxiliary_views(
+    mut commands: Commands,
+    viewports: Res<Viewports>,
+    app_cameras: Query<(), (With<AppCamera>, With<PanOrbitCamera>)>,
+    views: Query<(Entity, &AuxiliaryView)>,
+) {
+    let directions: Vec<ViewDirection> = if app_cameras.is_empty() {
+        Vec::new()
+    } else {
+        viewports
+            .layout
+            .regions()
+            .into_iter()
+            .filter_map(|(direction, _)| direction)
+            .collect()
+    };
+
+    for (entity, view) in views.iter() {
+        if !directions.contains(&view.0) {
+            commands.entity(entity).despawn();
+        }
+    }
+
+    for (i, direction) in directions.into_iter().enumerate() {
+        if views.iter().any(|(_, view)| view.0 == direction) {
+            continue;
+        }
+
+        commands.spawn((
+            AuxiliaryView(direction),
+            Camera3dBundle {
+                camera: Camera {
+                    // the free orbit camera already cleared the window
+                    priority: i as isize + 1,
+                    ..default()
+                },
+                camera_3d: Camera3d {
+                    clear_color: ClearColorConfig::None,
+                    ..default()
+                },
+                ..default()
+            },
+        ));
+    }
+}
+
+#[allow(clippy::type_complexity)]
+fn update_viewports(
+    windows: Res<Windows>,
+    ui_state: Res<UiState>,
+    viewports: Res<Viewports>,
+    mut app_cameras: Query<
+        (&mut Camera, &PanOrbitCamera),
+        (With<AppCamera>, Without<AuxiliaryView>),
+    >,
+    mut views: Query<
+        (&mut Camera, &mut Transform, &AuxiliaryView),
+        Without<AppCamera>,
+    >,
+) {
+    let window = if let Some(window) = windows.get_primary() {
+        window
+    } else {
+        return;
+    };
+    let (mut app_camera, pan_orbit) =
+        if let Ok(app_camera) = app_cameras.get_single_mut() {
+            app_camera
+        } else {
+            return;
+        };
+
+    if viewports.layout == ViewportLayout::Single {
+        if app_camera.viewport.is_some() {
+            app_camera.viewport = None;
+        }
+        return;
+    }
+
+    let (position, size) = ui_state.free_area(window);
+    let to_viewport = |region: Rect| {
+        let min = (region.min * size.as_vec2()).as_uvec2();
+        let max = (region.max * size.as_vec2()).as_uvec2();
+        Viewport {
+            physical_position: position + min,
+            physical_size: (max - min).max(UVec2::ONE),
+            ..default()
+        }
+    };
+
+    for (direction, region) in viewports.layout.regions() {
+        let direction = if let Some(direction) = direction {
+            direction
+        } else {
+            app_camera.viewport = Some(to_viewport(region));
+            continue;
+        };
+
+        if let Some((mut camera, mut transform, _)) =
+            views.iter_mut().find(|(_, _, view)| view.0 == direction)
+        {
+            camera.viewport = Some(to_viewport(region));
+            *transform = Transform::from_translation(
+                pan_orbit.focus + direction.offset() * pan_orbit.radius,
+            )
+            .looking_at(pan_orbit.focus, direction.up());
+        }
+    }
+}
+
+/// Bottom left corner of the viewport of the camera in logical window
+/// coordinates, which have their origin bottom left like the cursor
+pub fn viewport_origin(camera: &Camera, window: &Window) -> Vec2 {
+    let viewport = if let Some(viewport) = &camera.viewport {
+        viewport
+    } else {
+        return Vec2::ZERO;
+    };
+    let scale_factor = window.scale_factor() as f32;
+    // the viewport has its origin top left
+    let top_left = viewport.physical_position.as_vec2() / scale_factor;
+    let height = viewport.physical_size.y as f32 / scale_factor;
+
+    Vec2::new(top_left.x, window.height() - top_left.y - height)
+}
+
+/// Cursor position relative to the viewport of the camera, as
+/// `Camera::viewport_to_world` expects it, none if the cursor is outside of
+/// the viewport
+pub fn cursor_in_viewport(camera: &Camera, window: &Window) -> Option<Vec2> {
+    let position = window.cursor_position()? - viewport_origin(camera, window);
+    let size = camera.logical_viewport_size()?;
+
+    (position.cmpge(Vec2::ZERO).all() && position.cmplt(size).all())
+        .then_some(position)
+}
+
+/// Egui position of a position relative to the viewport of the camera
+pub fn viewport_to_egui(
+    camera: &Camera,
+    window: &Window,
+    position: Vec2,
+) -> egui::Pos2 {
+    let position = position + viewport_origin(camera, window);
+    // the window has its origin bottom left, egui top left
+    egui::pos2(position.x, window.height() - position.y)
+}
+
+/// Egui position of a point in the world seen by the camera, none if the
+/// camera can't project it
+pub fn world_to_egui(
+    camera: &Camera,
+    camera_transform: &GlobalTransform,
+    window: &Window,
+    world: Vec3,
+) -> Option<egui::Pos2> {
+    camera
+        .world_to_viewport(camera_transform, world)
+        .map(|position| viewport_to_egui(camera, window, position))
+}
+
+pub fn show_viewports(ui: &mut egui::Ui, viewports: &mut Viewports) {
+    ui.horizontal(|ui| {
+        ui.label("views:");
+        for option in ViewportLayout::ALL {
+            ui.radio_value(&mut viewports.layout, option, String::from(option));
+        }
+    });
+}
@@ -0,0 +1,160 @@
+use std::fs::File;
+use std::io::{self, BufWriter, Write};
+
+use bevy::prelude::*;
+use ndarray::ArrayView2;
+
+use crate::file_dialog::pick_file;
+
+const VTK_DIRECTORY: &str = "exports";
+
+/// Asks for a file name and writes a VTK file with `write`, e.g. with
+/// [`write_points`]
+pub fn save_vtk(
+    name: &str,
+    extension: &str,
+    write: impl FnOnce(&mut BufWriter<File>) -> io::Result<()>,
+) {
+    let path =
+        if let Some(path) = pick_file(VTK_DIRECTORY, extension, name, true) {
+            path
+        } else {
+            return;
+        };
+
+    let result = File::create(&path).and_then(|file| {
+        let mut writer = BufWriter::new(file);
+        write(&mut writer)?;
+        writer.flush()
+    });
+
+    match result {
+        Ok(()) => info!("exported {}", path.display()),
+        Err(error) => {
+            error!("failed to export {}: {}", path.display(), error)
+        }
+    }
+}
+
+/// Writes fields of a regular 2d grid as VTK image data (`.vti`), one
+/// point per cell `spacing` apart, the names are shown by ParaView
+pub fn write_image_data(
+    writer: &mut impl Write,
+    fields: &[(&str, ArrayView2<f32>)],
+    spacing: f32,
+) -> io::Result<()> {
+    let (dimx, dimy) = fields.first().map_or((0, 0), |(_, field)| field.dim());
+    let extent = format!(
+        "0 {} 0 {} 0 0",
+        dimx.saturating_sub(1),
+        dimy.saturating_sub(1)
+    );
+
+    write_header(writer, "ImageData")?;
+    writeln!(
+        writer,
+        r#"<ImageData WholeExtent="{}" Origin="0 0 0" Spacing="{} {} 1">"#,
+        extent, spacing, spacing
+    )?;
+    writeln!(writer, r#"<Piece Extent="{}">"#, extent)?;
+
+    let scalars = fields.first().map_or("", |(name, _)| name);
+    writeln!(writer, r#"<PointData Scalars="{}">"#, scalars)?;
+    for (name, field) in fields {
+        begin_data_array(writer, "Float32", name, 1)?;
+        // x runs fastest in VTK, the transposed view iterates that way
+        write_values(writer, field.t().iter().copied())?;
+        writeln!(writer, "</DataArray>")?;
+    }
+    writeln!(writer, "</PointData>")?;
+
+    writeln!(writer, "</Piece>")?;
+    writeln!(writer, "</ImageData>")?;
+    writeln!(writer, "</VTKFile>")
+}
+
+/// Writes particles as VTK unstructured grid (`.vtu`) of vertex cells, with
+/// their displacement from the rest position as vector attribute
+pub fn write_points(
+    writer: &mut impl Write,
+    positions: &[Vec3],
+    displacements: &[Vec3],
+) -> io::Result<()> {
+    let count = positions.len();
+
+    write_header(writer, "UnstructuredGrid")?;
+    writeln!(writer, "<UnstructuredGrid>")?;
+    writeln!(
+        writer,
+        r#"<Piece NumberOfPoints="{}" NumberOfCells="{}">"#,
+        count, count
+    )?;
+
+    writeln!(writer, r#"<PointData Vectors="displacement">"#)?;
+    begin_data_array(writer, "Float32", "displacement", 3)?;
+    write_values(writer, displacements.iter().flat_map(|d| d.to_array()))?;
+    writeln!(writer, "</DataArray>")?;
+    writeln!(writer, "</PointData>")?;
+
+    writeln!(writer, "<Points>")?;
+    begin_data_array(writer, "Float32", "position", 3)?;
+    write_values(writer, positions.iter().flat_map(|p| p.to_array()))?;
+    writeln!(writer, "</DataArray>")?;
+    writeln!(writer, "</Points>")?;
+
+    // every point is a cell of its own, VTK_VERTEX is type 1
+    writeln!(writer, "<Cells>")?;
+    begin_data_array(writer, "Int32", "connectivity", 1)?;
+    write_values(writer, 0..count)?;
+    writeln!(writer, "</DataArray>")?;
+    begin_data_array(writer, "Int32", "offsets", 1)?;
+    write_values(writer, 1..=count)?;
+    writeln!(writer, "</DataArray>")?;
+    begin_data_array(writer, "UInt8", "types", 1)?;
+    write_values(writer, std::iter::repeat(1).take(count))?;
+    writeln!(writer, "</DataArray>")?;
+    writeln!(writer, "</Cells>")?;
+
+    writeln!(writer, "</Piece>")?;
+    writeln!(writer, "</UnstructuredGrid>")?;
+    writeln!(writer, "</VTKFile>")
+}
+
+fn write_header(writer: &mut impl Write, kind: &str) -> io::Result<()> {
+    writeln!(writer, r#"<?xml version="1.0"?>"#)?;
+    writeln!(
+        writer,
+        r#"<VTKFile type="{}" version="0.1" byte_order="LittleEndian">"#,
+        kind
+    )
+}
+
+fn begin_data_array(
+    writer: &mut impl Write,
+    data_type: &str,
+    name: &str,
+    components: usize,
+) -> io::Result<()> {
+    writeln!(
+        writer,
+        r#"<DataArray type="{}" Name="{}" NumberOfComponents="{}" "#,
+        data_type, name, components
+    )?;
+    writeln!(writer, r#"format="ascii">"#)
+}
+
+/// Writes values separated by spaces, a line per few values keeps the
+/// file readable
+fn write_values<T: std::fmt::Display>(
+    writer: &mut impl Write,
+    values: impl Iterator<Item = T>,
+) -> io::Result<()> {
+    for (index, value) in values.enumerate() {
+        if index > 0 {
+            let separator = if index % 12 == 0 { "\n" } else { " " };
+            write!(writer, "{}", separator)?;
+        }
+        write!(writer, "{}", value)?;
+    }
+    writeln!(writer)
+}
@@ -0,0 +1,704 @@
+use std::f32::consts::TAU;
+
+use bevy::ecs::system::SystemState;
+use bevy::prelude::*;
+use bevy::render::mesh::{Indices, VertexAttributeValues};
+use bevy::render::render_resource::PrimitiveTopology;
+use bevy::sprite::Mesh2dHandle;
+use bevy_egui::egui;
+use serde::{Deserialize, Serialize};
+
+use crate::colored_mesh::{ColoredMesh2d, VERTEX_ATTRIBUTE_COLOR_ID};
+use crate::persistence::{PersistenceAppExt, RestoreParameters};
+use crate::recording::{
+    RecordableEvent, RecordableParameters, RecordingAppExt,
+};
+use crate::simulation::Simulation;
+use crate::simulation_control::{
+    forward_reset, ResetEvent, SimulationClock, SimulationControl,
+};
+use crate::ui::{show_tunables, Tunable, TunableParameter};
+use crate::{AppCamera, AppState};
+
+mod string;
+
+use string::{
+    harmonic, EndCondition, StringEnd, WaveString, MAX_STABLE_CFL_NUMBER,
+};
+
+/// Points the string is sampled at, including both ends
+const POINTS: usize = 201;
+
+/// Length of the string in m
+const LENGTH: f32 = 1.0;
+
+/// Width of the drawn string in world units
+const STRING_WIDTH: f32 = 900.0;
+
+/// World units per m of displacement
+const AMPLITUDE_SCALE: f32 = 300.0;
+
+/// The side panel covers the left part of the window, the camera looks a bit
+/// to the left so the string is centered in the rest
+const CAMERA_OFFSET: f32 = -175.0;
+
+/// Harmonics which can be overlaid
+const MAX_HARMONICS: usize = 6;
+
+/// An end is picked for dragging when clicked closer than this
+const PICK_DISTANCE: f32 = 20.0;
+
+const END_MARKER_RADIUS: f32 = 8.0;
+
+const STRING_COLOR: Color = Color::WHITE;
+const FIXED_END_COLOR: Color = Color::rgb(0.9, 0.3, 0.2);
+const FREE_END_COLOR: Color = Color::rgb(0.3, 0.8, 0.4);
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub enum Excitation {
+    /// a click displaces the string into a triangle and releases it
+    Pluck,
+    /// the left end moves up and down sinusoidally
+    Drive,
+}
+
+impl Excitation {
+    pub const ALL: [Excitation; 2] = [Excitation::Pluck, Excitation::Drive];
+}
+
+impl From<Excitation> for String {
+    fn from(value: Excitation) -> Self {
+        match value {
+            Excitation::Pluck => "pluck".to_string(),
+            Excitation::Drive => "drive".to_string(),
+        }
+    }
+}
+
+#[derive(Clone, Resource, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Wave1dParameters {
+    /// in m/s
+    pub wave_velocity: f32,
+    pub dt: f32,
+    /// fraction of the velocity every point loses per time step
+    pub loss: f32,
+    pub time_scale: f32,
+    pub steps_per_frame: usize,
+    /// left and right end
+    pub ends: [EndCondition; 2],
+    pub excitation: Excitation,
+    /// in m
+    pub pluck_amplitude: f32,
+    pub drive_frequency: f32,
+    /// in m
+    pub drive_amplitude: f32,
+    /// number of harmonics drawn on top of the string
+    pub harmonics: usize,
+}
+
+impl Default for Wave1dParameters {
+    fn default() -> Self {
+        Self {
+            wave_velocity: 2.0,
+            dt: 0.002,
+            loss: 0.0005,
+            time_scale: 1.0,
+            steps_per_frame: 50,
+            ends: [EndCondition::Fixed, EndCondition::Fixed],
+            excitation: Excitation::Pluck,
+            pluck_amplitude: 0.2,
+            drive_frequency: 1.0,
+            drive_amplitude: 0.05,
+            harmonics: 0,
+        }
+    }
+}
+
+impl Wave1dParameters {
+    pub fn cfl_number(&self) -> f32 {
+        self.wave_velocity * self.dt / (LENGTH / (POINTS - 1) as f32)
+    }
+
+    pub fn is_stable(&self) -> bool {
+        self.cfl_number() <= MAX_STABLE_CFL_NUMBER
+    }
+}
+
+impl RecordableParameters for Wave1dParameters {
+    const KIND: &'static str = "wave_1d_parameters";
+    const SIMULATION: AppState = Wave1dSimulationPlugin::STATE;
+
+    fn restore(&mut self, recorded: Self) {
+        *self = recorded;
+    }
+}
+
+impl Tunable for Wave1dParameters {
+    fn tunables() -> Vec<TunableParameter<Self>> {
+        vec![
+            TunableParameter::<Self>::new(
+                "wave velocity in m/s",
+                0.1..=10.0,
+                |p| p.wave_velocity as f64,
+                |p, v| p.wave_velocity = v as f32,
+            )
+            .step_by(0.01),
+            TunableParameter::<Self>::new(
+                "time step in s",
+                0.0001..=0.01,
+                |p| p.dt as f64,
+                |p, v| p.dt = v as f32,
+            )
+            .logarithmic(),
+            TunableParameter::<Self>::new(
+                "loss per step",
+                0.0..=0.01,
+                |p| p.loss as f64,
+                |p, v| p.loss = v as f32,
+            )
+            .step_by(0.0001),
+            TunableParameter::<Self>::new(
+                "time scale",
+                0.01..=2.0,
+                |p| p.time_scale as f64,
+                |p, v| p.time_scale = v as f32,
+            )
+            .logarithmic(),
+            TunableParameter::<Self>::new(
+                "max steps per frame",
+                1.0..=200.0,
+                |p| p.steps_per_frame as f64,
+                |p, v| p.steps_per_frame = v as usize,
+            )
+            .integer(),
+            TunableParameter::<Self>::new(
+                "pluck amplitude in m",
+                0.01..=0.5,
+                |p| p.pluck_amplitude as f64,
+                |p, v| p.pluck_amplitude = v as f32,
+            )
+            .step_by(0.01),
+            TunableParameter::<Self>::new(
+                "drive frequency in Hz",
+                0.0..=20.0,
+                |p| p.drive_frequency as f64,
+                |p, v| p.drive_frequency = v as f32,
+            )
+            .step_by(0.01)
+            .on_hover_text("the harmonics below are resonant"),
+            TunableParameter::<Self>::new(
+                "drive amplitude in m",
+                0.0..=0.2,
+                |p| p.drive_amplitude as f64,
+                |p, v| p.drive_amplitude = v as f32,
+            )
+            .step_by(0.001),
+            TunableParameter::<Self>::new(
+                "harmonics",
+                0.0..=MAX_HARMONICS as f64,
+                |p| p.harmonics as f64,
+                |p, v| p.harmonics = v as usize,
+            )
+            .integer(),
+        ]
+    }
+}
+
+#[derive(Clone, Copy, Debug, Serialize, Deserialize)]
+pub enum Wave1dUiEvents {
+    Reset,
+    /// plucks at a fraction of the length
+    Pluck(f32),
+    /// holds an end at a displacement in m until it is released
+    HoldEnd(StringEnd, f32),
+    ReleaseEnd(StringEnd),
+}
+
+impl RecordableEvent for Wave1dUiEvents {
+    const KIND: &'static str = "wave_1d_ui";
+}
+
+impl ResetEvent for Wave1dUiEvents {
+    fn reset() -> Self {
+        Wave1dUiEvents::Reset
+    }
+}
+
+#[derive(Default, Resource)]
+struct StringState {
+    string: WaveString,
+    /// displacement of the ends which are dragged
+    held: [Option<f32>; 2],
+    elapsed_secs: f32,
+    /// simulated time not yet covered by a solver step
+    accumulator: f32,
+}
+
+impl StringState {
+    fn reset(&mut self) {
+        *self = Self {
+            string: WaveString::new(POINTS),
+            ..default()
+        };
+    }
+}
+
+#[derive(Component)]
+struct StringLine;
+
+/// Standing wave of the `n`-th harmonic, starting at 1
+#[derive(Component)]
+struct HarmonicLine(usize);
+
+#[derive(Component)]
+struct EndMarker(StringEnd);
+
+pub struct Wave1dSimulationPlugin;
+
+impl Plugin for Wave1dSimulationPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_event::<Wave1dUiEvents>()
+            .add_recordable_event::<Wave1dUiEvents>()
+            .add_recordable_parameters::<Wave1dParameters>()
+            .add_persistent_parameters::<Wave1dParameters>()
+            .insert_resource(Wave1dParameters::default())
+            .insert_resource(StringState::default())
+            .add_system_set(
+                SystemSet::on_enter(Wave1dSimulationPlugin::STATE)
+                    .with_system(setup.after(RestoreParameters)),
+            )
+            .add_system_set(
+                SystemSet::on_update(Wave1dSimulationPlugin::STATE)
+                    .with_system(forward_reset::<Wave1dUiEvents>)
+                    .with_system(on_mouse_events)
+                    .with_system(on_ui_events)
+                    .with_system(update_string.after(on_ui_events))
+                    .with_system(update_lines.after(update_string))
+                    .with_system(update_end_markers.after(update_string)),
+            )
+            .add_system_set(
+                SystemSet::on_exit(Wave1dSimulationPlugin::STATE)
+                    .with_system(cleanup),
+            );
+    }
+}
+
+fn setup(
+    mut commands: Commands,
+    mut meshes: ResMut<Assets<Mesh>>,
+    mut state: ResMut<StringState>,
+    cameras: Query<Entity, With<AppCamera>>,
+) {
+    if let Ok(camera_entity) = cameras.get_single() {
+        commands.entity(camera_entity).despawn();
+    }
+
+    state.reset();
+
+    commands.spawn((
+        AppCamera,
+        Camera2dBundle {
+            transform: Transform::from_xyz(CAMERA_OFFSET, 0.0, 999.9),
+            ..default()
+        },
+    ));
+
+    commands.spawn((
+        StringLine,
+        ColoredMesh2d::default(),
+        Mesh2dHandle(meshes.add(line_mesh(STRING_COLOR))),
+        SpatialBundle::from_transform(Transform::from_xyz(0.0, 0.0, 1.0)),
+    ));
+
+    for n in 1..=MAX_HARMONICS {
+        let hue = 360.0 * (n - 1) as f32 / MAX_HARMONICS as f32;
+        commands.spawn((
+            HarmonicLine(n),
+            ColoredMesh2d::default(),
+            Mesh2dHandle(
+                meshes.add(line_mesh(Color::hsla(hue, 0.8, 0.6, 0.6))),
+            ),
+            SpatialBundle::default(),
+        ));
+    }
+
+    for end in StringEnd::ALL {
+        commands.spawn((
+            EndMarker(end),
+            ColoredMesh2d::default(),
+            Mesh2dHandle(meshes.add(disk_mesh(END_MARKER_RADIUS))),
+            SpatialBundle::from_transform(Transform::from_translation(
+                Vec3::new(x_position(end_point(end)), 0.0, 2.0),
+            )),
+        ));
+    }
+}
+
+/// Line strip through all points of the string, the positions are written
+/// every frame
+fn line_mesh(color: Color) -> Mesh {
+    let mut mesh = Mesh::new(PrimitiveTopology::LineStrip);
+    mesh.insert_attribute(
+        Mesh::ATTRIBUTE_POSITION,
+        (0..POINTS)
+            .map(|i| [x_position(i), 0.0, 0.0])
+            .collect::<Vec<[f32; 3]>>(),
+    );
+    mesh.insert_attribute(
+        VERTEX_ATTRIBUTE_COLOR_ID,
+        vec![color.as_linear_rgba_u32(); POINTS],
+    );
+    mesh
+}
+
+/// Triangle fan around the origin, white until the end condition colors it
+fn disk_mesh(radius: f32) -> Mesh {
+    const SEGMENTS: u32 = 24;
+
+    let mut positions = vec![[0.0, 0.0, 0.0]];
+    for i in 0..SEGMENTS {
+        let angle = TAU * i as f32 / SEGMENTS as f32;
+        positions.push([radius * angle.cos(), radius * angle.sin(), 0.0]);
+    }
+    let indices = (0..SEGMENTS)
+        .flat_map(|i| [0, i + 1, (i + 1) % SEGMENTS + 1])
+        .collect();
+
+    let mut mesh = Mesh::new(PrimitiveTopology::TriangleList);
+    mesh.insert_attribute(
+        VERTEX_ATTRIBUTE_COLOR_ID,
+        vec![Color::WHITE.as_linear_rgba_u32(); positions.len()],
+    );
+    mesh.insert_attribute(Mesh::ATTRIBUTE_POSITION, positions);
+    mesh.set_indices(Some(Indices::U32(indices)));
+    mesh
+}
+
+fn x_position(point: usize) -> f32 {
+    (point as f32 / (POINTS - 1) as f32 - 0.5) * STRING_WIDTH
+}
+
+fn end_point(end: StringEnd) -> usize {
+    match end {
+        StringEnd::Left => 0,
+        StringEnd::Right => POINTS - 1,
+    }
+}
+
+/// Dragging an end holds it at the cursor, a click anywhere else plucks the
+/// string there
+fn on_mouse_events(
+    windows: Res<Windows>,
+    buttons: Res<Input<MouseButton>>,
+    parameters: Res<Wave1dParameters>,
+    state: Res<StringState>,
+    cameras: Query<(&Camera, &GlobalTransform), With<AppCamera>>,
+    mut dragging: Local<Option<StringEnd>>,
+    mut ui_events: EventWriter<Wave1dUiEvents>,
+) {
+    if buttons.just_released(MouseButton::Left) {
+        if let Some(end) = dragging.take() {
+            ui_events.send(Wave1dUiEvents::ReleaseEnd(end));
+        }
+        return;
+    }
+
+    let (camera, camera_transform) = if let Ok(camera) = cameras.get_single() {
+        camera
+    } else {
+        return;
+    };
+    let cursor = if let Some(ray) = windows
+        .get_primary()
+        .and_then(|window| window.cursor_position())
+        .and_then(|position| {
+            camera.viewport_to_world(camera_transform, position)
+        }) {
+        ray.origin.truncate()
+    } else {
+        return;
+    };
+    let displacement = cursor.y / AMPLITUDE_SCALE;
+
+    if let Some(end) = *dragging {
+        if state.held[end.index()] != Some(displacement) {
+            ui_events.send(Wave1dUiEvents::HoldEnd(end, displacement));
+        }
+        return;
+    }
+
+    if !buttons.just_pressed(MouseButton::Left) {
+        return;
+    }
+
+    for end in StringEnd::ALL {
+        let point = end_point(end);
+        let position = Vec2::new(
+            x_position(point),
+            state.string.current.get(point).copied().unwrap_or_default()
+                * AMPLITUDE_SCALE,
+        );
+        if position.distance(cursor) < PICK_DISTANCE {
+            *dragging = Some(end);
+            ui_events.send(Wave1dUiEvents::HoldEnd(end, displacement));
+            return;
+        }
+    }
+
+    let position = cursor.x / STRING_WIDTH + 0.5;
+    if parameters.excitation == Excitation::Pluck
+        && (0.0..=1.0).contains(&position)
+    {
+        ui_events.send(Wave1dUiEvents::Pluck(position));
+    }
+}
+
+fn on_ui_events(
+    mut ui_events: EventReader<Wave1dUiEvents>,
+    parameters: Res<Wave1dParameters>,
+    mut state: ResMut<StringState>,
+) {
+    for event in ui_events.iter() {
+        match *event {
+            Wave1dUiEvents::Reset => state.reset(),
+            Wave1dUiEvents::Pluck(position) => {
+                state.string.pluck(position, parameters.pluck_amplitude);
+            }
+            Wave1dUiEvents::HoldEnd(end, displacement) => {
+                state.held[end.index()] = Some(displacement);
+            }
+            Wave1dUiEvents::ReleaseEnd(end) => {
+                state.held[end.index()] = None;
+            }
+        }
+    }
+}
+
+fn update_string(
+    time: Res<Time>,
+    control: Res<SimulationControl>,
+    mut clock: ResMut<SimulationClock>,
+    parameters: Res<Wave1dParameters>,
+    mut state: ResMut<StringState>,
+) {
+    let steps = if control.is_paused() {
+        usize::from(control.is_stepping())
+    } else {
+        state.accumulator +=
+            control.delta_seconds(&time) * parameters.time_scale;
+
+        let steps = ((state.accumulator / parameters.dt).floor() as usize)
+            .min(parameters.steps_per_frame);
+        state.accumulator = (state.accumulator - steps as f32 * parameters.dt)
+            .min(parameters.dt);
+
+        steps
+    };
+    clock.advance(steps, parameters.dt);
+
+    let cfl_number = parameters.cfl_number().min(MAX_STABLE_CFL_NUMBER);
+    for _ in 0..steps {
+        state.elapsed_secs += parameters.dt;
+
+        let mut held = state.held;
+        if parameters.excitation == Excitation::Drive && held[0].is_none() {
+            held[0] = Some(
+                parameters.drive_amplitude
+                    * (TAU * parameters.drive_frequency * state.elapsed_secs)
+                        .sin(),
+            );
+        }
+
+        state
+            .string
+            .step(cfl_number, parameters.loss, parameters.ends, held);
+    }
+}
+
+/// Writes the displacement of the string and the standing waves of the
+/// harmonics into the line meshes
+fn update_lines(
+    parameters: Res<Wave1dParameters>,
+    state: Res<StringState>,
+    mut meshes: ResMut<Assets<Mesh>>,
+    strings: Query<&Mesh2dHandle, With<StringLine>>,
+    mut harmonics: Query<(&HarmonicLine, &Mesh2dHandle, &mut Visibility)>,
+) {
+    let mut write_positions =
+        |mesh_handle: &Mesh2dHandle, displacement: &dyn Fn(usize) -> f32| {
+            let positions = meshes
+                .get_mut(&mesh_handle.0)
+                .and_then(|mesh| mesh.attribute_mut(Mesh::ATTRIBUTE_POSITION));
+            if let Some(VertexAttributeValues::Float32x3(positions)) = positions
+            {
+                for (i, position) in positions.iter_mut().enumerate() {
+                    position[1] = displacement(i) * AMPLITUDE_SCALE;
+                }
+            }
+        };
+
+    for mesh_handle in strings.iter() {
+        write_positions(mesh_handle, &|i| {
+            state.string.current.get(i).copied().unwrap_or_default()
+        });
+    }
+
+    for (harmonic_line, mesh_handle, mut visibility) in harmonics.iter_mut() {
+        let n = harmonic_line.0;
+        let is_visible = n <= parameters.harmonics;
+        if visibility.is_visible != is_visible {
+            visibility.is_visible = is_visible;
+        }
+        if !is_visible {
+            continue;
+        }
+
+        write_positions(mesh_handle, &|i| {
+            let x = i as f32 / (POINTS - 1) as f32;
+            let (shape, frequency) = harmonic(
+                n,
+                x,
+                parameters.ends,
+                parameters.wave_velocity,
+                LENGTH,
+            );
+
+            parameters.pluck_amplitude
+                * shape
+                * (TAU * frequency * state.elapsed_secs).cos()
+        });
+    }
+}
+
+/// Keeps the markers on the ends of the string and colors them by their end
+/// condition
+fn update_end_markers(
+    parameters: Res<Wave1dParameters>,
+    state: Res<StringState>,
+    mut meshes: ResMut<Assets<Mesh>>,
+    mut markers: Query<(&EndMarker, &Mesh2dHandle, &mut Transform)>,
+) {
+    for (marker, mesh_handle, mut transform) in markers.iter_mut() {
+        let point = end_point(marker.0);
+        transform.translation.y =
+            state.string.current.get(point).copied().unwrap_or_default()
+                * AMPLITUDE_SCALE;
+
+        let color = match parameters.ends[marker.0.index()] {
+            EndCondition::Fixed => FIXED_END_COLOR,
+            EndCondition::Free => FREE_END_COLOR,
+        }
+        .as_linear_rgba_u32();
+
+        let colors = meshes
+            .get_mut(&mesh_handle.0)
+            .and_then(|mesh| mesh.attribute_mut(VERTEX_ATTRIBUTE_COLOR_ID));
+        if let Some(VertexAttributeValues::Uint32(colors)) = colors {
+            if colors.first() != Some(&color) {
+                colors.fill(color);
+            }
+        }
+    }
+}
+
+fn cleanup(
+    mut commands: Commands,
+    entities: Query<
+        Entity,
+        Or<(With<StringLine>, With<HarmonicLine>, With<EndMarker>)>,
+    >,
+) {
+    for entity in entities.iter() {
+        commands.entity(entity).despawn();
+    }
+}
+
+impl Simulation for Wave1dSimulationPlugin {
+    const NAME: &'static str = "wave_1d";
+    type Parameters = Wave1dParameters;
+
+    fn show_ui(ui: &mut egui::Ui, world: &mut World) {
+        let mut state: SystemState<(
+            ResMut<Wave1dParameters>,
+            EventWriter<Wave1dUiEvents>,
+        )> = SystemState::new(world);
+        let (mut parameters, mut ui_events) = state.get_mut(world);
+
+        show_ui(ui, &mut parameters, &mut ui_events);
+    }
+}
+
+fn show_ui(
+    ui: &mut egui::Ui,
+    parameters: &mut Wave1dParameters,
+    ui_events: &mut EventWriter<Wave1dUiEvents>,
+) {
+    ui.allocate_space(egui::Vec2::new(1.0, 10.0));
+
+    show_tunables(ui, parameters);
+
+    let cfl_number = parameters.cfl_number();
+    if parameters.is_stable() {
+        ui.label(format!("CFL number: {:.3}", cfl_number));
+    } else {
+        ui.colored_label(
+            egui::Color32::YELLOW,
+            format!(
+                "CFL number: {:.3}, clamped to {:.3}",
+                cfl_number, MAX_STABLE_CFL_NUMBER
+            ),
+        );
+    }
+
+    ui.separator();
+
+    for (end, label) in
+        StringEnd::ALL.into_iter().zip(["left end:", "right end:"])
+    {
+        ui.horizontal(|ui| {
+            ui.label(label);
+            for option in EndCondition::ALL {
+                ui.radio_value(
+                    &mut parameters.ends[end.index()],
+                    option,
+                    String::from(option),
+                );
+            }
+        });
+    }
+
+    ui.horizontal(|ui| {
+        ui.label("excitation:");
+        for option in Excitation::ALL {
+            ui.radio_value(
+                &mut parameters.excitation,
+                option,
+                String::from(option),
+            );
+        }
+    });
+
+    if ui.button("Pluck center").clicked() {
+        ui_events.send(Wave1dUiEvents::Pluck(0.5));
+    }
+
+    if parameters.harmonics > 0 {
+        ui.separator();
+
+        for n in 1..=parameters.harmonics {
+            let (_, frequency) = harmonic(
+                n,
+                0.0,
+                parameters.ends,
+                parameters.wave_velocity,
+                LENGTH,
+            );
+            ui.label(format!("harmonic {}: {:.2} Hz", n, frequency));
+        }
+    }
+
+    ui.separator();
+
+    ui.label("click the string to pluck it, drag an end to move it");
+}
@@ -0,0 +1,233 @@
+use std::f32::consts::PI;
+
+use serde::{Deserialize, Serialize};
+
+/// Largest Courant number `c * dt / dx` for which the leapfrog scheme of the
+/// string stays stable
+pub const MAX_STABLE_CFL_NUMBER: f32 = 1.0;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub enum EndCondition {
+    /// the end is held at zero displacement and reflects inverted
+    Fixed,
+    /// the end slides without friction and reflects upright
+    Free,
+}
+
+impl EndCondition {
+    pub const ALL: [EndCondition; 2] =
+        [EndCondition::Fixed, EndCondition::Free];
+}
+
+impl From<EndCondition> for String {
+    fn from(value: EndCondition) -> Self {
+        match value {
+            EndCondition::Fixed => "fixed".to_string(),
+            EndCondition::Free => "free".to_string(),
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub enum StringEnd {
+    Left,
+    Right,
+}
+
+impl StringEnd {
+    pub const ALL: [StringEnd; 2] = [StringEnd::Left, StringEnd::Right];
+
+    pub fn index(self) -> usize {
+        match self {
+            StringEnd::Left => 0,
+            StringEnd::Right => 1,
+        }
+    }
+}
+
+/// Displacement of the string at every point, advanced with the leapfrog
+/// scheme
+#[derive(Clone, Default)]
+pub struct WaveString {
+    pub current: Vec<f32>,
+    previous: Vec<f32>,
+}
+
+impl WaveString {
+    pub fn new(points: usize) -> Self {
+        Self {
+            current: vec![0.0; points],
+            previous: vec![0.0; points],
+        }
+    }
+
+    /// Triangular displacement with its peak at `position`, a fraction of the
+    /// length, released at rest
+    pub fn pluck(&mut self, position: f32, amplitude: f32) {
+        let last = (self.current.len() - 1) as f32;
+        let peak = (position.clamp(0.0, 1.0) * last).max(f32::EPSILON);
+
+        for (i, u) in self.current.iter_mut().enumerate() {
+            let i = i as f32;
+            *u = if i <= peak {
+                amplitude * i / peak
+            } else {
+                amplitude * (last - i) / (last - peak).max(f32::EPSILON)
+            };
+        }
+        self.previous.clone_from(&self.current);
+    }
+
+    /// Advances the string by one time step.
+    ///
+    /// `held` overrides the displacement of an end, e.g. while it is dragged
+    /// or driven, `loss` is the fraction of the velocity lost per step.
+    pub fn step(
+        &mut self,
+        cfl: f32,
+        loss: f32,
+        ends: [EndCondition; 2],
+        held: [Option<f32>; 2],
+    ) {
+        let n = self.current.len();
+        if n < 3 {
+            return;
+        }
+
+        let cfl2 = cfl * cfl;
+        let u = &self.current;
+        let mut next = vec![0.0; n];
+
+        for i in 0..n {
+            // a free end mirrors its neighbour, so the slope at the end is
+            // zero
+            let left = if i == 0 { u[1] } else { u[i - 1] };
+            let right = if i == n - 1 { u[n - 2] } else { u[i + 1] };
+            let laplace_operator = left - 2.0 * u[i] + right;
+
+            next[i] = (2.0 - loss) * u[i] - (1.0 - loss) * self.previous[i]
+                + cfl2 * laplace_operator;
+        }
+
+        for (end, index) in [(0, 0), (1, n - 1)] {
+            if let Some(displacement) = held[end] {
+                next[index] = displacement;
+            } else if ends[end] == EndCondition::Fixed {
+                next[index] = 0.0;
+            }
+        }
+
+        self.previous = std::mem::replace(&mut self.current, next);
+    }
+}
+
+/// Shape of the `n`-th harmonic (starting at 1) at `x`, a fraction of the
+/// length, and its frequency for the given end conditions
+pub fn harmonic(
+    n: usize,
+    x: f32,
+    ends: [EndCondition; 2],
+    wave_velocity: f32,
+    length: f32,
+) -> (f32, f32) {
+    let n = n as f32;
+    // ends of the same kind fit whole half wavelengths, mixed ends odd
+    // quarter wavelengths, a fixed end is a node and a free end an antinode
+    let (wavenumber, shape): (f32, fn(f32) -> f32) = match ends {
+        [EndCondition::Fixed, EndCondition::Fixed] => (n * PI, f32::sin),
+        [EndCondition::Free, EndCondition::Free] => (n * PI, f32::cos),
+        [EndCondition::Fixed, EndCondition::Free] => ((n - 0.5) * PI, f32::sin),
+        [EndCondition::Free, EndCondition::Fixed] => ((n - 0.5) * PI, f32::cos),
+    };
+
+    let frequency = wavenumber * wave_velocity / (2.0 * PI * length);
+
+    (shape(wavenumber * x), frequency)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const CFL: f32 = 0.9;
+
+    /// String at rest in the given shape
+    fn string_at_rest(
+        shape: impl Fn(usize) -> f32,
+        points: usize,
+    ) -> WaveString {
+        let current: Vec<f32> = (0..points).map(shape).collect();
+        WaveString {
+            previous: current.clone(),
+            current,
+        }
+    }
+
+    #[test]
+    fn fixed_ends_stay_at_zero() {
+        let mut string = WaveString::new(41);
+        string.pluck(0.3, 1.0);
+
+        for _ in 0..200 {
+            string.step(CFL, 0.0, [EndCondition::Fixed; 2], [None; 2]);
+            assert_eq!(string.current[0], 0.0);
+            assert_eq!(string.current[40], 0.0);
+        }
+    }
+
+    #[test]
+    fn free_end_moves_like_the_middle_of_a_mirrored_string() {
+        // the free end of the half string is the middle of the whole one
+        let half = 21;
+        let peak = |i: usize| (half - 1 - i) as f32 / (half - 1) as f32;
+        let mut whole =
+            string_at_rest(|i| peak(i.abs_diff(half - 1)), 2 * half - 1);
+        let mut mirrored = string_at_rest(peak, half);
+
+        for _ in 0..200 {
+            whole.step(CFL, 0.0, [EndCondition::Fixed; 2], [None; 2]);
+            mirrored.step(
+                CFL,
+                0.0,
+                [EndCondition::Free, EndCondition::Fixed],
+                [None; 2],
+            );
+
+            for (i, u) in mirrored.current.iter().enumerate() {
+                assert!((whole.current[half - 1 + i] - u).abs() < 1e-4);
+            }
+            // zero slope at the free end
+            assert!(
+                (whole.current[half - 2] - mirrored.current[1]).abs() < 1e-4
+            );
+        }
+    }
+
+    #[test]
+    fn harmonic_frequencies_follow_the_end_conditions() {
+        let (wave_velocity, length) = (2.0, 3.0);
+
+        for n in 1..=4 {
+            let (_, fixed_fixed) = harmonic(
+                n,
+                0.0,
+                [EndCondition::Fixed; 2],
+                wave_velocity,
+                length,
+            );
+            let (_, fixed_free) = harmonic(
+                n,
+                0.0,
+                [EndCondition::Fixed, EndCondition::Free],
+                wave_velocity,
+                length,
+            );
+
+            let n = n as f32;
+            let expected = n * wave_velocity / (2.0 * length);
+            assert!((fixed_fixed - expected).abs() < 1e-5);
+            let expected = (2.0 * n - 1.0) * wave_velocity / (4.0 * length);
+            assert!((fixed_free - expected).abs() < 1e-5);
+        }
+    }
+}
@@ -0,0 +1,261 @@
+use std::time::Duration;
+
+use bevy::prelude::*;
+use bevy_egui::egui;
+use ndarray::{s, Array2};
+
+use super::simulation_plugin::{update_wave, ApplyingForceTimer};
+use super::Wave2dSimulationPlugin;
+use super::{
+    PlotQuantity, UiEvents, Wave2dSimulationGrid, Wave2dSimulationParameters,
+};
+use crate::simulation::Simulation;
+use crate::simulation_control::{SimulationClock, SimulationControlEvent};
+use crate::ui::Tunable;
+
+/// Amplitudes of the grid at a simulated time
+struct AbSnapshot {
+    amplitudes: Array2<f32>,
+    time: f64,
+    parameters: Wave2dSimulationParameters,
+}
+
+impl AbSnapshot {
+    fn record(
+        u: &Wave2dSimulationGrid,
+        clock: &SimulationClock,
+        parameters: &Wave2dSimulationParameters,
+    ) -> Self {
+        Self {
+            amplitudes: u.0.slice(s![0, .., ..]).to_owned(),
+            time: clock.elapsed_secs(),
+            parameters: parameters.clone(),
+        }
+    }
+}
+
+/// State of the rerun which records snapshot B
+#[derive(Clone, Copy, PartialEq, Eq)]
+enum Rerun {
+    /// the transport bar reset takes effect in the next frame
+    AwaitingReset,
+    /// the solver stops at the time of snapshot A
+    Running,
+}
+
+/// Snapshot A of the amplitudes, snapshot B of a rerun from rest up to the
+/// same simulated time, e.g. with another time step or boundary, and A − B.
+#[derive(Default, Resource)]
+pub struct Wave2dAbCompare {
+    a: Option<AbSnapshot>,
+    b: Option<AbSnapshot>,
+    rerun: Option<Rerun>,
+    /// A − B divided by its largest magnitude, empty until B is recorded
+    pub difference: Array2<f32>,
+    max_difference: f32,
+    error: Option<String>,
+}
+
+impl Wave2dAbCompare {
+    /// Solver steps left until the rerun reaches the time of snapshot A, as
+    /// close as steps of `dt` get there, none if no rerun is running
+    pub(super) fn steps_until_b(
+        &self,
+        clock: &SimulationClock,
+        dt: f32,
+    ) -> Option<usize> {
+        match (&self.a, self.rerun) {
+            (Some(a), Some(Rerun::Running)) => Some(
+                ((a.time - clock.elapsed_secs()) / dt as f64)
+                    .round()
+                    .max(0.0) as usize,
+            ),
+            _ => None,
+        }
+    }
+
+    fn record_b(&mut self, b: AbSnapshot) {
+        self.rerun = None;
+
+        let a = if let Some(a) = &self.a {
+            a
+        } else {
+            return;
+        };
+        if a.amplitudes.dim() != b.amplitudes.dim() {
+            self.error = Some("the grid was resized since A".to_string());
+            return;
+        }
+
+        let difference = &a.amplitudes - &b.amplitudes;
+        self.max_difference =
+            difference.fold(0.0, |max: f32, &value| max.max(value.abs()));
+        let scale = self.max_difference.max(f32::EPSILON);
+        self.difference = difference.mapv(|value| value / scale);
+        self.b = Some(b);
+        self.error = None;
+    }
+}
+
+pub struct AbComparePlugin;
+
+impl Plugin for AbComparePlugin {
+    fn build(&self, app: &mut App) {
+        app.insert_resource(Wave2dAbCompare::default())
+            .add_system_set(
+                SystemSet::on_update(Wave2dSimulationPlugin::STATE)
+                    .with_system(on_ui_events.before(start_rerun))
+                    .with_system(start_rerun.before(update_wave))
+                    .with_system(record_b.after(update_wave)),
+            );
+    }
+}
+
+fn on_ui_events(
+    mut ui_events: EventReader<UiEvents>,
+    mut control_events: EventWriter<SimulationControlEvent>,
+    mut ab_compare: ResMut<Wave2dAbCompare>,
+    mut parameters: ResMut<Wave2dSimulationParameters>,
+    u: Res<Wave2dSimulationGrid>,
+    clock: Res<SimulationClock>,
+) {
+    for event in ui_events.iter() {
+        match event {
+            UiEvents::StoreSnapshotA => {
+                *ab_compare = Wave2dAbCompare {
+                    a: Some(AbSnapshot::record(&u, &clock, &parameters)),
+                    ..default()
+                };
+            }
+            UiEvents::RerunSnapshotB if ab_compare.a.is_some() => {
+                control_events.send(SimulationControlEvent::Reset);
+                ab_compare.rerun = Some(Rerun::AwaitingReset);
+                ab_compare.b = None;
+            }
+            UiEvents::ClearAbCompare => {
+                *ab_compare = Wave2dAbCompare::default();
+                if parameters.plot_quantity == PlotQuantity::Difference {
+                    parameters.plot_quantity = PlotQuantity::Amplitude;
+                }
+            }
+            _ => {}
+        }
+    }
+}
+
+/// Starts the rerun from rest with the force at phase zero once the clock
+/// was reset
+fn start_rerun(
+    mut ab_compare: ResMut<Wave2dAbCompare>,
+    mut u: ResMut<Wave2dSimulationGrid>,
+    mut applying_force_timer: ResMut<ApplyingForceTimer>,
+    clock: Res<SimulationClock>,
+) {
+    if ab_compare.rerun != Some(Rerun::AwaitingReset) || clock.steps() != 0 {
+        return;
+    }
+
+    u.0.fill(0.0);
+    applying_force_timer.0.set_elapsed(Duration::ZERO);
+    ab_compare.rerun = Some(Rerun::Running);
+}
+
+fn record_b(
+    mut ab_compare: ResMut<Wave2dAbCompare>,
+    mut parameters: ResMut<Wave2dSimulationParameters>,
+    u: Res<Wave2dSimulationGrid>,
+    clock: Res<SimulationClock>,
+) {
+    if ab_compare.steps_until_b(&clock, parameters.dt) != Some(0) {
+        return;
+    }
+
+    ab_compare.record_b(AbSnapshot::record(&u, &clock, &parameters));
+    if ab_compare.b.is_some() {
+        parameters.plot_quantity = PlotQuantity::Difference;
+    }
+}
+
+pub fn show_ab_compare(
+    ui: &mut egui::Ui,
+    ab_compare: &Wave2dAbCompare,
+    clock: &SimulationClock,
+    ui_events: &mut EventWriter<UiEvents>,
+) {
+    ui.label("A/B compare");
+
+    ui.horizontal(|ui| {
+        if ui
+            .add_enabled(clock.steps() > 0, egui::Button::new("Store A"))
+            .on_hover_text(
+                "store the amplitudes at the current time, B reruns from rest \
+                 so A should start from rest too",
+            )
+            .clicked()
+        {
+            ui_events.send(UiEvents::StoreSnapshotA);
+        }
+        if ui
+            .add_enabled(
+                ab_compare.a.is_some() && ab_compare.rerun.is_none(),
+                egui::Button::new("Rerun for B"),
+            )
+            .on_hover_text(
+                "reset and run with the current parameters up to the time \
+                 of A",
+            )
+            .clicked()
+        {
+            ui_events.send(UiEvents::RerunSnapshotB);
+        }
+        if ui.button("Clear").clicked() {
+            ui_events.send(UiEvents::ClearAbCompare);
+        }
+    });
+
+    let a = if let Some(a) = &ab_compare.a {
+        a
+    } else {
+        ui.label("change a parameter between storing A and the rerun");
+        return;
+    };
+    ui.label(format!("A at {:.3} s", a.time));
+
+    if ab_compare.rerun.is_some() {
+        ui.label(format!(
+            "rerunning, {:.3} of {:.3} s",
+            clock.elapsed_secs(),
+            a.time
+        ));
+    }
+
+    if let Some(error) = &ab_compare.error {
+        ui.label(format!("failed to compare: {}", error));
+    }
+
+    let b = if let Some(b) = &ab_compare.b {
+        b
+    } else {
+        return;
+    };
+    ui.label(format!(
+        "B at {:.3} s, max |A − B| {:.3e}",
+        b.time, ab_compare.max_difference
+    ));
+
+    let changes: Vec<String> = Wave2dSimulationParameters::tunables()
+        .iter()
+        .filter_map(|tunable| {
+            let (from, to) =
+                ((tunable.get)(&a.parameters), (tunable.get)(&b.parameters));
+            let change = format!("{}: {} → {}", tunable.name, from, to);
+            (from != to).then_some(change)
+        })
+        .collect();
+    if changes.is_empty() {
+        ui.label("no tunable parameter changed");
+    }
+    for change in changes {
+        ui.label(change);
+    }
+}
@@ -1,31 +1,52 @@
-use std::f32::consts::E;
+use std::f32::consts::PI;
 
 use bevy::prelude::*;
-use bevy::render::mesh::Indices;
-use bevy::render::mesh::MeshVertexAttribute;
 use bevy::render::mesh::VertexAttributeValues;
-use bevy::render::render_resource::PrimitiveTopology;
-use bevy::render::render_resource::VertexFormat;
 use bevy::sprite::Mesh2dHandle;
-use ndarray::Array3;
+use ndarray::{s, Array2, Array3, ArrayView2};
 
+use super::moving_obstacle::MovingObstacleState;
+use super::surface_plot::{
+    export_surface, initialize_surface, update_surface, SurfacePlot,
+    SurfacePlotLight,
+};
 use super::UiEvents;
+use super::Wave2dAbCompare;
+use super::Wave2dEnvelope;
+use super::Wave2dLockIn;
+use super::Wave2dObstacleMask;
 use super::Wave2dSimulationGrid;
 use super::Wave2dSimulationParameters;
-use crate::colored_mesh::ColoredMesh2d;
+use super::Wave2dSimulationPlugin;
+use super::{PlotQuantity, PlotView};
 use crate::colored_mesh::ColoredMesh2dPlugin;
+pub(super) use crate::colored_mesh::VERTEX_ATTRIBUTE_COLOR_ID;
+use crate::colored_mesh::{grid_mesh, ColoredMesh2d};
+use crate::colormap::{Colormap, LogCompressionTable};
+use crate::pan_orbit_camera::{update_pan_orbit_camera, PanOrbitCamera};
+use crate::persistence::RestoreParameters;
+use crate::simulation::Simulation;
 use crate::AppCamera;
-use crate::AppState;
 
-const VERTEX_ATTRIBUTE_COLOR_ID: MeshVertexAttribute =
-    MeshVertexAttribute::new("Vertex_Color", 1, VertexFormat::Uint32);
+const OBSTACLE_COLOR: Color = Color::rgb(0.55, 0.35, 0.1);
 
-#[derive(Component)]
-struct Plot;
+/// Color of cells without a detected phase
+const UNDETECTED_COLOR: Color = Color::rgb(0.3, 0.3, 0.3);
+
+/// Change of the scaled amplitude, which spans `-1.0..=1.0`, below which a
+/// vertex keeps its color
+const COLOR_UPDATE_THRESHOLD: f32 = 1.0 / 256.0;
 
-pub struct PlotClickedEvent {
-    pub x: f32,
-    pub y: f32,
+#[derive(Component)]
+pub(super) struct Plot;
+
+/// Mesh of the flat plot and its colors, the only mesh [`update_mesh`]
+/// writes, so other 2d meshes like overlays are left untouched
+#[derive(Default, Resource)]
+struct PlotMesh {
+    /// weak, the mesh is dropped with the plot entity
+    handle: Mesh2dHandle,
+    colors: PlotColors,
 }
 
 pub struct AnimationPlugin;
@@ -33,19 +54,22 @@ pub struct AnimationPlugin;
 impl Plugin for AnimationPlugin {
     fn build(&self, app: &mut bevy::prelude::App) {
         app.add_plugin(ColoredMesh2dPlugin)
-            .add_event::<PlotClickedEvent>()
+            .insert_resource(PlotMesh::default())
             .add_system_set(
-                SystemSet::on_enter(AppState::Wave2dSimulation)
-                    .with_system(setup),
+                SystemSet::on_enter(Wave2dSimulationPlugin::STATE)
+                    .with_system(setup.after(RestoreParameters)),
             )
             .add_system_set(
-                SystemSet::on_update(AppState::Wave2dSimulation)
+                SystemSet::on_update(Wave2dSimulationPlugin::STATE)
                     .with_system(update_mesh)
-                    .with_system(mouse_event_handler)
+                    .with_system(update_surface)
+                    .with_system(export_surface.after(update_surface))
+                    .with_system(switch_plot_view)
+                    .with_system(update_pan_orbit_camera)
                     .with_system(on_ui_events),
             )
             .add_system_set(
-                SystemSet::on_exit(AppState::Wave2dSimulation)
+                SystemSet::on_exit(Wave2dSimulationPlugin::STATE)
                     .with_system(cleanup),
             );
     }
@@ -54,81 +78,104 @@ impl Plugin for AnimationPlugin {
 fn setup(
     mut commands: Commands,
     mut meshes: ResMut<Assets<Mesh>>,
+    mut materials: ResMut<Assets<StandardMaterial>>,
     parameters: Res<Wave2dSimulationParameters>,
     cameras: Query<Entity, With<AppCamera>>,
     mut mouse_button: ResMut<Input<MouseButton>>,
 ) {
     mouse_button.reset_all();
 
-    initialize_plot(&mut commands, &parameters, &mut meshes);
-
     if let Ok(camera_entity) = cameras.get_single() {
         commands.entity(camera_entity).despawn();
     }
 
-    commands.spawn((AppCamera, Camera2dBundle::default()));
+    spawn_plot_view(&mut commands, &parameters, &mut meshes, &mut materials);
 }
 
-fn initialize_plot(
+fn spawn_plot_view(
     commands: &mut Commands,
     parameters: &Wave2dSimulationParameters,
     meshes: &mut Assets<Mesh>,
+    materials: &mut Assets<StandardMaterial>,
 ) {
-    let dimx: u32 = (parameters.dimx - 1).try_into().unwrap();
-    let dimy: u32 = (parameters.dimy - 1).try_into().unwrap();
+    match parameters.plot_view {
+        PlotView::Flat => {
+            initialize_plot(commands, parameters, meshes);
 
-    let mut mesh = Mesh::new(PrimitiveTopology::TriangleList);
-
-    let mut v_pos: Vec<[f32; 3]> =
-        Vec::with_capacity(parameters.dimx * parameters.dimy);
-    let mut v_color: Vec<u32> =
-        Vec::with_capacity(parameters.dimx * parameters.dimy);
-
-    let white = Color::WHITE.as_linear_rgba_u32();
-
-    for x in 0..=dimx {
-        for y in 0..=dimy {
-            // positions of vertices
-            let scaled_x = x as f32 * parameters.cellsize;
-            let scaled_y = y as f32 * parameters.cellsize;
-            v_pos.push([scaled_x, scaled_y, 0.0]);
-
-            // color of vertices
-            v_color.push(white);
+            commands.spawn((AppCamera, Camera2dBundle::default()));
+        }
+        PlotView::Surface => {
+            initialize_surface(commands, parameters, meshes, materials);
+
+            let translation = Vec3::new(0.0, 18.0, 27.0);
+            commands.spawn((
+                AppCamera,
+                Camera3dBundle {
+                    transform: Transform::from_translation(translation)
+                        .looking_at(Vec3::ZERO, Vec3::Y),
+                    ..default()
+                },
+                PanOrbitCamera {
+                    radius: translation.length(),
+                    ..default()
+                },
+            ));
         }
     }
+}
 
-    mesh.insert_attribute(Mesh::ATTRIBUTE_POSITION, v_pos);
-    mesh.insert_attribute(VERTEX_ATTRIBUTE_COLOR_ID, v_color);
-
-    // indices of vertices
-    let mut indices: Vec<u32> =
-        Vec::with_capacity(parameters.dimx * parameters.dimy);
+#[allow(clippy::too_many_arguments)]
+fn switch_plot_view(
+    mut commands: Commands,
+    mut meshes: ResMut<Assets<Mesh>>,
+    mut materials: ResMut<Assets<StandardMaterial>>,
+    parameters: Res<Wave2dSimulationParameters>,
+    cameras: Query<Entity, With<AppCamera>>,
+    plots: Query<Entity, With<Plot>>,
+    surfaces: Query<Entity, Or<(With<SurfacePlot>, With<SurfacePlotLight>)>>,
+) {
+    let showing_flat = !plots.is_empty();
+    let showing_surface = !surfaces.is_empty();
 
-    for c in 0..dimx {
-        for r in 0..dimy {
-            let i = c * (dimy + 1) + r;
+    let up_to_date = match parameters.plot_view {
+        PlotView::Flat => showing_flat,
+        PlotView::Surface => showing_surface,
+    };
 
-            let r_ru_triangle = [i, i + dimy + 1, i + dimy + 2]; // right and right up triangle
-            let ru_u_triangle = [i, i + dimy + 2, i + 1]; // right up and up triagle
+    if up_to_date {
+        return;
+    }
 
-            indices.extend_from_slice(&r_ru_triangle);
-            indices.extend_from_slice(&ru_u_triangle);
-        }
+    for entity in cameras.iter().chain(plots.iter()).chain(surfaces.iter()) {
+        commands.entity(entity).despawn();
     }
 
-    mesh.set_indices(Some(Indices::U32(indices)));
+    spawn_plot_view(&mut commands, &parameters, &mut meshes, &mut materials);
+}
 
-    let dimx_shift: f32 = -(dimx as f32) * parameters.cellsize / 4.0;
-    let dimy_shift: f32 = -(dimy as f32) * parameters.cellsize / 2.0;
+fn initialize_plot(
+    commands: &mut Commands,
+    parameters: &Wave2dSimulationParameters,
+    meshes: &mut Assets<Mesh>,
+) {
+    let dimx_shift: f32 =
+        -((parameters.dimx - 1) as f32) * parameters.cellsize / 4.0;
+    let dimy_shift: f32 =
+        -((parameters.dimy - 1) as f32) * parameters.cellsize / 2.0;
 
     // info!("{:?}", dimx_shift);
     // info!("{:?}", dimy_shift);
 
+    let mesh_handle = meshes.add(plot_mesh(parameters));
+    commands.insert_resource(PlotMesh {
+        handle: Mesh2dHandle(mesh_handle.clone_weak()),
+        colors: PlotColors::default(),
+    });
+
     commands.spawn((
         Plot,
         ColoredMesh2d::default(),
-        Mesh2dHandle(meshes.add(mesh)),
+        Mesh2dHandle(mesh_handle),
         SpatialBundle {
             visibility: Visibility::VISIBLE,
             computed: ComputedVisibility::INVISIBLE,
@@ -142,49 +189,204 @@ fn initialize_plot(
     ));
 }
 
+/// Mesh with a vertex for every cell of the grid, colored by [`PlotColors`]
+pub(super) fn plot_mesh(parameters: &Wave2dSimulationParameters) -> Mesh {
+    grid_mesh(parameters.dimx, parameters.dimy, parameters.cellsize)
+}
+
+#[allow(clippy::too_many_arguments)]
 fn update_mesh(
     u: Res<Wave2dSimulationGrid>,
+    envelope: Res<Wave2dEnvelope>,
+    lock_in: Res<Wave2dLockIn>,
+    ab_compare: Res<Wave2dAbCompare>,
+    obstacles: Res<Wave2dObstacleMask>,
+    moving_obstacle: Res<MovingObstacleState>,
     mut parameters: ResMut<Wave2dSimulationParameters>,
     mut meshes: ResMut<Assets<Mesh>>,
+    mut plot_mesh: ResMut<PlotMesh>,
 ) {
-    for (_, mesh) in meshes.iter_mut() {
-        let vertex_attribute = mesh.attribute_mut(VERTEX_ATTRIBUTE_COLOR_ID);
+    let plot_mesh = &mut *plot_mesh;
+    let cells = parameters.dimx * parameters.dimy;
+
+    // the mesh is rebuilt a frame after the grid was resized, and is gone
+    // while the surface is shown
+    let mesh_cells = meshes
+        .get(&plot_mesh.handle.0)
+        .and_then(|mesh| mesh.attribute(VERTEX_ATTRIBUTE_COLOR_ID))
+        .map(|colors| colors.len());
+    let field =
+        shown_field(&parameters, &u.0, &envelope, &lock_in, &ab_compare);
+    if mesh_cells != Some(cells)
+        || field.shape() != [parameters.dimx, parameters.dimy]
+    {
+        return;
+    }
 
-        if let Some(VertexAttributeValues::Uint32(color_vector)) =
-            vertex_attribute
+    let max_amplitude = plot_mesh.colors.update(
+        &parameters,
+        field,
+        moving_obstacle.shown(&obstacles.0, &parameters),
+    );
+
+    // only a modified mesh is uploaded to the gpu again
+    if !plot_mesh.colors.changed.is_empty() {
+        let color_vector = meshes
+            .get_mut(&plot_mesh.handle.0)
+            .and_then(|mesh| mesh.attribute_mut(VERTEX_ATTRIBUTE_COLOR_ID));
+        if let Some(VertexAttributeValues::Uint32(color_vector)) = color_vector
         {
-            *color_vector = get_color_vector(&mut parameters, &u.0);
+            plot_mesh.colors.write_changes(&parameters, color_vector);
         }
     }
+
+    // phases and normalized differences say nothing about the amplitude
+    if !matches!(
+        parameters.plot_quantity,
+        PlotQuantity::Phase | PlotQuantity::Difference
+    ) {
+        update_max_amplitude(&mut parameters, max_amplitude);
+    }
 }
 
-fn get_color_vector(
-    parameters: &mut Wave2dSimulationParameters,
-    simulation_grid: &Array3<f32>,
-) -> Vec<u32> {
-    let dimx = parameters.dimx - 1;
-    let dimy = parameters.dimy - 1;
+/// Colors shown by a plot mesh, vertices are only recolored once their
+/// amplitude moved by more than [`COLOR_UPDATE_THRESHOLD`]
+#[derive(Component, Default)]
+pub(super) struct PlotColors {
+    /// value every vertex was colored with, see [`shown_value`], infinite
+    /// for obstacles
+    shown: Vec<f32>,
+    colormap: Option<Colormap>,
+    log_table: LogCompressionTable,
+    /// vertices recolored by the last update
+    pub(super) changed: Vec<usize>,
+}
 
-    let mut color_vector =
-        Vec::with_capacity(parameters.dimx * parameters.dimy);
+impl PlotColors {
+    /// Collects the vertices whose color has to change and returns the
+    /// maximum of the shown field
+    pub(super) fn update(
+        &mut self,
+        parameters: &Wave2dSimulationParameters,
+        amplitudes: ArrayView2<f32>,
+        obstacles: &Array2<bool>,
+    ) -> f32 {
+        let cells = parameters.dimx * parameters.dimy;
+
+        // unknown colors are NaN, which never equals a new color
+        if self.shown.len() != cells
+            || self.colormap != Some(parameters.shown_colormap())
+        {
+            self.shown = vec![f32::NAN; cells];
+            self.colormap = Some(parameters.shown_colormap());
+        }
 
-    let mut max_amplitude = f32::MIN;
+        self.changed.clear();
 
-    for x in 0..=dimx {
-        for y in 0..=dimy {
-            let amplitude = simulation_grid.get((0, x, y)).unwrap();
+        let mut max_amplitude = f32::MIN;
 
-            if *amplitude > max_amplitude {
-                max_amplitude = *amplitude;
+        for (i, (&amplitude, &obstacle)) in
+            amplitudes.iter().zip(obstacles.iter()).enumerate()
+        {
+            max_amplitude = max_amplitude.max(amplitude);
+
+            let target = if obstacle {
+                f32::INFINITY
+            } else {
+                shown_value(parameters, &self.log_table, amplitude)
+            };
+
+            let shown = &mut self.shown[i];
+            if target == *shown
+                || (target - *shown).abs() <= COLOR_UPDATE_THRESHOLD
+            {
+                continue;
             }
 
-            let amplitude = amplitude / parameters.max_amplitude;
-            let amplitude = (amplitude * 48.0 + 1.0).log(E) / 4.0;
+            *shown = target;
+            self.changed.push(i);
+        }
+
+        max_amplitude
+    }
 
-            color_vector.push(get_smooth_color_by_amplitude(amplitude));
+    pub(super) fn write_changes(
+        &self,
+        parameters: &Wave2dSimulationParameters,
+        color_vector: &mut [u32],
+    ) {
+        let obstacle_color = OBSTACLE_COLOR.as_linear_rgba_u32();
+
+        for &i in &self.changed {
+            let shown = self.shown[i];
+
+            color_vector[i] = if shown == f32::INFINITY {
+                obstacle_color
+            } else {
+                shown_color(parameters, shown).as_linear_rgba_u32()
+            };
         }
     }
+}
+
+/// Field selected by [`PlotQuantity`], the envelope and the phase are
+/// empty until the first solver step, the difference until B is recorded
+pub(super) fn shown_field<'a>(
+    parameters: &Wave2dSimulationParameters,
+    simulation_grid: &'a Array3<f32>,
+    envelope: &'a Wave2dEnvelope,
+    lock_in: &'a Wave2dLockIn,
+    ab_compare: &'a Wave2dAbCompare,
+) -> ArrayView2<'a, f32> {
+    match parameters.plot_quantity {
+        PlotQuantity::Amplitude => simulation_grid.slice(s![0, .., ..]),
+        PlotQuantity::Envelope => envelope.0.view(),
+        PlotQuantity::Phase => lock_in.phase.view(),
+        PlotQuantity::Difference => ab_compare.difference.view(),
+    }
+}
 
+/// Value of the shown field within `-1.0..=1.0` a vertex is colored with,
+/// negative infinity where no phase was detected
+pub(super) fn shown_value(
+    parameters: &Wave2dSimulationParameters,
+    log_table: &LogCompressionTable,
+    value: f32,
+) -> f32 {
+    match parameters.plot_quantity {
+        PlotQuantity::Phase if value.is_nan() => f32::NEG_INFINITY,
+        PlotQuantity::Phase => value / PI,
+        // already normalized to its largest magnitude
+        PlotQuantity::Difference => log_table.get(value),
+        _ => scaled_amplitude(parameters, log_table, value),
+    }
+}
+
+pub(super) fn shown_color(
+    parameters: &Wave2dSimulationParameters,
+    shown: f32,
+) -> Color {
+    if shown == f32::NEG_INFINITY {
+        UNDETECTED_COLOR
+    } else {
+        parameters.shown_colormap().color(shown)
+    }
+}
+
+/// Normalizes the amplitude and compresses it logarithmically while keeping
+/// its sign, so the result lies in `-1.0..=1.0`
+fn scaled_amplitude(
+    parameters: &Wave2dSimulationParameters,
+    log_table: &LogCompressionTable,
+    amplitude: f32,
+) -> f32 {
+    log_table.get(amplitude / parameters.color_amplitude())
+}
+
+pub(super) fn update_max_amplitude(
+    parameters: &mut Wave2dSimulationParameters,
+    max_amplitude: f32,
+) {
     parameters.max_amplitude_avg.pop_back();
     parameters.max_amplitude_avg.push_front(max_amplitude);
 
@@ -192,72 +394,66 @@ fn get_color_vector(
         / parameters.max_amplitude_avg.len() as f32;
 
     parameters.max_amplitude = avg.clamp(0.1, 0.9);
-
-    color_vector
 }
 
-fn get_smooth_color_by_amplitude(amplitude: f32) -> u32 {
-    Color::rgb(amplitude, amplitude, amplitude).as_linear_rgba_u32()
-}
-
-fn mouse_event_handler(
-    windows: Res<Windows>,
-    cameras: Query<(&Camera, &GlobalTransform), With<AppCamera>>,
-    buttons: Res<Input<MouseButton>>,
-    plots: Query<&Transform, With<Plot>>,
-    parameters: Res<Wave2dSimulationParameters>,
-    mut event: EventWriter<PlotClickedEvent>,
-) {
-    let (camera, camera_transform) = cameras.get_single().unwrap();
-    if buttons.just_pressed(MouseButton::Left) {
-        let window = windows.get_primary().unwrap();
-
-        if let Some(screen_position) = window.cursor_position() {
-            let window_size = Vec2::new(window.width(), window.height());
-            let ndc = (screen_position / window_size) * 2.0 - Vec2::ONE;
-            let ndc_to_world = camera_transform.compute_matrix()
-                * camera.projection_matrix().inverse();
-            let world_position = ndc_to_world.project_point3(ndc.extend(-1.0));
-            let world_position: Vec2 = world_position.truncate();
-
-            if let Some(plot_transform) = plots.iter().next() {
-                let plot_x = (world_position.x - plot_transform.translation.x)
-                    / parameters.cellsize;
-                let plot_y = (world_position.y - plot_transform.translation.y)
-                    / parameters.cellsize;
-
-                event.send(PlotClickedEvent {
-                    x: plot_x,
-                    y: plot_y,
-                });
-            }
-        }
-    }
+/// Grid coordinates in cells of a position in the window, measured from the
+/// bottom left like the cursor
+pub(super) fn screen_to_plot(
+    window: &Window,
+    camera: &Camera,
+    camera_transform: &GlobalTransform,
+    plot_transform: &Transform,
+    parameters: &Wave2dSimulationParameters,
+    screen_position: Vec2,
+) -> Vec2 {
+    let window_size = Vec2::new(window.width(), window.height());
+    let ndc = (screen_position / window_size) * 2.0 - Vec2::ONE;
+    let ndc_to_world = camera_transform.compute_matrix()
+        * camera.projection_matrix().inverse();
+    let world_position = ndc_to_world.project_point3(ndc.extend(-1.0));
+    let world_position: Vec2 = world_position.truncate();
+
+    (world_position - plot_transform.translation.truncate())
+        / parameters.cellsize
 }
 
 fn on_ui_events(
-    mut time: ResMut<Time>,
+    mut commands: Commands,
     mut ui_events: EventReader<UiEvents>,
     mut u: ResMut<Wave2dSimulationGrid>,
+    mut obstacles: ResMut<Wave2dObstacleMask>,
     parameters: Res<Wave2dSimulationParameters>,
+    plots: Query<
+        Entity,
+        Or<(With<Plot>, With<SurfacePlot>, With<SurfacePlotLight>)>,
+    >,
 ) {
     for event in ui_events.iter() {
         match event {
-            UiEvents::StartStopTime => {
-                if time.is_paused() {
-                    time.unpause();
-                } else {
-                    time.pause();
-                }
-            }
             UiEvents::Reset => {
                 u.0 = Array3::zeros((3, parameters.dimx, parameters.dimy));
             }
+            UiEvents::ClearObstacles => {
+                obstacles.0.fill(false);
+            }
+            UiEvents::ResizeGrid(_) | UiEvents::LoadSnapshot => {
+                // the plot is rebuilt with the new size by `switch_plot_view`
+                for entity in plots.iter() {
+                    commands.entity(entity).despawn();
+                }
+            }
+            _ => {}
         }
     }
 }
 
-fn cleanup(mut commands: Commands, plots: Query<Entity, With<Plot>>) {
+fn cleanup(
+    mut commands: Commands,
+    plots: Query<
+        Entity,
+        Or<(With<Plot>, With<SurfacePlot>, With<SurfacePlotLight>)>,
+    >,
+) {
     for plot in plots.iter() {
         if let Some(mut entity) = commands.get_entity(plot) {
             entity.despawn();
@@ -0,0 +1,189 @@
+use bevy::prelude::*;
+use bevy_egui::{egui, EguiContext};
+
+use super::animation_plugin::Plot;
+use super::Wave2dSimulationParameters;
+use super::Wave2dSimulationPlugin;
+use crate::simulation::Simulation;
+use crate::viewports::world_to_egui;
+use crate::AppCamera;
+
+/// Ticks along the longer side of the plot, the spacing is rounded to 1, 2
+/// or 5 times a power of ten
+const TARGET_TICKS: f32 = 8.0;
+
+/// Length of the ticks outside the plot in points
+const TICK_LENGTH: f32 = 5.0;
+
+const AXIS_COLOR: egui::Color32 = egui::Color32::LIGHT_GRAY;
+
+const GRID_COLOR: egui::Color32 =
+    egui::Color32::from_rgba_premultiplied(60, 60, 60, 60);
+
+pub struct AxesPlugin;
+
+impl Plugin for AxesPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_system_set(
+            SystemSet::on_update(Wave2dSimulationPlugin::STATE)
+                .with_system(draw_axes),
+        );
+    }
+}
+
+/// Spacing of the ticks for an axis of length `span`
+fn tick_spacing(span: f32) -> f32 {
+    let raw = span / TARGET_TICKS;
+    let magnitude = 10f32.powf(raw.log10().floor());
+
+    let normalized = raw / magnitude;
+    let nice = if normalized <= 1.0 {
+        1.0
+    } else if normalized <= 2.0 {
+        2.0
+    } else if normalized <= 5.0 {
+        5.0
+    } else {
+        10.0
+    };
+
+    nice * magnitude
+}
+
+/// Draws the ticks in m along the bottom and left edge of the flat plot and
+/// optionally grid lines across it
+fn draw_axes(
+    mut egui_ctx: ResMut<EguiContext>,
+    windows: Res<Windows>,
+    parameters: Res<Wave2dSimulationParameters>,
+    cameras: Query<(&Camera, &GlobalTransform), With<AppCamera>>,
+    plots: Query<&Transform, With<Plot>>,
+) {
+    if !parameters.show_axes {
+        return;
+    }
+
+    let ((camera, camera_transform), plot_transform, window) = match (
+        cameras.get_single(),
+        plots.get_single(),
+        windows.get_primary(),
+    ) {
+        (Ok(camera), Ok(transform), Some(window)) => {
+            (camera, transform, window)
+        }
+        _ => return,
+    };
+
+    let to_egui = |cells: Vec2| {
+        let world = plot_transform.translation
+            + (cells * parameters.cellsize).extend(0.0);
+        world_to_egui(camera, camera_transform, window, world)
+    };
+
+    let cells =
+        Vec2::new((parameters.dimx - 1) as f32, (parameters.dimy - 1) as f32);
+    let meters_per_cell = parameters.meters_per_cell();
+    if meters_per_cell <= 0.0 {
+        return;
+    }
+    let size = cells * meters_per_cell;
+    let spacing = tick_spacing(size.max_element());
+    let decimals = (-spacing.log10().floor()).max(0.0) as usize;
+
+    let painter = egui_ctx.ctx_mut().layer_painter(egui::LayerId::new(
+        egui::Order::Foreground,
+        egui::Id::new("wave_2d_axes"),
+    ));
+    let axis_stroke = egui::Stroke::new(1.0, AXIS_COLOR);
+    let grid_stroke = egui::Stroke::new(1.0, GRID_COLOR);
+    let font = egui::FontId::proportional(11.0);
+
+    let axis = |along_x: bool| {
+        let (length, end) = if along_x {
+            (size.x, Vec2::new(cells.x, 0.0))
+        } else {
+            (size.y, Vec2::new(0.0, cells.y))
+        };
+
+        if let (Some(start), Some(end)) = (to_egui(Vec2::ZERO), to_egui(end)) {
+            painter.line_segment([start, end], axis_stroke);
+        }
+
+        for i in 0..=(length / spacing + 1e-3).floor() as usize {
+            let meters = i as f32 * spacing;
+            let at = meters / meters_per_cell;
+            let (edge, across) = if along_x {
+                (Vec2::new(at, 0.0), Vec2::new(at, cells.y))
+            } else {
+                (Vec2::new(0.0, at), Vec2::new(cells.x, at))
+            };
+
+            let (edge, across) = match (to_egui(edge), to_egui(across)) {
+                (Some(edge), Some(across)) => (edge, across),
+                _ => continue,
+            };
+
+            if parameters.show_grid_lines && i > 0 {
+                painter.line_segment([edge, across], grid_stroke);
+            }
+
+            let (outside, anchor) = if along_x {
+                (egui::vec2(0.0, TICK_LENGTH), egui::Align2::CENTER_TOP)
+            } else {
+                (egui::vec2(-TICK_LENGTH, 0.0), egui::Align2::RIGHT_CENTER)
+            };
+            painter.line_segment([edge, edge + outside], axis_stroke);
+            painter.text(
+                edge + outside * 1.4,
+                anchor,
+                format!("{:.*}", decimals, meters),
+                font.clone(),
+                AXIS_COLOR,
+            );
+        }
+    };
+    axis(true);
+    axis(false);
+
+    if let Some(origin) = to_egui(Vec2::ZERO) {
+        painter.text(
+            origin + egui::vec2(-TICK_LENGTH, TICK_LENGTH) * 1.4,
+            egui::Align2::RIGHT_TOP,
+            "m",
+            font,
+            AXIS_COLOR,
+        );
+    }
+}
+
+pub fn show_axes(
+    ui: &mut egui::Ui,
+    parameters: &mut Wave2dSimulationParameters,
+) {
+    ui.horizontal(|ui| {
+        ui.add(egui::Checkbox::new(&mut parameters.show_axes, "axes in m"));
+        if parameters.show_axes {
+            ui.add(egui::Checkbox::new(
+                &mut parameters.show_grid_lines,
+                "grid lines",
+            ));
+        }
+    });
+
+    if !parameters.show_axes {
+        return;
+    }
+
+    ui.add(
+        egui::Slider::new(&mut parameters.meters_per_unit, 0.0001..=10.0)
+            .logarithmic(true)
+            .text("m per world unit"),
+    )
+    .on_hover_text("physical length of one world unit of the plot");
+    ui.label(format!(
+        "cell width: {:.3e} m, plot: {:.3} x {:.3} m",
+        parameters.meters_per_cell(),
+        (parameters.dimx - 1) as f32 * parameters.meters_per_cell(),
+        (parameters.dimy - 1) as f32 * parameters.meters_per_cell()
+    ));
+}
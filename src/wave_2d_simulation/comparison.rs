@@ -0,0 +1,514 @@
+use bevy::core_pipeline::clear_color::ClearColorConfig;
+use bevy::ecs::system::SystemState;
+use bevy::prelude::*;
+use bevy::render::camera::Viewport;
+use bevy::render::mesh::VertexAttributeValues;
+use bevy::render::view::RenderLayers;
+use bevy::sprite::Mesh2dHandle;
+use bevy_egui::egui;
+use ndarray::{s, Array2, Array3};
+use serde::{Deserialize, Serialize};
+
+use super::animation_plugin::{
+    plot_mesh, update_max_amplitude, PlotColors, VERTEX_ATTRIBUTE_COLOR_ID,
+};
+use super::simulation_plugin::{apply_force, step_wave, ApplyingForceTimer};
+use super::ui::show_stability;
+use super::Wave2dSimulationParameters;
+use crate::colored_mesh::ColoredMesh2d;
+use crate::persistence::{PersistenceAppExt, RestoreParameters};
+use crate::recording::{
+    RecordableEvent, RecordableParameters, RecordingAppExt,
+};
+use crate::simulation::Simulation;
+use crate::simulation_control::{
+    forward_reset, ResetEvent, SimulationClock, SimulationControl,
+};
+use crate::ui::{select_colormap, show_tunables, UiState};
+use crate::{AppCamera, AppState};
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub enum ComparisonSide {
+    Left,
+    Right,
+}
+
+impl ComparisonSide {
+    pub const ALL: [ComparisonSide; 2] =
+        [ComparisonSide::Left, ComparisonSide::Right];
+
+    fn index(self) -> usize {
+        match self {
+            ComparisonSide::Left => 0,
+            ComparisonSide::Right => 1,
+        }
+    }
+
+    fn other(self) -> Self {
+        match self {
+            ComparisonSide::Left => ComparisonSide::Right,
+            ComparisonSide::Right => ComparisonSide::Left,
+        }
+    }
+
+    /// Only the cameras of this side see its plot
+    fn render_layers(self) -> RenderLayers {
+        RenderLayers::layer(1 + self.index() as u8)
+    }
+}
+
+impl From<ComparisonSide> for String {
+    fn from(value: ComparisonSide) -> Self {
+        match value {
+            ComparisonSide::Left => "left".to_string(),
+            ComparisonSide::Right => "right".to_string(),
+        }
+    }
+}
+
+/// Two independent parameter sets of the 2d wave solver, both grids start
+/// from the same excitation
+#[derive(Clone, Resource, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Wave2dComparisonParameters {
+    sides: [Wave2dSimulationParameters; 2],
+    /// the side whose parameters are shown in the side panel
+    editing: ComparisonSide,
+}
+
+impl Default for Wave2dComparisonParameters {
+    fn default() -> Self {
+        let mut right = Wave2dSimulationParameters::default();
+        right.wave_velocity /= 2.0;
+
+        Self {
+            sides: [Wave2dSimulationParameters::default(), right],
+            editing: ComparisonSide::Left,
+        }
+    }
+}
+
+impl RecordableParameters for Wave2dComparisonParameters {
+    const KIND: &'static str = "wave_2d_comparison_parameters";
+    const SIMULATION: AppState = Wave2dComparisonPlugin::STATE;
+
+    fn restore(&mut self, recorded: Self) {
+        for (side, recorded) in self.sides.iter_mut().zip(recorded.sides) {
+            side.restore(recorded);
+        }
+        self.editing = recorded.editing;
+    }
+}
+
+#[derive(Serialize, Deserialize)]
+pub enum ComparisonUiEvents {
+    Reset,
+    /// takes over the parameters of the given side on the other side
+    CopyParameters(ComparisonSide),
+}
+
+impl RecordableEvent for ComparisonUiEvents {
+    const KIND: &'static str = "wave_2d_comparison_ui";
+}
+
+impl ResetEvent for ComparisonUiEvents {
+    fn reset() -> Self {
+        ComparisonUiEvents::Reset
+    }
+}
+
+#[derive(Default)]
+struct ComparisonGrid {
+    u: Array3<f32>,
+    obstacles: Array2<bool>,
+    damping: Array2<f32>,
+    applying_force_timer: ApplyingForceTimer,
+    /// simulated time not yet covered by a solver step
+    accumulator: f32,
+}
+
+impl ComparisonGrid {
+    fn reset(&mut self, parameters: &Wave2dSimulationParameters) {
+        *self = Self {
+            u: Array3::zeros((3, parameters.dimx, parameters.dimy)),
+            obstacles: Array2::from_elem(
+                (parameters.dimx, parameters.dimy),
+                false,
+            ),
+            damping: Array2::zeros((parameters.dimx, parameters.dimy)),
+            ..default()
+        };
+    }
+}
+
+#[derive(Default, Resource)]
+struct ComparisonGrids([ComparisonGrid; 2]);
+
+#[derive(Component)]
+struct ComparisonPlot(ComparisonSide);
+
+#[derive(Component)]
+struct ComparisonCamera(ComparisonSide);
+
+pub struct Wave2dComparisonPlugin;
+
+impl Plugin for Wave2dComparisonPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_event::<ComparisonUiEvents>()
+            .add_recordable_event::<ComparisonUiEvents>()
+            .add_recordable_parameters::<Wave2dComparisonParameters>()
+            .add_persistent_parameters::<Wave2dComparisonParameters>()
+            .insert_resource(Wave2dComparisonParameters::default())
+            .insert_resource(ComparisonGrids::default())
+            .add_system_set(
+                SystemSet::on_enter(Wave2dComparisonPlugin::STATE)
+                    .with_system(setup.after(RestoreParameters)),
+            )
+            .add_system_set(
+                SystemSet::on_update(Wave2dComparisonPlugin::STATE)
+                    .with_system(forward_reset::<ComparisonUiEvents>)
+                    .with_system(update_waves)
+                    .with_system(update_meshes.after(update_waves))
+                    .with_system(update_viewports)
+                    .with_system(on_mouseclick)
+                    .with_system(on_ui_events),
+            )
+            .add_system_set(
+                SystemSet::on_exit(Wave2dComparisonPlugin::STATE)
+                    .with_system(cleanup),
+            );
+    }
+}
+
+fn setup(
+    mut commands: Commands,
+    mut meshes: ResMut<Assets<Mesh>>,
+    mut grids: ResMut<ComparisonGrids>,
+    parameters: Res<Wave2dComparisonParameters>,
+    cameras: Query<Entity, With<AppCamera>>,
+) {
+    if let Ok(camera_entity) = cameras.get_single() {
+        commands.entity(camera_entity).despawn();
+    }
+
+    for side in ComparisonSide::ALL {
+        let side_parameters = &parameters.sides[side.index()];
+        grids.0[side.index()].reset(side_parameters);
+
+        let width =
+            (side_parameters.dimx - 1) as f32 * side_parameters.cellsize;
+        let height =
+            (side_parameters.dimy - 1) as f32 * side_parameters.cellsize;
+        commands.spawn((
+            ComparisonPlot(side),
+            PlotColors::default(),
+            ColoredMesh2d::default(),
+            Mesh2dHandle(meshes.add(plot_mesh(side_parameters))),
+            SpatialBundle::from_transform(Transform::from_xyz(
+                -width / 2.0,
+                -height / 2.0,
+                0.0,
+            )),
+            side.render_layers(),
+        ));
+
+        commands.spawn((
+            ComparisonCamera(side),
+            Camera2dBundle {
+                camera: Camera {
+                    priority: side.index() as isize,
+                    ..default()
+                },
+                camera_2d: Camera2d {
+                    // the left camera already cleared the window
+                    clear_color: match side {
+                        ComparisonSide::Left => ClearColorConfig::Default,
+                        ComparisonSide::Right => ClearColorConfig::None,
+                    },
+                },
+                ..default()
+            },
+            side.render_layers(),
+        ));
+    }
+}
+
+fn update_waves(
+    time: Res<Time>,
+    control: Res<SimulationControl>,
+    mut clock: ResMut<SimulationClock>,
+    parameters: Res<Wave2dComparisonParameters>,
+    mut grids: ResMut<ComparisonGrids>,
+) {
+    for (i, (grid, parameters)) in
+        grids.0.iter_mut().zip(&parameters.sides).enumerate()
+    {
+        let steps = if control.is_paused() {
+            usize::from(control.is_stepping())
+        } else {
+            grid.accumulator +=
+                control.delta_seconds(&time) * parameters.time_scale;
+
+            let steps = ((grid.accumulator / parameters.dt).floor() as usize)
+                .min(parameters.steps_per_frame);
+            grid.accumulator = (grid.accumulator
+                - steps as f32 * parameters.dt)
+                .min(parameters.dt);
+
+            steps
+        };
+        // the sides may step with different time steps, the clock follows
+        // the left one
+        if i == 0 {
+            clock.advance(steps, parameters.dt);
+        }
+
+        for _ in 0..steps {
+            apply_force(
+                &mut grid.applying_force_timer,
+                &mut grid.u,
+                parameters,
+            );
+            step_wave(&mut grid.u, &grid.obstacles, &grid.damping, parameters);
+        }
+    }
+}
+
+fn update_meshes(
+    grids: Res<ComparisonGrids>,
+    mut parameters: ResMut<Wave2dComparisonParameters>,
+    mut meshes: ResMut<Assets<Mesh>>,
+    mut plots: Query<(&ComparisonPlot, &Mesh2dHandle, &mut PlotColors)>,
+) {
+    for (plot, mesh_handle, mut plot_colors) in plots.iter_mut() {
+        let grid = &grids.0[plot.0.index()];
+        let side_parameters = &mut parameters.sides[plot.0.index()];
+
+        let max_amplitude = plot_colors.update(
+            side_parameters,
+            grid.u.slice(s![0, .., ..]),
+            &grid.obstacles,
+        );
+        update_max_amplitude(side_parameters, max_amplitude);
+
+        if plot_colors.changed.is_empty() {
+            continue;
+        }
+
+        let color_vector = meshes
+            .get_mut(&mesh_handle.0)
+            .and_then(|mesh| mesh.attribute_mut(VERTEX_ATTRIBUTE_COLOR_ID));
+        if let Some(VertexAttributeValues::Uint32(color_vector)) = color_vector
+        {
+            plot_colors.write_changes(side_parameters, color_vector);
+        }
+    }
+}
+
+/// Splits the part of the window next to the panels into two halves and
+/// zooms both plots to fit into their half
+fn update_viewports(
+    windows: Res<Windows>,
+    ui_state: Res<UiState>,
+    parameters: Res<Wave2dComparisonParameters>,
+    mut cameras: Query<(
+        &ComparisonCamera,
+        &mut Camera,
+        &mut OrthographicProjection,
+    )>,
+) {
+    let window = if let Some(window) = windows.get_primary() {
+        window
+    } else {
+        return;
+    };
+
+    let (position, size) = ui_state.free_area(window);
+    let half_size = UVec2::new((size.x / 2).max(1), size.y);
+
+    for (camera_side, mut camera, mut projection) in cameras.iter_mut() {
+        let side = camera_side.0;
+        camera.viewport = Some(Viewport {
+            physical_position: position
+                + UVec2::new(side.index() as u32 * half_size.x, 0),
+            physical_size: half_size,
+            ..default()
+        });
+
+        let side_parameters = &parameters.sides[side.index()];
+        let plot_size = Vec2::new(
+            (side_parameters.dimx - 1) as f32,
+            (side_parameters.dimy - 1) as f32,
+        ) * side_parameters.cellsize;
+        let viewport_size = half_size.as_vec2() / window.scale_factor() as f32;
+        let scale = (plot_size / viewport_size).max_element() * 1.05;
+        if projection.scale != scale {
+            projection.scale = scale;
+        }
+    }
+}
+
+/// A click into either half excites the same cell of both grids
+fn on_mouseclick(
+    windows: Res<Windows>,
+    buttons: Res<Input<MouseButton>>,
+    parameters: Res<Wave2dComparisonParameters>,
+    mut grids: ResMut<ComparisonGrids>,
+    cameras: Query<(&ComparisonCamera, &Camera, &GlobalTransform)>,
+    plots: Query<(&ComparisonPlot, &Transform)>,
+) {
+    if !buttons.just_pressed(MouseButton::Left) {
+        return;
+    }
+
+    let window = if let Some(window) = windows.get_primary() {
+        window
+    } else {
+        return;
+    };
+    // measured from the bottom left, viewports from the top left
+    let cursor = if let Some(cursor) = window.cursor_position() {
+        Vec2::new(cursor.x, window.height() - cursor.y)
+    } else {
+        return;
+    };
+
+    for (camera_side, camera, camera_transform) in cameras.iter() {
+        let viewport = if let Some(viewport) = &camera.viewport {
+            viewport
+        } else {
+            continue;
+        };
+        let scale_factor = window.scale_factor() as f32;
+        let min = viewport.physical_position.as_vec2() / scale_factor;
+        let size = viewport.physical_size.as_vec2() / scale_factor;
+
+        let relative = (cursor - min) / size;
+        if relative.cmplt(Vec2::ZERO).any() || relative.cmpge(Vec2::ONE).any() {
+            continue;
+        }
+
+        let ndc = Vec2::new(relative.x * 2.0 - 1.0, 1.0 - relative.y * 2.0);
+        let ndc_to_world = camera_transform.compute_matrix()
+            * camera.projection_matrix().inverse();
+        let world_position =
+            ndc_to_world.project_point3(ndc.extend(-1.0)).truncate();
+
+        let plot_transform = if let Some((_, transform)) =
+            plots.iter().find(|(plot, _)| plot.0 == camera_side.0)
+        {
+            transform
+        } else {
+            continue;
+        };
+
+        let side_parameters = &parameters.sides[camera_side.0.index()];
+        let plot_position = (world_position
+            - plot_transform.translation.truncate())
+            / side_parameters.cellsize;
+        let (x, y) = (
+            plot_position.x.round() as usize,
+            plot_position.y.round() as usize,
+        );
+
+        for (grid, parameters) in grids.0.iter_mut().zip(&parameters.sides) {
+            if 0 < x && x < parameters.dimx && 0 < y && y < parameters.dimy {
+                grid.u[(0, x, y)] = 1.0;
+            }
+        }
+        return;
+    }
+}
+
+fn on_ui_events(
+    mut ui_events: EventReader<ComparisonUiEvents>,
+    mut parameters: ResMut<Wave2dComparisonParameters>,
+    mut grids: ResMut<ComparisonGrids>,
+) {
+    for event in ui_events.iter() {
+        match event {
+            ComparisonUiEvents::Reset => {
+                for (grid, parameters) in
+                    grids.0.iter_mut().zip(&parameters.sides)
+                {
+                    grid.reset(parameters);
+                }
+            }
+            ComparisonUiEvents::CopyParameters(from) => {
+                let copied = parameters.sides[from.index()].clone();
+                parameters.sides[from.other().index()] = copied;
+            }
+        }
+    }
+}
+
+fn cleanup(
+    mut commands: Commands,
+    entities: Query<Entity, Or<(With<ComparisonPlot>, With<ComparisonCamera>)>>,
+) {
+    for entity in entities.iter() {
+        commands.entity(entity).despawn();
+    }
+}
+
+impl Simulation for Wave2dComparisonPlugin {
+    const NAME: &'static str = "wave_2d_comparison";
+    type Parameters = Wave2dComparisonParameters;
+
+    fn show_ui(ui: &mut egui::Ui, world: &mut World) {
+        let mut state: SystemState<(
+            ResMut<Wave2dComparisonParameters>,
+            EventWriter<ComparisonUiEvents>,
+        )> = SystemState::new(world);
+        let (mut parameters, mut ui_events) = state.get_mut(world);
+
+        show_ui(ui, &mut parameters, &mut ui_events);
+    }
+}
+
+fn show_ui(
+    ui: &mut egui::Ui,
+    parameters: &mut Wave2dComparisonParameters,
+    ui_events: &mut EventWriter<ComparisonUiEvents>,
+) {
+    ui.allocate_space(egui::Vec2::new(1.0, 10.0));
+
+    ui.horizontal(|ui| {
+        ui.label("parameters of:");
+        for option in ComparisonSide::ALL {
+            ui.radio_value(
+                &mut parameters.editing,
+                option,
+                String::from(option),
+            );
+        }
+    });
+
+    let editing = parameters.editing;
+    if ui
+        .button(format!("Copy to {} side", String::from(editing.other())))
+        .on_hover_text("start from equal sides and change a single parameter")
+        .clicked()
+    {
+        ui_events.send(ComparisonUiEvents::CopyParameters(editing));
+    }
+
+    ui.separator();
+
+    let side_parameters = &mut parameters.sides[editing.index()];
+
+    show_tunables(ui, side_parameters);
+
+    show_stability(ui, side_parameters);
+
+    ui.add(egui::Checkbox::new(
+        &mut side_parameters.apply_force,
+        "continuously apply frequency",
+    ));
+
+    select_colormap(ui, &mut side_parameters.colormap);
+
+    ui.separator();
+
+    ui.label("click into either side to excite both grids at the same cell");
+}
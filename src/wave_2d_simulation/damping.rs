@@ -0,0 +1,161 @@
+use bevy::prelude::*;
+use bevy::render::mesh::VertexAttributeValues;
+use bevy::sprite::Mesh2dHandle;
+use ndarray::Array2;
+use serde::{Deserialize, Serialize};
+
+use super::animation_plugin::{plot_mesh, Plot, VERTEX_ATTRIBUTE_COLOR_ID};
+use super::tools::{cells_within, PlotToolEvent};
+use super::Wave2dSimulationPlugin;
+use super::{UiEvents, Wave2dDampingMap, Wave2dSimulationParameters};
+use crate::colored_mesh::ColoredMesh2d;
+use crate::simulation::Simulation;
+
+/// Tint of fully damped cells, cells without damping are transparent
+const DAMPING_COLOR: Color = Color::rgba(0.2, 0.5, 1.0, 0.6);
+
+/// Paints the loss of the cells in a circle around the cursor
+#[derive(Clone, Copy, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct DampingBrush {
+    /// in cells
+    pub radius: f32,
+    /// fraction of the amplitude painted cells lose per step, 0 erases
+    pub strength: f32,
+}
+
+impl Default for DampingBrush {
+    fn default() -> Self {
+        Self {
+            radius: 6.0,
+            strength: 0.1,
+        }
+    }
+}
+
+/// Shows the painted damping on top of the flat plot
+#[derive(Component)]
+struct DampingOverlay;
+
+pub struct DampingPlugin;
+
+impl Plugin for DampingPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_system_set(
+            SystemSet::on_update(Wave2dSimulationPlugin::STATE)
+                .with_system(on_ui_events)
+                .with_system(update_overlay.after(on_ui_events)),
+        )
+        .add_system_set(
+            SystemSet::on_exit(Wave2dSimulationPlugin::STATE)
+                .with_system(cleanup),
+        );
+    }
+}
+
+fn on_ui_events(
+    mut ui_events: EventReader<UiEvents>,
+    mut tool_events: EventReader<PlotToolEvent>,
+    mut damping: ResMut<Wave2dDampingMap>,
+    parameters: Res<Wave2dSimulationParameters>,
+) {
+    for event in tool_events.iter() {
+        if let PlotToolEvent::PaintDamping { x, y } = *event {
+            paint(&mut damping.0, parameters.damping_brush, x, y);
+        }
+    }
+
+    for event in ui_events.iter() {
+        if let UiEvents::ClearDamping = event {
+            damping.0.fill(0.0);
+        }
+    }
+}
+
+fn paint(damping: &mut Array2<f32>, brush: DampingBrush, x: f32, y: f32) {
+    let dim = damping.dim();
+    for cell in cells_within(x, y, brush.radius.max(0.5), dim) {
+        damping[cell] = brush.strength.clamp(0.0, 1.0);
+    }
+}
+
+/// Follows the flat plot and recolors the overlay whenever the damping was
+/// painted
+fn update_overlay(
+    mut commands: Commands,
+    damping: Res<Wave2dDampingMap>,
+    parameters: Res<Wave2dSimulationParameters>,
+    mut meshes: ResMut<Assets<Mesh>>,
+    plots: Query<&Transform, (With<Plot>, Without<DampingOverlay>)>,
+    mut overlays: Query<
+        (Entity, &Mesh2dHandle, &mut Transform),
+        With<DampingOverlay>,
+    >,
+) {
+    let plot_translation = if let Ok(transform) = plots.get_single() {
+        transform.translation
+    } else {
+        for (entity, _, _) in overlays.iter() {
+            commands.entity(entity).despawn();
+        }
+        return;
+    };
+    let cells = parameters.dimx * parameters.dimy;
+    if damping.0.len() != cells {
+        return;
+    }
+
+    let overlay = overlays.get_single_mut().ok().filter(|(_, mesh, _)| {
+        meshes
+            .get(&mesh.0)
+            .and_then(|mesh| mesh.attribute(VERTEX_ATTRIBUTE_COLOR_ID))
+            .map(|colors| colors.len())
+            == Some(cells)
+    });
+
+    let (mesh_handle, rebuilt) =
+        if let Some((_, mesh_handle, mut transform)) = overlay {
+            transform.translation = plot_translation + Vec3::Z * 0.5;
+            (mesh_handle.0.clone(), false)
+        } else {
+            // the grid was resized or the plot was just spawned
+            for (entity, _, _) in overlays.iter() {
+                commands.entity(entity).despawn();
+            }
+
+            let mesh_handle = meshes.add(plot_mesh(&parameters));
+            commands.spawn((
+                DampingOverlay,
+                ColoredMesh2d::default(),
+                Mesh2dHandle(mesh_handle.clone()),
+                SpatialBundle::from_transform(Transform::from_translation(
+                    plot_translation + Vec3::Z * 0.5,
+                )),
+            ));
+            (mesh_handle, true)
+        };
+
+    if !rebuilt && !damping.is_changed() {
+        return;
+    }
+
+    let colors = meshes
+        .get_mut(&mesh_handle)
+        .and_then(|mesh| mesh.attribute_mut(VERTEX_ATTRIBUTE_COLOR_ID));
+    if let Some(VertexAttributeValues::Uint32(colors)) = colors {
+        for (color, &damping) in colors.iter_mut().zip(damping.0.iter()) {
+            let mut tint = DAMPING_COLOR;
+            tint.set_a(DAMPING_COLOR.a() * damping.sqrt());
+            *color = tint.as_linear_rgba_u32();
+        }
+    }
+}
+
+fn cleanup(
+    mut commands: Commands,
+    overlays: Query<Entity, With<DampingOverlay>>,
+) {
+    for entity in overlays.iter() {
+        commands.entity(entity).despawn();
+    }
+}
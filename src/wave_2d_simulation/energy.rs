@@ -0,0 +1,312 @@
+use std::collections::VecDeque;
+
+use bevy::prelude::*;
+use bevy_egui::egui::plot::{Legend, Line, Plot, PlotPoints};
+use bevy_egui::{egui, EguiContext};
+use ndarray::{Array3, Axis};
+
+use super::finite_difference::laplace_operator;
+use super::simulation_plugin::{alpha, update_wave};
+use super::tools::{apply_tools, PlotToolEvent};
+use super::Wave2dSimulationPlugin;
+use super::{UiEvents, Wave2dSimulationGrid, Wave2dSimulationParameters};
+use crate::simulation::Simulation;
+use crate::simulation_control::SimulationControl;
+
+/// Number of frames kept in the energy plot
+const HISTORY_SIZE: usize = 600;
+
+/// Below this total energy the field counts as empty and the drift is not
+/// measured
+const MIN_REFERENCE_ENERGY: f64 = 1e-9;
+
+#[derive(Clone, Copy)]
+struct EnergySample {
+    /// simulated seconds
+    time: f64,
+    kinetic: f64,
+    potential: f64,
+}
+
+impl EnergySample {
+    fn total(&self) -> f64 {
+        self.kinetic + self.potential
+    }
+}
+
+/// Kinetic and potential energy of the field over time.
+///
+/// The energies are given per unit of density, with lengths in world units.
+#[derive(Default, Resource)]
+pub struct EnergyMonitor {
+    /// oldest sample first
+    history: VecDeque<EnergySample>,
+    elapsed_secs: f64,
+    /// total energy the drift is measured against, taken again after every
+    /// excitation
+    reference: Option<f64>,
+    was_lossless: bool,
+}
+
+impl EnergyMonitor {
+    fn clear(&mut self) {
+        *self = Self {
+            was_lossless: self.was_lossless,
+            ..default()
+        };
+    }
+
+    /// Relative change of the total energy since the reference was taken
+    fn drift(&self) -> Option<f64> {
+        let reference = self.reference?;
+        let latest = self.history.back()?;
+
+        Some((latest.total() - reference) / reference)
+    }
+}
+
+/// Energy of the field after a solver step.
+///
+/// The potential energy pairs the older of the two newest time levels with
+/// the Laplace operator of the solver applied to the newest one, which is
+/// the form the leapfrog scheme with this stencil conserves.
+fn field_energy(
+    u: &Array3<f32>,
+    parameters: &Wave2dSimulationParameters,
+) -> EnergySample {
+    let newest = u.index_axis(Axis(0), 0);
+    let older = u.index_axis(Axis(0), 1);
+    let cell_area = (parameters.cellsize as f64).powi(2);
+
+    let kinetic: f64 = newest
+        .iter()
+        .zip(older.iter())
+        .map(|(newest, older)| {
+            ((newest - older) as f64 / parameters.dt as f64).powi(2)
+        })
+        .sum::<f64>()
+        * 0.5
+        * cell_area;
+
+    // the cell area cancels with the squared cell size of the operator
+    let laplace = laplace_operator(
+        parameters.dimx,
+        parameters.dimy,
+        newest.as_slice().expect("the grid is in standard layout"),
+    );
+    // the squared wave velocity the solver steps with, which is clamped to
+    // the stability limit if asked to
+    let velocity_squared = alpha(parameters) as f64
+        * (parameters.cellsize as f64 / parameters.dt as f64).powi(2);
+    let potential = -0.5
+        * velocity_squared
+        * older
+            .iter()
+            .zip(&laplace)
+            .map(|(older, laplace)| (older * laplace) as f64)
+            .sum::<f64>();
+
+    EnergySample {
+        time: 0.0,
+        kinetic,
+        potential,
+    }
+}
+
+pub struct EnergyPlugin;
+
+impl Plugin for EnergyPlugin {
+    fn build(&self, app: &mut App) {
+        app.insert_resource(EnergyMonitor::default())
+            .add_system_set(
+                SystemSet::on_update(Wave2dSimulationPlugin::STATE)
+                    .with_system(
+                        update_energy.after(update_wave).after(apply_tools),
+                    )
+                    .with_system(show_energy_window),
+            )
+            .add_system_set(
+                SystemSet::on_exit(Wave2dSimulationPlugin::STATE)
+                    .with_system(clear_history),
+            );
+    }
+}
+
+fn update_energy(
+    time: Res<Time>,
+    control: Res<SimulationControl>,
+    parameters: Res<Wave2dSimulationParameters>,
+    u: Res<Wave2dSimulationGrid>,
+    mut monitor: ResMut<EnergyMonitor>,
+    mut tool_events: EventReader<PlotToolEvent>,
+    mut ui_events: EventReader<UiEvents>,
+) {
+    // every excitation adds energy, the drift is measured from there on
+    let mut excited = false;
+    for event in tool_events.iter() {
+        excited |= event.excites();
+    }
+    for event in ui_events.iter() {
+        if let UiEvents::Reset
+        | UiEvents::ResizeGrid(_)
+        | UiEvents::LoadSnapshot = event
+        {
+            monitor.clear();
+        }
+    }
+    if excited || parameters.lossless != monitor.was_lossless {
+        monitor.reference = None;
+        monitor.was_lossless = parameters.lossless;
+    }
+
+    let elapsed_secs = if control.is_paused() {
+        if control.is_stepping() {
+            parameters.dt
+        } else {
+            return;
+        }
+    } else {
+        control.delta_seconds(&time) * parameters.time_scale
+    };
+    monitor.elapsed_secs += elapsed_secs as f64;
+
+    if u.0.dim().0 < 2 {
+        return;
+    }
+
+    let sample = EnergySample {
+        time: monitor.elapsed_secs,
+        ..field_energy(&u.0, &parameters)
+    };
+    if monitor.reference.is_none() && sample.total() > MIN_REFERENCE_ENERGY {
+        monitor.reference = Some(sample.total());
+    }
+
+    if monitor.history.len() >= HISTORY_SIZE {
+        monitor.history.pop_front();
+    }
+    monitor.history.push_back(sample);
+}
+
+fn clear_history(mut monitor: ResMut<EnergyMonitor>) {
+    monitor.clear();
+}
+
+fn show_energy_window(
+    mut egui_ctx: ResMut<EguiContext>,
+    monitor: Res<EnergyMonitor>,
+    mut parameters: ResMut<Wave2dSimulationParameters>,
+) {
+    if !parameters.show_energy {
+        return;
+    }
+
+    let mut open = true;
+    egui::Window::new("energy")
+        .open(&mut open)
+        .resizable(false)
+        .show(egui_ctx.ctx_mut(), |ui| {
+            let points = |energy: fn(&EnergySample) -> f64| -> PlotPoints {
+                monitor
+                    .history
+                    .iter()
+                    .map(|sample| [sample.time, energy(sample)])
+                    .collect()
+            };
+
+            Plot::new("energy_plot")
+                .width(320.0)
+                .height(160.0)
+                .allow_drag(false)
+                .allow_zoom(false)
+                .include_y(0.0)
+                .legend(Legend::default())
+                .show(ui, |plot_ui| {
+                    plot_ui.line(
+                        Line::new(points(|sample| sample.kinetic))
+                            .name("kinetic"),
+                    );
+                    plot_ui.line(
+                        Line::new(points(|sample| sample.potential))
+                            .name("potential"),
+                    );
+                    plot_ui.line(
+                        Line::new(points(EnergySample::total))
+                            .color(egui::Color32::YELLOW)
+                            .name("total"),
+                    );
+                });
+
+            if let Some(sample) = monitor.history.back() {
+                ui.label(format!(
+                    "kinetic: {:.3e}, potential: {:.3e}, total: {:.3e}",
+                    sample.kinetic,
+                    sample.potential,
+                    sample.total()
+                ));
+            }
+
+            ui.add(egui::Checkbox::new(
+                &mut parameters.lossless,
+                "lossless (no energy loss, damping or driving)",
+            ));
+            match monitor.drift() {
+                Some(drift) => {
+                    let label = if parameters.lossless {
+                        "drift"
+                    } else {
+                        "change"
+                    };
+                    ui.label(format!(
+                        "{} since the last excitation: {:+.3} %",
+                        label,
+                        drift * 100.0
+                    ));
+                }
+                None => {
+                    ui.label("click into the plot to excite the field");
+                }
+            }
+        });
+
+    if !open {
+        parameters.show_energy = false;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use ndarray::Array2;
+
+    use super::*;
+    use crate::wave_2d_simulation::simulation_plugin::step_wave;
+
+    #[test]
+    fn lossless_field_keeps_its_energy() {
+        let parameters = Wave2dSimulationParameters {
+            dimx: 64,
+            dimy: 64,
+            lossless: true,
+            ..default()
+        };
+        let dim = (parameters.dimx, parameters.dimy);
+        let obstacles = Array2::from_elem(dim, false);
+        let damping = Array2::zeros(dim);
+
+        // a gaussian bump at rest in the middle of the grid
+        let mut u = Array3::from_shape_fn((3, dim.0, dim.1), |(_, x, y)| {
+            let r2 = (x as f32 - 32.0).powi(2) + (y as f32 - 32.0).powi(2);
+            (-r2 / 18.0).exp()
+        });
+
+        step_wave(&mut u, &obstacles, &damping, &parameters);
+        let reference = field_energy(&u, &parameters).total();
+
+        for _ in 0..500 {
+            step_wave(&mut u, &obstacles, &damping, &parameters);
+            let drift =
+                (field_energy(&u, &parameters).total() - reference) / reference;
+            assert!(drift.abs() < 1e-3, "drift {:e}", drift);
+        }
+    }
+}
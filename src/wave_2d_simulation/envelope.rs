@@ -0,0 +1,49 @@
+use bevy::prelude::*;
+use ndarray::{s, Array2, Array3, Zip};
+
+use super::UiEvents;
+use super::Wave2dSimulationPlugin;
+use crate::simulation::Simulation;
+
+/// Largest absolute amplitude every cell reached since the last reset,
+/// recorded after every solver step so no peak between two frames is missed
+#[derive(Default, Resource)]
+pub struct Wave2dEnvelope(pub Array2<f32>);
+
+impl Wave2dEnvelope {
+    pub(super) fn record(&mut self, simulation_grid: &Array3<f32>) {
+        let amplitudes = simulation_grid.slice(s![0, .., ..]);
+
+        // the grid was resized or a snapshot was loaded
+        if self.0.shape() != amplitudes.shape() {
+            self.0 = Array2::zeros(amplitudes.raw_dim());
+        }
+
+        Zip::from(&mut self.0)
+            .and(&amplitudes)
+            .for_each(|max, &amplitude| *max = max.max(amplitude.abs()));
+    }
+}
+
+pub struct EnvelopePlugin;
+
+impl Plugin for EnvelopePlugin {
+    fn build(&self, app: &mut App) {
+        app.insert_resource(Wave2dEnvelope::default())
+            .add_system_set(
+                SystemSet::on_update(Wave2dSimulationPlugin::STATE)
+                    .with_system(on_ui_events),
+            );
+    }
+}
+
+fn on_ui_events(
+    mut ui_events: EventReader<UiEvents>,
+    mut envelope: ResMut<Wave2dEnvelope>,
+) {
+    for event in ui_events.iter() {
+        if let UiEvents::Reset | UiEvents::ClearEnvelope = event {
+            envelope.0.fill(0.0);
+        }
+    }
+}
@@ -0,0 +1,64 @@
+use bevy::prelude::*;
+use ndarray::Array3;
+use serde::{Deserialize, Serialize};
+
+/// Excites the grid with a gaussian bump around the cursor, its radius also
+/// sizes the eraser and the obstacle painter
+#[derive(Clone, Copy, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct ExcitationBrush {
+    /// holding the left mouse button keeps exciting the grid along the path
+    /// of the cursor instead of only on clicks
+    pub drag: bool,
+    /// in cells, the bump falls to 1/e² of its height at the radius
+    pub radius: f32,
+    /// amplitude the center of the bump is raised to
+    pub strength: f32,
+}
+
+impl Default for ExcitationBrush {
+    fn default() -> Self {
+        Self {
+            drag: false,
+            radius: 2.0,
+            strength: 1.0,
+        }
+    }
+}
+
+/// Pulls the newest time level towards the strength of the brush, with a
+/// gaussian falloff around `x` and `y` in cells.
+///
+/// Holding the brush in place keeps the center at its strength instead of
+/// adding up.
+pub(super) fn excite(
+    u: &mut Array3<f32>,
+    brush: ExcitationBrush,
+    x: f32,
+    y: f32,
+) {
+    let radius = brush.radius.max(0.5);
+    let reach = 1.5 * radius;
+    let (_, dimx, dimy) = u.dim();
+
+    // the outermost cells are never updated by the solver
+    let min_x = (x - reach).floor().max(1.0) as usize;
+    let min_y = (y - reach).floor().max(1.0) as usize;
+    let max_x = ((x + reach).ceil().max(0.0) as usize).min(dimx - 1);
+    let max_y = ((y + reach).ceil().max(0.0) as usize).min(dimy - 1);
+
+    for cell_x in min_x..=max_x {
+        for cell_y in min_y..=max_y {
+            let distance_squared =
+                Vec2::new(cell_x as f32 - x, cell_y as f32 - y)
+                    .length_squared();
+            if distance_squared > reach * reach {
+                continue;
+            }
+
+            let weight = (-2.0 * distance_squared / (radius * radius)).exp();
+            let cell = &mut u[(0, cell_x, cell_y)];
+            *cell += (brush.strength - *cell) * weight;
+        }
+    }
+}
@@ -0,0 +1,167 @@
+use std::fs::File;
+use std::io::{self, BufWriter, Write};
+use std::path::Path;
+
+use bevy::prelude::*;
+use ndarray::{s, ArrayView3, Axis};
+use serde::{Deserialize, Serialize};
+
+use super::Wave2dSimulationPlugin;
+use super::{UiEvents, Wave2dSimulationGrid, Wave2dSimulationParameters};
+use crate::file_dialog::pick_file;
+use crate::simulation::Simulation;
+use crate::vtk::write_image_data;
+
+const EXPORT_DIRECTORY: &str = "exports";
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub enum ExportFormat {
+    Csv,
+    Npy,
+    /// VTK image data for ParaView
+    Vti,
+}
+
+impl ExportFormat {
+    fn extension(&self) -> &'static str {
+        match self {
+            ExportFormat::Csv => "csv",
+            ExportFormat::Npy => "npy",
+            ExportFormat::Vti => "vti",
+        }
+    }
+}
+
+pub struct ExportPlugin;
+
+impl Plugin for ExportPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_system_set(
+            SystemSet::on_update(Wave2dSimulationPlugin::STATE)
+                .with_system(on_ui_events),
+        );
+    }
+}
+
+fn on_ui_events(
+    mut ui_events: EventReader<UiEvents>,
+    u: Res<Wave2dSimulationGrid>,
+    parameters: Res<Wave2dSimulationParameters>,
+) {
+    for event in ui_events.iter() {
+        if let UiEvents::ExportFrame = event {
+            let grid = if parameters.export_history {
+                u.0.view()
+            } else {
+                u.0.slice(s![0..1, .., ..])
+            };
+
+            let format = parameters.export_format;
+            // the browser has no files, the dialog warns about it there
+            let path = if let Some(path) =
+                pick_file(EXPORT_DIRECTORY, format.extension(), "wave_2d", true)
+            {
+                path
+            } else {
+                continue;
+            };
+
+            match export_grid(&path, grid, format, parameters.cellsize) {
+                Ok(()) => info!("exported frame to {}", path.display()),
+                Err(error) => error!("failed to export frame: {}", error),
+            }
+        }
+    }
+}
+
+fn export_grid(
+    path: &Path,
+    grid: ArrayView3<f32>,
+    format: ExportFormat,
+    cellsize: f32,
+) -> io::Result<()> {
+    let mut writer = BufWriter::new(File::create(path)?);
+
+    match format {
+        ExportFormat::Csv => write_csv(&mut writer, grid)?,
+        ExportFormat::Npy => write_npy(&mut writer, grid)?,
+        ExportFormat::Vti => write_vti(&mut writer, grid, cellsize)?,
+    }
+
+    writer.flush()
+}
+
+/// Writes every time slice as a block of `dimx` rows with `dimy` columns,
+/// introduced by a comment line (`numpy.loadtxt` skips those)
+pub fn write_csv(
+    writer: &mut impl Write,
+    grid: ArrayView3<f32>,
+) -> io::Result<()> {
+    for (index, slice) in grid.outer_iter().enumerate() {
+        writeln!(writer, "# slice {}", index)?;
+
+        for row in slice.outer_iter() {
+            let line = row
+                .iter()
+                .map(|value| value.to_string())
+                .collect::<Vec<_>>()
+                .join(",");
+            writeln!(writer, "{}", line)?;
+        }
+    }
+
+    Ok(())
+}
+
+/// Writes every time slice as a field of VTK image data, the current
+/// amplitude is `u`, older slices are numbered
+fn write_vti(
+    writer: &mut impl Write,
+    grid: ArrayView3<f32>,
+    cellsize: f32,
+) -> io::Result<()> {
+    let names: Vec<_> = (0..grid.len_of(Axis(0)))
+        .map(|index| match index {
+            0 => "u".to_string(),
+            _ => format!("u_{}", index),
+        })
+        .collect();
+    let fields: Vec<_> = names
+        .iter()
+        .map(String::as_str)
+        .zip(grid.outer_iter())
+        .collect();
+
+    write_image_data(writer, &fields, cellsize)
+}
+
+/// Writes the grid in the NumPy `.npy` format (version 1.0, little endian
+/// f32, C order)
+pub fn write_npy(
+    writer: &mut impl Write,
+    grid: ArrayView3<f32>,
+) -> io::Result<()> {
+    let shape = grid.shape();
+    let mut header = format!(
+        "{{'descr': '<f4', 'fortran_order': False, 'shape': ({}, {}, {}), }}",
+        shape[0], shape[1], shape[2]
+    );
+
+    // magic string, version and header length take 10 bytes, the whole
+    // preamble has to be aligned to 64 bytes and end with a newline
+    let unpadded = 10 + header.len() + 1;
+    header.push_str(&" ".repeat((64 - unpadded % 64) % 64));
+    header.push('\n');
+
+    writer.write_all(b"\x93NUMPY")?;
+    writer.write_all(&[1, 0])?;
+    writer.write_all(&(header.len() as u16).to_le_bytes())?;
+    writer.write_all(header.as_bytes())?;
+
+    // iterating an array view follows the logical (C) order
+    for value in grid.iter() {
+        writer.write_all(&value.to_le_bytes())?;
+    }
+
+    Ok(())
+}
@@ -1,41 +1,107 @@
 // courtesy of https://beltoforion.de/en/recreational_mathematics/2d-wave-equation.php
 
 use ndarray::prelude::*;
-use ndarray::ViewRepr;
-
+#[cfg(not(target_arch = "wasm32"))]
+use rayon::prelude::*;
+
+/// Largest Courant number `c * dt / dx` for which the leapfrog scheme with
+/// the stencil below stays stable.
+///
+/// The stencil has its largest eigenvalue (about -13.0) for the
+/// checkerboard mode, the scheme is stable as long as
+/// `cfl^2 * 13.0 <= 4`.
+pub const MAX_STABLE_CFL_NUMBER: f32 = 0.5546;
+
+/// Cells on each side of a cell the stencil reaches
+pub const STENCIL_RADIUS: usize = 4;
+
+/// Writes the next time step into the cells of `next` which are further than
+/// [`STENCIL_RADIUS`] from the edge, the layers are row major `dimx * dimy`
+/// slices
 pub fn update_with_laplace_operator(
     dimx: usize,
     dimy: usize,
-    tau: Array2<f32>,
-    u: &Array3<f32>,
-) -> Array2<f32> {
-    let alphas: ArrayBase<ViewRepr<&f32>, Dim<[usize; 2]>> =
-        tau.slice(s![4..dimx - 4, 4..dimy - 4]);
-
-    let laplace_operator: Array2<f32> = -1.0 / 500.0 * &u.slice(s![1, 4..dimx-4, 0..dimy-8])                     // c, r - 4
-        + 8.0/315.0 * &u.slice(s![1, 4..dimx-4, 1..dimy-7])         // c, r - 3
-        - 1.0/5.0 * &u.slice(s![1, 4..dimx-4, 2..dimy-6])           // c, r - 2
-        + 8.0/5.0 * &u.slice(s![1, 4..dimx-4, 3..dimy-5])           // c, r - 1
-
-        - 1.0/560.0 * &u.slice(s![1, 0..dimx-8, 4..dimy-4])         // c - 4, r
-        + 8.0/315.0 * &u.slice(s![1, 1..dimx-7, 4..dimy-4])         // c - 3, r
-        - 1.0/5.0 * &u.slice(s![1, 2..dimx-6, 4..dimy-4])
-        + 8.0/5.0 * &u.slice(s![1, 3..dimx-5, 4..dimy-4])
-        - 410.0/72.0 * &u.slice(s![1, 4..dimx-4, 4..dimy-4])        // c, r
-        + 8.0/5.0 * &u.slice(s![1, 5..dimx-3, 4..dimy-4])           // c + 1, r
-        - 1.0/5.0 * &u.slice(s![1, 6..dimx-2, 4..dimy-4])
-        + 8.0/315.0 * &u.slice(s![1, 7..dimx-1, 4..dimy-4])
-        - 1.0/560.0 * &u.slice(s![1, 8..dimx, 4..dimy-4])
-
-        + 8.0/5.0 * &u.slice(s![1, 4..dimx-4, 5..dimy-3])           // c, r + 1
-        - 1.0/5.0 * &u.slice(s![1, 4..dimx-4, 6..dimy-2])
-        + 8.0 / 325.0 * &u.slice(s![1, 4..dimx - 4, 7..dimy - 1])
-        - 1.0 / 560.0 * &u.slice(s![1, 4..dimx - 4, 8..dimy]);
-
-    let prev: Array2<f32> = 2.0 * &u.slice(s![1, 4..dimx - 4, 4..dimy - 4])
-        - u.slice(s![2, 4..dimx - 4, 4..dimy - 4]);
-
-    laplace_operator * alphas + prev
+    alpha: f32,
+    next: &mut [f32],
+    current: &[f32],
+    previous: &[f32],
+) {
+    for_each_row(next, dimy, |c, row| {
+        if (STENCIL_RADIUS..dimx - STENCIL_RADIUS).contains(&c) {
+            update_row(dimy, alpha, c, row, current, previous);
+        }
+    });
+}
+
+/// Laplace operator of the stencil below times the squared cell size, zero
+/// in the cells [`update_with_laplace_operator`] doesn't update
+pub fn laplace_operator(dimx: usize, dimy: usize, grid: &[f32]) -> Vec<f32> {
+    // a step with unit alpha from twice the grid leaves only the operator
+    let twice: Vec<f32> = grid.iter().map(|u| 2.0 * u).collect();
+    let mut laplace = vec![0.0; grid.len()];
+    update_with_laplace_operator(dimx, dimy, 1.0, &mut laplace, grid, &twice);
+
+    laplace
+}
+
+/// Calls `f` with the index and cells of every row of `grid`, spread over all
+/// cores where threads are available
+pub fn for_each_row(
+    grid: &mut [f32],
+    dimy: usize,
+    f: impl Fn(usize, &mut [f32]) + Send + Sync,
+) {
+    #[cfg(not(target_arch = "wasm32"))]
+    grid.par_chunks_mut(dimy)
+        .enumerate()
+        .for_each(|(c, row)| f(c, row));
+    #[cfg(target_arch = "wasm32")]
+    grid.chunks_mut(dimy)
+        .enumerate()
+        .for_each(|(c, row)| f(c, row));
+}
+
+/// The rows of the stencil are sliced once so the inner loop runs over
+/// plain slices without index arithmetic
+#[rustfmt::skip]
+fn update_row(
+    dimy: usize,
+    alpha: f32,
+    c: usize,
+    next: &mut [f32],
+    current: &[f32],
+    previous: &[f32],
+) {
+    let rows: [&[f32]; 2 * STENCIL_RADIUS + 1] = std::array::from_fn(|k| {
+        let start = (c + k - STENCIL_RADIUS) * dimy;
+        &current[start..start + dimy]
+    });
+    let center = rows[STENCIL_RADIUS];
+    let previous = &previous[c * dimy..(c + 1) * dimy];
+
+    for r in STENCIL_RADIUS..dimy - STENCIL_RADIUS {
+        let laplace_operator = -1.0 / 560.0 * center[r - 4]   // c, r - 4
+            + 8.0 / 315.0 * center[r - 3]                      // c, r - 3
+            - 1.0 / 5.0 * center[r - 2]                        // c, r - 2
+            + 8.0 / 5.0 * center[r - 1]                        // c, r - 1
+
+            - 1.0 / 560.0 * rows[0][r]                         // c - 4, r
+            + 8.0 / 315.0 * rows[1][r]                         // c - 3, r
+            - 1.0 / 5.0 * rows[2][r]
+            + 8.0 / 5.0 * rows[3][r]
+            - 410.0 / 72.0 * center[r]                         // c, r
+            + 8.0 / 5.0 * rows[5][r]                           // c + 1, r
+            - 1.0 / 5.0 * rows[6][r]
+            + 8.0 / 315.0 * rows[7][r]
+            - 1.0 / 560.0 * rows[8][r]
+
+            + 8.0 / 5.0 * center[r + 1]                        // c, r + 1
+            - 1.0 / 5.0 * center[r + 2]
+            + 8.0 / 315.0 * center[r + 3]
+            - 1.0 / 560.0 * center[r + 4];
+
+        next[r] = alpha * laplace_operator + 2.0 * center[r] - previous[r];
+    }
 }
 
 pub fn _update_with_absorbing_boundary(
@@ -0,0 +1,328 @@
+use std::f32::consts::{FRAC_PI_2, PI};
+
+use bevy::prelude::*;
+use bevy_egui::egui;
+use ndarray::Array2;
+use serde::{Deserialize, Serialize};
+
+use super::simulation_plugin::force_position;
+use super::Wave2dSimulationPlugin;
+use super::{UiEvents, Wave2dObstacleMask, Wave2dSimulationParameters};
+use crate::simulation::Simulation;
+
+/// Cells of the closed tube behind the source at the throat of the horn
+const HORN_BACK_LENGTH: f32 = 6.0;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub enum GeometryKind {
+    /// straight channel across the grid through the applied force
+    Waveguide,
+    /// channel from the applied force which turns by a right angle
+    Bend,
+    /// channel whose width grows exponentially from the applied force
+    Horn,
+}
+
+impl GeometryKind {
+    pub const ALL: [GeometryKind; 3] = [
+        GeometryKind::Waveguide,
+        GeometryKind::Bend,
+        GeometryKind::Horn,
+    ];
+}
+
+impl From<GeometryKind> for String {
+    fn from(value: GeometryKind) -> Self {
+        match value {
+            GeometryKind::Waveguide => "waveguide".to_string(),
+            GeometryKind::Bend => "bend".to_string(),
+            GeometryKind::Horn => "exponential horn".to_string(),
+        }
+    }
+}
+
+/// Walls of a guide around the applied force, rasterized into the obstacles
+#[derive(Clone, Copy, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct GeometryBuilder {
+    pub kind: GeometryKind,
+    /// inner width of the channel, the throat of the horn, in cells
+    pub width: f32,
+    /// thickness of the walls in cells
+    pub wall: f32,
+    /// radius of the center line of the bend in cells
+    pub bend_radius: f32,
+    /// inner width of the horn mouth in cells
+    pub mouth_width: f32,
+    /// length of the flare of the horn in cells
+    pub horn_length: f32,
+    /// clear the obstacles before the walls are placed
+    pub replace: bool,
+}
+
+impl Default for GeometryBuilder {
+    fn default() -> Self {
+        Self {
+            kind: GeometryKind::Waveguide,
+            width: 12.0,
+            wall: 2.0,
+            bend_radius: 30.0,
+            mouth_width: 80.0,
+            horn_length: 80.0,
+            replace: true,
+        }
+    }
+}
+
+impl GeometryBuilder {
+    /// Marks the cells of the walls
+    fn rasterize(
+        &self,
+        obstacles: &mut Array2<bool>,
+        parameters: &Wave2dSimulationParameters,
+    ) {
+        let (fx, fy) = force_position(parameters);
+        let source = Vec2::new(fx as f32, fy as f32);
+        let (dimx, dimy) = (parameters.dimx as f32, parameters.dimy as f32);
+        let half_width = self.width.max(1.0) / 2.0;
+        let wall = self.wall.max(1.0);
+
+        let center_line = match self.kind {
+            GeometryKind::Waveguide => vec![CenterLine::Segment(
+                Vec2::new(dimx / 6.0, source.y),
+                Vec2::new(5.0 * dimx / 6.0, source.y),
+            )],
+            GeometryKind::Bend => {
+                // the source sits in the straight part before the turn
+                let turn = Vec2::new(source.x - self.width, source.y);
+                let radius = self.bend_radius.max(half_width + wall);
+                let center = turn - Vec2::Y * radius;
+                let end_y = (dimy / 6.0).min(center.y);
+
+                vec![
+                    CenterLine::Segment(
+                        Vec2::new(5.0 * dimx / 6.0, source.y),
+                        turn,
+                    ),
+                    CenterLine::Arc {
+                        center,
+                        radius,
+                        from: FRAC_PI_2,
+                        to: PI,
+                    },
+                    CenterLine::Segment(
+                        Vec2::new(center.x - radius, center.y),
+                        Vec2::new(center.x - radius, end_y),
+                    ),
+                ]
+            }
+            GeometryKind::Horn => {
+                self.rasterize_horn(obstacles, source, half_width, wall);
+                return;
+            }
+        };
+
+        for ((x, y), obstacle) in obstacles.indexed_iter_mut() {
+            let cell = Vec2::new(x as f32, y as f32);
+            let distance = center_line
+                .iter()
+                .filter_map(|piece| piece.distance(cell))
+                .reduce(f32::min);
+
+            if let Some(distance) = distance {
+                if (half_width..=half_width + wall).contains(&distance) {
+                    *obstacle = true;
+                }
+            }
+        }
+    }
+
+    /// Marks the walls of the flare, which opens towards the left of the
+    /// source, and of the closed tube behind the source
+    fn rasterize_horn(
+        &self,
+        obstacles: &mut Array2<bool>,
+        source: Vec2,
+        half_width: f32,
+        wall: f32,
+    ) {
+        let flare = self.flare_rate();
+
+        for ((x, y), obstacle) in obstacles.indexed_iter_mut() {
+            // measured from the throat towards the mouth
+            let along = source.x - x as f32;
+            let off_axis = (y as f32 - source.y).abs();
+
+            let inner = if along < 0.0 {
+                half_width
+            } else {
+                half_width * (flare * along).exp()
+            };
+            let is_wall = (-HORN_BACK_LENGTH..=self.horn_length)
+                .contains(&along)
+                && (inner..=inner + wall).contains(&off_axis);
+            let is_back = (-HORN_BACK_LENGTH - wall..-HORN_BACK_LENGTH)
+                .contains(&along)
+                && off_axis <= half_width + wall;
+
+            if is_wall || is_back {
+                *obstacle = true;
+            }
+        }
+    }
+
+    /// Growth of the width of the horn per cell
+    fn flare_rate(&self) -> f32 {
+        (self.mouth_width.max(1.0) / self.width.max(1.0)).ln()
+            / self.horn_length.max(1.0)
+    }
+
+    /// Frequency in Hz below which waves don't travel along the guide
+    fn cutoff_frequency(&self, parameters: &Wave2dSimulationParameters) -> f32 {
+        match self.kind {
+            // the walls hold the field at rest, so the lowest mode spans
+            // half a wavelength across the channel
+            GeometryKind::Waveguide | GeometryKind::Bend => {
+                parameters.wave_velocity
+                    / (2.0 * self.width.max(1.0) * parameters.cellsize)
+            }
+            // Webster's horn passes frequencies above m c / (4 pi), with
+            // the flare rate m of the cross section
+            GeometryKind::Horn => {
+                self.flare_rate() / parameters.cellsize
+                    * parameters.wave_velocity
+                    / (4.0 * PI)
+            }
+        }
+    }
+}
+
+/// Piece of the center line of a channel
+enum CenterLine {
+    Segment(Vec2, Vec2),
+    /// counterclockwise between the angles in radians
+    Arc {
+        center: Vec2,
+        radius: f32,
+        from: f32,
+        to: f32,
+    },
+}
+
+impl CenterLine {
+    /// Distance of a point from the piece, none beyond its ends so open ends
+    /// stay open
+    fn distance(&self, point: Vec2) -> Option<f32> {
+        match *self {
+            CenterLine::Segment(start, end) => {
+                let along = end - start;
+                let t = (point - start).dot(along) / along.length_squared();
+
+                (0.0..=1.0)
+                    .contains(&t)
+                    .then(|| point.distance(start + along * t))
+            }
+            CenterLine::Arc {
+                center,
+                radius,
+                from,
+                to,
+            } => {
+                let offset = point - center;
+                let angle = offset.y.atan2(offset.x);
+
+                (from..=to)
+                    .contains(&angle)
+                    .then(|| (offset.length() - radius).abs())
+            }
+        }
+    }
+}
+
+pub struct GeometryPlugin;
+
+impl Plugin for GeometryPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_system_set(
+            SystemSet::on_update(Wave2dSimulationPlugin::STATE)
+                .with_system(on_ui_events),
+        );
+    }
+}
+
+fn on_ui_events(
+    mut ui_events: EventReader<UiEvents>,
+    mut obstacles: ResMut<Wave2dObstacleMask>,
+    parameters: Res<Wave2dSimulationParameters>,
+) {
+    for event in ui_events.iter() {
+        if let UiEvents::PlaceGeometry(builder) = event {
+            if builder.replace {
+                obstacles.0.fill(false);
+            }
+            builder.rasterize(&mut obstacles.0, &parameters);
+        }
+    }
+}
+
+pub fn show_geometry_builder(
+    ui: &mut egui::Ui,
+    parameters: &mut Wave2dSimulationParameters,
+    ui_events: &mut EventWriter<UiEvents>,
+) {
+    let builder = &mut parameters.geometry_builder;
+
+    ui.horizontal(|ui| {
+        ui.label("guide:");
+        for kind in GeometryKind::ALL {
+            ui.radio_value(&mut builder.kind, kind, String::from(kind));
+        }
+    });
+    let width_label = if builder.kind == GeometryKind::Horn {
+        "throat width in cells"
+    } else {
+        "width in cells"
+    };
+    ui.add(egui::Slider::new(&mut builder.width, 2.0..=80.0).text(width_label));
+    ui.add(
+        egui::Slider::new(&mut builder.wall, 1.0..=10.0)
+            .text("wall thickness in cells"),
+    );
+    match builder.kind {
+        GeometryKind::Waveguide => {}
+        GeometryKind::Bend => {
+            ui.add(
+                egui::Slider::new(&mut builder.bend_radius, 5.0..=150.0)
+                    .text("bend radius in cells"),
+            );
+        }
+        GeometryKind::Horn => {
+            ui.add(
+                egui::Slider::new(&mut builder.mouth_width, 2.0..=300.0)
+                    .text("mouth width in cells"),
+            );
+            ui.add(
+                egui::Slider::new(&mut builder.horn_length, 5.0..=300.0)
+                    .text("flare length in cells"),
+            );
+        }
+    }
+    ui.horizontal(|ui| {
+        if ui.button("Place").clicked() {
+            ui_events.send(UiEvents::PlaceGeometry(*builder));
+        }
+        ui.checkbox(&mut builder.replace, "replace obstacles");
+    });
+
+    let builder = *builder;
+    let cutoff = builder.cutoff_frequency(parameters);
+    let passes = if parameters.applied_force_frequency_hz > cutoff {
+        "passes"
+    } else {
+        "is cut off"
+    };
+    ui.label(format!(
+        "cutoff {:.2} Hz, the applied force at {:.2} Hz {}",
+        cutoff, parameters.applied_force_frequency_hz, passes
+    ));
+}
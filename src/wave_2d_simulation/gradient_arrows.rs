@@ -0,0 +1,129 @@
+use bevy::prelude::*;
+use bevy_egui::{egui, EguiContext};
+use serde::{Deserialize, Serialize};
+
+use super::animation_plugin::Plot;
+use super::Wave2dSimulationPlugin;
+use super::{PlotView, Wave2dSimulationGrid, Wave2dSimulationParameters};
+use crate::simulation::Simulation;
+use crate::velocity_arrows::paint_arrow;
+use crate::viewports::world_to_egui;
+use crate::AppCamera;
+
+const ARROW_COLOR: egui::Color32 = egui::Color32::from_rgb(255, 170, 60);
+
+/// Arrows of the discrete gradient of the amplitude on a coarse grid over
+/// the flat plot
+#[derive(Clone, Copy, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct GradientArrows {
+    pub show: bool,
+    /// cells between neighboring arrows
+    pub spacing: usize,
+    /// arrow length in cells per gradient, relative to the amplitude the
+    /// colors are normalized to
+    pub scale: f32,
+}
+
+impl Default for GradientArrows {
+    fn default() -> Self {
+        Self {
+            show: false,
+            spacing: 12,
+            scale: 20.0,
+        }
+    }
+}
+
+pub struct GradientArrowsPlugin;
+
+impl Plugin for GradientArrowsPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_system_set(
+            SystemSet::on_update(Wave2dSimulationPlugin::STATE)
+                .with_system(draw_gradient_arrows),
+        );
+    }
+}
+
+/// Draws an arrow along the central difference gradient at every
+/// `spacing`-th cell, the arrows are at most as long as their spacing
+fn draw_gradient_arrows(
+    mut egui_ctx: ResMut<EguiContext>,
+    windows: Res<Windows>,
+    u: Res<Wave2dSimulationGrid>,
+    parameters: Res<Wave2dSimulationParameters>,
+    cameras: Query<(&Camera, &GlobalTransform), With<AppCamera>>,
+    plots: Query<&Transform, With<Plot>>,
+) {
+    let arrows = parameters.gradient_arrows;
+    if !arrows.show || parameters.plot_view != PlotView::Flat {
+        return;
+    }
+
+    let ((camera, camera_transform), plot_transform, window) = match (
+        cameras.get_single(),
+        plots.get_single(),
+        windows.get_primary(),
+    ) {
+        (Ok(camera), Ok(transform), Some(window)) => {
+            (camera, transform, window)
+        }
+        _ => return,
+    };
+
+    let to_egui = |cells: Vec2| {
+        let world = plot_transform.translation
+            + (cells * parameters.cellsize).extend(0.0);
+        world_to_egui(camera, camera_transform, window, world)
+    };
+
+    let (_, dimx, dimy) = u.0.dim();
+    if dimx < 3 || dimy < 3 {
+        return;
+    }
+
+    let painter = egui_ctx.ctx_mut().layer_painter(egui::LayerId::new(
+        egui::Order::Foreground,
+        egui::Id::new("wave_2d_gradient_arrows"),
+    ));
+    let stroke = egui::Stroke::new(1.0, ARROW_COLOR);
+
+    let spacing = arrows.spacing.max(1);
+    let max_length = spacing as f32;
+    let factor = arrows.scale / parameters.color_amplitude().max(f32::EPSILON);
+
+    for x in (spacing / 2..dimx - 1).step_by(spacing).filter(|x| *x > 0) {
+        for y in (spacing / 2..dimy - 1).step_by(spacing).filter(|y| *y > 0) {
+            let gradient = Vec2::new(
+                u.0[(0, x + 1, y)] - u.0[(0, x - 1, y)],
+                u.0[(0, x, y + 1)] - u.0[(0, x, y - 1)],
+            ) / 2.0;
+            let arrow = (gradient * factor).clamp_length_max(max_length);
+
+            let origin = Vec2::new(x as f32, y as f32);
+            if let (Some(start), Some(end)) =
+                (to_egui(origin), to_egui(origin + arrow))
+            {
+                paint_arrow(&painter, start, end, stroke);
+            }
+        }
+    }
+}
+
+pub fn show_gradient_arrows(ui: &mut egui::Ui, arrows: &mut GradientArrows) {
+    ui.add(egui::Checkbox::new(&mut arrows.show, "gradient arrows"))
+        .on_hover_text("points uphill, perpendicular to the wavefronts");
+
+    if arrows.show {
+        ui.add(
+            egui::Slider::new(&mut arrows.spacing, 4..=64)
+                .text("arrow spacing in cells"),
+        );
+        ui.add(
+            egui::Slider::new(&mut arrows.scale, 1.0..=500.0)
+                .logarithmic(true)
+                .text("arrow scale"),
+        );
+    }
+}
@@ -0,0 +1,179 @@
+use std::fs::{self, File};
+use std::io::{BufWriter, Write};
+use std::path::Path;
+
+use ndarray::{s, Array2, Array3};
+
+use super::export::write_npy;
+use super::simulation_plugin::{apply_force, step_wave, ApplyingForceTimer};
+use super::sweep::{
+    ParameterSweep, ParameterSweepSettings, SweepMetric, SweepProbe,
+};
+use super::Wave2dSimulationParameters;
+use crate::cli::Cli;
+
+/// Runs the solver for `cli.steps` steps without any rendering and writes
+/// the final grid and the probe traces to `cli.output`, or the metric of
+/// every value of a parameter sweep
+pub fn run_headless(cli: &Cli) -> Result<(), String> {
+    let parameters = parameters_from_cli(cli);
+
+    if cli.sweep.is_some() {
+        return run_sweep(cli, &parameters);
+    }
+
+    for &(x, y) in cli.probes.iter() {
+        if x >= parameters.dimx || y >= parameters.dimy {
+            return Err(format!(
+                "probe ({}, {}) is outside of the {}x{} grid",
+                x, y, parameters.dimx, parameters.dimy
+            ));
+        }
+    }
+
+    let mut u = Array3::zeros((3, parameters.dimx, parameters.dimy));
+    let obstacles =
+        Array2::from_elem((parameters.dimx, parameters.dimy), false);
+    let damping = Array2::zeros((parameters.dimx, parameters.dimy));
+    let mut applying_force_timer = ApplyingForceTimer::default();
+
+    if !parameters.is_stable() {
+        eprintln!(
+            "warning: the CFL number {:.3} exceeds the stability limit",
+            parameters.cfl_number()
+        );
+    }
+
+    let mut traces = vec![Vec::new(); cli.probes.len()];
+
+    for _ in 0..cli.steps() {
+        apply_force(&mut applying_force_timer, &mut u, &parameters);
+        step_wave(&mut u, &obstacles, &damping, &parameters);
+
+        for (trace, &(x, y)) in traces.iter_mut().zip(cli.probes.iter()) {
+            trace.push(u[[0, x, y]]);
+        }
+    }
+
+    write_results(&cli.output(), &u, &cli.probes, &traces, parameters.dt)
+        .map_err(|error| format!("failed to write results: {}", error))
+}
+
+/// Runs `cli.steps` steps at every value of the swept parameter, the first
+/// half of them settles the field, and writes the metric of every run
+fn run_sweep(
+    cli: &Cli,
+    parameters: &Wave2dSimulationParameters,
+) -> Result<(), String> {
+    let defaults = ParameterSweepSettings::default();
+    let settings = ParameterSweepSettings {
+        parameter: cli.sweep.unwrap_or(defaults.parameter),
+        from: cli.sweep_from.unwrap_or(defaults.from),
+        to: cli.sweep_to.unwrap_or(defaults.to),
+        values: cli.sweep_values(),
+        steps: cli.steps(),
+        settle_steps: cli.steps() / 2,
+        metric: cli.sweep_metric.unwrap_or(SweepMetric::MaxAmplitude),
+        probe: cli
+            .probes
+            .first()
+            .map_or(SweepProbe::Grid, |&(x, y)| SweepProbe::Cell(x, y)),
+    };
+
+    let dim = (parameters.dimx, parameters.dimy);
+    let mut sweep = ParameterSweep::new(
+        settings,
+        parameters,
+        Array2::from_elem(dim, false),
+        Array2::zeros(dim),
+    )?;
+    while !sweep.is_done() {
+        sweep.advance(settings.steps);
+    }
+
+    write_sweep(&cli.output(), &sweep.curve)
+        .map_err(|error| format!("failed to write results: {}", error))
+}
+
+/// Default parameters with the grid size and the force of the command line,
+/// also used when the simulation starts with a window
+pub fn parameters_from_cli(cli: &Cli) -> Wave2dSimulationParameters {
+    let mut parameters = Wave2dSimulationParameters::default();
+
+    // the stencil needs at least one cell inside the boundary
+    let min_dim = 2 * parameters.boundary_size + 1;
+
+    if let Some(dimx) = cli.dimx {
+        parameters.dimx = dimx.max(min_dim);
+    }
+    if let Some(dimy) = cli.dimy {
+        parameters.dimy = dimy.max(min_dim);
+    }
+    parameters.requested_grid_size.dimx = parameters.dimx;
+    parameters.requested_grid_size.dimy = parameters.dimy;
+    if let Some(frequency) = cli.frequency {
+        parameters.apply_force = true;
+        parameters.applied_force_frequency_hz = frequency;
+    }
+    if let Some(wave_velocity) = cli.wave_velocity {
+        parameters.wave_velocity = wave_velocity;
+    }
+    if let Some(fraction) = cli.energy_loss_fraction {
+        parameters.syntetic_energy_loss_fraction = fraction;
+    }
+
+    parameters
+}
+
+fn write_results(
+    output: &Path,
+    u: &Array3<f32>,
+    probes: &[(usize, usize)],
+    traces: &[Vec<f32>],
+    dt: f32,
+) -> std::io::Result<()> {
+    fs::create_dir_all(output)?;
+
+    let mut writer =
+        BufWriter::new(File::create(output.join("wave_2d_grid.npy"))?);
+    write_npy(&mut writer, u.slice(s![0..1, .., ..]))?;
+    writer.flush()?;
+
+    if probes.is_empty() {
+        return Ok(());
+    }
+
+    let mut writer =
+        BufWriter::new(File::create(output.join("wave_2d_probes.csv"))?);
+
+    let header = probes
+        .iter()
+        .map(|(x, y)| format!("probe_{}_{}", x, y))
+        .collect::<Vec<_>>()
+        .join(",");
+    writeln!(writer, "time,{}", header)?;
+
+    for step in 0..traces[0].len() {
+        let line = traces
+            .iter()
+            .map(|trace| trace[step].to_string())
+            .collect::<Vec<_>>()
+            .join(",");
+        writeln!(writer, "{},{}", (step + 1) as f32 * dt, line)?;
+    }
+
+    writer.flush()
+}
+
+fn write_sweep(output: &Path, curve: &[[f64; 2]]) -> std::io::Result<()> {
+    fs::create_dir_all(output)?;
+
+    let mut writer =
+        BufWriter::new(File::create(output.join("wave_2d_sweep.csv"))?);
+    writeln!(writer, "value,metric")?;
+    for [value, metric] in curve {
+        writeln!(writer, "{},{}", value, metric)?;
+    }
+
+    writer.flush()
+}
@@ -0,0 +1,104 @@
+use std::path::Path;
+
+use bevy::prelude::*;
+use image::imageops::FilterType;
+use image::GrayImage;
+use ndarray::Array2;
+use serde::{Deserialize, Serialize};
+
+use super::Wave2dSimulationPlugin;
+use super::{
+    UiEvents, Wave2dObstacleMask, Wave2dSimulationGrid,
+    Wave2dSimulationParameters,
+};
+use crate::file_dialog::pick_file_of_types;
+use crate::simulation::Simulation;
+
+const IMAGE_DIRECTORY: &str = "images";
+const IMAGE_EXTENSIONS: &[&str] = &["png", "jpg", "jpeg", "bmp"];
+
+/// What the luminance of an imported image is turned into
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub enum ImageImportTarget {
+    /// bright pixels become a positive initial displacement
+    Displacement,
+    /// dark pixels become obstacles
+    Obstacles,
+}
+
+pub struct ImageImportPlugin;
+
+impl Plugin for ImageImportPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_system_set(
+            SystemSet::on_update(Wave2dSimulationPlugin::STATE)
+                .with_system(on_ui_events),
+        );
+    }
+}
+
+fn on_ui_events(
+    mut ui_events: EventReader<UiEvents>,
+    mut u: ResMut<Wave2dSimulationGrid>,
+    mut obstacles: ResMut<Wave2dObstacleMask>,
+    parameters: Res<Wave2dSimulationParameters>,
+) {
+    for event in ui_events.iter() {
+        if let UiEvents::ImportImage(target) = event {
+            let path = if let Some(path) = pick_file_of_types(
+                IMAGE_DIRECTORY,
+                IMAGE_EXTENSIONS,
+                "image",
+                false,
+            ) {
+                path
+            } else {
+                continue;
+            };
+
+            let luminance =
+                match load_luminance(&path, parameters.dimx, parameters.dimy) {
+                    Ok(luminance) => luminance,
+                    Err(error) => {
+                        error!(
+                            "failed to import {}: {}",
+                            path.display(),
+                            error
+                        );
+                        continue;
+                    }
+                };
+
+            match target {
+                ImageImportTarget::Displacement => {
+                    // all three time slices get the same field, so the
+                    // simulation starts without any velocity
+                    for mut slice in u.0.outer_iter_mut() {
+                        slice.assign(&luminance);
+                    }
+                }
+                ImageImportTarget::Obstacles => {
+                    obstacles.0 = luminance.mapv(|l| l < 0.5);
+                }
+            }
+        }
+    }
+}
+
+/// Loads an image, scales it to the grid and returns its luminance in
+/// `0.0..=1.0` indexed by grid cell
+fn load_luminance(
+    path: &Path,
+    dimx: usize,
+    dimy: usize,
+) -> image::ImageResult<Array2<f32>> {
+    let image: GrayImage = image::open(path)?
+        .resize_exact(dimx as u32, dimy as u32, FilterType::Triangle)
+        .to_luma8();
+
+    // image rows go downwards, the y axis of the grid goes upwards
+    Ok(Array2::from_shape_fn((dimx, dimy), |(x, y)| {
+        let pixel = image.get_pixel(x as u32, (dimy - 1 - y) as u32);
+        pixel.0[0] as f32 / 255.0
+    }))
+}
@@ -0,0 +1,192 @@
+use bevy::prelude::*;
+use bevy_egui::{egui, EguiContext};
+use ndarray::{s, ArrayView2};
+use serde::{Deserialize, Serialize};
+
+use super::animation_plugin::Plot;
+use super::Wave2dSimulationPlugin;
+use super::{PlotView, Wave2dSimulationGrid, Wave2dSimulationParameters};
+use crate::simulation::Simulation;
+use crate::viewports::world_to_egui;
+use crate::AppCamera;
+
+const POSITIVE_COLOR: egui::Color32 = egui::Color32::from_rgb(255, 120, 80);
+const NEGATIVE_COLOR: egui::Color32 = egui::Color32::from_rgb(80, 160, 255);
+const ZERO_COLOR: egui::Color32 = egui::Color32::WHITE;
+
+/// Contour lines of the amplitude at multiples of `step`
+#[derive(Clone, Copy, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Isolines {
+    pub show: bool,
+    /// amplitude between neighboring levels
+    pub step: f32,
+    /// levels above and below zero
+    pub count: usize,
+    /// also draws the nodal lines at zero
+    pub zero: bool,
+}
+
+impl Default for Isolines {
+    fn default() -> Self {
+        Self {
+            show: false,
+            step: 0.2,
+            count: 2,
+            zero: true,
+        }
+    }
+}
+
+impl Isolines {
+    fn levels(&self) -> impl Iterator<Item = f32> + '_ {
+        let count = self.count as i32;
+        (-count..=count)
+            .filter(move |i| *i != 0 || self.zero)
+            .map(move |i| i as f32 * self.step)
+    }
+}
+
+pub struct IsolinesPlugin;
+
+impl Plugin for IsolinesPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_system_set(
+            SystemSet::on_update(Wave2dSimulationPlugin::STATE)
+                .with_system(draw_isolines),
+        );
+    }
+}
+
+/// Line segments in cells where the bilinear interpolation of `u` crosses
+/// `level`, found with marching squares
+fn marching_squares(u: ArrayView2<f32>, level: f32) -> Vec<(Vec2, Vec2)> {
+    let (dimx, dimy) = u.dim();
+    let mut segments = Vec::new();
+
+    for x in 0..dimx.saturating_sub(1) {
+        for y in 0..dimy.saturating_sub(1) {
+            // corners counterclockwise from the bottom left
+            let corners = [
+                (Vec2::new(x as f32, y as f32), u[(x, y)] - level),
+                (Vec2::new(x as f32 + 1.0, y as f32), u[(x + 1, y)] - level),
+                (
+                    Vec2::new(x as f32 + 1.0, y as f32 + 1.0),
+                    u[(x + 1, y + 1)] - level,
+                ),
+                (Vec2::new(x as f32, y as f32 + 1.0), u[(x, y + 1)] - level),
+            ];
+
+            let above = corners.map(|(_, value)| value >= 0.0);
+            if above.iter().all(|a| *a) || above.iter().all(|a| !*a) {
+                continue;
+            }
+
+            // crossings on the bottom, right, top and left edge
+            let crossings = [0, 1, 2, 3].map(|edge| {
+                let (start, start_value) = corners[edge];
+                let (end, end_value) = corners[(edge + 1) % 4];
+                (above[edge] != above[(edge + 1) % 4]).then(|| {
+                    let t = start_value / (start_value - end_value);
+                    start.lerp(end, t)
+                })
+            });
+
+            match crossings {
+                [Some(bottom), Some(right), Some(top), Some(left)] => {
+                    // a saddle, the center decides which corners are joined
+                    let center = corners.iter().map(|(_, v)| v).sum::<f32>();
+                    if (center >= 0.0) == above[0] {
+                        segments.push((bottom, right));
+                        segments.push((top, left));
+                    } else {
+                        segments.push((left, bottom));
+                        segments.push((right, top));
+                    }
+                }
+                _ => {
+                    let mut points = crossings.iter().flatten();
+                    if let (Some(start), Some(end)) =
+                        (points.next(), points.next())
+                    {
+                        segments.push((*start, *end));
+                    }
+                }
+            }
+        }
+    }
+
+    segments
+}
+
+fn draw_isolines(
+    mut egui_ctx: ResMut<EguiContext>,
+    windows: Res<Windows>,
+    u: Res<Wave2dSimulationGrid>,
+    parameters: Res<Wave2dSimulationParameters>,
+    cameras: Query<(&Camera, &GlobalTransform), With<AppCamera>>,
+    plots: Query<&Transform, With<Plot>>,
+) {
+    let isolines = parameters.isolines;
+    if !isolines.show || parameters.plot_view != PlotView::Flat {
+        return;
+    }
+
+    let ((camera, camera_transform), plot_transform, window) = match (
+        cameras.get_single(),
+        plots.get_single(),
+        windows.get_primary(),
+    ) {
+        (Ok(camera), Ok(transform), Some(window)) => {
+            (camera, transform, window)
+        }
+        _ => return,
+    };
+
+    let to_egui = |cells: Vec2| {
+        let world = plot_transform.translation
+            + (cells * parameters.cellsize).extend(0.0);
+        world_to_egui(camera, camera_transform, window, world)
+    };
+
+    let painter = egui_ctx.ctx_mut().layer_painter(egui::LayerId::new(
+        egui::Order::Foreground,
+        egui::Id::new("wave_2d_isolines"),
+    ));
+
+    let amplitudes = u.0.slice(s![0, .., ..]);
+    for level in isolines.levels() {
+        let color = if level > 0.0 {
+            POSITIVE_COLOR
+        } else if level < 0.0 {
+            NEGATIVE_COLOR
+        } else {
+            ZERO_COLOR
+        };
+        let stroke = egui::Stroke::new(1.0, color);
+
+        for (start, end) in marching_squares(amplitudes, level) {
+            if let (Some(start), Some(end)) = (to_egui(start), to_egui(end)) {
+                painter.line_segment([start, end], stroke);
+            }
+        }
+    }
+}
+
+pub fn show_isolines(ui: &mut egui::Ui, isolines: &mut Isolines) {
+    ui.add(egui::Checkbox::new(&mut isolines.show, "isolines"))
+        .on_hover_text("contour lines at multiples of the level step");
+
+    if isolines.show {
+        ui.add(
+            egui::Slider::new(&mut isolines.step, 0.01..=1.0)
+                .logarithmic(true)
+                .text("level step"),
+        );
+        ui.add(
+            egui::Slider::new(&mut isolines.count, 0..=10)
+                .text("levels on each side of zero"),
+        );
+        ui.add(egui::Checkbox::new(&mut isolines.zero, "nodal lines"));
+    }
+}
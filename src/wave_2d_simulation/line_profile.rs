@@ -0,0 +1,350 @@
+use bevy::prelude::*;
+use bevy_egui::egui;
+use bevy_egui::egui::plot::{Legend, Line, Plot, PlotPoints, VLine};
+use ndarray::Array3;
+
+use super::animation_plugin::{screen_to_plot, Plot as PlotMesh};
+use super::resonance::find_peaks;
+use super::simulation_plugin::update_wave;
+use super::tools::PlotTool;
+use super::Wave2dSimulationPlugin;
+use super::{UiEvents, Wave2dSimulationGrid, Wave2dSimulationParameters};
+use crate::simulation::Simulation;
+use crate::simulation_control::SimulationControl;
+use crate::AppCamera;
+
+const LINE_COLOR: Color = Color::rgb(0.2, 1.0, 0.4);
+
+/// Fraction of the newest squared amplitude mixed into the running mean of
+/// the intensity every frame
+const INTENSITY_SMOOTHING: f32 = 0.02;
+
+/// Amplitude along a line dragged across the flat plot.
+///
+/// The running mean of the squared amplitude shows the stationary fringes of
+/// an interference pattern, their maxima give the fringe spacing.
+#[derive(Default, Resource)]
+pub struct LineProfile {
+    /// start and end in cells
+    line: Option<(Vec2, Vec2)>,
+    /// `[distance in world units, amplitude]` of the samples along the line
+    amplitude: Vec<[f64; 2]>,
+    /// running mean of the squared amplitude of every sample
+    intensity: Vec<f32>,
+    /// distance of the intensity maxima in world units
+    fringes: Vec<f64>,
+}
+
+impl LineProfile {
+    fn set_line(&mut self, line: Option<(Vec2, Vec2)>) {
+        *self = Self { line, ..default() };
+    }
+
+    /// Mean distance between neighbouring intensity maxima
+    fn fringe_spacing(&self) -> Option<f64> {
+        match (self.fringes.first(), self.fringes.last()) {
+            (Some(first), Some(last)) if self.fringes.len() > 1 => {
+                Some((last - first) / (self.fringes.len() - 1) as f64)
+            }
+            _ => None,
+        }
+    }
+
+    fn sample(
+        &mut self,
+        u: &Array3<f32>,
+        parameters: &Wave2dSimulationParameters,
+        accumulate: bool,
+    ) {
+        let (start, end) = if let Some(line) = self.line {
+            line
+        } else {
+            return;
+        };
+
+        let samples = (start.distance(end).ceil() as usize).max(1) + 1;
+        if self.intensity.len() != samples {
+            self.intensity = vec![0.0; samples];
+        }
+
+        self.amplitude = (0..samples)
+            .map(|i| {
+                let fraction = i as f32 / (samples - 1) as f32;
+                let position = start.lerp(end, fraction);
+                let distance = start.distance(position) * parameters.cellsize;
+
+                [distance as f64, interpolate(u, position) as f64]
+            })
+            .collect();
+
+        if accumulate {
+            for (intensity, [_, amplitude]) in
+                self.intensity.iter_mut().zip(&self.amplitude)
+            {
+                *intensity += INTENSITY_SMOOTHING
+                    * ((*amplitude as f32).powi(2) - *intensity);
+            }
+        }
+
+        let intensity_curve: Vec<[f64; 2]> = self
+            .amplitude
+            .iter()
+            .zip(&self.intensity)
+            .map(|([distance, _], intensity)| [*distance, *intensity as f64])
+            .collect();
+        self.fringes = find_peaks(&intensity_curve);
+    }
+}
+
+/// Bilinear interpolation of the current amplitude, zero outside the grid
+fn interpolate(u: &Array3<f32>, position: Vec2) -> f32 {
+    let cell = position.floor();
+    let fraction = position - cell;
+    if cell.min_element() < 0.0 {
+        return 0.0;
+    }
+
+    [
+        (0, 0, (1.0 - fraction.x) * (1.0 - fraction.y)),
+        (1, 0, fraction.x * (1.0 - fraction.y)),
+        (0, 1, (1.0 - fraction.x) * fraction.y),
+        (1, 1, fraction.x * fraction.y),
+    ]
+    .iter()
+    .filter_map(|(dx, dy, weight)| {
+        u.get((0, cell.x as usize + dx, cell.y as usize + dy))
+            .map(|u| u * weight)
+    })
+    .sum()
+}
+
+#[derive(Component)]
+struct ProfileLineMarker;
+
+pub struct LineProfilePlugin;
+
+impl Plugin for LineProfilePlugin {
+    fn build(&self, app: &mut App) {
+        app.insert_resource(LineProfile::default())
+            .add_system_set(
+                SystemSet::on_update(Wave2dSimulationPlugin::STATE)
+                    .with_system(drag_line)
+                    .with_system(
+                        update_profile.after(update_wave).after(drag_line),
+                    )
+                    .with_system(update_marker.after(drag_line))
+                    .with_system(on_ui_events),
+            )
+            .add_system_set(
+                SystemSet::on_exit(Wave2dSimulationPlugin::STATE)
+                    .with_system(cleanup),
+            );
+    }
+}
+
+/// Pressing the left button starts a new line, dragging moves its end
+fn drag_line(
+    windows: Res<Windows>,
+    buttons: Res<Input<MouseButton>>,
+    parameters: Res<Wave2dSimulationParameters>,
+    cameras: Query<(&Camera, &GlobalTransform), With<AppCamera>>,
+    plots: Query<&Transform, With<PlotMesh>>,
+    mut profile: ResMut<LineProfile>,
+) {
+    if parameters.tool != PlotTool::Profile
+        || !buttons.pressed(MouseButton::Left)
+    {
+        return;
+    }
+
+    let (camera, camera_transform) = if let Ok(camera) = cameras.get_single() {
+        camera
+    } else {
+        return;
+    };
+    let plot_transform = if let Ok(transform) = plots.get_single() {
+        transform
+    } else {
+        return;
+    };
+    let window = if let Some(window) = windows.get_primary() {
+        window
+    } else {
+        return;
+    };
+
+    if let Some(cursor) = window.cursor_position() {
+        let position = screen_to_plot(
+            window,
+            camera,
+            camera_transform,
+            plot_transform,
+            &parameters,
+            cursor,
+        );
+        let grid_size =
+            Vec2::new(parameters.dimx as f32, parameters.dimy as f32);
+        let position = position.clamp(Vec2::ZERO, grid_size - Vec2::ONE);
+
+        match profile.line {
+            Some((start, _)) if !buttons.just_pressed(MouseButton::Left) => {
+                profile.line = Some((start, position));
+            }
+            _ => profile.set_line(Some((position, position))),
+        }
+    }
+}
+
+fn update_profile(
+    control: Res<SimulationControl>,
+    parameters: Res<Wave2dSimulationParameters>,
+    u: Res<Wave2dSimulationGrid>,
+    mut profile: ResMut<LineProfile>,
+) {
+    // the intensity of a paused field would converge to a single snapshot
+    let accumulate = !control.is_paused() || control.is_stepping();
+    profile.sample(&u.0, &parameters, accumulate);
+}
+
+/// Shows the line on top of the flat plot
+fn update_marker(
+    mut commands: Commands,
+    parameters: Res<Wave2dSimulationParameters>,
+    profile: Res<LineProfile>,
+    plots: Query<&Transform, (With<PlotMesh>, Without<ProfileLineMarker>)>,
+    mut markers: Query<
+        (Entity, &mut Transform, &mut Sprite),
+        With<ProfileLineMarker>,
+    >,
+) {
+    let (plot_translation, (start, end)) =
+        match (plots.get_single(), profile.line) {
+            (Ok(transform), Some(line)) => (transform.translation, line),
+            _ => {
+                for (entity, _, _) in markers.iter() {
+                    commands.entity(entity).despawn();
+                }
+                return;
+            }
+        };
+
+    let center =
+        plot_translation.truncate() + (start + end) / 2.0 * parameters.cellsize;
+    let length = start.distance(end) * parameters.cellsize;
+    let direction = (end - start).normalize_or_zero();
+    let transform = Transform::from_translation(center.extend(1.0))
+        .with_rotation(Quat::from_rotation_z(direction.y.atan2(direction.x)));
+    let size = Vec2::new(length.max(1.0), parameters.cellsize.max(1.0));
+
+    if let Ok((_, mut marker_transform, mut sprite)) = markers.get_single_mut()
+    {
+        *marker_transform = transform;
+        sprite.custom_size = Some(size);
+    } else {
+        commands.spawn((
+            ProfileLineMarker,
+            SpriteBundle {
+                sprite: Sprite {
+                    color: LINE_COLOR,
+                    custom_size: Some(size),
+                    ..default()
+                },
+                transform,
+                ..default()
+            },
+        ));
+    }
+}
+
+fn on_ui_events(
+    mut ui_events: EventReader<UiEvents>,
+    mut profile: ResMut<LineProfile>,
+) {
+    for event in ui_events.iter() {
+        match event {
+            UiEvents::ResizeGrid(_) | UiEvents::ClearProfileLine => {
+                profile.set_line(None);
+            }
+            UiEvents::Reset => {
+                let line = profile.line;
+                profile.set_line(line);
+            }
+            _ => {}
+        }
+    }
+}
+
+fn cleanup(
+    mut commands: Commands,
+    markers: Query<Entity, With<ProfileLineMarker>>,
+) {
+    for entity in markers.iter() {
+        commands.entity(entity).despawn();
+    }
+}
+
+pub fn show_line_profile(
+    ui: &mut egui::Ui,
+    parameters: &Wave2dSimulationParameters,
+    profile: &LineProfile,
+    ui_events: &mut EventWriter<UiEvents>,
+) {
+    if profile.line.is_none() {
+        if parameters.tool == PlotTool::Profile {
+            ui.label("drag a line across the flat plot to measure its profile");
+        }
+        return;
+    }
+
+    ui.horizontal(|ui| {
+        ui.label("line profile");
+        if ui.button("Clear").clicked() {
+            ui_events.send(UiEvents::ClearProfileLine);
+        }
+    });
+
+    let intensity: PlotPoints = profile
+        .amplitude
+        .iter()
+        .zip(&profile.intensity)
+        .map(|([distance, _], intensity)| {
+            [*distance, (*intensity as f64).sqrt()]
+        })
+        .collect();
+
+    Plot::new("line_profile_plot")
+        .height(160.0)
+        .allow_drag(false)
+        .allow_zoom(false)
+        .include_y(-1.0)
+        .include_y(1.0)
+        .legend(Legend::default())
+        .show(ui, |plot_ui| {
+            plot_ui.line(
+                Line::new(PlotPoints::from(profile.amplitude.clone()))
+                    .name("amplitude"),
+            );
+            plot_ui.line(
+                Line::new(intensity)
+                    .color(egui::Color32::YELLOW)
+                    .name("rms amplitude"),
+            );
+            for fringe in &profile.fringes {
+                plot_ui
+                    .vline(VLine::new(*fringe).color(egui::Color32::DARK_GRAY));
+            }
+        });
+
+    if let Some(spacing) = profile.fringe_spacing() {
+        let cells = spacing / parameters.cellsize as f64;
+        ui.label(format!(
+            "{} fringes, spacing: {:.2} ({:.2} cells, {:.3e} m)",
+            profile.fringes.len(),
+            spacing,
+            cells,
+            cells * parameters.meters_per_cell() as f64
+        ));
+    } else {
+        ui.label("no fringes along the line yet");
+    }
+}
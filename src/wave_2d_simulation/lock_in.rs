@@ -0,0 +1,139 @@
+use std::f32::consts::TAU;
+
+use bevy::prelude::*;
+use ndarray::{s, Array2, Array3, Ix2, Zip};
+
+use super::Wave2dSimulationPlugin;
+use super::{AudioDrive, UiEvents, Wave2dSimulationParameters};
+use crate::simulation::Simulation;
+
+/// Cells whose response is weaker than this fraction of the strongest
+/// response have no meaningful phase
+const DETECTION_THRESHOLD: f32 = 0.02;
+
+/// Lock-in detection of the response of every cell at the frequency of the
+/// applied force.
+///
+/// The amplitude is multiplied with the sine and the cosine of the drive
+/// and both products are averaged exponentially over a few periods, their
+/// angle is the phase lag of the cell behind the source.
+#[derive(Default, Resource)]
+pub struct Wave2dLockIn {
+    in_phase: Array2<f32>,
+    quadrature: Array2<f32>,
+    /// frequency the averages belong to
+    frequency_hz: f32,
+    /// phase lag in `-PI..=PI`, NaN where nothing was detected
+    pub phase: Array2<f32>,
+}
+
+impl Wave2dLockIn {
+    /// Whether the phase can be detected, which needs a single frequency
+    /// driving the grid
+    pub fn is_locked(parameters: &Wave2dSimulationParameters) -> bool {
+        parameters.apply_force
+            && parameters.audio_drive == AudioDrive::Off
+            && !parameters.lossless
+            && parameters.applied_force_frequency_hz > 0.0
+    }
+
+    /// Adds a step to the averages, `reference` is the phase of the drive
+    /// in radians
+    pub(super) fn record(
+        &mut self,
+        simulation_grid: &Array3<f32>,
+        parameters: &Wave2dSimulationParameters,
+        reference: f32,
+    ) {
+        let amplitudes = simulation_grid.slice(s![0, .., ..]);
+
+        // the averages of another frequency are meaningless
+        if self.frequency_hz != parameters.applied_force_frequency_hz {
+            self.clear();
+            self.frequency_hz = parameters.applied_force_frequency_hz;
+        }
+        self.fit(amplitudes.raw_dim());
+
+        // the averages follow a change of the field within a few periods
+        let weight = (parameters.dt * self.frequency_hz
+            / parameters.lock_in_periods.max(f32::EPSILON))
+        .min(1.0);
+        let (sin, cos) = reference.sin_cos();
+
+        Zip::from(&mut self.in_phase)
+            .and(&mut self.quadrature)
+            .and(&amplitudes)
+            .for_each(|in_phase, quadrature, &amplitude| {
+                *in_phase += (amplitude * sin - *in_phase) * weight;
+                *quadrature += (amplitude * cos - *quadrature) * weight;
+            });
+    }
+
+    /// Updates the phase from the averages, once per frame is enough
+    pub(super) fn update_phase(&mut self, simulation_grid: &Array3<f32>) {
+        self.fit(simulation_grid.slice(s![0, .., ..]).raw_dim());
+
+        let strongest = Zip::from(&self.in_phase)
+            .and(&self.quadrature)
+            .fold(0.0f32, |max, &i, &q| max.max(i.hypot(q)));
+        let threshold = strongest * DETECTION_THRESHOLD;
+
+        // a response `sin(reference - lag)` averages to `cos(lag) / 2` with
+        // the sine and to `-sin(lag) / 2` with the cosine
+        Zip::from(&mut self.phase)
+            .and(&self.in_phase)
+            .and(&self.quadrature)
+            .for_each(|phase, &i, &q| {
+                *phase = if strongest > 0.0 && i.hypot(q) >= threshold {
+                    (-q).atan2(i)
+                } else {
+                    f32::NAN
+                };
+            });
+    }
+
+    /// Starts over when the grid was resized or a snapshot was loaded
+    fn fit(&mut self, dim: Ix2) {
+        if self.in_phase.raw_dim() != dim {
+            self.in_phase = Array2::zeros(dim);
+            self.quadrature = Array2::zeros(dim);
+            self.phase = Array2::from_elem(dim, f32::NAN);
+        }
+    }
+
+    pub(super) fn clear(&mut self) {
+        self.in_phase.fill(0.0);
+        self.quadrature.fill(0.0);
+        self.phase.fill(f32::NAN);
+    }
+}
+
+/// Phase of the drive at the given time since the force was switched on
+pub(super) fn reference_phase(
+    parameters: &Wave2dSimulationParameters,
+    elapsed_secs: f32,
+) -> f32 {
+    (elapsed_secs * parameters.applied_force_frequency_hz).fract() * TAU
+}
+
+pub struct LockInPlugin;
+
+impl Plugin for LockInPlugin {
+    fn build(&self, app: &mut App) {
+        app.insert_resource(Wave2dLockIn::default()).add_system_set(
+            SystemSet::on_update(Wave2dSimulationPlugin::STATE)
+                .with_system(on_ui_events),
+        );
+    }
+}
+
+fn on_ui_events(
+    mut ui_events: EventReader<UiEvents>,
+    mut lock_in: ResMut<Wave2dLockIn>,
+) {
+    for event in ui_events.iter() {
+        if let UiEvents::Reset = event {
+            lock_in.clear();
+        }
+    }
+}
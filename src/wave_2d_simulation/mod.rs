@@ -1,21 +1,203 @@
 use std::collections::VecDeque;
 
 use bevy::prelude::*;
-use ndarray::Array3;
+use ndarray::{Array2, Array3};
+use serde::{Deserialize, Serialize};
 
+use crate::colormap::Colormap;
+use crate::persistence::PersistenceAppExt;
+use crate::recording::{RecordableParameters, RecordingAppExt};
+use crate::simulation::Simulation;
+use crate::simulation_control::forward_reset;
+use crate::AppState;
+
+mod ab_compare;
 mod animation_plugin;
+mod axes;
+mod comparison;
+mod damping;
+mod energy;
+mod envelope;
+mod excitation;
+mod export;
 mod finite_difference;
+mod geometry;
+mod gradient_arrows;
+mod headless;
+mod image_import;
+mod isolines;
+mod line_profile;
+mod lock_in;
+mod moving_obstacle;
+mod moving_source;
+mod parallel;
+mod phononic_crystal;
+mod probe;
+mod reflector;
+mod resonance;
+mod run_export;
+mod scene_history;
 mod simulation_plugin;
+mod snapshot;
+mod streaming;
+mod surface_plot;
+mod sweep;
+mod tools;
 mod ui;
+mod validation;
 
+use ab_compare::{AbComparePlugin, Wave2dAbCompare};
 use animation_plugin::AnimationPlugin;
+use axes::AxesPlugin;
+pub use comparison::Wave2dComparisonPlugin;
+use damping::{DampingBrush, DampingPlugin};
+use energy::EnergyPlugin;
+use envelope::{EnvelopePlugin, Wave2dEnvelope};
+use excitation::ExcitationBrush;
+use export::{ExportFormat, ExportPlugin};
+use finite_difference::MAX_STABLE_CFL_NUMBER;
+use geometry::{GeometryBuilder, GeometryPlugin};
+use gradient_arrows::{GradientArrows, GradientArrowsPlugin};
+pub use headless::{parameters_from_cli, run_headless};
+use image_import::ImageImportPlugin;
+pub use image_import::ImageImportTarget;
+use isolines::{Isolines, IsolinesPlugin};
+use line_profile::LineProfilePlugin;
+use lock_in::{LockInPlugin, Wave2dLockIn};
+use moving_obstacle::{MovingObstacle, MovingObstaclePlugin};
+use moving_source::{MovingSource, MovingSourcePlugin};
+use phononic_crystal::{LatticeBuilder, PhononicCrystalPlugin};
+use probe::ProbePlugin;
+use reflector::{Reflector, ReflectorPlugin};
+use resonance::ResonancePlugin;
+use run_export::RunExportPlugin;
+use scene_history::SceneHistoryPlugin;
 use simulation_plugin::SimulationPlugin;
-pub use ui::{show_ui, UiEvents};
+use snapshot::SnapshotPlugin;
+use streaming::StreamingPlugin;
+use sweep::SweepPlugin;
+pub use sweep::{SweepMetric, SweptParameter};
+use tools::{PlotTool, ToolsPlugin};
+pub use ui::UiEvents;
+pub use validation::run_validation;
 
 #[derive(Default, Resource)]
 pub struct Wave2dSimulationGrid(Array3<f32>);
 
-#[derive(Resource)]
+/// Cells marked `true` are rigid obstacles which keep a zero amplitude
+#[derive(Default, Resource)]
+pub struct Wave2dObstacleMask(Array2<bool>);
+
+/// Fraction of the amplitude every cell loses per step on top of the energy
+/// loss, painted to place absorbing patches inside the grid
+#[derive(Default, Resource)]
+pub struct Wave2dDampingMap(Array2<f32>);
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub enum PlotView {
+    Flat,
+    Surface,
+}
+
+/// Audio signal driving the force position
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub enum AudioDrive {
+    Off,
+    Microphone,
+    /// the loaded WAV file
+    File,
+}
+
+impl AudioDrive {
+    pub const ALL: [AudioDrive; 3] =
+        [AudioDrive::Off, AudioDrive::Microphone, AudioDrive::File];
+}
+
+impl From<AudioDrive> for String {
+    fn from(value: AudioDrive) -> Self {
+        match value {
+            AudioDrive::Off => "off".to_string(),
+            AudioDrive::Microphone => "microphone".to_string(),
+            AudioDrive::File => "file".to_string(),
+        }
+    }
+}
+
+/// Field shown by the plot
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub enum PlotQuantity {
+    /// the current amplitude
+    Amplitude,
+    /// the largest absolute amplitude since the last reset
+    Envelope,
+    /// the phase lag behind the applied force
+    Phase,
+    /// snapshot A minus snapshot B of the A/B compare
+    Difference,
+}
+
+impl PlotQuantity {
+    pub const ALL: [PlotQuantity; 4] = [
+        PlotQuantity::Amplitude,
+        PlotQuantity::Envelope,
+        PlotQuantity::Phase,
+        PlotQuantity::Difference,
+    ];
+}
+
+impl From<PlotQuantity> for String {
+    fn from(value: PlotQuantity) -> Self {
+        match value {
+            PlotQuantity::Amplitude => "amplitude".to_string(),
+            PlotQuantity::Envelope => "max hold".to_string(),
+            PlotQuantity::Phase => "phase".to_string(),
+            PlotQuantity::Difference => "A − B".to_string(),
+        }
+    }
+}
+
+/// Amplitude the colors of the plot are normalized to
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub enum AmplitudeNormalization {
+    /// follows the running average of the maximum amplitude
+    Auto,
+    /// keeps the amplitude set in the ui, so frames stay comparable
+    Fixed,
+}
+
+impl AmplitudeNormalization {
+    pub const ALL: [AmplitudeNormalization; 2] =
+        [AmplitudeNormalization::Auto, AmplitudeNormalization::Fixed];
+}
+
+impl From<AmplitudeNormalization> for String {
+    fn from(value: AmplitudeNormalization) -> Self {
+        match value {
+            AmplitudeNormalization::Auto => "auto".to_string(),
+            AmplitudeNormalization::Fixed => "fixed".to_string(),
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Serialize, Deserialize)]
+pub struct GridSize {
+    pub dimx: usize,
+    pub dimy: usize,
+    pub cellsize: f32,
+}
+
+impl Default for GridSize {
+    fn default() -> Self {
+        Self {
+            dimx: 160 * 2,
+            dimy: 90 * 2,
+            cellsize: 2.7,
+        }
+    }
+}
+
+#[derive(Clone, Resource, Serialize, Deserialize)]
+#[serde(default)]
 pub struct Wave2dSimulationParameters {
     // set on initialization
     dimx: usize,
@@ -23,31 +205,174 @@ pub struct Wave2dSimulationParameters {
     cellsize: f32,
     boundary_size: usize,
     pub apply_force: bool,
+    /// audio driving the force position instead of the applied frequency
+    pub audio_drive: AudioDrive,
+    #[serde(skip)]
     pub max_amplitude: f32,
+    #[serde(skip)]
     pub max_amplitude_avg: VecDeque<f32>,
 
     // set on update
     pub syntetic_energy_loss_fraction: f32,
     pub applied_force_frequency_hz: f32,
+    /// wave velocity in world units per second of simulated time
     pub wave_velocity: f32,
+    /// simulated seconds per solver step
+    pub dt: f32,
+    /// limit the courant number to the stability limit of the solver
+    pub clamp_to_stability_limit: bool,
+    /// the local wave velocity grows with the amplitude, so crests overtake
+    /// troughs and fronts steepen
+    pub nonlinear: bool,
+    /// relative change of the wave velocity per unit of amplitude
+    pub nonlinearity: f32,
+    pub plot_view: PlotView,
+    pub plot_quantity: PlotQuantity,
+    /// periods of the applied force the lock-in averages over
+    pub lock_in_periods: f32,
+    pub surface_height_factor: f32,
+    pub colormap: Colormap,
+    pub normalization: AmplitudeNormalization,
+    pub show_axes: bool,
+    pub show_grid_lines: bool,
+    /// physical length of one world unit, which scales the axes
+    pub meters_per_unit: f32,
+    pub gradient_arrows: GradientArrows,
+    pub isolines: Isolines,
+    /// amplitude the colors are normalized to with a fixed normalization
+    pub fixed_amplitude: f32,
+    pub export_format: ExportFormat,
+    pub export_history: bool,
+    pub requested_grid_size: GridSize,
+    /// simulated time per real time
+    pub time_scale: f32,
+    /// upper bound of solver steps per rendered frame
+    pub steps_per_frame: usize,
+    /// threads of the solver, 0 uses all cores
+    pub solver_threads: usize,
+    pub moving_source: MovingSource,
+    pub moving_obstacle: MovingObstacle,
+    pub geometry_builder: GeometryBuilder,
+    pub lattice_builder: LatticeBuilder,
+    pub reflector: Reflector,
+    pub damping_brush: DampingBrush,
+    pub excitation_brush: ExcitationBrush,
+    /// action of the left mouse button on the flat plot
+    pub tool: PlotTool,
+    pub show_energy: bool,
+    /// switches off the energy loss, the damping and all driving, so the
+    /// energy of the field should stay constant
+    pub lossless: bool,
 }
 
 impl Default for Wave2dSimulationParameters {
     fn default() -> Self {
+        let grid_size = GridSize::default();
+
         Self {
-            dimx: 160 * 2,
-            dimy: 90 * 2,
-            cellsize: 2.7,
+            dimx: grid_size.dimx,
+            dimy: grid_size.dimy,
+            cellsize: grid_size.cellsize,
             boundary_size: 4,
             apply_force: false,
+            audio_drive: AudioDrive::Off,
             max_amplitude: 1.0,
             max_amplitude_avg: VecDeque::from(vec![0.0; 27]),
 
             syntetic_energy_loss_fraction: 0.99,
             applied_force_frequency_hz: 4.0,
-            wave_velocity: 0.27,
+            wave_velocity: 84.0,
+            dt: 1.0 / 60.0,
+            clamp_to_stability_limit: false,
+            nonlinear: false,
+            nonlinearity: 0.5,
+            plot_view: PlotView::Flat,
+            plot_quantity: PlotQuantity::Amplitude,
+            lock_in_periods: 4.0,
+            surface_height_factor: 2.0,
+            colormap: Colormap::Grayscale,
+            normalization: AmplitudeNormalization::Auto,
+            show_axes: false,
+            show_grid_lines: true,
+            meters_per_unit: 0.01,
+            gradient_arrows: GradientArrows::default(),
+            isolines: Isolines::default(),
+            fixed_amplitude: 0.5,
+            export_format: ExportFormat::Npy,
+            export_history: false,
+            requested_grid_size: grid_size,
+            time_scale: 1.0,
+            steps_per_frame: 10,
+            solver_threads: 0,
+            moving_source: MovingSource::default(),
+            moving_obstacle: MovingObstacle::default(),
+            geometry_builder: GeometryBuilder::default(),
+            lattice_builder: LatticeBuilder::default(),
+            reflector: Reflector::default(),
+            damping_brush: DampingBrush::default(),
+            excitation_brush: ExcitationBrush::default(),
+            tool: PlotTool::Impulse,
+            show_energy: false,
+            lossless: false,
+        }
+    }
+}
+
+impl Wave2dSimulationParameters {
+    /// Courant number of the current configuration, the solver is unstable
+    /// above [`MAX_STABLE_CFL_NUMBER`]
+    pub fn cfl_number(&self) -> f32 {
+        self.wave_velocity * self.dt / self.cellsize
+    }
+
+    pub fn is_stable(&self) -> bool {
+        self.cfl_number() <= MAX_STABLE_CFL_NUMBER
+    }
+
+    /// Colormap of the shown quantity, phases wrap around
+    pub fn shown_colormap(&self) -> Colormap {
+        match self.plot_quantity {
+            PlotQuantity::Phase => Colormap::Twilight,
+            // the sign of the difference matters more than its size
+            PlotQuantity::Difference => Colormap::Seismic,
+            _ => self.colormap,
+        }
+    }
+
+    /// Amplitude the colors of the plot are normalized to
+    pub fn color_amplitude(&self) -> f32 {
+        match self.normalization {
+            AmplitudeNormalization::Auto => self.max_amplitude,
+            AmplitudeNormalization::Fixed => self.fixed_amplitude,
         }
     }
+
+    /// Physical width of a cell
+    pub fn meters_per_cell(&self) -> f32 {
+        self.cellsize * self.meters_per_unit
+    }
+
+    /// Largest amplitude for which the local courant number of the
+    /// nonlinear equation stays below the stability limit
+    pub fn nonlinear_amplitude_limit(&self) -> f32 {
+        (MAX_STABLE_CFL_NUMBER / self.cfl_number() - 1.0)
+            / self.nonlinearity.max(f32::EPSILON)
+    }
+}
+
+impl RecordableParameters for Wave2dSimulationParameters {
+    const KIND: &'static str = "wave_2d_parameters";
+    const SIMULATION: AppState = Wave2dSimulationPlugin::STATE;
+    const SET_ON_INITIALIZATION: &'static [&'static str] =
+        &["dimx", "dimy", "cellsize", "boundary_size"];
+
+    fn restore(&mut self, recorded: Self) {
+        *self = Self {
+            max_amplitude: self.max_amplitude,
+            max_amplitude_avg: std::mem::take(&mut self.max_amplitude_avg),
+            ..recorded
+        };
+    }
 }
 
 pub struct Wave2dSimulationPlugin;
@@ -55,8 +380,39 @@ pub struct Wave2dSimulationPlugin;
 impl Plugin for Wave2dSimulationPlugin {
     fn build(&self, app: &mut bevy::prelude::App) {
         app.add_event::<UiEvents>()
+            .add_recordable_event::<UiEvents>()
+            .add_recordable_parameters::<Wave2dSimulationParameters>()
+            .add_persistent_parameters::<Wave2dSimulationParameters>()
             .add_plugin(SimulationPlugin)
             .add_plugin(AnimationPlugin)
-            .insert_resource(Wave2dSimulationParameters::default());
+            .add_plugin(AxesPlugin)
+            .add_plugin(GradientArrowsPlugin)
+            .add_plugin(IsolinesPlugin)
+            .add_plugin(ProbePlugin)
+            .add_plugin(ResonancePlugin)
+            .add_plugin(MovingSourcePlugin)
+            .add_plugin(MovingObstaclePlugin)
+            .add_plugin(GeometryPlugin)
+            .add_plugin(PhononicCrystalPlugin)
+            .add_plugin(ReflectorPlugin)
+            .add_plugin(DampingPlugin)
+            .add_plugin(ToolsPlugin)
+            .add_plugin(LineProfilePlugin)
+            .add_plugin(EnergyPlugin)
+            .add_plugin(EnvelopePlugin)
+            .add_plugin(LockInPlugin)
+            .add_plugin(ExportPlugin)
+            .add_plugin(RunExportPlugin)
+            .add_plugin(ImageImportPlugin)
+            .add_plugin(SnapshotPlugin)
+            .add_plugin(StreamingPlugin)
+            .add_plugin(SweepPlugin)
+            .add_plugin(AbComparePlugin)
+            .add_plugin(SceneHistoryPlugin)
+            .insert_resource(Wave2dSimulationParameters::default())
+            .add_system_set(
+                SystemSet::on_update(Wave2dSimulationPlugin::STATE)
+                    .with_system(forward_reset::<UiEvents>),
+            );
     }
 }
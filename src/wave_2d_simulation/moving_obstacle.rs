@@ -0,0 +1,265 @@
+use std::f32::consts::TAU;
+
+use bevy::prelude::*;
+use bevy_egui::egui;
+use ndarray::Array2;
+use serde::{Deserialize, Serialize};
+
+use super::Wave2dSimulationPlugin;
+use super::{UiEvents, Wave2dSimulationParameters};
+use crate::simulation::Simulation;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub enum ObstacleMotion {
+    /// a vertical wall moving back and forth like a piston
+    OscillatingWall,
+    /// a bar turning around the center of the grid
+    RotatingPaddle,
+}
+
+impl ObstacleMotion {
+    pub const ALL: [ObstacleMotion; 2] = [
+        ObstacleMotion::OscillatingWall,
+        ObstacleMotion::RotatingPaddle,
+    ];
+}
+
+impl From<ObstacleMotion> for String {
+    fn from(value: ObstacleMotion) -> Self {
+        match value {
+            ObstacleMotion::OscillatingWall => "oscillating wall".to_string(),
+            ObstacleMotion::RotatingPaddle => "rotating paddle".to_string(),
+        }
+    }
+}
+
+/// A rigid bar which follows an animated path, rasterized into the
+/// obstacles before every solver step
+#[derive(Clone, Copy, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct MovingObstacle {
+    pub enabled: bool,
+    pub motion: ObstacleMotion,
+    /// length of the wall or the paddle in cells
+    pub length: f32,
+    pub thickness: f32,
+    /// how far the wall moves to either side in cells
+    pub amplitude: f32,
+    /// oscillations of the wall or turns of the paddle per second
+    pub frequency: f32,
+}
+
+impl Default for MovingObstacle {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            motion: ObstacleMotion::OscillatingWall,
+            length: 60.0,
+            thickness: 3.0,
+            amplitude: 4.0,
+            frequency: 1.0,
+        }
+    }
+}
+
+impl MovingObstacle {
+    /// End points in cells of the bar `elapsed_secs` after the start
+    fn segment(&self, elapsed_secs: f32, dim: (usize, usize)) -> (Vec2, Vec2) {
+        let center = Vec2::new(dim.0 as f32, dim.1 as f32) / 2.0;
+        let phase = TAU * self.frequency * elapsed_secs;
+
+        match self.motion {
+            ObstacleMotion::OscillatingWall => {
+                let x = dim.0 as f32 / 3.0 + self.amplitude * phase.sin();
+                let half = Vec2::Y * self.length / 2.0;
+                let middle = Vec2::new(x, center.y);
+
+                (middle - half, middle + half)
+            }
+            ObstacleMotion::RotatingPaddle => {
+                let half =
+                    Vec2::new(phase.cos(), phase.sin()) * self.length / 2.0;
+
+                (center - half, center + half)
+            }
+        }
+    }
+
+    /// Marks the cells covered by the bar
+    fn rasterize(&self, elapsed_secs: f32, mask: &mut Array2<bool>) {
+        let dim = mask.dim();
+        let (start, end) = self.segment(elapsed_secs, dim);
+        let radius = self.thickness.max(1.0) / 2.0;
+
+        // only the bounding box of the bar is visited
+        let min = (start.min(end) - radius).floor().max(Vec2::ZERO);
+        let max = (start.max(end) + radius).ceil().max(Vec2::ZERO);
+        let xs = min.x as usize..(max.x as usize + 1).min(dim.0);
+        let ys = min.y as usize..(max.y as usize + 1).min(dim.1);
+
+        for x in xs {
+            for y in ys.clone() {
+                let cell = Vec2::new(x as f32, y as f32);
+                if distance_to_segment(cell, start, end) <= radius {
+                    mask[(x, y)] = true;
+                }
+            }
+        }
+    }
+
+    /// Fastest speed of a point of the bar in world units per second, the
+    /// bar leaves a wake once it outruns the waves
+    fn max_speed(&self, parameters: &Wave2dSimulationParameters) -> f32 {
+        let radius = match self.motion {
+            ObstacleMotion::OscillatingWall => self.amplitude,
+            ObstacleMotion::RotatingPaddle => self.length / 2.0,
+        };
+
+        TAU * self.frequency * radius * parameters.cellsize
+    }
+}
+
+fn distance_to_segment(point: Vec2, start: Vec2, end: Vec2) -> f32 {
+    let along = end - start;
+    let t = if along.length_squared() > 0.0 {
+        ((point - start).dot(along) / along.length_squared()).clamp(0.0, 1.0)
+    } else {
+        0.0
+    };
+
+    point.distance(start + along * t)
+}
+
+/// Time the obstacle moved and the obstacles of the last solver step
+#[derive(Default, Resource)]
+pub struct MovingObstacleState {
+    elapsed_secs: f32,
+    /// the painted obstacles and the bar where it was in the last step
+    combined: Array2<bool>,
+}
+
+impl MovingObstacleState {
+    /// Moves the bar by one solver step and returns the obstacles the step
+    /// sees, called by the solver before every step
+    pub(super) fn step<'a>(
+        &'a mut self,
+        obstacles: &'a Array2<bool>,
+        parameters: &Wave2dSimulationParameters,
+    ) -> &'a Array2<bool> {
+        let obstacle = &parameters.moving_obstacle;
+        if !obstacle.enabled {
+            return obstacles;
+        }
+
+        if self.combined.dim() == obstacles.dim() {
+            self.combined.assign(obstacles);
+        } else {
+            self.combined = obstacles.clone();
+        }
+        obstacle.rasterize(self.elapsed_secs, &mut self.combined);
+
+        // a moving boundary drives the field, which the lossless field
+        // doesn't allow
+        if !parameters.lossless {
+            self.elapsed_secs += parameters.dt;
+        }
+
+        &self.combined
+    }
+
+    /// Obstacles the plot shows, including the bar where it was in the last
+    /// step
+    pub(super) fn shown<'a>(
+        &'a self,
+        obstacles: &'a Array2<bool>,
+        parameters: &Wave2dSimulationParameters,
+    ) -> &'a Array2<bool> {
+        if parameters.moving_obstacle.enabled
+            && self.combined.dim() == obstacles.dim()
+        {
+            &self.combined
+        } else {
+            obstacles
+        }
+    }
+}
+
+pub struct MovingObstaclePlugin;
+
+impl Plugin for MovingObstaclePlugin {
+    fn build(&self, app: &mut App) {
+        app.insert_resource(MovingObstacleState::default())
+            .add_system_set(
+                SystemSet::on_update(Wave2dSimulationPlugin::STATE)
+                    .with_system(on_ui_events),
+            );
+    }
+}
+
+fn on_ui_events(
+    mut ui_events: EventReader<UiEvents>,
+    mut state: ResMut<MovingObstacleState>,
+) {
+    for event in ui_events.iter() {
+        if let UiEvents::Reset | UiEvents::ResizeGrid(_) = event {
+            *state = MovingObstacleState::default();
+        }
+    }
+}
+
+pub fn show_moving_obstacle(
+    ui: &mut egui::Ui,
+    parameters: &mut Wave2dSimulationParameters,
+) {
+    let obstacle = &mut parameters.moving_obstacle;
+
+    ui.add(egui::Checkbox::new(
+        &mut obstacle.enabled,
+        "moving obstacle",
+    ));
+    if !obstacle.enabled {
+        return;
+    }
+
+    ui.horizontal(|ui| {
+        ui.label("motion:");
+        for option in ObstacleMotion::ALL {
+            ui.radio_value(&mut obstacle.motion, option, String::from(option));
+        }
+    });
+    ui.add(
+        egui::Slider::new(&mut obstacle.length, 2.0..=200.0)
+            .text("length in cells"),
+    );
+    ui.add(
+        egui::Slider::new(&mut obstacle.thickness, 1.0..=10.0)
+            .text("thickness in cells"),
+    );
+    if obstacle.motion == ObstacleMotion::OscillatingWall {
+        ui.add(
+            egui::Slider::new(&mut obstacle.amplitude, 0.0..=40.0)
+                .text("travel in cells"),
+        );
+    }
+    ui.add(
+        egui::Slider::new(&mut obstacle.frequency, 0.0..=10.0)
+            .step_by(0.01)
+            .text(match obstacle.motion {
+                ObstacleMotion::OscillatingWall => "oscillations per second",
+                ObstacleMotion::RotatingPaddle => "turns per second",
+            }),
+    );
+
+    let obstacle = *obstacle;
+    let mach_number = obstacle.max_speed(parameters)
+        / parameters.wave_velocity.max(f32::EPSILON);
+    ui.label(format!(
+        "fastest point at {:.2} times the wave velocity{}",
+        mach_number,
+        if mach_number > 1.0 {
+            ", leaves a wake"
+        } else {
+            ""
+        }
+    ));
+}
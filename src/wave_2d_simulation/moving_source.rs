@@ -0,0 +1,299 @@
+use std::f32::consts::TAU;
+
+use bevy::prelude::*;
+use bevy_egui::egui;
+use ndarray::Array3;
+use serde::{Deserialize, Serialize};
+
+use super::animation_plugin::Plot;
+use super::probe::Probe;
+use super::simulation_plugin::update_wave;
+use super::Wave2dSimulationPlugin;
+use super::{UiEvents, Wave2dSimulationParameters};
+use crate::simulation::Simulation;
+use crate::spectrum::peak_frequency;
+use crate::ui::to_color32;
+
+const SOURCE_COLOR: Color = Color::WHITE;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub enum SourcePath {
+    /// back and forth along the horizontal center line
+    Line,
+    /// around the center of the grid
+    Circle,
+}
+
+impl SourcePath {
+    pub const ALL: [SourcePath; 2] = [SourcePath::Line, SourcePath::Circle];
+}
+
+impl From<SourcePath> for String {
+    fn from(value: SourcePath) -> Self {
+        match value {
+            SourcePath::Line => "line".to_string(),
+            SourcePath::Circle => "circle".to_string(),
+        }
+    }
+}
+
+/// A point source which oscillates while it moves through the grid
+#[derive(Clone, Copy, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct MovingSource {
+    pub enabled: bool,
+    pub path: SourcePath,
+    /// speed of the source relative to the wave velocity
+    pub mach_number: f32,
+    pub frequency: f32,
+}
+
+impl Default for MovingSource {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            path: SourcePath::Line,
+            mach_number: 0.5,
+            frequency: 2.0,
+        }
+    }
+}
+
+impl MovingSource {
+    /// Position in cells and velocity in cells per second after the source
+    /// moved `distance` cells along its path
+    fn position_and_velocity(
+        &self,
+        distance: f32,
+        parameters: &Wave2dSimulationParameters,
+    ) -> (Vec2, Vec2) {
+        let (dimx, dimy) = (parameters.dimx as f32, parameters.dimy as f32);
+        let center = Vec2::new(dimx, dimy) / 2.0;
+        let speed =
+            self.mach_number * parameters.wave_velocity / parameters.cellsize;
+
+        match self.path {
+            SourcePath::Line => {
+                let start = Vec2::new(dimx / 5.0, center.y);
+                let length = 3.0 * dimx / 5.0;
+                let travelled = distance % (2.0 * length);
+
+                if travelled < length {
+                    (start + Vec2::X * travelled, Vec2::X * speed)
+                } else {
+                    (
+                        start + Vec2::X * (2.0 * length - travelled),
+                        Vec2::NEG_X * speed,
+                    )
+                }
+            }
+            SourcePath::Circle => {
+                let radius = dimx.min(dimy) / 3.0;
+                let angle = distance / radius;
+                let direction = Vec2::new(angle.cos(), angle.sin());
+
+                (center + direction * radius, direction.perp() * speed)
+            }
+        }
+    }
+}
+
+/// Distance the source moved along its path and the time it oscillated
+#[derive(Default, Resource)]
+pub struct MovingSourceState {
+    distance: f32,
+    elapsed_secs: f32,
+}
+
+impl MovingSourceState {
+    /// Moves the source by one solver step and writes its displacement into
+    /// the cells around it, called by the solver before every step
+    pub(super) fn step(
+        &mut self,
+        u: &mut Array3<f32>,
+        parameters: &Wave2dSimulationParameters,
+    ) {
+        let source = &parameters.moving_source;
+        if !source.enabled {
+            return;
+        }
+
+        let (position, velocity) =
+            source.position_and_velocity(self.distance, parameters);
+        let displacement = (TAU * source.frequency * self.elapsed_secs).sin();
+
+        // spread over the four nearest cells so the source moves smoothly
+        // between them
+        let cell = position.floor();
+        let fraction = position - cell;
+        for (dx, dy, weight) in [
+            (0, 0, (1.0 - fraction.x) * (1.0 - fraction.y)),
+            (1, 0, fraction.x * (1.0 - fraction.y)),
+            (0, 1, (1.0 - fraction.x) * fraction.y),
+            (1, 1, fraction.x * fraction.y),
+        ] {
+            let index = (0, cell.x as usize + dx, cell.y as usize + dy);
+            if let Some(u) = u.get_mut(index) {
+                *u += weight * (displacement - *u);
+            }
+        }
+
+        self.distance += velocity.length() * parameters.dt;
+        self.elapsed_secs += parameters.dt;
+    }
+}
+
+#[derive(Component)]
+struct MovingSourceMarker;
+
+pub struct MovingSourcePlugin;
+
+impl Plugin for MovingSourcePlugin {
+    fn build(&self, app: &mut App) {
+        app.insert_resource(MovingSourceState::default())
+            .add_system_set(
+                SystemSet::on_update(Wave2dSimulationPlugin::STATE)
+                    .with_system(update_marker.after(update_wave))
+                    .with_system(on_ui_events),
+            )
+            .add_system_set(
+                SystemSet::on_exit(Wave2dSimulationPlugin::STATE)
+                    .with_system(cleanup),
+            );
+    }
+}
+
+/// Shows where the source is on the flat plot
+fn update_marker(
+    mut commands: Commands,
+    parameters: Res<Wave2dSimulationParameters>,
+    state: Res<MovingSourceState>,
+    plots: Query<&Transform, (With<Plot>, Without<MovingSourceMarker>)>,
+    mut markers: Query<(Entity, &mut Transform), With<MovingSourceMarker>>,
+) {
+    let plot_translation = match plots.get_single() {
+        Ok(transform) if parameters.moving_source.enabled => {
+            transform.translation
+        }
+        _ => {
+            for (entity, _) in markers.iter() {
+                commands.entity(entity).despawn();
+            }
+            return;
+        }
+    };
+
+    let (position, _) = parameters
+        .moving_source
+        .position_and_velocity(state.distance, &parameters);
+    let translation =
+        plot_translation.truncate() + position * parameters.cellsize;
+
+    if let Ok((_, mut transform)) = markers.get_single_mut() {
+        transform.translation = translation.extend(1.0);
+    } else {
+        commands.spawn((
+            MovingSourceMarker,
+            SpriteBundle {
+                sprite: Sprite {
+                    color: SOURCE_COLOR,
+                    custom_size: Some(Vec2::splat(parameters.cellsize * 2.0)),
+                    ..default()
+                },
+                transform: Transform::from_translation(translation.extend(1.0)),
+                ..default()
+            },
+        ));
+    }
+}
+
+fn on_ui_events(
+    mut ui_events: EventReader<UiEvents>,
+    mut state: ResMut<MovingSourceState>,
+) {
+    for event in ui_events.iter() {
+        if let UiEvents::Reset | UiEvents::ResizeGrid(_) = event {
+            *state = MovingSourceState::default();
+        }
+    }
+}
+
+fn cleanup(
+    mut commands: Commands,
+    markers: Query<Entity, With<MovingSourceMarker>>,
+) {
+    for entity in markers.iter() {
+        commands.entity(entity).despawn();
+    }
+}
+
+/// Settings of the source and the frequency it is heard with at every probe
+pub fn show_moving_source(
+    ui: &mut egui::Ui,
+    parameters: &mut Wave2dSimulationParameters,
+    state: &MovingSourceState,
+    probes: &Query<&Probe>,
+) {
+    let source = &mut parameters.moving_source;
+
+    ui.add(egui::Checkbox::new(&mut source.enabled, "moving source"));
+    if !source.enabled {
+        return;
+    }
+
+    ui.horizontal(|ui| {
+        ui.label("path:");
+        for option in SourcePath::ALL {
+            ui.radio_value(&mut source.path, option, String::from(option));
+        }
+    });
+    ui.add(
+        egui::Slider::new(&mut source.mach_number, 0.0..=3.0)
+            .step_by(0.01)
+            .text("speed relative to wave velocity"),
+    );
+    ui.add(
+        egui::Slider::new(&mut source.frequency, 0.1..=20.0)
+            .step_by(0.1)
+            .text("source frequency in Hz"),
+    );
+
+    if source.mach_number > 1.0 {
+        ui.label(format!(
+            "mach cone half angle: {:.1}°",
+            (1.0 / source.mach_number).asin().to_degrees()
+        ));
+    }
+
+    let source = *source;
+    let (position, velocity) =
+        source.position_and_velocity(state.distance, parameters);
+
+    ui.label(format!("emitted: {:.2} Hz", source.frequency));
+    for probe in probes.iter() {
+        // moving towards the probe compresses the waves which reach it
+        let towards_probe = (Vec2::new(probe.x as f32, probe.y as f32)
+            - position)
+            .normalize_or_zero();
+        let approach_speed = velocity.dot(towards_probe) * parameters.cellsize;
+        let expected = if approach_speed < parameters.wave_velocity {
+            format!(
+                "{:.2} Hz",
+                source.frequency * parameters.wave_velocity
+                    / (parameters.wave_velocity - approach_speed)
+            )
+        } else {
+            "none, ahead of the mach cone".to_string()
+        };
+        let observed = peak_frequency(&probe.spectrum)
+            .map_or("-".to_string(), |peak| format!("{:.2} Hz", peak));
+
+        ui.colored_label(
+            to_color32(probe.color),
+            format!(
+                "({}, {}) observed: {}, expected now: {}",
+                probe.x, probe.y, observed, expected
+            ),
+        );
+    }
+}
@@ -0,0 +1,135 @@
+use bevy::prelude::*;
+
+#[cfg(not(target_arch = "wasm32"))]
+use super::{
+    finite_difference::update_with_laplace_operator, simulation_plugin::alpha,
+    Wave2dSimulationGrid, Wave2dSimulationParameters,
+};
+
+/// Laplace operator evaluations timed to estimate the speedup of the pool
+#[cfg(not(target_arch = "wasm32"))]
+const CALIBRATION_STEPS: u32 = 5;
+
+/// Thread pool the solver runs on, rebuilt whenever the requested number of
+/// threads changes
+#[derive(Default, Resource)]
+pub struct SolverThreads {
+    #[cfg(not(target_arch = "wasm32"))]
+    pool: Option<rayon::ThreadPool>,
+    requested: Option<usize>,
+    /// grid the speedup was measured on
+    dims: (usize, usize),
+    /// time of a solver step on one thread divided by the time on the pool
+    speedup: Option<f32>,
+}
+
+impl SolverThreads {
+    pub fn threads(&self) -> usize {
+        #[cfg(not(target_arch = "wasm32"))]
+        if let Some(pool) = &self.pool {
+            return pool.current_num_threads();
+        }
+
+        1
+    }
+
+    pub fn describe(&self) -> String {
+        match self.speedup {
+            Some(speedup) => format!(
+                "solver: {} threads, {:.1}x speedup",
+                self.threads(),
+                speedup
+            ),
+            None => format!("solver: {} threads", self.threads()),
+        }
+    }
+
+    /// Runs `op` on the pool, parallel iterators inside use its threads
+    pub(super) fn install<R: Send>(&self, op: impl FnOnce() -> R + Send) -> R {
+        #[cfg(not(target_arch = "wasm32"))]
+        if let Some(pool) = &self.pool {
+            return pool.install(op);
+        }
+
+        op()
+    }
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+pub(super) fn update_solver_threads(
+    mut solver_threads: ResMut<SolverThreads>,
+    u: Res<Wave2dSimulationGrid>,
+    parameters: Res<Wave2dSimulationParameters>,
+) {
+    let requested = Some(parameters.solver_threads);
+    let dims = (parameters.dimx, parameters.dimy);
+
+    if solver_threads.requested == requested && solver_threads.dims == dims {
+        return;
+    }
+
+    if solver_threads.requested != requested {
+        // zero threads lets rayon use all cores
+        match rayon::ThreadPoolBuilder::new()
+            .num_threads(parameters.solver_threads)
+            .build()
+        {
+            Ok(pool) => solver_threads.pool = Some(pool),
+            Err(error) => {
+                error!("failed to build the solver thread pool: {}", error);
+                solver_threads.pool = None;
+            }
+        }
+        solver_threads.requested = requested;
+    }
+
+    // the grid is resized on the frame after the parameters changed
+    if u.0.shape()[1..] != [dims.0, dims.1] {
+        return;
+    }
+
+    solver_threads.dims = dims;
+    solver_threads.speedup = measure_speedup(&solver_threads, &u, &parameters);
+}
+
+#[cfg(target_arch = "wasm32")]
+pub(super) fn update_solver_threads() {}
+
+#[cfg(not(target_arch = "wasm32"))]
+fn measure_speedup(
+    solver_threads: &SolverThreads,
+    u: &Wave2dSimulationGrid,
+    parameters: &Wave2dSimulationParameters,
+) -> Option<f32> {
+    let single_thread = rayon::ThreadPoolBuilder::new()
+        .num_threads(1)
+        .build()
+        .ok()?;
+    let pool = solver_threads.pool.as_ref()?;
+
+    let cells = parameters.dimx * parameters.dimy;
+    let (current, previous) = u.0.as_slice()?[cells..].split_at(cells);
+    let mut next = vec![0.0; cells];
+
+    let mut measure = |pool: &rayon::ThreadPool| {
+        let start = std::time::Instant::now();
+        for _ in 0..CALIBRATION_STEPS {
+            pool.install(|| {
+                update_with_laplace_operator(
+                    parameters.dimx,
+                    parameters.dimy,
+                    alpha(parameters),
+                    &mut next,
+                    current,
+                    previous,
+                )
+            });
+        }
+        start.elapsed().as_secs_f32()
+    };
+
+    let single_thread_secs = measure(&single_thread);
+    let pool_secs = measure(pool);
+
+    (pool_secs > 0.0).then(|| single_thread_secs / pool_secs)
+}
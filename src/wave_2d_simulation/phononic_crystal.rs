@@ -0,0 +1,423 @@
+use std::f32::consts::PI;
+
+use bevy::prelude::*;
+use bevy_egui::egui;
+use bevy_egui::egui::plot::{Line, Plot, PlotPoints, Points, VLine};
+use ndarray::Array2;
+use serde::{Deserialize, Serialize};
+
+use super::simulation_plugin::force_position;
+use super::sweep::{
+    ParameterSweep, ParameterSweepSettings, SweepMetric, SweepProbe,
+    SweptParameter,
+};
+use super::Wave2dSimulationPlugin;
+use super::{
+    UiEvents, Wave2dDampingMap, Wave2dObstacleMask, Wave2dSimulationParameters,
+};
+use crate::simulation::Simulation;
+
+/// Solver steps of both runs of the band gap sweep per rendered frame
+const BAND_GAP_STEPS_PER_FRAME: usize = 20;
+
+/// Transmission in dB below which a frequency counts as part of the gap
+const GAP_THRESHOLD_DB: f64 = -10.0;
+
+/// Square lattice of circular inclusions, a slab of columns between the
+/// applied force and the left of the grid
+#[derive(Clone, Copy, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct LatticeBuilder {
+    /// distance between neighbouring inclusions in cells
+    pub pitch: f32,
+    /// fraction of the unit cell covered by the inclusion
+    pub fill_factor: f32,
+    /// columns of inclusions along the way of the waves
+    pub columns: usize,
+    /// clear the obstacles before the lattice is placed
+    pub replace: bool,
+}
+
+impl Default for LatticeBuilder {
+    fn default() -> Self {
+        Self {
+            pitch: 12.0,
+            fill_factor: 0.3,
+            columns: 5,
+            replace: true,
+        }
+    }
+}
+
+impl LatticeBuilder {
+    fn pitch(&self) -> f32 {
+        self.pitch.max(2.0)
+    }
+
+    /// Radius of an inclusion in cells, at most touching its neighbours
+    fn inclusion_radius(&self) -> f32 {
+        let fill_factor = self.fill_factor.clamp(0.0, PI / 4.0);
+        self.pitch() * (fill_factor / PI).sqrt()
+    }
+
+    /// Left and right edge of the slab in cells, two pitches away from the
+    /// applied force
+    fn slab(&self, parameters: &Wave2dSimulationParameters) -> (f32, f32) {
+        let (fx, _) = force_position(parameters);
+        let right = fx as f32 - 2.0 * self.pitch();
+
+        (right - self.columns.max(1) as f32 * self.pitch(), right)
+    }
+
+    /// Column the transmission is measured along, two pitches behind the
+    /// slab, none if it falls into the absorbing boundary
+    fn probe_column(
+        &self,
+        parameters: &Wave2dSimulationParameters,
+    ) -> Option<usize> {
+        let (left, _) = self.slab(parameters);
+        let x = (left - 2.0 * self.pitch()).round();
+
+        (x >= parameters.boundary_size as f32).then_some(x as usize)
+    }
+
+    /// Lowest frequency in Hz at which waves through the lattice are
+    /// reflected in phase by consecutive columns, the center of the first
+    /// band gap
+    fn bragg_frequency(&self, parameters: &Wave2dSimulationParameters) -> f32 {
+        parameters.wave_velocity / (2.0 * self.pitch() * parameters.cellsize)
+    }
+
+    /// Marks the cells of the inclusions
+    fn rasterize(
+        &self,
+        obstacles: &mut Array2<bool>,
+        parameters: &Wave2dSimulationParameters,
+    ) {
+        let pitch = self.pitch();
+        let radius = self.inclusion_radius();
+        let (left, right) = self.slab(parameters);
+
+        for ((x, y), obstacle) in obstacles.indexed_iter_mut() {
+            let (x, y) = (x as f32, y as f32);
+            if !(left..right).contains(&x) {
+                continue;
+            }
+
+            // offset from the center of the unit cell
+            let dx = (x - left).rem_euclid(pitch) - pitch / 2.0;
+            let dy = y.rem_euclid(pitch) - pitch / 2.0;
+            if dx * dx + dy * dy <= radius * radius {
+                *obstacle = true;
+            }
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Serialize, Deserialize)]
+pub struct BandGapSettings {
+    pub from: f32,
+    pub to: f32,
+    pub values: usize,
+    /// solver steps at every frequency, the second half is measured
+    pub steps: usize,
+}
+
+impl Default for BandGapSettings {
+    fn default() -> Self {
+        Self {
+            from: 0.5,
+            to: 8.0,
+            values: 30,
+            steps: 800,
+        }
+    }
+}
+
+/// Sweeps the frequency of the applied force once with the obstacles and
+/// once without them, the transmission is the ratio of the rms amplitudes
+/// along the probe column behind the lattice.
+#[derive(Default, Resource)]
+pub struct BandGapExplorer {
+    /// edited in the side panel, taken over when a sweep starts
+    pub settings: BandGapSettings,
+    /// runs with and without the obstacles
+    sweeps: Option<(ParameterSweep, ParameterSweep)>,
+    /// `[frequency, transmission in dB]` of the finished frequencies
+    transmission: Vec<[f64; 2]>,
+    bragg_frequency: f32,
+    probe_column: usize,
+    error: Option<String>,
+}
+
+impl BandGapExplorer {
+    fn start(
+        &mut self,
+        settings: BandGapSettings,
+        parameters: &Wave2dSimulationParameters,
+        obstacles: &Array2<bool>,
+        damping: &Array2<f32>,
+    ) -> Result<(), String> {
+        let lattice = parameters.lattice_builder;
+        let probe_column =
+            lattice.probe_column(parameters).ok_or_else(|| {
+                "the lattice leaves no room for the probe column".to_string()
+            })?;
+        let sweep_settings = ParameterSweepSettings {
+            parameter: SweptParameter::Frequency,
+            from: settings.from,
+            to: settings.to,
+            values: settings.values,
+            steps: settings.steps,
+            settle_steps: settings.steps / 2,
+            metric: SweepMetric::RmsAmplitude,
+            probe: SweepProbe::Column(probe_column),
+        };
+
+        let with_obstacles = ParameterSweep::new(
+            sweep_settings,
+            parameters,
+            obstacles.clone(),
+            damping.clone(),
+        )?;
+        let without_obstacles = ParameterSweep::new(
+            sweep_settings,
+            parameters,
+            Array2::from_elem(obstacles.dim(), false),
+            damping.clone(),
+        )?;
+
+        *self = Self {
+            settings: self.settings,
+            sweeps: Some((with_obstacles, without_obstacles)),
+            transmission: Vec::new(),
+            bragg_frequency: lattice.bragg_frequency(parameters),
+            probe_column,
+            error: None,
+        };
+
+        Ok(())
+    }
+
+    fn progress(&self) -> f32 {
+        self.sweeps.as_ref().map_or(1.0, |(with, without)| {
+            (with.progress() + without.progress()) / 2.0
+        })
+    }
+
+    /// Frequencies around the deepest transmission which stay below the gap
+    /// threshold
+    fn gap(&self) -> Option<(f64, f64, f64)> {
+        let (deepest, _) = self
+            .transmission
+            .iter()
+            .enumerate()
+            .min_by(|(_, a), (_, b)| a[1].total_cmp(&b[1]))?;
+        if self.transmission[deepest][1] > GAP_THRESHOLD_DB {
+            return None;
+        }
+
+        let in_gap =
+            |index: &usize| self.transmission[*index][1] <= GAP_THRESHOLD_DB;
+        let lower = (0..=deepest).rev().take_while(in_gap).last()?;
+        let upper = (deepest..self.transmission.len())
+            .take_while(in_gap)
+            .last()?;
+
+        Some((
+            self.transmission[lower][0],
+            self.transmission[upper][0],
+            self.transmission[deepest][1],
+        ))
+    }
+}
+
+pub struct PhononicCrystalPlugin;
+
+impl Plugin for PhononicCrystalPlugin {
+    fn build(&self, app: &mut App) {
+        app.insert_resource(BandGapExplorer::default())
+            .add_system_set(
+                SystemSet::on_update(Wave2dSimulationPlugin::STATE)
+                    .with_system(on_ui_events)
+                    .with_system(advance_band_gap_sweep.after(on_ui_events)),
+            )
+            .add_system_set(
+                SystemSet::on_exit(Wave2dSimulationPlugin::STATE)
+                    .with_system(stop_band_gap_sweep),
+            );
+    }
+}
+
+fn on_ui_events(
+    mut ui_events: EventReader<UiEvents>,
+    mut explorer: ResMut<BandGapExplorer>,
+    mut obstacles: ResMut<Wave2dObstacleMask>,
+    parameters: Res<Wave2dSimulationParameters>,
+    damping: Res<Wave2dDampingMap>,
+) {
+    for event in ui_events.iter() {
+        match event {
+            UiEvents::PlaceLattice(lattice) => {
+                if lattice.replace {
+                    obstacles.0.fill(false);
+                }
+                lattice.rasterize(&mut obstacles.0, &parameters);
+            }
+            UiEvents::StartBandGapSweep(settings) => {
+                if let Err(error) = explorer.start(
+                    *settings,
+                    &parameters,
+                    &obstacles.0,
+                    &damping.0,
+                ) {
+                    explorer.error = Some(error);
+                }
+            }
+            UiEvents::StopBandGapSweep => explorer.sweeps = None,
+            _ => {}
+        }
+    }
+}
+
+fn advance_band_gap_sweep(mut explorer: ResMut<BandGapExplorer>) {
+    let explorer = &mut *explorer;
+    let (with, without) = if let Some(sweeps) = &mut explorer.sweeps {
+        sweeps
+    } else {
+        return;
+    };
+
+    with.advance(BAND_GAP_STEPS_PER_FRAME);
+    without.advance(BAND_GAP_STEPS_PER_FRAME);
+    explorer.transmission = with
+        .curve
+        .iter()
+        .zip(&without.curve)
+        .map(|(with, without)| {
+            let ratio = with[1] / without[1].max(f64::EPSILON);
+            [with[0], 20.0 * ratio.max(f64::EPSILON).log10()]
+        })
+        .collect();
+
+    if with.is_done() && without.is_done() {
+        explorer.sweeps = None;
+    }
+}
+
+fn stop_band_gap_sweep(mut explorer: ResMut<BandGapExplorer>) {
+    explorer.sweeps = None;
+}
+
+pub fn show_phononic_crystal(
+    ui: &mut egui::Ui,
+    parameters: &mut Wave2dSimulationParameters,
+    explorer: &mut BandGapExplorer,
+    ui_events: &mut EventWriter<UiEvents>,
+) {
+    let lattice = &mut parameters.lattice_builder;
+
+    ui.label("phononic crystal");
+    ui.add(
+        egui::Slider::new(&mut lattice.pitch, 4.0..=40.0)
+            .text("pitch in cells"),
+    );
+    ui.add(
+        egui::Slider::new(&mut lattice.fill_factor, 0.05..=0.75)
+            .text("fill factor"),
+    )
+    .on_hover_text("fraction of every unit cell covered by the inclusion");
+    ui.add(egui::Slider::new(&mut lattice.columns, 1..=20).text("columns"));
+    ui.horizontal(|ui| {
+        if ui.button("Place lattice").clicked() {
+            ui_events.send(UiEvents::PlaceLattice(*lattice));
+        }
+        ui.checkbox(&mut lattice.replace, "replace obstacles");
+    });
+
+    let lattice = *lattice;
+    ui.label(format!(
+        "inclusion radius {:.1} cells, first Bragg gap around {:.2} Hz",
+        lattice.inclusion_radius(),
+        lattice.bragg_frequency(parameters)
+    ));
+
+    ui.label("band gap explorer");
+
+    let settings = &mut explorer.settings;
+    ui.horizontal(|ui| {
+        ui.add(egui::DragValue::new(&mut settings.from).prefix("from "));
+        ui.add(egui::DragValue::new(&mut settings.to).prefix("to "));
+        ui.add(
+            egui::DragValue::new(&mut settings.values)
+                .clamp_range(2..=200)
+                .suffix(" Hz values"),
+        );
+    });
+    ui.add(
+        egui::Slider::new(&mut settings.steps, 20..=10000)
+            .logarithmic(true)
+            .text("steps per frequency"),
+    )
+    .on_hover_text("the first half of every run is not measured");
+
+    ui.horizontal(|ui| {
+        if explorer.sweeps.is_some() {
+            if ui.button("Stop").clicked() {
+                ui_events.send(UiEvents::StopBandGapSweep);
+            }
+            ui.add(
+                egui::ProgressBar::new(explorer.progress()).show_percentage(),
+            );
+        } else if ui
+            .button("Measure transmission")
+            .on_hover_text(
+                "sweep the applied force with the current obstacles and \
+                 without them",
+            )
+            .clicked()
+        {
+            ui_events.send(UiEvents::StartBandGapSweep(explorer.settings));
+        }
+    });
+
+    if let Some(error) = &explorer.error {
+        ui.label(format!("failed to start: {}", error));
+    }
+    if explorer.transmission.is_empty() {
+        return;
+    }
+
+    ui.label(format!(
+        "transmission in dB along column {}",
+        explorer.probe_column
+    ));
+    Plot::new("band_gap_plot")
+        .height(160.0)
+        .allow_drag(false)
+        .allow_zoom(false)
+        .include_y(0.0)
+        .show(ui, |plot_ui| {
+            plot_ui.vline(
+                VLine::new(explorer.bragg_frequency as f64).name("Bragg"),
+            );
+            plot_ui.line(Line::new(PlotPoints::from(
+                explorer.transmission.clone(),
+            )));
+            plot_ui.points(
+                Points::new(PlotPoints::from(explorer.transmission.clone()))
+                    .radius(2.0),
+            );
+        });
+
+    match explorer.gap() {
+        Some((lower, upper, deepest)) => ui.label(format!(
+            "gap from {:.2} to {:.2} Hz, down to {:.1} dB",
+            lower, upper, deepest
+        )),
+        None => ui.label(format!(
+            "no frequency below {:.0} dB yet",
+            GAP_THRESHOLD_DB
+        )),
+    };
+}
@@ -0,0 +1,251 @@
+use std::collections::VecDeque;
+
+use bevy::prelude::*;
+use bevy_egui::egui;
+use bevy_egui::egui::plot::{Legend, Line, Plot, PlotPoints};
+use ndarray::Array3;
+
+use super::animation_plugin::Plot as PlotMesh;
+use super::simulation_plugin::update_wave;
+use super::tools::PlotToolEvent;
+use super::Wave2dSimulationPlugin;
+use super::{UiEvents, Wave2dSimulationParameters};
+use crate::simulation::Simulation;
+use crate::simulation_control::SimulationControl;
+use crate::spectrum::{amplitude_spectrum, peak_frequency, SpectrumTransforms};
+use crate::ui::to_color32;
+
+const PROBE_COLORS: [Color; 6] = [
+    Color::RED,
+    Color::CYAN,
+    Color::YELLOW,
+    Color::FUCHSIA,
+    Color::ORANGE,
+    Color::LIME_GREEN,
+];
+
+/// Number of solver steps kept per probe
+const PROBE_BUFFER_SIZE: usize = 600;
+
+/// Records the amplitude of a single cell of the simulation grid
+#[derive(Component)]
+pub struct Probe {
+    pub x: usize,
+    pub y: usize,
+    pub color: Color,
+    /// most recent sample first
+    pub samples: VecDeque<f32>,
+    /// `[frequency in Hz, amplitude]` of the recorded samples
+    pub spectrum: Vec<[f64; 2]>,
+}
+
+impl Probe {
+    fn new(x: usize, y: usize, color: Color) -> Self {
+        Self {
+            x,
+            y,
+            color,
+            samples: VecDeque::from(vec![0.0; PROBE_BUFFER_SIZE]),
+            spectrum: Vec::new(),
+        }
+    }
+}
+
+pub struct ProbePlugin;
+
+impl Plugin for ProbePlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<SpectrumTransforms>()
+            .add_system_set(
+                SystemSet::on_update(Wave2dSimulationPlugin::STATE)
+                    .with_system(on_plot_right_click)
+                    .with_system(update_spectra.after(update_wave))
+                    .with_system(on_ui_events),
+            )
+            .add_system_set(
+                SystemSet::on_exit(Wave2dSimulationPlugin::STATE)
+                    .with_system(cleanup),
+            );
+    }
+}
+
+fn on_plot_right_click(
+    mut commands: Commands,
+    parameters: Res<Wave2dSimulationParameters>,
+    mut tool_events: EventReader<PlotToolEvent>,
+    plots: Query<&Transform, With<PlotMesh>>,
+    probes: Query<(Entity, &Probe)>,
+) {
+    for event in tool_events.iter() {
+        let (x, y) = if let PlotToolEvent::PlaceProbe { x, y } = *event {
+            (x.round() as usize, y.round() as usize)
+        } else {
+            continue;
+        };
+
+        if !(0 < x && x < parameters.dimx && 0 < y && y < parameters.dimy) {
+            continue;
+        }
+
+        // right clicking an existing probe removes it
+        if let Some((entity, _)) = probes
+            .iter()
+            .find(|(_, probe)| probe.x == x && probe.y == y)
+        {
+            commands.entity(entity).despawn();
+            continue;
+        }
+
+        let plot_translation = if let Ok(transform) = plots.get_single() {
+            transform.translation
+        } else {
+            continue;
+        };
+
+        let color = PROBE_COLORS[probes.iter().len() % PROBE_COLORS.len()];
+
+        commands.spawn((
+            Probe::new(x, y, color),
+            SpriteBundle {
+                sprite: Sprite {
+                    color,
+                    custom_size: Some(Vec2::splat(parameters.cellsize * 2.0)),
+                    ..default()
+                },
+                transform: Transform::from_xyz(
+                    plot_translation.x + x as f32 * parameters.cellsize,
+                    plot_translation.y + y as f32 * parameters.cellsize,
+                    1.0,
+                ),
+                ..default()
+            },
+        ));
+    }
+}
+
+/// Called by the solver after every step
+pub(super) fn record_probe_samples(
+    u: &Array3<f32>,
+    probes: &mut Query<&mut Probe>,
+) {
+    for mut probe in probes.iter_mut() {
+        // probes outside a resized grid are despawned at the end of the frame
+        let amplitude = if let Some(amplitude) = u.get((0, probe.x, probe.y)) {
+            *amplitude
+        } else {
+            continue;
+        };
+
+        probe.samples.pop_back();
+        probe.samples.push_front(amplitude);
+    }
+}
+
+fn update_spectra(
+    control: Res<SimulationControl>,
+    parameters: Res<Wave2dSimulationParameters>,
+    mut transforms: ResMut<SpectrumTransforms>,
+    mut probes: Query<&mut Probe>,
+) {
+    if control.is_paused() {
+        return;
+    }
+
+    for mut probe in probes.iter_mut() {
+        let samples: Vec<f32> = probe.samples.iter().copied().collect();
+        probe.spectrum =
+            amplitude_spectrum(&mut transforms, &samples, parameters.dt);
+    }
+}
+
+fn on_ui_events(
+    mut commands: Commands,
+    mut ui_events: EventReader<UiEvents>,
+    mut probes: Query<(Entity, &mut Probe)>,
+) {
+    for event in ui_events.iter() {
+        match event {
+            UiEvents::Reset => {
+                for (_, mut probe) in probes.iter_mut() {
+                    probe.samples.iter_mut().for_each(|s| *s = 0.0);
+                }
+            }
+            UiEvents::ClearProbes | UiEvents::ResizeGrid(_) => {
+                for (entity, _) in probes.iter() {
+                    commands.entity(entity).despawn();
+                }
+            }
+            _ => {}
+        }
+    }
+}
+
+fn cleanup(mut commands: Commands, probes: Query<Entity, With<Probe>>) {
+    for probe in probes.iter() {
+        commands.entity(probe).despawn();
+    }
+}
+
+pub fn show_probes(
+    ui: &mut egui::Ui,
+    probes: &Query<&Probe>,
+    ui_events: &mut EventWriter<UiEvents>,
+) {
+    ui.horizontal(|ui| {
+        ui.label("probes (right click on the plot)");
+        if ui.button("Clear").clicked() {
+            ui_events.send(UiEvents::ClearProbes);
+        }
+    });
+
+    Plot::new("probe_plot")
+        .height(160.0)
+        .allow_drag(false)
+        .allow_zoom(false)
+        .include_y(-1.0)
+        .include_y(1.0)
+        .legend(Legend::default())
+        .show(ui, |plot_ui| {
+            for probe in probes.iter() {
+                let points: PlotPoints = probe
+                    .samples
+                    .iter()
+                    .enumerate()
+                    .map(|(i, sample)| [-(i as f64), *sample as f64])
+                    .collect();
+
+                plot_ui.line(
+                    Line::new(points)
+                        .color(to_color32(probe.color))
+                        .name(format!("({}, {})", probe.x, probe.y)),
+                );
+            }
+        });
+
+    ui.label("spectrum (Hz of simulated time)");
+
+    Plot::new("probe_spectrum_plot")
+        .height(160.0)
+        .allow_drag(false)
+        .allow_zoom(false)
+        .include_y(0.0)
+        .legend(Legend::default())
+        .show(ui, |plot_ui| {
+            for probe in probes.iter() {
+                plot_ui.line(
+                    Line::new(PlotPoints::from(probe.spectrum.clone()))
+                        .color(to_color32(probe.color))
+                        .name(format!("({}, {})", probe.x, probe.y)),
+                );
+            }
+        });
+
+    for probe in probes.iter() {
+        if let Some(frequency) = peak_frequency(&probe.spectrum) {
+            ui.colored_label(
+                to_color32(probe.color),
+                format!("({}, {}) peak: {:.2} Hz", probe.x, probe.y, frequency),
+            );
+        }
+    }
+}
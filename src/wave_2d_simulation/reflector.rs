@@ -0,0 +1,366 @@
+use bevy::prelude::*;
+use bevy_egui::egui;
+use ndarray::Array2;
+use serde::{Deserialize, Serialize};
+
+use super::animation_plugin::Plot;
+use super::envelope::Wave2dEnvelope;
+use super::simulation_plugin::force_position;
+use super::Wave2dSimulationPlugin;
+use super::{UiEvents, Wave2dObstacleMask, Wave2dSimulationParameters};
+use crate::simulation::Simulation;
+
+const FOCUS_COLOR: Color = Color::RED;
+
+/// Cells around the theoretical focus searched for the brightest cell of
+/// the envelope
+const FOCUS_SEARCH_RADIUS: f32 = 10.0;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub enum ReflectorKind {
+    /// mirror left of the applied force which opens towards it
+    Parabolic,
+    /// closed cavity with the applied force in its right focus
+    Elliptic,
+}
+
+impl ReflectorKind {
+    pub const ALL: [ReflectorKind; 2] =
+        [ReflectorKind::Parabolic, ReflectorKind::Elliptic];
+}
+
+impl From<ReflectorKind> for String {
+    fn from(value: ReflectorKind) -> Self {
+        match value {
+            ReflectorKind::Parabolic => "parabolic mirror".to_string(),
+            ReflectorKind::Elliptic => "elliptic cavity".to_string(),
+        }
+    }
+}
+
+/// Curved mirror around the applied force, rasterized into the obstacles,
+/// with the point the reflected waves converge to
+#[derive(Clone, Copy, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Reflector {
+    pub kind: ReflectorKind,
+    /// distance from the vertex to the focus in cells
+    pub focal_length: f32,
+    /// distance from the applied force to the vertex of the parabola in
+    /// cells
+    pub distance: f32,
+    /// height of the parabola in cells
+    pub aperture: f32,
+    /// distance between the foci of the ellipse in cells
+    pub focal_separation: f32,
+    /// thickness of the mirror in cells
+    pub wall: f32,
+    /// clear the obstacles before the mirror is placed
+    pub replace: bool,
+    /// show where the waves should converge on the flat plot
+    pub show_focus: bool,
+}
+
+impl Default for Reflector {
+    fn default() -> Self {
+        Self {
+            kind: ReflectorKind::Parabolic,
+            focal_length: 20.0,
+            distance: 80.0,
+            aperture: 100.0,
+            focal_separation: 80.0,
+            wall: 2.0,
+            replace: true,
+            show_focus: true,
+        }
+    }
+}
+
+impl Reflector {
+    fn source(parameters: &Wave2dSimulationParameters) -> Vec2 {
+        let (fx, fy) = force_position(parameters);
+        Vec2::new(fx as f32, fy as f32)
+    }
+
+    fn focal_length(&self) -> f32 {
+        self.focal_length.max(1.0)
+    }
+
+    fn vertex(&self, parameters: &Wave2dSimulationParameters) -> Vec2 {
+        Self::source(parameters) - Vec2::X * self.distance
+    }
+
+    /// Focus of the ellipse which the applied force doesn't sit in
+    fn far_focus(&self, parameters: &Wave2dSimulationParameters) -> Vec2 {
+        Self::source(parameters) - Vec2::X * self.focal_separation
+    }
+
+    /// Cell in which the waves of the applied force converge after the
+    /// reflection, none if they don't converge in front of the mirror
+    fn image(&self, parameters: &Wave2dSimulationParameters) -> Option<Vec2> {
+        match self.kind {
+            // the paraxial mirror equation 1/d + 1/d' = 1/f, with a plane
+            // wave from far away converging in the focus
+            ReflectorKind::Parabolic => {
+                let f = self.focal_length();
+                (self.distance > f).then(|| {
+                    let image_distance =
+                        self.distance * f / (self.distance - f);
+                    self.vertex(parameters) + Vec2::X * image_distance
+                })
+            }
+            // every path from one focus over the ellipse to the other one
+            // has the same length
+            ReflectorKind::Elliptic => Some(self.far_focus(parameters)),
+        }
+    }
+
+    /// Marks the cells of the mirror
+    fn rasterize(
+        &self,
+        obstacles: &mut Array2<bool>,
+        parameters: &Wave2dSimulationParameters,
+    ) {
+        let wall = self.wall.max(1.0);
+
+        for ((x, y), obstacle) in obstacles.indexed_iter_mut() {
+            let cell = Vec2::new(x as f32, y as f32);
+            let behind = match self.kind {
+                ReflectorKind::Parabolic => {
+                    self.behind_parabola(cell, parameters)
+                }
+                ReflectorKind::Elliptic => {
+                    self.outside_ellipse(cell, parameters)
+                }
+            };
+
+            if let Some(distance) = behind {
+                if (0.0..=wall).contains(&distance) {
+                    *obstacle = true;
+                }
+            }
+        }
+    }
+
+    /// Distance of a cell behind the parabola from its surface, none
+    /// beyond the aperture
+    fn behind_parabola(
+        &self,
+        cell: Vec2,
+        parameters: &Wave2dSimulationParameters,
+    ) -> Option<f32> {
+        let vertex = self.vertex(parameters);
+        let f = self.focal_length();
+        let off_axis = cell.y - vertex.y;
+        if off_axis.abs() > self.aperture / 2.0 {
+            return None;
+        }
+
+        let surface = vertex.x + off_axis * off_axis / (4.0 * f);
+        let slope = off_axis / (2.0 * f);
+
+        Some((surface - cell.x) / (1.0 + slope * slope).sqrt())
+    }
+
+    /// Distance of a cell outside of the ellipse from it
+    fn outside_ellipse(
+        &self,
+        cell: Vec2,
+        parameters: &Wave2dSimulationParameters,
+    ) -> Option<f32> {
+        let (near, far) =
+            (Self::source(parameters), self.far_focus(parameters));
+        let semi_major =
+            self.focal_separation.abs() / 2.0 + self.focal_length();
+        let (to_near, to_far) = (cell - near, cell - far);
+
+        // half the sum of the distances to the foci grows with the cosine
+        // of half the angle between them per cell along the normal
+        let growth = (to_near.normalize_or_zero() + to_far.normalize_or_zero())
+            .length()
+            / 2.0;
+        let excess = (to_near.length() + to_far.length()) / 2.0 - semi_major;
+
+        (growth > f32::EPSILON).then_some(excess / growth)
+    }
+
+    /// Brightest cell of the envelope around the image and its largest
+    /// amplitude
+    fn measured_focus(
+        &self,
+        envelope: &Array2<f32>,
+        parameters: &Wave2dSimulationParameters,
+    ) -> Option<(Vec2, f32)> {
+        let image = self.image(parameters)?;
+
+        envelope
+            .indexed_iter()
+            .map(|((x, y), &max)| (Vec2::new(x as f32, y as f32), max))
+            .filter(|(cell, _)| cell.distance(image) <= FOCUS_SEARCH_RADIUS)
+            .max_by(|(_, a), (_, b)| a.total_cmp(b))
+            .filter(|(_, max)| *max > 0.0)
+    }
+}
+
+#[derive(Component)]
+struct FocusMarker;
+
+pub struct ReflectorPlugin;
+
+impl Plugin for ReflectorPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_system_set(
+            SystemSet::on_update(Wave2dSimulationPlugin::STATE)
+                .with_system(on_ui_events)
+                .with_system(update_marker),
+        )
+        .add_system_set(
+            SystemSet::on_exit(Wave2dSimulationPlugin::STATE)
+                .with_system(cleanup),
+        );
+    }
+}
+
+fn on_ui_events(
+    mut ui_events: EventReader<UiEvents>,
+    mut obstacles: ResMut<Wave2dObstacleMask>,
+    parameters: Res<Wave2dSimulationParameters>,
+) {
+    for event in ui_events.iter() {
+        if let UiEvents::PlaceReflector(reflector) = event {
+            if reflector.replace {
+                obstacles.0.fill(false);
+            }
+            reflector.rasterize(&mut obstacles.0, &parameters);
+        }
+    }
+}
+
+/// Shows the theoretical focus on the flat plot
+fn update_marker(
+    mut commands: Commands,
+    parameters: Res<Wave2dSimulationParameters>,
+    plots: Query<&Transform, (With<Plot>, Without<FocusMarker>)>,
+    mut markers: Query<(Entity, &mut Transform), With<FocusMarker>>,
+) {
+    let reflector = parameters.reflector;
+    let image = reflector
+        .image(&parameters)
+        .filter(|_| reflector.show_focus);
+    let (plot_translation, image) = match (plots.get_single(), image) {
+        (Ok(transform), Some(image)) => (transform.translation, image),
+        _ => {
+            for (entity, _) in markers.iter() {
+                commands.entity(entity).despawn();
+            }
+            return;
+        }
+    };
+
+    let translation = plot_translation.truncate() + image * parameters.cellsize;
+
+    if let Ok((_, mut transform)) = markers.get_single_mut() {
+        transform.translation = translation.extend(1.0);
+    } else {
+        commands.spawn((
+            FocusMarker,
+            SpriteBundle {
+                sprite: Sprite {
+                    color: FOCUS_COLOR,
+                    custom_size: Some(Vec2::splat(parameters.cellsize * 2.0)),
+                    ..default()
+                },
+                transform: Transform::from_translation(translation.extend(1.0)),
+                ..default()
+            },
+        ));
+    }
+}
+
+fn cleanup(mut commands: Commands, markers: Query<Entity, With<FocusMarker>>) {
+    for entity in markers.iter() {
+        commands.entity(entity).despawn();
+    }
+}
+
+pub fn show_reflector(
+    ui: &mut egui::Ui,
+    parameters: &mut Wave2dSimulationParameters,
+    envelope: &Wave2dEnvelope,
+    ui_events: &mut EventWriter<UiEvents>,
+) {
+    let reflector = &mut parameters.reflector;
+
+    ui.horizontal(|ui| {
+        ui.label("mirror:");
+        for kind in ReflectorKind::ALL {
+            ui.radio_value(&mut reflector.kind, kind, String::from(kind));
+        }
+    });
+    match reflector.kind {
+        ReflectorKind::Parabolic => {
+            ui.add(
+                egui::Slider::new(&mut reflector.focal_length, 2.0..=100.0)
+                    .text("focal length in cells"),
+            );
+            ui.add(
+                egui::Slider::new(&mut reflector.distance, 2.0..=300.0)
+                    .text("distance from the force in cells"),
+            );
+            ui.add(
+                egui::Slider::new(&mut reflector.aperture, 4.0..=300.0)
+                    .text("aperture in cells"),
+            );
+        }
+        ReflectorKind::Elliptic => {
+            ui.add(
+                egui::Slider::new(&mut reflector.focal_separation, 0.0..=300.0)
+                    .text("distance of the foci in cells"),
+            );
+            ui.add(
+                egui::Slider::new(&mut reflector.focal_length, 2.0..=100.0)
+                    .text("focus to vertex in cells"),
+            );
+        }
+    }
+    ui.add(
+        egui::Slider::new(&mut reflector.wall, 1.0..=10.0)
+            .text("wall thickness in cells"),
+    );
+    ui.horizontal(|ui| {
+        if ui
+            .button("Place")
+            .on_hover_text("also clears the envelope to measure the focus")
+            .clicked()
+        {
+            ui_events.send(UiEvents::PlaceReflector(*reflector));
+            ui_events.send(UiEvents::ClearEnvelope);
+        }
+        ui.checkbox(&mut reflector.replace, "replace obstacles");
+        ui.checkbox(&mut reflector.show_focus, "show focus");
+    });
+
+    let reflector = *reflector;
+    let image = if let Some(image) = reflector.image(parameters) {
+        image
+    } else {
+        ui.label("the force is inside the focal length, no real image");
+        return;
+    };
+    ui.label(format!(
+        "theoretical focus at ({:.1}, {:.1})",
+        image.x, image.y
+    ));
+
+    match reflector.measured_focus(&envelope.0, parameters) {
+        Some((focus, max)) => ui.label(format!(
+            "brightest cell of the envelope nearby at ({}, {}), {:.1} cells \
+             off, max amplitude {:.3}",
+            focus.x,
+            focus.y,
+            focus.distance(image),
+            max
+        )),
+        None => ui.label("apply the force to measure the focus"),
+    };
+}
@@ -0,0 +1,277 @@
+use bevy::prelude::*;
+use bevy_egui::egui;
+use bevy_egui::egui::plot::{Line, Plot, PlotPoints, VLine};
+use ndarray::Array2;
+use serde::{Deserialize, Serialize};
+
+use super::sweep::{
+    ParameterSweep, ParameterSweepSettings, SweepMetric, SweepProbe,
+    SweptParameter, SWEEP_STEPS_PER_FRAME,
+};
+use super::Wave2dSimulationPlugin;
+use super::{
+    UiEvents, Wave2dDampingMap, Wave2dObstacleMask, Wave2dSimulationParameters,
+};
+use crate::simulation::Simulation;
+
+/// Peaks lower than this fraction of the highest peak are not reported as
+/// eigenfrequencies
+const PEAK_THRESHOLD: f64 = 0.1;
+
+#[derive(Clone, Copy, Debug, PartialEq, Serialize, Deserialize)]
+pub struct SweepSettings {
+    pub start_frequency: f32,
+    pub end_frequency: f32,
+    pub frequency_steps: usize,
+    /// simulated seconds at every frequency until the response is steady
+    pub settle_secs: f32,
+    /// simulated seconds at every frequency the response is averaged over
+    pub measure_secs: f32,
+}
+
+impl Default for SweepSettings {
+    fn default() -> Self {
+        Self {
+            start_frequency: 0.05,
+            end_frequency: 1.0,
+            frequency_steps: 40,
+            settle_secs: 5.0,
+            measure_secs: 5.0,
+        }
+    }
+}
+
+impl SweepSettings {
+    /// The frequency sweep of the applied force with the rms amplitude of
+    /// the whole grid as its metric, the times are rounded to solver steps
+    fn parameter_sweep(&self, dt: f32) -> ParameterSweepSettings {
+        let settle_steps = (self.settle_secs / dt).round() as usize;
+        let measure_steps = ((self.measure_secs / dt).round() as usize).max(1);
+
+        ParameterSweepSettings {
+            parameter: SweptParameter::Frequency,
+            from: self.start_frequency,
+            to: self.end_frequency,
+            values: self.frequency_steps,
+            steps: settle_steps + measure_steps,
+            settle_steps,
+            metric: SweepMetric::RmsAmplitude,
+            probe: SweepProbe::Grid,
+        }
+    }
+}
+
+/// Sweeps the frequency of the applied force next to the shown simulation
+/// and records the steady state rms amplitude of the whole grid at every
+/// frequency.
+///
+/// The peaks of the resulting resonance curve are the eigenfrequencies of
+/// the cavity formed by the boundary and the obstacles.
+#[derive(Default, Resource)]
+pub struct ResonanceAnalyzer {
+    /// edited in the side panel, taken over when a sweep starts
+    pub settings: SweepSettings,
+    sweep: Option<ParameterSweep>,
+    /// `[frequency in Hz, rms amplitude]` of the measured frequencies
+    curve: Vec<[f64; 2]>,
+    eigenfrequencies: Vec<f64>,
+    error: Option<String>,
+}
+
+impl ResonanceAnalyzer {
+    pub fn is_running(&self) -> bool {
+        self.sweep.is_some()
+    }
+
+    fn start(
+        &mut self,
+        settings: SweepSettings,
+        parameters: &Wave2dSimulationParameters,
+        obstacles: &Array2<bool>,
+        damping: &Array2<f32>,
+    ) -> Result<(), String> {
+        let sweep = ParameterSweep::new(
+            settings.parameter_sweep(parameters.dt),
+            parameters,
+            obstacles.clone(),
+            damping.clone(),
+        )?;
+
+        self.sweep = Some(sweep);
+        self.curve.clear();
+        self.eigenfrequencies.clear();
+        self.error = None;
+
+        Ok(())
+    }
+
+    fn stop(&mut self) {
+        self.sweep = None;
+    }
+
+    /// Fraction of the sweep which is measured
+    fn progress(&self) -> f32 {
+        self.sweep.as_ref().map_or(1.0, ParameterSweep::progress)
+    }
+}
+
+/// Local maxima of the curve which reach [`PEAK_THRESHOLD`] of the highest
+/// one, refined by fitting a parabola through the neighbours
+pub(super) fn find_peaks(curve: &[[f64; 2]]) -> Vec<f64> {
+    let highest = curve.iter().map(|point| point[1]).fold(0.0, f64::max);
+
+    curve
+        .windows(3)
+        .filter(|window| {
+            window[1][1] > window[0][1]
+                && window[1][1] >= window[2][1]
+                && window[1][1] >= PEAK_THRESHOLD * highest
+        })
+        .map(|window| {
+            let [left, center, right] = [window[0], window[1], window[2]];
+            let curvature = left[1] - 2.0 * center[1] + right[1];
+            let offset = if curvature < 0.0 {
+                0.5 * (left[1] - right[1]) / curvature
+            } else {
+                0.0
+            };
+
+            center[0] + offset * (right[0] - center[0])
+        })
+        .collect()
+}
+
+pub struct ResonancePlugin;
+
+impl Plugin for ResonancePlugin {
+    fn build(&self, app: &mut App) {
+        app.insert_resource(ResonanceAnalyzer::default())
+            .add_system_set(
+                SystemSet::on_update(Wave2dSimulationPlugin::STATE)
+                    .with_system(on_ui_events)
+                    .with_system(advance_resonance_sweep.after(on_ui_events)),
+            )
+            .add_system_set(
+                SystemSet::on_exit(Wave2dSimulationPlugin::STATE)
+                    .with_system(stop_sweep),
+            );
+    }
+}
+
+fn on_ui_events(
+    mut ui_events: EventReader<UiEvents>,
+    mut analyzer: ResMut<ResonanceAnalyzer>,
+    parameters: Res<Wave2dSimulationParameters>,
+    obstacles: Res<Wave2dObstacleMask>,
+    damping: Res<Wave2dDampingMap>,
+) {
+    for event in ui_events.iter() {
+        match event {
+            UiEvents::StartResonanceSweep(settings) => {
+                if let Err(error) = analyzer.start(
+                    *settings,
+                    &parameters,
+                    &obstacles.0,
+                    &damping.0,
+                ) {
+                    analyzer.error = Some(error);
+                }
+            }
+            UiEvents::StopResonanceSweep => analyzer.stop(),
+            _ => {}
+        }
+    }
+}
+
+fn advance_resonance_sweep(mut analyzer: ResMut<ResonanceAnalyzer>) {
+    let analyzer = &mut *analyzer;
+    let sweep = if let Some(sweep) = &mut analyzer.sweep {
+        sweep
+    } else {
+        return;
+    };
+
+    sweep.advance(SWEEP_STEPS_PER_FRAME);
+    analyzer.curve = sweep.curve.clone();
+
+    if sweep.is_done() {
+        analyzer.sweep = None;
+        analyzer.eigenfrequencies = find_peaks(&analyzer.curve);
+    }
+}
+
+fn stop_sweep(mut analyzer: ResMut<ResonanceAnalyzer>) {
+    analyzer.stop();
+}
+
+pub fn show_resonance(
+    ui: &mut egui::Ui,
+    analyzer: &mut ResonanceAnalyzer,
+    ui_events: &mut EventWriter<UiEvents>,
+) {
+    ui.label("resonance sweep (Hz of simulated time)");
+
+    let settings = &mut analyzer.settings;
+    ui.add(
+        egui::Slider::new(&mut settings.start_frequency, 0.01..=30.0)
+            .logarithmic(true)
+            .text("start frequency"),
+    );
+    ui.add(
+        egui::Slider::new(&mut settings.end_frequency, 0.01..=30.0)
+            .logarithmic(true)
+            .text("end frequency"),
+    );
+    ui.add(
+        egui::Slider::new(&mut settings.frequency_steps, 2..=200)
+            .text("frequencies"),
+    );
+    ui.add(
+        egui::Slider::new(&mut settings.settle_secs, 0.0..=60.0)
+            .text("settle time in s"),
+    )
+    .on_hover_text("the response is measured once it stopped changing");
+    ui.add(
+        egui::Slider::new(&mut settings.measure_secs, 0.1..=60.0)
+            .text("measure time in s"),
+    );
+
+    ui.horizontal(|ui| {
+        if analyzer.is_running() {
+            if ui.button("Stop sweep").clicked() {
+                ui_events.send(UiEvents::StopResonanceSweep);
+            }
+            ui.add(
+                egui::ProgressBar::new(analyzer.progress()).show_percentage(),
+            );
+        } else if ui.button("Start sweep").clicked() {
+            ui_events.send(UiEvents::StartResonanceSweep(analyzer.settings));
+        }
+    });
+
+    Plot::new("resonance_plot")
+        .height(160.0)
+        .allow_drag(false)
+        .allow_zoom(false)
+        .include_y(0.0)
+        .show(ui, |plot_ui| {
+            plot_ui.line(Line::new(PlotPoints::from(analyzer.curve.clone())));
+            for frequency in &analyzer.eigenfrequencies {
+                plot_ui
+                    .vline(VLine::new(*frequency).color(egui::Color32::YELLOW));
+            }
+        });
+
+    if let Some(error) = &analyzer.error {
+        ui.label(format!("failed to start: {}", error));
+    }
+
+    if !analyzer.eigenfrequencies.is_empty() {
+        let frequencies: Vec<String> = analyzer
+            .eigenfrequencies
+            .iter()
+            .map(|frequency| format!("{:.3}", frequency))
+            .collect();
+        ui.label(format!("eigenfrequencies: {} Hz", frequencies.join(", ")));
+    }
+}
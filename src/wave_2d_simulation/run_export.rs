@@ -0,0 +1,333 @@
+#[cfg(feature = "hdf5")]
+use std::path::Path;
+use std::path::PathBuf;
+#[cfg(feature = "hdf5")]
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use bevy::prelude::*;
+use bevy_egui::egui;
+#[cfg(feature = "hdf5")]
+use ndarray::{s, ArrayView2};
+
+use super::simulation_plugin::update_wave;
+use super::Wave2dSimulationPlugin;
+use super::{UiEvents, Wave2dSimulationGrid, Wave2dSimulationParameters};
+use crate::simulation::Simulation;
+#[cfg(feature = "hdf5")]
+use crate::simulation_control::SimulationClock;
+
+#[cfg(feature = "hdf5")]
+const EXPORT_DIRECTORY: &str = "exports";
+
+/// Frame rate the size estimate assumes
+const ESTIMATED_FRAME_RATE: f32 = 60.0;
+
+/// Records every `frame_interval`th frame of a run into an HDF5 file.
+///
+/// The file holds the dataset `u` of shape `(frames, dimx, dimy)`, the
+/// dataset `time` with the simulated seconds of every frame and the
+/// attribute `parameters` with the parameters of the start as JSON, e.g.
+/// `h5py.File(path)["u"][:]` reads all frames in Python. Recording needs
+/// the `hdf5` feature and the HDF5 library.
+#[derive(Resource)]
+pub struct RunExport {
+    pub frame_interval: u32,
+    #[cfg(feature = "hdf5")]
+    file: Option<Hdf5Run>,
+    path: Option<PathBuf>,
+    frames_written: usize,
+    error: Option<String>,
+}
+
+impl Default for RunExport {
+    fn default() -> Self {
+        Self {
+            frame_interval: 10,
+            #[cfg(feature = "hdf5")]
+            file: None,
+            path: None,
+            frames_written: 0,
+            error: None,
+        }
+    }
+}
+
+impl RunExport {
+    #[cfg(feature = "hdf5")]
+    pub fn is_recording(&self) -> bool {
+        self.file.is_some()
+    }
+
+    #[cfg(not(feature = "hdf5"))]
+    pub fn is_recording(&self) -> bool {
+        false
+    }
+
+    #[cfg(feature = "hdf5")]
+    fn start(
+        &mut self,
+        parameters: &Wave2dSimulationParameters,
+        dim: (usize, usize),
+    ) {
+        let path = match new_path() {
+            Ok(path) => path,
+            Err(error) => {
+                self.error = Some(error.to_string());
+                return;
+            }
+        };
+        let parameters = serde_json::to_string(parameters).unwrap_or_default();
+
+        match Hdf5Run::create(&path, dim, &parameters) {
+            Ok(file) => {
+                info!("recording run to {}", path.display());
+                self.file = Some(file);
+                self.path = Some(path);
+                self.frames_written = 0;
+                self.error = None;
+            }
+            Err(error) => {
+                error!("failed to create {}: {}", path.display(), error);
+                self.error = Some(error.to_string());
+            }
+        }
+    }
+
+    #[cfg(not(feature = "hdf5"))]
+    fn start(
+        &mut self,
+        _parameters: &Wave2dSimulationParameters,
+        _dim: (usize, usize),
+    ) {
+        self.error = Some("built without the hdf5 feature".to_string());
+    }
+
+    fn stop(&mut self) {
+        #[cfg(feature = "hdf5")]
+        if self.file.take().is_some() {
+            if let Some(path) = &self.path {
+                info!(
+                    "recorded {} frames to {}",
+                    self.frames_written,
+                    path.display()
+                );
+            }
+        }
+    }
+}
+
+/// Bytes a recorded frame of the grid takes, the amplitudes and its time
+fn frame_size(dimx: usize, dimy: usize) -> usize {
+    dimx * dimy * std::mem::size_of::<f32>() + std::mem::size_of::<f64>()
+}
+
+#[cfg(feature = "hdf5")]
+fn new_path() -> std::io::Result<PathBuf> {
+    std::fs::create_dir_all(EXPORT_DIRECTORY)?;
+
+    let timestamp = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .unwrap_or_default()
+        .as_millis();
+
+    Ok(Path::new(EXPORT_DIRECTORY)
+        .join(format!("wave_2d_run_{}.h5", timestamp)))
+}
+
+/// Open HDF5 file with datasets which grow by a frame at a time
+#[cfg(feature = "hdf5")]
+struct Hdf5Run {
+    /// kept open until the recording stops
+    _file: hdf5::File,
+    frames: hdf5::Dataset,
+    times: hdf5::Dataset,
+    dim: (usize, usize),
+    frames_since_written: u32,
+}
+
+#[cfg(feature = "hdf5")]
+impl Hdf5Run {
+    fn create(
+        path: &Path,
+        dim: (usize, usize),
+        parameters: &str,
+    ) -> hdf5::Result<Self> {
+        let file = hdf5::File::create(path)?;
+        let frames = file
+            .new_dataset::<f32>()
+            .chunk((1, dim.0, dim.1))
+            .shape((0.., dim.0, dim.1))
+            .create("u")?;
+        let times = file
+            .new_dataset::<f64>()
+            .chunk(1024)
+            .shape(0..)
+            .create("time")?;
+
+        let parameters: hdf5::types::VarLenUnicode = parameters
+            .parse()
+            .map_err(|error| format!("invalid parameters: {}", error))?;
+        file.new_attr::<hdf5::types::VarLenUnicode>()
+            .create("parameters")?
+            .write_scalar(&parameters)?;
+
+        Ok(Self {
+            _file: file,
+            frames,
+            times,
+            dim,
+            frames_since_written: 0,
+        })
+    }
+
+    fn append(
+        &mut self,
+        index: usize,
+        frame: ArrayView2<f32>,
+        time: f64,
+    ) -> hdf5::Result<()> {
+        self.frames.resize((index + 1, self.dim.0, self.dim.1))?;
+        self.frames.write_slice(frame, s![index, .., ..])?;
+        self.times.resize(index + 1)?;
+        self.times.write_slice(&[time], s![index..index + 1])?;
+
+        Ok(())
+    }
+}
+
+pub struct RunExportPlugin;
+
+impl Plugin for RunExportPlugin {
+    fn build(&self, app: &mut App) {
+        app.insert_resource(RunExport::default())
+            .add_system_set(
+                SystemSet::on_update(Wave2dSimulationPlugin::STATE)
+                    .with_system(on_ui_events)
+                    .with_system(record_frame.after(update_wave)),
+            )
+            .add_system_set(
+                SystemSet::on_exit(Wave2dSimulationPlugin::STATE)
+                    .with_system(stop_recording),
+            );
+    }
+}
+
+fn on_ui_events(
+    mut ui_events: EventReader<UiEvents>,
+    mut run_export: ResMut<RunExport>,
+    u: Res<Wave2dSimulationGrid>,
+    parameters: Res<Wave2dSimulationParameters>,
+) {
+    for event in ui_events.iter() {
+        match event {
+            UiEvents::StartRunExport => {
+                let (_, dimx, dimy) = u.0.dim();
+                run_export.start(&parameters, (dimx, dimy));
+            }
+            UiEvents::StopRunExport => run_export.stop(),
+            _ => {}
+        }
+    }
+}
+
+/// Writes every `frame_interval`th frame in which the simulation advanced
+#[cfg(feature = "hdf5")]
+fn record_frame(
+    mut run_export: ResMut<RunExport>,
+    u: Res<Wave2dSimulationGrid>,
+    clock: Res<SimulationClock>,
+    mut recorded_steps: Local<u64>,
+) {
+    let advanced =
+        std::mem::replace(&mut *recorded_steps, clock.steps()) != clock.steps();
+    if !advanced {
+        return;
+    }
+
+    let frame = u.0.slice(s![0, .., ..]);
+    let index = run_export.frames_written;
+    let interval = run_export.frame_interval;
+    let result = if let Some(file) = &mut run_export.file {
+        file.frames_since_written += 1;
+        if file.frames_since_written < interval {
+            return;
+        }
+        file.frames_since_written = 0;
+
+        if frame.dim() == file.dim {
+            file.append(index, frame, clock.elapsed_secs())
+                .map_err(|error| error.to_string())
+        } else {
+            Err("the grid was resized".to_string())
+        }
+    } else {
+        return;
+    };
+
+    match result {
+        Ok(()) => run_export.frames_written += 1,
+        Err(error) => {
+            error!("stopped recording the run: {}", error);
+            run_export.error = Some(error);
+            run_export.stop();
+        }
+    }
+}
+
+#[cfg(not(feature = "hdf5"))]
+fn record_frame() {}
+
+fn stop_recording(mut run_export: ResMut<RunExport>) {
+    run_export.stop();
+}
+
+pub fn show_run_export(
+    ui: &mut egui::Ui,
+    run_export: &mut RunExport,
+    parameters: &Wave2dSimulationParameters,
+    ui_events: &mut EventWriter<UiEvents>,
+) {
+    let frame_bytes = frame_size(parameters.dimx, parameters.dimy) as f32;
+
+    ui.horizontal(|ui| {
+        if run_export.is_recording() {
+            if ui.button("Stop HDF5 run").clicked() {
+                ui_events.send(UiEvents::StopRunExport);
+            }
+        } else if ui.button("Record HDF5 run").clicked() {
+            ui_events.send(UiEvents::StartRunExport);
+        }
+        ui.add_enabled(
+            !run_export.is_recording(),
+            egui::DragValue::new(&mut run_export.frame_interval)
+                .clamp_range(1..=600)
+                .prefix("every ")
+                .suffix(". frame"),
+        );
+    });
+
+    if run_export.is_recording() {
+        ui.label(format!(
+            "{} frames, {:.1} MB",
+            run_export.frames_written,
+            run_export.frames_written as f32 * frame_bytes / 1e6
+        ));
+    } else {
+        let per_minute = ESTIMATED_FRAME_RATE * 60.0
+            / run_export.frame_interval.max(1) as f32
+            * frame_bytes;
+        ui.label(format!(
+            "about {:.1} MB per minute at {} fps",
+            per_minute / 1e6,
+            ESTIMATED_FRAME_RATE
+        ));
+    }
+
+    if let Some(error) = &run_export.error {
+        ui.label(format!("failed to record: {}", error));
+    } else if let (false, Some(path)) =
+        (run_export.is_recording(), &run_export.path)
+    {
+        ui.label(format!("last run: {}", path.display()));
+    }
+}
@@ -0,0 +1,156 @@
+use bevy::prelude::*;
+use ndarray::Array2;
+
+use super::Wave2dSimulationPlugin;
+use super::{Wave2dDampingMap, Wave2dObstacleMask, Wave2dSimulationParameters};
+use crate::edit_history::{EditHistory, EditHistoryEvent};
+use crate::simulation::Simulation;
+
+/// Everything the side panel and the tools edit, the field itself evolves
+/// and is not part of the history
+pub struct SceneState {
+    parameters: Wave2dSimulationParameters,
+    /// the parameters as saved, to find changes without comparing fields
+    saved_parameters: serde_json::Value,
+    obstacles: Array2<bool>,
+    damping: Array2<f32>,
+}
+
+impl SceneState {
+    fn new(
+        parameters: &Wave2dSimulationParameters,
+        obstacles: &Wave2dObstacleMask,
+        damping: &Wave2dDampingMap,
+    ) -> Self {
+        Self {
+            parameters: parameters.clone(),
+            saved_parameters: serde_json::to_value(parameters)
+                .unwrap_or_default(),
+            obstacles: obstacles.0.clone(),
+            damping: damping.0.clone(),
+        }
+    }
+
+    fn matches(
+        &self,
+        parameters: &Wave2dSimulationParameters,
+        obstacles: &Wave2dObstacleMask,
+        damping: &Wave2dDampingMap,
+    ) -> bool {
+        self.obstacles == obstacles.0
+            && self.damping == damping.0
+            && serde_json::to_value(parameters).ok().as_ref()
+                == Some(&self.saved_parameters)
+    }
+
+    /// Whether the state was committed on a grid of another size
+    fn is_resized(&self, obstacles: &Wave2dObstacleMask) -> bool {
+        self.obstacles.dim() != obstacles.0.dim()
+    }
+
+    fn restore(
+        &self,
+        parameters: &mut Wave2dSimulationParameters,
+        obstacles: &mut Wave2dObstacleMask,
+        damping: &mut Wave2dDampingMap,
+    ) {
+        // the normalization keeps following the running field
+        *parameters = Wave2dSimulationParameters {
+            max_amplitude: parameters.max_amplitude,
+            max_amplitude_avg: parameters.max_amplitude_avg.clone(),
+            ..self.parameters.clone()
+        };
+        obstacles.0.clone_from(&self.obstacles);
+        damping.0.clone_from(&self.damping);
+    }
+}
+
+/// Parameter, obstacle and damping edits of the 2d simulation, a slider
+/// drag or a painted stroke is committed as one edit once the mouse button
+/// is released
+#[derive(Default, Resource)]
+pub struct Wave2dSceneHistory(pub EditHistory<SceneState>);
+
+pub struct SceneHistoryPlugin;
+
+impl Plugin for SceneHistoryPlugin {
+    fn build(&self, app: &mut App) {
+        app.insert_resource(Wave2dSceneHistory::default())
+            .add_system_set(
+                SystemSet::on_update(Wave2dSimulationPlugin::STATE)
+                    .with_system(on_history_events)
+                    .with_system(commit_edits.after(on_history_events)),
+            )
+            .add_system_set(
+                SystemSet::on_exit(Wave2dSimulationPlugin::STATE)
+                    .with_system(clear_history),
+            );
+    }
+}
+
+fn on_history_events(
+    mut history_events: EventReader<EditHistoryEvent>,
+    mut history: ResMut<Wave2dSceneHistory>,
+    mut parameters: ResMut<Wave2dSimulationParameters>,
+    mut obstacles: ResMut<Wave2dObstacleMask>,
+    mut damping: ResMut<Wave2dDampingMap>,
+) {
+    for event in history_events.iter() {
+        // an edit still in progress is undone as a whole
+        let edited = history.0.current().map_or(false, |state| {
+            !state.is_resized(&obstacles)
+                && !state.matches(&parameters, &obstacles, &damping)
+        });
+        if edited {
+            history.0.commit(SceneState::new(
+                &parameters,
+                &obstacles,
+                &damping,
+            ));
+        }
+
+        let state = match event {
+            EditHistoryEvent::Undo => history.0.undo(),
+            EditHistoryEvent::Redo => history.0.redo(),
+        };
+        if let Some(state) = state {
+            state.restore(&mut parameters, &mut obstacles, &mut damping);
+        }
+    }
+}
+
+/// Commits the state once it changed and no mouse button is held anymore
+fn commit_edits(
+    buttons: Res<Input<MouseButton>>,
+    mut history: ResMut<Wave2dSceneHistory>,
+    parameters: Res<Wave2dSimulationParameters>,
+    obstacles: Res<Wave2dObstacleMask>,
+    damping: Res<Wave2dDampingMap>,
+) {
+    if buttons.get_pressed().next().is_some() {
+        return;
+    }
+
+    let (resized, unchanged) = match history.0.current() {
+        Some(state) => (
+            state.is_resized(&obstacles),
+            state.matches(&parameters, &obstacles, &damping),
+        ),
+        None => (false, false),
+    };
+    if unchanged {
+        return;
+    }
+    // edits of another grid size can't be restored
+    if resized {
+        history.0.clear();
+    }
+
+    history
+        .0
+        .commit(SceneState::new(&parameters, &obstacles, &damping));
+}
+
+fn clear_history(mut history: ResMut<Wave2dSceneHistory>) {
+    history.0.clear();
+}
@@ -1,51 +1,120 @@
 use std::f32::consts::TAU;
+use std::time::Duration;
 
 use bevy::prelude::*;
 use bevy::time::Stopwatch;
 use ndarray::prelude::*;
-use ndarray::Zip;
 
-use crate::AppState;
+use crate::audio_file::AudioFile;
+use crate::audio_input::AudioInput;
+use crate::persistence::RestoreParameters;
+use crate::simulation::Simulation;
+use crate::simulation_control::{SimulationClock, SimulationControl};
 
-use super::animation_plugin::PlotClickedEvent;
-use super::finite_difference::update_with_laplace_operator;
+use super::finite_difference::{
+    for_each_row, update_with_laplace_operator, MAX_STABLE_CFL_NUMBER,
+    STENCIL_RADIUS,
+};
+use super::lock_in::{reference_phase, Wave2dLockIn};
+use super::moving_obstacle::MovingObstacleState;
+use super::moving_source::MovingSourceState;
+use super::parallel::{update_solver_threads, SolverThreads};
+use super::probe::{record_probe_samples, Probe};
+use super::Wave2dAbCompare;
+use super::Wave2dDampingMap;
+use super::Wave2dEnvelope;
+use super::Wave2dObstacleMask;
 use super::Wave2dSimulationGrid;
 use super::Wave2dSimulationParameters;
+use super::Wave2dSimulationPlugin;
+use super::{AudioDrive, GridSize, PlotQuantity, UiEvents};
 
-#[derive(Resource)]
-struct ApplyingForceTimer(Stopwatch);
+#[derive(Default, Resource)]
+pub(super) struct ApplyingForceTimer(pub(super) Stopwatch);
+
+/// Simulated time not yet covered by a solver step of length `dt`
+#[derive(Default, Resource)]
+struct StepAccumulator(f32);
 
 pub struct SimulationPlugin;
 
 impl Plugin for SimulationPlugin {
     fn build(&self, app: &mut App) {
         app.insert_resource(Wave2dSimulationGrid::default())
-            .insert_resource(ApplyingForceTimer(Stopwatch::new()))
+            .insert_resource(Wave2dObstacleMask::default())
+            .insert_resource(Wave2dDampingMap::default())
+            .insert_resource(ApplyingForceTimer::default())
+            .insert_resource(StepAccumulator::default())
+            .insert_resource(SolverThreads::default())
             .add_system_set(
-                SystemSet::on_enter(AppState::Wave2dSimulation)
-                    .with_system(setup),
+                SystemSet::on_enter(Wave2dSimulationPlugin::STATE)
+                    .with_system(setup.after(RestoreParameters)),
             )
             .add_system_set(
-                SystemSet::on_update(AppState::Wave2dSimulation)
-                    .with_system(apply_force)
+                SystemSet::on_update(Wave2dSimulationPlugin::STATE)
+                    .with_system(update_solver_threads.before(update_wave))
                     .with_system(update_wave)
-                    .with_system(on_mouseclick),
+                    // a replayed resize arrives with the parameters of the
+                    // resized grid
+                    .with_system(on_ui_events.before(update_wave)),
             );
     }
 }
 
 fn setup(
     mut u: ResMut<Wave2dSimulationGrid>,
+    mut obstacles: ResMut<Wave2dObstacleMask>,
+    mut damping: ResMut<Wave2dDampingMap>,
     parameters: Res<Wave2dSimulationParameters>,
 ) {
     u.0 = Array3::zeros((3, parameters.dimx, parameters.dimy));
+    obstacles.0 = Array2::from_elem((parameters.dimx, parameters.dimy), false);
+    damping.0 = Array2::zeros((parameters.dimx, parameters.dimy));
 }
 
-fn apply_force(
-    time: Res<Time>,
-    mut applying_force_timer: ResMut<ApplyingForceTimer>,
+fn on_ui_events(
+    mut ui_events: EventReader<UiEvents>,
     mut u: ResMut<Wave2dSimulationGrid>,
-    parameters: Res<Wave2dSimulationParameters>,
+    mut obstacles: ResMut<Wave2dObstacleMask>,
+    mut damping: ResMut<Wave2dDampingMap>,
+    mut parameters: ResMut<Wave2dSimulationParameters>,
+) {
+    for event in ui_events.iter() {
+        if let UiEvents::ResizeGrid(grid_size) = event {
+            resize_grid(
+                &mut u,
+                &mut obstacles,
+                &mut damping,
+                &mut parameters,
+                *grid_size,
+            );
+        }
+    }
+}
+
+fn resize_grid(
+    u: &mut Wave2dSimulationGrid,
+    obstacles: &mut Wave2dObstacleMask,
+    damping: &mut Wave2dDampingMap,
+    parameters: &mut Wave2dSimulationParameters,
+    grid_size: GridSize,
+) {
+    // the stencil needs at least one cell inside the boundary
+    let min_dim = 2 * parameters.boundary_size + 1;
+
+    parameters.dimx = grid_size.dimx.max(min_dim);
+    parameters.dimy = grid_size.dimy.max(min_dim);
+    parameters.cellsize = grid_size.cellsize;
+
+    u.0 = Array3::zeros((3, parameters.dimx, parameters.dimy));
+    obstacles.0 = Array2::from_elem((parameters.dimx, parameters.dimy), false);
+    damping.0 = Array2::zeros((parameters.dimx, parameters.dimy));
+}
+
+pub(super) fn apply_force(
+    applying_force_timer: &mut ApplyingForceTimer,
+    u: &mut Array3<f32>,
+    parameters: &Wave2dSimulationParameters,
 ) {
     if !parameters.apply_force {
         return;
@@ -56,71 +125,235 @@ fn apply_force(
         (elapsed.as_secs_f32() * parameters.applied_force_frequency_hz * TAU)
             .sin();
 
-    let init_x = 4 * parameters.dimx / 6;
-    let init_y = 4 * parameters.dimy / 6;
+    let (init_x, init_y) = force_position(parameters);
 
-    *u.0.get_mut((0, init_x, init_y)).unwrap() = amplitude;
+    *u.get_mut((0, init_x, init_y)).unwrap() = amplitude;
 
-    applying_force_timer.0.tick(time.delta());
+    applying_force_timer
+        .0
+        .tick(Duration::from_secs_f32(parameters.dt));
 }
 
-fn on_mouseclick(
-    mut u: ResMut<Wave2dSimulationGrid>,
-    parameters: Res<Wave2dSimulationParameters>,
-    mut plot_clicked_events: EventReader<PlotClickedEvent>,
-) {
-    for event in plot_clicked_events.iter() {
-        let event_x: usize = event.x.round() as usize;
-        let event_y: usize = event.y.round() as usize;
-
-        if 0 < event_x
-            && event_x < parameters.dimx
-            && 0 < event_y
-            && event_y < parameters.dimy
-        {
-            *u.0.get_mut((0, event_x, event_y)).unwrap() = 1.0;
-        }
-    }
+/// Cell the applied force drives
+pub(super) fn force_position(
+    parameters: &Wave2dSimulationParameters,
+) -> (usize, usize) {
+    (4 * parameters.dimx / 6, 4 * parameters.dimy / 6)
 }
 
-fn update_wave(
+#[allow(clippy::too_many_arguments)]
+pub(super) fn update_wave(
     time: Res<Time>,
+    control: Res<SimulationControl>,
+    mut clock: ResMut<SimulationClock>,
+    mut accumulator: ResMut<StepAccumulator>,
+    mut applying_force_timer: ResMut<ApplyingForceTimer>,
     mut u: ResMut<Wave2dSimulationGrid>,
+    mut envelope: ResMut<Wave2dEnvelope>,
+    mut lock_in: ResMut<Wave2dLockIn>,
+    obstacles: Res<Wave2dObstacleMask>,
+    damping: Res<Wave2dDampingMap>,
     parameters: Res<Wave2dSimulationParameters>,
+    solver_threads: Res<SolverThreads>,
+    (mut moving_source, mut moving_obstacle): (
+        ResMut<MovingSourceState>,
+        ResMut<MovingObstacleState>,
+    ),
+    (mut audio_input, mut audio_file, ab_compare): (
+        ResMut<AudioInput>,
+        ResMut<AudioFile>,
+        Res<Wave2dAbCompare>,
+    ),
+    mut probes: Query<&mut Probe>,
 ) {
-    if time.is_paused() {
+    // the parameters may name a size the grid isn't allocated for yet
+    let shape = (parameters.dimx, parameters.dimy);
+    if u.0.dim() != (3, shape.0, shape.1)
+        || obstacles.0.dim() != shape
+        || damping.0.dim() != shape
+    {
         return;
     }
 
-    let (u_2, mut u_1, u_0) =
-        u.0.multi_slice_mut((s![2, .., ..], s![1, .., ..], s![0, .., ..]));
+    let steps = if control.is_paused() {
+        // a single step is one solver step, not a frame
+        usize::from(control.is_stepping())
+    } else {
+        // every step advances the simulation by exactly dt, the number of
+        // steps follows real time, not the frame rate
+        accumulator.0 += control.delta_seconds(&time) * parameters.time_scale;
+
+        let steps = ((accumulator.0 / parameters.dt).floor() as usize)
+            .min(parameters.steps_per_frame);
+        accumulator.0 =
+            (accumulator.0 - steps as f32 * parameters.dt).min(parameters.dt);
+
+        steps
+    };
+    // a rerun for the A/B compare stops at the time of snapshot A
+    let steps = match ab_compare.steps_until_b(&clock, parameters.dt) {
+        Some(remaining) => steps.min(remaining),
+        None => steps,
+    };
+    clock.advance(steps, parameters.dt);
+
+    // the lock-in only runs while its phase is shown
+    let detect_phase = parameters.plot_quantity == PlotQuantity::Phase
+        && Wave2dLockIn::is_locked(&parameters);
+
+    for _ in 0..steps {
+        let reference =
+            reference_phase(&parameters, applying_force_timer.0.elapsed_secs());
+
+        // nothing drives the lossless field
+        if !parameters.lossless {
+            if parameters.audio_drive != AudioDrive::Off {
+                // a step covers this much audio
+                let secs = parameters.dt / parameters.time_scale;
+                let sample = match parameters.audio_drive {
+                    AudioDrive::Microphone => audio_input.next_sample(secs),
+                    _ => audio_file.next_sample(secs),
+                };
+                let (x, y) = force_position(&parameters);
+                u.0[(0, x, y)] = sample;
+            } else {
+                apply_force(&mut applying_force_timer, &mut u.0, &parameters);
+            }
+            moving_source.step(&mut u.0, &parameters);
+        }
+        let step_obstacles = moving_obstacle.step(&obstacles.0, &parameters);
+        solver_threads.install(|| {
+            step_wave(&mut u.0, step_obstacles, &damping.0, &parameters)
+        });
+        envelope.record(&u.0);
+        if detect_phase {
+            lock_in.record(&u.0, &parameters, reference);
+        }
+        record_probe_samples(&u.0, &mut probes);
+    }
+
+    if parameters.plot_quantity == PlotQuantity::Phase {
+        // without a single drive frequency no cell shows a phase
+        if !detect_phase {
+            lock_in.clear();
+        }
+        lock_in.update_phase(&u.0);
+    }
+}
+
+/// Advances the grid by one step, `damping` is the fraction of the
+/// amplitude every cell loses on top of the energy loss
+pub(super) fn step_wave(
+    u: &mut Array3<f32>,
+    obstacles: &Array2<bool>,
+    damping: &Array2<f32>,
+    parameters: &Wave2dSimulationParameters,
+) {
+    let (dimx, dimy) = (parameters.dimx, parameters.dimy);
+    let energy_loss = parameters.syntetic_energy_loss_fraction;
+    let kept_fraction = |damping: f32| {
+        if parameters.lossless {
+            1.0
+        } else {
+            energy_loss * (1.0 - damping)
+        }
+    };
 
-    Zip::from(u_2).and(&mut u_1).for_each(std::mem::swap);
+    let u = u.as_slice_mut().expect("the grid is in standard layout");
+    let obstacles = obstacles
+        .as_slice()
+        .expect("the obstacle mask is in standard layout");
+    let damping = damping
+        .as_slice()
+        .expect("the damping map is in standard layout");
 
-    Zip::from(u_1).and(u_0).for_each(std::mem::swap);
+    let (next, rest) = u.split_at_mut(dimx * dimy);
+    let (current, previous) = rest.split_at_mut(dimx * dimy);
 
-    let tau = get_tau(&parameters);
+    // the oldest layer is overwritten with the next time step
+    next.swap_with_slice(previous);
+    current.swap_with_slice(previous);
 
-    let new_u = update_with_laplace_operator(
-        parameters.dimx,
-        parameters.dimy,
-        tau,
-        &u.0,
+    update_with_laplace_operator(
+        dimx,
+        dimy,
+        alpha(parameters),
+        next,
+        current,
+        previous,
     );
 
-    u.0.slice_mut(s![
-        0,
-        parameters.boundary_size..(parameters.dimx - parameters.boundary_size),
-        parameters.boundary_size..(parameters.dimy - parameters.boundary_size)
-    ])
-    .assign(&new_u);
+    if parameters.nonlinear {
+        apply_nonlinearity(dimx, dimy, parameters, next, current, previous);
+    }
+
+    for_each_row(next, dimy, |c, row| {
+        let obstacles = &obstacles[c * dimy..(c + 1) * dimy];
+        let damping = &damping[c * dimy..(c + 1) * dimy];
+        for ((u, &obstacle), &damping) in
+            row.iter_mut().zip(obstacles).zip(damping)
+        {
+            *u = if obstacle {
+                0.0
+            } else {
+                *u * kept_fraction(damping)
+            };
+        }
+    });
+    for layer in [current, previous] {
+        for_each_row(layer, dimy, |c, row| {
+            let damping = &damping[c * dimy..(c + 1) * dimy];
+            for (u, &damping) in row.iter_mut().zip(damping) {
+                *u *= kept_fraction(damping);
+            }
+        });
+    }
+}
 
-    u.0.mapv_inplace(|u| u * parameters.syntetic_energy_loss_fraction);
+/// Scales the laplace term of the linear update with the squared local wave
+/// velocity `c * (1 + nonlinearity * u)`
+fn apply_nonlinearity(
+    dimx: usize,
+    dimy: usize,
+    parameters: &Wave2dSimulationParameters,
+    next: &mut [f32],
+    current: &[f32],
+    previous: &[f32],
+) {
+    let nonlinearity = parameters.nonlinearity;
+    // the local courant number is clamped like the global one
+    let max_factor = if parameters.clamp_to_stability_limit {
+        MAX_STABLE_CFL_NUMBER.powi(2) / alpha(parameters)
+    } else {
+        f32::INFINITY
+    };
+
+    // only the cells the stencil updated hold a laplace term
+    let inner = STENCIL_RADIUS..dimy - STENCIL_RADIUS;
+    for_each_row(next, dimy, |c, row| {
+        if !(STENCIL_RADIUS..dimx - STENCIL_RADIUS).contains(&c) {
+            return;
+        }
+
+        let current = &current[c * dimy..(c + 1) * dimy];
+        let previous = &previous[c * dimy..(c + 1) * dimy];
+        for r in inner.clone() {
+            let u = current[r];
+            let laplace_term = row[r] - 2.0 * u + previous[r];
+            let factor = (1.0 + nonlinearity * u).max(0.0).powi(2);
+            row[r] =
+                2.0 * u - previous[r] + laplace_term * factor.min(max_factor);
+        }
+    });
 }
 
-fn get_tau(parameters: &Wave2dSimulationParameters) -> Array2<f32> {
-    Array::from_elem(
-        (parameters.dimx, parameters.dimy),
-        parameters.wave_velocity,
-    )
+/// Squared courant number, the factor of the laplace operator in the update
+pub(super) fn alpha(parameters: &Wave2dSimulationParameters) -> f32 {
+    let cfl_number = if parameters.clamp_to_stability_limit {
+        parameters.cfl_number().min(MAX_STABLE_CFL_NUMBER)
+    } else {
+        parameters.cfl_number()
+    };
+
+    cfl_number.powi(2)
 }
@@ -0,0 +1,86 @@
+use std::time::Duration;
+
+use bevy::prelude::*;
+use ndarray::{Array2, Array3};
+use serde::{Deserialize, Serialize};
+
+use super::simulation_plugin::ApplyingForceTimer;
+use super::Wave2dSimulationPlugin;
+use super::{
+    UiEvents, Wave2dObstacleMask, Wave2dSimulationGrid,
+    Wave2dSimulationParameters,
+};
+use crate::simulation::Simulation;
+use crate::snapshot::{load_snapshot, save_snapshot};
+
+/// Everything needed to continue a simulation where it was saved
+#[derive(Serialize, Deserialize)]
+struct Wave2dSnapshot {
+    parameters: Wave2dSimulationParameters,
+    grid: Array3<f32>,
+    obstacles: Array2<bool>,
+    applying_force_elapsed_secs: f32,
+}
+
+pub struct SnapshotPlugin;
+
+impl Plugin for SnapshotPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_system_set(
+            SystemSet::on_update(Wave2dSimulationPlugin::STATE)
+                .with_system(on_ui_events),
+        );
+    }
+}
+
+fn on_ui_events(
+    mut ui_events: EventReader<UiEvents>,
+    mut u: ResMut<Wave2dSimulationGrid>,
+    mut obstacles: ResMut<Wave2dObstacleMask>,
+    mut parameters: ResMut<Wave2dSimulationParameters>,
+    mut applying_force_timer: ResMut<ApplyingForceTimer>,
+) {
+    let name = Wave2dSimulationPlugin::NAME.to_string();
+
+    for event in ui_events.iter() {
+        match event {
+            UiEvents::SaveSnapshot => {
+                let snapshot = Wave2dSnapshot {
+                    parameters: parameters.clone(),
+                    grid: u.0.clone(),
+                    obstacles: obstacles.0.clone(),
+                    applying_force_elapsed_secs: applying_force_timer
+                        .0
+                        .elapsed_secs(),
+                };
+
+                save_snapshot(&name, &snapshot);
+            }
+            UiEvents::LoadSnapshot => {
+                let snapshot: Wave2dSnapshot =
+                    if let Some(snapshot) = load_snapshot(&name) {
+                        snapshot
+                    } else {
+                        continue;
+                    };
+
+                let shape =
+                    (snapshot.parameters.dimx, snapshot.parameters.dimy);
+                if snapshot.grid.shape() != [3, shape.0, shape.1]
+                    || snapshot.obstacles.dim() != shape
+                {
+                    error!("snapshot grid doesn't match its parameters");
+                    continue;
+                }
+
+                *parameters = snapshot.parameters;
+                u.0 = snapshot.grid;
+                obstacles.0 = snapshot.obstacles;
+                applying_force_timer.0.set_elapsed(Duration::from_secs_f32(
+                    snapshot.applying_force_elapsed_secs,
+                ));
+            }
+            _ => {}
+        }
+    }
+}
@@ -0,0 +1,112 @@
+use bevy::prelude::*;
+use ndarray::{s, ArrayView2};
+use serde_json::json;
+
+use super::probe::Probe;
+use super::simulation_plugin::update_wave;
+use super::Wave2dSimulationGrid;
+use super::Wave2dSimulationPlugin;
+use crate::data_stream::DataStream;
+use crate::simulation::Simulation;
+use crate::simulation_control::SimulationClock;
+
+/// Publishes the 2d simulation on the [`DataStream`].
+///
+/// Every frame sends `{"type": "probes", "time": .., "probes": [{"x", "y",
+/// "samples"}]}` with the samples recorded since the previous message,
+/// oldest first. Every `frame_interval` frames sends `{"type": "frame",
+/// "time": .., "width", "height", "values"}` with the amplitudes averaged
+/// over blocks of `downsample` cells, row by row.
+pub struct StreamingPlugin;
+
+impl Plugin for StreamingPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_system_set(
+            SystemSet::on_update(Wave2dSimulationPlugin::STATE)
+                .with_system(publish.after(update_wave)),
+        );
+    }
+}
+
+fn publish(
+    stream: Res<DataStream>,
+    clock: Res<SimulationClock>,
+    u: Res<Wave2dSimulationGrid>,
+    probes: Query<&Probe>,
+    mut published_steps: Local<u64>,
+    mut frames: Local<u32>,
+) {
+    let steps = clock.steps();
+    // the clock restarted with a reset
+    let new_steps = steps.saturating_sub(*published_steps) as usize;
+    *published_steps = steps;
+
+    if !stream.has_clients() {
+        return;
+    }
+
+    let time = clock.elapsed_secs();
+
+    if new_steps > 0 && !probes.is_empty() {
+        let probes: Vec<_> = probes
+            .iter()
+            .map(|probe| {
+                let samples: Vec<f32> = probe
+                    .samples
+                    .iter()
+                    .take(new_steps)
+                    .rev()
+                    .copied()
+                    .collect();
+                json!({ "x": probe.x, "y": probe.y, "samples": samples })
+            })
+            .collect();
+        stream.publish(
+            &json!({ "type": "probes", "time": time, "probes": probes })
+                .to_string(),
+        );
+    }
+
+    *frames += 1;
+    if *frames < stream.frame_interval {
+        return;
+    }
+    *frames = 0;
+
+    let (width, height, values) =
+        downsample(u.0.slice(s![0, .., ..]), stream.downsample.max(1));
+    stream.publish(
+        &json!({
+            "type": "frame",
+            "time": time,
+            "width": width,
+            "height": height,
+            "values": values,
+        })
+        .to_string(),
+    );
+}
+
+/// Means of `block` by `block` cells, the blocks at the far edges may be
+/// smaller, returns the width, the height and the means row by row
+fn downsample(
+    amplitudes: ArrayView2<f32>,
+    block: usize,
+) -> (usize, usize, Vec<f32>) {
+    let (cells_x, cells_y) = amplitudes.dim();
+    let width = (cells_x + block - 1) / block;
+    let height = (cells_y + block - 1) / block;
+
+    let mut values = Vec::with_capacity(width * height);
+    for y in 0..height {
+        for x in 0..width {
+            let cells = amplitudes.slice(s![
+                x * block..((x + 1) * block).min(cells_x),
+                y * block..((y + 1) * block).min(cells_y)
+            ]);
+            values.push(cells.sum() / cells.len() as f32);
+        }
+    }
+
+    (width, height, values)
+}
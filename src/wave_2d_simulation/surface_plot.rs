@@ -0,0 +1,218 @@
+use bevy::prelude::*;
+use bevy::render::mesh::{Indices, VertexAttributeValues};
+use bevy::render::render_resource::PrimitiveTopology;
+use ndarray::{s, ArrayView2};
+
+use super::animation_plugin::{
+    shown_color, shown_field, shown_value, update_max_amplitude,
+};
+use super::PlotQuantity;
+use super::UiEvents;
+use super::Wave2dAbCompare;
+use super::Wave2dEnvelope;
+use super::Wave2dLockIn;
+use super::Wave2dSimulationGrid;
+use super::Wave2dSimulationParameters;
+use crate::colormap::LogCompressionTable;
+use crate::gltf_export::save_gltf;
+
+/// world units of one grid cell in the surface view
+const SURFACE_CELLSIZE: f32 = 0.1;
+
+#[derive(Component)]
+pub struct SurfacePlot;
+
+#[derive(Component)]
+pub struct SurfacePlotLight;
+
+pub fn initialize_surface(
+    commands: &mut Commands,
+    parameters: &Wave2dSimulationParameters,
+    meshes: &mut Assets<Mesh>,
+    materials: &mut Assets<StandardMaterial>,
+) {
+    let dimx: u32 = (parameters.dimx - 1).try_into().unwrap();
+    let dimy: u32 = (parameters.dimy - 1).try_into().unwrap();
+
+    let mut mesh = Mesh::new(PrimitiveTopology::TriangleList);
+
+    let mut v_pos: Vec<[f32; 3]> =
+        Vec::with_capacity(parameters.dimx * parameters.dimy);
+
+    for x in 0..=dimx {
+        for y in 0..=dimy {
+            // the grid lies in the xz-plane, the amplitude goes along y
+            let scaled_x = x as f32 * SURFACE_CELLSIZE;
+            let scaled_z = y as f32 * SURFACE_CELLSIZE;
+            v_pos.push([scaled_x, 0.0, scaled_z]);
+        }
+    }
+
+    let v_normal: Vec<[f32; 3]> = vec![[0.0, 1.0, 0.0]; v_pos.len()];
+    let v_color: Vec<[f32; 4]> = vec![[1.0, 1.0, 1.0, 1.0]; v_pos.len()];
+
+    mesh.insert_attribute(Mesh::ATTRIBUTE_POSITION, v_pos);
+    mesh.insert_attribute(Mesh::ATTRIBUTE_NORMAL, v_normal);
+    mesh.insert_attribute(Mesh::ATTRIBUTE_COLOR, v_color);
+
+    // indices of vertices
+    let mut indices: Vec<u32> =
+        Vec::with_capacity(parameters.dimx * parameters.dimy * 6);
+
+    for c in 0..dimx {
+        for r in 0..dimy {
+            let i = c * (dimy + 1) + r;
+
+            indices.extend_from_slice(&[i, i + 1, i + dimy + 2]);
+            indices.extend_from_slice(&[i, i + dimy + 2, i + dimy + 1]);
+        }
+    }
+
+    mesh.set_indices(Some(Indices::U32(indices)));
+
+    let dimx_shift = -(dimx as f32) * SURFACE_CELLSIZE / 2.0;
+    let dimz_shift = -(dimy as f32) * SURFACE_CELLSIZE / 2.0;
+
+    commands.spawn((
+        SurfacePlot,
+        PbrBundle {
+            mesh: meshes.add(mesh),
+            material: materials.add(StandardMaterial {
+                base_color: Color::WHITE,
+                perceptual_roughness: 0.8,
+                double_sided: true,
+                cull_mode: None,
+                ..default()
+            }),
+            transform: Transform::from_xyz(dimx_shift, 0.0, dimz_shift),
+            ..default()
+        },
+    ));
+
+    commands.spawn((
+        SurfacePlotLight,
+        DirectionalLightBundle {
+            directional_light: DirectionalLight {
+                illuminance: 10000.0,
+                ..default()
+            },
+            transform: Transform::from_translation(Vec3::new(4.0, 27.0, 9.0))
+                .looking_at(Vec3::ZERO, Vec3::Y),
+            ..default()
+        },
+    ));
+}
+
+#[allow(clippy::too_many_arguments)]
+pub fn update_surface(
+    u: Res<Wave2dSimulationGrid>,
+    envelope: Res<Wave2dEnvelope>,
+    lock_in: Res<Wave2dLockIn>,
+    ab_compare: Res<Wave2dAbCompare>,
+    mut parameters: ResMut<Wave2dSimulationParameters>,
+    mut meshes: ResMut<Assets<Mesh>>,
+    surfaces: Query<&Handle<Mesh>, With<SurfacePlot>>,
+    log_table: Local<LogCompressionTable>,
+) {
+    let mesh = if let Some(mesh) = surfaces
+        .get_single()
+        .ok()
+        .and_then(|handle| meshes.get_mut(handle))
+    {
+        mesh
+    } else {
+        return;
+    };
+
+    // the mesh is rebuilt a frame after the grid was resized
+    let field =
+        shown_field(&parameters, &u.0, &envelope, &lock_in, &ab_compare);
+    if mesh.count_vertices() != parameters.dimx * parameters.dimy
+        || field.shape() != [parameters.dimx, parameters.dimy]
+    {
+        return;
+    }
+
+    // phases and differences are shown as colors on the amplitude
+    let heights = match parameters.plot_quantity {
+        PlotQuantity::Phase | PlotQuantity::Difference => {
+            u.0.slice(s![0, .., ..])
+        }
+        _ => field,
+    };
+
+    let max_amplitude =
+        write_surface_attributes(mesh, &parameters, &log_table, heights, field);
+    update_max_amplitude(&mut parameters, max_amplitude);
+}
+
+pub fn export_surface(
+    mut ui_events: EventReader<UiEvents>,
+    meshes: Res<Assets<Mesh>>,
+    surfaces: Query<(&Handle<Mesh>, &GlobalTransform), With<SurfacePlot>>,
+) {
+    for event in ui_events.iter() {
+        if let UiEvents::ExportGltf = event {
+            if let Some((mesh, transform)) =
+                surfaces.get_single().ok().and_then(|(handle, transform)| {
+                    meshes.get(handle).map(|mesh| (mesh, transform))
+                })
+            {
+                save_gltf("wave_2d_surface", mesh, transform);
+            }
+        }
+    }
+}
+
+/// Overwrites the heights, normals and colors of the surface in place, so
+/// no vertex buffers are allocated every frame, returns the maximum height
+fn write_surface_attributes(
+    mesh: &mut Mesh,
+    parameters: &Wave2dSimulationParameters,
+    log_table: &LogCompressionTable,
+    heights: ArrayView2<f32>,
+    values: ArrayView2<f32>,
+) -> f32 {
+    let dimx = parameters.dimx;
+    let dimy = parameters.dimy;
+
+    let height =
+        |x: usize, y: usize| heights[(x, y)] * parameters.surface_height_factor;
+
+    // the vertices are ordered like the cells, row by row along x
+    if let Some(VertexAttributeValues::Float32x3(positions)) =
+        mesh.attribute_mut(Mesh::ATTRIBUTE_POSITION)
+    {
+        for (i, position) in positions.iter_mut().enumerate() {
+            position[1] = height(i / dimy, i % dimy);
+        }
+    }
+
+    if let Some(VertexAttributeValues::Float32x3(normals)) =
+        mesh.attribute_mut(Mesh::ATTRIBUTE_NORMAL)
+    {
+        for (i, normal) in normals.iter_mut().enumerate() {
+            let (x, y) = (i / dimy, i % dimy);
+
+            // central differences, one sided at the borders
+            let dh_dx = (height((x + 1).min(dimx - 1), y)
+                - height(x.saturating_sub(1), y))
+                / (2.0 * SURFACE_CELLSIZE);
+            let dh_dz = (height(x, (y + 1).min(dimy - 1))
+                - height(x, y.saturating_sub(1)))
+                / (2.0 * SURFACE_CELLSIZE);
+            *normal = Vec3::new(-dh_dx, 1.0, -dh_dz).normalize().into();
+        }
+    }
+
+    if let Some(VertexAttributeValues::Float32x4(colors)) =
+        mesh.attribute_mut(Mesh::ATTRIBUTE_COLOR)
+    {
+        for (color, &value) in colors.iter_mut().zip(values.iter()) {
+            let shown = shown_value(parameters, log_table, value);
+            *color = shown_color(parameters, shown).as_linear_rgba_f32();
+        }
+    }
+
+    heights.fold(f32::MIN, |max, &height| max.max(height))
+}
@@ -0,0 +1,470 @@
+use bevy::prelude::*;
+use bevy_egui::egui;
+use bevy_egui::egui::plot::{Line, Plot, PlotPoints, Points};
+use clap::ValueEnum;
+use ndarray::{s, Array2, Array3};
+use serde::{Deserialize, Serialize};
+
+use super::probe::Probe;
+use super::simulation_plugin::{apply_force, step_wave, ApplyingForceTimer};
+use super::Wave2dSimulationPlugin;
+use super::{
+    UiEvents, Wave2dDampingMap, Wave2dObstacleMask, Wave2dSimulationParameters,
+};
+use crate::simulation::Simulation;
+
+/// Solver steps of a sweep per rendered frame, the sweep runs on its own
+/// grid next to the shown simulation
+pub(super) const SWEEP_STEPS_PER_FRAME: usize = 40;
+
+/// Parameter a sweep varies
+#[derive(
+    Clone, Copy, Debug, PartialEq, Eq, ValueEnum, Serialize, Deserialize,
+)]
+pub enum SweptParameter {
+    Frequency,
+    WaveVelocity,
+    EnergyLoss,
+    Nonlinearity,
+}
+
+impl SweptParameter {
+    pub const ALL: [SweptParameter; 4] = [
+        SweptParameter::Frequency,
+        SweptParameter::WaveVelocity,
+        SweptParameter::EnergyLoss,
+        SweptParameter::Nonlinearity,
+    ];
+
+    fn set(&self, parameters: &mut Wave2dSimulationParameters, value: f32) {
+        match self {
+            SweptParameter::Frequency => {
+                parameters.applied_force_frequency_hz = value
+            }
+            SweptParameter::WaveVelocity => parameters.wave_velocity = value,
+            SweptParameter::EnergyLoss => {
+                parameters.syntetic_energy_loss_fraction = value
+            }
+            SweptParameter::Nonlinearity => {
+                parameters.nonlinear = true;
+                parameters.nonlinearity = value;
+            }
+        }
+    }
+}
+
+impl From<SweptParameter> for String {
+    fn from(value: SweptParameter) -> Self {
+        match value {
+            SweptParameter::Frequency => "frequency in Hz".to_string(),
+            SweptParameter::WaveVelocity => "wave velocity".to_string(),
+            SweptParameter::EnergyLoss => "energy loss fraction".to_string(),
+            SweptParameter::Nonlinearity => "nonlinearity".to_string(),
+        }
+    }
+}
+
+/// Response recorded at every value of a sweep
+#[derive(
+    Clone, Copy, Debug, PartialEq, Eq, ValueEnum, Serialize, Deserialize,
+)]
+pub enum SweepMetric {
+    MaxAmplitude,
+    RmsAmplitude,
+}
+
+impl SweepMetric {
+    pub const ALL: [SweepMetric; 2] =
+        [SweepMetric::MaxAmplitude, SweepMetric::RmsAmplitude];
+}
+
+impl From<SweepMetric> for String {
+    fn from(value: SweepMetric) -> Self {
+        match value {
+            SweepMetric::MaxAmplitude => "max amplitude".to_string(),
+            SweepMetric::RmsAmplitude => "rms amplitude".to_string(),
+        }
+    }
+}
+
+/// Cells the metric of a sweep is measured over
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub enum SweepProbe {
+    Grid,
+    Cell(usize, usize),
+    /// every cell of the column at this x, e.g. behind a structure
+    Column(usize),
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Serialize, Deserialize)]
+pub struct ParameterSweepSettings {
+    pub parameter: SweptParameter,
+    pub from: f32,
+    pub to: f32,
+    pub values: usize,
+    /// solver steps at every value, started from rest
+    pub steps: usize,
+    /// leading steps of every run which are not measured
+    pub settle_steps: usize,
+    pub metric: SweepMetric,
+    pub probe: SweepProbe,
+}
+
+impl Default for ParameterSweepSettings {
+    fn default() -> Self {
+        Self {
+            parameter: SweptParameter::Frequency,
+            from: 0.5,
+            to: 8.0,
+            values: 20,
+            steps: 600,
+            settle_steps: 300,
+            metric: SweepMetric::MaxAmplitude,
+            probe: SweepProbe::Grid,
+        }
+    }
+}
+
+impl ParameterSweepSettings {
+    fn value(&self, index: usize) -> f32 {
+        let fraction = index as f32 / (self.values - 1).max(1) as f32;
+        self.from + (self.to - self.from) * fraction
+    }
+}
+
+/// Runs the solver from rest for every value of the swept parameter, with
+/// the applied force switched on and the obstacles and damping of the
+/// start, and records the metric of every run.
+///
+/// The same runs are done stepwise next to the shown simulation and in
+/// headless mode.
+pub struct ParameterSweep {
+    settings: ParameterSweepSettings,
+    base_parameters: Wave2dSimulationParameters,
+    obstacles: Array2<bool>,
+    damping: Array2<f32>,
+    /// state of the run of the current value
+    parameters: Wave2dSimulationParameters,
+    u: Array3<f32>,
+    applying_force_timer: ApplyingForceTimer,
+    index: usize,
+    step: usize,
+    max_amplitude: f32,
+    sum_of_squares: f64,
+    samples: usize,
+    /// `[value, metric]` of the finished runs
+    pub curve: Vec<[f64; 2]>,
+}
+
+impl ParameterSweep {
+    pub fn new(
+        settings: ParameterSweepSettings,
+        parameters: &Wave2dSimulationParameters,
+        obstacles: Array2<bool>,
+        damping: Array2<f32>,
+    ) -> Result<Self, String> {
+        let dim = (parameters.dimx, parameters.dimy);
+        let outside = match settings.probe {
+            SweepProbe::Grid => false,
+            SweepProbe::Cell(x, y) => x >= dim.0 || y >= dim.1,
+            SweepProbe::Column(x) => x >= dim.0,
+        };
+        if outside {
+            return Err(format!(
+                "{:?} is outside of the {}x{} grid",
+                settings.probe, dim.0, dim.1
+            ));
+        }
+        if settings.values == 0 || settings.steps <= settings.settle_steps {
+            return Err("the sweep measures no steps".to_string());
+        }
+
+        let mut base_parameters = parameters.clone();
+        base_parameters.apply_force = true;
+        base_parameters.lossless = false;
+
+        let mut sweep = Self {
+            settings,
+            parameters: base_parameters.clone(),
+            base_parameters,
+            obstacles,
+            damping,
+            u: Array3::zeros((3, dim.0, dim.1)),
+            applying_force_timer: ApplyingForceTimer::default(),
+            index: 0,
+            step: 0,
+            max_amplitude: 0.0,
+            sum_of_squares: 0.0,
+            samples: 0,
+            curve: Vec::new(),
+        };
+        sweep.start_run();
+
+        Ok(sweep)
+    }
+
+    pub fn is_done(&self) -> bool {
+        self.index >= self.settings.values
+    }
+
+    pub fn progress(&self) -> f32 {
+        (self.index as f32 + self.step as f32 / self.settings.steps as f32)
+            / self.settings.values as f32
+    }
+
+    fn start_run(&mut self) {
+        self.parameters = self.base_parameters.clone();
+        self.settings
+            .parameter
+            .set(&mut self.parameters, self.settings.value(self.index));
+
+        self.u.fill(0.0);
+        self.applying_force_timer = ApplyingForceTimer::default();
+        self.step = 0;
+        self.max_amplitude = 0.0;
+        self.sum_of_squares = 0.0;
+        self.samples = 0;
+    }
+
+    /// Runs up to `max_steps` solver steps, moving on to the next value
+    /// whenever a run is finished
+    pub fn advance(&mut self, max_steps: usize) {
+        for _ in 0..max_steps {
+            if self.is_done() {
+                return;
+            }
+
+            apply_force(
+                &mut self.applying_force_timer,
+                &mut self.u,
+                &self.parameters,
+            );
+            step_wave(
+                &mut self.u,
+                &self.obstacles,
+                &self.damping,
+                &self.parameters,
+            );
+            self.step += 1;
+
+            if self.step > self.settings.settle_steps {
+                self.measure();
+            }
+
+            if self.step >= self.settings.steps {
+                self.finish_run();
+            }
+        }
+    }
+
+    fn measure(&mut self) {
+        let amplitudes = self.u.slice(s![0, .., ..]);
+        let cells = match self.settings.probe {
+            SweepProbe::Grid => amplitudes,
+            SweepProbe::Cell(x, y) => amplitudes.slice_move(s![x..=x, y..=y]),
+            SweepProbe::Column(x) => amplitudes.slice_move(s![x..=x, ..]),
+        };
+
+        let (max, sum_of_squares) =
+            cells.fold((0.0f32, 0.0f64), |(max, sum), &amplitude| {
+                (max.max(amplitude.abs()), sum + (amplitude as f64).powi(2))
+            });
+        self.max_amplitude = self.max_amplitude.max(max);
+        self.sum_of_squares += sum_of_squares / cells.len() as f64;
+        self.samples += 1;
+    }
+
+    fn finish_run(&mut self) {
+        let metric = match self.settings.metric {
+            SweepMetric::MaxAmplitude => self.max_amplitude as f64,
+            SweepMetric::RmsAmplitude => {
+                (self.sum_of_squares / self.samples.max(1) as f64).sqrt()
+            }
+        };
+        self.curve
+            .push([self.settings.value(self.index) as f64, metric]);
+
+        self.index += 1;
+        if !self.is_done() {
+            self.start_run();
+        }
+    }
+}
+
+/// Parameter sweeps of the side panel
+#[derive(Default, Resource)]
+pub struct ParameterSweeper {
+    /// edited in the side panel, taken over when a sweep starts
+    pub settings: ParameterSweepSettings,
+    /// measure at the first probe instead of the whole grid
+    pub at_probe: bool,
+    sweep: Option<ParameterSweep>,
+    /// curve of the last sweep, kept once it finished
+    curve: Vec<[f64; 2]>,
+    error: Option<String>,
+}
+
+pub struct SweepPlugin;
+
+impl Plugin for SweepPlugin {
+    fn build(&self, app: &mut App) {
+        app.insert_resource(ParameterSweeper::default())
+            .add_system_set(
+                SystemSet::on_update(Wave2dSimulationPlugin::STATE)
+                    .with_system(on_ui_events)
+                    .with_system(advance_sweep.after(on_ui_events)),
+            )
+            .add_system_set(
+                SystemSet::on_exit(Wave2dSimulationPlugin::STATE)
+                    .with_system(stop_sweep),
+            );
+    }
+}
+
+fn on_ui_events(
+    mut ui_events: EventReader<UiEvents>,
+    mut sweeper: ResMut<ParameterSweeper>,
+    parameters: Res<Wave2dSimulationParameters>,
+    obstacles: Res<Wave2dObstacleMask>,
+    damping: Res<Wave2dDampingMap>,
+) {
+    for event in ui_events.iter() {
+        match event {
+            UiEvents::StartParameterSweep(settings) => {
+                match ParameterSweep::new(
+                    *settings,
+                    &parameters,
+                    obstacles.0.clone(),
+                    damping.0.clone(),
+                ) {
+                    Ok(sweep) => {
+                        sweeper.sweep = Some(sweep);
+                        sweeper.error = None;
+                    }
+                    Err(error) => sweeper.error = Some(error),
+                }
+            }
+            UiEvents::StopParameterSweep => sweeper.sweep = None,
+            _ => {}
+        }
+    }
+}
+
+fn advance_sweep(mut sweeper: ResMut<ParameterSweeper>) {
+    let sweeper = &mut *sweeper;
+    let sweep = if let Some(sweep) = &mut sweeper.sweep {
+        sweep
+    } else {
+        return;
+    };
+
+    sweep.advance(SWEEP_STEPS_PER_FRAME);
+    sweeper.curve = sweep.curve.clone();
+
+    if sweep.is_done() {
+        sweeper.sweep = None;
+    }
+}
+
+fn stop_sweep(mut sweeper: ResMut<ParameterSweeper>) {
+    sweeper.sweep = None;
+}
+
+pub fn show_parameter_sweep(
+    ui: &mut egui::Ui,
+    sweeper: &mut ParameterSweeper,
+    probes: &Query<&Probe>,
+    ui_events: &mut EventWriter<UiEvents>,
+) {
+    ui.label("parameter sweep");
+
+    let settings = &mut sweeper.settings;
+    egui::ComboBox::from_label("swept parameter")
+        .selected_text(String::from(settings.parameter))
+        .show_ui(ui, |ui| {
+            for parameter in SweptParameter::ALL {
+                ui.selectable_value(
+                    &mut settings.parameter,
+                    parameter,
+                    String::from(parameter),
+                );
+            }
+        });
+    ui.horizontal(|ui| {
+        ui.add(egui::DragValue::new(&mut settings.from).prefix("from "));
+        ui.add(egui::DragValue::new(&mut settings.to).prefix("to "));
+        ui.add(
+            egui::DragValue::new(&mut settings.values)
+                .clamp_range(2..=200)
+                .suffix(" values"),
+        );
+    });
+    ui.add(
+        egui::Slider::new(&mut settings.steps, 10..=10000)
+            .logarithmic(true)
+            .text("steps per value"),
+    );
+    ui.add(
+        egui::Slider::new(&mut settings.settle_steps, 0..=settings.steps - 1)
+            .text("settle steps"),
+    )
+    .on_hover_text("the first steps of every run are not measured");
+    ui.horizontal(|ui| {
+        ui.label("metric:");
+        for metric in SweepMetric::ALL {
+            ui.radio_value(&mut settings.metric, metric, String::from(metric));
+        }
+    });
+    ui.checkbox(&mut sweeper.at_probe, "at the first probe")
+        .on_hover_text("otherwise over the whole grid");
+
+    ui.horizontal(|ui| {
+        if let Some(sweep) = &sweeper.sweep {
+            if ui.button("Stop sweep").clicked() {
+                ui_events.send(UiEvents::StopParameterSweep);
+            }
+            ui.add(egui::ProgressBar::new(sweep.progress()).show_percentage());
+        } else if ui.button("Start sweep").clicked() {
+            let probe = probes
+                .iter()
+                .next()
+                .filter(|_| sweeper.at_probe)
+                .map_or(SweepProbe::Grid, |probe| {
+                    SweepProbe::Cell(probe.x, probe.y)
+                });
+            ui_events.send(UiEvents::StartParameterSweep(
+                ParameterSweepSettings {
+                    probe,
+                    ..sweeper.settings
+                },
+            ));
+        }
+    });
+
+    if let Some(error) = &sweeper.error {
+        ui.label(format!("failed to start: {}", error));
+    }
+
+    Plot::new("parameter_sweep_plot")
+        .height(160.0)
+        .allow_drag(false)
+        .allow_zoom(false)
+        .include_y(0.0)
+        .show(ui, |plot_ui| {
+            plot_ui.line(Line::new(PlotPoints::from(sweeper.curve.clone())));
+            plot_ui.points(
+                Points::new(PlotPoints::from(sweeper.curve.clone()))
+                    .radius(2.0),
+            );
+        });
+
+    if let Some(best) =
+        sweeper.curve.iter().max_by(|a, b| a[1].total_cmp(&b[1]))
+    {
+        ui.label(format!(
+            "highest {} at {:.3}",
+            String::from(sweeper.settings.metric),
+            best[0]
+        ));
+    }
+}
@@ -0,0 +1,424 @@
+use bevy::prelude::*;
+use bevy_egui::egui;
+use ndarray::s;
+use serde::{Deserialize, Serialize};
+
+use super::animation_plugin::{screen_to_plot, Plot};
+use super::excitation::{excite, ExcitationBrush};
+use super::Wave2dSimulationPlugin;
+use super::{
+    UiEvents, Wave2dObstacleMask, Wave2dSimulationGrid,
+    Wave2dSimulationParameters,
+};
+use crate::recording::{RecordableEvent, RecordingAppExt};
+use crate::remote_control::RemotePulse;
+use crate::scripting::ScriptObstacle;
+use crate::simulation::Simulation;
+use crate::ui::PointerOverUi;
+use crate::AppCamera;
+
+/// Action of the left mouse button on the flat plot
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub enum PlotTool {
+    /// raises a gaussian bump
+    Impulse,
+    /// lowers a gaussian bump, like a suction source
+    Suction,
+    /// brings the field within the brush to rest
+    Eraser,
+    /// paints rigid obstacles
+    Obstacles,
+    /// paints absorbing patches
+    Damping,
+    /// drags the line of the amplitude profile
+    Profile,
+}
+
+impl PlotTool {
+    pub const ALL: [PlotTool; 6] = [
+        PlotTool::Impulse,
+        PlotTool::Suction,
+        PlotTool::Eraser,
+        PlotTool::Obstacles,
+        PlotTool::Damping,
+        PlotTool::Profile,
+    ];
+
+    fn hover_text(&self) -> &'static str {
+        match self {
+            PlotTool::Impulse => "click to excite the grid",
+            PlotTool::Suction => "click to excite the grid negatively",
+            PlotTool::Eraser => "drag to bring the field to rest",
+            PlotTool::Obstacles => "drag to paint rigid obstacles",
+            PlotTool::Damping => "drag to paint absorbing patches",
+            PlotTool::Profile => "drag a line across the plot",
+        }
+    }
+}
+
+impl From<PlotTool> for String {
+    fn from(value: PlotTool) -> Self {
+        match value {
+            PlotTool::Impulse => "impulse".to_string(),
+            PlotTool::Suction => "suction".to_string(),
+            PlotTool::Eraser => "eraser".to_string(),
+            PlotTool::Obstacles => "obstacles".to_string(),
+            PlotTool::Damping => "damping".to_string(),
+            PlotTool::Profile => "profile".to_string(),
+        }
+    }
+}
+
+/// A tool used on the flat plot at a position in cells
+#[derive(Clone, Copy, Debug, Serialize, Deserialize)]
+pub enum PlotToolEvent {
+    /// pulls the amplitude towards `strength` with the excitation brush
+    Excite {
+        x: f32,
+        y: f32,
+        strength: f32,
+    },
+    Erase {
+        x: f32,
+        y: f32,
+    },
+    PaintObstacle {
+        x: f32,
+        y: f32,
+    },
+    PaintDamping {
+        x: f32,
+        y: f32,
+    },
+    /// places a probe, or removes the probe at the position
+    PlaceProbe {
+        x: f32,
+        y: f32,
+    },
+}
+
+impl PlotToolEvent {
+    /// Whether the event changes the field itself
+    pub fn excites(&self) -> bool {
+        matches!(
+            self,
+            PlotToolEvent::Excite { .. }
+                | PlotToolEvent::Erase { .. }
+                | PlotToolEvent::PaintObstacle { .. }
+        )
+    }
+}
+
+impl RecordableEvent for PlotToolEvent {
+    const KIND: &'static str = "wave_2d_plot_tool";
+}
+
+pub struct ToolsPlugin;
+
+impl Plugin for ToolsPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_event::<PlotToolEvent>()
+            .add_recordable_event::<PlotToolEvent>()
+            .add_system_set(
+                SystemSet::on_update(Wave2dSimulationPlugin::STATE)
+                    .with_system(use_tools_with_mouse)
+                    .with_system(excite_remote_pulses)
+                    .with_system(place_script_obstacles)
+                    .with_system(
+                        apply_tools
+                            .after(use_tools_with_mouse)
+                            .after(excite_remote_pulses),
+                    ),
+            );
+    }
+}
+
+/// Event of the selected tool at a position in cells, the profile line is
+/// dragged by its own system
+fn tool_event(
+    parameters: &Wave2dSimulationParameters,
+    position: Vec2,
+) -> Option<PlotToolEvent> {
+    let (x, y) = (position.x, position.y);
+    let strength = parameters.excitation_brush.strength;
+
+    match parameters.tool {
+        PlotTool::Impulse => Some(PlotToolEvent::Excite { x, y, strength }),
+        PlotTool::Suction => Some(PlotToolEvent::Excite {
+            x,
+            y,
+            strength: -strength,
+        }),
+        PlotTool::Eraser => Some(PlotToolEvent::Erase { x, y }),
+        PlotTool::Obstacles => Some(PlotToolEvent::PaintObstacle { x, y }),
+        PlotTool::Damping => Some(PlotToolEvent::PaintDamping { x, y }),
+        PlotTool::Profile => None,
+    }
+}
+
+/// Whether holding the left button keeps using the tool
+fn tool_drags(parameters: &Wave2dSimulationParameters) -> bool {
+    match parameters.tool {
+        PlotTool::Impulse | PlotTool::Suction => {
+            parameters.excitation_brush.drag
+        }
+        PlotTool::Eraser | PlotTool::Obstacles | PlotTool::Damping => true,
+        PlotTool::Profile => false,
+    }
+}
+
+fn tool_radius(parameters: &Wave2dSimulationParameters) -> f32 {
+    match parameters.tool {
+        PlotTool::Damping => parameters.damping_brush.radius,
+        _ => parameters.excitation_brush.radius,
+    }
+}
+
+/// Cells within `radius` of `x` and `y`, all in cells, inside a grid of
+/// `dim` cells
+pub(super) fn cells_within(
+    x: f32,
+    y: f32,
+    radius: f32,
+    dim: (usize, usize),
+) -> impl Iterator<Item = (usize, usize)> {
+    let min_x = (x - radius).floor().max(0.0) as usize;
+    let min_y = (y - radius).floor().max(0.0) as usize;
+    let max_x = ((x + radius).ceil().max(0.0) as usize).min(dim.0);
+    let max_y = ((y + radius).ceil().max(0.0) as usize).min(dim.1);
+
+    (min_x..max_x)
+        .flat_map(move |cell_x| {
+            (min_y..max_y).map(move |cell_y| (cell_x, cell_y))
+        })
+        .filter(move |(cell_x, cell_y)| {
+            Vec2::new(*cell_x as f32 - x, *cell_y as f32 - y).length() <= radius
+        })
+}
+
+/// Turns clicks and drags on the flat plot into tool events, the right
+/// button places probes whatever tool is selected
+#[allow(clippy::too_many_arguments)]
+fn use_tools_with_mouse(
+    windows: Res<Windows>,
+    buttons: Res<Input<MouseButton>>,
+    touches: Res<Touches>,
+    pointer_over_ui: Res<PointerOverUi>,
+    parameters: Res<Wave2dSimulationParameters>,
+    cameras: Query<(&Camera, &GlobalTransform), With<AppCamera>>,
+    plots: Query<&Transform, With<Plot>>,
+    mut tool_events: EventWriter<PlotToolEvent>,
+    mut last_position: Local<Option<Vec2>>,
+) {
+    let ((camera, camera_transform), plot_transform, window) = match (
+        cameras.get_single(),
+        plots.get_single(),
+        windows.get_primary(),
+    ) {
+        (Ok(camera), Ok(transform), Some(window)) => {
+            (camera, transform, window)
+        }
+        _ => {
+            *last_position = None;
+            return;
+        }
+    };
+    let to_plot = |screen_position: Vec2| {
+        screen_to_plot(
+            window,
+            camera,
+            camera_transform,
+            plot_transform,
+            &parameters,
+            screen_position,
+        )
+    };
+
+    if buttons.just_pressed(MouseButton::Right) {
+        if let Some(cursor) = window.cursor_position() {
+            let position = to_plot(cursor);
+            tool_events.send(PlotToolEvent::PlaceProbe {
+                x: position.x,
+                y: position.y,
+            });
+        }
+    }
+
+    // a tap uses the tool like a click, touches are measured from the top
+    // of the window while the cursor is measured from the bottom, taps on
+    // the ui still reach the touches
+    let taps = touches.iter_just_pressed().filter(|_| !pointer_over_ui.0);
+    for touch in taps {
+        let position = touch.position();
+        let position =
+            to_plot(Vec2::new(position.x, window.height() - position.y));
+        if let Some(event) = tool_event(&parameters, position) {
+            tool_events.send(event);
+        }
+    }
+
+    let position = match window.cursor_position() {
+        Some(cursor) if buttons.pressed(MouseButton::Left) => to_plot(cursor),
+        _ => {
+            *last_position = None;
+            return;
+        }
+    };
+
+    let just_pressed = buttons.just_pressed(MouseButton::Left);
+    if !just_pressed && !tool_drags(&parameters) {
+        return;
+    }
+
+    // fast movements are filled in, so the path stays continuous
+    let start = if just_pressed {
+        position
+    } else {
+        last_position.unwrap_or(position)
+    };
+    let spacing = (tool_radius(&parameters) / 2.0).max(0.5);
+    let strokes = (start.distance(position) / spacing).ceil().max(1.0);
+    for i in 1..=strokes as usize {
+        let at = start.lerp(position, i as f32 / strokes);
+        if let Some(event) = tool_event(&parameters, at) {
+            tool_events.send(event);
+        }
+    }
+
+    *last_position = Some(position);
+}
+
+/// Turns remote pulses, placed in fractions of the grid size, into
+/// excitations, so they are recorded like clicks
+fn excite_remote_pulses(
+    mut pulses: EventReader<RemotePulse>,
+    parameters: Res<Wave2dSimulationParameters>,
+    mut tool_events: EventWriter<PlotToolEvent>,
+) {
+    for pulse in pulses.iter() {
+        tool_events.send(PlotToolEvent::Excite {
+            x: pulse.x.clamp(0.0, 1.0) * parameters.dimx as f32,
+            y: pulse.y.clamp(0.0, 1.0) * parameters.dimy as f32,
+            strength: pulse.strength,
+        });
+    }
+}
+
+/// Fills or clears the rectangles of scripts, placed in fractions of the
+/// grid size
+fn place_script_obstacles(
+    mut script_obstacles: EventReader<ScriptObstacle>,
+    mut obstacles: ResMut<Wave2dObstacleMask>,
+) {
+    for obstacle in script_obstacles.iter() {
+        let (dimx, dimy) = obstacles.0.dim();
+        let cell = |fraction: f32, dim: usize| {
+            (fraction.clamp(0.0, 1.0) * dim as f32).round() as usize
+        };
+        let (x0, x1) = (cell(obstacle.x0, dimx), cell(obstacle.x1, dimx));
+        let (y0, y1) = (cell(obstacle.y0, dimy), cell(obstacle.y1, dimy));
+
+        obstacles
+            .0
+            .slice_mut(s![x0.min(x1)..x0.max(x1), y0.min(y1)..y0.max(y1)])
+            .fill(obstacle.solid);
+    }
+}
+
+pub(super) fn apply_tools(
+    mut tool_events: EventReader<PlotToolEvent>,
+    mut u: ResMut<Wave2dSimulationGrid>,
+    mut obstacles: ResMut<Wave2dObstacleMask>,
+    parameters: Res<Wave2dSimulationParameters>,
+) {
+    let radius = parameters.excitation_brush.radius.max(0.5);
+
+    for event in tool_events.iter() {
+        match *event {
+            PlotToolEvent::Excite { x, y, strength } => {
+                let brush = ExcitationBrush {
+                    strength,
+                    ..parameters.excitation_brush
+                };
+                excite(&mut u.0, brush, x, y);
+            }
+            PlotToolEvent::Erase { x, y } => {
+                let (layers, dimx, dimy) = u.0.dim();
+                for (cell_x, cell_y) in cells_within(x, y, radius, (dimx, dimy))
+                {
+                    for layer in 0..layers {
+                        u.0[(layer, cell_x, cell_y)] = 0.0;
+                    }
+                }
+            }
+            PlotToolEvent::PaintObstacle { x, y } => {
+                let dim = obstacles.0.dim();
+                for cell in cells_within(x, y, radius, dim) {
+                    obstacles.0[cell] = true;
+                }
+            }
+            _ => {}
+        }
+    }
+}
+
+/// Buttons to select the tool of the left mouse button and the settings of
+/// the selected tool
+pub fn show_toolbar(
+    ui: &mut egui::Ui,
+    parameters: &mut Wave2dSimulationParameters,
+    ui_events: &mut EventWriter<UiEvents>,
+) {
+    ui.horizontal_wrapped(|ui| {
+        ui.label("tool:");
+        for tool in PlotTool::ALL {
+            ui.selectable_value(&mut parameters.tool, tool, String::from(tool))
+                .on_hover_text(tool.hover_text());
+        }
+    });
+    ui.label("right click places or removes a probe");
+
+    match parameters.tool {
+        PlotTool::Impulse | PlotTool::Suction => {
+            let brush = &mut parameters.excitation_brush;
+            ui.add(egui::Checkbox::new(
+                &mut brush.drag,
+                "keep exciting while dragging",
+            ));
+            ui.add(
+                egui::Slider::new(&mut brush.radius, 0.5..=20.0)
+                    .text("radius in cells"),
+            );
+            ui.add(
+                egui::Slider::new(&mut brush.strength, 0.0..=2.0)
+                    .step_by(0.01)
+                    .text("amplitude"),
+            );
+        }
+        PlotTool::Eraser | PlotTool::Obstacles => {
+            ui.add(
+                egui::Slider::new(
+                    &mut parameters.excitation_brush.radius,
+                    0.5..=20.0,
+                )
+                .text("radius in cells"),
+            );
+        }
+        PlotTool::Damping => {
+            let brush = &mut parameters.damping_brush;
+            ui.add(
+                egui::Slider::new(&mut brush.radius, 1.0..=40.0)
+                    .text("radius in cells"),
+            );
+            ui.add(
+                egui::Slider::new(&mut brush.strength, 0.0..=1.0)
+                    .logarithmic(true)
+                    .text("loss per step (0 erases)"),
+            );
+            if ui.button("Clear damping").clicked() {
+                ui_events.send(UiEvents::ClearDamping);
+            }
+        }
+        PlotTool::Profile => {}
+    }
+}
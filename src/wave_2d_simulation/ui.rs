@@ -1,69 +1,616 @@
+use bevy::ecs::system::SystemState;
 use bevy::prelude::*;
 use bevy_egui::egui;
+use serde::{Deserialize, Serialize};
 
-use crate::ui::UiState;
+use crate::audio_file::{show_audio_file, AudioFile, LoadAudioFile};
+use crate::audio_input::{show_audio_input, AudioInput};
+use crate::colormap::{log_expand, Colormap};
+use crate::edit_history::{show_edit_history, EditHistoryEvent};
+use crate::recording::RecordableEvent;
+use crate::simulation::Simulation;
+use crate::simulation_control::{ResetEvent, SimulationClock};
+use crate::ui::{
+    select_colormap, show_colormap_legend, show_tunables, Tunable,
+    TunableParameter, UiState,
+};
 use crate::AppState;
 
-use super::Wave2dSimulationParameters;
+use super::ab_compare::{show_ab_compare, Wave2dAbCompare};
+use super::axes::show_axes;
+use super::envelope::Wave2dEnvelope;
+use super::geometry::{show_geometry_builder, GeometryBuilder};
+use super::gradient_arrows::show_gradient_arrows;
+use super::isolines::show_isolines;
+use super::line_profile::{show_line_profile, LineProfile};
+use super::lock_in::Wave2dLockIn;
+use super::moving_obstacle::show_moving_obstacle;
+use super::moving_source::{show_moving_source, MovingSourceState};
+use super::parallel::SolverThreads;
+use super::phononic_crystal::{
+    show_phononic_crystal, BandGapExplorer, BandGapSettings, LatticeBuilder,
+};
+use super::probe::{show_probes, Probe};
+use super::reflector::{show_reflector, Reflector};
+use super::resonance::{show_resonance, ResonanceAnalyzer, SweepSettings};
+use super::run_export::{show_run_export, RunExport};
+use super::scene_history::Wave2dSceneHistory;
+use super::sweep::{
+    show_parameter_sweep, ParameterSweepSettings, ParameterSweeper,
+};
+use super::tools::show_toolbar;
+use super::{
+    AmplitudeNormalization, AudioDrive, ExportFormat, GridSize,
+    ImageImportTarget, PlotQuantity, PlotView, Wave2dSimulationParameters,
+    Wave2dSimulationPlugin, MAX_STABLE_CFL_NUMBER,
+};
 
+#[derive(Serialize, Deserialize)]
 pub enum UiEvents {
-    StartStopTime,
     Reset,
+    ClearProbes,
+    ExportFrame,
+    ImportImage(ImageImportTarget),
+    ClearObstacles,
+    PlaceGeometry(GeometryBuilder),
+    PlaceLattice(LatticeBuilder),
+    PlaceReflector(Reflector),
+    ResizeGrid(GridSize),
+    SaveSnapshot,
+    LoadSnapshot,
+    StartResonanceSweep(SweepSettings),
+    StopResonanceSweep,
+    StartParameterSweep(ParameterSweepSettings),
+    StopParameterSweep,
+    StartBandGapSweep(BandGapSettings),
+    StopBandGapSweep,
+    ClearDamping,
+    ClearProfileLine,
+    ClearEnvelope,
+    StartRunExport,
+    StopRunExport,
+    ExportGltf,
+    StoreSnapshotA,
+    RerunSnapshotB,
+    ClearAbCompare,
 }
 
-pub fn show_ui(
+impl RecordableEvent for UiEvents {
+    const KIND: &'static str = "wave_2d_ui";
+
+    fn is_recordable(&self) -> bool {
+        !matches!(
+            self,
+            UiEvents::ExportFrame
+                | UiEvents::StartRunExport
+                | UiEvents::StopRunExport
+                | UiEvents::ExportGltf
+                | UiEvents::ImportImage(_)
+                | UiEvents::SaveSnapshot
+                | UiEvents::LoadSnapshot
+        )
+    }
+}
+
+impl ResetEvent for UiEvents {
+    fn reset() -> Self {
+        UiEvents::Reset
+    }
+}
+
+impl Simulation for Wave2dSimulationPlugin {
+    const NAME: &'static str = "wave_2d";
+    type Parameters = Wave2dSimulationParameters;
+
+    #[allow(clippy::type_complexity)]
+    fn show_ui(ui: &mut egui::Ui, world: &mut World) {
+        let mut state: SystemState<(
+            Res<Wave2dSceneHistory>,
+            EventWriter<EditHistoryEvent>,
+        )> = SystemState::new(world);
+        let (history, mut history_events) = state.get_mut(world);
+
+        show_edit_history(ui, &history.0, &mut history_events);
+
+        let mut state: SystemState<(
+            ResMut<UiState>,
+            ResMut<State<AppState>>,
+            ResMut<Wave2dSimulationParameters>,
+            EventWriter<UiEvents>,
+            Query<&Probe>,
+            ResMut<ResonanceAnalyzer>,
+        )> = SystemState::new(world);
+        let (
+            mut ui_state,
+            mut app_state,
+            mut parameters,
+            ui_events,
+            probes,
+            mut resonance_analyzer,
+        ) = state.get_mut(world);
+
+        show_ui(
+            ui,
+            &mut ui_state,
+            &mut app_state,
+            &mut parameters,
+            ui_events,
+            &probes,
+            &mut resonance_analyzer,
+        );
+
+        let mut state: SystemState<(
+            ResMut<Wave2dSimulationParameters>,
+            Res<MovingSourceState>,
+            Query<&Probe>,
+            Res<Wave2dEnvelope>,
+            Res<LineProfile>,
+            ResMut<RunExport>,
+            ResMut<ParameterSweeper>,
+            ResMut<BandGapExplorer>,
+            Res<Wave2dAbCompare>,
+            Res<SimulationClock>,
+            EventWriter<UiEvents>,
+        )> = SystemState::new(world);
+        let (
+            mut parameters,
+            moving_source,
+            probes,
+            envelope,
+            line_profile,
+            mut run_export,
+            mut sweeper,
+            mut band_gap_explorer,
+            ab_compare,
+            clock,
+            mut ui_events,
+        ) = state.get_mut(world);
+
+        ui.separator();
+
+        show_moving_source(ui, &mut parameters, &moving_source, &probes);
+
+        ui.separator();
+
+        show_moving_obstacle(ui, &mut parameters);
+
+        ui.separator();
+
+        show_geometry_builder(ui, &mut parameters, &mut ui_events);
+
+        ui.separator();
+
+        show_reflector(ui, &mut parameters, &envelope, &mut ui_events);
+
+        ui.separator();
+
+        show_phononic_crystal(
+            ui,
+            &mut parameters,
+            &mut band_gap_explorer,
+            &mut ui_events,
+        );
+
+        ui.separator();
+
+        show_line_profile(ui, &parameters, &line_profile, &mut ui_events);
+
+        ui.separator();
+
+        show_run_export(ui, &mut run_export, &parameters, &mut ui_events);
+
+        ui.separator();
+
+        show_parameter_sweep(ui, &mut sweeper, &probes, &mut ui_events);
+
+        ui.separator();
+
+        show_ab_compare(ui, &ab_compare, &clock, &mut ui_events);
+
+        let mut state: SystemState<(
+            Res<Wave2dSimulationParameters>,
+            ResMut<AudioInput>,
+            ResMut<AudioFile>,
+            EventWriter<LoadAudioFile>,
+        )> = SystemState::new(world);
+        let (parameters, mut audio_input, mut audio_file, mut load_events) =
+            state.get_mut(world);
+
+        match parameters.audio_drive {
+            AudioDrive::Off => {}
+            AudioDrive::Microphone => {
+                ui.separator();
+                show_audio_input(ui, &mut audio_input);
+            }
+            AudioDrive::File => {
+                ui.separator();
+                show_audio_file(ui, &mut audio_file, &mut load_events);
+            }
+        }
+    }
+
+    fn debug_info(world: &World) -> Option<String> {
+        world
+            .get_resource::<SolverThreads>()
+            .map(SolverThreads::describe)
+    }
+}
+
+impl Tunable for Wave2dSimulationParameters {
+    fn tunables() -> Vec<TunableParameter<Self>> {
+        vec![
+            TunableParameter::<Self>::new(
+                "energy loss fraction",
+                0.8..=1.0,
+                |p| p.syntetic_energy_loss_fraction as f64,
+                |p, v| p.syntetic_energy_loss_fraction = v as f32,
+            )
+            .step_by(0.001),
+            TunableParameter::<Self>::new(
+                "wave velocity",
+                0.0..=150.0,
+                |p| p.wave_velocity as f64,
+                |p, v| p.wave_velocity = v as f32,
+            )
+            .step_by(0.1),
+            TunableParameter::<Self>::new(
+                "time step in s",
+                0.001..=0.05,
+                |p| p.dt as f64,
+                |p, v| p.dt = v as f32,
+            )
+            .logarithmic(),
+            TunableParameter::<Self>::new(
+                "frequency in Hz of applying force",
+                0.0..=100.0,
+                |p| p.applied_force_frequency_hz as f64,
+                |p, v| p.applied_force_frequency_hz = v as f32,
+            )
+            .step_by(0.01),
+            TunableParameter::<Self>::new(
+                "time scale",
+                0.1..=10.0,
+                |p| p.time_scale as f64,
+                |p, v| p.time_scale = v as f32,
+            )
+            .logarithmic(),
+            TunableParameter::<Self>::new(
+                "max solver steps per frame",
+                1.0..=50.0,
+                |p| p.steps_per_frame as f64,
+                |p, v| p.steps_per_frame = v as usize,
+            )
+            .integer(),
+        ]
+    }
+}
+
+fn show_ui(
     ui: &mut egui::Ui,
     _ui_state: &mut UiState,
     _app_state: &mut State<AppState>,
     parameters: &mut Wave2dSimulationParameters,
     mut ui_events: EventWriter<UiEvents>,
+    probes: &Query<&Probe>,
+    resonance_analyzer: &mut ResonanceAnalyzer,
 ) {
     ui.allocate_space(egui::Vec2::new(1.0, 10.0));
 
-    ui.add(
-        egui::Slider::new(
-            &mut parameters.syntetic_energy_loss_fraction,
-            0.8..=1.0,
-        )
-        .step_by(0.001)
-        .text("energy loss fraction"),
-    );
+    show_tunables(ui, parameters);
 
-    ui.add(
-        egui::Slider::new(&mut parameters.wave_velocity, 0.00..=0.4)
-            .step_by(0.001)
-            .text("wave velocity"),
-    );
+    show_stability(ui, parameters);
 
-    ui.add(
-        egui::Slider::new(
-            &mut parameters.applied_force_frequency_hz,
-            0.0..=100.0,
-        )
-        .step_by(0.01)
-        .text("frequency in Hz of applying force"),
-    );
+    show_nonlinearity(ui, parameters);
 
     ui.add(egui::Checkbox::new(
         &mut parameters.apply_force,
         "continuously apply frequency",
     ));
 
+    ui.horizontal(|ui| {
+        ui.label("audio drive:");
+        for option in AudioDrive::ALL {
+            ui.radio_value(
+                &mut parameters.audio_drive,
+                option,
+                String::from(option),
+            );
+        }
+    })
+    .response
+    .on_hover_text("audio replaces the applied frequency");
+
+    ui.separator();
+
+    show_toolbar(ui, parameters, &mut ui_events);
+
+    ui.separator();
+
+    #[cfg(not(target_arch = "wasm32"))]
+    {
+        let cores =
+            std::thread::available_parallelism().map_or(1, |cores| cores.get());
+        ui.add(
+            egui::Slider::new(&mut parameters.solver_threads, 0..=cores)
+                .text("solver threads (0: all cores)"),
+        );
+    }
+
     ui.separator();
 
+    select_colormap(ui, &mut parameters.colormap);
+
+    show_color_bar(ui, parameters);
+
+    show_axes(ui, parameters);
+
+    show_gradient_arrows(ui, &mut parameters.gradient_arrows);
+
+    show_isolines(ui, &mut parameters.isolines);
+
     ui.horizontal(|ui| {
-        if ui.button("Start/Stop time").clicked() {
-            ui_events.send(UiEvents::StartStopTime);
+        ui.label("view:");
+        ui.selectable_value(&mut parameters.plot_view, PlotView::Flat, "flat");
+        ui.selectable_value(
+            &mut parameters.plot_view,
+            PlotView::Surface,
+            "surface",
+        );
+    });
+
+    ui.horizontal(|ui| {
+        ui.label("show:");
+        for quantity in PlotQuantity::ALL {
+            ui.selectable_value(
+                &mut parameters.plot_quantity,
+                quantity,
+                String::from(quantity),
+            );
         }
-        if ui.button("Reset values").clicked() {
-            *parameters = Wave2dSimulationParameters::default();
+        if parameters.plot_quantity == PlotQuantity::Envelope
+            && ui.button("Clear").clicked()
+        {
+            ui_events.send(UiEvents::ClearEnvelope);
         }
-        if ui.button("Reset waves").clicked() {
-            ui_events.send(UiEvents::Reset);
+    });
+
+    if parameters.plot_quantity == PlotQuantity::Phase {
+        show_phase_detection(ui, parameters);
+    }
+
+    if parameters.plot_view == PlotView::Surface {
+        ui.add(
+            egui::Slider::new(
+                &mut parameters.surface_height_factor,
+                0.0..=10.0,
+            )
+            .step_by(0.1)
+            .text("surface height factor"),
+        );
+        if ui
+            .button("Export glTF")
+            .on_hover_text("the surface as mesh, e.g. to render or print it")
+            .clicked()
+        {
+            ui_events.send(UiEvents::ExportGltf);
         }
+    }
+
+    ui.separator();
+
+    if ui.button("Reset values").clicked() {
+        // the grid keeps its size until it is resized explicitly
+        *parameters = Wave2dSimulationParameters {
+            dimx: parameters.dimx,
+            dimy: parameters.dimy,
+            cellsize: parameters.cellsize,
+            ..Default::default()
+        };
+    }
+
+    ui.separator();
+
+    ui.add(egui::Checkbox::new(
+        &mut parameters.show_energy,
+        "show energy",
+    ));
+
+    ui.separator();
+
+    let grid_size = &mut parameters.requested_grid_size;
+    ui.add(
+        egui::Slider::new(&mut grid_size.dimx, 32..=1280)
+            .step_by(16.0)
+            .text("grid width"),
+    );
+    ui.add(
+        egui::Slider::new(&mut grid_size.dimy, 18..=720)
+            .step_by(9.0)
+            .text("grid height"),
+    );
+    ui.add(
+        egui::Slider::new(&mut grid_size.cellsize, 0.5..=10.0)
+            .step_by(0.1)
+            .text("cell size"),
+    );
+    if ui.button("Resize grid").clicked() {
+        ui_events.send(UiEvents::ResizeGrid(*grid_size));
+    }
+
+    ui.separator();
+
+    ui.horizontal(|ui| {
+        if ui.button("Export frame").clicked() {
+            ui_events.send(UiEvents::ExportFrame);
+        }
+        ui.selectable_value(
+            &mut parameters.export_format,
+            ExportFormat::Npy,
+            ".npy",
+        );
+        ui.selectable_value(
+            &mut parameters.export_format,
+            ExportFormat::Csv,
+            ".csv",
+        );
+        ui.selectable_value(
+            &mut parameters.export_format,
+            ExportFormat::Vti,
+            ".vti",
+        );
     });
+    ui.add(egui::Checkbox::new(
+        &mut parameters.export_history,
+        "include all 3 time slices",
+    ));
+
+    ui.horizontal(|ui| {
+        ui.label("import image as");
+        if ui.button("displacement").clicked() {
+            ui_events
+                .send(UiEvents::ImportImage(ImageImportTarget::Displacement));
+        }
+        if ui.button("obstacles").clicked() {
+            ui_events.send(UiEvents::ImportImage(ImageImportTarget::Obstacles));
+        }
+    });
+    if ui.button("Clear obstacles").clicked() {
+        ui_events.send(UiEvents::ClearObstacles);
+    }
+
+    ui.horizontal(|ui| {
+        ui.label("snapshot:");
+        if ui.button("Save").clicked() {
+            ui_events.send(UiEvents::SaveSnapshot);
+        }
+        if ui.button("Load").clicked() {
+            ui_events.send(UiEvents::LoadSnapshot);
+        }
+    });
+
+    ui.separator();
+
+    show_probes(ui, probes, &mut ui_events);
 
     ui.separator();
 
-    ui.label(format!("max amplitude: {}", parameters.max_amplitude));
+    show_resonance(ui, resonance_analyzer, &mut ui_events);
+}
+
+/// Normalization of the colors and the amplitudes at both ends of the
+/// colormap, which the plot compresses logarithmically
+fn show_color_bar(
+    ui: &mut egui::Ui,
+    parameters: &mut Wave2dSimulationParameters,
+) {
+    ui.horizontal(|ui| {
+        ui.label("color range:");
+        for option in AmplitudeNormalization::ALL {
+            ui.radio_value(
+                &mut parameters.normalization,
+                option,
+                String::from(option),
+            );
+        }
+    });
+
+    if parameters.normalization == AmplitudeNormalization::Fixed {
+        ui.add(
+            egui::Slider::new(&mut parameters.fixed_amplitude, 0.01..=10.0)
+                .logarithmic(true)
+                .text("normalized amplitude"),
+        );
+    }
+
+    let end = parameters.color_amplitude() * log_expand(1.0);
+    show_colormap_legend(
+        ui,
+        parameters.colormap,
+        &format!("{:.2}", -end),
+        &format!("{:.2}", end),
+    );
+}
+
+fn show_phase_detection(
+    ui: &mut egui::Ui,
+    parameters: &mut Wave2dSimulationParameters,
+) {
+    ui.add(
+        egui::Slider::new(&mut parameters.lock_in_periods, 1.0..=50.0)
+            .logarithmic(true)
+            .text("lock-in periods"),
+    )
+    .on_hover_text("longer averages are less noisy but react slower");
+
+    show_colormap_legend(ui, Colormap::Twilight, "lag -π", "π");
+
+    if !Wave2dLockIn::is_locked(parameters) {
+        ui.label("the phase needs a continuously applied frequency");
+    }
+}
+
+fn show_nonlinearity(
+    ui: &mut egui::Ui,
+    parameters: &mut Wave2dSimulationParameters,
+) {
+    ui.add(egui::Checkbox::new(
+        &mut parameters.nonlinear,
+        "nonlinear (velocity grows with amplitude)",
+    ));
+    if !parameters.nonlinear {
+        return;
+    }
+
+    ui.add(
+        egui::Slider::new(&mut parameters.nonlinearity, 0.0..=2.0)
+            .step_by(0.01)
+            .text("nonlinearity"),
+    );
+
+    // crests travel faster, so the local courant number is higher than the
+    // global one shown above
+    let amplitude_limit = parameters.nonlinear_amplitude_limit();
+    if amplitude_limit > 0.0 {
+        ui.label(format!(
+            "stable for amplitudes below {:.2}, clicks excite with {:.2}",
+            amplitude_limit, parameters.excitation_brush.strength
+        ));
+    } else {
+        ui.colored_label(
+            egui::Color32::RED,
+            "unstable at any amplitude, lower the time step",
+        );
+    }
+}
+
+pub(super) fn show_stability(
+    ui: &mut egui::Ui,
+    parameters: &mut Wave2dSimulationParameters,
+) {
+    let cfl_number = parameters.cfl_number();
+
+    if parameters.is_stable() {
+        ui.label(format!("CFL number: {:.3}", cfl_number));
+    } else if parameters.clamp_to_stability_limit {
+        ui.colored_label(
+            egui::Color32::YELLOW,
+            format!(
+                "CFL number: {:.3}, clamped to {:.3}",
+                cfl_number, MAX_STABLE_CFL_NUMBER
+            ),
+        );
+    } else {
+        ui.colored_label(
+            egui::Color32::RED,
+            format!(
+                "CFL number: {:.3} exceeds {:.3}, the solver is unstable",
+                cfl_number, MAX_STABLE_CFL_NUMBER
+            ),
+        );
+    }
+
+    ui.add(egui::Checkbox::new(
+        &mut parameters.clamp_to_stability_limit,
+        "clamp to stability limit",
+    ));
 }
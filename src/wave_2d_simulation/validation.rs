@@ -0,0 +1,356 @@
+use std::f64::consts::{FRAC_PI_4, TAU};
+use std::time::{Duration, Instant};
+
+use super::finite_difference::{update_with_laplace_operator, STENCIL_RADIUS};
+
+/// Courant number of all validation runs, the time step shrinks with the
+/// cells
+const CFL_NUMBER: f64 = 0.3;
+
+/// Lowest accepted convergence order, the second order leapfrog time
+/// stepping dominates the error of the eighth order stencil
+const MIN_CONVERGENCE_ORDER: f64 = 1.8;
+
+/// Simulated time of the plane wave, which travels at unit velocity through
+/// the periodic unit square
+const PLANE_WAVE_DURATION: f64 = 0.5;
+
+/// Width of the gaussian pulse in the middle of the square of side 2, the
+/// pulse is evolved until shortly before it reaches the edge
+const PULSE_WIDTH: f64 = 0.08;
+const PULSE_DURATION: f64 = 0.3;
+
+/// Errors of the solver against an analytical solution on a series of
+/// refined grids
+pub struct ConvergenceStudy {
+    pub name: &'static str,
+    /// `(cells along the domain, l2 error)` from the coarsest grid on
+    pub errors: Vec<(usize, f64)>,
+    pub elapsed: Duration,
+}
+
+impl ConvergenceStudy {
+    fn run(
+        name: &'static str,
+        resolutions: &[usize],
+        error: fn(usize) -> f64,
+    ) -> Self {
+        let start = Instant::now();
+        let errors = resolutions
+            .iter()
+            .map(|&cells| (cells, error(cells)))
+            .collect();
+
+        Self {
+            name,
+            errors,
+            elapsed: start.elapsed(),
+        }
+    }
+
+    /// Order of convergence between every pair of neighbouring resolutions
+    pub fn orders(&self) -> Vec<f64> {
+        self.errors
+            .windows(2)
+            .map(|pair| {
+                let (coarse_cells, coarse_error) = pair[0];
+                let (fine_cells, fine_error) = pair[1];
+
+                (coarse_error / fine_error).ln()
+                    / (fine_cells as f64 / coarse_cells as f64).ln()
+            })
+            .collect()
+    }
+
+    pub fn passed(&self) -> bool {
+        let orders = self.orders();
+        !orders.is_empty()
+            && orders.iter().all(|order| *order >= MIN_CONVERGENCE_ORDER)
+    }
+}
+
+pub fn plane_wave_study() -> ConvergenceStudy {
+    ConvergenceStudy::run("plane wave, periodic box", &[16, 32, 64], plane_wave)
+}
+
+pub fn circular_wave_study() -> ConvergenceStudy {
+    ConvergenceStudy::run(
+        "circular gaussian pulse",
+        &[64, 128, 256],
+        circular_wave,
+    )
+}
+
+/// Compares the solver with analytical solutions and prints the errors and
+/// convergence orders, returns whether every study converged as expected
+pub fn run_validation() -> bool {
+    let mut passed = true;
+
+    for study in [plane_wave_study(), circular_wave_study()] {
+        println!("{} ({:.2?})", study.name, study.elapsed);
+        println!("{:>8} {:>14} {:>8}", "cells", "l2 error", "order");
+
+        let orders = study.orders();
+        for (i, (cells, error)) in study.errors.iter().enumerate() {
+            let order = i
+                .checked_sub(1)
+                .map_or("-".to_string(), |i| format!("{:.2}", orders[i]));
+            println!("{:>8} {:>14.4e} {:>8}", cells, error, order);
+        }
+
+        let verdict = if study.passed() { "ok" } else { "FAILED" };
+        println!(
+            "{}: expected an order of at least {}\n",
+            verdict, MIN_CONVERGENCE_ORDER
+        );
+        passed &= study.passed();
+    }
+
+    passed
+}
+
+/// Time levels of a square grid in the row major layout of the solver
+struct Layers {
+    dim: usize,
+    next: Vec<f32>,
+    current: Vec<f32>,
+    previous: Vec<f32>,
+}
+
+impl Layers {
+    fn new(
+        dim: usize,
+        current: impl Fn(usize, usize) -> f64,
+        previous: impl Fn(usize, usize) -> f64,
+    ) -> Self {
+        let layer = |value: &dyn Fn(usize, usize) -> f64| {
+            (0..dim * dim)
+                .map(|i| value(i / dim, i % dim) as f32)
+                .collect::<Vec<f32>>()
+        };
+
+        Self {
+            dim,
+            next: vec![0.0; dim * dim],
+            current: layer(&current),
+            previous: layer(&previous),
+        }
+    }
+
+    fn step(&mut self, alpha: f32) {
+        update_with_laplace_operator(
+            self.dim,
+            self.dim,
+            alpha,
+            &mut self.next,
+            &self.current,
+            &self.previous,
+        );
+
+        std::mem::swap(&mut self.previous, &mut self.current);
+        std::mem::swap(&mut self.current, &mut self.next);
+    }
+
+    /// Copies the opposite side of the inner `cells` into the cells the
+    /// stencil reaches beyond them, which makes the grid periodic
+    fn wrap_periodic(&mut self, cells: usize) {
+        let wrap =
+            |i: usize| (i + cells - STENCIL_RADIUS) % cells + STENCIL_RADIUS;
+        let inner = STENCIL_RADIUS..STENCIL_RADIUS + cells;
+
+        for x in 0..self.dim {
+            for y in 0..self.dim {
+                if !inner.contains(&x) || !inner.contains(&y) {
+                    self.current[x * self.dim + y] =
+                        self.current[wrap(x) * self.dim + wrap(y)];
+                }
+            }
+        }
+    }
+}
+
+/// Steps needed to reach `duration` with at most the validation courant
+/// number, and the time step which reaches it exactly
+fn time_steps(duration: f64, cellsize: f64) -> (usize, f64) {
+    let steps = (duration / (CFL_NUMBER * cellsize)).ceil() as usize;
+    (steps, duration / steps as f64)
+}
+
+fn l2_error(samples: impl Iterator<Item = (f32, f64)>) -> f64 {
+    let (sum_of_squares, count) =
+        samples.fold((0.0, 0), |(sum, count), (numerical, exact)| {
+            (sum + (numerical as f64 - exact).powi(2), count + 1)
+        });
+
+    (sum_of_squares / count.max(1) as f64).sqrt()
+}
+
+/// L2 error of a diagonal plane wave with one wavelength along both axes of
+/// the periodic unit square, which is split into `cells` cells per axis
+fn plane_wave(cells: usize) -> f64 {
+    let cellsize = 1.0 / cells as f64;
+    let dim = cells + 2 * STENCIL_RADIUS;
+    let (steps, dt) = time_steps(PLANE_WAVE_DURATION, cellsize);
+
+    let wavenumber = [TAU, TAU];
+    let angular_frequency = wavenumber[0].hypot(wavenumber[1]);
+    let exact = |x: usize, y: usize, t: f64| {
+        let position = [
+            (x as f64 - STENCIL_RADIUS as f64) * cellsize,
+            (y as f64 - STENCIL_RADIUS as f64) * cellsize,
+        ];
+        (wavenumber[0] * position[0] + wavenumber[1] * position[1]
+            - angular_frequency * t)
+            .sin()
+    };
+
+    let mut layers =
+        Layers::new(dim, |x, y| exact(x, y, 0.0), |x, y| exact(x, y, -dt));
+    let alpha = (dt / cellsize).powi(2) as f32;
+    for _ in 0..steps {
+        layers.wrap_periodic(cells);
+        layers.step(alpha);
+    }
+
+    let inner = STENCIL_RADIUS..STENCIL_RADIUS + cells;
+    l2_error(inner.clone().flat_map(|x| {
+        let layers = &layers;
+        inner.clone().map(move |y| {
+            (
+                layers.current[x * dim + y],
+                exact(x, y, PLANE_WAVE_DURATION),
+            )
+        })
+    }))
+}
+
+/// L2 error along the center row of a gaussian pulse which starts at rest
+/// in the middle of a square of side 2, split into `cells` cells per axis
+fn circular_wave(cells: usize) -> f64 {
+    let cellsize = 2.0 / cells as f64;
+    let dim = cells + 1;
+    let center = cells / 2;
+    let (steps, dt) = time_steps(PULSE_DURATION, cellsize);
+
+    let radius = |x: usize, y: usize| {
+        (x as f64 - center as f64).hypot(y as f64 - center as f64) * cellsize
+    };
+    let sigma_squared = PULSE_WIDTH.powi(2);
+    let gaussian = |r: f64| (-r * r / (2.0 * sigma_squared)).exp();
+
+    // second order taylor expansion backwards in time, the laplacian of the
+    // gaussian is known and its velocity is zero
+    let previous = |x: usize, y: usize| {
+        let r = radius(x, y);
+        let laplacian =
+            gaussian(r) * (r * r / sigma_squared - 2.0) / sigma_squared;
+        gaussian(r) + 0.5 * dt * dt * laplacian
+    };
+
+    let mut layers = Layers::new(dim, |x, y| gaussian(radius(x, y)), previous);
+    let alpha = (dt / cellsize).powi(2) as f32;
+    for _ in 0..steps {
+        layers.step(alpha);
+    }
+
+    l2_error((STENCIL_RADIUS..dim - STENCIL_RADIUS).map(|x| {
+        (
+            layers.current[x * dim + center],
+            gaussian_pulse(radius(x, center), PULSE_DURATION),
+        )
+    }))
+}
+
+/// Exact solution of a gaussian pulse of [`PULSE_WIDTH`] which starts at
+/// rest, the green's function of the wave equation smoothed by the gaussian.
+///
+/// Every wavenumber of the hankel transform of the gaussian oscillates with
+/// `cos(k t)` at unit wave velocity.
+fn gaussian_pulse(r: f64, t: f64) -> f64 {
+    let samples = 4000;
+    let max_wavenumber = 10.0 / PULSE_WIDTH;
+    let dk = max_wavenumber / samples as f64;
+    let sigma_squared = PULSE_WIDTH.powi(2);
+
+    // the integrand vanishes at both ends, so the trapezoidal rule is a sum
+    (1..samples)
+        .map(|i| {
+            let k = i as f64 * dk;
+            k * sigma_squared
+                * (-k * k * sigma_squared / 2.0).exp()
+                * (k * t).cos()
+                * bessel_j0(k * r)
+        })
+        .sum::<f64>()
+        * dk
+}
+
+/// Bessel function of the first kind of order zero, polynomial
+/// approximations 9.4.1 and 9.4.3 of Abramowitz and Stegun with an absolute
+/// error below 1e-7
+fn bessel_j0(x: f64) -> f64 {
+    let x = x.abs();
+
+    if x <= 3.0 {
+        let y = (x / 3.0).powi(2);
+        return 1.0
+            + y * (-2.2499997
+                + y * (1.2656208
+                    + y * (-0.3163866
+                        + y * (0.0444479 + y * (-0.0039444 + y * 0.00021)))));
+    }
+
+    let y = 3.0 / x;
+    let amplitude = 0.79788456
+        + y * (-0.00000077
+            + y * (-0.0055274
+                + y * (-0.00009512
+                    + y * (0.00137237 + y * (-0.00072805 + y * 0.00014476)))));
+    let phase = x - FRAC_PI_4
+        + y * (-0.04166397
+            + y * (-0.00003954
+                + y * (0.00262573
+                    + y * (-0.00054125 + y * (-0.00029333 + y * 0.00013558)))));
+
+    amplitude * phase.cos() / x.sqrt()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn bessel_j0_matches_tabulated_values() {
+        for (x, expected) in [
+            (0.0, 1.0),
+            (1.0, 0.7651976866),
+            (2.404825557695773, 0.0),
+            (5.0, -0.1775967713),
+            (20.0, 0.1670246643),
+        ] {
+            assert!((bessel_j0(x) - expected).abs() < 1e-6, "J0({})", x);
+        }
+    }
+
+    #[test]
+    fn gaussian_pulse_starts_as_gaussian() {
+        for r in [0.0, 0.05, 0.1, 0.2] {
+            let expected = (-r * r / (2.0 * PULSE_WIDTH.powi(2))).exp();
+            assert!((gaussian_pulse(r, 0.0) - expected).abs() < 1e-5);
+        }
+    }
+
+    #[test]
+    fn plane_wave_converges_with_second_order() {
+        let study = plane_wave_study();
+        assert!(study.passed(), "orders: {:?}", study.orders());
+        assert!(study.errors.last().unwrap().1 < 1e-2);
+    }
+
+    #[test]
+    fn circular_wave_converges_with_second_order() {
+        let study = circular_wave_study();
+        assert!(study.passed(), "orders: {:?}", study.orders());
+        assert!(study.errors.last().unwrap().1 < 1e-2);
+    }
+}
@@ -0,0 +1,376 @@
+use bevy::prelude::*;
+use bevy_egui::{egui, EguiContext};
+
+use super::driver::{Driver, Waveform};
+use super::{Particle, RestPosition, UiEvents, WaveInPanelParameters};
+use crate::colormap::{build_palette, Colormap};
+use crate::simulation_control::SimulationControl;
+
+const PALETTE_SIZE: usize = 32;
+
+/// Time the panel gets to settle after the frequency changed before the
+/// vibrations are averaged
+const SETTLE_SECS: f32 = 1.0;
+
+/// Time every frequency of a sweep is driven for
+const SWEEP_DWELL_SECS: f32 = 4.0;
+
+/// Marks the particle driven in the center of the panel
+#[derive(Component)]
+pub struct ChladniDriver;
+
+/// Time integrated squared displacement of a particle along z
+#[derive(Component, Default)]
+pub struct Vibration {
+    squared_displacement: f32,
+    duration: f32,
+}
+
+impl Vibration {
+    fn rms(&self) -> f32 {
+        if self.duration > 0.0 {
+            (self.squared_displacement / self.duration).sqrt()
+        } else {
+            0.0
+        }
+    }
+}
+
+/// Drives the center of the panel at a single frequency and shows the root
+/// mean square displacement of every particle, the particles which hardly
+/// move form the nodal lines
+#[derive(Default, Resource)]
+pub struct Chladni {
+    active: bool,
+    /// frequency the vibrations are averaged for
+    frequency: f32,
+    /// time driven at the current frequency
+    dwell_secs: f32,
+    /// frequency of the current step while sweeping
+    sweep: Option<f32>,
+    /// mean rms displacement of the panel per swept frequency
+    response: Vec<(f32, f32)>,
+    /// frequencies at which the response has a local maximum
+    resonances: Vec<f32>,
+    palette: Vec<Handle<StandardMaterial>>,
+}
+
+impl Chladni {
+    pub fn is_active(&self) -> bool {
+        self.active
+    }
+}
+
+#[allow(clippy::type_complexity)]
+pub fn on_chladni_events(
+    mut commands: Commands,
+    mut ui_events: EventReader<UiEvents>,
+    mut chladni: ResMut<Chladni>,
+    mut parameters: ResMut<WaveInPanelParameters>,
+    mut materials: ResMut<Assets<StandardMaterial>>,
+    mut particles: Query<(
+        Entity,
+        &RestPosition,
+        &mut Particle,
+        &mut Handle<StandardMaterial>,
+    )>,
+) {
+    for event in ui_events.iter() {
+        match event {
+            UiEvents::StartChladni => {
+                if chladni.palette.is_empty() {
+                    chladni.palette = build_palette(
+                        Colormap::Inferno,
+                        PALETTE_SIZE,
+                        &mut materials,
+                    );
+                }
+
+                let center = Vec3::new(
+                    parameters.dimx / 2.0,
+                    parameters.dimy / 2.0,
+                    0.0,
+                );
+                let driven = particles
+                    .iter()
+                    .min_by(|(_, a, ..), (_, b, ..)| {
+                        a.0.distance(center).total_cmp(&b.0.distance(center))
+                    })
+                    .map(|(entity, ..)| entity);
+
+                // the plate has a single driver
+                for (entity, _, mut particle, mut material) in
+                    particles.iter_mut()
+                {
+                    if Some(entity) == driven {
+                        *particle = Particle::Active;
+                        *material =
+                            parameters.active_particle_material_handle.clone();
+                        commands.entity(entity).insert((
+                            ChladniDriver,
+                            Driver {
+                                waveform: Waveform::Sine,
+                                frequency: parameters.chladni_frequency,
+                                ..Driver::new(&parameters)
+                            },
+                        ));
+                    } else if let Particle::Active = *particle {
+                        *particle = Particle::Passive;
+                        commands.entity(entity).remove::<Driver>();
+                    }
+                }
+
+                // never equal to a frequency, so the averages start over
+                chladni.active = true;
+                chladni.frequency = f32::NAN;
+            }
+            UiEvents::StopChladni => {
+                chladni.active = false;
+                chladni.sweep = None;
+
+                for (entity, _, particle, mut material) in particles.iter_mut()
+                {
+                    *material = match *particle {
+                        Particle::Active => {
+                            parameters.active_particle_material_handle.clone()
+                        }
+                        Particle::Passive => {
+                            parameters.passive_particle_material_handle.clone()
+                        }
+                    };
+                    commands.entity(entity).remove::<ChladniDriver>();
+                }
+            }
+            UiEvents::Reset | UiEvents::LoadSnapshot => {
+                // the respawned particles have no chladni driver
+                chladni.active = false;
+                chladni.sweep = None;
+            }
+            UiEvents::SweepChladni => {
+                chladni.sweep = Some(parameters.chladni_frequency);
+                chladni.response.clear();
+                chladni.resonances.clear();
+            }
+            UiEvents::NextResonance => {
+                if let Some(resonance) = chladni
+                    .resonances
+                    .iter()
+                    .find(|&&f| f > parameters.chladni_frequency)
+                {
+                    parameters.chladni_frequency = *resonance;
+                }
+            }
+            UiEvents::PreviousResonance => {
+                if let Some(resonance) = chladni
+                    .resonances
+                    .iter()
+                    .rev()
+                    .find(|&&f| f < parameters.chladni_frequency)
+                {
+                    parameters.chladni_frequency = *resonance;
+                }
+            }
+            _ => {}
+        }
+    }
+}
+
+#[allow(clippy::type_complexity)]
+pub fn update_chladni(
+    time: Res<Time>,
+    control: Res<SimulationControl>,
+    mut chladni: ResMut<Chladni>,
+    mut parameters: ResMut<WaveInPanelParameters>,
+    mut drivers: Query<&mut Driver, With<ChladniDriver>>,
+    mut vibrations: Query<
+        (
+            &RestPosition,
+            &Transform,
+            &mut Vibration,
+            &mut Handle<StandardMaterial>,
+        ),
+        Without<ChladniDriver>,
+    >,
+) {
+    if !chladni.active {
+        return;
+    }
+
+    if let Some(sweep_frequency) = chladni.sweep {
+        parameters.chladni_frequency = sweep_frequency;
+    }
+
+    // a new frequency starts a new average
+    if chladni.frequency != parameters.chladni_frequency {
+        chladni.frequency = parameters.chladni_frequency;
+        chladni.dwell_secs = 0.0;
+
+        for (_, _, mut vibration, _) in vibrations.iter_mut() {
+            *vibration = Vibration::default();
+        }
+    }
+
+    for mut driver in drivers.iter_mut() {
+        driver.frequency = chladni.frequency;
+    }
+
+    let dt = control.delta_seconds(&time);
+    chladni.dwell_secs += dt;
+
+    if chladni.dwell_secs > SETTLE_SECS {
+        for (rest_position, transform, mut vibration, _) in
+            vibrations.iter_mut()
+        {
+            let displacement = transform.translation.z - rest_position.0.z;
+            vibration.squared_displacement += displacement.powi(2) * dt;
+            vibration.duration += dt;
+        }
+    }
+
+    let (max_rms, sum_rms, count) = vibrations.iter().fold(
+        (0.0_f32, 0.0, 0),
+        |(max_rms, sum_rms, count), (_, _, vibration, _)| {
+            let rms = vibration.rms();
+            (max_rms.max(rms), sum_rms + rms, count + 1)
+        },
+    );
+
+    // nodal lines are bright, the particles moving most are dark
+    if max_rms > 0.0 {
+        for (_, _, vibration, mut material) in vibrations.iter_mut() {
+            let stillness = 1.0 - vibration.rms() / max_rms;
+            let index =
+                (stillness * (PALETTE_SIZE - 1) as f32).round() as usize;
+            *material = chladni.palette[index.min(PALETTE_SIZE - 1)].clone();
+        }
+    }
+
+    if let Some(sweep_frequency) = chladni.sweep {
+        if chladni.dwell_secs < SWEEP_DWELL_SECS {
+            return;
+        }
+
+        let mean_rms = if count > 0 {
+            sum_rms / count as f32
+        } else {
+            0.0
+        };
+        chladni.response.push((sweep_frequency, mean_rms));
+
+        let next_frequency = sweep_frequency + parameters.chladni_sweep_step;
+        if next_frequency <= parameters.chladni_sweep_end {
+            chladni.sweep = Some(next_frequency);
+        } else {
+            finish_sweep(&mut chladni, &mut parameters);
+        }
+    }
+}
+
+fn finish_sweep(chladni: &mut Chladni, parameters: &mut WaveInPanelParameters) {
+    chladni.sweep = None;
+
+    chladni.resonances = chladni
+        .response
+        .windows(3)
+        .filter(|window| {
+            window[1].1 > window[0].1 && window[1].1 >= window[2].1
+        })
+        .map(|window| window[1].0)
+        .collect();
+
+    // continue with the strongest resonance
+    if let Some((frequency, _)) = chladni
+        .response
+        .iter()
+        .filter(|(frequency, _)| chladni.resonances.contains(frequency))
+        .max_by(|a, b| a.1.total_cmp(&b.1))
+    {
+        parameters.chladni_frequency = *frequency;
+    }
+}
+
+pub fn show_chladni_window(
+    mut egui_ctx: ResMut<EguiContext>,
+    chladni: Res<Chladni>,
+    mut parameters: ResMut<WaveInPanelParameters>,
+    mut ui_events: EventWriter<UiEvents>,
+) {
+    if !chladni.active {
+        return;
+    }
+
+    let mut open = true;
+    egui::Window::new("chladni plate")
+        .open(&mut open)
+        .resizable(false)
+        .show(egui_ctx.ctx_mut(), |ui| {
+            ui.add_enabled(
+                chladni.sweep.is_none(),
+                egui::Slider::new(
+                    &mut parameters.chladni_frequency,
+                    0.1..=40.0,
+                )
+                .step_by(0.05)
+                .text("frequency in Hz"),
+            );
+
+            ui.label(format!(
+                "averaged over {:.1} s",
+                (chladni.dwell_secs - SETTLE_SECS).max(0.0)
+            ));
+
+            ui.separator();
+
+            ui.add(
+                egui::Slider::new(
+                    &mut parameters.chladni_sweep_end,
+                    0.1..=40.0,
+                )
+                .step_by(0.05)
+                .text("sweep end in Hz"),
+            );
+            ui.add(
+                egui::Slider::new(
+                    &mut parameters.chladni_sweep_step,
+                    0.05..=2.0,
+                )
+                .step_by(0.05)
+                .text("sweep step in Hz"),
+            );
+
+            if let Some(sweep_frequency) = chladni.sweep {
+                ui.label(format!(
+                    "sweeping {:.2} Hz, {} frequencies done",
+                    sweep_frequency,
+                    chladni.response.len()
+                ));
+            } else if ui.button("Sweep from current frequency").clicked() {
+                ui_events.send(UiEvents::SweepChladni);
+            }
+
+            if !chladni.resonances.is_empty() {
+                ui.label(format!(
+                    "resonances: {}",
+                    chladni
+                        .resonances
+                        .iter()
+                        .map(|f| format!("{:.2}", f))
+                        .collect::<Vec<_>>()
+                        .join(", ")
+                ));
+
+                ui.horizontal(|ui| {
+                    if ui.button("Previous resonance").clicked() {
+                        ui_events.send(UiEvents::PreviousResonance);
+                    }
+                    if ui.button("Next resonance").clicked() {
+                        ui_events.send(UiEvents::NextResonance);
+                    }
+                });
+            }
+        });
+
+    if !open {
+        ui_events.send(UiEvents::StopChladni);
+    }
+}
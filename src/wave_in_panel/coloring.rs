@@ -0,0 +1,72 @@
+use bevy::prelude::*;
+
+use super::chladni::Chladni;
+use super::{Particle, RestPosition, WaveInPanelParameters};
+use crate::colormap::{build_palette, palette_index, Colormap};
+
+const PALETTE_SIZE: usize = 64;
+
+/// Materials sampling the diverging colormap, built when the coloring is
+/// first enabled
+#[derive(Default, Resource)]
+pub struct DisplacementPalette(Vec<Handle<StandardMaterial>>);
+
+/// Colors the passive particles by their displacement along z, negative
+/// displacements are blue and positive ones red, the drivers keep their
+/// material
+pub fn update_displacement_colors(
+    parameters: Res<WaveInPanelParameters>,
+    chladni: Res<Chladni>,
+    mut palette: ResMut<DisplacementPalette>,
+    mut materials: ResMut<Assets<StandardMaterial>>,
+    mut was_coloring: Local<bool>,
+    mut particles: Query<(
+        &Particle,
+        &Transform,
+        &RestPosition,
+        &mut Handle<StandardMaterial>,
+    )>,
+) {
+    // the chladni mode paints the particles itself
+    if chladni.is_active() {
+        *was_coloring = false;
+        return;
+    }
+
+    if !parameters.displacement_coloring {
+        if *was_coloring {
+            for (particle, _, _, mut material) in particles.iter_mut() {
+                if let Particle::Passive = particle {
+                    *material =
+                        parameters.passive_particle_material_handle.clone();
+                }
+            }
+            *was_coloring = false;
+        }
+        return;
+    }
+    *was_coloring = true;
+
+    if palette.0.is_empty() {
+        palette.0 =
+            build_palette(Colormap::Seismic, PALETTE_SIZE, &mut materials);
+    }
+
+    let scale = parameters.displacement_color_scale.max(f32::EPSILON);
+    for (particle, transform, rest_position, mut material) in
+        particles.iter_mut()
+    {
+        if let Particle::Active = particle {
+            continue;
+        }
+
+        let displacement = transform.translation.z - rest_position.0.z;
+        let color =
+            &palette.0[palette_index(displacement / scale, PALETTE_SIZE)];
+
+        // avoids flagging every material as changed each frame
+        if *material != *color {
+            *material = color.clone();
+        }
+    }
+}
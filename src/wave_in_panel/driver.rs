@@ -0,0 +1,254 @@
+use std::f32::consts::TAU;
+
+use bevy::prelude::*;
+use bevy_egui::{egui, EguiContext};
+use serde::{Deserialize, Serialize};
+
+use super::{ParticleIndex, UiEvents, WaveInPanelParameters, WaveStopwatch};
+use crate::inspector::Selection;
+
+/// Signal shape of a driver
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub enum Waveform {
+    Sine,
+    Square,
+    Triangle,
+    /// a new random displacement twice per period
+    Noise,
+    /// sine sweeping linearly from the frequency to the sweep end frequency,
+    /// restarting after the sweep duration
+    Chirp,
+    /// the captured microphone signal
+    Microphone,
+    /// the loaded WAV file
+    AudioFile,
+}
+
+impl Waveform {
+    pub const ALL: [Waveform; 7] = [
+        Waveform::Sine,
+        Waveform::Square,
+        Waveform::Triangle,
+        Waveform::Noise,
+        Waveform::Chirp,
+        Waveform::Microphone,
+        Waveform::AudioFile,
+    ];
+}
+
+impl From<Waveform> for String {
+    fn from(value: Waveform) -> Self {
+        match value {
+            Waveform::Sine => "sine".to_string(),
+            Waveform::Square => "square".to_string(),
+            Waveform::Triangle => "triangle".to_string(),
+            Waveform::Noise => "noise".to_string(),
+            Waveform::Chirp => "chirp".to_string(),
+            Waveform::Microphone => "microphone".to_string(),
+            Waveform::AudioFile => "audio file".to_string(),
+        }
+    }
+}
+
+/// Current samples of the audio sources, which are shared by all drivers
+#[derive(Clone, Copy, Default)]
+pub struct AudioSamples {
+    pub microphone: f32,
+    pub file: f32,
+}
+
+/// Displaces an active particle along z, every driver has its own signal so
+/// several of them can interfere
+#[derive(Clone, Copy, Component, Debug, PartialEq, Serialize, Deserialize)]
+pub struct Driver {
+    pub waveform: Waveform,
+    pub frequency: f32,
+    pub amplitude: f32,
+    /// phase offset in radians
+    pub phase: f32,
+    pub sweep_end_frequency: f32,
+    pub sweep_duration_secs: f32,
+}
+
+impl Driver {
+    /// A driver with the frequency and amplitude currently set in the panel
+    pub fn new(parameters: &WaveInPanelParameters) -> Self {
+        Self {
+            waveform: parameters.waveform,
+            frequency: parameters.applying_force_frequency,
+            amplitude: parameters.applying_force_factor,
+            phase: 0.0,
+            sweep_end_frequency: 20.0,
+            sweep_duration_secs: 10.0,
+        }
+    }
+
+    pub fn displacement(&self, elapsed_secs: f32, audio: AudioSamples) -> f32 {
+        let cycles = self.frequency * elapsed_secs + self.phase / TAU;
+
+        let signal = match self.waveform {
+            Waveform::Sine => (TAU * cycles).sin(),
+            Waveform::Square => (TAU * cycles).sin().signum(),
+            Waveform::Triangle => {
+                1.0 - 4.0 * (cycles - 0.25 - (cycles + 0.25).floor()).abs()
+            }
+            Waveform::Noise => noise((2.0 * cycles).floor() as i64),
+            Waveform::Chirp => {
+                let duration = self.sweep_duration_secs.max(f32::EPSILON);
+                let t = elapsed_secs % duration;
+                let sweep_rate =
+                    (self.sweep_end_frequency - self.frequency) / duration;
+
+                (TAU * (self.frequency * t + 0.5 * sweep_rate * t * t)
+                    + self.phase)
+                    .sin()
+            }
+            Waveform::Microphone => audio.microphone,
+            Waveform::AudioFile => audio.file,
+        };
+
+        signal * self.amplitude
+    }
+
+    /// Frequency the driver oscillates with at the given time
+    pub fn frequency_at(&self, elapsed_secs: f32) -> f32 {
+        match self.waveform {
+            Waveform::Chirp => {
+                let duration = self.sweep_duration_secs.max(f32::EPSILON);
+                let t = elapsed_secs % duration;
+
+                self.frequency
+                    + (self.sweep_end_frequency - self.frequency) * t / duration
+            }
+            _ => self.frequency,
+        }
+    }
+}
+
+/// Deterministic pseudo random value in `-1.0..=1.0` for every sample, so
+/// replays and snapshots reproduce the same noise
+fn noise(sample: i64) -> f32 {
+    // splitmix64
+    let mut z = (sample as u64).wrapping_add(0x9e37_79b9_7f4a_7c15);
+    z = (z ^ (z >> 30)).wrapping_mul(0xbf58_476d_1ce4_e5b9);
+    z = (z ^ (z >> 27)).wrapping_mul(0x94d0_49bb_1331_11eb);
+    z ^= z >> 31;
+
+    (z >> 40) as f32 / (1u64 << 23) as f32 - 1.0
+}
+
+/// Particle index of the driver edited in the driver window
+#[derive(Default, Resource)]
+pub struct SelectedDriver(pub Option<usize>);
+
+pub fn show_driver_window(
+    mut egui_ctx: ResMut<EguiContext>,
+    mut selected_driver: ResMut<SelectedDriver>,
+    stopwatch: Res<WaveStopwatch>,
+    drivers: Query<(&ParticleIndex, &Driver)>,
+    mut ui_events: EventWriter<UiEvents>,
+) {
+    let index = if let Some(index) = selected_driver.0 {
+        index
+    } else {
+        return;
+    };
+
+    let driver = if let Some((_, driver)) = drivers
+        .iter()
+        .find(|(particle_index, _)| particle_index.0 == index)
+    {
+        driver
+    } else {
+        selected_driver.0 = None;
+        return;
+    };
+
+    let mut open = true;
+    egui::Window::new(format!("driver {}", index))
+        .open(&mut open)
+        .resizable(false)
+        .show(egui_ctx.ctx_mut(), |ui| {
+            let mut edited = *driver;
+
+            select_waveform(ui, &mut edited.waveform);
+
+            ui.add(
+                egui::Slider::new(&mut edited.frequency, 0.0..=20.0)
+                    .step_by(0.1)
+                    .text("frequency in Hz"),
+            );
+
+            if edited.waveform == Waveform::Chirp {
+                ui.add(
+                    egui::Slider::new(
+                        &mut edited.sweep_end_frequency,
+                        0.0..=40.0,
+                    )
+                    .step_by(0.1)
+                    .text("sweep end frequency in Hz"),
+                );
+                ui.add(
+                    egui::Slider::new(
+                        &mut edited.sweep_duration_secs,
+                        1.0..=60.0,
+                    )
+                    .text("sweep duration in s"),
+                );
+                ui.label(format!(
+                    "current frequency: {:.2} Hz",
+                    driver.frequency_at(stopwatch.0.elapsed_secs())
+                ));
+            }
+            ui.add(
+                egui::Slider::new(&mut edited.amplitude, 0.0..=0.4)
+                    .step_by(0.01)
+                    .text("amplitude"),
+            );
+            ui.add(
+                egui::Slider::new(&mut edited.phase, 0.0..=TAU)
+                    .text("phase in rad"),
+            );
+
+            if edited != *driver {
+                ui_events.send(UiEvents::SetDriver(index, edited));
+            }
+
+            if ui.button("Remove driver").clicked() {
+                ui_events.send(UiEvents::RemoveDriver(index));
+            }
+        });
+
+    if !open {
+        selected_driver.0 = None;
+    }
+}
+
+/// Selecting a driver in the inspector opens its settings
+pub fn open_selected_driver(
+    selection: Res<Selection>,
+    drivers: Query<&ParticleIndex, With<Driver>>,
+    mut selected_driver: ResMut<SelectedDriver>,
+) {
+    if !selection.is_changed() {
+        return;
+    }
+
+    if let Some(index) = selection.0.and_then(|entity| drivers.get(entity).ok())
+    {
+        selected_driver.0 = Some(index.0);
+    }
+}
+
+pub fn select_waveform(ui: &mut egui::Ui, waveform: &mut Waveform) {
+    ui.horizontal(|ui| {
+        ui.label("waveform:");
+        egui::ComboBox::from_id_source("waveform_selection")
+            .selected_text(String::from(*waveform))
+            .show_ui(ui, |ui| {
+                for option in Waveform::ALL {
+                    ui.selectable_value(waveform, option, String::from(option));
+                }
+            });
+    });
+}
@@ -1,17 +1,56 @@
-use std::f32::consts::TAU;
+use std::time::Duration;
 
+use bevy::ecs::system::SystemState;
 use bevy::prelude::*;
 use bevy::time::Stopwatch;
 use bevy::utils::HashMap;
 use bevy_egui::egui;
 use bevy_rapier3d::prelude::*;
 use bevy_rapier3d::render::DebugRenderContext;
-use itertools::Itertools;
+use serde::{Deserialize, Serialize};
 
+use crate::audio_file::{show_audio_file, AudioFile, LoadAudioFile};
+use crate::audio_input::{show_audio_input, AudioInput};
+use crate::hover::Hoverable;
+use crate::inspector::selects;
+use crate::instancing::InstancedParticle;
+use crate::level_of_detail::LevelOfDetail;
 use crate::objects_3d::spawn_koordinate_system_helper;
 use crate::pan_orbit_camera::{update_pan_orbit_camera, PanOrbitCamera};
+use crate::persistence::{PersistenceAppExt, RestoreParameters};
+use crate::recording::{
+    RecordableEvent, RecordableParameters, RecordingAppExt,
+};
+use crate::simulation::Simulation;
+use crate::simulation_control::{
+    forward_reset, ResetEvent, SimulationClock, SimulationControl,
+    SimulationControlEvent,
+};
+use crate::snapshot::{load_snapshot, save_snapshot, BodyState};
+use crate::ui::{show_tunables, Tunable, TunableParameter};
+use crate::viewports::cursor_in_viewport;
 use crate::{AppCamera, AppState};
 
+mod chladni;
+mod coloring;
+mod driver;
+mod selection;
+mod skin;
+
+use chladni::{
+    on_chladni_events, show_chladni_window, update_chladni, Chladni, Vibration,
+};
+use coloring::{update_displacement_colors, DisplacementPalette};
+use driver::{
+    open_selected_driver, select_waveform, show_driver_window, AudioSamples,
+    Driver, SelectedDriver, Waveform,
+};
+use selection::{
+    on_box_selection, on_particles_selected, BoxSelection,
+    ParticlesSelectedEvent,
+};
+use skin::{export_panel_skin, hide_particles_under_skin, update_panel_skin};
+
 #[derive(Default, Resource)]
 struct WaveStopwatch(Stopwatch);
 
@@ -21,22 +60,92 @@ enum Particle {
     Active,
 }
 
-#[derive(Resource)]
+/// Position of a particle in the spawn order, used to restore snapshots
+#[derive(Component)]
+struct ParticleIndex(usize);
+
+/// Lattice position a particle is spawned at, displacements are measured
+/// against it
+#[derive(Component)]
+struct RestPosition(Vec3);
+
+/// Particles closer than this are coupled
+const NEIGHBOR_DISTANCE: f32 = 1.1;
+
+/// Spring joints connect lattice neighbors up to this multiple of the
+/// particle spacing, which includes the diagonals
+const JOINT_NEIGHBOR_DISTANCE: f32 = 1.5;
+
+/// How neighboring particles pull on each other
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub enum Coupling {
+    /// nudges the velocities towards the displacement of the neighbors
+    Velocity,
+    /// rapier impulse joints with a spring motor on every axis
+    SpringJoints,
+}
+
+impl From<Coupling> for String {
+    fn from(coupling: Coupling) -> Self {
+        match coupling {
+            Coupling::Velocity => String::from("velocity"),
+            Coupling::SpringJoints => String::from("spring joints"),
+        }
+    }
+}
+
+/// A click into the scene as a ray in world coordinates
+#[derive(Serialize, Deserialize)]
+struct PanelClickedEvent {
+    origin: Vec3,
+    direction: Vec3,
+    /// shift click, toggles the hit particle between fixed and dynamic
+    #[serde(default)]
+    toggle_fixed: bool,
+}
+
+impl RecordableEvent for PanelClickedEvent {
+    const KIND: &'static str = "wave_in_panel_click";
+}
+
+#[derive(Clone, Resource, Serialize, Deserialize)]
+#[serde(default)]
 pub struct WaveInPanelParameters {
+    #[serde(skip)]
     particle_mesh_handle: Handle<Mesh>,
+    #[serde(skip)]
     passive_particle_material_handle: Handle<StandardMaterial>,
+    #[serde(skip)]
     active_particle_material_handle: Handle<StandardMaterial>,
+    #[serde(skip)]
     particles_map: HashMap<Entity, Vec<Entity>>,
+    /// steps of the spawned lattice along x, y and z, its particles are one
+    /// more along each axis
+    #[serde(skip)]
+    lattice_steps: [usize; 3],
 
     dimx: f32,
     dimy: f32,
     dimz: f32,
     particle_radius: f32,
 
+    coupling: Coupling,
     equalizing_force_factor: f32,
+    joint_stiffness: f32,
+    joint_damping: f32,
+    waveform: Waveform,
     applying_force_frequency: f32,
     applying_force_factor: f32,
     sysnthetic_energy_loss_factor: f32,
+    displacement_coloring: bool,
+    /// displacement shown with the most saturated color
+    displacement_color_scale: f32,
+    /// draws a membrane through the front layer instead of the particles
+    skin: bool,
+
+    chladni_frequency: f32,
+    chladni_sweep_end: f32,
+    chladni_sweep_step: f32,
 }
 
 impl Default for WaveInPanelParameters {
@@ -48,43 +157,106 @@ impl Default for WaveInPanelParameters {
             active_particle_material_handle:
                 Handle::<StandardMaterial>::default(),
             particles_map: HashMap::<Entity, Vec<Entity>>::default(),
+            lattice_steps: [0; 3],
 
-            // initially fixed parameters
+            // applied when the particles are respawned
             dimx: 14.0,
             dimy: 8.0,
             dimz: 0.0,
             particle_radius: 0.1,
 
             // dynamically applicable parameters
+            coupling: Coupling::Velocity,
             equalizing_force_factor: 2.0,
+            joint_stiffness: 50.0,
+            joint_damping: 0.5,
+            waveform: Waveform::Sine,
             applying_force_frequency: 3.5,
             applying_force_factor: 0.1,
             sysnthetic_energy_loss_factor: 0.997,
+            displacement_coloring: false,
+            displacement_color_scale: 0.1,
+            skin: false,
+
+            chladni_frequency: 5.0,
+            chladni_sweep_end: 20.0,
+            chladni_sweep_step: 0.25,
         }
     }
 }
 
+impl RecordableParameters for WaveInPanelParameters {
+    const KIND: &'static str = "wave_in_panel_parameters";
+    const SIMULATION: AppState = WaveInPanelPlugin::STATE;
+
+    fn restore(&mut self, recorded: Self) {
+        // the handles and the neighbors belong to this session
+        *self = Self {
+            particle_mesh_handle: self.particle_mesh_handle.clone(),
+            passive_particle_material_handle: self
+                .passive_particle_material_handle
+                .clone(),
+            active_particle_material_handle: self
+                .active_particle_material_handle
+                .clone(),
+            particles_map: std::mem::take(&mut self.particles_map),
+            lattice_steps: self.lattice_steps,
+            ..recorded
+        };
+    }
+}
+
 pub struct WaveInPanelPlugin;
 
 impl Plugin for WaveInPanelPlugin {
     fn build(&self, app: &mut App) {
         app.add_event::<UiEvents>()
+            .add_event::<PanelClickedEvent>()
+            .add_event::<ParticlesSelectedEvent>()
+            .add_recordable_event::<UiEvents>()
+            .add_recordable_event::<PanelClickedEvent>()
+            .add_recordable_event::<ParticlesSelectedEvent>()
+            .add_recordable_parameters::<WaveInPanelParameters>()
+            .add_persistent_parameters::<WaveInPanelParameters>()
             .insert_resource(WaveStopwatch::default())
+            .insert_resource(SelectedDriver::default())
+            .insert_resource(BoxSelection::default())
+            .insert_resource(Chladni::default())
+            .insert_resource(DisplacementPalette::default())
             .insert_resource(WaveInPanelParameters::default())
             .add_system_set(
-                SystemSet::on_enter(AppState::WaveInPanel).with_system(setup),
+                SystemSet::on_enter(WaveInPanelPlugin::STATE)
+                    .with_system(setup.after(RestoreParameters)),
             )
             .add_system_set(
-                SystemSet::on_update(AppState::WaveInPanel)
+                SystemSet::on_update(WaveInPanelPlugin::STATE)
                     .with_system(update_equalizing_forces)
+                    .with_system(update_spring_joints)
                     .with_system(apply_external_force)
                     .with_system(on_ui_events)
+                    .with_system(on_snapshot_events)
                     .with_system(apply_synthetic_energy_loss)
-                    .with_system(on_input_events)
-                    .with_system(update_pan_orbit_camera),
+                    .with_system(on_mouse_events)
+                    .with_system(on_panel_clicked)
+                    .with_system(on_box_selection)
+                    .with_system(on_particles_selected)
+                    .with_system(show_driver_window)
+                    .with_system(open_selected_driver)
+                    .with_system(on_chladni_events)
+                    .with_system(update_chladni.after(on_chladni_events))
+                    .with_system(show_chladni_window)
+                    .with_system(
+                        update_displacement_colors.after(update_chladni),
+                    )
+                    .with_system(hide_particles_under_skin)
+                    .with_system(update_panel_skin)
+                    .with_system(export_panel_skin.after(update_panel_skin))
+                    .with_system(update_pan_orbit_camera)
+                    .with_system(forward_reset::<UiEvents>),
             )
             .add_system_set(
-                SystemSet::on_exit(AppState::WaveInPanel).with_system(cleanup),
+                SystemSet::on_exit(WaveInPanelPlugin::STATE)
+                    .with_system(cleanup),
             );
     }
 }
@@ -110,11 +282,7 @@ fn setup(
     }
 
     // mesh
-    parameters.particle_mesh_handle =
-        meshes.add(Mesh::from(shape::Icosphere {
-            radius: parameters.particle_radius * 1.3,
-            subdivisions: 1,
-        }));
+    parameters.particle_mesh_handle = meshes.add(particle_mesh(&parameters));
 
     // material
     parameters.passive_particle_material_handle =
@@ -155,20 +323,56 @@ fn setup(
     ));
 
     // spawn particles
-    let entities_and_positions = spawn_particles(&mut commands, &parameters);
+    let entities_and_positions =
+        spawn_particles(&mut commands, &mut parameters);
 
     // find nearby particles
-    store_nearby_particles(&entities_and_positions, &mut parameters);
+    couple_particles(&mut commands, &entities_and_positions, &mut parameters);
+}
+
+fn particle_mesh(parameters: &WaveInPanelParameters) -> Mesh {
+    Mesh::from(shape::Icosphere {
+        radius: particle_mesh_radius(parameters),
+        subdivisions: 1,
+    })
+}
+
+/// The meshes are slightly larger than the colliders, so the panel looks
+/// closed
+fn particle_mesh_radius(parameters: &WaveInPanelParameters) -> f32 {
+    parameters.particle_radius * 1.3
+}
+
+/// Replaces all particles with a new lattice built from the current
+/// parameters
+fn respawn_particles(
+    commands: &mut Commands,
+    meshes: &mut Assets<Mesh>,
+    parameters: &mut WaveInPanelParameters,
+    particles: Query<Entity, With<Particle>>,
+) -> Vec<(Entity, Vec3)> {
+    cleanup_particles(commands, parameters, particles);
+
+    // the particle radius may have changed
+    meshes.remove(&parameters.particle_mesh_handle);
+    parameters.particle_mesh_handle = meshes.add(particle_mesh(parameters));
+
+    let entities_and_positions = spawn_particles(commands, parameters);
+
+    couple_particles(commands, &entities_and_positions, parameters);
+
+    entities_and_positions
 }
 
 fn spawn_particles(
     commands: &mut Commands,
-    parameters: &WaveInPanelParameters,
+    parameters: &mut WaveInPanelParameters,
 ) -> Vec<(Entity, Vec3)> {
-    let particle_size = parameters.particle_radius * 2.1;
+    let particle_size = particle_spacing(parameters);
     let stepsx = (parameters.dimx / particle_size).floor() as usize;
     let stepsy = (parameters.dimy / particle_size).floor() as usize;
     let stepsz = (parameters.dimz / particle_size).floor() as usize;
+    parameters.lattice_steps = [stepsx, stepsy, stepsz];
 
     let mut entities_and_positions = Vec::new();
 
@@ -191,14 +395,32 @@ fn spawn_particles(
                     Velocity::default(),
                     ExternalForce::default(),
                     Particle::Passive,
+                    ParticleIndex(entities_and_positions.len()),
+                    RestPosition(position),
+                    Vibration::default(),
+                    Hoverable {
+                        rest_position: Some(position),
+                        radius: parameters.particle_radius,
+                    },
+                    InstancedParticle,
+                    LevelOfDetail {
+                        radius: particle_mesh_radius(parameters),
+                    },
                 ));
 
+                // the edges are clamped, front and back face of the slab
+                // are free
                 if x == 0 || x == stepsx || y == 0 || y == stepsy {
                     entity.insert(RigidBody::Fixed);
                 } else {
                     entity.insert(RigidBody::Dynamic);
                 }
 
+                // the joint anchors would swing around rotating particles
+                if parameters.coupling == Coupling::SpringJoints {
+                    entity.insert(LockedAxes::ROTATION_LOCKED);
+                }
+
                 entities_and_positions.push((entity.id(), position));
             }
         }
@@ -207,36 +429,125 @@ fn spawn_particles(
     entities_and_positions
 }
 
+fn particle_spacing(parameters: &WaveInPanelParameters) -> f32 {
+    parameters.particle_radius * 2.1
+}
+
+fn couple_particles(
+    commands: &mut Commands,
+    entities_and_positions: &[(Entity, Vec3)],
+    parameters: &mut WaveInPanelParameters,
+) {
+    store_nearby_particles(entities_and_positions, parameters);
+
+    if parameters.coupling == Coupling::SpringJoints {
+        spawn_spring_joints(commands, entities_and_positions, parameters);
+    }
+}
+
+/// Connects every pair of lattice neighbors with a spring joint, the joints
+/// are children of the particles since a particle can have only one joint
+/// component
+fn spawn_spring_joints(
+    commands: &mut Commands,
+    entities_and_positions: &[(Entity, Vec3)],
+    parameters: &WaveInPanelParameters,
+) {
+    let max_distance = particle_spacing(parameters) * JOINT_NEIGHBOR_DISTANCE;
+    let positions: HashMap<Entity, Vec3> =
+        entities_and_positions.iter().copied().collect();
+
+    for (entity, position) in entities_and_positions {
+        let neighbors =
+            if let Some(neighbors) = parameters.particles_map.get(entity) {
+                neighbors
+            } else {
+                continue;
+            };
+
+        commands.entity(*entity).with_children(|children| {
+            for neighbor in neighbors {
+                // every pair is joined once
+                if neighbor < entity {
+                    continue;
+                }
+
+                let neighbor_position = positions[neighbor];
+                if position.distance(neighbor_position) > max_distance {
+                    continue;
+                }
+
+                let mut joint =
+                    GenericJointBuilder::new(JointAxesMask::empty())
+                        .local_anchor1(*position - neighbor_position);
+                for axis in [JointAxis::X, JointAxis::Y, JointAxis::Z] {
+                    joint = joint.motor_position(
+                        axis,
+                        0.0,
+                        parameters.joint_stiffness,
+                        parameters.joint_damping,
+                    );
+                }
+
+                children.spawn(ImpulseJoint::new(*neighbor, joint));
+            }
+        });
+    }
+}
+
+/// Looks up neighbors in a uniform grid of cells as large as the coupling
+/// distance, so only particles in the same or an adjacent cell are compared
 fn store_nearby_particles(
     entities_and_positions: &[(Entity, Vec3)],
     parameters: &mut WaveInPanelParameters,
 ) {
-    for combinations in entities_and_positions.iter().combinations(2) {
-        let xz1 = combinations[0].1;
-        let xz2 = combinations[1].1;
-        let distance = xz1.distance(xz2);
-
-        if distance < 1.1 {
-            parameters
-                .particles_map
-                .entry(combinations[0].0)
-                .and_modify(|n| n.push(combinations[1].0))
-                .or_insert(vec![combinations[1].0]);
-
-            parameters
-                .particles_map
-                .entry(combinations[1].0)
-                .and_modify(|n| n.push(combinations[0].0))
-                .or_insert(vec![combinations[0].0]);
+    let cell_of =
+        |position: Vec3| (position / NEIGHBOR_DISTANCE).floor().as_ivec3();
+
+    let mut cells: HashMap<IVec3, Vec<usize>> = HashMap::default();
+    for (i, (_, position)) in entities_and_positions.iter().enumerate() {
+        cells.entry(cell_of(*position)).or_default().push(i);
+    }
+
+    for (i, (entity, position)) in entities_and_positions.iter().enumerate() {
+        let cell = cell_of(*position);
+        let mut neighbors = Vec::new();
+
+        for offset in (-1..=1).flat_map(|x| {
+            (-1..=1)
+                .flat_map(move |y| (-1..=1).map(move |z| IVec3::new(x, y, z)))
+        }) {
+            let candidates =
+                if let Some(candidates) = cells.get(&(cell + offset)) {
+                    candidates
+                } else {
+                    continue;
+                };
+
+            for &j in candidates {
+                let (neighbor, neighbor_position) = entities_and_positions[j];
+
+                if j != i
+                    && position.distance(neighbor_position) < NEIGHBOR_DISTANCE
+                {
+                    neighbors.push(neighbor);
+                }
+            }
+        }
+
+        if !neighbors.is_empty() {
+            parameters.particles_map.insert(*entity, neighbors);
         }
     }
 }
 
 fn apply_synthetic_energy_loss(
+    control: Res<SimulationControl>,
     parameters: Res<WaveInPanelParameters>,
     mut particles: Query<&mut Velocity, With<Particle>>,
 ) {
-    if parameters.sysnthetic_energy_loss_factor == 1.0 {
+    if parameters.sysnthetic_energy_loss_factor == 1.0 || !control.is_running()
+    {
         return;
     }
 
@@ -245,12 +556,27 @@ fn apply_synthetic_energy_loss(
     }
 }
 
+/// Couples every particle to its neighbors along all three axes, each pair
+/// acts like a spring pulling their displacements together
 fn update_equalizing_forces(
+    control: Res<SimulationControl>,
     parameters: Res<WaveInPanelParameters>,
-    mut particles: Query<(Entity, &Particle, &Transform, &mut Velocity)>,
-    particles_transforms: Query<&Transform, With<Particle>>,
+    mut particles: Query<(
+        Entity,
+        &Particle,
+        &Transform,
+        &RestPosition,
+        &mut Velocity,
+    )>,
+    particles_displacements: Query<(&Transform, &RestPosition), With<Particle>>,
 ) {
-    for (entity, particle, transform, mut velocity) in particles.iter_mut() {
+    if parameters.coupling != Coupling::Velocity || !control.is_running() {
+        return;
+    }
+
+    for (entity, particle, transform, rest_position, mut velocity) in
+        particles.iter_mut()
+    {
         if let Particle::Active = particle {
             continue;
         }
@@ -262,12 +588,17 @@ fn update_equalizing_forces(
                 continue;
             };
 
+        let displacement = transform.translation - rest_position.0;
+
         for neighbor in neighbors.iter() {
-            if let Ok(neighbour_transform) = particles_transforms.get(*neighbor)
+            if let Ok((neighbour_transform, neighbour_rest_position)) =
+                particles_displacements.get(*neighbor)
             {
-                let equalizing_force = (neighbour_transform.translation
-                    - transform.translation)
-                    * Vec3::new(0.0, 0.0, parameters.equalizing_force_factor);
+                let neighbour_displacement =
+                    neighbour_transform.translation - neighbour_rest_position.0;
+
+                let equalizing_force = (neighbour_displacement - displacement)
+                    * parameters.equalizing_force_factor;
 
                 velocity.linvel += equalizing_force;
             } else {
@@ -277,99 +608,290 @@ fn update_equalizing_forces(
     }
 }
 
+fn update_spring_joints(
+    parameters: Res<WaveInPanelParameters>,
+    mut applied_springs: Local<Option<(f32, f32)>>,
+    mut joints: Query<&mut ImpulseJoint>,
+) {
+    let springs = (parameters.joint_stiffness, parameters.joint_damping);
+    if *applied_springs == Some(springs) {
+        return;
+    }
+
+    for mut joint in joints.iter_mut() {
+        for axis in [JointAxis::X, JointAxis::Y, JointAxis::Z] {
+            joint
+                .data
+                .set_motor_position(axis, 0.0, springs.0, springs.1);
+        }
+    }
+
+    *applied_springs = Some(springs);
+}
+
 fn apply_external_force(
     time: Res<Time>,
+    control: Res<SimulationControl>,
+    mut clock: ResMut<SimulationClock>,
     mut stopwatch: ResMut<WaveStopwatch>,
-    mut particles: Query<(&Particle, &mut Transform)>,
-    parameters: Res<WaveInPanelParameters>,
+    mut audio_input: ResMut<AudioInput>,
+    mut audio_file: ResMut<AudioFile>,
+    mut drivers: Query<(&Driver, &RestPosition, &mut Transform)>,
 ) {
-    stopwatch.0.tick(time.delta());
+    stopwatch.0.tick(control.delta(&time));
+    // rapier takes a single step per frame
+    if control.is_running() {
+        clock.advance(1, control.delta_seconds(&time));
+    }
 
-    for (particle, mut transform) in particles.iter_mut() {
-        if let Particle::Active = particle {
-            let elapsed_time = stopwatch.0.elapsed();
-            let amplitude = (TAU
-                * parameters.applying_force_frequency
-                * elapsed_time.as_secs_f32())
-            .sin()
-                * parameters.applying_force_factor;
-
-            transform.translation.z = amplitude;
+    // the microphone stays closed while no driver listens to it
+    let uses = |waveform: Waveform| {
+        drivers
+            .iter()
+            .any(|(driver, _, _)| driver.waveform == waveform)
+    };
+    let mut audio = AudioSamples::default();
+    if control.is_running() {
+        let secs = control.delta_seconds(&time);
+        if uses(Waveform::Microphone) {
+            audio.microphone = audio_input.next_sample(secs);
+        }
+        if uses(Waveform::AudioFile) {
+            audio.file = audio_file.next_sample(secs);
         }
     }
+
+    let elapsed_secs = stopwatch.0.elapsed_secs();
+    for (driver, rest_position, mut transform) in drivers.iter_mut() {
+        transform.translation.z =
+            rest_position.0.z + driver.displacement(elapsed_secs, audio);
+    }
 }
 
-fn on_input_events(
+fn on_mouse_events(
     windows: Res<Windows>,
     input_mouse: Res<Input<MouseButton>>,
+    keys: Res<Input<KeyCode>>,
     camera: Query<(&Camera, &GlobalTransform), With<AppCamera>>,
-    rapier_context: Res<RapierContext>,
-    parameters: Res<WaveInPanelParameters>,
-    mut particles: Query<(&mut Handle<StandardMaterial>, &mut Particle)>,
+    mut panel_clicked_events: EventWriter<PanelClickedEvent>,
 ) {
-    if input_mouse.just_pressed(MouseButton::Left) {
+    // a control click selects the particle for the inspector instead
+    if input_mouse.just_pressed(MouseButton::Left) && !selects(&keys) {
         let (camera, camera_transform) = camera.get_single().unwrap();
         let window = windows.get_primary().unwrap();
 
-        if let Some(ray) = window
-            .cursor_position()
+        if let Some(ray) = cursor_in_viewport(camera, window)
             .and_then(|p| camera.viewport_to_world(camera_transform, p))
         {
-            if let Some(entity) = rapier_context.cast_ray(
-                ray.origin,
-                ray.direction,
-                Real::MAX,
-                true,
-                QueryFilter::default(),
-            ) {
-                if let Ok((mut material, mut particle)) =
-                    particles.get_mut(entity.0)
-                {
-                    if let Particle::Active = particle.as_ref() {
-                        *material =
-                            parameters.passive_particle_material_handle.clone();
-                        *particle = Particle::Passive;
-                    } else {
-                        *material =
-                            parameters.active_particle_material_handle.clone();
-                        *particle = Particle::Active;
-                    }
+            panel_clicked_events.send(PanelClickedEvent {
+                origin: ray.origin,
+                direction: ray.direction,
+                toggle_fixed: keys.pressed(KeyCode::LShift)
+                    || keys.pressed(KeyCode::RShift),
+            });
+        }
+    }
+}
+
+/// Clicking a passive particle turns it into a driver, clicking a driver
+/// opens its settings, a shift click anchors or releases the particle
+fn on_panel_clicked(
+    mut commands: Commands,
+    mut panel_clicked_events: EventReader<PanelClickedEvent>,
+    rapier_context: Res<RapierContext>,
+    parameters: Res<WaveInPanelParameters>,
+    mut selected_driver: ResMut<SelectedDriver>,
+    mut particles: Query<(
+        &ParticleIndex,
+        &mut Handle<StandardMaterial>,
+        &mut Particle,
+        &mut RigidBody,
+    )>,
+) {
+    for event in panel_clicked_events.iter() {
+        if let Some(entity) = rapier_context.cast_ray(
+            event.origin,
+            event.direction,
+            Real::MAX,
+            true,
+            QueryFilter::default(),
+        ) {
+            if let Ok((index, mut material, mut particle, mut rigid_body)) =
+                particles.get_mut(entity.0)
+            {
+                if event.toggle_fixed {
+                    *rigid_body = match *rigid_body {
+                        RigidBody::Fixed => RigidBody::Dynamic,
+                        _ => RigidBody::Fixed,
+                    };
+                    continue;
+                }
+
+                if let Particle::Passive = particle.as_ref() {
+                    *material =
+                        parameters.active_particle_material_handle.clone();
+                    *particle = Particle::Active;
+                    commands.entity(entity.0).insert(Driver::new(&parameters));
                 }
+
+                selected_driver.0 = Some(index.0);
             }
         }
     }
 }
 
 fn on_ui_events(
-    mut time: ResMut<Time>,
     mut commands: Commands,
     mut ui_events: EventReader<UiEvents>,
     particles: Query<Entity, With<Particle>>,
+    particle_indices: Query<(Entity, &ParticleIndex)>,
+    mut meshes: ResMut<Assets<Mesh>>,
     mut parameters: ResMut<WaveInPanelParameters>,
+    mut selected_driver: ResMut<SelectedDriver>,
 ) {
     let mut cleanup = false;
     for event in ui_events.iter() {
         match event {
-            UiEvents::StartStopTime => {
-                if time.is_paused() {
-                    time.unpause();
-                } else {
-                    time.pause();
-                }
-            }
             UiEvents::Reset => {
                 cleanup = true;
             }
+            UiEvents::SetDriver(index, driver) => {
+                if let Some((entity, _)) = particle_indices
+                    .iter()
+                    .find(|(_, particle_index)| particle_index.0 == *index)
+                {
+                    commands.entity(entity).insert((
+                        Particle::Active,
+                        *driver,
+                        parameters.active_particle_material_handle.clone(),
+                    ));
+                }
+            }
+            UiEvents::RemoveDriver(index) => {
+                if let Some((entity, _)) = particle_indices
+                    .iter()
+                    .find(|(_, particle_index)| particle_index.0 == *index)
+                {
+                    commands
+                        .entity(entity)
+                        .insert((
+                            Particle::Passive,
+                            parameters.passive_particle_material_handle.clone(),
+                        ))
+                        .remove::<Driver>();
+                }
+
+                if selected_driver.0 == Some(*index) {
+                    selected_driver.0 = None;
+                }
+            }
+            _ => {}
         }
     }
 
     if cleanup {
-        cleanup_particles(&mut commands, &mut parameters, particles);
+        selected_driver.0 = None;
+
+        respawn_particles(
+            &mut commands,
+            &mut meshes,
+            &mut parameters,
+            particles,
+        );
+    }
+}
 
-        let entities_and_positions =
-            spawn_particles(&mut commands, &parameters);
+/// State of the panel written to and read from snapshot files
+#[derive(Serialize, Deserialize)]
+struct WaveInPanelSnapshot {
+    parameters: WaveInPanelParameters,
+    /// particles in spawn order with their driver if they are active
+    particles: Vec<(Option<Driver>, BodyState)>,
+    stopwatch_elapsed_secs: f32,
+}
 
-        store_nearby_particles(&entities_and_positions, &mut parameters);
+fn on_snapshot_events(
+    mut commands: Commands,
+    mut meshes: ResMut<Assets<Mesh>>,
+    mut ui_events: EventReader<UiEvents>,
+    mut stopwatch: ResMut<WaveStopwatch>,
+    mut parameters: ResMut<WaveInPanelParameters>,
+    particle_states: Query<(
+        &ParticleIndex,
+        Option<&Driver>,
+        &Transform,
+        &Velocity,
+    )>,
+    particles: Query<Entity, With<Particle>>,
+) {
+    let name = WaveInPanelPlugin::NAME.to_string();
+
+    let mut loaded_snapshot = None;
+    for event in ui_events.iter() {
+        match event {
+            UiEvents::SaveSnapshot => {
+                let mut states: Vec<_> = particle_states.iter().collect();
+                states.sort_by_key(|(index, ..)| index.0);
+
+                let snapshot = WaveInPanelSnapshot {
+                    parameters: parameters.clone(),
+                    particles: states
+                        .into_iter()
+                        .map(|(_, driver, transform, velocity)| {
+                            (
+                                driver.copied(),
+                                BodyState::new(transform, velocity),
+                            )
+                        })
+                        .collect(),
+                    stopwatch_elapsed_secs: stopwatch.0.elapsed_secs(),
+                };
+
+                save_snapshot(&name, &snapshot);
+            }
+            UiEvents::LoadSnapshot => {
+                loaded_snapshot = load_snapshot::<WaveInPanelSnapshot>(&name);
+            }
+            _ => {}
+        }
+    }
+
+    let snapshot = if let Some(snapshot) = loaded_snapshot {
+        snapshot
+    } else {
+        return;
+    };
+
+    parameters.restore(snapshot.parameters);
+
+    stopwatch
+        .0
+        .set_elapsed(Duration::from_secs_f32(snapshot.stopwatch_elapsed_secs));
+
+    let entities_and_positions = respawn_particles(
+        &mut commands,
+        &mut meshes,
+        &mut parameters,
+        particles,
+    );
+
+    if entities_and_positions.len() != snapshot.particles.len() {
+        error!("snapshot particles don't match its parameters");
+    }
+
+    for ((entity, _), (driver, state)) in
+        entities_and_positions.iter().zip(snapshot.particles.iter())
+    {
+        let mut entity = commands.entity(*entity);
+        entity.insert((state.transform(), state.velocity()));
+
+        if let Some(driver) = driver {
+            entity.insert((
+                Particle::Active,
+                *driver,
+                parameters.active_particle_material_handle.clone(),
+            ));
+        }
     }
 }
 
@@ -380,8 +902,9 @@ fn cleanup_particles(
     parameters: &mut WaveInPanelParameters,
     particles: Query<Entity, With<Particle>>,
 ) {
+    // the spring joints are children of the particles
     for entity in particles.iter() {
-        commands.entity(entity).despawn();
+        commands.entity(entity).despawn_recursive();
     }
 
     parameters.particles_map.clear();
@@ -405,52 +928,247 @@ fn cleanup(
 
 // ui
 
+impl Simulation for WaveInPanelPlugin {
+    const NAME: &'static str = "wave_in_panel";
+    type Parameters = WaveInPanelParameters;
+
+    fn show_ui(ui: &mut egui::Ui, world: &mut World) {
+        let mut state: SystemState<(
+            ResMut<DebugRenderContext>,
+            EventWriter<UiEvents>,
+            EventWriter<SimulationControlEvent>,
+            ResMut<WaveInPanelParameters>,
+        )> = SystemState::new(world);
+        let (
+            mut rapier_debug_config,
+            ui_events,
+            mut control_events,
+            mut parameters,
+        ) = state.get_mut(world);
+
+        show_ui(
+            ui,
+            &mut rapier_debug_config,
+            ui_events,
+            &mut control_events,
+            &mut parameters,
+        );
+
+        let mut state: SystemState<(
+            Res<WaveInPanelParameters>,
+            ResMut<AudioInput>,
+            ResMut<AudioFile>,
+            EventWriter<LoadAudioFile>,
+        )> = SystemState::new(world);
+        let (parameters, mut audio_input, mut audio_file, mut load_events) =
+            state.get_mut(world);
+
+        match parameters.waveform {
+            Waveform::Microphone => {
+                ui.separator();
+                show_audio_input(ui, &mut audio_input);
+            }
+            Waveform::AudioFile => {
+                ui.separator();
+                show_audio_file(ui, &mut audio_file, &mut load_events);
+            }
+            _ => {}
+        }
+    }
+}
+
+impl Tunable for WaveInPanelParameters {
+    fn tunables() -> Vec<TunableParameter<Self>> {
+        vec![
+            TunableParameter::<Self>::new(
+                "frequency of new drivers in Hz",
+                0.0..=20.0,
+                |p| p.applying_force_frequency as f64,
+                |p, v| p.applying_force_frequency = v as f32,
+            )
+            .step_by(0.1),
+            TunableParameter::<Self>::new(
+                "amplitude of new drivers",
+                0.0..=0.4,
+                |p| p.applying_force_factor as f64,
+                |p, v| p.applying_force_factor = v as f32,
+            )
+            .step_by(0.01),
+            TunableParameter::<Self>::new(
+                "synthetic velocity loss factor",
+                0.5..=1.0,
+                |p| p.sysnthetic_energy_loss_factor as f64,
+                |p, v| p.sysnthetic_energy_loss_factor = v as f32,
+            )
+            .step_by(0.01),
+        ]
+    }
+}
+
+#[derive(Serialize, Deserialize)]
 pub enum UiEvents {
-    StartStopTime,
     Reset,
+    SaveSnapshot,
+    LoadSnapshot,
+    /// replaces the driver of the particle with the given spawn index
+    SetDriver(usize, Driver),
+    RemoveDriver(usize),
+    StartChladni,
+    StopChladni,
+    SweepChladni,
+    NextResonance,
+    PreviousResonance,
+    ExportGltf,
 }
 
-pub fn show_ui(
+impl RecordableEvent for UiEvents {
+    const KIND: &'static str = "wave_in_panel_ui";
+
+    fn is_recordable(&self) -> bool {
+        !matches!(
+            self,
+            UiEvents::SaveSnapshot
+                | UiEvents::LoadSnapshot
+                | UiEvents::ExportGltf
+        )
+    }
+}
+
+impl ResetEvent for UiEvents {
+    fn reset() -> Self {
+        UiEvents::Reset
+    }
+}
+
+fn show_ui(
     ui: &mut egui::Ui,
     rapier_debug_config: &mut DebugRenderContext,
     mut ui_events: EventWriter<UiEvents>,
+    control_events: &mut EventWriter<SimulationControlEvent>,
     parameters: &mut WaveInPanelParameters,
 ) {
     ui.allocate_space(egui::vec2(1.0, 10.0));
 
-    ui.label("equalizing force factor");
+    ui.label("panel, applied on reset:");
     ui.add(
-        egui::Slider::new(&mut parameters.equalizing_force_factor, 0.0..=10.0)
-            .step_by(0.1),
+        egui::Slider::new(&mut parameters.dimx, 1.0..=30.0)
+            .step_by(0.1)
+            .text("width"),
     );
-
-    ui.label("applying force frequency in Hz");
     ui.add(
-        egui::Slider::new(&mut parameters.applying_force_frequency, 0.0..=20.0)
-            .step_by(0.1),
+        egui::Slider::new(&mut parameters.dimy, 1.0..=30.0)
+            .step_by(0.1)
+            .text("height"),
     );
-
-    ui.label("applying force factor");
     ui.add(
-        egui::Slider::new(&mut parameters.applying_force_factor, 0.0..=0.4)
-            .step_by(0.01),
+        egui::Slider::new(&mut parameters.dimz, 0.0..=1.0)
+            .step_by(0.01)
+            .text("thickness"),
     );
-
-    ui.label("synthetic velocity loss factor:");
     ui.add(
-        egui::Slider::new(
-            &mut parameters.sysnthetic_energy_loss_factor,
-            0.5..=1.0,
-        )
-        .step_by(0.01),
+        egui::Slider::new(&mut parameters.particle_radius, 0.02..=0.5)
+            .step_by(0.01)
+            .text("particle radius"),
     );
 
+    let coupling = parameters.coupling;
+    ui.horizontal(|ui| {
+        ui.label("coupling:");
+        for option in [Coupling::Velocity, Coupling::SpringJoints] {
+            ui.radio_value(
+                &mut parameters.coupling,
+                option,
+                String::from(option),
+            );
+        }
+    });
+    // the particles are coupled when they are spawned, the reset is
+    // forwarded to the panel and restarts the clock
+    if parameters.coupling != coupling {
+        control_events.send(SimulationControlEvent::Reset);
+    }
+
+    match parameters.coupling {
+        Coupling::Velocity => {
+            ui.label("equalizing force factor");
+            ui.add(
+                egui::Slider::new(
+                    &mut parameters.equalizing_force_factor,
+                    0.0..=10.0,
+                )
+                .step_by(0.1),
+            );
+        }
+        Coupling::SpringJoints => {
+            ui.label("joint stiffness");
+            ui.add(
+                egui::Slider::new(&mut parameters.joint_stiffness, 0.0..=500.0)
+                    .logarithmic(true),
+            );
+
+            ui.label("joint damping");
+            ui.add(
+                egui::Slider::new(&mut parameters.joint_damping, 0.0..=10.0)
+                    .step_by(0.1),
+            );
+        }
+    }
+
+    select_waveform(ui, &mut parameters.waveform);
+
+    show_tunables(ui, parameters);
+
+    ui.add(egui::Checkbox::new(
+        &mut parameters.displacement_coloring,
+        "color by displacement",
+    ));
+    if parameters.displacement_coloring {
+        ui.add(
+            egui::Slider::new(
+                &mut parameters.displacement_color_scale,
+                0.01..=0.5,
+            )
+            .logarithmic(true)
+            .text("color scale"),
+        );
+    }
+
+    ui.add(egui::Checkbox::new(
+        &mut parameters.skin,
+        "show as membrane",
+    ))
+    .on_hover_text(
+        "draws the front layer as a surface colored by displacement",
+    );
+    if parameters.skin
+        && ui
+            .button("Export glTF")
+            .on_hover_text("the membrane as mesh, e.g. to render or print it")
+            .clicked()
+    {
+        ui_events.send(UiEvents::ExportGltf);
+    }
+
+    ui.label("shift click: toggle fixed")
+        .on_hover_text("anchors a particle or releases it");
+    ui.label("right drag: toggle drivers, with shift: toggle fixed")
+        .on_hover_text("selects the particles inside the dragged rectangle");
+
+    if ui
+        .button("Chladni plate")
+        .on_hover_text("drive the center and show the nodal lines")
+        .clicked()
+    {
+        ui_events.send(UiEvents::StartChladni);
+    }
+
     ui.horizontal(|ui| {
-        if ui.button("Start / Stop time").clicked() {
-            ui_events.send(UiEvents::StartStopTime);
+        ui.label("snapshot:");
+        if ui.button("Save").clicked() {
+            ui_events.send(UiEvents::SaveSnapshot);
         }
-        if ui.button("Reset").clicked() {
-            ui_events.send(UiEvents::Reset);
+        if ui.button("Load").clicked() {
+            ui_events.send(UiEvents::LoadSnapshot);
         }
     });
 
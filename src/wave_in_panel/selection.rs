@@ -0,0 +1,203 @@
+use bevy::prelude::*;
+use bevy_egui::{egui, EguiContext};
+use bevy_rapier3d::prelude::*;
+use serde::{Deserialize, Serialize};
+
+use super::driver::Driver;
+use super::{Particle, ParticleIndex, WaveInPanelParameters};
+use crate::recording::RecordableEvent;
+use crate::viewports::{cursor_in_viewport, viewport_to_egui};
+use crate::AppCamera;
+
+/// Button which drags the selection box, the left one pans the camera
+const SELECTION_BUTTON: MouseButton = MouseButton::Right;
+
+/// Drags shorter than this in pixels don't select anything
+const MIN_DRAG_DISTANCE: f32 = 4.0;
+
+/// Position in the viewport the selection box was started at
+#[derive(Default, Resource)]
+pub struct BoxSelection(Option<Vec2>);
+
+#[derive(Clone, Copy, Debug, Serialize, Deserialize)]
+pub enum SelectionAction {
+    /// turns all selected particles into drivers, or all back to passive if
+    /// every one of them already is a driver
+    ToggleDriver,
+    /// makes all selected particles dynamic, or all fixed if every one of
+    /// them already is dynamic
+    ToggleFixed,
+}
+
+/// Particles, by spawn index, selected with the box
+#[derive(Serialize, Deserialize)]
+pub struct ParticlesSelectedEvent {
+    pub particles: Vec<usize>,
+    pub action: SelectionAction,
+}
+
+impl RecordableEvent for ParticlesSelectedEvent {
+    const KIND: &'static str = "wave_in_panel_selection";
+}
+
+/// Selects the particles whose centers project into the dragged rectangle,
+/// holding shift toggles fixed and dynamic instead of drivers
+#[allow(clippy::too_many_arguments)]
+pub fn on_box_selection(
+    windows: Res<Windows>,
+    input_mouse: Res<Input<MouseButton>>,
+    keys: Res<Input<KeyCode>>,
+    mut egui_ctx: ResMut<EguiContext>,
+    mut box_selection: ResMut<BoxSelection>,
+    cameras: Query<(&Camera, &GlobalTransform), With<AppCamera>>,
+    particles: Query<(&ParticleIndex, &GlobalTransform), With<Particle>>,
+    mut selected_events: EventWriter<ParticlesSelectedEvent>,
+) {
+    let window = if let Some(window) = windows.get_primary() {
+        window
+    } else {
+        return;
+    };
+    let (camera, camera_transform) = if let Ok(camera) = cameras.get_single() {
+        camera
+    } else {
+        return;
+    };
+    // relative to the viewport, like the projected particles
+    let cursor = cursor_in_viewport(camera, window);
+
+    if input_mouse.just_pressed(SELECTION_BUTTON) {
+        box_selection.0 = cursor;
+    }
+
+    let (start, end) = match (box_selection.0, cursor) {
+        (Some(start), Some(end)) => (start, end),
+        _ => return,
+    };
+    let (min, max) = (start.min(end), start.max(end));
+
+    if input_mouse.pressed(SELECTION_BUTTON) {
+        let to_egui =
+            |position: Vec2| viewport_to_egui(camera, window, position);
+        egui_ctx
+            .ctx_mut()
+            .layer_painter(egui::LayerId::new(
+                egui::Order::Foreground,
+                egui::Id::new("box_selection"),
+            ))
+            .rect_stroke(
+                egui::Rect::from_two_pos(to_egui(start), to_egui(end)),
+                0.0,
+                egui::Stroke::new(1.0, egui::Color32::WHITE),
+            );
+    }
+
+    if !input_mouse.just_released(SELECTION_BUTTON) {
+        return;
+    }
+    box_selection.0 = None;
+
+    if start.distance(end) < MIN_DRAG_DISTANCE {
+        return;
+    }
+
+    let mut selected: Vec<usize> = particles
+        .iter()
+        .filter(|(_, transform)| {
+            camera
+                .world_to_viewport(camera_transform, transform.translation())
+                .map_or(false, |position| {
+                    position.cmpge(min).all() && position.cmple(max).all()
+                })
+        })
+        .map(|(index, _)| index.0)
+        .collect();
+    selected.sort_unstable();
+
+    if selected.is_empty() {
+        return;
+    }
+
+    let action =
+        if keys.pressed(KeyCode::LShift) || keys.pressed(KeyCode::RShift) {
+            SelectionAction::ToggleFixed
+        } else {
+            SelectionAction::ToggleDriver
+        };
+
+    selected_events.send(ParticlesSelectedEvent {
+        particles: selected,
+        action,
+    });
+}
+
+#[allow(clippy::type_complexity)]
+pub fn on_particles_selected(
+    mut commands: Commands,
+    mut selected_events: EventReader<ParticlesSelectedEvent>,
+    parameters: Res<WaveInPanelParameters>,
+    mut particles: Query<(
+        Entity,
+        &ParticleIndex,
+        &mut Particle,
+        &mut Handle<StandardMaterial>,
+        &mut RigidBody,
+    )>,
+) {
+    for event in selected_events.iter() {
+        let is_selected = |index: &ParticleIndex| {
+            event.particles.binary_search(&index.0).is_ok()
+        };
+
+        match event.action {
+            SelectionAction::ToggleDriver => {
+                let all_drivers = particles
+                    .iter()
+                    .filter(|(_, index, ..)| is_selected(index))
+                    .all(|(_, _, particle, ..)| {
+                        matches!(*particle, Particle::Active)
+                    });
+
+                for (entity, index, mut particle, mut material, _) in
+                    particles.iter_mut()
+                {
+                    if !is_selected(index) {
+                        continue;
+                    }
+
+                    if all_drivers {
+                        *particle = Particle::Passive;
+                        *material =
+                            parameters.passive_particle_material_handle.clone();
+                        commands.entity(entity).remove::<Driver>();
+                    } else if let Particle::Passive = *particle {
+                        *particle = Particle::Active;
+                        *material =
+                            parameters.active_particle_material_handle.clone();
+                        commands
+                            .entity(entity)
+                            .insert(Driver::new(&parameters));
+                    }
+                }
+            }
+            SelectionAction::ToggleFixed => {
+                let all_dynamic = particles
+                    .iter()
+                    .filter(|(_, index, ..)| is_selected(index))
+                    .all(|(.., rigid_body)| *rigid_body == RigidBody::Dynamic);
+
+                for (_, index, .., mut rigid_body) in particles.iter_mut() {
+                    if !is_selected(index) {
+                        continue;
+                    }
+
+                    *rigid_body = if all_dynamic {
+                        RigidBody::Fixed
+                    } else {
+                        RigidBody::Dynamic
+                    };
+                }
+            }
+        }
+    }
+}
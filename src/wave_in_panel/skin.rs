@@ -0,0 +1,216 @@
+use bevy::prelude::*;
+use bevy::render::mesh::{Indices, VertexAttributeValues};
+use bevy::render::render_resource::PrimitiveTopology;
+use bevy::render::view::NoFrustumCulling;
+
+use super::{ParticleIndex, RestPosition, UiEvents, WaveInPanelParameters};
+use crate::colored_mesh::{ColoredMesh3d, VERTEX_ATTRIBUTE_COLOR_ID};
+use crate::colormap::Colormap;
+use crate::gltf_export::save_gltf;
+use crate::instancing::InstancedParticle;
+
+/// Membrane through the front layer of the lattice
+#[derive(Component)]
+pub struct PanelSkin;
+
+/// Hides the particles while the skin is shown, marking them as instanced
+/// again lets the instancing decide how they are drawn
+#[allow(clippy::type_complexity)]
+pub fn hide_particles_under_skin(
+    mut commands: Commands,
+    parameters: Res<WaveInPanelParameters>,
+    mut was_skinned: Local<bool>,
+    mut particles: Query<(
+        Entity,
+        &mut Visibility,
+        ChangeTrackers<ParticleIndex>,
+    )>,
+) {
+    let toggled = parameters.skin != *was_skinned;
+    *was_skinned = parameters.skin;
+
+    for (entity, mut visibility, tracker) in particles.iter_mut() {
+        if parameters.skin && (toggled || tracker.is_added()) {
+            commands.entity(entity).remove::<InstancedParticle>();
+            visibility.is_visible = false;
+        } else if !parameters.skin && toggled {
+            commands.entity(entity).insert(InstancedParticle);
+            visibility.is_visible = true;
+        }
+    }
+}
+
+/// Moves the vertices of the skin to the particles of the front layer and
+/// colors them by their displacement along z, the skin is rebuilt when the
+/// lattice was respawned
+pub fn update_panel_skin(
+    mut commands: Commands,
+    parameters: Res<WaveInPanelParameters>,
+    mut meshes: ResMut<Assets<Mesh>>,
+    particles: Query<(&ParticleIndex, &Transform, &RestPosition)>,
+    skins: Query<(Entity, &Handle<Mesh>), With<PanelSkin>>,
+) {
+    if !parameters.skin {
+        for (entity, mesh) in skins.iter() {
+            meshes.remove(mesh);
+            commands.entity(entity).despawn();
+        }
+        return;
+    }
+
+    let [stepsx, stepsy, stepsz] = parameters.lattice_steps;
+    let (columns, rows, layers) = (stepsx + 1, stepsy + 1, stepsz + 1);
+    if particles.iter().len() != columns * rows * layers {
+        return;
+    }
+
+    // the particles are spawned layer by layer along z within each row
+    let mut positions = vec![Vec3::ZERO; columns * rows];
+    let mut displacements = vec![0.0; columns * rows];
+    for (index, transform, rest_position) in particles.iter() {
+        if index.0 % layers == 0 {
+            let vertex = index.0 / layers;
+            positions[vertex] = transform.translation;
+            displacements[vertex] = transform.translation.z - rest_position.0.z;
+        }
+    }
+
+    let skin = skins.get_single().ok();
+    let fits = skin
+        .and_then(|(_, handle)| meshes.get(handle))
+        .map_or(false, |mesh| mesh.count_vertices() == positions.len());
+
+    let handle = match skin {
+        Some((_, handle)) if fits => handle.clone(),
+        Some((entity, handle)) => {
+            // the lattice was respawned with another size
+            meshes.remove(handle);
+            let handle = meshes.add(skin_mesh(columns, rows));
+            commands.entity(entity).insert(handle.clone());
+            handle
+        }
+        None => {
+            let handle = meshes.add(skin_mesh(columns, rows));
+            commands.spawn((
+                PanelSkin,
+                ColoredMesh3d,
+                handle.clone(),
+                SpatialBundle::VISIBLE_IDENTITY,
+                // the bounding box of the flat skin is outdated once it
+                // deforms
+                NoFrustumCulling,
+            ));
+            handle
+        }
+    };
+
+    let mesh = if let Some(mesh) = meshes.get_mut(&handle) {
+        mesh
+    } else {
+        return;
+    };
+
+    write_skin_attributes(
+        mesh,
+        (columns, rows),
+        &positions,
+        &displacements,
+        parameters.displacement_color_scale,
+    );
+}
+
+pub fn export_panel_skin(
+    mut ui_events: EventReader<UiEvents>,
+    meshes: Res<Assets<Mesh>>,
+    skins: Query<(&Handle<Mesh>, &GlobalTransform), With<PanelSkin>>,
+) {
+    for event in ui_events.iter() {
+        if let UiEvents::ExportGltf = event {
+            if let Some((mesh, transform)) =
+                skins.get_single().ok().and_then(|(handle, transform)| {
+                    meshes.get(handle).map(|mesh| (mesh, transform))
+                })
+            {
+                save_gltf("panel_skin", mesh, transform);
+            }
+        }
+    }
+}
+
+/// Grid of `columns * rows` vertices, two triangles per cell
+fn skin_mesh(columns: usize, rows: usize) -> Mesh {
+    let vertices = columns * rows;
+    let mut mesh = Mesh::new(PrimitiveTopology::TriangleList);
+
+    mesh.insert_attribute(
+        Mesh::ATTRIBUTE_POSITION,
+        vec![[0.0f32; 3]; vertices],
+    );
+    mesh.insert_attribute(Mesh::ATTRIBUTE_NORMAL, vec![[0.0f32; 3]; vertices]);
+    mesh.insert_attribute(
+        VERTEX_ATTRIBUTE_COLOR_ID,
+        vec![Color::WHITE.as_linear_rgba_u32(); vertices],
+    );
+
+    let (columns, rows) = (columns as u32, rows as u32);
+    let mut indices: Vec<u32> =
+        Vec::with_capacity((columns * rows) as usize * 6);
+    for c in 0..columns.saturating_sub(1) {
+        for r in 0..rows.saturating_sub(1) {
+            let i = c * rows + r;
+
+            indices.extend_from_slice(&[i, i + rows, i + rows + 1]);
+            indices.extend_from_slice(&[i, i + rows + 1, i + 1]);
+        }
+    }
+    mesh.set_indices(Some(Indices::U32(indices)));
+
+    mesh
+}
+
+/// Overwrites the positions, normals and colors of the skin in place
+fn write_skin_attributes(
+    mesh: &mut Mesh,
+    (columns, rows): (usize, usize),
+    positions: &[Vec3],
+    displacements: &[f32],
+    color_scale: f32,
+) {
+    let position = |column: usize, row: usize| positions[column * rows + row];
+
+    if let Some(VertexAttributeValues::Float32x3(mesh_positions)) =
+        mesh.attribute_mut(Mesh::ATTRIBUTE_POSITION)
+    {
+        for (mesh_position, position) in
+            mesh_positions.iter_mut().zip(positions)
+        {
+            *mesh_position = position.to_array();
+        }
+    }
+
+    if let Some(VertexAttributeValues::Float32x3(normals)) =
+        mesh.attribute_mut(Mesh::ATTRIBUTE_NORMAL)
+    {
+        for (i, normal) in normals.iter_mut().enumerate() {
+            let (c, r) = (i / rows, i % rows);
+
+            // central differences, one sided at the edges
+            let along_x = position((c + 1).min(columns - 1), r)
+                - position(c.saturating_sub(1), r);
+            let along_y = position(c, (r + 1).min(rows - 1))
+                - position(c, r.saturating_sub(1));
+            *normal = along_x.cross(along_y).normalize_or_zero().to_array();
+        }
+    }
+
+    let scale = color_scale.max(f32::EPSILON);
+    if let Some(VertexAttributeValues::Uint32(colors)) =
+        mesh.attribute_mut(VERTEX_ATTRIBUTE_COLOR_ID)
+    {
+        for (color, displacement) in colors.iter_mut().zip(displacements) {
+            *color = Colormap::Seismic
+                .color(displacement / scale)
+                .as_linear_rgba_u32();
+        }
+    }
+}